@@ -17,11 +17,32 @@
           cargoLock = {
             lockFile = ./Cargo.lock;
           };
-          nativeBuildInputs = [ pkgs.pkg-config ];
+          nativeBuildInputs = [ pkgs.pkg-config pkgs.installShellFiles ];
           buildInputs = [ pkgs.fontconfig ];
+          postInstall = ''
+            installShellCompletion --cmd symmetri \
+              --bash <($out/bin/symmetri completions bash) \
+              --zsh <($out/bin/symmetri completions zsh) \
+              --fish <($out/bin/symmetri completions fish)
+            $out/bin/symmetri man --dir man
+            installManPage man/*.1
+          '';
+        };
+        # Fully static musl binaries; graphs are left out, fontconfig does not link statically.
+        staticApp = pkgsStatic: pkgsStatic.rustPlatform.buildRustPackage {
+          pname = "symmetri";
+          version = "0.3.0";
+          src = ./.;
+          cargoLock = {
+            lockFile = ./Cargo.lock;
+          };
+          buildNoDefaultFeatures = true;
+          buildFeatures = [ "portable" ];
         };
       in {
         packages.default = app;
+        packages.static = staticApp pkgs.pkgsStatic;
+        packages.static-aarch64 = staticApp pkgs.pkgsCross.aarch64-multiplatform-musl.pkgsStatic;
         apps.default = flake-utils.lib.mkApp { drv = app; };
         devShells.default = pkgs.mkShell {
           shell = pkgs.fish;
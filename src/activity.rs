@@ -0,0 +1,138 @@
+//! `symmetri mark start NAME` / `mark stop`: named activity intervals (a meeting, a
+//! pomodoro) and what each activity cost the battery.
+
+use std::collections::BTreeMap;
+
+use crate::cli_helpers::DischargeSegment;
+
+/// One recorded interval; `end_ts` is `None` while it runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Activity {
+    pub name: String,
+    pub start_ts: f64,
+    pub end_ts: Option<f64>,
+}
+
+impl Activity {
+    /// The part of the interval inside `since..until`, an open one running until `until`.
+    fn clipped(&self, since: f64, until: f64) -> Option<(f64, f64)> {
+        let start = self.start_ts.max(since);
+        let end = self.end_ts.unwrap_or(until).min(until);
+        (end > start).then_some((start, end))
+    }
+}
+
+/// Everything recorded under one name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActivityUsage {
+    pub name: String,
+    /// Intervals recorded.
+    pub count: usize,
+    pub seconds: f64,
+    /// Awake time on battery within the intervals.
+    pub battery_hours: f64,
+    pub wh: f64,
+}
+
+impl ActivityUsage {
+    /// Average drain while on battery.
+    pub fn watts(&self) -> Option<f64> {
+        (self.battery_hours > 0.0).then(|| self.wh / self.battery_hours)
+    }
+}
+
+/// Usage per activity name within `since..until`, longest first. Discharge segments that
+/// straddle an interval's edge count for the share of them inside it.
+pub fn usage(
+    activities: &[Activity],
+    segments: &[DischargeSegment],
+    since: f64,
+    until: f64,
+) -> Vec<ActivityUsage> {
+    let mut by_name: BTreeMap<&str, ActivityUsage> = BTreeMap::new();
+    for activity in activities {
+        let Some((start, end)) = activity.clipped(since, until) else {
+            continue;
+        };
+        let entry = by_name
+            .entry(&activity.name)
+            .or_insert_with(|| ActivityUsage {
+                name: activity.name.clone(),
+                count: 0,
+                seconds: 0.0,
+                battery_hours: 0.0,
+                wh: 0.0,
+            });
+        entry.count += 1;
+        entry.seconds += end - start;
+        for segment in segments {
+            let seconds = segment.hours * 3600.0;
+            let overlap = segment.ts.min(end) - (segment.ts - seconds).max(start);
+            if overlap > 0.0 {
+                entry.battery_hours += overlap / 3600.0;
+                entry.wh += segment.watts * overlap / 3600.0;
+            }
+        }
+    }
+    let mut usage: Vec<ActivityUsage> = by_name.into_values().collect();
+    usage.sort_by(|a, b| b.seconds.total_cmp(&a.seconds));
+    usage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::segment;
+
+    fn activity(name: &str, start_ts: f64, end_ts: Option<f64>) -> Activity {
+        Activity {
+            name: name.to_string(),
+            start_ts,
+            end_ts,
+        }
+    }
+
+    /// Two meetings, then a focus session still running.
+    fn activities() -> [Activity; 3] {
+        [
+            activity("meeting", 0.0, Some(3600.0)),
+            activity("meeting", 7200.0, Some(9000.0)),
+            activity("focus", 9000.0, None),
+        ]
+    }
+
+    /// 10W for the first half hour, on AC after that, 6W from 7200s onwards.
+    fn segments() -> [DischargeSegment; 2] {
+        [segment(1800.0, 10.0, 0.5), segment(9900.0, 6.0, 0.75)]
+    }
+
+    #[test]
+    fn repeated_activities_add_up() {
+        let used = usage(&activities(), &segments(), 0.0, 10800.0);
+        assert_eq!(used.len(), 2);
+        assert_eq!((used[0].count, used[0].seconds), (2, 5400.0));
+    }
+
+    #[test]
+    fn energy_only_counts_time_on_battery() {
+        let meeting = &usage(&activities(), &segments(), 0.0, 10800.0)[0];
+        // Half an hour at 10W, then the half hour of the 6W segment up to 9000s.
+        assert!((meeting.battery_hours - 1.0).abs() < 1e-9);
+        assert!((meeting.wh - 8.0).abs() < 1e-9);
+        assert_eq!(meeting.watts(), Some(8.0));
+    }
+
+    #[test]
+    fn running_activities_count_up_to_the_window_end() {
+        let focus = &usage(&activities(), &segments(), 0.0, 10800.0)[1];
+        assert_eq!(focus.seconds, 1800.0);
+        assert!((focus.wh - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn windows_starting_mid_activity_count_the_rest() {
+        let late = usage(&activities()[..1], &segments(), 1800.0, 10800.0);
+        assert_eq!(late[0].seconds, 1800.0);
+        assert_eq!(late[0].watts(), None);
+    }
+}
@@ -1,7 +1,13 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
 use std::path::Path;
 
+use crate::config::{PowerConfig, SmoothingConfig, SmoothingMethod};
 use crate::db::Sample;
+use crate::metrics::{MetricKind, MetricSample};
+
+/// Readings further apart than this start a new smoothing run, as do status and battery
+/// changes, so a filter never blends a charge into the discharge before it.
+const SMOOTHING_MAX_GAP_SECONDS: f64 = 1800.0;
 
 fn sum_or_none(values: impl Iterator<Item = Option<f64>>) -> Option<f64> {
     let mut total = 0.0;
@@ -124,6 +130,212 @@ fn aggregate_already_sorted(samples: &[Sample]) -> Vec<Sample> {
     aggregated
 }
 
+/// Filters gauge jitter out of `percentage` (aggregated samples, oldest first) with the
+/// configured method. Other fields, and the stored rows, keep their raw values.
+pub fn smooth_percentages(samples: &mut [Sample], config: &SmoothingConfig) {
+    if config.method == SmoothingMethod::None {
+        return;
+    }
+    let mut start = 0usize;
+    for idx in 1..=samples.len() {
+        let splits = idx == samples.len() || {
+            let (previous, current) = (&samples[idx - 1], &samples[idx]);
+            current.ts - previous.ts > SMOOTHING_MAX_GAP_SECONDS
+                || current.status != previous.status
+                || current.source_path != previous.source_path
+        };
+        if splits {
+            smooth_run(&mut samples[start..idx], config);
+            start = idx;
+        }
+    }
+}
+
+fn smooth_run(run: &mut [Sample], config: &SmoothingConfig) {
+    let readings: Vec<(usize, f64, f64)> = run
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, sample)| Some((idx, sample.ts, sample.percentage?)))
+        .collect();
+    let smoothed = match config.method {
+        SmoothingMethod::None => return,
+        SmoothingMethod::Median => {
+            let values: Vec<f64> = readings.iter().map(|(_, _, pct)| *pct).collect();
+            running_median(&values, config.window)
+        }
+        SmoothingMethod::Kalman => {
+            let points: Vec<(f64, f64)> = readings.iter().map(|(_, ts, pct)| (*ts, *pct)).collect();
+            kalman_levels(&points, config)
+        }
+    };
+    for ((idx, _, _), value) in readings.iter().zip(smoothed) {
+        run[*idx].percentage = Some(value);
+    }
+}
+
+/// Centered median; the window narrows symmetrically near the ends of the run.
+fn running_median(values: &[f64], window: usize) -> Vec<f64> {
+    let half = window / 2;
+    (0..values.len())
+        .map(|idx| {
+            let reach = half.min(idx).min(values.len() - 1 - idx);
+            let mut neighbours = values[idx - reach..=idx + reach].to_vec();
+            neighbours.sort_by(f64::total_cmp);
+            neighbours[reach]
+        })
+        .collect()
+}
+
+/// Constant-velocity Kalman filter over (ts, percent) readings: the state is the level and
+/// its rate of change, so a steady drain is followed without lag while jitter of
+/// `measurement_noise_pct` is averaged out. Levels are clamped to 0-100% and rounded to
+/// 0.1%, or the filter's settling wiggles on a flat stretch (plugged in at 100%) would
+/// count as charging and draining.
+fn kalman_levels(points: &[(f64, f64)], config: &SmoothingConfig) -> Vec<f64> {
+    let Some(&(first_ts, first_pct)) = points.first() else {
+        return Vec::new();
+    };
+    let r = config.measurement_noise_pct.max(f64::EPSILON).powi(2);
+    // Rate in %/s; its variance grows by (rate_drift_pct_h / 3600)² per hour.
+    let q = (config.rate_drift_pct_h / 3600.0).powi(2) / 3600.0;
+    let (mut level, mut rate) = (first_pct, 0.0);
+    let mut p = [[r, 0.0], [0.0, (100.0f64 / 3600.0).powi(2)]];
+    let mut last_ts = first_ts;
+    let mut levels = vec![first_pct];
+    for &(ts, pct) in &points[1..] {
+        let dt = (ts - last_ts).max(0.0);
+        last_ts = ts;
+        level += rate * dt;
+        let p00 = p[0][0] + dt * (p[1][0] + p[0][1]) + dt * dt * p[1][1] + q * dt.powi(3) / 3.0;
+        let p01 = p[0][1] + dt * p[1][1] + q * dt * dt / 2.0;
+        let p11 = p[1][1] + q * dt;
+        let s = p00 + r;
+        let (k0, k1) = (p00 / s, p01 / s);
+        let innovation = pct - level;
+        level += k0 * innovation;
+        rate += k1 * innovation;
+        p = [
+            [(1.0 - k0) * p00, (1.0 - k0) * p01],
+            [p01 - k1 * p00, p11 - k1 * p01],
+        ];
+        levels.push((level.clamp(0.0, 100.0) * 10.0).round() / 10.0);
+    }
+    levels
+}
+
+/// Power readings kept out of averages and graphs by [`reject_power_outliers`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RejectedPower {
+    /// Battery readings whose energy change implied an impossible rate.
+    pub energy: usize,
+    /// `power_draw` metric samples.
+    pub power_draw: usize,
+}
+
+impl RejectedPower {
+    pub fn total(&self) -> usize {
+        self.energy + self.power_draw
+    }
+}
+
+/// Floor for the spread in the median-deviation test, so a source that read the same
+/// value for hours does not reject every change.
+const MIN_POWER_SPREAD_W: f64 = 1.0;
+
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+/// Highest believable reading among `values`: `max_watts`, lowered to `outlier_mads`
+/// median absolute deviations above the median when that test is on.
+fn power_ceiling(values: &[f64], config: &PowerConfig) -> f64 {
+    if config.outlier_mads <= 0.0 || values.is_empty() {
+        return config.max_watts;
+    }
+    let center = median(values);
+    let deviations: Vec<f64> = values.iter().map(|v| (v - center).abs()).collect();
+    let spread = median(&deviations).max(MIN_POWER_SPREAD_W);
+    config.max_watts.min(center + config.outlier_mads * spread)
+}
+
+/// Watts implied by the energy change between two readings of the same battery.
+fn energy_watts(previous: &Sample, current: &Sample) -> Option<f64> {
+    let seconds = current.ts - previous.ts;
+    if seconds <= 0.0 || previous.source_path != current.source_path {
+        return None;
+    }
+    Some((current.energy_now_wh? - previous.energy_now_wh?) * 3600.0 / seconds)
+}
+
+/// Drops power readings outside the [`PowerConfig`] bounds, such as the hundreds of watts
+/// some drivers report right after resuming. A battery reading whose energy change from
+/// the last believable one implies such a rate loses its `energy_now_wh` (charge and
+/// drain are judged apart); `power_draw` metrics are judged per source and removed.
+pub fn reject_power_outliers(
+    samples: &mut [Sample],
+    metrics: &mut Vec<MetricSample>,
+    config: &PowerConfig,
+) -> RejectedPower {
+    let mut rejected = RejectedPower::default();
+
+    let pairs: Vec<f64> = samples
+        .windows(2)
+        .filter_map(|pair| energy_watts(&pair[0], &pair[1]))
+        .collect();
+    let charge: Vec<f64> = pairs.iter().copied().filter(|w| *w > 0.0).collect();
+    let drain: Vec<f64> = pairs.iter().filter(|w| **w < 0.0).map(|w| -w).collect();
+    let (charge_ceiling, drain_ceiling) = (
+        power_ceiling(&charge, config),
+        power_ceiling(&drain, config),
+    );
+    let mut last_kept: HashMap<String, usize> = HashMap::new();
+    for idx in 0..samples.len() {
+        if samples[idx].energy_now_wh.is_none() {
+            continue;
+        }
+        let watts = last_kept
+            .get(&samples[idx].source_path)
+            .and_then(|&previous| energy_watts(&samples[previous], &samples[idx]));
+        let ceiling = match watts {
+            Some(w) if w > 0.0 => charge_ceiling,
+            _ => drain_ceiling,
+        };
+        if watts.is_some_and(|w| w.abs() > ceiling) {
+            samples[idx].energy_now_wh = None;
+            rejected.energy += 1;
+        } else {
+            last_kept.insert(samples[idx].source_path.clone(), idx);
+        }
+    }
+
+    let mut by_source: HashMap<&str, Vec<f64>> = HashMap::new();
+    for metric in metrics.iter().filter(|m| m.kind == MetricKind::PowerDraw) {
+        if let Some(value) = metric.value {
+            by_source.entry(&metric.source).or_default().push(value);
+        }
+    }
+    let ceilings: HashMap<String, f64> = by_source
+        .into_iter()
+        .map(|(source, values)| (source.to_string(), power_ceiling(&values, config)))
+        .collect();
+    let before = metrics.len();
+    metrics.retain(|m| {
+        m.kind != MetricKind::PowerDraw
+            || m.value
+                .zip(ceilings.get(&m.source))
+                .is_none_or(|(value, ceiling)| value <= *ceiling)
+    });
+    rejected.power_draw = before - metrics.len();
+    rejected
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -185,4 +397,125 @@ mod tests {
         assert_eq!(aggregated[1].ts, 2.0);
         assert_eq!(aggregated[1].energy_now_wh, Some(2.0));
     }
+
+    #[test]
+    fn smoothing_filters_gauge_jitter_within_runs() {
+        // 12%/h drain read every 5 minutes with ±2% of jitter, then a charging reading.
+        let jitter = [2.0, -2.0, 1.0, -1.0, 2.0, 0.0, -2.0];
+        let truth = |step: usize| 90.0 - step as f64;
+        let mut samples: Vec<Sample> = (0..48)
+            .map(|step| Sample {
+                percentage: Some(truth(step) + jitter[step % jitter.len()]),
+                ..sample(
+                    step as f64 * 300.0,
+                    1.0,
+                    2.0,
+                    3.0,
+                    None,
+                    "Discharging",
+                    "BAT0",
+                )
+            })
+            .collect();
+        let mut charging = sample(48.0 * 300.0, 1.0, 2.0, 3.0, None, "Charging", "BAT0");
+        charging.percentage = Some(60.0);
+        samples.push(charging);
+        let worst = |samples: &[Sample]| {
+            (12..48)
+                .map(|step| (samples[step].percentage.unwrap() - truth(step)).abs())
+                .fold(0.0, f64::max)
+        };
+
+        let mut raw = samples.clone();
+        smooth_percentages(&mut raw, &SmoothingConfig::default());
+        assert_eq!(raw, samples);
+
+        for method in [SmoothingMethod::Median, SmoothingMethod::Kalman] {
+            let config = SmoothingConfig {
+                method,
+                ..SmoothingConfig::default()
+            };
+            let mut smoothed = samples.clone();
+            smooth_percentages(&mut smoothed, &config);
+            assert!(worst(&smoothed) < 1.5, "{method:?}: {}", worst(&smoothed));
+            assert_eq!(smoothed[48].percentage, Some(60.0), "{method:?}");
+            assert_eq!(smoothed[5].energy_now_wh, samples[5].energy_now_wh);
+        }
+        assert_eq!(worst(&samples), 2.0);
+    }
+
+    #[test]
+    fn absurd_power_readings_are_rejected_and_counted() {
+        // 8W drain read every minute, then a 40W charge; one reading right after resume
+        // jumps 10Wh (600W).
+        let mut energy = 40.0;
+        let mut samples = Vec::new();
+        for minute in 0..30 {
+            energy += match minute {
+                0 => 0.0,
+                10 => 10.0,
+                11 => -10.0 - 8.0 / 60.0,
+                20.. => 40.0 / 60.0,
+                _ => -8.0 / 60.0,
+            };
+            let status = if minute >= 20 {
+                "Charging"
+            } else {
+                "Discharging"
+            };
+            samples.push(sample(
+                minute as f64 * 60.0,
+                energy,
+                60.0,
+                60.0,
+                None,
+                status,
+                "BAT0",
+            ));
+        }
+        let power = |ts: f64, watts: f64| {
+            MetricSample::new(
+                ts,
+                MetricKind::PowerDraw,
+                "rapl:power1",
+                Some(watts),
+                Some("W"),
+                serde_json::Value::Null,
+            )
+        };
+        let mut metrics: Vec<MetricSample> = (0..30)
+            .map(|i| power(i as f64 * 60.0, 8.0 + (i % 3) as f64))
+            .collect();
+        metrics.push(power(630.0, 500.0));
+        metrics.push(power(640.0, 60.0));
+
+        let mut bounded = samples.clone();
+        let mut bounded_metrics = metrics.clone();
+        let rejected =
+            reject_power_outliers(&mut bounded, &mut bounded_metrics, &PowerConfig::default());
+        assert_eq!(
+            rejected,
+            RejectedPower {
+                energy: 1,
+                power_draw: 1
+            }
+        );
+        assert_eq!(rejected.total(), 2);
+        assert_eq!(bounded[10].energy_now_wh, None);
+        assert_eq!(bounded[11].energy_now_wh, samples[11].energy_now_wh);
+        assert!(bounded_metrics.iter().all(|m| m.value != Some(500.0)));
+
+        // The median-deviation test also catches the 60W spike, but not the 40W charge.
+        let strict = PowerConfig {
+            outlier_mads: 10.0,
+            ..PowerConfig::default()
+        };
+        let mut strict_samples = samples.clone();
+        let rejected = reject_power_outliers(&mut strict_samples, &mut metrics, &strict);
+        assert_eq!(rejected.power_draw, 2);
+        assert_eq!(rejected.energy, 1);
+        assert!(strict_samples[21..]
+            .iter()
+            .all(|s| s.energy_now_wh.is_some()));
+    }
 }
@@ -0,0 +1,587 @@
+//! Alert rules (`[alerts.<name>]`): a condition on a metric or battery field that has to
+//! hold for a while before the alert fires, sent to desktop notifications, a command or a
+//! webhook. Which alerts are firing is stored in the database, so a restarted collector
+//! does not send them all again.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+use std::thread;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::config::{AlertConfig, Config, GotifyConfig, NtfyConfig, WebhookConfig};
+use crate::db::Sample;
+use crate::metrics::{MetricKind, MetricSample};
+use crate::push;
+use crate::query::BatteryField;
+use crate::sensors::sensor_matches;
+use crate::webhook;
+
+/// What a rule watches: `kind[source]` with `*` wildcards in the source, optionally
+/// `.field` for a numeric detail, or `battery[BAT0].percentage` for a battery field.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct AlertMetric {
+    pub target: Target,
+    /// Source pattern; each matching source is tracked on its own.
+    pub source: String,
+    pub field: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    Battery(BatteryField),
+    Metric(MetricKind),
+}
+
+impl TryFrom<String> for AlertMetric {
+    type Error = String;
+
+    fn try_from(raw: String) -> Result<Self, Self::Error> {
+        raw.parse()
+    }
+}
+
+impl FromStr for AlertMetric {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let (kind, rest) = raw
+            .trim()
+            .split_once('[')
+            .ok_or_else(|| format!("expected kind[source] in {raw:?}"))?;
+        let (source, rest) = rest
+            .split_once(']')
+            .ok_or_else(|| format!("missing ']' in {raw:?}"))?;
+        let field = match rest {
+            "" => None,
+            _ => match rest.strip_prefix('.') {
+                Some(field) if !field.is_empty() => Some(field.to_string()),
+                _ => return Err(format!("expected .field after ']' in {raw:?}")),
+            },
+        };
+        let target = if kind == "battery" {
+            let name = field.as_deref().unwrap_or("percentage");
+            match BatteryField::from_name(name) {
+                Some(BatteryField::DischargeW | BatteryField::ChargeW) | None => {
+                    return Err(format!(
+                        "battery has no field {name:?}; use percentage, health, capacity or energy_wh"
+                    ))
+                }
+                Some(field) => Target::Battery(field),
+            }
+        } else {
+            Target::Metric(MetricKind::from_str(kind)?)
+        };
+        Ok(AlertMetric {
+            target,
+            source: source.to_string(),
+            field,
+        })
+    }
+}
+
+impl fmt::Display for AlertMetric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.target {
+            Target::Battery(_) => write!(f, "battery[{}]", self.source)?,
+            Target::Metric(kind) => write!(f, "{}[{}]", kind.as_str(), self.source)?,
+        }
+        match &self.field {
+            Some(field) => write!(f, ".{field}"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Comparator {
+    #[serde(rename = ">")]
+    Above,
+    #[serde(rename = ">=")]
+    AtLeast,
+    #[serde(rename = "<")]
+    Below,
+    #[serde(rename = "<=")]
+    AtMost,
+    #[serde(rename = "==")]
+    Equal,
+    #[serde(rename = "!=")]
+    NotEqual,
+}
+
+impl Comparator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Comparator::Above => ">",
+            Comparator::AtLeast => ">=",
+            Comparator::Below => "<",
+            Comparator::AtMost => "<=",
+            Comparator::Equal => "==",
+            Comparator::NotEqual => "!=",
+        }
+    }
+
+    pub fn holds(&self, value: f64, threshold: f64) -> bool {
+        match self {
+            Comparator::Above => value > threshold,
+            Comparator::AtLeast => value >= threshold,
+            Comparator::Below => value < threshold,
+            Comparator::AtMost => value <= threshold,
+            Comparator::Equal => value == threshold,
+            Comparator::NotEqual => value != threshold,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SinkKind {
+    /// `notify-send`, with the urgency following the severity.
+    Desktop,
+    /// The rule's `command`, through `sh -c`.
+    Command,
+    /// A POST of the alert to the rule's `webhook`, a `[webhooks]` entry or URL.
+    Webhook,
+    /// A message on the `[ntfy]` server, to the rule's `ntfy_topic` or `[ntfy] topic`.
+    Ntfy,
+    /// A message on the `[gotify]` server.
+    Gotify,
+}
+
+/// An alert that fired.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alert {
+    pub rule: String,
+    pub severity: Severity,
+    pub ts: f64,
+    pub metric: String,
+    pub source: String,
+    pub value: f64,
+    pub unit: Option<String>,
+    pub comparator: &'static str,
+    pub threshold: f64,
+    pub message: String,
+}
+
+impl Alert {
+    pub fn title(&self) -> String {
+        format!("symmetri: {}", self.rule)
+    }
+
+    /// The alert as a webhook event: its fields plus `event` and `title`.
+    pub fn event(&self) -> Value {
+        let mut event = serde_json::to_value(self).unwrap_or_default();
+        if let Value::Object(fields) = &mut event {
+            fields.insert("event".into(), "alert".into());
+            fields.insert("title".into(), self.title().into());
+        }
+        event
+    }
+
+    fn push_message(&self) -> push::Message {
+        push::Message {
+            title: self.title(),
+            body: self.message.clone(),
+            severity: self.severity,
+        }
+    }
+}
+
+/// Where fired alerts go.
+pub trait Sink: Send {
+    fn name(&self) -> &str;
+    fn send(&self, alert: &Alert) -> Result<()>;
+}
+
+struct DesktopSink;
+
+impl Sink for DesktopSink {
+    fn name(&self) -> &str {
+        "desktop"
+    }
+
+    fn send(&self, alert: &Alert) -> Result<()> {
+        let urgency = match alert.severity {
+            Severity::Info => "low",
+            Severity::Warning => "normal",
+            Severity::Critical => "critical",
+        };
+        let status = Command::new("notify-send")
+            .args(["--app-name=symmetri", "--icon=dialog-warning"])
+            .arg(format!("--urgency={urgency}"))
+            .arg(alert.title())
+            .arg(&alert.message)
+            .status()
+            .context("Cannot run notify-send; install libnotify to get notifications")?;
+        if !status.success() {
+            bail!("notify-send exited with {status}");
+        }
+        Ok(())
+    }
+}
+
+struct CommandSink(String);
+
+impl Sink for CommandSink {
+    fn name(&self) -> &str {
+        "command"
+    }
+
+    fn send(&self, alert: &Alert) -> Result<()> {
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(&self.0)
+            .env("SYMMETRI_ALERT", &alert.rule)
+            .env("SYMMETRI_SEVERITY", alert.severity.as_str())
+            .env("SYMMETRI_SOURCE", &alert.source)
+            .env("SYMMETRI_VALUE", format!("{:.2}", alert.value))
+            .env("SYMMETRI_THRESHOLD", alert.threshold.to_string())
+            .env("SYMMETRI_MESSAGE", &alert.message)
+            .status()
+            .with_context(|| format!("Cannot run {}", self.0))?;
+        if !status.success() {
+            bail!("{} exited with {status}", self.0);
+        }
+        Ok(())
+    }
+}
+
+struct WebhookSink(WebhookConfig);
+
+impl Sink for WebhookSink {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn send(&self, alert: &Alert) -> Result<()> {
+        webhook::send(&self.0, &alert.event())
+    }
+}
+
+struct NtfySink {
+    config: NtfyConfig,
+    topic: String,
+}
+
+impl Sink for NtfySink {
+    fn name(&self) -> &str {
+        "ntfy"
+    }
+
+    fn send(&self, alert: &Alert) -> Result<()> {
+        push::send_ntfy(&self.config, &self.topic, &alert.push_message())
+    }
+}
+
+struct GotifySink(GotifyConfig);
+
+impl Sink for GotifySink {
+    fn name(&self) -> &str {
+        "gotify"
+    }
+
+    fn send(&self, alert: &Alert) -> Result<()> {
+        push::send_gotify(&self.0, &alert.push_message())
+    }
+}
+
+/// The sinks a rule lists, with the settings they need.
+pub fn sinks(rule: &AlertConfig, config: &Config) -> Vec<Box<dyn Sink>> {
+    rule.sinks
+        .iter()
+        .filter_map(|kind| -> Option<Box<dyn Sink>> {
+            Some(match kind {
+                SinkKind::Desktop => Box::new(DesktopSink),
+                SinkKind::Command => Box::new(CommandSink(rule.command.clone()?)),
+                SinkKind::Webhook => Box::new(WebhookSink(
+                    webhook::resolve(rule.webhook.as_deref()?, &config.webhooks)?.into_owned(),
+                )),
+                SinkKind::Ntfy => Box::new(NtfySink {
+                    config: config.ntfy.clone(),
+                    topic: rule.ntfy_topic.clone().or(config.ntfy.topic.clone())?,
+                }),
+                SinkKind::Gotify => Box::new(GotifySink(config.gotify.clone())),
+            })
+        })
+        .collect()
+}
+
+/// Sends each alert to its rule's sinks on a background thread, so a slow webhook does
+/// not stall collection.
+pub fn dispatch(alerts: Vec<Alert>, config: &Config) {
+    for alert in alerts {
+        let Some(rule) = config.alerts.get(&alert.rule) else {
+            continue;
+        };
+        info!("Alert {}: {}", alert.rule, alert.message);
+        let sinks = sinks(rule, config);
+        thread::spawn(move || {
+            for sink in sinks {
+                if let Err(err) = sink.send(&alert) {
+                    warn!(
+                        "Cannot send alert {} to {}: {err:#}",
+                        alert.rule,
+                        sink.name()
+                    );
+                }
+            }
+        });
+    }
+}
+
+/// Where one rule stands for one source.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SourceState {
+    /// When the condition started holding without interruption.
+    pub pending_since: Option<f64>,
+    pub firing: bool,
+    pub last_fired: Option<f64>,
+}
+
+/// Every rule's state by source, stored between collections and across restarts.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AlertState {
+    pub rules: BTreeMap<String, BTreeMap<String, SourceState>>,
+}
+
+/// A value a rule reads from a batch: source, timestamp, value and unit.
+type Reading = (String, f64, f64, Option<String>);
+
+fn readings(rule: &AlertConfig, batteries: &[Sample], metrics: &[MetricSample]) -> Vec<Reading> {
+    let metric = &rule.metric;
+    match &metric.target {
+        Target::Battery(field) => batteries
+            .iter()
+            .filter(|sample| {
+                rule.status.as_deref().is_none_or(|status| {
+                    sample
+                        .status
+                        .as_deref()
+                        .is_some_and(|current| current.eq_ignore_ascii_case(status))
+                })
+            })
+            .filter_map(|sample| {
+                let name = Path::new(&sample.source_path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| sample.source_path.clone());
+                if !sensor_matches(&metric.source, &name) {
+                    return None;
+                }
+                let unit = match field {
+                    BatteryField::EnergyWh => "Wh",
+                    _ => "%",
+                };
+                Some((name, sample.ts, field.reading(sample)?, Some(unit.into())))
+            })
+            .collect(),
+        Target::Metric(kind) => metrics
+            .iter()
+            .filter(|sample| sample.kind == *kind && sensor_matches(&metric.source, &sample.source))
+            .filter_map(|sample| {
+                let value = match &metric.field {
+                    Some(field) => sample.details.get(field).and_then(Value::as_f64)?,
+                    None => sample.value?,
+                };
+                let unit = metric
+                    .field
+                    .is_none()
+                    .then(|| sample.unit.clone())
+                    .flatten();
+                Some((sample.source.clone(), sample.ts, value, unit))
+            })
+            .collect(),
+    }
+}
+
+impl AlertState {
+    /// Updates the state with a batch of stored samples and returns the alerts that fire:
+    /// a condition that has held for `for_minutes` fires once, then again only after it
+    /// cleared and `cooldown_minutes` passed since it last fired.
+    pub fn evaluate(
+        &mut self,
+        rules: &BTreeMap<String, AlertConfig>,
+        batteries: &[Sample],
+        metrics: &[MetricSample],
+    ) -> Vec<Alert> {
+        self.rules.retain(|name, _| rules.contains_key(name));
+        let mut alerts = Vec::new();
+        for (name, rule) in rules {
+            let states = self.rules.entry(name.clone()).or_default();
+            for (source, ts, value, unit) in readings(rule, batteries, metrics) {
+                let state = states.entry(source.clone()).or_default();
+                if !rule.comparator.holds(value, rule.threshold) {
+                    if state.firing {
+                        info!("Alert {name} cleared for {source}");
+                    }
+                    state.pending_since = None;
+                    state.firing = false;
+                    continue;
+                }
+                let since = *state.pending_since.get_or_insert(ts);
+                let held = ts - since >= rule.for_minutes * 60.0;
+                let cooled = state
+                    .last_fired
+                    .is_none_or(|last| ts - last >= rule.cooldown_minutes * 60.0);
+                if state.firing || !held || !cooled {
+                    continue;
+                }
+                state.firing = true;
+                state.last_fired = Some(ts);
+                let shown = match &unit {
+                    Some(unit) => format!("{value:.1} {unit}"),
+                    None => format!("{value:.1}"),
+                };
+                alerts.push(Alert {
+                    rule: name.clone(),
+                    severity: rule.severity,
+                    ts,
+                    metric: rule.metric.to_string(),
+                    message: format!(
+                        "{source} is {shown} ({} {})",
+                        rule.comparator.as_str(),
+                        rule.threshold
+                    ),
+                    source,
+                    value,
+                    unit,
+                    comparator: rule.comparator.as_str(),
+                    threshold: rule.threshold,
+                });
+            }
+        }
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+    use crate::test_support::{battery, metric};
+
+    const RULES: &str =
+        "[alerts.cpu_hot]\nmetric = \"temperature[coretemp*]\"\ncomparator = \">\"\n\
+         threshold = 90\nfor_minutes = 2\ncooldown_minutes = 30\nseverity = \"critical\"\n\n\
+         [alerts.low]\nmetric = \"battery[BAT*]\"\ncomparator = \"<\"\nthreshold = 15\n\
+         status = \"Discharging\"\nsinks = [\"command\"]\ncommand = \"true\"\n";
+
+    fn temperature(ts: f64, value: f64) -> MetricSample {
+        MetricSample {
+            unit: Some("°C".to_string()),
+            ..metric(
+                ts,
+                MetricKind::Temperature,
+                "coretemp:Package id 0",
+                value,
+                Value::Null,
+            )
+        }
+    }
+
+    #[test]
+    fn invalid_rules_are_rejected() {
+        assert!(parse_config(
+            "[alerts.x]\nmetric = \"battery[BAT0].discharge_w\"\ncomparator = \">\"\nthreshold = 1\n"
+        )
+        .is_err());
+        assert!(parse_config(
+            "[alerts.x]\nmetric = \"cpu_usage[*]\"\ncomparator = \">\"\nthreshold = 1\nsinks = [\"webhook\"]\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn rules_fire_once_the_condition_held_for_their_duration() {
+        let config = parse_config(RULES).unwrap();
+        let mut state = AlertState::default();
+        assert!(state
+            .evaluate(&config.alerts, &[], &[temperature(0.0, 95.0)])
+            .is_empty());
+
+        let fired = state.evaluate(&config.alerts, &[], &[temperature(150.0, 96.0)]);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].severity, Severity::Critical);
+        assert_eq!(fired[0].message, "coretemp:Package id 0 is 96.0 °C (> 90)");
+    }
+
+    #[test]
+    fn fired_rules_stay_quiet_until_cleared_and_cooled_down() {
+        let config = parse_config(RULES).unwrap();
+        let rules = &config.alerts;
+        let mut state = AlertState::default();
+        state.evaluate(rules, &[], &[temperature(0.0, 95.0)]);
+        state.evaluate(rules, &[], &[temperature(150.0, 96.0)]);
+
+        // Still firing: nothing new.
+        assert!(state
+            .evaluate(rules, &[], &[temperature(300.0, 97.0)])
+            .is_empty());
+        // Cleared and back within the cooldown: held back.
+        state.evaluate(rules, &[], &[temperature(400.0, 70.0)]);
+        state.evaluate(rules, &[], &[temperature(500.0, 95.0)]);
+        assert!(state
+            .evaluate(rules, &[], &[temperature(700.0, 95.0)])
+            .is_empty());
+        assert_eq!(
+            state
+                .evaluate(rules, &[], &[temperature(2000.0, 95.0)])
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn alert_state_survives_the_database_json() {
+        let config = parse_config(RULES).unwrap();
+        let mut state = AlertState::default();
+        state.evaluate(&config.alerts, &[], &[temperature(0.0, 95.0)]);
+
+        let restored: AlertState =
+            serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+        assert_eq!(restored, state);
+    }
+
+    #[test]
+    fn battery_rules_only_fire_in_their_status() {
+        let config = parse_config(RULES).unwrap();
+        let rules = &config.alerts;
+        let mut state = AlertState::default();
+        assert!(state
+            .evaluate(rules, &[battery(3000.0, 10.0, "Charging")], &[])
+            .is_empty());
+
+        let low = state.evaluate(rules, &[battery(3000.0, 10.0, "Discharging")], &[]);
+        assert_eq!(
+            (low[0].rule.as_str(), low[0].source.as_str()),
+            ("low", "BAT0")
+        );
+        assert_eq!(sinks(&rules["low"], &config).len(), 1);
+    }
+}
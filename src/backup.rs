@@ -0,0 +1,254 @@
+//! `db backup` and `db restore`. Copies go through SQLite's online backup API, so they
+//! are consistent even while the collector is writing, unlike copying the live file.
+
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+
+use crate::gzip;
+use crate::locale::Formats;
+
+/// Pages copied per backup step; the collector can write between steps.
+const PAGES_PER_STEP: i32 = 256;
+const STEP_PAUSE: Duration = Duration::from_millis(10);
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Where a backup asked for at `dest` is written: `dest` itself, or when `dest` is a
+/// directory a `symmetri-YYYYmmdd-HHMMSS.sqlite` (`.sqlite.gz` with `gzip`) inside it,
+/// stamped with `ts` in the configured time zone.
+pub fn backup_path(dest: &Path, gzip: bool, ts: f64, formats: &Formats) -> PathBuf {
+    if !dest.is_dir() {
+        return dest.to_path_buf();
+    }
+    let stamp = formats
+        .datetime(ts)
+        .map(|dt| dt.format("%Y%m%d-%H%M%S").to_string())
+        .unwrap_or_else(|| format!("{}", ts as i64));
+    let extension = if gzip { "sqlite.gz" } else { "sqlite" };
+    dest.join(format!("symmetri-{stamp}.{extension}"))
+}
+
+fn has_gz_extension(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "gz")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// `PRAGMA quick_check` on `conn`, and that it holds the battery table.
+fn check_database(conn: &Connection, path: &Path) -> Result<()> {
+    let status: String = conn
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .with_context(|| format!("{} is not a SQLite database", path.display()))?;
+    if status != "ok" {
+        anyhow::bail!("{} is damaged: {status}", path.display());
+    }
+    let has_samples: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'samples')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_samples {
+        anyhow::bail!("{} is not a symmetri database", path.display());
+    }
+    Ok(())
+}
+
+/// Copies the database at `db_path` to `dest` (see [`backup_path`]), gzipped when `gzip`
+/// is set or `dest` ends in `.gz`, and returns the file written. The copy is checked
+/// before it takes its final name, so a failed backup never leaves a usable-looking file.
+pub fn backup(
+    db_path: &Path,
+    dest: &Path,
+    gzip: bool,
+    ts: f64,
+    formats: &Formats,
+) -> Result<PathBuf> {
+    if !db_path.exists() {
+        anyhow::bail!("No database at {}", db_path.display());
+    }
+    let target = backup_path(dest, gzip, ts, formats);
+    let gzip = gzip || has_gz_extension(&target);
+    if target.exists() {
+        anyhow::bail!("{} already exists", target.display());
+    }
+    if let Some(parent) = target.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let partial = with_suffix(&target, ".partial");
+    let written = copy_database(db_path, &partial).and_then(|()| {
+        if gzip {
+            let out = gzip::compress(
+                BufReader::new(File::open(&partial)?),
+                BufWriter::new(File::create(&target)?),
+            )?;
+            out.into_inner()?.sync_all()?;
+            fs::remove_file(&partial)?;
+        } else {
+            fs::rename(&partial, &target)?;
+        }
+        Ok(())
+    });
+    if let Err(err) = written {
+        let _ = fs::remove_file(&partial);
+        let _ = fs::remove_file(&target);
+        return Err(err.context(format!("Cannot write {}", target.display())));
+    }
+    Ok(target)
+}
+
+fn copy_database(db_path: &Path, to: &Path) -> Result<()> {
+    let _ = fs::remove_file(to);
+    let src = Connection::open_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    let mut dst = Connection::open(to)?;
+    Backup::new(&src, &mut dst)?.run_to_completion(PAGES_PER_STEP, STEP_PAUSE, None)?;
+    check_database(&dst, db_path)
+}
+
+/// Replaces the contents of the database at `db_path` with the backup at `from` (plain or
+/// gzipped). The backup is checked first, and the live file is written through the backup
+/// API, so a running collector simply sees the restored data on its next write.
+pub fn restore(from: &Path, db_path: &Path) -> Result<()> {
+    let mut magic = [0_u8; 2];
+    File::open(from)
+        .with_context(|| format!("Cannot open {}", from.display()))?
+        .read_exact(&mut magic)
+        .with_context(|| format!("{} is not a SQLite database", from.display()))?;
+    if let Some(parent) = db_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let unpacked = with_suffix(db_path, ".restore");
+    let source = if magic == GZIP_MAGIC {
+        let mut out = BufWriter::new(File::create(&unpacked)?);
+        gzip::decompress(BufReader::new(File::open(from)?), &mut out)
+            .with_context(|| format!("Cannot decompress {}", from.display()))?;
+        out.into_inner()?.sync_all()?;
+        unpacked.as_path()
+    } else {
+        from
+    };
+    let restored = (|| -> Result<()> {
+        let src = Connection::open_with_flags(
+            source,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        check_database(&src, from)?;
+        drop(src);
+        let mut dst = Connection::open(db_path)?;
+        dst.restore(
+            DatabaseName::Main,
+            source,
+            None::<fn(rusqlite::backup::Progress)>,
+        )?;
+        Ok(())
+    })();
+    let _ = fs::remove_file(&unpacked);
+    restored
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{self, Sample};
+    use crate::zone::Zone;
+
+    fn sample(ts: f64) -> Sample {
+        Sample {
+            ts,
+            percentage: Some(80.0),
+            capacity_pct: None,
+            health_pct: None,
+            energy_now_wh: Some(40.0),
+            energy_full_wh: Some(50.0),
+            energy_full_design_wh: None,
+            status: Some("Discharging".to_string()),
+            source_path: "/sys/class/power_supply/BAT0".to_string(),
+        }
+    }
+
+    /// A database holding two samples, and the connection the "collector" keeps writing on.
+    fn live_db(dir: &Path) -> (PathBuf, Connection) {
+        let db_path = dir.join("live.db");
+        let mut conn = db::init_db_connection(&db_path).unwrap();
+        db::insert_all_samples(&mut conn, &[sample(1.0), sample(2.0)], &[]).unwrap();
+        (db_path, conn)
+    }
+
+    fn utc() -> Formats {
+        Formats {
+            zone: Zone::Utc,
+            ..Formats::default()
+        }
+    }
+
+    // 2024-06-01 00:00:05 UTC
+    const TS: f64 = 1_717_200_005.0;
+
+    #[test]
+    fn restoring_a_backup_rolls_back_later_writes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (db_path, mut conn) = live_db(tmp.path());
+        let copy = tmp.path().join("copy.sqlite");
+        let plain = backup(&db_path, &copy, false, TS, &utc()).unwrap();
+        assert_eq!(plain, copy);
+
+        db::insert_all_samples(&mut conn, &[sample(3.0)], &[]).unwrap();
+        restore(&plain, &db_path).unwrap();
+        assert_eq!(db::count_samples(&db_path, None).unwrap(), 2);
+        assert!(!with_suffix(&db_path, ".restore").exists());
+    }
+
+    #[test]
+    fn restoring_creates_a_missing_database() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (db_path, _conn) = live_db(tmp.path());
+        let plain = backup(&db_path, &tmp.path().join("copy.sqlite"), false, TS, &utc()).unwrap();
+
+        let fresh = tmp.path().join("new/restored.db");
+        restore(&plain, &fresh).unwrap();
+        assert_eq!(
+            db::fetch_samples(&fresh, None).unwrap(),
+            [sample(1.0), sample(2.0)]
+        );
+    }
+
+    #[test]
+    fn restoring_junk_leaves_the_database_alone() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (db_path, _conn) = live_db(tmp.path());
+        let junk = tmp.path().join("junk.sqlite");
+        fs::write(&junk, "not a database at all").unwrap();
+
+        assert!(restore(&junk, &db_path).is_err());
+        assert_eq!(db::count_samples(&db_path, None).unwrap(), 2);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzipped_backups_into_a_directory_are_stamped_and_restore() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (db_path, mut conn) = live_db(tmp.path());
+        let backups = tmp.path().join("backups");
+        fs::create_dir(&backups).unwrap();
+
+        let gzipped = backup(&db_path, &backups, true, TS, &utc()).unwrap();
+        assert_eq!(gzipped, backups.join("symmetri-20240601-000005.sqlite.gz"));
+        assert!(backup(&db_path, &backups, true, TS, &utc()).is_err());
+        assert_eq!(fs::read_dir(&backups).unwrap().count(), 1);
+
+        db::insert_all_samples(&mut conn, &[sample(3.0)], &[]).unwrap();
+        restore(&gzipped, &db_path).unwrap();
+        assert_eq!(db::count_samples(&db_path, None).unwrap(), 2);
+    }
+}
@@ -0,0 +1,284 @@
+//! `symmetri baseline`: named profiles of a controlled period (an idle desktop on the
+//! powersave profile, say) that later windows are scored against, to show what drifted.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cli_helpers::DischargeSegment;
+use crate::correlate;
+use crate::metrics::{MetricKind, MetricSample};
+
+/// What a baseline records besides discharge power, by `analyze correlate` name.
+pub const METRICS: [&str; 9] = [
+    "cpu",
+    "frequency",
+    "gpu",
+    "memory",
+    "load",
+    "power",
+    "temperature",
+    "brightness",
+    "network",
+];
+/// Key of discharge power in a profile.
+pub const DISCHARGE: &str = "discharge";
+/// A metric this many baseline standard deviations away has diverged.
+pub const DIVERGED_Z: f64 = 2.0;
+/// Least spread, as a share of the larger mean, so a metric that sat still during the
+/// baseline does not turn any wobble into a huge score.
+const RELATIVE_FLOOR: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Stat {
+    pub mean: f64,
+    pub stddev: f64,
+    pub count: usize,
+}
+
+impl Stat {
+    /// `None` without readings.
+    pub fn of(values: impl IntoIterator<Item = f64>) -> Option<Self> {
+        let values: Vec<f64> = values.into_iter().collect();
+        if values.is_empty() {
+            return None;
+        }
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values
+            .iter()
+            .map(|value| (value - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        Some(Stat {
+            mean,
+            stddev: variance.sqrt(),
+            count: values.len(),
+        })
+    }
+}
+
+/// A saved profile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Baseline {
+    pub name: String,
+    /// When it was saved.
+    pub ts: f64,
+    pub start_ts: f64,
+    pub end_ts: f64,
+    /// Keyed as in [`profile`].
+    pub metrics: BTreeMap<String, Stat>,
+}
+
+/// The metric kinds [`METRICS`] read, for fetching only those.
+pub fn kinds() -> Vec<MetricKind> {
+    METRICS
+        .iter()
+        .filter_map(|name| correlate::metric_kind(name))
+        .collect()
+}
+
+/// Statistics of discharge power and of every source of [`METRICS`] in `metrics`, keyed
+/// `discharge`, `cpu[cpu]`, `temperature[coretemp]`, ...
+pub fn profile(segments: &[DischargeSegment], metrics: &[MetricSample]) -> BTreeMap<String, Stat> {
+    let mut profile = BTreeMap::new();
+    if let Some(stat) = Stat::of(segments.iter().map(|segment| segment.watts)) {
+        profile.insert(DISCHARGE.to_string(), stat);
+    }
+    for name in METRICS {
+        for (source, series) in correlate::series_for(name, metrics).unwrap_or_default() {
+            if let Some(stat) = Stat::of(series.values()) {
+                profile.insert(format!("{name}[{source}]"), stat);
+            }
+        }
+    }
+    profile
+}
+
+/// How far one metric is from its baseline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deviation {
+    pub metric: String,
+    pub baseline: Stat,
+    pub current: Stat,
+    /// Baseline standard deviations between the two means; positive when higher now.
+    pub z: f64,
+}
+
+impl Deviation {
+    pub fn diverged(&self) -> bool {
+        self.z.abs() >= DIVERGED_Z
+    }
+
+    /// `cpu[cpu] +3.1σ`.
+    pub fn summary(&self) -> String {
+        format!("{} {:+.1}σ", self.metric, self.z)
+    }
+}
+
+/// The metrics both sides have, furthest from the baseline first.
+pub fn deviations(baseline: &Baseline, current: &BTreeMap<String, Stat>) -> Vec<Deviation> {
+    let mut deviations: Vec<Deviation> = baseline
+        .metrics
+        .iter()
+        .filter_map(|(metric, base)| {
+            let now = current.get(metric)?;
+            let spread = base
+                .stddev
+                .max(RELATIVE_FLOOR * base.mean.abs().max(now.mean.abs()));
+            Some(Deviation {
+                metric: metric.clone(),
+                baseline: *base,
+                current: *now,
+                z: if spread > 0.0 {
+                    (now.mean - base.mean) / spread
+                } else {
+                    0.0
+                },
+            })
+        })
+        .collect();
+    deviations.sort_by(|a, b| b.z.abs().total_cmp(&a.z.abs()));
+    deviations
+}
+
+/// Root mean square of the z scores: 0 matches the baseline, around 1 is ordinary
+/// variation. `None` with nothing to compare.
+pub fn score(deviations: &[Deviation]) -> Option<f64> {
+    if deviations.is_empty() {
+        return None;
+    }
+    let sum: f64 = deviations.iter().map(|deviation| deviation.z.powi(2)).sum();
+    Some((sum / deviations.len() as f64).sqrt())
+}
+
+/// One line for reports: the score and the metrics that diverged.
+pub fn describe(baseline: &Baseline, deviations: &[Deviation]) -> String {
+    let Some(score) = score(deviations) else {
+        return format!(
+            "Nothing to compare with baseline {}: none of its metrics were recorded.",
+            baseline.name
+        );
+    };
+    let diverged: Vec<String> = deviations
+        .iter()
+        .filter(|deviation| deviation.diverged())
+        .map(Deviation::summary)
+        .collect();
+    let detail = if diverged.is_empty() {
+        format!("none of {} metrics diverged", deviations.len())
+    } else {
+        format!(
+            "{} of {} metrics diverged: {}",
+            diverged.len(),
+            deviations.len(),
+            diverged.join(", ")
+        )
+    };
+    format!(
+        "Deviation from baseline {}: score {score:.1} ({detail})",
+        baseline.name
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_support::{metric, segment};
+
+    /// Idle: ~5W drain, 3% CPU, a steady 60% brightness.
+    fn idle() -> Baseline {
+        let idle_metrics: Vec<MetricSample> = (0..10)
+            .flat_map(|i| {
+                let ts = i as f64 * 60.0;
+                [
+                    metric(
+                        ts,
+                        MetricKind::CpuUsage,
+                        "cpu",
+                        2.0 + (i % 3) as f64,
+                        json!({}),
+                    ),
+                    metric(ts, MetricKind::CpuUsage, "cpu0", 9.0, json!({})),
+                    metric(
+                        ts,
+                        MetricKind::Display,
+                        "screen",
+                        1.0,
+                        json!({ "brightness_pct": 60.0 }),
+                    ),
+                ]
+            })
+            .collect();
+        let segments = [
+            segment(0.0, 4.8, 0.1),
+            segment(0.0, 5.0, 0.1),
+            segment(0.0, 5.2, 0.1),
+        ];
+        Baseline {
+            name: "idle-powersave".to_string(),
+            ts: 600.0,
+            start_ts: 0.0,
+            end_ts: 600.0,
+            metrics: profile(&segments, &idle_metrics),
+        }
+    }
+
+    /// Later the drain doubled while the screen stayed the same; no CPU readings.
+    fn drifted(baseline: &Baseline) -> Vec<Deviation> {
+        let mut now = baseline.metrics.clone();
+        now.insert(DISCHARGE.to_string(), Stat::of([9.8, 10.2]).unwrap());
+        now.remove("cpu[cpu]");
+        deviations(baseline, &now)
+    }
+
+    #[test]
+    fn stats_are_population_mean_and_spread() {
+        assert_eq!(Stat::of(Vec::new()), None);
+        let stat = Stat::of([2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]).unwrap();
+        assert_eq!((stat.mean, stat.stddev, stat.count), (5.0, 2.0, 8));
+    }
+
+    #[test]
+    fn every_profiled_metric_has_a_kind() {
+        assert_eq!(kinds().len(), METRICS.len());
+    }
+
+    #[test]
+    fn profiles_keep_the_aggregate_sources_and_the_drain() {
+        assert_eq!(
+            idle().metrics.keys().collect::<Vec<_>>(),
+            ["brightness[screen]", "cpu[cpu]", "discharge"]
+        );
+    }
+
+    #[test]
+    fn an_unchanged_profile_scores_zero() {
+        let baseline = idle();
+        assert_eq!(score(&deviations(&baseline, &baseline.metrics)), Some(0.0));
+    }
+
+    #[test]
+    fn diverged_metrics_raise_the_score() {
+        let drifted = drifted(&idle());
+        assert_eq!(drifted.len(), 2);
+        assert_eq!(drifted[0].metric, "discharge");
+        assert!(drifted[0].diverged());
+        // The 0.16W spread is floored at 5% of the 10W mean.
+        assert!((drifted[0].z - 10.0).abs() < 1e-9);
+        assert!((score(&drifted).unwrap() - 10.0 / 2f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn descriptions_name_the_diverged_metrics() {
+        let baseline = idle();
+        assert_eq!(
+            describe(&baseline, &drifted(&baseline)),
+            "Deviation from baseline idle-powersave: score 7.1 (1 of 2 metrics diverged: \
+             discharge +10.0σ)"
+        );
+        assert!(describe(&baseline, &[]).starts_with("Nothing to compare"));
+    }
+}
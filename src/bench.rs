@@ -0,0 +1,148 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use rusqlite::Connection;
+
+use crate::aggregate::aggregate_samples_by_timestamp;
+use crate::cli_helpers::{average_rates, energy_totals};
+use crate::db;
+use crate::locale::Formats;
+use crate::synthetic::{self, Profile};
+
+/// Read windows timed by `dev bench`, ending at the newest battery sample.
+pub const WINDOWS: [(&str, Option<f64>); 5] = [
+    ("1 hour", Some(3600.0)),
+    ("1 day", Some(86_400.0)),
+    ("7 days", Some(7.0 * 86_400.0)),
+    ("30 days", Some(30.0 * 86_400.0)),
+    ("all", None),
+];
+
+/// One timed step: the median over the runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    pub name: String,
+    pub rows: usize,
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    pub fn rows_per_second(&self) -> Option<f64> {
+        let seconds = self.elapsed.as_secs_f64();
+        (seconds > 0.0 && self.rows > 0).then(|| self.rows as f64 / seconds)
+    }
+}
+
+/// Runs `step` `runs` times and keeps its last output with the median duration.
+fn timed<T>(runs: usize, mut step: impl FnMut() -> Result<T>) -> Result<(T, Duration)> {
+    let mut durations = Vec::with_capacity(runs);
+    let mut output = None;
+    for _ in 0..runs.max(1) {
+        let started = Instant::now();
+        output = Some(step()?);
+        durations.push(started.elapsed());
+    }
+    durations.sort();
+    let output = output.expect("at least one run");
+    Ok((output, durations[durations.len() / 2]))
+}
+
+/// Times inserts, battery and metric reads over [`WINDOWS`], and the report's battery
+/// aggregation on the database at `db_path`. Inserted rows are rolled back, so the
+/// database is left as it was.
+pub fn run(db_path: &Path, runs: usize) -> Result<Vec<BenchResult>> {
+    let mut results = Vec::new();
+    let mut push = |name: String, rows: usize, elapsed: Duration| {
+        results.push(BenchResult {
+            name,
+            rows,
+            elapsed,
+        })
+    };
+
+    let latest_ts = db::fetch_latest_sample(db_path)?.map(|sample| sample.ts);
+    let end_ts = latest_ts.unwrap_or_else(|| chrono::Utc::now().timestamp() as f64);
+    // A day of readings, stamped just after the newest stored one.
+    let batch = synthetic::generate(
+        Profile::OfficeLaptop,
+        1,
+        end_ts + 86_400.0,
+        60.0,
+        1,
+        &Formats::default(),
+    );
+    let mut conn = Connection::open(db_path)?;
+    let (_, elapsed) = timed(runs, || {
+        let tx = conn.transaction()?;
+        db::insert_all_samples_in(&tx, &batch.samples, &batch.metrics)?;
+        tx.rollback()?;
+        Ok(())
+    })?;
+    push(
+        "insert (rolled back)".to_string(),
+        batch.samples.len() + batch.metrics.len(),
+        elapsed,
+    );
+
+    let mut month = Vec::new();
+    for (label, window) in WINDOWS {
+        let since = window.map(|seconds| end_ts - seconds);
+        let (samples, elapsed) = timed(runs, || db::fetch_samples(db_path, since))?;
+        push(format!("fetch_samples {label}"), samples.len(), elapsed);
+        if label == "30 days" {
+            month = samples;
+        }
+    }
+    for (label, window) in WINDOWS {
+        let since = window.map(|seconds| end_ts - seconds);
+        let (metrics, elapsed) = timed(runs, || db::fetch_metric_samples(db_path, since, None))?;
+        push(
+            format!("fetch_metric_samples {label}"),
+            metrics.len(),
+            elapsed,
+        );
+    }
+
+    let (_, elapsed) = timed(runs, || {
+        let aggregated = aggregate_samples_by_timestamp(&month);
+        Ok((average_rates(&aggregated), energy_totals(&aggregated)))
+    })?;
+    push("aggregate 30 days".to_string(), month.len(), elapsed);
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bench_times_every_step_and_leaves_the_database_alone() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("bench.db");
+        let mut conn = db::init_db_connection(&path).unwrap();
+        let data = synthetic::generate(
+            Profile::OfficeLaptop,
+            2,
+            1_717_200_000.0,
+            300.0,
+            3,
+            &Formats::default(),
+        );
+        db::insert_all_samples(&mut conn, &data.samples, &data.metrics).unwrap();
+
+        let results = run(&path, 1).unwrap();
+        assert_eq!(results.len(), 1 + 2 * WINDOWS.len() + 1);
+        assert!(results[0].rows > 1000);
+        let rows = |name: &str| results.iter().find(|r| r.name == name).unwrap().rows;
+        assert_eq!(rows("fetch_samples all"), data.samples.len());
+        assert_eq!(rows("fetch_metric_samples 30 days"), data.metrics.len());
+        assert!(rows("fetch_samples 1 hour") < rows("fetch_samples 1 day"));
+        assert_eq!(rows("aggregate 30 days"), data.samples.len());
+        assert_eq!(
+            db::count_samples(&path, None).unwrap(),
+            data.samples.len(),
+            "inserts must be rolled back"
+        );
+    }
+}
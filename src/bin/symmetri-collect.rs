@@ -7,7 +7,6 @@ fn main() {
     }
     args.insert(1, OsString::from("collect"));
     if let Err(err) = symmetri::cli::run(args) {
-        eprintln!("Error: {err}");
-        std::process::exit(1);
+        std::process::exit(symmetri::exit::report(&err));
     }
 }
@@ -0,0 +1,346 @@
+//! Battery readings on the BSDs, which have no `/sys/class/power_supply`: `acpiconf -i N`
+//! on FreeBSD, and on OpenBSD the `acpibat` sensors from `sysctl hw.sensors`, falling back
+//! to `apm` on machines without them. Readings come out in the same units and status
+//! strings as the sysfs ones, so the database, reports and graphs do not tell them apart.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::sysfs::{BatteryInfo, BatteryReading};
+
+/// One battery as read from the BSD tools.
+#[derive(Debug, Clone)]
+pub struct BsdBattery {
+    pub reading: BatteryReading,
+    pub info: BatteryInfo,
+}
+
+fn health(full: Option<f64>, design: Option<f64>) -> Option<f64> {
+    match (full, design) {
+        (Some(full), Some(design)) if design > 0.0 => Some(full / design * 100.0),
+        _ => None,
+    }
+}
+
+/// A status in the sysfs vocabulary; `idle` is neither charging nor discharging.
+fn status(charging: bool, discharging: bool, percentage: Option<f64>) -> &'static str {
+    if charging {
+        "Charging"
+    } else if discharging {
+        "Discharging"
+    } else if percentage.is_some_and(|pct| pct >= 99.0) {
+        "Full"
+    } else {
+        "Not charging"
+    }
+}
+
+/// Leading number of an `acpiconf` value and its unit: `4190 mAh` → (4190, "mAh").
+fn quantity(value: &str) -> Option<(f64, &str)> {
+    let mut parts = value.split_whitespace();
+    let number = parts.next()?.trim_end_matches('%').parse().ok()?;
+    Some((number, parts.next().unwrap_or("")))
+}
+
+/// One battery from `acpiconf -i <unit>`. Capacities are in mWh, or in mAh converted with
+/// the design voltage. `None` when the slot is empty.
+pub fn parse_acpiconf(unit: usize, output: &str) -> Option<BsdBattery> {
+    let fields: BTreeMap<&str, &str> = output
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect();
+    let state = fields.get("State").copied().unwrap_or("");
+    if state.contains("not present") {
+        return None;
+    }
+    let voltage_v = fields
+        .get("Design voltage")
+        .and_then(|value| quantity(value))
+        .filter(|(_, unit)| *unit == "mV")
+        .map(|(mv, _)| mv / 1000.0);
+    let energy_wh = |key: &str| {
+        let (value, unit) = quantity(fields.get(key)?)?;
+        match unit {
+            "mWh" => Some(value / 1000.0),
+            "mAh" => Some(value / 1000.0 * voltage_v?),
+            _ => None,
+        }
+    };
+    let percentage = fields
+        .get("Remaining capacity")
+        .and_then(|value| quantity(value))
+        .map(|(pct, _)| pct);
+    let full = energy_wh("Last full capacity");
+    let design = energy_wh("Design capacity");
+    let path = PathBuf::from(format!("acpi/BAT{unit}"));
+    let text = |key: &str| {
+        fields
+            .get(key)
+            .map(|value| value.to_string())
+            .filter(|value| !value.is_empty())
+    };
+    Some(BsdBattery {
+        reading: BatteryReading {
+            path: path.clone(),
+            capacity_pct: percentage,
+            percentage,
+            energy_now_wh: full.zip(percentage).map(|(full, pct)| full * pct / 100.0),
+            energy_full_wh: full,
+            energy_full_design_wh: design,
+            health_pct: health(full, design),
+            status: Some(
+                status(
+                    state.contains("charging") && !state.contains("discharging"),
+                    state.contains("discharging"),
+                    percentage,
+                )
+                .to_string(),
+            ),
+        },
+        info: BatteryInfo {
+            source_path: path.to_string_lossy().to_string(),
+            manufacturer: text("OEM info"),
+            model_name: text("Model number"),
+            serial_number: text("Serial number"),
+            technology: text("Type"),
+            energy_full_design_wh: design,
+        },
+    })
+}
+
+/// Every `acpibatN` battery in `sysctl hw.sensors` output, e.g.
+/// `hw.sensors.acpibat0.watthour3=37.72 Wh (remaining capacity), OK`.
+pub fn parse_hw_sensors(output: &str) -> Vec<BsdBattery> {
+    let mut sensors: BTreeMap<&str, Vec<(&str, &str)>> = BTreeMap::new();
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let Some(rest) = key.strip_prefix("hw.sensors.") else {
+            continue;
+        };
+        let Some((device, _)) = rest.split_once('.') else {
+            continue;
+        };
+        if device.starts_with("acpibat") {
+            sensors.entry(device).or_default().push((rest, value));
+        }
+    }
+    sensors
+        .into_iter()
+        .map(|(device, values)| {
+            let described = |description: &str| {
+                values.iter().find_map(|(_, value)| {
+                    value
+                        .contains(&format!("({description})"))
+                        .then(|| quantity(value).map(|(number, _)| number))
+                        .flatten()
+                })
+            };
+            let full = described("last full capacity");
+            let design = described("design capacity");
+            let remaining = described("remaining capacity");
+            let percentage = remaining
+                .zip(full.filter(|full| *full > 0.0))
+                .map(|(remaining, full)| (remaining / full * 100.0).min(100.0));
+            let state = values
+                .iter()
+                .find(|(_, value)| value.contains("(battery "))
+                .map(|(_, value)| *value)
+                .unwrap_or("");
+            let path = PathBuf::from(format!("sensors/{device}"));
+            BsdBattery {
+                reading: BatteryReading {
+                    path: path.clone(),
+                    capacity_pct: percentage,
+                    percentage,
+                    energy_now_wh: remaining,
+                    energy_full_wh: full,
+                    energy_full_design_wh: design,
+                    health_pct: health(full, design),
+                    status: Some(
+                        status(
+                            state.contains("battery charging"),
+                            state.contains("battery discharging")
+                                || state.contains("battery critical"),
+                            percentage,
+                        )
+                        .to_string(),
+                    ),
+                },
+                info: BatteryInfo {
+                    source_path: path.to_string_lossy().to_string(),
+                    energy_full_design_wh: design,
+                    ..BatteryInfo::default()
+                },
+            }
+        })
+        .collect()
+}
+
+/// The combined battery from `apm -l` (percent) and `apm -b` (0 high, 1 low, 2 critical,
+/// 3 charging, 4 absent, 255 unknown) and `apm -a` (1 on AC).
+pub fn parse_apm(percent: &str, state: &str, ac: &str) -> Option<BsdBattery> {
+    let percentage = percent
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|pct| (0.0..=100.0).contains(pct));
+    let state: u8 = state.trim().parse().ok()?;
+    if state == 4 || percentage.is_none() {
+        return None;
+    }
+    let on_ac = ac.trim() == "1";
+    let path = PathBuf::from("apm/battery");
+    Some(BsdBattery {
+        reading: BatteryReading {
+            path: path.clone(),
+            capacity_pct: percentage,
+            percentage,
+            energy_now_wh: None,
+            energy_full_wh: None,
+            energy_full_design_wh: None,
+            health_pct: None,
+            status: Some(status(state == 3, !on_ac, percentage).to_string()),
+        },
+        info: BatteryInfo {
+            source_path: path.to_string_lossy().to_string(),
+            ..BatteryInfo::default()
+        },
+    })
+}
+
+#[cfg(any(target_os = "freebsd", target_os = "openbsd"))]
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    use std::process::{Command, Stdio};
+
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Batteries reported by `acpiconf`, one per `hw.acpi.battery.units`.
+#[cfg(target_os = "freebsd")]
+pub fn read_batteries() -> Vec<BsdBattery> {
+    let units: usize = run("sysctl", &["-n", "hw.acpi.battery.units"])
+        .and_then(|raw| raw.trim().parse().ok())
+        .unwrap_or(0);
+    (0..units)
+        .filter_map(|unit| parse_acpiconf(unit, &run("acpiconf", &["-i", &unit.to_string()])?))
+        .collect()
+}
+
+/// Batteries from the `acpibat` sensors, or the combined `apm` battery without them.
+#[cfg(target_os = "openbsd")]
+pub fn read_batteries() -> Vec<BsdBattery> {
+    let batteries = run("sysctl", &["hw.sensors"])
+        .map(|output| parse_hw_sensors(&output))
+        .unwrap_or_default();
+    if !batteries.is_empty() {
+        return batteries;
+    }
+    let apm = |flag: &str| run("apm", &[flag]);
+    match (apm("-l"), apm("-b"), apm("-a")) {
+        (Some(percent), Some(state), Some(ac)) => {
+            parse_apm(&percent, &state, &ac).into_iter().collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACPICONF: &str = "Design capacity:\t4190 mAh\n\
+                            Last full capacity:\t3851 mAh\n\
+                            Technology:\t\tsecondary (rechargeable)\n\
+                            Design voltage:\t\t11100 mV\n\
+                            Model number:\t\t5B10W13930\n\
+                            Serial number:\t\t1234\n\
+                            Type:\t\t\tLION\n\
+                            OEM info:\t\tSMP\n\
+                            State:\t\t\tdischarging\n\
+                            Remaining capacity:\t80%\n\
+                            Remaining time:\t\t2:31\n\
+                            Present rate:\t\t1159 mA\n";
+
+    #[test]
+    fn acpiconf_capacities_are_converted_with_the_design_voltage() {
+        let battery = parse_acpiconf(0, ACPICONF).unwrap();
+        let reading = &battery.reading;
+        assert_eq!(reading.path, PathBuf::from("acpi/BAT0"));
+        assert_eq!(reading.percentage, Some(80.0));
+        assert_eq!(reading.status.as_deref(), Some("Discharging"));
+        assert!((reading.energy_full_wh.unwrap() - 3.851 * 11.1).abs() < 1e-9);
+        assert!((reading.energy_now_wh.unwrap() - 3.851 * 11.1 * 0.8).abs() < 1e-9);
+        assert!((reading.health_pct.unwrap() - 3851.0 / 4190.0 * 100.0).abs() < 1e-9);
+        assert_eq!(battery.info.manufacturer.as_deref(), Some("SMP"));
+        assert_eq!(battery.info.serial_number.as_deref(), Some("1234"));
+    }
+
+    #[test]
+    fn an_empty_acpiconf_slot_is_skipped() {
+        assert!(parse_acpiconf(1, "State:\t\t\tnot present\n").is_none());
+    }
+
+    #[test]
+    fn sysctl_acpibat_sensors_become_a_battery() {
+        let sensors = "hw.sensors.cpu0.temp0=45.00 degC\n\
+                       hw.sensors.acpibat0.volt0=11.10 VDC (voltage)\n\
+                       hw.sensors.acpibat0.power0=7.50 W (rate)\n\
+                       hw.sensors.acpibat0.watthour0=40.00 Wh (last full capacity)\n\
+                       hw.sensors.acpibat0.watthour3=30.00 Wh (remaining capacity), OK\n\
+                       hw.sensors.acpibat0.watthour4=50.00 Wh (design capacity)\n\
+                       hw.sensors.acpibat0.raw0=2 (battery charging), OK\n";
+        let batteries = parse_hw_sensors(sensors);
+        assert_eq!(batteries.len(), 1);
+        let reading = &batteries[0].reading;
+        assert_eq!(reading.path, PathBuf::from("sensors/acpibat0"));
+        assert_eq!(reading.percentage, Some(75.0));
+        assert_eq!(reading.energy_now_wh, Some(30.0));
+        assert_eq!(reading.health_pct, Some(80.0));
+        assert_eq!(reading.status.as_deref(), Some("Charging"));
+    }
+
+    #[test]
+    fn apm_state_and_ac_line_pick_the_status() {
+        let apm = parse_apm("100\n", "0\n", "1\n").unwrap();
+        assert_eq!(apm.reading.status.as_deref(), Some("Full"));
+        assert_eq!(
+            parse_apm("42", "1", "0").unwrap().reading.status.as_deref(),
+            Some("Discharging")
+        );
+        assert!(parse_apm("255", "4", "1").is_none());
+    }
+
+    #[test]
+    fn malformed_or_empty_output_yields_no_values() {
+        let empty = parse_acpiconf(0, "").unwrap().reading;
+        assert_eq!(empty.percentage, None);
+        assert_eq!(empty.energy_now_wh, None);
+        assert_eq!(empty.status.as_deref(), Some("Not charging"));
+
+        // mAh without a design voltage cannot be turned into watt-hours.
+        let no_voltage = ACPICONF.replace("Design voltage:\t\t11100 mV\n", "");
+        let reading = parse_acpiconf(0, &no_voltage).unwrap().reading;
+        assert_eq!(reading.percentage, Some(80.0));
+        assert_eq!(reading.energy_full_wh, None);
+        assert_eq!(reading.health_pct, None);
+
+        assert!(parse_hw_sensors("").is_empty());
+        assert!(parse_hw_sensors("garbage\nhw.sensors.acpibat0\nhw.sensors=1\n").is_empty());
+
+        assert!(parse_apm("", "", "").is_none());
+        assert!(parse_apm("n/a", "0", "1").is_none());
+        assert!(parse_apm("142", "0", "1").is_none());
+    }
+}
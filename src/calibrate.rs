@@ -0,0 +1,393 @@
+use std::io::Write;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::cli_helpers::format_runtime;
+use crate::config::QuirkOverrides;
+use crate::db::{create_sample_from_reading, Sample};
+use crate::signals;
+use crate::sysfs::{read_battery, read_charge_thresholds, read_power_w};
+
+/// A run must start at least this full and end at most this full to describe the gauge.
+const MIN_START_PCT: f64 = 95.0;
+const MAX_END_PCT: f64 = 20.0;
+/// Power readings further apart than this span a suspend or a stall; the energy counter
+/// is used for that stretch instead.
+const MAX_POWER_GAP_SECONDS: f64 = 600.0;
+/// Reported levels the energy left below the last reading is extrapolated from.
+const TAIL_PCT: f64 = 5.0;
+/// How often the waiting phases look at the battery again.
+const WAIT_POLL: Duration = Duration::from_secs(5);
+
+/// One reading of a calibration run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TracePoint {
+    pub sample: Sample,
+    /// `power_now` (or current × voltage) when the driver exposes it.
+    pub power_w: Option<f64>,
+}
+
+impl TracePoint {
+    /// The level the gauge shows the user: `capacity`, else energy_now / energy_full.
+    fn reported_pct(&self) -> Option<f64> {
+        self.sample.capacity_pct.or(self.sample.percentage)
+    }
+}
+
+/// What the gauge reported against the share of usable energy actually left.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationPoint {
+    pub reported_pct: f64,
+    pub true_pct: f64,
+}
+
+/// Result of a full discharge: the usable energy and the gauge curve.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calibration {
+    /// When the run finished.
+    pub ts: f64,
+    pub source_path: String,
+    /// `energy_full` when the run started.
+    pub full_wh: f64,
+    /// Energy counted out of the battery from full to empty.
+    pub usable_wh: f64,
+    /// One point per reported percent, highest first.
+    pub points: Vec<CalibrationPoint>,
+}
+
+impl Calibration {
+    /// Counts the energy drawn over a discharge `trace` (oldest first) by integrating the
+    /// power readings, falling back to energy_now deltas where power is missing, and
+    /// compares the share left at each reading with what the gauge reported.
+    pub fn from_trace(trace: &[TracePoint]) -> Result<Self> {
+        let readings: Vec<(&TracePoint, f64)> = trace
+            .iter()
+            .filter_map(|point| Some((point, point.reported_pct()?)))
+            .collect();
+        let (Some(&(first, start_pct)), Some(&(last, end_pct))) =
+            (readings.first(), readings.last())
+        else {
+            bail!("The trace has no charge level readings");
+        };
+        if start_pct < MIN_START_PCT {
+            bail!("The run started at {start_pct:.0}%; start from a full battery");
+        }
+        if end_pct > MAX_END_PCT {
+            bail!("The run stopped at {end_pct:.0}%; discharge below {MAX_END_PCT:.0}%");
+        }
+        let full_wh = first
+            .sample
+            .energy_full_wh
+            .or(first.sample.energy_full_design_wh)
+            .filter(|wh| *wh > 0.0);
+        let Some(full_wh) = full_wh else {
+            bail!(
+                "{} does not report its full energy",
+                first.sample.source_path
+            );
+        };
+
+        let mut used = Vec::with_capacity(readings.len());
+        let mut total = 0.0;
+        used.push(0.0);
+        for pair in readings.windows(2) {
+            let ((a, _), (b, _)) = (pair[0], pair[1]);
+            let seconds = b.sample.ts - a.sample.ts;
+            if seconds < 0.0 {
+                bail!("The trace goes back in time at {}", b.sample.ts);
+            }
+            let counted = match (a.power_w, b.power_w) {
+                (Some(pa), Some(pb)) if seconds <= MAX_POWER_GAP_SECONDS => {
+                    Some((pa + pb) / 2.0 * seconds / 3600.0)
+                }
+                _ => match (a.sample.energy_now_wh, b.sample.energy_now_wh) {
+                    (Some(ea), Some(eb)) => Some((ea - eb).max(0.0)),
+                    _ => None,
+                },
+            };
+            let Some(counted) = counted else {
+                bail!(
+                    "{} reports neither power nor energy; it cannot be calibrated",
+                    first.sample.source_path
+                );
+            };
+            total += counted;
+            used.push(total);
+        }
+        if total <= 0.0 {
+            bail!("No energy was drawn during the run");
+        }
+
+        // The run stops short of empty: the rest is extrapolated from the last few percent.
+        let tail = readings
+            .iter()
+            .rposition(|(_, pct)| *pct >= end_pct + TAIL_PCT)
+            .unwrap_or(0);
+        let tail_drop = readings[tail].1 - end_pct;
+        let wh_per_pct = if tail_drop > 0.0 {
+            (total - used[tail]) / tail_drop
+        } else {
+            total / (start_pct - end_pct).max(1.0)
+        };
+        let usable_wh = total + wh_per_pct * end_pct;
+
+        let mut points: Vec<CalibrationPoint> = Vec::new();
+        for ((_, reported), used_wh) in readings.iter().zip(&used) {
+            let reported_pct = reported.round();
+            if points
+                .last()
+                .is_some_and(|p| p.reported_pct <= reported_pct)
+            {
+                continue;
+            }
+            points.push(CalibrationPoint {
+                reported_pct,
+                true_pct: (usable_wh - used_wh) / usable_wh * 100.0,
+            });
+        }
+        Ok(Calibration {
+            ts: last.sample.ts,
+            source_path: first.sample.source_path.clone(),
+            full_wh,
+            usable_wh,
+            points,
+        })
+    }
+
+    /// Share of usable energy left when the gauge reads `reported_pct`, interpolated
+    /// along the recorded curve (0% and 100% are pinned to themselves).
+    pub fn true_pct(&self, reported_pct: f64) -> f64 {
+        let mut curve: Vec<(f64, f64)> = self
+            .points
+            .iter()
+            .map(|p| (p.reported_pct, p.true_pct))
+            .collect();
+        curve.extend([(0.0, 0.0), (100.0, 100.0)]);
+        curve.sort_by(|a, b| a.0.total_cmp(&b.0));
+        curve.dedup_by(|later, kept| later.0 == kept.0);
+        let x = reported_pct.clamp(0.0, 100.0);
+        let idx = curve.partition_point(|(reported, _)| *reported < x);
+        if idx == 0 {
+            return curve[0].1;
+        }
+        let ((x0, y0), (x1, y1)) = (curve[idx - 1], curve[idx]);
+        y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+    }
+
+    /// Usable energy today, scaled by how much `energy_full` has moved since the run.
+    pub fn usable_wh_now(&self, energy_full_wh: Option<f64>) -> f64 {
+        match energy_full_wh {
+            Some(full) if full > 0.0 && self.full_wh > 0.0 => self.usable_wh * full / self.full_wh,
+            _ => self.usable_wh,
+        }
+    }
+}
+
+fn read_point(path: &Path, quirks: &QuirkOverrides) -> TracePoint {
+    TracePoint {
+        sample: create_sample_from_reading(&read_battery(path, quirks), None),
+        power_w: read_power_w(path, quirks),
+    }
+}
+
+/// Sleeps up to `duration`, waking early on SIGINT/SIGTERM; returns false when asked to stop.
+fn pause(duration: Duration) -> bool {
+    let deadline = Instant::now() + duration;
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        if signals::shutdown_requested() {
+            return false;
+        }
+        thread::sleep(remaining.min(Duration::from_millis(250)));
+    }
+    !signals::shutdown_requested()
+}
+
+/// Walks the user through a calibration run on the battery at `path`: charge to full,
+/// unplug, then a reading every `interval` until the gauge shows `stop_at_pct`. Ctrl-C
+/// stops the recording early and keeps what was recorded.
+pub fn record_discharge(
+    path: &Path,
+    interval: Duration,
+    stop_at_pct: f64,
+    quirks: &QuirkOverrides,
+) -> Result<Vec<TracePoint>> {
+    signals::install_handlers()?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    if let Some(end) = read_charge_thresholds(path).and_then(|t| t.end_pct) {
+        if end < 100 {
+            bail!("{name} stops charging at {end}%; run `symmetri charge-limit set 100` first");
+        }
+    }
+
+    let is_full = |point: &TracePoint| {
+        point.sample.status.as_deref() == Some("Full")
+            || (point.sample.status.as_deref() != Some("Discharging")
+                && point.reported_pct().is_some_and(|pct| pct >= 99.0))
+    };
+    if !is_full(&read_point(path, quirks)) {
+        println!("Plug in the charger and let {name} charge to 100%...");
+        while !is_full(&read_point(path, quirks)) {
+            if !pause(WAIT_POLL) {
+                bail!("Calibration cancelled");
+            }
+        }
+    }
+    println!(
+        "{name} is full. Unplug the charger to start recording, and keep the machine awake \
+         (no suspend) until it reaches {stop_at_pct:.0}%."
+    );
+    while read_point(path, quirks).sample.status.as_deref() != Some("Discharging") {
+        if !pause(WAIT_POLL) {
+            bail!("Calibration cancelled");
+        }
+    }
+
+    println!(
+        "Recording every {}s; Ctrl-C stops early.",
+        interval.as_secs()
+    );
+    let started = Instant::now();
+    let mut trace = Vec::new();
+    loop {
+        let point = read_point(path, quirks);
+        if point.sample.status.as_deref() == Some("Charging") {
+            println!();
+            bail!("The charger was plugged back in; start the calibration again");
+        }
+        let reported = point.reported_pct();
+        trace.push(point);
+        print!(
+            "\r  {} reported, {} elapsed, {} readings   ",
+            reported
+                .map(|pct| format!("{pct:.0}%"))
+                .unwrap_or_else(|| "--".to_string()),
+            format_runtime(Some(started.elapsed().as_secs_f64() / 3600.0)),
+            trace.len()
+        );
+        std::io::stdout().flush()?;
+        if reported.is_some_and(|pct| pct <= stop_at_pct) || !pause(interval) {
+            break;
+        }
+    }
+    println!();
+    Ok(trace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 50Wh battery drained at 10W for five hours, read every minute, whose gauge shows
+    /// the top half of the charge too slowly and the bottom half too fast.
+    fn nonlinear_trace() -> Vec<TracePoint> {
+        (0..=270)
+            .map(|minute| {
+                let seconds = minute as f64 * 60.0;
+                let energy = 50.0 - 10.0 * seconds / 3600.0;
+                let true_pct = energy / 50.0 * 100.0;
+                let reported = if true_pct >= 50.0 {
+                    60.0 + (true_pct - 50.0) * 0.8
+                } else {
+                    true_pct * 1.2
+                };
+                TracePoint {
+                    sample: Sample {
+                        ts: seconds,
+                        percentage: Some(true_pct),
+                        capacity_pct: Some(reported.round()),
+                        health_pct: None,
+                        energy_now_wh: Some(energy),
+                        energy_full_wh: Some(50.0),
+                        energy_full_design_wh: None,
+                        status: Some("Discharging".to_string()),
+                        source_path: "BAT0".to_string(),
+                    },
+                    power_w: Some(10.0),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn discharge_trace_maps_reported_to_true_level() {
+        let trace = nonlinear_trace();
+        let calibration = Calibration::from_trace(&trace).unwrap();
+        assert_eq!(calibration.full_wh, 50.0);
+        assert!(
+            (calibration.usable_wh - 50.0).abs() < 0.5,
+            "{calibration:?}"
+        );
+        assert_eq!(calibration.points[0].reported_pct, 100.0);
+        assert!(calibration
+            .points
+            .windows(2)
+            .all(|pair| pair[0].reported_pct > pair[1].reported_pct));
+
+        // The gauge shows 60% when half the energy is left, and 30% at a quarter.
+        assert!((calibration.true_pct(60.0) - 50.0).abs() < 1.5);
+        assert!((calibration.true_pct(30.0) - 25.0).abs() < 1.5);
+        assert_eq!(calibration.true_pct(0.0), 0.0);
+        assert_eq!(calibration.true_pct(120.0), 100.0);
+        assert!((calibration.usable_wh_now(Some(45.0)) - calibration.usable_wh * 0.9).abs() < 1e-9);
+
+        // Without power readings the energy counter is integrated instead.
+        let no_power: Vec<TracePoint> = trace
+            .iter()
+            .cloned()
+            .map(|point| TracePoint {
+                power_w: None,
+                ..point
+            })
+            .collect();
+        let from_energy = Calibration::from_trace(&no_power).unwrap();
+        assert!((from_energy.usable_wh - calibration.usable_wh).abs() < 0.5);
+
+        assert!(Calibration::from_trace(&trace[..100]).is_err());
+        assert!(Calibration::from_trace(&trace[60..]).is_err());
+    }
+
+    #[test]
+    fn a_gauge_that_bounces_back_up_keeps_the_curve_monotonic() {
+        let mut trace = nonlinear_trace();
+        for point in &mut trace[100..110] {
+            point.sample.capacity_pct = point.sample.capacity_pct.map(|pct| pct + 5.0);
+        }
+        let calibration = Calibration::from_trace(&trace).unwrap();
+        let reference = Calibration::from_trace(&nonlinear_trace()).unwrap();
+        assert!((calibration.usable_wh - reference.usable_wh).abs() < 1e-9);
+        assert!(calibration
+            .points
+            .windows(2)
+            .all(|pair| pair[0].reported_pct > pair[1].reported_pct));
+    }
+
+    #[test]
+    fn a_trace_that_goes_back_in_time_is_rejected() {
+        let mut trace = nonlinear_trace();
+        trace.swap(100, 101);
+        let err = Calibration::from_trace(&trace).unwrap_err();
+        assert!(err.to_string().contains("back in time"), "{err}");
+    }
+
+    #[test]
+    fn too_few_points_are_rejected() {
+        let trace = nonlinear_trace();
+        assert!(Calibration::from_trace(&[]).is_err());
+        assert!(Calibration::from_trace(&trace[..1]).is_err());
+        let no_levels: Vec<TracePoint> = trace
+            .iter()
+            .cloned()
+            .map(|mut point| {
+                point.sample.capacity_pct = None;
+                point.sample.percentage = None;
+                point
+            })
+            .collect();
+        let err = Calibration::from_trace(&no_levels).unwrap_err();
+        assert!(err.to_string().contains("no charge level"), "{err}");
+    }
+}
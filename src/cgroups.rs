@@ -0,0 +1,318 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::json;
+
+use crate::collector::Collector;
+use crate::config::CgroupsConfig;
+use crate::metrics::{MetricKind, MetricSample};
+use crate::sensors::sensor_matches;
+
+/// Config name of the collector reading cgroup v2 accounting files.
+pub const CGROUPS_COLLECTOR: &str = "cgroups";
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// Cumulative counters of one cgroup, read from `cpu.stat`, `memory.current` and `io.stat`.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct CgroupStats {
+    cpu_usage_usec: f64,
+    cpu_user_usec: Option<f64>,
+    cpu_system_usec: Option<f64>,
+    memory_bytes: Option<f64>,
+    io_read_bytes: Option<f64>,
+    io_write_bytes: Option<f64>,
+}
+
+fn parse_cpu_stat(content: &str) -> Option<(f64, Option<f64>, Option<f64>)> {
+    let mut usage = None;
+    let mut user = None;
+    let mut system = None;
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(key), Some(value)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let value = value.parse::<f64>().ok();
+        match key {
+            "usage_usec" => usage = value,
+            "user_usec" => user = value,
+            "system_usec" => system = value,
+            _ => {}
+        }
+    }
+    Some((usage?, user, system))
+}
+
+/// Sums `rbytes`/`wbytes` over every device line of `io.stat`.
+fn parse_io_stat(content: &str) -> (f64, f64) {
+    let mut read = 0.0;
+    let mut write = 0.0;
+    for field in content.split_whitespace() {
+        let Some((key, value)) = field.split_once('=') else {
+            continue;
+        };
+        let value = value.parse::<f64>().unwrap_or(0.0);
+        match key {
+            "rbytes" => read += value,
+            "wbytes" => write += value,
+            _ => {}
+        }
+    }
+    (read, write)
+}
+
+fn read_cgroup(dir: &Path) -> Option<CgroupStats> {
+    let (usage, user, system) = parse_cpu_stat(&fs::read_to_string(dir.join("cpu.stat")).ok()?)?;
+    let memory_bytes = fs::read_to_string(dir.join("memory.current"))
+        .ok()
+        .and_then(|raw| raw.trim().parse::<f64>().ok());
+    // io.stat only exists with the io controller enabled for the parent.
+    let io = fs::read_to_string(dir.join("io.stat"))
+        .ok()
+        .map(|raw| parse_io_stat(&raw));
+    Some(CgroupStats {
+        cpu_usage_usec: usage,
+        cpu_user_usec: user,
+        cpu_system_usec: system,
+        memory_bytes,
+        io_read_bytes: io.map(|(read, _)| read),
+        io_write_bytes: io.map(|(_, write)| write),
+    })
+}
+
+/// Cgroup directories under `root` matching `pattern`, one glob per path component, e.g.
+/// `user.slice` or `system.slice/docker-*.scope`.
+fn expand_pattern(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    let mut dirs = vec![root.to_path_buf()];
+    for component in pattern.split('/').filter(|c| !c.is_empty()) {
+        let mut next = Vec::new();
+        for dir in &dirs {
+            if !component.contains('*') {
+                let path = dir.join(component);
+                if path.is_dir() {
+                    next.push(path);
+                }
+                continue;
+            }
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            let mut matched: Vec<PathBuf> = entries
+                .flatten()
+                .filter(|entry| entry.path().is_dir())
+                .filter(|entry| sensor_matches(component, &entry.file_name().to_string_lossy()))
+                .map(|entry| entry.path())
+                .collect();
+            matched.sort();
+            next.extend(matched);
+        }
+        dirs = next;
+    }
+    dirs
+}
+
+/// One sample per cgroup matching `patterns`; the value is the cumulative CPU time in
+/// seconds and the source the path relative to the cgroup root.
+pub fn cgroup_samples_from(root: &Path, ts: f64, patterns: &[String]) -> Vec<MetricSample> {
+    let mut seen = BTreeMap::new();
+    for pattern in patterns {
+        for dir in expand_pattern(root, pattern) {
+            let Ok(relative) = dir.strip_prefix(root) else {
+                continue;
+            };
+            let source = relative.to_string_lossy().to_string();
+            if source.is_empty() || seen.contains_key(&source) {
+                continue;
+            }
+            if let Some(stats) = read_cgroup(&dir) {
+                seen.insert(source, stats);
+            }
+        }
+    }
+    seen.into_iter()
+        .map(|(source, stats)| {
+            let seconds = |usec: Option<f64>| usec.map(|usec| usec / 1_000_000.0);
+            MetricSample::new(
+                ts,
+                MetricKind::Cgroup,
+                source,
+                Some(stats.cpu_usage_usec / 1_000_000.0),
+                Some("s"),
+                json!({
+                    "cpu_user_seconds": seconds(stats.cpu_user_usec),
+                    "cpu_system_seconds": seconds(stats.cpu_system_usec),
+                    "memory_bytes": stats.memory_bytes,
+                    "io_read_bytes": stats.io_read_bytes,
+                    "io_write_bytes": stats.io_write_bytes,
+                }),
+            )
+        })
+        .collect()
+}
+
+/// Per-interval usage of one cgroup, derived from consecutive counter samples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CgroupUsage {
+    pub ts: f64,
+    /// CPU time over wall time; 100 means one core fully busy.
+    pub cpu_pct: Option<f64>,
+    pub memory_bytes: Option<f64>,
+    pub read_bytes_per_sec: Option<f64>,
+    pub write_bytes_per_sec: Option<f64>,
+}
+
+/// Turns the cumulative cgroup counters into rates per source. Counter resets (the
+/// cgroup was recreated, e.g. a restarted container) leave a gap instead of a negative rate.
+pub fn cgroup_usage(metrics: &[MetricSample]) -> BTreeMap<String, Vec<CgroupUsage>> {
+    let mut by_source: BTreeMap<&str, Vec<&MetricSample>> = BTreeMap::new();
+    for sample in metrics.iter().filter(|m| m.kind == MetricKind::Cgroup) {
+        by_source.entry(&sample.source).or_default().push(sample);
+    }
+    let detail = |sample: &MetricSample, key: &str| sample.details.get(key)?.as_f64();
+    let rate = |previous: Option<f64>, current: Option<f64>, dt: f64| match (previous, current) {
+        (Some(previous), Some(current)) if current >= previous => Some((current - previous) / dt),
+        _ => None,
+    };
+    by_source
+        .into_iter()
+        .map(|(source, mut samples)| {
+            samples.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+            let usage = samples
+                .windows(2)
+                .filter_map(|pair| {
+                    let (previous, current) = (pair[0], pair[1]);
+                    let dt = current.ts - previous.ts;
+                    (dt > 0.0).then(|| CgroupUsage {
+                        ts: current.ts,
+                        cpu_pct: rate(previous.value, current.value, dt).map(|r| r * 100.0),
+                        memory_bytes: detail(current, "memory_bytes"),
+                        read_bytes_per_sec: rate(
+                            detail(previous, "io_read_bytes"),
+                            detail(current, "io_read_bytes"),
+                            dt,
+                        ),
+                        write_bytes_per_sec: rate(
+                            detail(previous, "io_write_bytes"),
+                            detail(current, "io_write_bytes"),
+                            dt,
+                        ),
+                    })
+                })
+                .collect();
+            (source.to_string(), usage)
+        })
+        .collect()
+}
+
+pub struct CgroupCollector {
+    config: CgroupsConfig,
+}
+
+impl CgroupCollector {
+    pub fn new(config: CgroupsConfig) -> Self {
+        CgroupCollector { config }
+    }
+}
+
+impl Collector for CgroupCollector {
+    fn name(&self) -> &str {
+        CGROUPS_COLLECTOR
+    }
+
+    fn kinds(&self) -> &[MetricKind] {
+        &[MetricKind::Cgroup]
+    }
+
+    fn collect(&self, ts: f64) -> Vec<MetricSample> {
+        let root = self
+            .config
+            .root
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(CGROUP_ROOT));
+        cgroup_samples_from(&root, ts, &self.config.paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_cgroup(dir: &Path, usage_usec: u64, memory: u64, io: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(
+            dir.join("cpu.stat"),
+            format!("usage_usec {usage_usec}\nuser_usec 1000000\nsystem_usec 500000\n"),
+        )
+        .unwrap();
+        fs::write(dir.join("memory.current"), format!("{memory}\n")).unwrap();
+        fs::write(dir.join("io.stat"), io).unwrap();
+    }
+
+    const DOCKER: &str = "system.slice/docker-abc.scope";
+
+    fn patterns() -> Vec<String> {
+        [
+            "user.slice",
+            "system.slice/docker-*.scope",
+            "system.slice/*.service",
+            "missing.slice",
+        ]
+        .map(String::from)
+        .to_vec()
+    }
+
+    /// A user slice, a docker scope with I/O on two devices, and an empty sshd service.
+    fn hierarchy(root: &Path) {
+        write_cgroup(&root.join("user.slice"), 1_500_000, 1024, "");
+        write_cgroup(
+            &root.join(DOCKER),
+            2_000_000,
+            4096,
+            "8:0 rbytes=100 wbytes=200 rios=1 wios=2\n259:0 rbytes=50 wbytes=0\n",
+        );
+        fs::create_dir_all(root.join("system.slice/sshd.service")).unwrap();
+    }
+
+    #[test]
+    fn only_matching_cgroups_with_accounting_are_sampled() {
+        let tmp = tempfile::tempdir().unwrap();
+        hierarchy(tmp.path());
+        let samples = cgroup_samples_from(tmp.path(), 0.0, &patterns());
+        let sources: Vec<&str> = samples.iter().map(|s| s.source.as_str()).collect();
+        assert_eq!(sources, vec![DOCKER, "user.slice"]);
+    }
+
+    #[test]
+    fn cpu_and_io_counters_are_read() {
+        let tmp = tempfile::tempdir().unwrap();
+        hierarchy(tmp.path());
+        let samples = cgroup_samples_from(tmp.path(), 0.0, &patterns());
+        assert_eq!(samples[0].value, Some(2.0));
+        assert_eq!(samples[0].details["io_read_bytes"], 150.0);
+        assert_eq!(samples[0].details["cpu_user_seconds"], 1.0);
+    }
+
+    #[test]
+    fn consecutive_samples_become_rates() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        hierarchy(root);
+        let mut metrics = cgroup_samples_from(root, 0.0, &patterns());
+        write_cgroup(
+            &root.join(DOCKER),
+            7_000_000,
+            8192,
+            "8:0 rbytes=1100 wbytes=200\n259:0 rbytes=50 wbytes=0\n",
+        );
+        metrics.extend(cgroup_samples_from(root, 10.0, &patterns()));
+        let usage = cgroup_usage(&metrics);
+        let docker_usage = &usage[DOCKER];
+        assert_eq!(docker_usage.len(), 1);
+        assert_eq!(docker_usage[0].cpu_pct, Some(50.0));
+        assert_eq!(docker_usage[0].memory_bytes, Some(8192.0));
+        assert_eq!(docker_usage[0].read_bytes_per_sec, Some(100.0));
+        assert_eq!(docker_usage[0].write_bytes_per_sec, Some(0.0));
+        assert_eq!(usage["user.slice"][0].cpu_pct, Some(0.0));
+    }
+}
@@ -0,0 +1,306 @@
+use std::fmt;
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::aggregate::{aggregate_group, aggregate_samples_by_timestamp};
+use crate::cli_helpers::average_rates;
+use crate::db;
+use crate::metrics::{MetricKind, MetricSample};
+
+/// How many recent collections feed the fallback discharge rate when no power sensor exists.
+const RECENT_EVENTS_FOR_RATE: usize = 3;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Thresholds {
+    pub below_pct: Option<f64>,
+    pub discharge_above_w: Option<f64>,
+    pub temp_above_c: Option<f64>,
+    /// Fail when the newest sample is older than this, so a stopped collector does not
+    /// keep passing on its last reading.
+    pub max_age_s: Option<f64>,
+}
+
+impl Thresholds {
+    pub fn is_empty(&self) -> bool {
+        self.below_pct.is_none()
+            && self.discharge_above_w.is_none()
+            && self.temp_above_c.is_none()
+            && self.max_age_s.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CheckInputs {
+    pub percentage: Option<f64>,
+    pub discharge_w: Option<f64>,
+    pub max_temp: Option<(String, f64)>,
+    /// Timestamp of the newest battery or metric sample read.
+    pub latest_ts: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckFailure {
+    pub reason: &'static str,
+    pub value: f64,
+    pub threshold: f64,
+    pub source: Option<String>,
+}
+
+impl fmt::Display for CheckFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "reason={} value={:.2} threshold={:.2}",
+            self.reason, self.value, self.threshold
+        )?;
+        if let Some(source) = &self.source {
+            write!(f, " source={source}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The crossed thresholds at `now`. An empty database is as stale as it gets: with a
+/// maximum age it fails with an infinite age.
+pub fn evaluate(thresholds: &Thresholds, inputs: &CheckInputs, now: f64) -> Vec<CheckFailure> {
+    let mut failures = Vec::new();
+    if let Some(threshold) = thresholds.max_age_s {
+        let age = inputs.latest_ts.map_or(f64::INFINITY, |ts| now - ts);
+        if age > threshold {
+            failures.push(CheckFailure {
+                reason: "stale",
+                value: age,
+                threshold,
+                source: None,
+            });
+        }
+    }
+    if let (Some(threshold), Some(value)) = (thresholds.below_pct, inputs.percentage) {
+        if value < threshold {
+            failures.push(CheckFailure {
+                reason: "battery_below",
+                value,
+                threshold,
+                source: None,
+            });
+        }
+    }
+    if let (Some(threshold), Some(value)) = (thresholds.discharge_above_w, inputs.discharge_w) {
+        if value > threshold {
+            failures.push(CheckFailure {
+                reason: "discharge_above",
+                value,
+                threshold,
+                source: None,
+            });
+        }
+    }
+    if let (Some(threshold), Some((source, value))) = (thresholds.temp_above_c, &inputs.max_temp) {
+        if *value > threshold {
+            failures.push(CheckFailure {
+                reason: "temperature_above",
+                value: *value,
+                threshold,
+                source: Some(source.clone()),
+            });
+        }
+    }
+    failures
+}
+
+fn latest_values(metrics: &[MetricSample], kind: MetricKind) -> Vec<&MetricSample> {
+    metrics
+        .iter()
+        .filter(|m| m.kind == kind && m.value.is_some())
+        .collect()
+}
+
+/// Reads the most recent collection from the database.
+pub fn load_inputs(db_path: &Path) -> Result<CheckInputs> {
+    let latest_event = db::fetch_latest_event(db_path)?;
+    let battery = aggregate_group(&latest_event).ok();
+    let battery_ts = latest_event.iter().map(|s| s.ts).max_by(f64::total_cmp);
+    let percentage = battery.as_ref().and_then(|s| s.percentage);
+    // On the charger the power sensors read AC draw, not what leaves the battery.
+    let discharging = battery
+        .as_ref()
+        .and_then(|s| s.status.as_deref())
+        .is_some_and(|s| s.eq_ignore_ascii_case("discharging"));
+
+    let latest_metrics = db::fetch_latest_metric_samples(
+        db_path,
+        Some(&[MetricKind::PowerDraw, MetricKind::Temperature]),
+    )?;
+    let power = latest_values(&latest_metrics, MetricKind::PowerDraw);
+    let discharge_w = if !discharging {
+        None
+    } else if power.is_empty() {
+        let mut events: Vec<_> = db::fetch_recent_events(db_path, RECENT_EVENTS_FOR_RATE)?
+            .into_iter()
+            .flatten()
+            .collect();
+        events.sort_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap());
+        average_rates(&aggregate_samples_by_timestamp(&events)).discharge_w
+    } else {
+        Some(power.iter().filter_map(|m| m.value).sum::<f64>() / power.len() as f64)
+    };
+    let max_temp = latest_values(&latest_metrics, MetricKind::Temperature)
+        .into_iter()
+        .filter_map(|m| Some((m.source.clone(), m.value?)))
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+    let latest_ts = latest_metrics
+        .iter()
+        .map(|m| m.ts)
+        .chain(battery_ts)
+        .max_by(f64::total_cmp);
+
+    Ok(CheckInputs {
+        percentage,
+        discharge_w,
+        max_temp,
+        latest_ts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_crossed_thresholds_fail() {
+        let thresholds = Thresholds {
+            below_pct: Some(15.0),
+            discharge_above_w: Some(20.0),
+            temp_above_c: Some(90.0),
+            max_age_s: Some(600.0),
+        };
+        let inputs = CheckInputs {
+            percentage: Some(12.5),
+            discharge_w: Some(8.0),
+            max_temp: Some(("x86_pkg_temp".to_string(), 95.0)),
+            latest_ts: Some(1000.0),
+        };
+
+        let failures = evaluate(&thresholds, &inputs, 1060.0);
+        assert_eq!(failures.len(), 2);
+        assert_eq!(
+            failures[0].to_string(),
+            "reason=battery_below value=12.50 threshold=15.00"
+        );
+        assert_eq!(
+            failures[1].to_string(),
+            "reason=temperature_above value=95.00 threshold=90.00 source=x86_pkg_temp"
+        );
+    }
+
+    #[test]
+    fn missing_inputs_never_fail() {
+        let thresholds = Thresholds {
+            below_pct: Some(15.0),
+            ..Thresholds::default()
+        };
+        assert!(evaluate(&thresholds, &CheckInputs::default(), 0.0).is_empty());
+    }
+
+    #[test]
+    fn old_samples_fail_as_stale() {
+        let thresholds = Thresholds {
+            max_age_s: Some(600.0),
+            ..Thresholds::default()
+        };
+        let inputs = CheckInputs {
+            latest_ts: Some(1000.0),
+            ..CheckInputs::default()
+        };
+        assert!(evaluate(&thresholds, &inputs, 1600.0).is_empty());
+        let failures = evaluate(&thresholds, &inputs, 1900.0);
+        assert_eq!(
+            failures[0].to_string(),
+            "reason=stale value=900.00 threshold=600.00"
+        );
+        // Nothing recorded at all is stale too.
+        let failures = evaluate(&thresholds, &CheckInputs::default(), 1900.0);
+        assert_eq!(failures[0].reason, "stale");
+        assert_eq!(failures[0].value, f64::INFINITY);
+    }
+
+    #[test]
+    fn inputs_come_from_latest_collection() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("check.db");
+        let mut conn = db::init_db_connection(&db_path).unwrap();
+        let battery = db::Sample {
+            ts: 10.0,
+            percentage: Some(42.0),
+            capacity_pct: None,
+            health_pct: None,
+            energy_now_wh: Some(21.0),
+            energy_full_wh: Some(50.0),
+            energy_full_design_wh: None,
+            status: Some("Discharging".to_string()),
+            source_path: "BAT0".to_string(),
+        };
+        let metrics = vec![
+            MetricSample::new(
+                10.0,
+                MetricKind::PowerDraw,
+                "rapl:power1",
+                Some(11.0),
+                Some("W"),
+                serde_json::Value::Null,
+            ),
+            MetricSample::new(
+                10.0,
+                MetricKind::Temperature,
+                "acpitz",
+                Some(55.0),
+                Some("C"),
+                serde_json::Value::Null,
+            ),
+        ];
+        db::insert_all_samples(&mut conn, &[battery], &metrics).unwrap();
+
+        let inputs = load_inputs(&db_path).unwrap();
+        assert_eq!(inputs.latest_ts, Some(10.0));
+        assert_eq!(inputs.discharge_w, Some(11.0));
+        assert_eq!(inputs.max_temp, Some(("acpitz".to_string(), 55.0)));
+        assert!((inputs.percentage.unwrap() - 42.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn power_draw_on_the_charger_is_not_discharge() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("check.db");
+        let mut conn = db::init_db_connection(&db_path).unwrap();
+        let battery = db::Sample {
+            ts: 10.0,
+            percentage: Some(42.0),
+            capacity_pct: None,
+            health_pct: None,
+            energy_now_wh: Some(21.0),
+            energy_full_wh: Some(50.0),
+            energy_full_design_wh: None,
+            status: Some("Charging".to_string()),
+            source_path: "BAT0".to_string(),
+        };
+        let metrics = vec![MetricSample::new(
+            10.0,
+            MetricKind::PowerDraw,
+            "rapl:power1",
+            Some(45.0),
+            Some("W"),
+            serde_json::Value::Null,
+        )];
+        db::insert_all_samples(&mut conn, &[battery], &metrics).unwrap();
+
+        let inputs = load_inputs(&db_path).unwrap();
+        assert_eq!(inputs.discharge_w, None);
+        let thresholds = Thresholds {
+            discharge_above_w: Some(20.0),
+            ..Thresholds::default()
+        };
+        assert!(evaluate(&thresholds, &inputs, 10.0).is_empty());
+    }
+}
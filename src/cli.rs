@@ -1,26 +1,94 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ffi::OsString;
-use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
 
-use anyhow::Result;
-use clap::{Parser, Subcommand, ValueEnum};
+use anyhow::{Context, Result};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
 use comfy_table::modifiers::UTF8_ROUND_CORNERS;
 use comfy_table::presets::UTF8_FULL_CONDENSED;
-use comfy_table::{Attribute, Cell, CellAlignment, Color, ContentArrangement, Table};
+use comfy_table::{Cell, CellAlignment, ContentArrangement, Table};
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use ordered_float::OrderedFloat;
 
-use crate::aggregate::aggregate_samples_by_timestamp;
+use crate::activity;
+use crate::aggregate::{
+    aggregate_samples_by_timestamp, reject_power_outliers, smooth_percentages, RejectedPower,
+};
+use crate::backup;
+use crate::baseline::{self, Baseline};
+use crate::bench::{self, BenchResult};
+use crate::calibrate::{self, Calibration};
+use crate::cgroups::{cgroup_usage, CgroupUsage};
+use crate::check::{self, Thresholds};
 use crate::cli_helpers::{
-    average_rates, bucket_span_seconds, bucket_start, default_graph_path, estimate_runtime_hours,
-    format_runtime,
+    average_pct_rates, average_rates, average_rates_awake, average_rates_by, bucket_start,
+    default_graph_path, discharge_segments, energy_by_day, energy_totals, format_runtime,
+    format_span, full_charge_time, histogram, parse_bucket, parse_window, resolve_bucket_seconds,
+    AverageRates, EnergyTotals, Histogram, WeekHeatmap,
+};
+use crate::collector::{
+    collect_loop, collect_once, now_ts, preview_once, resolve_db_path, resolve_db_path_with_origin,
+    CollectionPreview, CollectorRegistry,
+};
+use crate::completions::{self, Shell};
+use crate::config::{
+    load_config, parse_config, resolve_config_path, resolve_config_path_with_origin, Config,
+    EnergyConfig, GraphTheme, HealthConfig, LocationConfig, RuntimeConfig, RuntimeModel,
+};
+use crate::correlate::{self, Correlation};
+use crate::daily::{self, DailyComparison, DaySummary};
+use crate::db::{self, Annotation, DbStats, Sample, SleepInterval};
+use crate::disk::normalize_mountpoint;
+use crate::display;
+use crate::doctor;
+use crate::email;
+use crate::env::{self, Origin};
+use crate::exit::{self, Exit, ExitStatus};
+use crate::graph::{self, GraphStyle};
+use crate::habits::{ChargingHabits, MICRO_CHARGE_PCT};
+use crate::import::{parse_import, ImportFormat};
+use crate::influx;
+use crate::internal;
+use crate::interrupts::{self, SourceActivity, Storm};
+use crate::ipc::{self, Request};
+use crate::live::{self, LiveKey, RawTerminal};
+use crate::locale::{self, ByteUnits, Clock, DateOrder, Formats};
+use crate::logging::{init_logging, LogFormat, LogOptions};
+use crate::media::{AUDIO_CAPTURE_SOURCE, AUDIO_PLAYBACK_SOURCE, WEBCAM_SOURCE};
+use crate::metrics::{
+    MetricKind, MetricSample, CONTEXT_SWITCHES_SOURCE, CPU_GOVERNOR_SOURCE, INTERRUPTS_SOURCE,
+    LOAD_SOURCES, MEMORY_SOURCE, PLATFORM_PROFILE_SOURCE, SWAP_SOURCE,
 };
-use crate::collector::{collect_loop, collect_once, resolve_db_path};
-use crate::db::{self, Sample};
-use crate::graph;
-use crate::metrics::{MetricKind, MetricSample};
-use crate::timeframe::{build_timeframe, Timeframe};
+use crate::nix_module::NixModule;
+use crate::predict;
+use crate::progress;
+use crate::query::Query;
+use crate::regression;
+use crate::report::{self, ReportSection};
+use crate::runtime::{self, estimate_runtime, RuntimeEstimate};
+use crate::sensors::{self, sensor_matches};
+use crate::setup;
+use crate::signals;
+use crate::snapshot;
+use crate::sources::{self, DiscoveredSource};
+#[cfg(not(feature = "color"))]
+use crate::style::Unstyled;
+use crate::style::{Attribute, Color};
+use crate::synthetic::{self, Profile};
+use crate::sysfs::{
+    find_battery_paths, read_charge_thresholds, write_charge_thresholds, BatteryInfo,
+};
+use crate::thermal::{self, ThrottleCriteria, ThrottleEpisode};
+use crate::timeframe::{build_timeframe, CalendarPeriod, Timeframe};
+use crate::tunables::{self, Tunable};
+use crate::usb;
+use crate::versions;
+use crate::wear::{WearTrend, PROJECTION_TARGETS};
+use crate::webhook;
+use crate::workload;
+use crate::zone::Zone;
 
 #[derive(Parser)]
 #[command(name = "symmetri", version)]
@@ -30,6 +98,26 @@ use crate::timeframe::{build_timeframe, Timeframe};
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+    /// Byte prefixes: iec (KiB, MiB, GiB) or si (kB, MB, GB)
+    #[arg(long = "units", value_enum, global = true)]
+    pub units: Option<ByteUnits>,
+    /// Date order in tables and graphs (default: from LC_TIME/LANG, else ymd)
+    #[arg(long = "date-order", value_enum, global = true)]
+    pub date_order: Option<DateOrder>,
+    /// 24h or 12h clock (default: from LC_TIME/LANG, else 24h)
+    #[arg(long = "clock", value_enum, global = true)]
+    pub clock: Option<Clock>,
+    /// Show times, buckets and days in UTC
+    #[arg(long = "utc", global = true, conflicts_with = "timezone")]
+    pub utc: bool,
+    /// Show times, buckets and days in this IANA timezone (e.g. Europe/Paris) instead of
+    /// the system one
+    #[arg(long = "timezone", value_name = "ZONE", global = true)]
+    pub timezone: Option<Zone>,
+    /// Print nothing on stdout; errors still go to stderr and the exit code (2 no data,
+    /// 3 database error, 4 collector failure, 5 threshold tripped) is unchanged
+    #[arg(short = 'q', long = "quiet", global = true)]
+    pub quiet: bool,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -41,6 +129,280 @@ pub enum ReportPreset {
     Network,
     Temperature,
     Disk,
+    /// Load averages, context switches and interrupts per second
+    Load,
+    /// CPU, memory and IO of the cgroups listed in `[cgroups] paths`
+    Cgroups,
+    /// symmetri's own overhead (cycle time, db latency, RSS, collector errors)
+    Internal,
+}
+
+/// What `report --heatmap` averages per weekday and hour.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum HeatmapMetric {
+    /// Battery discharge power (awake only)
+    Power,
+    /// Overall CPU usage
+    Cpu,
+}
+
+/// How `query` and `db sql` print their rows.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(Subcommand)]
+pub enum ChargeLimitAction {
+    /// Show the current charge thresholds
+    Show,
+    /// Stop charging at END percent (optionally resume below --start)
+    Set {
+        /// Stop charging at this percentage
+        #[arg(value_parser = clap::value_parser!(u8).range(1..=100))]
+        end: u8,
+        /// Start charging again below this percentage
+        #[arg(long = "start", value_parser = clap::value_parser!(u8).range(0..=99))]
+        start: Option<u8>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Where the database, config file, log filter and control socket come from
+    Show,
+}
+
+/// Calendar-aligned windows shared by the commands that take `--hours`/`--days`.
+#[derive(Args, Debug, Clone, Copy, Default)]
+#[group(multiple = false)]
+pub struct CalendarArgs {
+    /// Since local midnight (overrides hours/days/months)
+    #[arg(long = "today", conflicts_with = "all_time")]
+    today: bool,
+    /// The previous local day, midnight to midnight
+    #[arg(long = "yesterday", conflicts_with = "all_time")]
+    yesterday: bool,
+    /// Since Monday 00:00 local time
+    #[arg(long = "this-week", conflicts_with = "all_time")]
+    this_week: bool,
+    /// Since the 1st of the month, 00:00 local time
+    #[arg(long = "this-month", conflicts_with = "all_time")]
+    this_month: bool,
+}
+
+impl CalendarArgs {
+    pub fn period(&self) -> Option<CalendarPeriod> {
+        [
+            (self.today, CalendarPeriod::Today),
+            (self.yesterday, CalendarPeriod::Yesterday),
+            (self.this_week, CalendarPeriod::ThisWeek),
+            (self.this_month, CalendarPeriod::ThisMonth),
+        ]
+        .into_iter()
+        .find_map(|(set, period)| set.then_some(period))
+    }
+}
+
+#[derive(Subcommand)]
+pub enum DbAction {
+    /// Row counts per table and metric source, file size and time span
+    Stats,
+    /// Rebuild the database file to reclaim unused space
+    Vacuum,
+    /// Remove duplicate samples (e.g. from two collectors running at once)
+    Dedupe,
+    /// Copy the database safely while the collector runs (online backup)
+    Backup {
+        /// Backup file, or a directory to write a timestamped `symmetri-*.sqlite` into
+        path: PathBuf,
+        /// Gzip the copy (implied by a `.gz` path)
+        #[arg(long = "gzip")]
+        gzip: bool,
+    },
+    /// Replace the database with a backup (plain or gzipped)
+    Restore {
+        /// Backup file written by `db backup`
+        path: PathBuf,
+        /// Replace a database that already has samples
+        #[arg(long = "force")]
+        force: bool,
+    },
+    /// Store metric details zstd-compressed from now on and compress the stored ones
+    Compress {
+        /// Turn compression off and store every row's details as JSON text again
+        #[arg(long = "undo")]
+        undo: bool,
+    },
+    /// Run a read-only SQL statement, e.g. `db sql "SELECT kind, COUNT(*) FROM metric_samples GROUP BY kind"`
+    Sql {
+        /// The statement; anything that writes is refused
+        sql: String,
+        #[arg(long = "format", value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+    /// Table definitions, column meanings and units, and the metric kinds
+    Schema {
+        /// Print as JSON for tools reading the database
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// SQLite query plans of the reads reports and exports run; exits 5 if one scans or sorts
+    Explain,
+}
+
+#[derive(Subcommand)]
+pub enum AnalyzeAction {
+    /// Rank metrics by how closely discharge power follows them, directly and shifted in time
+    Correlate {
+        /// Metrics to test: cpu, frequency, gpu, memory, load, power, screen, brightness,
+        /// network, disk or any metric kind (e.g. temperature)
+        #[arg(
+            long = "with",
+            value_name = "METRICS",
+            value_delimiter = ',',
+            default_value = "cpu,gpu,temperature,brightness,network"
+        )]
+        with: Vec<String>,
+        /// Largest shift tried either way between a metric and the drain (e.g. 10m, 1h)
+        #[arg(long = "max-lag", value_name = "SPAN", default_value = "10m", value_parser = parse_window)]
+        max_lag: i64,
+        /// Window in hours (used when days/months are zero)
+        #[arg(long = "hours", default_value_t = 6)]
+        hours: u64,
+        /// Window in days (overrides hours when non-zero)
+        #[arg(long = "days", default_value_t = 0)]
+        days: u64,
+        /// Window in months (~30d each; overrides days/hours when non-zero)
+        #[arg(long = "months", default_value_t = 0)]
+        months: u64,
+        /// Ignore timeframe limits and use the entire history
+        #[arg(long = "all")]
+        all_time: bool,
+        #[command(flatten)]
+        calendar: CalendarArgs,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+    /// Compare discharge power before and after each recorded kernel/OS/symmetri update
+    /// and flag significant regressions (Mann–Whitney U test)
+    Regressions {
+        /// Discharge history compared on each side of an update (e.g. 3d, 2w)
+        #[arg(long = "window", value_name = "SPAN", default_value = "7d", value_parser = parse_window)]
+        window: i64,
+        /// Significance level below which a shift counts
+        #[arg(long = "alpha", default_value_t = 0.01)]
+        alpha: f64,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum MarkAction {
+    /// Start an activity, stopping the one running
+    Start {
+        /// Activity name (e.g. meeting)
+        name: String,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+    /// Stop the running activity
+    Stop {
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BaselineAction {
+    /// Profile the history just recorded (run it right after a controlled period, such as
+    /// an idle desktop) and store it as NAME, replacing any baseline of that name
+    Save {
+        /// Name to save it under (e.g. idle-powersave)
+        name: String,
+        /// History to profile, ending now (e.g. 30m, 1h, 2h)
+        #[arg(long = "last", value_name = "SPAN", default_value = "1h", value_parser = parse_window)]
+        last: i64,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+        /// Path to the config file (or set SYMMETRI_CONFIG)
+        #[arg(long = "config")]
+        config_path: Option<PathBuf>,
+    },
+    /// Show how far each metric of a recent window is from a baseline
+    Compare {
+        /// Baseline to compare with; defaults to the most recently saved one
+        name: Option<String>,
+        /// Window in hours (used when days/months are zero)
+        #[arg(long = "hours", default_value_t = 1)]
+        hours: u64,
+        /// Window in days (overrides hours when non-zero)
+        #[arg(long = "days", default_value_t = 0)]
+        days: u64,
+        /// Window in months (~30d each; overrides days/hours when non-zero)
+        #[arg(long = "months", default_value_t = 0)]
+        months: u64,
+        /// Ignore timeframe limits and use the entire history
+        #[arg(long = "all")]
+        all_time: bool,
+        #[command(flatten)]
+        calendar: CalendarArgs,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+        /// Path to the config file (or set SYMMETRI_CONFIG)
+        #[arg(long = "config")]
+        config_path: Option<PathBuf>,
+    },
+    /// List the saved baselines
+    List {
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+    /// Delete a saved baseline
+    Delete {
+        name: String,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DevAction {
+    /// Fill a database with synthetic battery and metric curves
+    Generate {
+        /// Days of data, ending now
+        #[arg(long = "days", default_value_t = 30, value_parser = clap::value_parser!(u32).range(1..=3650))]
+        days: u32,
+        /// Usage pattern to simulate
+        #[arg(long = "profile", value_enum, default_value_t = Profile::OfficeLaptop)]
+        profile: Profile,
+        /// Seconds between readings while awake
+        #[arg(long = "interval", default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+        /// Seed for the noise; the same seed gives the same data
+        #[arg(long = "seed", default_value_t = 1)]
+        seed: u64,
+        /// Write into a database that already has battery samples
+        #[arg(long = "force")]
+        force: bool,
+    },
+    /// Time inserts, reads over several windows and aggregation on the database
+    Bench {
+        /// Runs per step; the median is shown
+        #[arg(long = "runs", default_value_t = 3, value_parser = clap::value_parser!(u64).range(1..=100))]
+        runs: u64,
+    },
 }
 
 #[derive(Subcommand)]
@@ -53,10 +415,437 @@ pub enum Commands {
         /// Optional interval seconds to loop forever
         #[arg(long = "interval")]
         interval: Option<u64>,
+        /// Path to the config file (or set SYMMETRI_CONFIG)
+        #[arg(long = "config")]
+        config_path: Option<PathBuf>,
+        /// Log output format
+        #[arg(long = "log-format", value_enum, default_value_t = LogFormat::Text)]
+        log_format: LogFormat,
+        /// Write logs to this file instead of stderr (rotated daily, 7 files kept)
+        #[arg(long = "log-file")]
+        log_file: Option<PathBuf>,
+        /// Print what would be stored as JSON instead of writing the database
+        #[arg(long = "dry-run", visible_alias = "stdout", conflicts_with_all = ["db_path", "interval"])]
+        dry_run: bool,
+        /// Stop the collector already writing to this database and take over
+        #[arg(long = "replace", requires = "interval")]
+        replace: bool,
         /// Enable debug logging
         #[arg(short, long)]
         verbose: bool,
     },
+    /// Record a full discharge and store how far the gauge's percentage is from the energy
+    /// actually left, so runtime estimates can correct for it
+    Calibrate {
+        /// Battery to calibrate (e.g. BAT0); defaults to the first battery
+        #[arg(long = "battery")]
+        battery: Option<String>,
+        /// Seconds between readings while discharging
+        #[arg(long = "interval", default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+        /// Stop recording once the gauge reads this percentage
+        #[arg(long = "stop-at", value_name = "PERCENT", default_value_t = 5.0)]
+        stop_at: f64,
+        /// Print the stored calibrations instead of running one
+        #[arg(long = "show", conflicts_with_all = ["battery", "interval", "stop_at"])]
+        show: bool,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+        /// Path to the config file (or set SYMMETRI_CONFIG)
+        #[arg(long = "config")]
+        config_path: Option<PathBuf>,
+    },
+    /// Talk to a running `collect --interval` daemon over its control socket
+    Ctl {
+        /// Request to send
+        #[arg(value_enum)]
+        request: Request,
+        /// Control socket path (or set SYMMETRI_SOCKET; default $XDG_RUNTIME_DIR/symmetri.sock)
+        #[arg(long = "socket")]
+        socket: Option<PathBuf>,
+    },
+    /// Watch battery and system gauges refresh in the terminal (space pauses, h shows the
+    /// last hour, q quits)
+    Live {
+        /// Seconds between refreshes
+        #[arg(long = "interval", default_value_t = 1)]
+        interval: u64,
+        /// Path to the config file (or set SYMMETRI_CONFIG)
+        #[arg(long = "config")]
+        config_path: Option<PathBuf>,
+        /// Path to SQLite database (or set SYMMETRI_DB), for the history view
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+        /// Control socket of a running collector to read from (or set SYMMETRI_SOCKET)
+        #[arg(long = "socket")]
+        socket: Option<PathBuf>,
+    },
+    /// Exit with status 5 when the latest collection crosses a threshold
+    Check {
+        /// Fail when the battery percentage is below this value
+        #[arg(long = "below", value_name = "PERCENT")]
+        below: Option<f64>,
+        /// Fail when the battery is discharging at more than this many watts
+        #[arg(long = "discharge-above", value_name = "WATTS")]
+        discharge_above: Option<f64>,
+        /// Fail when any temperature sensor is above this many degrees Celsius
+        #[arg(long = "temp-above", value_name = "CELSIUS")]
+        temp_above: Option<f64>,
+        /// Fail with reason=stale when the newest sample is older than this (e.g. 10m, 1h)
+        #[arg(long = "max-age", value_name = "SPAN", value_parser = parse_window)]
+        max_age: Option<i64>,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+    /// Show or set vendor charge thresholds (charge_control_end_threshold)
+    ChargeLimit {
+        #[command(subcommand)]
+        action: ChargeLimitAction,
+        /// Only touch this battery (e.g. BAT0); defaults to every battery
+        #[arg(long = "battery", global = true)]
+        battery: Option<String>,
+    },
+    /// Audit power tunables (USB autosuspend, SATA link power, PCIe ASPM, NMI watchdog)
+    Tune {
+        /// List tunables with their current and recommended values (the default)
+        #[arg(long = "audit", conflicts_with = "apply")]
+        audit: bool,
+        /// Set every tunable to its recommended value and record an annotation (needs root)
+        #[arg(long = "apply")]
+        apply: bool,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+    /// First-run setup: a starter config, the database and, optionally, systemd user timers
+    Init {
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+        /// Path to the config file (or set SYMMETRI_CONFIG)
+        #[arg(long = "config")]
+        config_path: Option<PathBuf>,
+        /// Answer yes to every question instead of prompting
+        #[arg(short = 'y', long = "yes")]
+        yes: bool,
+        /// Install and enable the systemd user timers without asking
+        #[arg(long = "systemd")]
+        systemd: bool,
+    },
+    /// Inspect the resolved settings
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db", global = true)]
+        db_path: Option<PathBuf>,
+        /// Path to the config file (or set SYMMETRI_CONFIG)
+        #[arg(long = "config", global = true)]
+        config_path: Option<PathBuf>,
+    },
+    /// Print a shell completion script (e.g. `symmetri completions fish > ~/.config/fish/completions/symmetri.fish`)
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Print the man page, or write one page per subcommand with --dir
+    Man {
+        /// Write symmetri.1 and symmetri-<subcommand>.1 pages into this directory
+        #[arg(long = "dir", value_name = "DIR")]
+        dir: Option<PathBuf>,
+    },
+    /// List every battery, sensor, interface and disk the collectors see, with current values,
+    /// including those the config leaves out
+    Sources {
+        /// Path to the config file (or set SYMMETRI_CONFIG)
+        #[arg(long = "config")]
+        config_path: Option<PathBuf>,
+        /// Print JSON instead of a table
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Check batteries, sensors, the database, the clock and the systemd units, with hints
+    Doctor {
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+        /// Path to the config file (or set SYMMETRI_CONFIG)
+        #[arg(long = "config")]
+        config_path: Option<PathBuf>,
+    },
+    /// Print a NixOS (or home-manager) module with the collector and summary units and the
+    /// current config file
+    NixModule {
+        /// Emit a home-manager module with user units instead of a NixOS one
+        #[arg(long = "home-manager")]
+        home_manager: bool,
+        /// Database the collector writes (or set SYMMETRI_DB); default /var/lib/symmetri for
+        /// NixOS, the user's database for home-manager
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+        /// Path to the config file to embed (or set SYMMETRI_CONFIG)
+        #[arg(long = "config")]
+        config_path: Option<PathBuf>,
+        /// Seconds between collections
+        #[arg(long = "interval", default_value_t = 300, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+    },
+    /// Inspect or compact the database
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db", global = true)]
+        db_path: Option<PathBuf>,
+    },
+    /// Development helpers (synthetic data, benchmarks)
+    Dev {
+        #[command(subcommand)]
+        action: DevAction,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db", global = true)]
+        db_path: Option<PathBuf>,
+    },
+    /// Write stored samples in another format (InfluxDB line protocol)
+    Export {
+        /// InfluxDB line protocol (the only format so far)
+        #[arg(long = "influx", required = true)]
+        influx: bool,
+        /// Window in hours (used when days/months are zero)
+        #[arg(long = "hours", default_value_t = 6)]
+        hours: u64,
+        /// Window in days (overrides hours when non-zero)
+        #[arg(long = "days", default_value_t = 0)]
+        days: u64,
+        /// Window in months (~30d each; overrides days/hours when non-zero)
+        #[arg(long = "months", default_value_t = 0)]
+        months: u64,
+        /// Ignore timeframe limits and use the entire history
+        #[arg(long = "all")]
+        all_time: bool,
+        #[command(flatten)]
+        calendar: CalendarArgs,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+        /// Write to this file instead of stdout
+        #[arg(long = "output")]
+        output: Option<PathBuf>,
+    },
+    /// Compare two databases side by side (two machines, or before/after a reinstall)
+    CompareHosts {
+        /// First database; the Delta column is this one minus --db-b
+        #[arg(long = "db-a", value_name = "PATH")]
+        db_a: PathBuf,
+        /// Second database
+        #[arg(long = "db-b", value_name = "PATH")]
+        db_b: PathBuf,
+        /// Column name for --db-a (default: its file name)
+        #[arg(long = "label-a")]
+        label_a: Option<String>,
+        /// Column name for --db-b (default: its file name)
+        #[arg(long = "label-b")]
+        label_b: Option<String>,
+        /// Window in hours (used when days/months are zero)
+        #[arg(long = "hours", default_value_t = 0)]
+        hours: u64,
+        /// Window in days (overrides hours when non-zero)
+        #[arg(long = "days", default_value_t = 7)]
+        days: u64,
+        /// Window in months (~30d each; overrides days/hours when non-zero)
+        #[arg(long = "months", default_value_t = 0)]
+        months: u64,
+        /// Compare the entire history of both databases
+        #[arg(long = "all")]
+        all_time: bool,
+        /// Path to the config file (or set SYMMETRI_CONFIG)
+        #[arg(long = "config")]
+        config_path: Option<PathBuf>,
+        /// Print the tables as JSON instead of text
+        #[arg(long = "json")]
+        json: bool,
+    },
+    /// Import battery history exported from UPower or other loggers
+    Import {
+        /// Input format
+        #[arg(long = "format", value_enum)]
+        format: ImportFormat,
+        /// Files to import
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Source name for rows that do not name a battery (defaults to each file's name)
+        #[arg(long = "source")]
+        source: Option<String>,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+    /// Store a timestamped note shown in reports and graphs
+    Annotate {
+        /// Annotation text (e.g. "switched to power-saver")
+        text: String,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+    /// List suspend periods with battery drain per hour asleep
+    SleepReport {
+        /// Window in hours (used when days/months are zero)
+        #[arg(long = "hours", default_value_t = 6)]
+        hours: u64,
+        /// Window in days (overrides hours when non-zero)
+        #[arg(long = "days", default_value_t = 0)]
+        days: u64,
+        /// Window in months (~30d each; overrides days/hours when non-zero)
+        #[arg(long = "months", default_value_t = 0)]
+        months: u64,
+        /// Ignore timeframe limits and use the entire history
+        #[arg(long = "all")]
+        all_time: bool,
+        #[command(flatten)]
+        calendar: CalendarArgs,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+    /// List thermal throttling episodes: high temperature while CPU frequency dropped
+    Thermals {
+        /// Window in hours (used when days/months are zero)
+        #[arg(long = "hours", default_value_t = 6)]
+        hours: u64,
+        /// Window in days (overrides hours when non-zero)
+        #[arg(long = "days", default_value_t = 0)]
+        days: u64,
+        /// Window in months (~30d each; overrides days/hours when non-zero)
+        #[arg(long = "months", default_value_t = 0)]
+        months: u64,
+        /// Ignore timeframe limits and use the entire history
+        #[arg(long = "all")]
+        all_time: bool,
+        #[command(flatten)]
+        calendar: CalendarArgs,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+        /// Only consider these temperature sensors (repeatable; case-insensitive, `*` wildcards)
+        #[arg(long = "sensor", value_name = "NAME")]
+        sensor_filters: Vec<String>,
+        /// Path to the config file (or set SYMMETRI_CONFIG)
+        #[arg(long = "config")]
+        config_path: Option<PathBuf>,
+        /// Temperature (°C) at or above which a slow CPU counts as throttled
+        #[arg(long = "min-temp", default_value_t = thermal::DEFAULT_MIN_TEMP_C)]
+        min_temp: f64,
+        /// How far (%) the average CPU frequency must fall below its usual peak
+        #[arg(long = "freq-drop", default_value_t = thermal::DEFAULT_FREQ_DROP_PCT)]
+        freq_drop: f64,
+    },
+    /// Rank interrupt and wake-up sources and list interrupt storms, or find what drain
+    /// follows (`analyze correlate`)
+    #[command(args_conflicts_with_subcommands = true)]
+    Analyze {
+        #[command(subcommand)]
+        action: Option<AnalyzeAction>,
+        /// Window in hours (used when days/months are zero)
+        #[arg(long = "hours", default_value_t = 6)]
+        hours: u64,
+        /// Window in days (overrides hours when non-zero)
+        #[arg(long = "days", default_value_t = 0)]
+        days: u64,
+        /// Window in months (~30d each; overrides days/hours when non-zero)
+        #[arg(long = "months", default_value_t = 0)]
+        months: u64,
+        /// Ignore timeframe limits and use the entire history
+        #[arg(long = "all")]
+        all_time: bool,
+        #[command(flatten)]
+        calendar: CalendarArgs,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+        /// Number of wake-up sources to list
+        #[arg(long = "top", default_value_t = 10)]
+        top: usize,
+    },
+    /// Save named baselines of a controlled period and score later windows against them
+    Baseline {
+        #[command(subcommand)]
+        action: BaselineAction,
+    },
+    /// Predict whether the current charge lasts through a planned mix of workloads, from
+    /// the drain each workload class cost in the recorded history
+    Predict {
+        /// Planned workloads in order, e.g. "video:2h,browsing:3h" (idle, browsing,
+        /// compiling, video or gaming)
+        #[arg(long = "workload", value_name = "PLAN")]
+        workload: String,
+        /// Days of history to learn each workload's drain from
+        #[arg(long = "days", default_value_t = 30)]
+        days: u64,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+        /// Path to the config file (or set SYMMETRI_CONFIG)
+        #[arg(long = "config")]
+        config_path: Option<PathBuf>,
+    },
+    /// Label history in 5-minute windows as idle, browsing, compiling, video or gaming and
+    /// store the labels, so `report --workloads` can group drain by class
+    Classify {
+        /// Days of history to (re)label
+        #[arg(long = "days", default_value_t = 30)]
+        days: u64,
+        /// Label the entire history
+        #[arg(long = "all", conflicts_with = "days")]
+        all_time: bool,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
+    /// Battery vendor, model, serial and design capacity, with each battery's current health
+    /// and when its wear trend reaches 80% and 60% of design capacity
+    Health {
+        /// Send a desktop notification when a battery's projection newly falls within the
+        /// `[health]` warning, instead of printing
+        #[arg(long = "notify")]
+        notify: bool,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+        /// Path to the config file (or set SYMMETRI_CONFIG)
+        #[arg(long = "config")]
+        config_path: Option<PathBuf>,
+    },
+    /// Today's battery use next to the trailing 7-day average
+    Summary {
+        /// Send it as a desktop notification (notify-send) instead of printing it
+        #[arg(long = "notify")]
+        notify: bool,
+        /// Post it to a `[webhooks]` entry or URL instead of printing it; repeatable
+        #[arg(long = "webhook", value_name = "NAME")]
+        webhooks: Vec<String>,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+        /// Path to the config file (or set SYMMETRI_CONFIG)
+        #[arg(long = "config")]
+        config_path: Option<PathBuf>,
+    },
+    /// Ad-hoc aggregates over the stored samples, e.g. `avg(discharge_w) by day last 30d`
+    Query {
+        /// AGG(FIELD) [where source=NAME|status=NAME [and ...]] [by hour|day|week|month|source,...]
+        /// [last 6h|30d|2w]. AGG is avg, min, max, sum or count; FIELD is a metric kind or
+        /// percentage, health, capacity, energy_wh, discharge_w, charge_w
+        #[arg(value_name = "QUERY", required = true, num_args = 1..)]
+        query: Vec<String>,
+        #[arg(long = "format", value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+    },
     /// Render a timeframe report (optionally save a graph image)
     Report {
         /// Window in hours (used when days/months are zero)
@@ -71,7 +860,9 @@ pub enum Commands {
         /// Ignore timeframe limits and use the entire history
         #[arg(long = "all")]
         all_time: bool,
-        /// Path to SQLite database (or set BATTERY_MONITOR_DB)
+        #[command(flatten)]
+        calendar: CalendarArgs,
+        /// Path to SQLite database (or set SYMMETRI_DB)
         #[arg(long = "db")]
         db_path: Option<PathBuf>,
         /// Save a graph image with an auto-generated name
@@ -80,9 +871,19 @@ pub enum Commands {
         /// Custom path for the graph image (png/pdf/etc); overrides --graph name
         #[arg(long = "graph-path")]
         graph_path: Option<PathBuf>,
-        /// Limit metrics to specific sensor names (repeatable)
+        /// Graph colors; overrides `[graph] theme` in the config
+        #[arg(long = "graph-theme", value_enum)]
+        graph_theme: Option<GraphTheme>,
+        /// Downsample each graph line to at most this many points (0 keeps all); overrides
+        /// `[graph] max_points`
+        #[arg(long = "max-points", value_name = "N")]
+        max_points: Option<usize>,
+        /// Limit metrics to specific sensor names (repeatable; case-insensitive, `*` wildcards)
         #[arg(long = "sensor", value_name = "NAME", num_args = 0..)]
         sensor_filters: Vec<String>,
+        /// Limit disk usage to these mountpoints (repeatable, e.g. `--mount /home`)
+        #[arg(long = "mount", value_name = "PATH")]
+        mount_filters: Vec<String>,
         /// Which report presets to render (repeatable)
         #[arg(
             long = "preset",
@@ -91,23 +892,87 @@ pub enum Commands {
             default_values_t = [ReportPreset::Battery]
         )]
         presets: Vec<ReportPreset>,
+        /// Path to the config file (or set SYMMETRI_CONFIG)
+        #[arg(long = "config")]
+        config_path: Option<PathBuf>,
+        /// Compare the battery summary against the preceding window of equal length (the
+        /// whole previous day, week or month with --today/--this-week/...)
+        #[arg(long = "compare")]
+        compare: bool,
+        /// Include symmetri's own overhead (same as --preset internal)
+        #[arg(long = "internal")]
+        internal: bool,
+        /// Report on the past 7 days (overrides hours/days/months)
+        #[arg(long = "weekly", conflicts_with_all = ["all_time", "CalendarArgs"])]
+        weekly: bool,
+        /// Write a self-contained HTML report (tables and graphs) to this path
+        #[arg(long = "output", short = 'o')]
+        output: Option<PathBuf>,
+        /// Email the HTML report to this address through the `[email]` SMTP server
+        /// (repeatable)
+        #[arg(long = "email", value_name = "ADDRESS", conflicts_with = "json")]
+        emails: Vec<String>,
+        /// Add a weekday × hour-of-day grid of discharge power (default) or CPU usage
+        #[arg(long = "heatmap", value_enum, num_args = 0..=1, default_missing_value = "power")]
+        heatmap: Option<HeatmapMetric>,
+        /// Add a histogram of discharge power with p50/p90/p99 (also drawn next to --graph)
+        #[arg(long = "distribution")]
+        distribution: bool,
+        /// Add discharge time, energy and average drain per workload class, from the labels
+        /// `symmetri classify` stored
+        #[arg(long = "workloads")]
+        workloads: bool,
+        /// Score the window against this saved baseline instead of the most recently saved
+        /// one
+        #[arg(long = "baseline", value_name = "NAME")]
+        baseline: Option<String>,
+        /// Table bucket size (e.g. 15m, 1h, 1d) instead of one picked from the window length
+        #[arg(long = "bucket", value_name = "SIZE", value_parser = parse_bucket)]
+        bucket: Option<i64>,
+        /// Print the report tables as JSON instead of text
+        #[arg(long = "json", conflicts_with = "output")]
+        json: bool,
+        /// Clear the screen and re-render the report every SECONDS until Ctrl-C
+        #[arg(
+            long = "watch",
+            value_name = "SECONDS",
+            conflicts_with_all = ["output", "emails", "graph", "graph_path", "json"]
+        )]
+        watch: Option<u64>,
         /// Enable debug logging
         #[arg(short, long)]
         verbose: bool,
     },
+    /// Record named activity intervals (`mark start meeting`, `mark stop`); reports add
+    /// the duration, energy and average drain of each activity
+    Mark {
+        #[command(subcommand)]
+        action: MarkAction,
+    },
+    /// Pack the data recorded around an incident into a .tar.gz for a bug report
+    Snapshot {
+        /// When it happened: "2024-05-02 14:30" (in the report time zone), RFC 3339 or Unix
+        /// seconds
+        #[arg(long = "around", value_name = "TIME")]
+        around: String,
+        /// History to include, centered on --around (e.g. 30m, 2h, 1d)
+        #[arg(long = "window", value_name = "SPAN", default_value = "2h", value_parser = parse_window)]
+        window: i64,
+        /// Where to write the archive
+        #[arg(long = "output", short = 'o', default_value = "incident.tar.gz")]
+        output: PathBuf,
+        /// Graph colors; overrides `[graph] theme` in the config
+        #[arg(long = "graph-theme", value_enum)]
+        graph_theme: Option<GraphTheme>,
+        /// Path to SQLite database (or set SYMMETRI_DB)
+        #[arg(long = "db")]
+        db_path: Option<PathBuf>,
+        /// Path to the config file (or set SYMMETRI_CONFIG)
+        #[arg(long = "config")]
+        config_path: Option<PathBuf>,
+    },
 }
 
-fn configure_logging(verbose: bool) {
-    let mut builder = env_logger::Builder::from_env(env_logger::Env::default());
-    builder.format(|buf, record| writeln!(buf, "{}", record.args()));
-    if verbose {
-        builder.filter_level(log::LevelFilter::Debug);
-    } else {
-        builder.filter_level(log::LevelFilter::Info);
-    }
-    let _ = builder.try_init();
-}
-
 fn normalize_presets(mut presets: Vec<ReportPreset>) -> Vec<ReportPreset> {
     if presets.is_empty() {
         return vec![ReportPreset::Battery];
@@ -121,7 +986,14 @@ fn metric_kinds_for_presets(presets: &[ReportPreset]) -> Vec<MetricKind> {
     let mut kinds = Vec::new();
     for preset in presets {
         match preset {
-            ReportPreset::Battery => kinds.push(MetricKind::PowerDraw),
+            ReportPreset::Battery => {
+                kinds.push(MetricKind::PowerDraw);
+                kinds.push(MetricKind::PowerProfile);
+                kinds.push(MetricKind::Media);
+                kinds.push(MetricKind::Display);
+                kinds.push(MetricKind::Usb);
+                kinds.push(MetricKind::Wifi);
+            }
             ReportPreset::Cpu => {
                 kinds.push(MetricKind::CpuUsage);
                 kinds.push(MetricKind::CpuFrequency);
@@ -131,9 +1003,16 @@ fn metric_kinds_for_presets(presets: &[ReportPreset]) -> Vec<MetricKind> {
                 kinds.push(MetricKind::GpuFrequency);
             }
             ReportPreset::Memory => kinds.push(MetricKind::MemoryUsage),
-            ReportPreset::Network => kinds.push(MetricKind::NetworkBytes),
-            ReportPreset::Temperature => kinds.push(MetricKind::Temperature),
+            ReportPreset::Network => kinds.extend([MetricKind::NetworkBytes, MetricKind::Wifi]),
+            ReportPreset::Temperature => {
+                kinds.push(MetricKind::Temperature);
+                // Needed to spot throttling next to the temperatures.
+                kinds.push(MetricKind::CpuFrequency);
+            }
             ReportPreset::Disk => kinds.push(MetricKind::DiskUsage),
+            ReportPreset::Load => kinds.push(MetricKind::SystemLoad),
+            ReportPreset::Cgroups => kinds.push(MetricKind::Cgroup),
+            ReportPreset::Internal => kinds.push(MetricKind::Internal),
         }
     }
     kinds.sort_by(|a, b| a.as_str().cmp(b.as_str()));
@@ -153,9 +1032,14 @@ fn has_data_for_preset(preset: ReportPreset, samples: &[Sample], metrics: &[Metr
             .iter()
             .any(|m| matches!(m.kind, MetricKind::GpuUsage | MetricKind::GpuFrequency)),
         ReportPreset::Memory => metrics.iter().any(|m| m.kind == MetricKind::MemoryUsage),
-        ReportPreset::Network => metrics.iter().any(|m| m.kind == MetricKind::NetworkBytes),
+        ReportPreset::Network => metrics
+            .iter()
+            .any(|m| matches!(m.kind, MetricKind::NetworkBytes | MetricKind::Wifi)),
         ReportPreset::Temperature => metrics.iter().any(|m| m.kind == MetricKind::Temperature),
         ReportPreset::Disk => metrics.iter().any(|m| m.kind == MetricKind::DiskUsage),
+        ReportPreset::Load => metrics.iter().any(|m| m.kind == MetricKind::SystemLoad),
+        ReportPreset::Cgroups => metrics.iter().any(|m| m.kind == MetricKind::Cgroup),
+        ReportPreset::Internal => metrics.iter().any(|m| m.kind == MetricKind::Internal),
     }
 }
 
@@ -165,585 +1049,4047 @@ where
     T: Into<OsString> + Clone,
 {
     let cli = Cli::parse_from(args);
+    if cli.quiet {
+        exit::silence_stdout().context("Cannot silence stdout for --quiet")?;
+        progress::set_enabled(false);
+    }
+    let mut formats = Formats::from_env();
+    formats.units = cli.units.unwrap_or(formats.units);
+    formats.date_order = cli.date_order.unwrap_or(formats.date_order);
+    formats.clock = cli.clock.unwrap_or(formats.clock);
+    formats.zone = if cli.utc {
+        Zone::Utc
+    } else {
+        cli.timezone.unwrap_or(formats.zone)
+    };
+    locale::set_current(formats);
     match cli.command {
         Commands::Collect {
             db_path,
             interval,
+            config_path,
+            log_format,
+            log_file,
+            dry_run,
+            replace,
             verbose,
         } => {
-            configure_logging(verbose);
-            if let Some(interval) = interval {
-                collect_loop(interval, db_path.as_deref(), None)?;
+            init_logging(&LogOptions {
+                verbose,
+                format: log_format,
+                file: log_file,
+            })?;
+            let config = load_config(config_path.as_deref())?;
+            let unknown = CollectorRegistry::from_config(&config).unknown_names(&config);
+            if !unknown.is_empty() {
+                tracing::warn!(
+                    "Ignoring config for unknown collectors: {}",
+                    unknown.join(", ")
+                );
+            }
+            if cfg!(not(feature = "otlp")) && config.otlp.endpoint.is_some() {
+                tracing::warn!("Ignoring [otlp]: this build lacks the `otlp` feature");
+            }
+            if dry_run {
+                let preview = preview_once(None, &config);
+                println!("{}", serde_json::to_string_pretty(&preview)?);
+            } else if let Some(interval) = interval {
+                collect_loop(
+                    interval,
+                    db_path.as_deref(),
+                    None,
+                    config_path.as_deref(),
+                    &config,
+                    replace,
+                )?;
             } else {
-                let code = collect_once(db_path.as_deref(), None)?;
-                if code != 0 {
-                    std::process::exit(code);
+                let status = collect_once(db_path.as_deref(), None, &config)?;
+                if status != ExitStatus::Success {
+                    return Err(Exit::silent(status).into());
                 }
             }
         }
-        Commands::Report {
+        Commands::Ctl { request, socket } => {
+            let path = socket.unwrap_or_else(ipc::socket_path);
+            let response = ipc::send_request(&path, request)?;
+            println!("{}", serde_json::to_string_pretty(&response)?);
+        }
+        Commands::Live {
+            interval,
+            config_path,
+            db_path,
+            socket,
+        } => {
+            let config = load_config(config_path.as_deref())?;
+            let resolved = resolve_db_path(db_path.as_deref());
+            let socket = socket.unwrap_or_else(ipc::socket_path);
+            signals::install_handlers()?;
+            let terminal = RawTerminal::enable();
+            let interval = Duration::from_secs(interval.max(1));
+            let mut paused = false;
+            let mut history: Option<String> = None;
+            let mut reading = live_reading(&socket, &config);
+            while !signals::shutdown_requested() {
+                let screen = match &history {
+                    Some(history) => history.clone(),
+                    None => live_view(&reading, interval, paused),
+                };
+                print!("\x1b[2J\x1b[H{screen}");
+                std::io::Write::flush(&mut std::io::stdout())?;
+                let deadline = Instant::now() + interval;
+                let mut quit = false;
+                while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+                    if signals::shutdown_requested() {
+                        break;
+                    }
+                    let Some(terminal) = terminal.as_ref() else {
+                        std::thread::sleep(remaining.min(Duration::from_millis(250)));
+                        continue;
+                    };
+                    match terminal.read_key(remaining).and_then(live::key_action) {
+                        Some(LiveKey::Quit) => quit = true,
+                        Some(LiveKey::TogglePause) => paused = !paused,
+                        Some(LiveKey::ToggleHistory) => {
+                            history = match history {
+                                Some(_) => None,
+                                None => Some(history_view(&resolved)),
+                            };
+                        }
+                        None => continue,
+                    }
+                    break;
+                }
+                if quit {
+                    break;
+                }
+                if !paused && history.is_none() {
+                    reading = live_reading(&socket, &config);
+                }
+            }
+        }
+        Commands::Check {
+            below,
+            discharge_above,
+            temp_above,
+            max_age,
+            db_path,
+        } => {
+            let thresholds = Thresholds {
+                below_pct: below,
+                discharge_above_w: discharge_above,
+                temp_above_c: temp_above,
+                max_age_s: max_age.map(|seconds| seconds as f64),
+            };
+            if thresholds.is_empty() {
+                anyhow::bail!(
+                    "pass at least one of --below, --discharge-above, --temp-above or --max-age"
+                );
+            }
+            let resolved = resolve_db_path(db_path.as_deref());
+            let inputs = check::load_inputs(&resolved)?;
+            let failures = check::evaluate(&thresholds, &inputs, now_ts());
+            if failures.is_empty() {
+                println!("status=ok");
+            } else {
+                for failure in &failures {
+                    println!("{failure}");
+                }
+                return Err(Exit::silent(ExitStatus::ThresholdTripped).into());
+            }
+        }
+        Commands::Calibrate {
+            battery,
+            interval,
+            stop_at,
+            show,
+            db_path,
+            config_path,
+        } => {
+            let resolved = resolve_db_path(db_path.as_deref());
+            if show {
+                let calibrations = db::fetch_calibrations(&resolved)?;
+                if calibrations.is_empty() {
+                    println!("No calibrations stored; run `symmetri calibrate` first.");
+                }
+                for calibration in &calibrations {
+                    print_calibration(calibration);
+                }
+                return Ok(());
+            }
+            let config = load_config(config_path.as_deref())?;
+            let path = matching_batteries(battery.as_deref())?.remove(0);
+            let trace = calibrate::record_discharge(
+                &path,
+                Duration::from_secs(interval),
+                stop_at,
+                &config.quirks_for(&path),
+            )?;
+            let samples: Vec<Sample> = trace.iter().map(|point| point.sample.clone()).collect();
+            db::insert_samples(&resolved, &samples)?;
+            let calibration = Calibration::from_trace(&trace)?;
+            db::insert_calibration(&resolved, &calibration)?;
+            print_calibration(&calibration);
+            println!(
+                "Saved to {}. Plug the charger back in and let it charge to full to finish the cycle.",
+                resolved.display()
+            );
+        }
+        Commands::ChargeLimit { action, battery } => {
+            for path in matching_batteries(battery.as_deref())? {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                if let ChargeLimitAction::Set { end, start } = action {
+                    if start.is_some_and(|start| start >= end) {
+                        anyhow::bail!("--start must be below the end threshold");
+                    }
+                    write_charge_thresholds(&path, end, start)?;
+                }
+                match read_charge_thresholds(&path) {
+                    Some(thresholds) => println!(
+                        "{name}: start={} end={}",
+                        thresholds
+                            .start_pct
+                            .map(|v| format!("{v}%"))
+                            .unwrap_or_else(|| "--".to_string()),
+                        thresholds
+                            .end_pct
+                            .map(|v| format!("{v}%"))
+                            .unwrap_or_else(|| "--".to_string())
+                    ),
+                    None => println!("{name}: no charge threshold support"),
+                }
+            }
+        }
+        Commands::Init {
+            db_path,
+            config_path,
+            yes,
+            systemd,
+        } => run_init(db_path, config_path, yes, systemd)?,
+        Commands::Config {
+            action: ConfigAction::Show,
+            db_path,
+            config_path,
+        } => print_config_show(db_path.as_deref(), config_path.as_deref()),
+        Commands::Completions { shell } => {
+            completions::write_completions(shell, &mut Cli::command(), &mut std::io::stdout())?;
+        }
+        Commands::Man { dir } => match dir {
+            Some(dir) => {
+                for page in completions::write_man_pages(Cli::command(), &dir)? {
+                    println!("{}", page.display());
+                }
+            }
+            None => completions::write_man_page(Cli::command(), &mut std::io::stdout())?,
+        },
+        Commands::Sources { config_path, json } => {
+            let config = load_config(config_path.as_deref())?;
+            let found = sources::discover(None, &config);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&found)?);
+            } else {
+                print_sources(&found);
+            }
+        }
+        Commands::Doctor {
+            db_path,
+            config_path,
+        } => run_doctor(db_path.as_deref(), config_path.as_deref())?,
+        Commands::NixModule {
+            home_manager,
+            db_path,
+            config_path,
+            interval,
+        } => print_nix_module(
+            home_manager,
+            db_path.as_deref(),
+            config_path.as_deref(),
+            interval,
+        )?,
+        Commands::Db { action, db_path } => {
+            let resolved = resolve_db_path(db_path.as_deref());
+            match action {
+                DbAction::Stats => print_db_stats(&resolved, &db::db_stats(&resolved)?),
+                DbAction::Vacuum => {
+                    let (before, after) = db::vacuum(&resolved)?;
+                    println!(
+                        "Vacuumed {}: {} -> {}",
+                        resolved.display(),
+                        format_bytes(before as f64),
+                        format_bytes(after as f64)
+                    );
+                }
+                DbAction::Dedupe => {
+                    let (samples, metrics) = db::dedupe(&resolved)?;
+                    println!(
+                        "Removed {samples} duplicate battery samples and {metrics} duplicate metric samples from {}",
+                        resolved.display()
+                    );
+                }
+                DbAction::Backup { path, gzip } => {
+                    let now = Utc::now().timestamp() as f64;
+                    let written = backup::backup(&resolved, &path, gzip, now, &formats)?;
+                    let size = std::fs::metadata(&written)?.len();
+                    println!(
+                        "Backed up {} to {} ({})",
+                        resolved.display(),
+                        written.display(),
+                        format_bytes(size as f64)
+                    );
+                }
+                DbAction::Restore { path, force } => {
+                    if resolved.exists() && !force {
+                        let rows = db::db_stats(&resolved)?.sample_rows();
+                        if rows > 0 {
+                            anyhow::bail!(
+                                "{} already holds {rows} samples; pass --force to replace them",
+                                resolved.display()
+                            );
+                        }
+                    }
+                    backup::restore(&path, &resolved)?;
+                    println!(
+                        "Restored {} from {} ({} samples)",
+                        resolved.display(),
+                        path.display(),
+                        db::db_stats(&resolved)?.sample_rows()
+                    );
+                }
+                DbAction::Compress { undo } => {
+                    let mut bar = None;
+                    let rewrite = db::set_details_compression_with_progress(
+                        &resolved,
+                        !undo,
+                        |done, total| {
+                            bar.get_or_insert_with(|| progress::rows("Rewriting", total))
+                                .set_position(done);
+                        },
+                    )?;
+                    if let Some(bar) = bar {
+                        bar.finish_and_clear();
+                    }
+                    println!(
+                        "{} the details of {} metric samples in {}: {} -> {}",
+                        if undo { "Decompressed" } else { "Compressed" },
+                        rewrite.rows,
+                        resolved.display(),
+                        format_bytes(rewrite.bytes_before as f64),
+                        format_bytes(rewrite.bytes_after as f64)
+                    );
+                    if !undo && rewrite.rows > 0 {
+                        println!("Run `symmetri db vacuum` to shrink the file.");
+                    }
+                }
+                DbAction::Sql { sql, format } => {
+                    let (columns, rows) = db::read_only_query(&resolved, &sql)?;
+                    if rows.is_empty() && format == OutputFormat::Table {
+                        println!("No rows.");
+                    } else {
+                        print_rows(&columns, &rows, format)?;
+                    }
+                }
+                DbAction::Explain => {
+                    let plans = db::query_plans(&resolved)?;
+                    let mut table = themed_table();
+                    table.set_header(header_cells(&["Read", "Plan", "Indexed"]));
+                    for plan in &plans {
+                        table.add_row(vec![
+                            label_cell(plan.name),
+                            Cell::new(plan.steps.join("\n")),
+                            value_cell(if plan.is_indexed() { "yes" } else { "NO" }),
+                        ]);
+                    }
+                    println!("{table}");
+                    if plans.iter().any(|plan| !plan.is_indexed()) {
+                        return Err(Exit::silent(ExitStatus::ThresholdTripped).into());
+                    }
+                }
+                DbAction::Schema { json } => {
+                    let tables = db::schema()?;
+                    if json {
+                        let kinds: Vec<serde_json::Value> = MetricKind::ALL
+                            .iter()
+                            .map(|kind| {
+                                serde_json::json!({
+                                    "name": kind.as_str(),
+                                    "unit": kind.unit(),
+                                    "description": kind.description(),
+                                })
+                            })
+                            .collect();
+                        let schema = serde_json::json!({
+                            "tables": tables,
+                            "metric_kinds": kinds,
+                        });
+                        println!("{}", serde_json::to_string_pretty(&schema)?);
+                    } else {
+                        print_schema(&tables);
+                    }
+                }
+            }
+        }
+        Commands::Dev { action, db_path } => {
+            let resolved = resolve_db_path(db_path.as_deref());
+            match action {
+                DevAction::Generate {
+                    days,
+                    profile,
+                    interval,
+                    seed,
+                    force,
+                } => {
+                    let mut conn = db::init_db_connection(&resolved)?;
+                    if !force && db::count_samples(&resolved, None)? > 0 {
+                        anyhow::bail!(
+                            "{} already has battery samples; pass --force to add synthetic ones or use --db with a new file",
+                            resolved.display()
+                        );
+                    }
+                    let data = synthetic::generate(
+                        profile,
+                        days,
+                        Utc::now().timestamp() as f64,
+                        interval as f64,
+                        seed,
+                        &locale::current(),
+                    );
+                    db::insert_all_samples(&mut conn, &data.samples, &data.metrics)?;
+                    for sleep in &data.sleeps {
+                        db::insert_sleep_interval(&conn, sleep)?;
+                    }
+                    println!(
+                        "Wrote {} battery samples, {} metric samples and {} suspends ({} days, {}) to {}",
+                        data.samples.len(),
+                        data.metrics.len(),
+                        data.sleeps.len(),
+                        days,
+                        profile.to_possible_value().map(|v| v.get_name().to_string()).unwrap_or_default(),
+                        resolved.display()
+                    );
+                }
+                DevAction::Bench { runs } => {
+                    db::init_db(&resolved)?;
+                    print_bench(&resolved, &bench::run(&resolved, runs as usize)?);
+                }
+            }
+        }
+        Commands::Export {
+            influx: _,
             hours,
             days,
             months,
             all_time,
+            calendar,
             db_path,
-            graph: graph_flag,
-            graph_path,
-            presets,
-            sensor_filters,
-            verbose,
+            output,
         } => {
-            configure_logging(verbose);
-            let timeframe = build_timeframe(hours as i64, days as i64, months as i64, all_time)?;
+            let timeframe = build_timeframe(
+                hours as i64,
+                days as i64,
+                months as i64,
+                all_time,
+                calendar.period(),
+            )?;
             let resolved = resolve_db_path(db_path.as_deref());
-            let presets = normalize_presets(presets);
-            let metric_kinds = metric_kinds_for_presets(&presets);
-
-            let battery_total = db::count_samples(&resolved, None)?;
-            let metric_total = db::count_metric_samples(&resolved, None)?;
-            if battery_total == 0 && metric_total == 0 {
-                println!("No records available; collect data first.");
-                std::process::exit(1);
+            let since = timeframe.since_timestamp(None);
+            let until = timeframe.until_timestamp();
+            let mut out: Box<dyn std::io::Write> = match &output {
+                Some(path) => Box::new(std::io::BufWriter::new(
+                    std::fs::File::create(path)
+                        .with_context(|| format!("Cannot write {}", path.display()))?,
+                )),
+                None => Box::new(std::io::stdout().lock()),
+            };
+            // Written a page at a time, so a year-long export never sits in memory whole.
+            let mut points = 0;
+            let mut write_body = |body: String| -> Result<()> {
+                points += body.lines().count();
+                out.write_all(body.as_bytes())?;
+                Ok(())
+            };
+            let mut samples = db::sample_cursor(&resolved, since, until)?;
+            let mut metrics = db::metric_cursor(&resolved, since, until, None)?;
+            let bar = progress::rows(
+                "Exporting",
+                (samples.estimated_rows()? + metrics.estimated_rows()?) as u64,
+            );
+            let mut stream = || -> Result<()> {
+                while let Some(page) = samples.next_page()? {
+                    bar.inc(page.len() as u64);
+                    write_body(influx::to_line_protocol(&page, &[]))?;
+                }
+                while let Some(page) = metrics.next_page()? {
+                    bar.inc(page.len() as u64);
+                    write_body(influx::to_line_protocol(&[], &page))?;
+                }
+                Ok(())
+            };
+            let written = stream().and_then(|()| Ok(out.flush()?));
+            bar.finish_and_clear();
+            if let Err(err) = written {
+                // `export | head` closing the pipe early is not an error.
+                let broken_pipe = err
+                    .downcast_ref::<std::io::Error>()
+                    .is_some_and(|err| err.kind() == std::io::ErrorKind::BrokenPipe);
+                if output.is_some() || !broken_pipe {
+                    return Err(err);
+                }
             }
-
-            let since_ts = timeframe.since_timestamp(None);
-            let raw_samples =
-                if presets.contains(&ReportPreset::Battery) || graph_flag || graph_path.is_some() {
-                    db::fetch_samples(&resolved, since_ts)?
-                } else {
-                    Vec::new()
-                };
-            let metric_samples =
-                db::fetch_metric_samples(&resolved, since_ts, Some(&metric_kinds))?;
-            let metric_samples = filter_metrics_by_source(&metric_samples, &sensor_filters);
-            let timeframe_record_count = raw_samples.len();
-            let samples = aggregate_samples_by_timestamp(&raw_samples);
-            let has_selected_data = presets
+            if let Some(path) = &output {
+                println!("Wrote {points} points to {}", path.display());
+            }
+        }
+        Commands::CompareHosts {
+            db_a,
+            db_b,
+            label_a,
+            label_b,
+            hours,
+            days,
+            months,
+            all_time,
+            config_path,
+            json,
+        } => {
+            let config = load_config(config_path.as_deref())?;
+            let timeframe =
+                build_timeframe(hours as i64, days as i64, months as i64, all_time, None)?;
+            let label = |label: Option<String>, path: &Path| {
+                label.unwrap_or_else(|| {
+                    path.file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| path.display().to_string())
+                })
+            };
+            let a = host_summary(&db_a, label(label_a, &db_a), timeframe.seconds, &config)?;
+            let b = host_summary(&db_b, label(label_b, &db_b), timeframe.seconds, &config)?;
+            let window = timeframe.label.replace('_', " ");
+            let mut sections: Vec<ReportSection> = [&a, &b]
                 .iter()
-                .any(|preset| has_data_for_preset(*preset, &samples, &metric_samples));
-            if !has_selected_data {
+                .map(|host| {
+                    ReportSection::note(format!(
+                        "{}: {}, {} up to {}",
+                        host.label,
+                        host.path.display(),
+                        window,
+                        format_timestamp(host.end_ts)
+                    ))
+                })
+                .collect();
+            sections.push(ReportSection::table(
+                format!("Battery summary ({window})"),
+                battery_comparison_table(
+                    &a.battery,
+                    &b.battery,
+                    [&a.label, &b.label],
+                    &config.energy,
+                ),
+            ));
+            sections.push(ReportSection::table(
+                format!("System ({window})"),
+                host_system_table(&a, &b),
+            ));
+            if json {
+                let title = format!("symmetri host comparison ({window})");
+                let generated_at = formats.timestamp(&Utc::now().with_timezone(&formats.zone));
+                let report = report::render_json(&title, &generated_at, &sections);
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                for section in &sections {
+                    println!("{section}");
+                }
+            }
+        }
+        Commands::Import {
+            format,
+            files,
+            source,
+            db_path,
+        } => {
+            let resolved = resolve_db_path(db_path.as_deref());
+            db::init_db(&resolved)?;
+            for file in files {
+                let content = std::fs::read_to_string(&file)
+                    .with_context(|| format!("Cannot read {}", file.display()))?;
+                let source = source
+                    .clone()
+                    .unwrap_or_else(|| default_import_source(format, &file));
+                let samples = parse_import(format, &content, &source)
+                    .with_context(|| format!("Cannot import {}", file.display()))?;
+                db::insert_samples(&resolved, &samples)?;
+                let first = samples.iter().map(|s| s.ts).reduce(f64::min);
+                let last = samples.iter().map(|s| s.ts).reduce(f64::max);
+                match first.zip(last) {
+                    Some((first, last)) => println!(
+                        "Imported {} samples ({} -> {}) from {}",
+                        samples.len(),
+                        format_timestamp(first),
+                        format_timestamp(last),
+                        file.display()
+                    ),
+                    None => println!("No samples found in {}", file.display()),
+                }
+            }
+        }
+        Commands::Tune {
+            audit: _,
+            apply,
+            db_path,
+        } => {
+            let tunables = tunables::audit_tunables();
+            if tunables.is_empty() {
+                println!("No known power tunables found on this machine.");
+                return Ok(());
+            }
+            if !apply {
+                println!("\nPower tunables\n{}", tunables_table(&tunables));
+                let untuned = tunables.iter().filter(|t| !t.is_tuned()).count();
                 println!(
-                    "No records for the selected presets in {}; try a broader timeframe or enable those collectors.",
-                    timeframe.label.replace('_', " ")
+                    "{untuned} of {} not at the recommended value; run `symmetri tune --apply` as root to set them.",
+                    tunables.len()
                 );
-                std::process::exit(1);
+                return Ok(());
             }
-
-            let output_path = match (graph_path, graph_flag) {
-                (Some(path), _) => Some(path),
-                (None, true) => Some(default_graph_path(
-                    &timeframe.label,
-                    None,
-                    Some(Local::now()),
-                )),
-                _ => None,
-            };
-
-            if let Some(path) = output_path {
-                if samples.is_empty() && metric_samples.is_empty() {
-                    println!("Skipping graph output; no data in timeframe.");
-                } else {
-                    let battery_for_plot: &[Sample] = if presets.contains(&ReportPreset::Battery) {
-                        &samples
-                    } else {
-                        &[]
-                    };
-                    graph::render_plot(
-                        battery_for_plot,
-                        &metric_samples,
-                        &presets,
-                        &timeframe,
-                        &path,
-                    )?;
+            let mut applied = Vec::new();
+            for tunable in tunables.iter().filter(|t| !t.is_tuned()) {
+                match tunables::apply_tunable(tunable) {
+                    Ok(()) => {
+                        println!(
+                            "{}: {} -> {}",
+                            tunable.name, tunable.current, tunable.recommended
+                        );
+                        applied.push(format!("{}={}", tunable.name, tunable.recommended));
+                    }
+                    Err(err) => eprintln!("{}: {err:#}", tunable.name),
                 }
             }
-
-            summarize(
-                &samples,
-                &timeframe,
-                timeframe_record_count,
-                &metric_samples,
-                &presets,
-            );
+            if applied.is_empty() {
+                println!("Nothing changed.");
+                return Ok(());
+            }
+            let resolved = resolve_db_path(db_path.as_deref());
+            let ts = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            db::insert_annotation(
+                &resolved,
+                &Annotation {
+                    ts,
+                    text: format!("Tuned: {}", applied.join(", ")),
+                },
+            )?;
+            println!("Applied {} tunables.", applied.len());
         }
-    }
-    Ok(())
-}
-
-fn summarize(
-    timeframe_samples: &[Sample],
-    timeframe: &Timeframe,
-    timeframe_records: usize,
-    metrics: &[MetricSample],
-    presets: &[ReportPreset],
-) {
-    let timeframe_label = timeframe.label.replace('_', " ");
-    let bucket_seconds =
-        bucket_span_seconds(timeframe, data_span_seconds(timeframe_samples, metrics));
-    let battery_rates = average_rates(timeframe_samples);
-    let power_draw_stats = average_for_kind(metrics, MetricKind::PowerDraw);
-    let avg_discharge_w = power_draw_stats.average().or(battery_rates.discharge_w);
-    let est_runtime_hours = timeframe_samples
-        .last()
-        .and_then(|sample| estimate_runtime_hours(avg_discharge_w, sample));
-    let power_draw_by_bucket =
-        bucket_stats_for_kind(metrics, MetricKind::PowerDraw, bucket_seconds);
-    let network_rates = compute_network_rates(metrics);
-
-    if presets.contains(&ReportPreset::Battery) {
-        println!(
-            "\nBattery summary ({})\n{}",
-            timeframe_label,
-            battery_summary_table(
-                timeframe_records,
-                avg_discharge_w,
-                battery_rates.charge_w,
-                est_runtime_hours
-            )
-        );
-
-        if timeframe_samples.is_empty() {
-            println!("\nNo battery samples available for buckets in {timeframe_label}.");
-        } else {
+        Commands::Annotate { text, db_path } => {
+            let text = text.trim();
+            if text.is_empty() {
+                anyhow::bail!("annotation text must not be empty");
+            }
+            let resolved = resolve_db_path(db_path.as_deref());
+            let ts = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            db::insert_annotation(
+                &resolved,
+                &Annotation {
+                    ts,
+                    text: text.to_string(),
+                },
+            )?;
+            println!("Added annotation at {}", format_timestamp(ts));
+        }
+        Commands::SleepReport {
+            hours,
+            days,
+            months,
+            all_time,
+            calendar,
+            db_path,
+        } => {
+            let timeframe = build_timeframe(
+                hours as i64,
+                days as i64,
+                months as i64,
+                all_time,
+                calendar.period(),
+            )?;
+            let resolved = resolve_db_path(db_path.as_deref());
+            let sleeps = db::fetch_sleep_intervals(
+                &resolved,
+                timeframe.since_timestamp(None),
+                timeframe.until_timestamp(),
+            )?;
+            let label = timeframe.label.replace('_', " ");
+            if sleeps.is_empty() {
+                println!("No suspend periods recorded in {label}.");
+                return Ok(());
+            }
             println!(
-                "\nBattery stats ({})\n{}",
-                timeframe.label.replace('_', " "),
-                battery_stats_table(timeframe_samples, &power_draw_by_bucket, bucket_seconds)
+                "\nSuspend periods ({label})\n{}",
+                sleep_report_table(&sleeps)
             );
+            println!("{}", sleep_report_footer(&sleeps));
         }
-    }
-
-    if presets.contains(&ReportPreset::Cpu) {
-        let usage_buckets =
-            bucket_stats_for_kind_by_source(metrics, MetricKind::CpuUsage, bucket_seconds);
-        let freq_buckets =
-            bucket_stats_for_kind_by_source(metrics, MetricKind::CpuFrequency, bucket_seconds);
-        if usage_buckets.is_empty() && freq_buckets.is_empty() {
-            println!("\nNo CPU samples available for {timeframe_label}.");
-        } else {
+        Commands::Thermals {
+            hours,
+            days,
+            months,
+            all_time,
+            calendar,
+            db_path,
+            sensor_filters,
+            config_path,
+            min_temp,
+            freq_drop,
+        } => {
+            let config = load_config(config_path.as_deref())?;
+            let timeframe = build_timeframe(
+                hours as i64,
+                days as i64,
+                months as i64,
+                all_time,
+                calendar.period(),
+            )?;
+            let resolved = resolve_db_path(db_path.as_deref());
+            let mut metrics = db::fetch_metric_samples(
+                &resolved,
+                timeframe.since_timestamp(None),
+                Some(&[MetricKind::Temperature, MetricKind::CpuFrequency]),
+            )?;
+            sensors::apply_renames(&mut metrics, &config.sensors);
+            metrics.retain(|m| {
+                timeframe.is_before_end(m.ts)
+                    && (m.kind != MetricKind::Temperature
+                        || sensor_filters.is_empty()
+                        || sensor_filters.iter().any(|f| sensor_matches(f, &m.source)))
+            });
+            let label = timeframe.label.replace('_', " ");
+            let has = |kind: MetricKind| metrics.iter().any(|m| m.kind == kind);
+            if !has(MetricKind::Temperature) || !has(MetricKind::CpuFrequency) {
+                println!(
+                    "Throttling detection needs temperature and CPU frequency samples; none in {label}."
+                );
+                return Ok(());
+            }
+            let criteria = ThrottleCriteria {
+                min_temp_c: min_temp,
+                freq_drop_pct: freq_drop,
+            };
+            let episodes = thermal::detect_throttling(&metrics, &criteria);
+            if episodes.is_empty() {
+                println!(
+                    "No throttling in {label} (no samples at >= {min_temp:.0}°C with CPU frequency {freq_drop:.0}% below its peak)."
+                );
+                return Ok(());
+            }
             println!(
-                "\nCPU stats ({})\n{}",
-                timeframe.label.replace('_', " "),
-                cpu_stats_table(bucket_seconds, &usage_buckets, &freq_buckets)
+                "\nThrottling episodes ({label})\n{}",
+                thermals_table(&episodes)
             );
-        }
-    }
-
-    if presets.contains(&ReportPreset::Gpu) {
-        let usage_buckets =
-            bucket_stats_for_kind_by_source(metrics, MetricKind::GpuUsage, bucket_seconds);
-        let freq_buckets =
-            bucket_stats_for_kind_by_source(metrics, MetricKind::GpuFrequency, bucket_seconds);
-        if usage_buckets.is_empty() && freq_buckets.is_empty() {
-            println!("\nNo GPU samples available for {timeframe_label}.");
-        } else {
+            let throttled_hours =
+                episodes.iter().map(|e| e.duration_seconds()).sum::<f64>() / 3600.0;
             println!(
-                "\nGPU stats ({})\n{}",
-                timeframe.label.replace('_', " "),
-                gpu_stats_table(bucket_seconds, &usage_buckets, &freq_buckets)
+                "{} episodes, {} throttled in total (usual peak {:.0} MHz)",
+                episodes.len(),
+                format_runtime(Some(throttled_hours)),
+                episodes[0].reference_mhz
             );
         }
-    }
-
-    if presets.contains(&ReportPreset::Memory) {
-        let memory_buckets = bucket_usage_stats(metrics, MetricKind::MemoryUsage, bucket_seconds);
-        if memory_buckets.is_empty() {
-            println!("\nNo memory samples available for {timeframe_label}.");
-        } else {
+        Commands::Health {
+            notify,
+            db_path,
+            config_path,
+        } => {
+            let config = load_config(config_path.as_deref())?;
+            let resolved = resolve_db_path(db_path.as_deref());
+            let batteries = db::fetch_battery_info(&resolved)?;
+            if batteries.is_empty() {
+                return Err(Exit::new(
+                    ExitStatus::NoData,
+                    "No battery metadata stored yet; it is recorded on the next collection.",
+                )
+                .into());
+            }
+            // Each slot's newest battery; wear is fitted from when it was first seen, so a
+            // replacement starts a fresh trend.
+            let mut trends = Vec::new();
+            for (index, (first_seen, info)) in batteries.iter().enumerate() {
+                let replaced = batteries
+                    .get(index + 1)
+                    .is_some_and(|(_, next)| next.source_path == info.source_path);
+                if !replaced {
+                    let daily = db::fetch_daily_health(&resolved, &info.source_path, *first_seen)?;
+                    trends.push((info.source_path.clone(), WearTrend::fit(&daily)));
+                }
+            }
+            if notify {
+                return notify_wear(&resolved, &trends, &config.health);
+            }
+            let latest = db::fetch_latest_event(&resolved)?;
+            println!("\nBatteries\n{}", battery_info_table(&batteries, &latest));
+            for pair in batteries.windows(2) {
+                let [(_, before), (ts, after)] = pair else {
+                    continue;
+                };
+                if before.source_path == after.source_path {
+                    println!(
+                        "{} changed on {}: {}",
+                        battery_name(&after.source_path),
+                        format_timestamp(*ts),
+                        battery_label(after)
+                    );
+                }
+            }
+            println!("\nWear\n{}", wear_table(&trends));
+        }
+        Commands::Summary {
+            notify,
+            webhooks,
+            db_path,
+            config_path,
+        } => {
+            let config = load_config(config_path.as_deref())?;
+            let resolved = resolve_db_path(db_path.as_deref());
+            let today = Utc::now().with_timezone(&formats.zone).date_naive();
+            let since = today
+                .checked_sub_days(chrono::Days::new(daily::TRAILING_DAYS))
+                .and_then(|first| first.and_hms_opt(0, 0, 0))
+                .and_then(|midnight| formats.zone.from_local_datetime(&midnight).earliest())
+                .map(|start| start.timestamp() as f64);
+            let mut samples = aggregate_samples_by_timestamp(&db::fetch_samples(&resolved, since)?);
+            smooth_percentages(&mut samples, &config.smoothing);
+            reject_power_outliers(&mut samples, &mut Vec::new(), &config.power);
+            let sleeps = db::fetch_sleep_intervals(&resolved, since, None)?;
+            let comparison = DailyComparison::compute(&samples, &sleeps, today, &formats);
+            if notify || !webhooks.is_empty() {
+                let (title, body) = comparison.notification(&formats);
+                if notify {
+                    daily::send_notification(&title, &body)?;
+                }
+                let event = daily::summary_event(&title, &body);
+                for target in &webhooks {
+                    let endpoint =
+                        webhook::resolve(target, &config.webhooks).with_context(|| {
+                            format!("{target:?} is neither a URL nor a [webhooks] entry")
+                        })?;
+                    webhook::send(&endpoint, &event)?;
+                }
+            } else {
+                println!(
+                    "Battery today ({})\n{}",
+                    formats.day(&today),
+                    daily_summary_table(&comparison)
+                );
+            }
+        }
+        Commands::Query {
+            query,
+            format,
+            db_path,
+        } => {
+            let query: Query = query.join(" ").parse()?;
+            let resolved = resolve_db_path(db_path.as_deref());
+            let now = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            let result = query.run(&resolved, now, &formats)?;
+            if result.rows.is_empty() && format == OutputFormat::Table {
+                return Err(Exit::new(ExitStatus::NoData, "No samples match the query.").into());
+            }
+            print_rows(&result.columns, &result.rows, format)?;
+            if result.rows.is_empty() {
+                return Err(Exit::silent(ExitStatus::NoData).into());
+            }
+        }
+        Commands::Analyze {
+            action:
+                Some(AnalyzeAction::Correlate {
+                    with,
+                    max_lag,
+                    hours,
+                    days,
+                    months,
+                    all_time,
+                    calendar,
+                    db_path,
+                }),
+            ..
+        } => {
+            let timeframe = build_timeframe(
+                hours as i64,
+                days as i64,
+                months as i64,
+                all_time,
+                calendar.period(),
+            )?;
+            let resolved = resolve_db_path(db_path.as_deref());
+            let (since_ts, until_ts) =
+                (timeframe.since_timestamp(None), timeframe.until_timestamp());
+            let mut samples = db::fetch_samples(&resolved, since_ts)?;
+            samples.retain(|s| timeframe.is_before_end(s.ts));
+            let mut metrics = db::fetch_metric_samples(&resolved, since_ts, None)?;
+            metrics.retain(|m| timeframe.is_before_end(m.ts));
+            let sleeps = db::fetch_sleep_intervals(&resolved, since_ts, until_ts)?;
+            let label = timeframe.label.replace('_', " ");
+            let segments = discharge_segments(&samples, &sleeps);
+            if segments.len() < correlate::MIN_PAIRS {
+                return Err(Exit::new(
+                    ExitStatus::NoData,
+                    format!(
+                        "Correlation needs at least {} discharge readings; {} in {label}.",
+                        correlate::MIN_PAIRS,
+                        segments.len()
+                    ),
+                )
+                .into());
+            }
+            let ranked = correlate::correlate(&segments, &metrics, &with, max_lag, 60)
+                .map_err(anyhow::Error::msg)?;
+            if ranked.is_empty() {
+                println!(
+                    "None of {} varied alongside the {} discharge readings in {label}.",
+                    with.join(", "),
+                    segments.len()
+                );
+                return Ok(());
+            }
             println!(
-                "\nMemory stats ({})\n{}",
-                timeframe.label.replace('_', " "),
-                memory_stats_table(bucket_seconds, &memory_buckets)
+                "\nLikely drain drivers ({label}, {} discharge readings)\n{}",
+                segments.len(),
+                correlations_table(&ranked)
             );
-        }
-    }
-
-    if presets.contains(&ReportPreset::Disk) {
-        let disk_buckets = bucket_usage_stats(metrics, MetricKind::DiskUsage, bucket_seconds);
-        if disk_buckets.is_empty() {
-            println!("\nNo disk samples available for {timeframe_label}.");
-        } else {
             println!(
-                "\nDisk stats ({})\n{}",
-                timeframe.label.replace('_', " "),
-                disk_stats_table(bucket_seconds, &disk_buckets)
+                "r runs from -1 to 1; a positive lag means the metric moves before the drain, a negative one after it."
             );
         }
-    }
-
-    if presets.contains(&ReportPreset::Network) {
-        let network_buckets = bucket_network_rates(&network_rates, bucket_seconds);
-        if network_buckets.is_empty() {
-            println!("\nNo network samples available for {timeframe_label}.");
-        } else {
+        Commands::Analyze {
+            action:
+                Some(AnalyzeAction::Regressions {
+                    window,
+                    alpha,
+                    db_path,
+                }),
+            ..
+        } => {
+            let resolved = resolve_db_path(db_path.as_deref());
+            let changes = regression::changes(&db::fetch_system_versions(&resolved, None)?);
+            let Some(first) = changes.first() else {
+                return Err(Exit::new(
+                    ExitStatus::NoData,
+                    "No kernel, OS or symmetri updates recorded yet; the collector notes them as they happen.",
+                )
+                .into());
+            };
+            let since_ts = Some(first.ts - window as f64);
+            let samples = db::fetch_samples(&resolved, since_ts)?;
+            let sleeps = db::fetch_sleep_intervals(&resolved, since_ts, None)?;
+            let segments = discharge_segments(&samples, &sleeps);
+            let compared = regression::compare(&changes, &segments, window as f64);
             println!(
-                "\nNetwork stats ({})\n{}",
-                timeframe.label.replace('_', " "),
-                network_stats_table(bucket_seconds, &network_buckets)
+                "\nDrain around updates ({} either side)\n{}",
+                format_span(window),
+                regressions_table(&compared, alpha)
             );
+            let regressions: Vec<_> = compared
+                .iter()
+                .filter(|comparison| comparison.is_regression(alpha))
+                .collect();
+            if regressions.is_empty() {
+                println!(
+                    "No significant drain regression across {} update(s) (alpha {alpha}).",
+                    compared.len()
+                );
+            }
+            for comparison in regressions {
+                println!("{}", comparison.summary());
+            }
         }
-    }
-
-    if presets.contains(&ReportPreset::Temperature) {
-        let temp_buckets =
-            bucket_stats_for_kind_by_source(metrics, MetricKind::Temperature, bucket_seconds);
-        if temp_buckets.is_empty() {
-            println!("\nNo temperature samples available for {timeframe_label}.");
-        } else {
+        Commands::Analyze {
+            action: None,
+            hours,
+            days,
+            months,
+            all_time,
+            calendar,
+            db_path,
+            top,
+        } => {
+            let timeframe = build_timeframe(
+                hours as i64,
+                days as i64,
+                months as i64,
+                all_time,
+                calendar.period(),
+            )?;
+            let resolved = resolve_db_path(db_path.as_deref());
+            let mut metrics = db::fetch_metric_samples(
+                &resolved,
+                timeframe.since_timestamp(None),
+                Some(&[MetricKind::Interrupts]),
+            )?;
+            metrics.retain(|m| timeframe.is_before_end(m.ts));
+            let label = timeframe.label.replace('_', " ");
+            let (activity, storms) = interrupts::analyze_interrupts(&metrics);
+            if activity.is_empty() {
+                println!(
+                    "Wake-up analysis needs at least two interrupt samples per source; none in {label}."
+                );
+                return Ok(());
+            }
             println!(
-                "\nTemperature stats ({})\n{}",
-                timeframe.label.replace('_', " "),
-                temperature_stats_table(bucket_seconds, &temp_buckets)
+                "\nTop wake-up sources ({label})\n{}",
+                wakeup_sources_table(&activity[..top.min(activity.len())])
             );
+            if storms.is_empty() {
+                println!(
+                    "No interrupt storms in {label} (no interval above {:.0}/s and 10x the source's usual rate).",
+                    interrupts::STORM_MIN_RATE
+                );
+            } else {
+                println!("\nInterrupt storms ({label})\n{}", storms_table(&storms));
+            }
         }
-    }
-}
+        Commands::Baseline { action } => match action {
+            BaselineAction::Save {
+                name,
+                last,
+                db_path,
+                config_path,
+            } => {
+                let config = load_config(config_path.as_deref())?;
+                let resolved = resolve_db_path(db_path.as_deref());
+                let ts = SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs_f64();
+                let start_ts = ts - last as f64;
+                let metrics = baseline_profile(&resolved, Some(start_ts), Some(ts), &config)?;
+                if metrics.is_empty() {
+                    return Err(Exit::new(
+                        ExitStatus::NoData,
+                        format!(
+                            "Nothing recorded in the last {}; keep the collector running through the period first.",
+                            format_span(last)
+                        ),
+                    )
+                    .into());
+                }
+                let saved = Baseline {
+                    name,
+                    ts,
+                    start_ts,
+                    end_ts: ts,
+                    metrics,
+                };
+                db::insert_baseline(&resolved, &saved)?;
+                println!("{}", baseline_table(&saved, None));
+                println!(
+                    "Saved baseline {} ({} metrics over the last {}).",
+                    saved.name,
+                    saved.metrics.len(),
+                    format_span(last)
+                );
+            }
+            BaselineAction::Compare {
+                name,
+                hours,
+                days,
+                months,
+                all_time,
+                calendar,
+                db_path,
+                config_path,
+            } => {
+                let config = load_config(config_path.as_deref())?;
+                let resolved = resolve_db_path(db_path.as_deref());
+                let saved = saved_baseline(&resolved, name.as_deref())?;
+                let timeframe = build_timeframe(
+                    hours as i64,
+                    days as i64,
+                    months as i64,
+                    all_time,
+                    calendar.period(),
+                )?;
+                let label = timeframe.label.replace('_', " ");
+                let current = baseline_profile(
+                    &resolved,
+                    timeframe.since_timestamp(None),
+                    timeframe.until_timestamp(),
+                    &config,
+                )?;
+                let deviations = baseline::deviations(&saved, &current);
+                if deviations.is_empty() {
+                    return Err(Exit::new(
+                        ExitStatus::NoData,
+                        format!(
+                            "None of baseline {}'s metrics were recorded in {label}.",
+                            saved.name
+                        ),
+                    )
+                    .into());
+                }
+                println!(
+                    "\nBaseline {} vs {label}\n{}",
+                    saved.name,
+                    baseline_table(&saved, Some(&deviations))
+                );
+                println!("{}", baseline::describe(&saved, &deviations));
+            }
+            BaselineAction::List { db_path } => {
+                let baselines = db::fetch_baselines(&resolve_db_path(db_path.as_deref()))?;
+                if baselines.is_empty() {
+                    println!("No baselines saved; run `symmetri baseline save NAME` after a controlled period.");
+                    return Ok(());
+                }
+                let mut table = themed_table();
+                table.set_header(header_cells(&[
+                    "Name", "Saved", "Period", "Metrics", "Drain",
+                ]));
+                for saved in &baselines {
+                    table.add_row(vec![
+                        label_cell(&saved.name),
+                        Cell::new(format_timestamp(saved.ts)),
+                        value_cell(format_span((saved.end_ts - saved.start_ts) as i64)),
+                        value_cell(saved.metrics.len().to_string()),
+                        value_cell(format_power(
+                            saved.metrics.get(baseline::DISCHARGE).map(|stat| stat.mean),
+                        )),
+                    ]);
+                }
+                println!("{table}");
+            }
+            BaselineAction::Delete { name, db_path } => {
+                if !db::delete_baseline(&resolve_db_path(db_path.as_deref()), &name)? {
+                    anyhow::bail!("No baseline named {name}; see `symmetri baseline list`");
+                }
+                println!("Deleted baseline {name}.");
+            }
+        },
+        Commands::Predict {
+            workload,
+            days,
+            db_path,
+            config_path,
+        } => {
+            let plan = predict::parse_plan(&workload).map_err(anyhow::Error::msg)?;
+            let config = load_config(config_path.as_deref())?;
+            let resolved = resolve_db_path(db_path.as_deref());
+            let now = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            let since_ts = Some(now - days as f64 * 86400.0);
+            let mut samples =
+                aggregate_samples_by_timestamp(&db::fetch_samples(&resolved, since_ts)?);
+            let Some(latest) = samples.last().cloned() else {
+                return Err(Exit::new(
+                    ExitStatus::NoData,
+                    format!("No battery readings in the last {days} days; collect data first."),
+                )
+                .into());
+            };
+            let calibration = db::fetch_calibration(&resolved, &latest.source_path)?;
+            let Some((start_pct, capacity_wh)) = runtime::charge_now(&latest, calibration.as_ref())
+            else {
+                anyhow::bail!(
+                    "The latest battery reading ({}) has no charge level or capacity",
+                    format_timestamp(latest.ts)
+                );
+            };
+            let mut metrics =
+                db::fetch_metric_samples(&resolved, since_ts, Some(&workload::kinds()))?;
+            reject_power_outliers(&mut samples, &mut metrics, &config.power);
+            let sleeps = db::fetch_sleep_intervals(&resolved, since_ts, None)?;
+            let labels = workload::label_windows(&metrics, workload::WINDOW_SECONDS);
+            let rates = workload::drain_rates(&labels, &discharge_segments(&samples, &sleeps));
+            let prediction = predict::predict(start_pct, capacity_wh, &plan, &rates)
+                .map_err(|missing| {
+                    let learned: Vec<String> = rates
+                        .iter()
+                        .map(|(workload, rate)| format!("{workload} {:.1}W", rate.watts))
+                        .collect();
+                    Exit::new(
+                        ExitStatus::NoData,
+                        format!(
+                            "No {missing} on battery in the last {days} days to learn its drain from (learned: {}).",
+                            if learned.is_empty() {
+                                "nothing".to_string()
+                            } else {
+                                learned.join(", ")
+                            }
+                        ),
+                    )
+                })?;
+            println!(
+                "\nStarting from {start_pct:.0}% of {} (reading of {}, {})\n{}",
+                format_energy(capacity_wh),
+                format_timestamp(latest.ts),
+                latest
+                    .status
+                    .as_deref()
+                    .unwrap_or("unknown status")
+                    .to_lowercase(),
+                prediction_table(&prediction)
+            );
+            println!("{}", prediction.verdict());
+        }
+        Commands::Classify {
+            days,
+            all_time,
+            db_path,
+        } => {
+            let resolved = resolve_db_path(db_path.as_deref());
+            let now = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            let window = workload::WINDOW_SECONDS as f64;
+            // From a window boundary, so the first window is labelled from all its readings.
+            let since_ts =
+                (!all_time).then(|| ((now - days as f64 * 86400.0) / window).floor() * window);
+            let label = if all_time {
+                "all history".to_string()
+            } else {
+                format!("last {days} days")
+            };
+            let metrics = db::fetch_metric_samples(&resolved, since_ts, Some(&workload::kinds()))?;
+            let labels = workload::label_windows(&metrics, workload::WINDOW_SECONDS);
+            if labels.is_empty() {
+                return Err(Exit::new(
+                    ExitStatus::NoData,
+                    format!("No CPU usage recorded in the {label}; nothing to classify."),
+                )
+                .into());
+            }
+            db::replace_workloads(&resolved, since_ts, None, &labels)?;
+            let samples = aggregate_samples_by_timestamp(&db::fetch_samples(&resolved, since_ts)?);
+            let sleeps = db::fetch_sleep_intervals(&resolved, since_ts, None)?;
+            println!(
+                "\nWorkloads ({label})\n{}",
+                workloads_table(&workload::summarize(
+                    &labels,
+                    &discharge_segments(&samples, &sleeps)
+                ))
+            );
+            println!(
+                "Stored {} labelled windows of {}.",
+                labels.len(),
+                format_span(workload::WINDOW_SECONDS)
+            );
+        }
+        Commands::Mark { action } => {
+            let ts = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            let (resolved, stopped) = match &action {
+                MarkAction::Start { name, db_path } => {
+                    let name = name.trim();
+                    if name.is_empty() {
+                        anyhow::bail!("activity name must not be empty");
+                    }
+                    let resolved = resolve_db_path(db_path.as_deref());
+                    let stopped = db::start_activity(&resolved, name, ts)?;
+                    (resolved, stopped)
+                }
+                MarkAction::Stop { db_path } => {
+                    let resolved = resolve_db_path(db_path.as_deref());
+                    let stopped = db::stop_activity(&resolved, ts)?;
+                    (resolved, stopped)
+                }
+            };
+            match stopped {
+                Some(stopped) => {
+                    let since_ts = Some(stopped.start_ts);
+                    let samples =
+                        aggregate_samples_by_timestamp(&db::fetch_samples(&resolved, since_ts)?);
+                    let sleeps = db::fetch_sleep_intervals(&resolved, since_ts, None)?;
+                    let used = activity::usage(
+                        std::slice::from_ref(&stopped),
+                        &discharge_segments(&samples, &sleeps),
+                        stopped.start_ts,
+                        ts,
+                    );
+                    let cost = match used.first().and_then(|used| Some((used.wh, used.watts()?))) {
+                        Some((wh, watts)) => format!(
+                            "{} at {} average drain",
+                            format_energy(wh),
+                            format_power(Some(watts))
+                        ),
+                        None => "no discharge recorded".to_string(),
+                    };
+                    println!(
+                        "Stopped {} after {} ({cost}).",
+                        stopped.name,
+                        format_runtime(Some((ts - stopped.start_ts) / 3600.0))
+                    );
+                }
+                None if matches!(action, MarkAction::Stop { .. }) => {
+                    anyhow::bail!("No activity running; start one with `symmetri mark start NAME`")
+                }
+                None => {}
+            }
+            if let MarkAction::Start { name, .. } = &action {
+                println!("Started {} at {}.", name.trim(), format_timestamp(ts));
+            }
+        }
+        Commands::Snapshot {
+            around,
+            window,
+            output,
+            graph_theme,
+            db_path,
+            config_path,
+        } => {
+            let config = load_config(config_path.as_deref())?;
+            let around =
+                snapshot::parse_instant(&around, &formats.zone).map_err(anyhow::Error::msg)?;
+            let window = snapshot::Window::centered(around, window);
+            let resolved = resolve_db_path(db_path.as_deref());
+            let incident = snapshot::Incident::load(&resolved, window)?;
+            if incident.is_empty() {
+                return Err(Exit::new(
+                    ExitStatus::NoData,
+                    format!(
+                        "No records between {} and {}.",
+                        format_timestamp(window.start),
+                        format_timestamp(window.end)
+                    ),
+                )
+                .into());
+            }
+            let presets: Vec<ReportPreset> = ReportPreset::value_variants()
+                .iter()
+                .copied()
+                .filter(|preset| {
+                    *preset != ReportPreset::Internal
+                        && has_data_for_preset(*preset, &incident.samples, &incident.metrics)
+                })
+                .collect();
+            let style = GraphStyle::from_config(&config.graph, graph_theme)?.with_formats(formats);
+            let graph_png = render_graph_png(
+                &incident.samples,
+                &incident.metrics,
+                &incident.annotations,
+                &presets,
+                &window.timeframe(),
+                &style,
+            )?;
+            let files = incident.files(&formats, graph_png)?;
+            // The archive unpacks into a directory named after it.
+            let name = output
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let dir = name
+                .strip_suffix(".tar.gz")
+                .or_else(|| name.strip_suffix(".tgz"))
+                .filter(|dir| !dir.is_empty())
+                .unwrap_or("symmetri-snapshot");
+            snapshot::write_tar_gz(&output, dir, &files, around)?;
+            println!(
+                "Wrote {} battery and {} metric samples from {} to {} to {}",
+                incident.samples.len(),
+                incident.metrics.len(),
+                format_timestamp(window.start),
+                format_timestamp(window.end),
+                output.display()
+            );
+        }
+        Commands::Report {
+            hours,
+            days,
+            months,
+            all_time,
+            calendar,
+            db_path,
+            graph: graph_flag,
+            graph_path,
+            graph_theme,
+            max_points,
+            presets,
+            sensor_filters,
+            mount_filters,
+            config_path,
+            compare,
+            internal,
+            weekly,
+            output,
+            emails,
+            heatmap,
+            distribution,
+            workloads,
+            baseline,
+            bucket,
+            json,
+            watch,
+            verbose,
+        } => {
+            init_logging(&LogOptions {
+                verbose,
+                ..LogOptions::default()
+            })?;
+            let config = load_config(config_path.as_deref())?;
+            let mut graph_style =
+                GraphStyle::from_config(&config.graph, graph_theme)?.with_formats(formats);
+            if let Some(max_points) = max_points {
+                graph_style = graph_style.with_max_points(max_points);
+            }
+            let watch = watch.map(|seconds| Duration::from_secs(seconds.max(1)));
+            if watch.is_some() {
+                signals::install_handlers()?;
+            }
+            loop {
+                if watch.is_some() {
+                    print!("\x1b[2J\x1b[H");
+                }
+                // Rebuilt every round, so rolling windows and --today follow the clock.
+                let rendered = (|| -> Result<()> {
+                    let timeframe = if weekly {
+                        build_timeframe(0, 7, 0, false, None)?
+                    } else {
+                        build_timeframe(
+                            hours as i64,
+                            days as i64,
+                            months as i64,
+                            all_time,
+                            calendar.period(),
+                        )?
+                    };
+                    if compare && timeframe.seconds.is_none() {
+                        anyhow::bail!("--compare needs a bounded timeframe; drop --all");
+                    }
+                    let resolved = resolve_db_path(db_path.as_deref());
+                    let mut presets = presets.clone();
+                    if internal {
+                        presets.push(ReportPreset::Internal);
+                    }
+                    let presets = normalize_presets(presets);
+                    let mut metric_kinds = metric_kinds_for_presets(&presets);
+                    if (config.runtime.model == RuntimeModel::Load
+                        || heatmap == Some(HeatmapMetric::Cpu))
+                        && !metric_kinds.contains(&MetricKind::CpuUsage)
+                    {
+                        metric_kinds.push(MetricKind::CpuUsage);
+                    }
+                    if config.runtime.model == RuntimeModel::Load
+                        && !metric_kinds.contains(&MetricKind::Display)
+                    {
+                        metric_kinds.push(MetricKind::Display);
+                    }
 
-fn format_power(value: Option<f64>) -> String {
-    match value {
-        Some(v) => format!("{v:.2}W"),
-        None => "--".to_string(),
-    }
-}
+                    let battery_total = db::count_samples(&resolved, None)?;
+                    let metric_total = db::count_metric_samples(&resolved, None)?;
+                    if battery_total == 0 && metric_total == 0 {
+                        return Err(Exit::new(
+                            ExitStatus::NoData,
+                            "No records available; collect data first.",
+                        )
+                        .into());
+                    }
 
-fn themed_table() -> Table {
-    let mut table = Table::new();
-    table
-        .load_preset(UTF8_FULL_CONDENSED)
-        .apply_modifier(UTF8_ROUND_CORNERS)
-        .set_content_arrangement(ContentArrangement::Dynamic);
-    table
-}
+                    let now = SystemTime::now();
+                    let since_ts = timeframe.since_timestamp(Some(now));
+                    let until_ts = timeframe.until_timestamp();
+                    // Battery rows are aggregated a page at a time, so `--all` never holds the raw
+                    // table in memory.
+                    let mut timeframe_record_count = 0;
+                    let mut samples = Vec::new();
+                    let mut battery_cursor = None;
+                    let activities = db::fetch_activities(&resolved, since_ts, until_ts)?;
+                    if presets.contains(&ReportPreset::Battery)
+                        || heatmap == Some(HeatmapMetric::Power)
+                        || distribution
+                        || workloads
+                        || !activities.is_empty()
+                        || graph_flag
+                        || graph_path.is_some()
+                        || output.is_some()
+                    {
+                        battery_cursor = Some(db::sample_cursor(&resolved, since_ts, until_ts)?);
+                    }
+                    let mut metric_cursor =
+                        db::metric_cursor(&resolved, since_ts, until_ts, Some(&metric_kinds))?;
+                    let estimated = match &battery_cursor {
+                        Some(cursor) => cursor.estimated_rows()?,
+                        None => 0,
+                    } + metric_cursor.estimated_rows()?;
+                    let bar = progress::rows("Reading", estimated as u64);
+                    if let Some(cursor) = &mut battery_cursor {
+                        while let Some(page) = cursor.next_page()? {
+                            bar.inc(page.len() as u64);
+                            timeframe_record_count += page.len();
+                            samples.extend(aggregate_samples_by_timestamp(&page));
+                        }
+                    }
+                    let mut metric_samples = Vec::new();
+                    while let Some(page) = metric_cursor.next_page()? {
+                        bar.inc(page.len() as u64);
+                        metric_samples.extend(page);
+                    }
+                    bar.finish_and_clear();
+                    sensors::apply_renames(&mut metric_samples, &config.sensors);
+                    let metric_samples = filter_metrics_by_source(&metric_samples, &sensor_filters);
+                    let mut metric_samples = filter_disk_mounts(&metric_samples, &mount_filters);
+                    let mut annotations = db::fetch_annotations(&resolved, since_ts, until_ts)?;
+                    annotations.extend(versions::change_annotations(
+                        &db::fetch_system_versions(&resolved, until_ts)?,
+                        since_ts,
+                    ));
+                    annotations.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+                    let sleeps = db::fetch_sleep_intervals(&resolved, since_ts, until_ts)?;
+                    smooth_percentages(&mut samples, &config.smoothing);
+                    let rejected_power =
+                        reject_power_outliers(&mut samples, &mut metric_samples, &config.power);
+                    let has_selected_data = presets
+                        .iter()
+                        .any(|preset| has_data_for_preset(*preset, &samples, &metric_samples));
+                    if !has_selected_data {
+                        return Err(Exit::new(
+                            ExitStatus::NoData,
+                            format!(
+                                "No records for the selected presets in {}; try a broader timeframe or enable those collectors.",
+                                timeframe.label.replace('_', " ")
+                            ),
+                        )
+                        .into());
+                    }
 
-fn header_cells(labels: &[&str]) -> Vec<Cell> {
-    labels
-        .iter()
-        .map(|label| {
-            Cell::new(*label)
-                .add_attribute(Attribute::Bold)
-                .fg(Color::Cyan)
-        })
-        .collect()
-}
+                    let output_path = match (graph_path.clone(), graph_flag) {
+                        (Some(path), _) => Some(path),
+                        (None, true) => {
+                            let dir = config.graph.output_dir();
+                            if let Some(dir) = &dir {
+                                std::fs::create_dir_all(dir)
+                                    .with_context(|| format!("Cannot create {}", dir.display()))?;
+                            }
+                            Some(default_graph_path(
+                                &timeframe.label,
+                                dir.as_deref(),
+                                Some(Local::now()),
+                            ))
+                        }
+                        _ => None,
+                    };
 
-fn label_cell(text: &str) -> Cell {
-    Cell::new(text).add_attribute(Attribute::Bold)
-}
+                    let battery_for_plot: &[Sample] = if presets.contains(&ReportPreset::Battery) {
+                        &samples
+                    } else {
+                        &[]
+                    };
+                    let discharge_histogram = if distribution {
+                        let watts: Vec<f64> = discharge_segments(&samples, &sleeps)
+                            .iter()
+                            .map(|segment| segment.watts)
+                            .collect();
+                        histogram(&watts, DISTRIBUTION_BINS)
+                    } else {
+                        None
+                    };
+                    if let (Some(hist), Some(path)) = (&discharge_histogram, &output_path) {
+                        graph::render_histogram(
+                            hist,
+                            &format!(
+                                "Discharge power distribution ({})",
+                                timeframe.label.replace('_', " ")
+                            ),
+                            "Discharge power (W)",
+                            &distribution_graph_path(path),
+                            &graph_style,
+                        )?;
+                    }
+                    if let Some(path) = output_path {
+                        if samples.is_empty() && metric_samples.is_empty() {
+                            println!("Skipping graph output; no data in timeframe.");
+                        } else {
+                            graph::render_plot(
+                                battery_for_plot,
+                                &metric_samples,
+                                &annotations,
+                                &presets,
+                                &timeframe,
+                                &path,
+                                &graph_style,
+                            )?;
+                        }
+                    }
 
-fn value_cell<T: std::fmt::Display>(value: T) -> Cell {
-    Cell::new(value.to_string()).set_alignment(CellAlignment::Right)
-}
+                    let calibration = match samples.last() {
+                        Some(latest) => db::fetch_calibration(&resolved, &latest.source_path)?,
+                        None => None,
+                    };
+                    let previous_summary = match timeframe.previous_window(Some(now)) {
+                        Some((start, end)) if compare => {
+                            let previous_raw = db::fetch_samples_between(&resolved, start, end)?;
+                            let previous_metrics = db::fetch_metric_samples_between(
+                                &resolved,
+                                start,
+                                end,
+                                Some(&[MetricKind::PowerDraw, MetricKind::CpuUsage]),
+                            )?;
+                            let mut previous_metrics =
+                                filter_metrics_by_source(&previous_metrics, &sensor_filters);
+                            let mut previous_samples =
+                                aggregate_samples_by_timestamp(&previous_raw);
+                            smooth_percentages(&mut previous_samples, &config.smoothing);
+                            reject_power_outliers(
+                                &mut previous_samples,
+                                &mut previous_metrics,
+                                &config.power,
+                            );
+                            let previous_sleeps =
+                                db::fetch_sleep_intervals(&resolved, Some(start), Some(end))?;
+                            Some(BatterySummary::compute(
+                                &previous_samples,
+                                previous_raw.len(),
+                                &previous_metrics,
+                                &previous_sleeps,
+                                &config.runtime,
+                                calibration.as_ref(),
+                            ))
+                        }
+                        _ => None,
+                    };
 
-fn status_cell(status: Option<&str>) -> Cell {
-    let status_text = status.unwrap_or("unknown");
-    let color = match status_text.to_ascii_lowercase().as_str() {
-        s if s.contains("charging") && !s.contains("dis") => Color::Green,
-        s if s.contains("discharging") => Color::Yellow,
-        s if s.contains("full") => Color::Blue,
-        _ => Color::White,
-    };
-    Cell::new(status_text).fg(color)
-}
+                    let mut battery_summary = BatterySummary::compute(
+                        &samples,
+                        timeframe_record_count,
+                        &metric_samples,
+                        &sleeps,
+                        &config.runtime,
+                        calibration.as_ref(),
+                    );
+                    battery_summary.rejected_power = rejected_power;
+                    let window = SectionWindow {
+                        label: timeframe.label.replace('_', " "),
+                        bucket_seconds: resolve_bucket_seconds(
+                            &timeframe,
+                            data_span_seconds(&samples, &metric_samples),
+                            bucket,
+                        )?,
+                    };
+                    let mut sections = summary_sections(
+                        &samples,
+                        &window,
+                        &metric_samples,
+                        &presets,
+                        &battery_summary,
+                        previous_summary.as_ref(),
+                        &config,
+                    );
+                    if let Some(metric) = heatmap {
+                        sections.push(heatmap_section(
+                            metric,
+                            &samples,
+                            &metric_samples,
+                            &sleeps,
+                            &timeframe.label.replace('_', " "),
+                        ));
+                    }
+                    if distribution {
+                        let label = timeframe.label.replace('_', " ");
+                        match &discharge_histogram {
+                            Some(hist) => {
+                                sections.push(ReportSection::table(
+                                    format!("Discharge power distribution ({label})"),
+                                    distribution_table(hist),
+                                ));
+                                sections.push(ReportSection::note(format!(
+                                    "Discharge power over {} readings: p50 {} · p90 {} · p99 {}",
+                                    hist.total,
+                                    format_power(Some(hist.p50)),
+                                    format_power(Some(hist.p90)),
+                                    format_power(Some(hist.p99))
+                                )));
+                            }
+                            None => sections.push(ReportSection::note(format!(
+                                "No discharge readings for a distribution in {label}."
+                            ))),
+                        }
+                    }
+                    if !activities.is_empty() {
+                        let now_ts = now
+                            .duration_since(SystemTime::UNIX_EPOCH)
+                            .unwrap_or_default()
+                            .as_secs_f64();
+                        sections.push(ReportSection::table(
+                            format!("Activities ({})", timeframe.label.replace('_', " ")),
+                            activities_table(&activity::usage(
+                                &activities,
+                                &discharge_segments(&samples, &sleeps),
+                                since_ts.unwrap_or(f64::NEG_INFINITY),
+                                until_ts.unwrap_or(now_ts).min(now_ts),
+                            )),
+                        ));
+                    }
+                    if workloads {
+                        let label = timeframe.label.replace('_', " ");
+                        let labels = db::fetch_workloads(&resolved, since_ts, until_ts)?;
+                        sections.push(if labels.is_empty() {
+                            ReportSection::note(format!(
+                                "No workload labels in {label}; run `symmetri classify` first."
+                            ))
+                        } else {
+                            ReportSection::table(
+                                format!("Drain by workload ({label})"),
+                                workloads_table(&workload::summarize(
+                                    &labels,
+                                    &discharge_segments(&samples, &sleeps),
+                                )),
+                            )
+                        });
+                    }
+                    let saved = match baseline.as_deref() {
+                        Some(name) => Some(saved_baseline(&resolved, Some(name))?),
+                        None => db::fetch_baseline(&resolved, None)?,
+                    };
+                    if let Some(saved) = saved {
+                        let current = baseline_profile(&resolved, since_ts, until_ts, &config)?;
+                        sections.push(ReportSection::note(baseline::describe(
+                            &saved,
+                            &baseline::deviations(&saved, &current),
+                        )));
+                    }
+                    if !annotations.is_empty() {
+                        sections.push(ReportSection::table(
+                            format!("Annotations ({})", timeframe.label.replace('_', " ")),
+                            annotations_table(&annotations),
+                        ));
+                    }
 
-fn format_percent(value: Option<f64>) -> String {
-    value
-        .map(|v| format!("{v:.1}%"))
-        .unwrap_or_else(|| "--".to_string())
+                    let title = format!(
+                        "symmetri battery report ({})",
+                        timeframe.label.replace('_', " ")
+                    );
+                    let generated_at = formats.timestamp(&Utc::now().with_timezone(&formats.zone));
+                    if output.is_some() || !emails.is_empty() {
+                        // Checked before rendering, so a missing server fails fast.
+                        let smtp = if emails.is_empty() {
+                            None
+                        } else {
+                            Some(email::SmtpSettings::resolve(&config.email)?)
+                        };
+                        let graph_png = render_graph_png(
+                            battery_for_plot,
+                            &metric_samples,
+                            &annotations,
+                            &presets,
+                            &timeframe,
+                            &graph_style,
+                        )?;
+                        if let Some(path) = &output {
+                            let html = report::render_html(
+                                &title,
+                                &generated_at,
+                                &sections,
+                                graph_png.as_deref(),
+                            );
+                            std::fs::write(path, html).with_context(|| {
+                                format!("Cannot write HTML report to {}", path.display())
+                            })?;
+                            println!("Saved HTML report to {}", path.display());
+                        }
+                        if let Some(smtp) = &smtp {
+                            let html = report::render_html_with_graph(
+                                &title,
+                                &generated_at,
+                                &sections,
+                                graph_png
+                                    .as_ref()
+                                    .map(|_| format!("cid:{}", email::GRAPH_CID))
+                                    .as_deref(),
+                            );
+                            email::send_report(smtp, &emails, &title, &html, graph_png.as_deref())?;
+                            println!("Sent the report to {}", emails.join(", "));
+                        }
+                    } else if json {
+                        let report = report::render_json(&title, &generated_at, &sections);
+                        println!("{}", serde_json::to_string_pretty(&report)?);
+                    } else {
+                        for section in &sections {
+                            println!("{section}");
+                        }
+                    }
+                    Ok(())
+                })();
+                let Some(interval) = watch else {
+                    return rendered;
+                };
+                if let Err(err) = rendered {
+                    // Data may still arrive; anything else would fail the same way again.
+                    if exit::status_of(&err) != ExitStatus::NoData {
+                        return Err(err);
+                    }
+                    println!("{err}");
+                }
+                println!(
+                    "\nRefreshing every {}s; Ctrl-C to stop.",
+                    interval.as_secs()
+                );
+                let deadline = Instant::now() + interval;
+                while !signals::shutdown_requested() && Instant::now() < deadline {
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                if signals::shutdown_requested() {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
 }
 
-fn format_rate(value: Option<f64>) -> String {
-    value
-        .map(|v| format!("{}/s", format_bytes(v)))
-        .unwrap_or_else(|| "--".to_string())
+/// The report window as the summary tables see it.
+struct SectionWindow {
+    label: String,
+    bucket_seconds: i64,
 }
 
-#[derive(Default, Clone)]
-struct NumberStats {
-    total: f64,
-    count: usize,
-    min: f64,
-    max: f64,
-}
+fn summary_sections(
+    timeframe_samples: &[Sample],
+    window: &SectionWindow,
+    metrics: &[MetricSample],
+    presets: &[ReportPreset],
+    battery_summary: &BatterySummary,
+    previous: Option<&BatterySummary>,
+    config: &Config,
+) -> Vec<ReportSection> {
+    let energy = &config.energy;
+    let timeframe_label = &window.label;
+    let bucket_seconds = window.bucket_seconds;
+    let power_draw_by_bucket =
+        bucket_stats_for_kind(metrics, MetricKind::PowerDraw, bucket_seconds);
+    let network_rates = compute_network_rates(metrics);
+    let mut sections = Vec::new();
 
-impl NumberStats {
-    fn record(&mut self, value: f64) {
-        if self.count == 0 {
-            self.min = value;
-            self.max = value;
+    if presets.contains(&ReportPreset::Battery) {
+        sections.push(match previous {
+            Some(previous) => ReportSection::table(
+                format!("Battery summary ({timeframe_label} vs previous window)"),
+                battery_comparison_table(
+                    battery_summary,
+                    previous,
+                    ["Current", "Previous"],
+                    energy,
+                ),
+            ),
+            None => ReportSection::table(
+                format!("Battery summary ({timeframe_label})"),
+                battery_summary_table(battery_summary, energy),
+            ),
+        });
+
+        if timeframe_samples.is_empty() {
+            sections.push(ReportSection::note(format!(
+                "No battery samples available for buckets in {timeframe_label}."
+            )));
         } else {
-            self.min = self.min.min(value);
-            self.max = self.max.max(value);
+            sections.push(ReportSection::table(
+                format!("Battery stats ({timeframe_label})"),
+                battery_stats_table(timeframe_samples, &power_draw_by_bucket, bucket_seconds),
+            ));
         }
-        self.total += value;
-        self.count += 1;
-    }
 
-    fn record_opt(&mut self, value: Option<f64>) {
-        if let Some(v) = value {
-            self.record(v);
+        let days = energy_by_day(timeframe_samples);
+        if days
+            .values()
+            .any(|day| day.discharged_wh > 0.0 || day.charged_wh > 0.0)
+        {
+            sections.push(ReportSection::table(
+                format!("Energy per day ({timeframe_label})"),
+                energy_by_day_table(&days, energy),
+            ));
         }
-    }
 
-    fn average(&self) -> Option<f64> {
-        (self.count > 0).then_some(self.total / self.count as f64)
-    }
+        let full = full_charge_time(timeframe_samples);
+        if full.longest_hours >= FULL_CHARGE_WARN_HOURS {
+            sections.push(ReportSection::note(format!(
+                "Warning: battery sat at 100% for {} in {} (longest stretch {}); long periods at full charge accelerate wear. Consider `symmetri charge-limit set 80`.",
+                format_runtime(Some(full.total_hours)),
+                timeframe_label,
+                format_runtime(Some(full.longest_hours))
+            )));
+        }
 
-    fn min(&self) -> Option<f64> {
-        (self.count > 0).then_some(self.min)
-    }
+        if let Some(habits) = ChargingHabits::compute(timeframe_samples)
+            .filter(|habits| habits.tracked_hours() > 0.0 || habits.discharges > 0)
+        {
+            sections.push(ReportSection::table(
+                format!("Charging habits ({timeframe_label})"),
+                charging_habits_table(&habits),
+            ));
+        }
 
-    fn max(&self) -> Option<f64> {
-        (self.count > 0).then_some(self.max)
-    }
-}
+        let profiles = profile_breakdown(timeframe_samples, metrics);
+        if !profiles.is_empty() {
+            sections.push(ReportSection::table(
+                format!("Drain by power profile ({timeframe_label})"),
+                profile_breakdown_table(&profiles),
+            ));
+        }
 
-#[derive(Default, Clone)]
-struct UsageStats {
-    used: NumberStats,
-    percent: NumberStats,
-}
+        let usb = usb_device_stats(timeframe_samples, metrics);
+        if !usb.is_empty() {
+            sections.push(ReportSection::table(
+                format!("USB devices ({timeframe_label})"),
+                usb_devices_table(&usb),
+            ));
+        }
 
-impl UsageStats {
-    fn record(&mut self, used: Option<f64>, total: Option<f64>) {
-        if let Some(used_bytes) = used {
-            self.used.record(used_bytes);
-            if let Some(total_bytes) = total {
-                if total_bytes > 0.0 {
-                    self.percent.record((used_bytes / total_bytes) * 100.0);
-                }
+        let media_days = media_by_day(metrics);
+        if !media_days.is_empty() {
+            sections.push(ReportSection::table(
+                format!("Media activity per day ({timeframe_label})"),
+                media_by_day_table(&media_days, &days),
+            ));
+            let timeline = media_timeline(metrics);
+            let rates = average_rates_by(timeframe_samples, |s| {
+                profile_at(&timeline, s.ts).map(str::to_string)
+            });
+            if !rates.is_empty() {
+                sections.push(ReportSection::table(
+                    format!("Drain by media activity ({timeframe_label})"),
+                    media_drain_table(&rates),
+                ));
             }
         }
-    }
-}
 
-#[derive(Default, Clone)]
-struct RateStats {
-    rx: NumberStats,
-    tx: NumberStats,
-}
+        let places = location_timeline(metrics, &config.location);
+        if places.iter().any(|(_, place)| place != NO_WIFI) {
+            let rates = average_rates_by(timeframe_samples, |s| {
+                profile_at(&places, s.ts).map(str::to_string)
+            });
+            sections.push(ReportSection::table(
+                format!("Drain by location ({timeframe_label})"),
+                location_table(&places, &rates),
+            ));
+        }
 
-impl RateStats {
-    fn record(&mut self, rx: Option<f64>, tx: Option<f64>) {
-        self.rx.record_opt(rx);
-        self.tx.record_opt(tx);
-    }
-}
+        let screen = display::screen_timeline(metrics);
+        if !screen.is_empty() {
+            let formats = locale::current();
+            let day_of = |s: &Sample| formats.datetime(s.ts).map(|dt| dt.date_naive());
+            let by_state = average_rates_by(timeframe_samples, |s| {
+                Some((day_of(s)?, display::screen_on_at(&screen, s.ts)?))
+            });
+            let overall = average_rates_by(timeframe_samples, day_of);
+            sections.push(ReportSection::table(
+                format!("Screen time per day ({timeframe_label})"),
+                screen_time_table(
+                    &display::screen_on_hours_by_day(&screen, &formats),
+                    &by_state,
+                    &overall,
+                ),
+            ));
+        }
+    }
 
-fn average_for_kind(metrics: &[MetricSample], kind: MetricKind) -> NumberStats {
-    let mut stats = NumberStats::default();
-    for sample in metrics.iter().filter(|s| s.kind == kind) {
-        stats.record_opt(sample.value);
+    if presets.contains(&ReportPreset::Cpu) {
+        let usage_buckets =
+            bucket_stats_for_kind_by_source(metrics, MetricKind::CpuUsage, bucket_seconds);
+        let freq_buckets =
+            bucket_stats_for_kind_by_source(metrics, MetricKind::CpuFrequency, bucket_seconds);
+        if usage_buckets.is_empty() && freq_buckets.is_empty() {
+            sections.push(ReportSection::note(format!(
+                "No CPU samples available for {timeframe_label}."
+            )));
+        } else {
+            sections.push(ReportSection::table(
+                format!("CPU stats ({timeframe_label})"),
+                cpu_stats_table(bucket_seconds, &usage_buckets, &freq_buckets),
+            ));
+        }
     }
-    stats
-}
 
-fn filter_metrics_by_source(
-    metrics: &[MetricSample],
-    sensor_filters: &[String],
-) -> Vec<MetricSample> {
-    if sensor_filters.is_empty() {
-        return metrics.to_vec();
+    if presets.contains(&ReportPreset::Gpu) {
+        let usage_buckets =
+            bucket_stats_for_kind_by_source(metrics, MetricKind::GpuUsage, bucket_seconds);
+        let freq_buckets =
+            bucket_stats_for_kind_by_source(metrics, MetricKind::GpuFrequency, bucket_seconds);
+        if usage_buckets.is_empty() && freq_buckets.is_empty() {
+            sections.push(ReportSection::note(format!(
+                "No GPU samples available for {timeframe_label}."
+            )));
+        } else {
+            sections.push(ReportSection::table(
+                format!("GPU stats ({timeframe_label})"),
+                gpu_stats_table(bucket_seconds, &usage_buckets, &freq_buckets),
+            ));
+        }
     }
-    metrics
-        .iter()
-        .filter(|m| sensor_filters.iter().any(|f| f == &m.source))
-        .cloned()
-        .collect()
-}
 
-type SourceBuckets = BTreeMap<String, BTreeMap<DateTime<Local>, NumberStats>>;
+    if presets.contains(&ReportPreset::Memory) {
+        let ram: Vec<MetricSample> = metrics
+            .iter()
+            .filter(|m| m.kind == MetricKind::MemoryUsage && m.source == MEMORY_SOURCE)
+            .cloned()
+            .collect();
+        let memory_buckets = bucket_usage_stats(&ram, MetricKind::MemoryUsage, bucket_seconds);
+        if memory_buckets.is_empty() {
+            sections.push(ReportSection::note(format!(
+                "No memory samples available for {timeframe_label}."
+            )));
+        } else {
+            sections.push(ReportSection::table(
+                format!("Memory stats ({timeframe_label})"),
+                memory_stats_table(
+                    bucket_seconds,
+                    &memory_buckets,
+                    &bucket_memory_details(metrics, bucket_seconds),
+                ),
+            ));
+        }
+    }
 
-fn bucket_stats_for_kind_by_source(
-    metrics: &[MetricSample],
-    kind: MetricKind,
-    bucket_seconds: i64,
-) -> SourceBuckets {
-    let mut buckets: SourceBuckets = BTreeMap::new();
-    for sample in metrics.iter().filter(|s| s.kind == kind) {
-        if let Some(value) = sample.value {
-            let bucket = bucket_start(sample.ts, bucket_seconds);
-            buckets
+    if presets.contains(&ReportPreset::Disk) {
+        let mut disk_buckets: BTreeMap<String, BTreeMap<DateTime<Zone>, UsageStats>> =
+            BTreeMap::new();
+        for sample in metrics.iter().filter(|m| m.kind == MetricKind::DiskUsage) {
+            disk_buckets
                 .entry(sample.source.clone())
                 .or_default()
-                .entry(bucket)
+                .entry(bucket_start(sample.ts, bucket_seconds))
                 .or_default()
-                .record(value);
+                .record(sample.value, number_from_details(sample, "total_bytes"));
+        }
+        if disk_buckets.is_empty() {
+            sections.push(ReportSection::note(format!(
+                "No disk samples available for {timeframe_label}."
+            )));
+        } else {
+            sections.push(ReportSection::table(
+                format!("Disk stats ({timeframe_label})"),
+                disk_stats_table(bucket_seconds, &disk_buckets),
+            ));
         }
     }
-    buckets
+
+    if presets.contains(&ReportPreset::Network) {
+        let network_buckets = bucket_network_rates(&network_rates, bucket_seconds);
+        if network_buckets.is_empty() {
+            sections.push(ReportSection::note(format!(
+                "No network samples available for {timeframe_label}."
+            )));
+        } else {
+            sections.push(ReportSection::table(
+                format!("Network stats ({timeframe_label})"),
+                network_stats_table(bucket_seconds, &network_buckets),
+            ));
+        }
+        let wifi_buckets = bucket_wifi_stats(metrics, bucket_seconds);
+        if !wifi_buckets.is_empty() {
+            sections.push(ReportSection::table(
+                format!("Wi-Fi ({timeframe_label})"),
+                wifi_stats_table(bucket_seconds, &wifi_buckets),
+            ));
+        }
+    }
+
+    if presets.contains(&ReportPreset::Temperature) {
+        let temp_buckets =
+            bucket_stats_for_kind_by_source(metrics, MetricKind::Temperature, bucket_seconds);
+        if temp_buckets.is_empty() {
+            sections.push(ReportSection::note(format!(
+                "No temperature samples available for {timeframe_label}."
+            )));
+        } else {
+            sections.push(ReportSection::table(
+                format!("Temperature stats ({timeframe_label})"),
+                temperature_stats_table(bucket_seconds, &temp_buckets),
+            ));
+            let episodes = thermal::detect_throttling(metrics, &ThrottleCriteria::default());
+            if !episodes.is_empty() {
+                sections.push(ReportSection::note(format!(
+                    "{} thermal throttling episodes in {timeframe_label}; run `symmetri thermals` for details.",
+                    episodes.len()
+                )));
+            }
+        }
+    }
+
+    if presets.contains(&ReportPreset::Load) {
+        let load_buckets =
+            bucket_stats_for_kind_by_source(metrics, MetricKind::SystemLoad, bucket_seconds);
+        if load_buckets.is_empty() {
+            sections.push(ReportSection::note(format!(
+                "No load samples available for {timeframe_label}."
+            )));
+        } else {
+            sections.push(ReportSection::table(
+                format!("System load ({timeframe_label})"),
+                load_stats_table(bucket_seconds, &load_buckets),
+            ));
+        }
+    }
+
+    if presets.contains(&ReportPreset::Cgroups) {
+        let usage = cgroup_usage(metrics);
+        if usage.values().all(Vec::is_empty) {
+            sections.push(ReportSection::note(format!(
+                "No cgroup samples available for {timeframe_label}; list groups in `[cgroups] paths`."
+            )));
+        } else {
+            sections.push(ReportSection::table(
+                format!("Cgroups ({timeframe_label})"),
+                cgroup_stats_table(bucket_seconds, &usage),
+            ));
+        }
+    }
+
+    if presets.contains(&ReportPreset::Internal) {
+        let overhead = internal_overhead(metrics);
+        if overhead.is_empty() {
+            sections.push(ReportSection::note(format!(
+                "No internal samples available for {timeframe_label}."
+            )));
+        } else {
+            sections.push(ReportSection::table(
+                format!("Symmetri overhead ({timeframe_label})"),
+                internal_stats_table(&overhead),
+            ));
+            let errors = collector_error_counts(metrics);
+            if !errors.is_empty() {
+                let listed: Vec<String> = errors
+                    .iter()
+                    .map(|(name, count)| format!("{name} x{count}"))
+                    .collect();
+                sections.push(ReportSection::note(format!(
+                    "Collector errors in {timeframe_label}: {}",
+                    listed.join(", ")
+                )));
+            }
+        }
+    }
+
+    sections
 }
 
-fn data_span_seconds(samples: &[Sample], metrics: &[MetricSample]) -> Option<f64> {
-    let mut min_ts = f64::INFINITY;
-    let mut max_ts = f64::NEG_INFINITY;
+/// Per-source summary of internal samples, in time order within each source.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct OverheadStats {
+    unit: Option<String>,
+    count: usize,
+    sum: f64,
+    max: f64,
+    latest: f64,
+}
 
-    for ts in samples
+impl OverheadStats {
+    fn average(&self) -> f64 {
+        self.sum / self.count.max(1) as f64
+    }
+}
+
+fn overhead_source_rank(source: &str) -> (u8, &str) {
+    let rank = match source {
+        internal::CYCLE_SOURCE => 0,
+        internal::DB_INSERT_SOURCE => 1,
+        internal::RSS_SOURCE => 2,
+        internal::ERRORS_SOURCE => 3,
+        _ => 4,
+    };
+    (rank, source)
+}
+
+fn internal_overhead(metrics: &[MetricSample]) -> Vec<(String, OverheadStats)> {
+    let mut internal: Vec<&MetricSample> = metrics
         .iter()
-        .map(|s| s.ts)
-        .chain(metrics.iter().map(|m| m.ts))
+        .filter(|m| m.kind == MetricKind::Internal && m.value.is_some())
+        .collect();
+    internal.sort_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap());
+    let mut by_source: BTreeMap<String, OverheadStats> = BTreeMap::new();
+    for sample in internal {
+        let value = sample.value.unwrap_or_default();
+        let stats = by_source.entry(sample.source.clone()).or_default();
+        stats.unit = sample.unit.clone();
+        stats.count += 1;
+        stats.sum += value;
+        stats.max = if stats.count == 1 {
+            value
+        } else {
+            stats.max.max(value)
+        };
+        stats.latest = value;
+    }
+    let mut rows: Vec<(String, OverheadStats)> = by_source.into_iter().collect();
+    rows.sort_by(|a, b| overhead_source_rank(&a.0).cmp(&overhead_source_rank(&b.0)));
+    rows
+}
+
+fn collector_error_counts(metrics: &[MetricSample]) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for sample in metrics
+        .iter()
+        .filter(|m| m.kind == MetricKind::Internal && m.source == internal::ERRORS_SOURCE)
     {
-        min_ts = min_ts.min(ts);
-        max_ts = max_ts.max(ts);
+        if let Some(names) = sample.details["collectors"].as_array() {
+            for name in names.iter().filter_map(|n| n.as_str()) {
+                *counts.entry(name.to_string()).or_insert(0) += 1;
+            }
+        }
     }
+    counts
+}
 
-    if max_ts.is_finite() && min_ts.is_finite() && max_ts >= min_ts {
-        Some(max_ts - min_ts)
-    } else {
-        None
+fn format_overhead(value: f64, unit: Option<&str>) -> String {
+    match unit {
+        Some("ms") => format!("{value:.1}ms"),
+        Some("bytes") => format_bytes(value),
+        _ => format!("{value:.0}"),
     }
 }
 
-fn bucket_stats_for_kind(
-    metrics: &[MetricSample],
-    kind: MetricKind,
-    bucket_seconds: i64,
-) -> BTreeMap<DateTime<Local>, NumberStats> {
-    let mut buckets: BTreeMap<DateTime<Local>, NumberStats> = BTreeMap::new();
-    for sample in metrics.iter().filter(|s| s.kind == kind) {
-        if let Some(value) = sample.value {
-            let bucket = bucket_start(sample.ts, bucket_seconds);
-            buckets.entry(bucket).or_default().record(value);
-        }
+fn internal_stats_table(rows: &[(String, OverheadStats)]) -> Table {
+    let mut report = themed_table();
+    report.set_header(header_cells(&["Source", "Samples", "Avg", "Max", "Latest"]));
+    for (source, stats) in rows {
+        let unit = stats.unit.as_deref();
+        report.add_row(vec![
+            label_cell(source),
+            value_cell(stats.count),
+            value_cell(format_overhead(stats.average(), unit)),
+            value_cell(format_overhead(stats.max, unit)),
+            value_cell(format_overhead(stats.latest, unit)),
+        ]);
     }
-    buckets
+    report
 }
 
-#[cfg(test)]
-fn usage_stats_for_kind(metrics: &[MetricSample], kind: MetricKind) -> UsageStats {
-    let mut stats = UsageStats::default();
-    for sample in metrics.iter().filter(|s| s.kind == kind) {
-        let total = number_from_details(sample, "total_bytes");
-        stats.record(sample.value, total);
+/// UPower names files `history-charge-<model>-<capacity>-<serial>.dat`; keep the device part.
+fn default_import_source(format: ImportFormat, file: &Path) -> String {
+    let stem = file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "import".to_string());
+    match format {
+        ImportFormat::UpowerHistory => format!(
+            "upower:{}",
+            stem.strip_prefix("history-charge-").unwrap_or(&stem)
+        ),
+        _ => stem,
     }
-    stats
 }
 
-fn bucket_usage_stats(
-    metrics: &[MetricSample],
-    kind: MetricKind,
-    bucket_seconds: i64,
-) -> BTreeMap<DateTime<Local>, UsageStats> {
-    let mut buckets: BTreeMap<DateTime<Local>, UsageStats> = BTreeMap::new();
-    for sample in metrics.iter().filter(|s| s.kind == kind) {
-        let bucket = bucket_start(sample.ts, bucket_seconds);
-        let total = number_from_details(sample, "total_bytes");
-        buckets
-            .entry(bucket)
-            .or_default()
-            .record(sample.value, total);
+fn print_schema(tables: &[db::TableSchema]) {
+    for table in tables {
+        let mut columns = themed_table();
+        columns.set_header(header_cells(&["Column", "Type", "Unit", "Meaning"]));
+        for column in &table.columns {
+            let sql_type = if column.not_null {
+                format!("{} NOT NULL", column.sql_type)
+            } else {
+                column.sql_type.clone()
+            };
+            columns.add_row(vec![
+                label_cell(&column.name),
+                Cell::new(sql_type),
+                Cell::new(column.unit.unwrap_or("")),
+                Cell::new(column.meaning),
+            ]);
+        }
+        println!("{}\n{columns}", table.name);
+        for index in &table.indexes {
+            println!("{index};");
+        }
+        println!();
     }
-    buckets
+
+    let mut kinds = themed_table();
+    kinds.set_header(header_cells(&["Kind", "Unit", "Meaning"]));
+    for kind in MetricKind::ALL {
+        kinds.add_row(vec![
+            label_cell(kind.as_str()),
+            Cell::new(kind.unit().unwrap_or("")),
+            Cell::new(kind.description()),
+        ]);
+    }
+    println!("Metric kinds (metric_samples.kind)\n{kinds}");
 }
 
-struct NetworkRateSample {
-    ts: f64,
-    rx_rate: Option<f64>,
-    tx_rate: Option<f64>,
+/// Usable energy and the gauge curve at every tenth percent.
+fn print_calibration(calibration: &Calibration) {
+    let name = battery_name(&calibration.source_path);
+    println!(
+        "\n{name} calibrated {}: {} usable of {} reported full",
+        format_timestamp(calibration.ts),
+        format_energy(calibration.usable_wh),
+        format_energy(calibration.full_wh)
+    );
+    let mut table = themed_table();
+    table.set_header(header_cells(&["Gauge shows", "Energy left", "Off by"]));
+    for reported in (1..=10).rev().map(|tenth| tenth as f64 * 10.0) {
+        let actual = calibration.true_pct(reported);
+        table.add_row(vec![
+            label_cell(&format!("{reported:.0}%")),
+            value_cell(format_percent(Some(actual))),
+            value_cell(format!("{:+.1} pts", reported - actual)),
+        ]);
+    }
+    println!("{table}");
 }
 
-fn rate_from_counters(previous: Option<f64>, current: Option<f64>, dt: f64) -> Option<f64> {
-    match (previous, current) {
-        (Some(prev), Some(next)) if next >= prev && dt > 0.0 => Some((next - prev) / dt),
-        _ => None,
+fn battery_name(source_path: &str) -> String {
+    Path::new(source_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| source_path.to_string())
+}
+
+/// `SMP 5B10W13975 #1234`, from whichever of the three the driver reports.
+fn battery_label(info: &BatteryInfo) -> String {
+    let parts: Vec<String> = [
+        info.manufacturer.clone(),
+        info.model_name.clone(),
+        info.serial_number
+            .as_ref()
+            .map(|serial| format!("#{serial}")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if parts.is_empty() {
+        "unidentified battery".to_string()
+    } else {
+        parts.join(" ")
     }
 }
 
-fn compute_network_rates(metrics: &[MetricSample]) -> Vec<NetworkRateSample> {
-    let mut by_iface: BTreeMap<&str, Vec<&MetricSample>> = BTreeMap::new();
-    for sample in metrics
-        .iter()
-        .filter(|s| s.kind == MetricKind::NetworkBytes)
-    {
-        by_iface.entry(&sample.source).or_default().push(sample);
+/// One row per battery seen in each slot, oldest first; the current one shows its latest
+/// full capacity and health from `latest`.
+fn battery_info_table(batteries: &[(f64, BatteryInfo)], latest: &[Sample]) -> Table {
+    let mut table = themed_table();
+    table.set_header(header_cells(&[
+        "Battery",
+        "Manufacturer",
+        "Model",
+        "Serial",
+        "Technology",
+        "Design",
+        "Full now",
+        "Health",
+        "First seen",
+    ]));
+    let text = |value: &Option<String>| value.clone().unwrap_or_else(|| "--".to_string());
+    for (index, (ts, info)) in batteries.iter().enumerate() {
+        let replaced = batteries
+            .get(index + 1)
+            .is_some_and(|(_, next)| next.source_path == info.source_path);
+        let current = latest
+            .iter()
+            .find(|sample| !replaced && sample.source_path == info.source_path);
+        let (full, health) = match current {
+            Some(sample) => (
+                sample
+                    .energy_full_wh
+                    .map(format_energy)
+                    .unwrap_or_else(|| "--".to_string()),
+                format_percent(sample.health_pct),
+            ),
+            None if replaced => ("replaced".to_string(), "--".to_string()),
+            None => ("--".to_string(), "--".to_string()),
+        };
+        table.add_row(vec![
+            label_cell(&battery_name(&info.source_path)),
+            Cell::new(text(&info.manufacturer)),
+            Cell::new(text(&info.model_name)),
+            Cell::new(text(&info.serial_number)),
+            Cell::new(text(&info.technology)),
+            value_cell(
+                info.energy_full_design_wh
+                    .map(format_energy)
+                    .unwrap_or_else(|| "--".to_string()),
+            ),
+            value_cell(full),
+            value_cell(health),
+            Cell::new(format_timestamp(*ts)),
+        ]);
     }
+    table
+}
 
-    let mut rates = Vec::new();
-    for (_iface, mut samples) in by_iface {
-        samples.sort_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap());
-        for window in samples.windows(2) {
-            let prev = window[0];
-            let next = window[1];
-            let dt = next.ts - prev.ts;
-            if dt <= 0.0 {
-                continue;
+/// Wear trend of each current battery, with the dates it reaches [`PROJECTION_TARGETS`].
+fn wear_table(trends: &[(String, Option<WearTrend>)]) -> Table {
+    let mut table = themed_table();
+    let targets: Vec<String> = PROJECTION_TARGETS
+        .iter()
+        .map(|target| format!("Below {target:.0}%"))
+        .collect();
+    let mut header = vec!["Battery", "Health", "Loss per year", "Fitted over"];
+    header.extend(targets.iter().map(String::as_str));
+    table.set_header(header_cells(&header));
+    for (source_path, trend) in trends {
+        let mut row = vec![label_cell(&battery_name(source_path))];
+        match trend {
+            Some(trend) => {
+                row.push(value_cell(format_percent(Some(trend.current_pct))));
+                row.push(value_cell(format!("{:.1} pts", trend.loss_pct_per_year)));
+                row.push(value_cell(format!("{:.0} days", trend.fitted_days())));
+                for target in PROJECTION_TARGETS {
+                    row.push(value_cell(format_wear_projection(trend, target)));
+                }
             }
-            let rx_rate = rate_from_counters(
-                number_from_details(prev, "rx_bytes"),
-                number_from_details(next, "rx_bytes"),
-                dt,
-            );
-            let tx_rate = rate_from_counters(
-                number_from_details(prev, "tx_bytes"),
-                number_from_details(next, "tx_bytes"),
-                dt,
-            );
-            if rx_rate.is_none() && tx_rate.is_none() {
-                continue;
+            None => {
+                row.push(Cell::new("needs two weeks of daily readings"));
             }
-            rates.push(NetworkRateSample {
-                ts: next.ts,
-                rx_rate,
-                tx_rate,
-            });
         }
+        table.add_row(row);
     }
-    rates
+    table
 }
 
-fn bucket_network_rates(
-    rates: &[NetworkRateSample],
-    bucket_seconds: i64,
-) -> BTreeMap<DateTime<Local>, RateStats> {
-    let mut buckets: BTreeMap<DateTime<Local>, RateStats> = BTreeMap::new();
-    for rate in rates {
-        let bucket = bucket_start(rate.ts, bucket_seconds);
-        buckets
-            .entry(bucket)
-            .or_default()
-            .record(rate.rx_rate, rate.tx_rate);
+/// `2027-03-14 (in 5 months)` for when `trend` reaches `target_pct`.
+fn format_wear_projection(trend: &WearTrend, target_pct: f64) -> String {
+    let Some(ts) = trend.reaches(target_pct) else {
+        return "not falling".to_string();
+    };
+    if ts <= trend.last_ts {
+        return "reached".to_string();
+    }
+    let formats = locale::current();
+    let days = (ts - trend.last_ts) / 86_400.0;
+    let ahead = if days < 60.0 {
+        format!("in {days:.0} days")
+    } else if days < 547.0 {
+        format!("in {:.0} months", days / 30.44)
+    } else if days < 20.0 * 365.25 {
+        format!("in {:.1} years", days / 365.25)
+    } else {
+        return "not within 20 years".to_string();
+    };
+    match formats.datetime(ts) {
+        Some(dt) => format!("{} ({ahead})", formats.date(&dt)),
+        None => ahead,
     }
-    buckets
 }
 
-fn format_freq(value: Option<f64>) -> String {
-    value
-        .map(|v| format!("{v:.0}MHz"))
-        .unwrap_or_else(|| "--".to_string())
+/// Collector state key holding the batteries already warned about, so `health --notify`
+/// on a timer only fires when a projection crosses into the warning window.
+const WEAR_WARNED_STATE: &str = "health_wear_warned";
+
+fn notify_wear(
+    db_path: &Path,
+    trends: &[(String, Option<WearTrend>)],
+    health: &HealthConfig,
+) -> Result<()> {
+    let conn = db::init_db_connection(db_path)?;
+    let previously: Vec<String> = db::get_state(&conn, WEAR_WARNED_STATE)?
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default();
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    let mut warned = Vec::new();
+    for (source_path, trend) in trends {
+        let Some(trend) = trend else {
+            continue;
+        };
+        if !trend.warns(health.warn_below_pct, health.warn_within_days, now) {
+            continue;
+        }
+        warned.push(source_path.clone());
+        if previously.contains(source_path) {
+            continue;
+        }
+        let body = format!(
+            "{} is at {} of its design capacity, losing {:.1} points a year; below {:.0}%: {}",
+            battery_name(source_path),
+            format_percent(Some(trend.current_pct)),
+            trend.loss_pct_per_year,
+            health.warn_below_pct,
+            format_wear_projection(trend, health.warn_below_pct)
+        );
+        daily::send_notification("Battery wearing out", &body)?;
+    }
+    db::set_state(&conn, WEAR_WARNED_STATE, &serde_json::to_string(&warned)?)
 }
 
-fn battery_summary_table(
-    timeframe_records: usize,
-    avg_discharge_w: Option<f64>,
-    avg_charge_w: Option<f64>,
-    est_runtime_hours: Option<f64>,
-) -> Table {
+fn charging_habits_table(habits: &ChargingHabits) -> Table {
     let mut table = themed_table();
     table.set_header(header_cells(&["Metric", "Value"]));
+    let depth = |value: Option<f64>| {
+        value
+            .map(|pct| format!("{pct:.0} pts"))
+            .unwrap_or_else(|| "--".to_string())
+    };
     table.add_row(vec![
-        label_cell("Records in window"),
-        value_cell(timeframe_records),
+        label_cell("Discharges"),
+        value_cell(habits.discharges),
     ]);
     table.add_row(vec![
-        label_cell("Avg discharge power"),
-        value_cell(format_power(avg_discharge_w)),
+        label_cell("Average depth of discharge"),
+        value_cell(depth(habits.avg_depth_pct)),
     ]);
     table.add_row(vec![
-        label_cell("Avg charge power"),
-        value_cell(format_power(avg_charge_w)),
+        label_cell("Deepest discharge"),
+        value_cell(depth(habits.max_depth_pct)),
     ]);
+    table.add_row(vec![label_cell("Charges"), value_cell(habits.charges)]);
     table.add_row(vec![
-        label_cell("Est runtime (full)"),
-        value_cell(format_runtime(est_runtime_hours)),
+        label_cell(&format!("Micro-charges (< {MICRO_CHARGE_PCT:.0} pts)")),
+        value_cell(match habits.micro_charges_per_week {
+            Some(per_week) => format!("{} ({per_week:.1}/week)", habits.micro_charges),
+            None => habits.micro_charges.to_string(),
+        }),
     ]);
-    table
+    let tracked = habits.tracked_hours();
+    for (label, hours) in [
+        ("Time below 20%", habits.hours_below_20),
+        ("Time in 20–80%", habits.hours_20_80),
+        ("Time in 80–99%", habits.hours_above_80),
+        ("Time at 100%", habits.hours_full),
+    ] {
+        let share = (tracked > 0.0).then(|| hours / tracked * 100.0);
+        table.add_row(vec![
+            label_cell(label),
+            value_cell(format!(
+                "{} ({})",
+                format_runtime(Some(hours)),
+                format_percent(share)
+            )),
+        ]);
+    }
+    table
+}
+
+fn daily_summary_table(comparison: &DailyComparison) -> Table {
+    let today = &comparison.summary;
+    let trailing = comparison.trailing.as_ref();
+    let mut table = themed_table();
+    table.set_header(header_cells(&[
+        "Metric",
+        "Today",
+        &format!("{}-day avg", daily::TRAILING_DAYS),
+    ]));
+    let values = |d: &DaySummary| {
+        [
+            format_runtime(Some(d.awake_hours)),
+            d.used_wh
+                .map(format_energy)
+                .unwrap_or_else(|| "--".to_string()),
+            format_power(d.avg_drain_w),
+            format_drain_rate(d.avg_drain_pct_h),
+            format_percent(d.min_pct),
+        ]
+    };
+    let labels = [
+        "Screen-on (est.)",
+        "Energy used",
+        "Avg drain",
+        "Avg drain rate",
+        "Lowest charge",
+    ];
+    let averages = trailing.map(values);
+    for (idx, (label, value)) in labels.into_iter().zip(values(today)).enumerate() {
+        let average = averages
+            .as_ref()
+            .map(|averages| averages[idx].clone())
+            .unwrap_or_else(|| "--".to_string());
+        table.add_row(vec![
+            label_cell(label),
+            value_cell(value),
+            value_cell(average),
+        ]);
+    }
+    table
+}
+
+fn print_db_stats(path: &Path, stats: &DbStats) {
+    println!("Database: {}", path.display());
+    println!("Size: {}", format_bytes(stats.file_size_bytes as f64));
+    match (stats.first_ts, stats.last_ts) {
+        (Some(first), Some(last)) => {
+            println!(
+                "Span: {} -> {}",
+                format_timestamp(first),
+                format_timestamp(last)
+            );
+        }
+        _ => println!("Span: no samples yet"),
+    }
+    if let Some(per_day) = stats.samples_per_day() {
+        println!("Samples per day: {per_day:.0}");
+    }
+
+    let mut tables = themed_table();
+    tables.set_header(header_cells(&["Table", "Rows"]));
+    for (table, rows) in &stats.table_rows {
+        tables.add_row(vec![label_cell(table), value_cell(rows)]);
+    }
+    println!("\nTables\n{tables}");
+
+    if !stats.metric_rows.is_empty() {
+        let mut sources = themed_table();
+        sources.set_header(header_cells(&["Kind", "Source", "Rows"]));
+        for (kind, source, rows) in &stats.metric_rows {
+            sources.add_row(vec![label_cell(kind), Cell::new(source), value_cell(rows)]);
+        }
+        println!("\nMetric sources\n{sources}");
+    }
+}
+
+fn print_bench(path: &Path, results: &[BenchResult]) {
+    println!("Database: {}", path.display());
+    let mut table = themed_table();
+    table.set_header(header_cells(&["Step", "Rows", "Median time", "Rows/s"]));
+    for result in results {
+        table.add_row(vec![
+            label_cell(&result.name),
+            value_cell(result.rows),
+            value_cell(format!("{:.1}ms", result.elapsed.as_secs_f64() * 1000.0)),
+            value_cell(
+                result
+                    .rows_per_second()
+                    .map(|rate| format!("{rate:.0}"))
+                    .unwrap_or_else(|| "--".to_string()),
+            ),
+        ]);
+    }
+    println!("{table}");
+}
+
+/// Renders the report graphs to a scratch PNG and returns its bytes for embedding, or
+/// `None` when there is nothing to plot. The scratch file is created exclusively with a
+/// random name, so another user cannot plant or read it in a shared temp directory.
+#[cfg(feature = "graphs")]
+fn render_graph_png(
+    battery_samples: &[Sample],
+    metrics: &[MetricSample],
+    annotations: &[Annotation],
+    presets: &[ReportPreset],
+    timeframe: &Timeframe,
+    style: &GraphStyle,
+) -> Result<Option<Vec<u8>>> {
+    if battery_samples.is_empty() && metrics.is_empty() {
+        return Ok(None);
+    }
+    let scratch = tempfile::Builder::new()
+        .prefix("symmetri_report_")
+        .suffix(".png")
+        .tempfile()
+        .context("Cannot create a scratch file for the report graph")?;
+    let drawn = graph::draw_plot(
+        battery_samples,
+        metrics,
+        annotations,
+        presets,
+        timeframe,
+        scratch.path(),
+        style,
+    )?;
+    if !drawn {
+        return Ok(None);
+    }
+    let png = std::fs::read(scratch.path())
+        .with_context(|| format!("Cannot read rendered graph {}", scratch.path().display()))?;
+    Ok(Some(png))
+}
+
+#[cfg(not(feature = "graphs"))]
+fn render_graph_png(
+    _battery_samples: &[Sample],
+    _metrics: &[MetricSample],
+    _annotations: &[Annotation],
+    _presets: &[ReportPreset],
+    _timeframe: &Timeframe,
+    _style: &GraphStyle,
+) -> Result<Option<Vec<u8>>> {
+    Ok(None)
+}
+
+/// A continuous stretch at 100% this long triggers the charge-limit hint in reports.
+const FULL_CHARGE_WARN_HOURS: f64 = 8.0;
+
+const POWER_SUPPLY_ROOT: &str = "/sys/class/power_supply";
+
+/// Batteries named `battery` (e.g. BAT0), or all of them; fails when there are none.
+fn matching_batteries(battery: Option<&str>) -> Result<Vec<PathBuf>> {
+    let mut batteries: Vec<PathBuf> = find_battery_paths(Path::new(POWER_SUPPLY_ROOT))
+        .into_iter()
+        .filter(|path| {
+            battery
+                .map(|name| path.file_name().is_some_and(|f| f == name))
+                .unwrap_or(true)
+        })
+        .collect();
+    if batteries.is_empty() {
+        anyhow::bail!("No matching batteries found in {POWER_SUPPLY_ROOT}");
+    }
+    batteries.sort();
+    Ok(batteries)
+}
+
+fn format_power(value: Option<f64>) -> String {
+    match value {
+        Some(v) => format!("{v:.2}W"),
+        None => "--".to_string(),
+    }
+}
+
+/// Prints rows of JSON cells as a table, an array of objects keyed by column, or CSV.
+fn print_rows(
+    columns: &[String],
+    rows: &[Vec<serde_json::Value>],
+    format: OutputFormat,
+) -> Result<()> {
+    let text = |cell: &serde_json::Value| match cell {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Number(number) => match number.as_f64() {
+            Some(value) if number.is_f64() => format!("{value:.2}"),
+            _ => number.to_string(),
+        },
+        other => other.to_string(),
+    };
+    match format {
+        OutputFormat::Table => {
+            let mut table = themed_table();
+            let header: Vec<&str> = columns.iter().map(String::as_str).collect();
+            table.set_header(header_cells(&header));
+            for row in rows {
+                table.add_row(row.iter().map(|cell| match cell {
+                    serde_json::Value::Number(_) => value_cell(text(cell)),
+                    serde_json::Value::Null => value_cell("--"),
+                    _ => label_cell(&text(cell)),
+                }));
+            }
+            println!("{table}");
+        }
+        OutputFormat::Json => {
+            let objects: Vec<serde_json::Map<String, serde_json::Value>> = rows
+                .iter()
+                .map(|row| columns.iter().cloned().zip(row.iter().cloned()).collect())
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&objects)?);
+        }
+        OutputFormat::Csv => {
+            let records: Vec<Vec<String>> = rows
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|cell| match cell {
+                            serde_json::Value::Number(number) => number.to_string(),
+                            _ => text(cell),
+                        })
+                        .collect()
+                })
+                .collect();
+            write_csv(columns, &records)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "csv")]
+fn write_csv(columns: &[String], records: &[Vec<String>]) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(std::io::stdout());
+    writer.write_record(columns)?;
+    for record in records {
+        writer.write_record(record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "csv"))]
+fn write_csv(_columns: &[String], _records: &[Vec<String>]) -> Result<()> {
+    anyhow::bail!("symmetri was built without csv; rebuild with `--features csv`")
+}
+
+fn themed_table() -> Table {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL_CONDENSED)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+    table
+}
+
+fn header_cells(labels: &[&str]) -> Vec<Cell> {
+    labels
+        .iter()
+        .map(|label| {
+            Cell::new(*label)
+                .add_attribute(Attribute::Bold)
+                .fg(Color::Cyan)
+        })
+        .collect()
+}
+
+fn label_cell(text: &str) -> Cell {
+    Cell::new(text).add_attribute(Attribute::Bold)
+}
+
+fn value_cell<T: std::fmt::Display>(value: T) -> Cell {
+    Cell::new(value.to_string()).set_alignment(CellAlignment::Right)
+}
+
+fn status_cell(status: Option<&str>) -> Cell {
+    let status_text = status.unwrap_or("unknown");
+    let color = match status_text.to_ascii_lowercase().as_str() {
+        s if s.contains("charging") && !s.contains("dis") => Color::Green,
+        s if s.contains("discharging") => Color::Yellow,
+        s if s.contains("full") => Color::Blue,
+        _ => Color::White,
+    };
+    Cell::new(status_text).fg(color)
+}
+
+fn format_percent(value: Option<f64>) -> String {
+    value
+        .map(|v| format!("{v:.1}%"))
+        .unwrap_or_else(|| "--".to_string())
+}
+
+fn format_rate(value: Option<f64>) -> String {
+    value
+        .map(|v| format!("{}/s", format_bytes(v)))
+        .unwrap_or_else(|| "--".to_string())
+}
+
+#[derive(Default, Clone)]
+struct NumberStats {
+    total: f64,
+    count: usize,
+    min: f64,
+    max: f64,
+}
+
+impl NumberStats {
+    fn record(&mut self, value: f64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.total += value;
+        self.count += 1;
+    }
+
+    fn record_opt(&mut self, value: Option<f64>) {
+        if let Some(v) = value {
+            self.record(v);
+        }
+    }
+
+    fn average(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.total / self.count as f64)
+    }
+
+    fn min(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    fn max(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.max)
+    }
+}
+
+#[derive(Default, Clone)]
+struct UsageStats {
+    used: NumberStats,
+    percent: NumberStats,
+}
+
+impl UsageStats {
+    fn record(&mut self, used: Option<f64>, total: Option<f64>) {
+        if let Some(used_bytes) = used {
+            self.used.record(used_bytes);
+            if let Some(total_bytes) = total {
+                if total_bytes > 0.0 {
+                    self.percent.record((used_bytes / total_bytes) * 100.0);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+struct RateStats {
+    rx: NumberStats,
+    tx: NumberStats,
+}
+
+impl RateStats {
+    fn record(&mut self, rx: Option<f64>, tx: Option<f64>) {
+        self.rx.record_opt(rx);
+        self.tx.record_opt(tx);
+    }
+}
+
+fn average_for_kind(metrics: &[MetricSample], kind: MetricKind) -> NumberStats {
+    let mut stats = NumberStats::default();
+    for sample in metrics.iter().filter(|s| s.kind == kind) {
+        stats.record_opt(sample.value);
+    }
+    stats
+}
+
+fn filter_metrics_by_source(
+    metrics: &[MetricSample],
+    sensor_filters: &[String],
+) -> Vec<MetricSample> {
+    if sensor_filters.is_empty() {
+        return metrics.to_vec();
+    }
+    metrics
+        .iter()
+        .filter(|m| sensor_filters.iter().any(|f| sensor_matches(f, &m.source)))
+        .cloned()
+        .collect()
+}
+
+/// Keeps disk samples for the given mountpoints; other metrics pass through.
+fn filter_disk_mounts(metrics: &[MetricSample], mounts: &[String]) -> Vec<MetricSample> {
+    if mounts.is_empty() {
+        return metrics.to_vec();
+    }
+    metrics
+        .iter()
+        .filter(|m| {
+            m.kind != MetricKind::DiskUsage
+                || mounts
+                    .iter()
+                    .any(|mount| normalize_mountpoint(mount) == m.source)
+        })
+        .cloned()
+        .collect()
+}
+
+type SourceBuckets = BTreeMap<String, BTreeMap<DateTime<Zone>, NumberStats>>;
+
+fn bucket_stats_for_kind_by_source(
+    metrics: &[MetricSample],
+    kind: MetricKind,
+    bucket_seconds: i64,
+) -> SourceBuckets {
+    let mut buckets: SourceBuckets = BTreeMap::new();
+    for sample in metrics.iter().filter(|s| s.kind == kind) {
+        if let Some(value) = sample.value {
+            let bucket = bucket_start(sample.ts, bucket_seconds);
+            buckets
+                .entry(sample.source.clone())
+                .or_default()
+                .entry(bucket)
+                .or_default()
+                .record(value);
+        }
+    }
+    buckets
+}
+
+#[derive(Default)]
+struct WifiStats {
+    signal: NumberStats,
+    tx_bitrate: NumberStats,
+    ssids: BTreeSet<String>,
+}
+
+fn bucket_wifi_stats(
+    metrics: &[MetricSample],
+    bucket_seconds: i64,
+) -> BTreeMap<String, BTreeMap<DateTime<Zone>, WifiStats>> {
+    let mut buckets: BTreeMap<String, BTreeMap<DateTime<Zone>, WifiStats>> = BTreeMap::new();
+    for sample in metrics.iter().filter(|s| s.kind == MetricKind::Wifi) {
+        let stats = buckets
+            .entry(sample.source.clone())
+            .or_default()
+            .entry(bucket_start(sample.ts, bucket_seconds))
+            .or_default();
+        stats.signal.record_opt(sample.value);
+        stats.tx_bitrate.record_opt(
+            sample
+                .details
+                .get("tx_bitrate_mbps")
+                .and_then(serde_json::Value::as_f64),
+        );
+        if let Some(ssid) = sample
+            .details
+            .get("ssid")
+            .and_then(serde_json::Value::as_str)
+        {
+            stats.ssids.insert(ssid.to_string());
+        }
+    }
+    buckets
+}
+
+fn data_span_seconds(samples: &[Sample], metrics: &[MetricSample]) -> Option<f64> {
+    let mut min_ts = f64::INFINITY;
+    let mut max_ts = f64::NEG_INFINITY;
+
+    for ts in samples
+        .iter()
+        .map(|s| s.ts)
+        .chain(metrics.iter().map(|m| m.ts))
+    {
+        min_ts = min_ts.min(ts);
+        max_ts = max_ts.max(ts);
+    }
+
+    if max_ts.is_finite() && min_ts.is_finite() && max_ts >= min_ts {
+        Some(max_ts - min_ts)
+    } else {
+        None
+    }
+}
+
+fn bucket_stats_for_kind(
+    metrics: &[MetricSample],
+    kind: MetricKind,
+    bucket_seconds: i64,
+) -> BTreeMap<DateTime<Zone>, NumberStats> {
+    let mut buckets: BTreeMap<DateTime<Zone>, NumberStats> = BTreeMap::new();
+    for sample in metrics.iter().filter(|s| s.kind == kind) {
+        if let Some(value) = sample.value {
+            let bucket = bucket_start(sample.ts, bucket_seconds);
+            buckets.entry(bucket).or_default().record(value);
+        }
+    }
+    buckets
+}
+
+#[cfg(test)]
+fn usage_stats_for_kind(metrics: &[MetricSample], kind: MetricKind) -> UsageStats {
+    let mut stats = UsageStats::default();
+    for sample in metrics.iter().filter(|s| s.kind == kind) {
+        let total = number_from_details(sample, "total_bytes");
+        stats.record(sample.value, total);
+    }
+    stats
+}
+
+fn bucket_usage_stats(
+    metrics: &[MetricSample],
+    kind: MetricKind,
+    bucket_seconds: i64,
+) -> BTreeMap<DateTime<Zone>, UsageStats> {
+    let mut buckets: BTreeMap<DateTime<Zone>, UsageStats> = BTreeMap::new();
+    for sample in metrics.iter().filter(|s| s.kind == kind) {
+        let bucket = bucket_start(sample.ts, bucket_seconds);
+        let total = number_from_details(sample, "total_bytes");
+        buckets
+            .entry(bucket)
+            .or_default()
+            .record(sample.value, total);
+    }
+    buckets
+}
+
+/// Per bucket, a short summary of swap and zram use for the memory table's details column.
+fn bucket_memory_details(
+    metrics: &[MetricSample],
+    bucket_seconds: i64,
+) -> BTreeMap<DateTime<Zone>, String> {
+    let mut buckets: BTreeMap<DateTime<Zone>, BTreeMap<&str, (NumberStats, NumberStats)>> =
+        BTreeMap::new();
+    for sample in metrics
+        .iter()
+        .filter(|m| m.kind == MetricKind::MemoryUsage && m.source != MEMORY_SOURCE)
+    {
+        let (used, size) = buckets
+            .entry(bucket_start(sample.ts, bucket_seconds))
+            .or_default()
+            .entry(sample.source.as_str())
+            .or_default();
+        used.record_opt(sample.value);
+        size.record_opt(
+            number_from_details(sample, "total_bytes")
+                .or_else(|| number_from_details(sample, "original_bytes")),
+        );
+    }
+    buckets
+        .into_iter()
+        .map(|(bucket, sources)| {
+            let parts: Vec<String> = sources
+                .iter()
+                .map(|(source, (used, size))| {
+                    let used = format_opt_bytes(used.average());
+                    let size = format_opt_bytes(size.average());
+                    if *source == SWAP_SOURCE {
+                        format!("swap {used}/{size}")
+                    } else {
+                        format!("{source} {size} in {used}")
+                    }
+                })
+                .collect();
+            (bucket, parts.join(", "))
+        })
+        .collect()
+}
+
+struct NetworkRateSample {
+    ts: f64,
+    rx_rate: Option<f64>,
+    tx_rate: Option<f64>,
+}
+
+fn rate_from_counters(previous: Option<f64>, current: Option<f64>, dt: f64) -> Option<f64> {
+    match (previous, current) {
+        (Some(prev), Some(next)) if next >= prev && dt > 0.0 => Some((next - prev) / dt),
+        _ => None,
+    }
+}
+
+fn compute_network_rates(metrics: &[MetricSample]) -> Vec<NetworkRateSample> {
+    let mut by_iface: BTreeMap<&str, Vec<&MetricSample>> = BTreeMap::new();
+    for sample in metrics
+        .iter()
+        .filter(|s| s.kind == MetricKind::NetworkBytes)
+    {
+        by_iface.entry(&sample.source).or_default().push(sample);
+    }
+
+    let mut rates = Vec::new();
+    for (_iface, mut samples) in by_iface {
+        samples.sort_by(|a, b| a.ts.partial_cmp(&b.ts).unwrap());
+        for window in samples.windows(2) {
+            let prev = window[0];
+            let next = window[1];
+            let dt = next.ts - prev.ts;
+            if dt <= 0.0 {
+                continue;
+            }
+            let rx_rate = rate_from_counters(
+                number_from_details(prev, "rx_bytes"),
+                number_from_details(next, "rx_bytes"),
+                dt,
+            );
+            let tx_rate = rate_from_counters(
+                number_from_details(prev, "tx_bytes"),
+                number_from_details(next, "tx_bytes"),
+                dt,
+            );
+            if rx_rate.is_none() && tx_rate.is_none() {
+                continue;
+            }
+            rates.push(NetworkRateSample {
+                ts: next.ts,
+                rx_rate,
+                tx_rate,
+            });
+        }
+    }
+    rates
+}
+
+fn bucket_network_rates(
+    rates: &[NetworkRateSample],
+    bucket_seconds: i64,
+) -> BTreeMap<DateTime<Zone>, RateStats> {
+    let mut buckets: BTreeMap<DateTime<Zone>, RateStats> = BTreeMap::new();
+    for rate in rates {
+        let bucket = bucket_start(rate.ts, bucket_seconds);
+        buckets
+            .entry(bucket)
+            .or_default()
+            .record(rate.rx_rate, rate.tx_rate);
+    }
+    buckets
+}
+
+fn format_freq(value: Option<f64>) -> String {
+    value
+        .map(|v| format!("{v:.0}MHz"))
+        .unwrap_or_else(|| "--".to_string())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct BatterySummary {
+    records: usize,
+    avg_discharge_w: Option<f64>,
+    avg_charge_w: Option<f64>,
+    avg_discharge_pct_h: Option<f64>,
+    avg_charge_pct_h: Option<f64>,
+    energy: EnergyTotals,
+    est_runtime: Option<RuntimeEstimate>,
+    asleep_hours: f64,
+    sleep_loss_pct: Option<f64>,
+    rejected_power: RejectedPower,
+}
+
+impl BatterySummary {
+    fn compute(
+        samples: &[Sample],
+        records: usize,
+        metrics: &[MetricSample],
+        sleeps: &[SleepInterval],
+        runtime_config: &RuntimeConfig,
+        calibration: Option<&Calibration>,
+    ) -> Self {
+        let battery_rates = average_rates_awake(samples, sleeps);
+        let pct_rates = average_pct_rates(samples, sleeps);
+        let power_draw_stats = average_for_kind(metrics, MetricKind::PowerDraw);
+        let avg_discharge_w = power_draw_stats.average().or(battery_rates.discharge_w);
+        let est_runtime = estimate_runtime(
+            runtime_config,
+            samples,
+            metrics,
+            sleeps,
+            avg_discharge_w,
+            calibration,
+        );
+        BatterySummary {
+            records,
+            avg_discharge_w,
+            avg_charge_w: battery_rates.charge_w,
+            avg_discharge_pct_h: pct_rates.discharge_pct_h,
+            avg_charge_pct_h: pct_rates.charge_pct_h,
+            energy: energy_totals(samples),
+            est_runtime,
+            asleep_hours: sleeps.iter().map(|s| s.slept_seconds).sum::<f64>() / 3600.0,
+            sleep_loss_pct: sleeps
+                .iter()
+                .filter_map(SleepInterval::lost_pct)
+                .reduce(|a, b| a + b),
+            rejected_power: RejectedPower::default(),
+        }
+    }
+}
+
+fn battery_summary_table(summary: &BatterySummary, energy: &EnergyConfig) -> Table {
+    let mut table = themed_table();
+    table.set_header(header_cells(&["Metric", "Value"]));
+    table.add_row(vec![
+        label_cell("Records in window"),
+        value_cell(summary.records),
+    ]);
+    table.add_row(vec![
+        label_cell("Avg discharge power"),
+        value_cell(format_power(summary.avg_discharge_w)),
+    ]);
+    table.add_row(vec![
+        label_cell("Avg charge power"),
+        value_cell(format_power(summary.avg_charge_w)),
+    ]);
+    table.add_row(vec![
+        label_cell("Avg drain rate"),
+        value_cell(format_drain_rate(summary.avg_discharge_pct_h)),
+    ]);
+    table.add_row(vec![
+        label_cell("Avg charge rate"),
+        value_cell(format_drain_rate(summary.avg_charge_pct_h)),
+    ]);
+    table.add_row(vec![
+        label_cell("Energy used"),
+        value_cell(format_energy(summary.energy.discharged_wh)),
+    ]);
+    table.add_row(vec![
+        label_cell("Energy charged"),
+        value_cell(format_energy(summary.energy.charged_wh)),
+    ]);
+    if let Some(cost) = energy.cost(summary.energy.charged_wh) {
+        table.add_row(vec![
+            label_cell("Est charging cost"),
+            value_cell(format_cost(cost, &energy.currency)),
+        ]);
+    }
+    table.add_row(vec![
+        label_cell("Est runtime (full)"),
+        value_cell(format_runtime_estimate(summary.est_runtime.as_ref())),
+    ]);
+    if let Some(remaining) = summary.est_runtime.as_ref().and_then(|e| e.remaining_hours) {
+        table.add_row(vec![
+            label_cell("Est time left"),
+            value_cell(format_runtime(Some(remaining))),
+        ]);
+    }
+    if summary.asleep_hours > 0.0 {
+        table.add_row(vec![
+            label_cell("Time asleep"),
+            value_cell(format_runtime(Some(summary.asleep_hours))),
+        ]);
+        table.add_row(vec![
+            label_cell("Lost in sleep"),
+            value_cell(format_percent(summary.sleep_loss_pct)),
+        ]);
+    }
+    let rejected = &summary.rejected_power;
+    if rejected.total() > 0 {
+        table.add_row(vec![
+            label_cell("Rejected power readings"),
+            value_cell(format!(
+                "{} ({} energy, {} power_draw)",
+                rejected.total(),
+                rejected.energy,
+                rejected.power_draw
+            )),
+        ]);
+    }
+    table
+}
+
+const DISTRIBUTION_BINS: usize = 12;
+const DISTRIBUTION_BAR_WIDTH: usize = 30;
+
+/// `plot.png` -> `plot_distribution.png`, next to the main graph.
+fn distribution_graph_path(graph_path: &Path) -> PathBuf {
+    let stem = graph_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "symmetri".to_string());
+    graph_path.with_file_name(format!("{stem}_distribution.png"))
+}
+
+fn distribution_table(hist: &Histogram) -> Table {
+    let max_count = hist.bins.iter().map(|b| b.count).max().unwrap_or(0).max(1);
+    let mut table = themed_table();
+    table.set_header(header_cells(&["Discharge power", "Readings", "Share", ""]));
+    for bin in &hist.bins {
+        let width = (bin.count * DISTRIBUTION_BAR_WIDTH).div_ceil(max_count);
+        table.add_row(vec![
+            label_cell(&format!("{:.1}-{:.1}W", bin.start, bin.end)),
+            value_cell(bin.count),
+            value_cell(format_percent(Some(
+                bin.count as f64 / hist.total as f64 * 100.0,
+            ))),
+            Cell::new("█".repeat(width)).fg(Color::Yellow),
+        ]);
+    }
+    table
+}
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+fn heatmap_section(
+    metric: HeatmapMetric,
+    samples: &[Sample],
+    metrics: &[MetricSample],
+    sleeps: &[SleepInterval],
+    timeframe_label: &str,
+) -> ReportSection {
+    let mut heatmap = WeekHeatmap::default();
+    let (title, unit) = match metric {
+        HeatmapMetric::Power => {
+            for segment in discharge_segments(samples, sleeps) {
+                let midpoint = segment.ts - segment.hours * 1800.0;
+                heatmap.record(midpoint, segment.watts, segment.hours);
+            }
+            ("Avg discharge power", "W")
+        }
+        HeatmapMetric::Cpu => {
+            let usage: Vec<&MetricSample> = metrics
+                .iter()
+                .filter(|m| m.kind == MetricKind::CpuUsage)
+                .collect();
+            // Prefer the all-CPU aggregate; per-core sources would be double counted.
+            let has_total = usage.iter().any(|m| m.source == "cpu");
+            for sample in usage {
+                if let (Some(value), true) = (sample.value, !has_total || sample.source == "cpu") {
+                    heatmap.record(sample.ts, value, 1.0);
+                }
+            }
+            ("Avg CPU usage", "%")
+        }
+    };
+    if heatmap.is_empty() {
+        return ReportSection::note(format!(
+            "No data for the {} heatmap in {timeframe_label}.",
+            title.to_lowercase()
+        ));
+    }
+    ReportSection::table(
+        format!("{title} by weekday and hour ({unit}, {timeframe_label})"),
+        heatmap_table(&heatmap),
+    )
+}
+
+fn heatmap_table(heatmap: &WeekHeatmap) -> Table {
+    let max = heatmap.max().unwrap_or(0.0);
+    let mut table = themed_table();
+    let mut header = vec!["Hour"];
+    header.extend(WEEKDAYS);
+    table.set_header(header_cells(&header));
+    for hour in 0..24 {
+        let mut row = vec![label_cell(&format!("{hour:02}:00"))];
+        for weekday in 0..7 {
+            let cell = match heatmap.average(weekday, hour) {
+                Some(value) => {
+                    let ratio = if max > 0.0 { value / max } else { 0.0 };
+                    let color = match ratio {
+                        r if r >= 0.75 => Color::Red,
+                        r if r >= 0.5 => Color::Yellow,
+                        r if r >= 0.25 => Color::Green,
+                        _ => Color::DarkGrey,
+                    };
+                    value_cell(format!("{value:.1}")).fg(color)
+                }
+                None => value_cell("--"),
+            };
+            row.push(cell);
+        }
+        table.add_row(row);
+    }
+    table
+}
+
+fn format_energy(wh: f64) -> String {
+    format!("{wh:.1}Wh")
+}
+
+fn format_cost(amount: f64, currency: &str) -> String {
+    if currency.is_empty() {
+        format!("{amount:.2}")
+    } else {
+        format!("{amount:.2} {currency}")
+    }
+}
+
+fn energy_by_day_table(days: &BTreeMap<NaiveDate, EnergyTotals>, energy: &EnergyConfig) -> Table {
+    let mut table = themed_table();
+    let mut header = vec!["Day", "Used", "Charged"];
+    if energy.price_per_kwh.is_some() {
+        header.push("Charging cost");
+    }
+    table.set_header(header_cells(&header));
+    for (day, totals) in days {
+        let mut row = vec![
+            label_cell(&locale::current().day(day)),
+            value_cell(format_energy(totals.discharged_wh)),
+            value_cell(format_energy(totals.charged_wh)),
+        ];
+        if let Some(cost) = energy.cost(totals.charged_wh) {
+            row.push(value_cell(format_cost(cost, &energy.currency)));
+        }
+        table.add_row(row);
+    }
+    table
+}
+
+fn format_runtime_estimate(estimate: Option<&RuntimeEstimate>) -> String {
+    let Some(estimate) = estimate else {
+        return "--".to_string();
+    };
+    let hours = format_runtime(Some(estimate.hours));
+    match estimate.margin_hours {
+        Some(margin) if margin.is_finite() => {
+            let minutes = (margin * 60.0).round() as i64;
+            if minutes < 60 {
+                format!("{hours} ± {minutes}m")
+            } else {
+                format!("{hours} ± {}", format_runtime(Some(margin)))
+            }
+        }
+        _ => hours,
+    }
+}
+
+fn delta(current: Option<f64>, previous: Option<f64>) -> Option<f64> {
+    Some(current? - previous?)
+}
+
+fn format_signed(value: Option<f64>, unit: &str) -> String {
+    value
+        .map(|v| format!("{v:+.2}{unit}"))
+        .unwrap_or_else(|| "--".to_string())
+}
+
+fn format_runtime_delta(hours: Option<f64>) -> String {
+    match hours {
+        Some(v) if v.is_finite() => {
+            let sign = if v < 0.0 { "-" } else { "+" };
+            format!("{sign}{}", format_runtime(Some(v.abs())))
+        }
+        _ => "--".to_string(),
+    }
+}
+
+/// `current` and `previous` side by side under `labels`, with `current - previous` deltas.
+fn battery_comparison_table(
+    current: &BatterySummary,
+    previous: &BatterySummary,
+    labels: [&str; 2],
+    energy: &EnergyConfig,
+) -> Table {
+    let mut table = themed_table();
+    table.set_header(header_cells(&["Metric", labels[0], labels[1], "Delta"]));
+    table.add_row(vec![
+        label_cell("Records in window"),
+        value_cell(current.records),
+        value_cell(previous.records),
+        value_cell(format!(
+            "{:+}",
+            current.records as i64 - previous.records as i64
+        )),
+    ]);
+    table.add_row(vec![
+        label_cell("Avg discharge power"),
+        value_cell(format_power(current.avg_discharge_w)),
+        value_cell(format_power(previous.avg_discharge_w)),
+        value_cell(format_signed(
+            delta(current.avg_discharge_w, previous.avg_discharge_w),
+            "W",
+        )),
+    ]);
+    table.add_row(vec![
+        label_cell("Avg charge power"),
+        value_cell(format_power(current.avg_charge_w)),
+        value_cell(format_power(previous.avg_charge_w)),
+        value_cell(format_signed(
+            delta(current.avg_charge_w, previous.avg_charge_w),
+            "W",
+        )),
+    ]);
+    table.add_row(vec![
+        label_cell("Avg drain rate"),
+        value_cell(format_drain_rate(current.avg_discharge_pct_h)),
+        value_cell(format_drain_rate(previous.avg_discharge_pct_h)),
+        value_cell(format_signed(
+            delta(current.avg_discharge_pct_h, previous.avg_discharge_pct_h),
+            "%/h",
+        )),
+    ]);
+    table.add_row(vec![
+        label_cell("Avg charge rate"),
+        value_cell(format_drain_rate(current.avg_charge_pct_h)),
+        value_cell(format_drain_rate(previous.avg_charge_pct_h)),
+        value_cell(format_signed(
+            delta(current.avg_charge_pct_h, previous.avg_charge_pct_h),
+            "%/h",
+        )),
+    ]);
+    table.add_row(vec![
+        label_cell("Energy used"),
+        value_cell(format_energy(current.energy.discharged_wh)),
+        value_cell(format_energy(previous.energy.discharged_wh)),
+        value_cell(format_signed(
+            Some(current.energy.discharged_wh - previous.energy.discharged_wh),
+            "Wh",
+        )),
+    ]);
+    table.add_row(vec![
+        label_cell("Energy charged"),
+        value_cell(format_energy(current.energy.charged_wh)),
+        value_cell(format_energy(previous.energy.charged_wh)),
+        value_cell(format_signed(
+            Some(current.energy.charged_wh - previous.energy.charged_wh),
+            "Wh",
+        )),
+    ]);
+    if let (Some(now), Some(before)) = (
+        energy.cost(current.energy.charged_wh),
+        energy.cost(previous.energy.charged_wh),
+    ) {
+        table.add_row(vec![
+            label_cell("Est charging cost"),
+            value_cell(format_cost(now, &energy.currency)),
+            value_cell(format_cost(before, &energy.currency)),
+            value_cell(format_cost(now - before, &energy.currency)),
+        ]);
+    }
+    table.add_row(vec![
+        label_cell("Est runtime (full)"),
+        value_cell(format_runtime_estimate(current.est_runtime.as_ref())),
+        value_cell(format_runtime_estimate(previous.est_runtime.as_ref())),
+        value_cell(format_runtime_delta(delta(
+            current.est_runtime.as_ref().map(|e| e.hours),
+            previous.est_runtime.as_ref().map(|e| e.hours),
+        ))),
+    ]);
+    if current.asleep_hours > 0.0 || previous.asleep_hours > 0.0 {
+        table.add_row(vec![
+            label_cell("Time asleep"),
+            value_cell(format_runtime(Some(current.asleep_hours))),
+            value_cell(format_runtime(Some(previous.asleep_hours))),
+            value_cell(format_runtime_delta(Some(
+                current.asleep_hours - previous.asleep_hours,
+            ))),
+        ]);
+        table.add_row(vec![
+            label_cell("Lost in sleep"),
+            value_cell(format_percent(current.sleep_loss_pct)),
+            value_cell(format_percent(previous.sleep_loss_pct)),
+            value_cell(format_signed(
+                delta(current.sleep_loss_pct, previous.sleep_loss_pct),
+                "%",
+            )),
+        ]);
+    }
+    table
+}
+
+/// Metric kinds `compare-hosts` reads: power for the battery summary, the rest for the
+/// system table.
+const HOST_METRIC_KINDS: [MetricKind; 6] = [
+    MetricKind::PowerDraw,
+    MetricKind::CpuUsage,
+    MetricKind::MemoryUsage,
+    MetricKind::SystemLoad,
+    MetricKind::Temperature,
+    // Brightness, for the load runtime model.
+    MetricKind::Display,
+];
+
+/// One side of `compare-hosts`.
+struct HostSummary {
+    label: String,
+    path: PathBuf,
+    end_ts: f64,
+    battery: BatterySummary,
+    metrics: Vec<MetricSample>,
+}
+
+/// The battery summary and system metrics of the database at `path` over the `seconds`
+/// (everything when `None`) ending at its newest sample rather than now, so a database
+/// from before a reinstall is compared over the same length of history.
+fn host_summary(
+    path: &Path,
+    label: String,
+    seconds: Option<f64>,
+    config: &Config,
+) -> Result<HostSummary> {
+    if !path.exists() {
+        anyhow::bail!("No database at {}", path.display());
+    }
+    let Some(end_ts) = db::latest_ts(path)? else {
+        return Err(Exit::new(
+            ExitStatus::NoData,
+            format!("{} has no samples yet", path.display()),
+        )
+        .into());
+    };
+    let since = seconds.map(|seconds| end_ts - seconds);
+    let mut records = 0;
+    let mut samples = Vec::new();
+    let mut cursor = db::sample_cursor(path, since, None)?;
+    while let Some(page) = cursor.next_page()? {
+        records += page.len();
+        samples.extend(aggregate_samples_by_timestamp(&page));
+    }
+    let mut metrics = db::metric_cursor(path, since, None, Some(&HOST_METRIC_KINDS))?
+        .collect::<Result<Vec<_>>>()?;
+    sensors::apply_renames(&mut metrics, &config.sensors);
+    let sleeps = db::fetch_sleep_intervals(path, since, None)?;
+    smooth_percentages(&mut samples, &config.smoothing);
+    let rejected_power = reject_power_outliers(&mut samples, &mut metrics, &config.power);
+    let calibration = match samples.last() {
+        Some(latest) => db::fetch_calibration(path, &latest.source_path)?,
+        None => None,
+    };
+    let mut battery = BatterySummary::compute(
+        &samples,
+        records,
+        &metrics,
+        &sleeps,
+        &config.runtime,
+        calibration.as_ref(),
+    );
+    battery.rejected_power = rejected_power;
+    Ok(HostSummary {
+        label,
+        path: path.to_path_buf(),
+        end_ts,
+        battery,
+        metrics,
+    })
+}
+
+/// CPU, memory, load and temperature of two hosts side by side, with `a - b` deltas.
+/// Rows neither database has data for are left out.
+fn host_system_table(a: &HostSummary, b: &HostSummary) -> Table {
+    let stats = |host: &HostSummary, kind: MetricKind, source: Option<&str>| {
+        let mut stats = NumberStats::default();
+        for sample in host
+            .metrics
+            .iter()
+            .filter(|m| m.kind == kind && source.is_none_or(|source| m.source == source))
+        {
+            stats.record_opt(sample.value);
+        }
+        stats
+    };
+    let mut table = themed_table();
+    table.set_header(header_cells(&["Metric", &a.label, &b.label, "Delta"]));
+    let mut add_row = |label: &str,
+                       values: [Option<f64>; 2],
+                       format: fn(f64) -> String,
+                       format_delta: fn(f64) -> String| {
+        let [first, second] = values;
+        if first.is_none() && second.is_none() {
+            return;
+        }
+        let cell = |value: Option<f64>| value.map(format).unwrap_or_else(|| "--".to_string());
+        table.add_row(vec![
+            label_cell(label),
+            value_cell(cell(first)),
+            value_cell(cell(second)),
+            value_cell(
+                delta(first, second)
+                    .map(format_delta)
+                    .unwrap_or_else(|| "--".to_string()),
+            ),
+        ]);
+    };
+    add_row(
+        "Avg CPU usage",
+        [a, b].map(|host| stats(host, MetricKind::CpuUsage, Some("cpu")).average()),
+        |value| format_percent(Some(value)),
+        |value| format_signed(Some(value), "%"),
+    );
+    add_row(
+        "Avg memory used",
+        [a, b].map(|host| stats(host, MetricKind::MemoryUsage, Some(MEMORY_SOURCE)).average()),
+        format_bytes,
+        |value| {
+            let sign = if value < 0.0 { "-" } else { "+" };
+            format!("{sign}{}", format_bytes(value.abs()))
+        },
+    );
+    add_row(
+        "Avg load (1 min)",
+        [a, b].map(|host| stats(host, MetricKind::SystemLoad, Some("load1")).average()),
+        |value| format!("{value:.2}"),
+        |value| format_signed(Some(value), ""),
+    );
+    add_row(
+        "Peak temperature",
+        [a, b].map(|host| stats(host, MetricKind::Temperature, None).max()),
+        |value| format!("{value:.1}°C"),
+        |value| format_signed(Some(value), "°C"),
+    );
+    table
+}
+
+/// Profile changes over time, preferring the platform profile over the cpufreq governor.
+fn profile_timeline(metrics: &[MetricSample]) -> Vec<(f64, String)> {
+    let pick = |source: &str| -> Vec<(f64, String)> {
+        let mut timeline: Vec<(f64, String)> = metrics
+            .iter()
+            .filter(|m| m.kind == MetricKind::PowerProfile && m.source == source)
+            .filter_map(|m| {
+                let profile = m.details.get("profile")?.as_str()?;
+                Some((m.ts, profile.to_string()))
+            })
+            .collect();
+        timeline.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        timeline
+    };
+    let platform = pick(PLATFORM_PROFILE_SOURCE);
+    if platform.is_empty() {
+        pick(CPU_GOVERNOR_SOURCE)
+    } else {
+        platform
+    }
+}
+
+fn profile_at(timeline: &[(f64, String)], ts: f64) -> Option<&str> {
+    let idx = timeline.partition_point(|(profile_ts, _)| *profile_ts <= ts);
+    idx.checked_sub(1).map(|i| timeline[i].1.as_str())
+}
+
+#[derive(Default)]
+struct ProfileStats {
+    records: usize,
+    power_draw: NumberStats,
+    discharge_w: Option<f64>,
+    charge_w: Option<f64>,
+}
+
+impl ProfileStats {
+    fn avg_discharge_w(&self) -> Option<f64> {
+        self.power_draw.average().or(self.discharge_w)
+    }
+}
+
+fn profile_breakdown(
+    samples: &[Sample],
+    metrics: &[MetricSample],
+) -> BTreeMap<String, ProfileStats> {
+    let timeline = profile_timeline(metrics);
+    let mut stats: BTreeMap<String, ProfileStats> = BTreeMap::new();
+    if timeline.is_empty() {
+        return stats;
+    }
+    for sample in samples {
+        if let Some(profile) = profile_at(&timeline, sample.ts) {
+            stats.entry(profile.to_string()).or_default().records += 1;
+        }
+    }
+    for sample in metrics.iter().filter(|m| m.kind == MetricKind::PowerDraw) {
+        if let (Some(profile), Some(value)) = (profile_at(&timeline, sample.ts), sample.value) {
+            stats
+                .entry(profile.to_string())
+                .or_default()
+                .power_draw
+                .record(value);
+        }
+    }
+    let rates = average_rates_by(samples, |s| profile_at(&timeline, s.ts).map(str::to_string));
+    for (profile, rate) in rates {
+        let entry = stats.entry(profile).or_default();
+        entry.discharge_w = rate.discharge_w;
+        entry.charge_w = rate.charge_w;
+    }
+    stats
+}
+
+fn profile_breakdown_table(profiles: &BTreeMap<String, ProfileStats>) -> Table {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Profile",
+        "Records",
+        "Avg discharge W",
+        "Avg charge W",
+    ]));
+    for (profile, stats) in profiles {
+        report.add_row(vec![
+            label_cell(profile),
+            value_cell(stats.records),
+            value_cell(format_power(stats.avg_discharge_w())),
+            value_cell(format_power(stats.charge_w)),
+        ]);
+    }
+    report
+}
+
+#[derive(Default)]
+struct UsbStats {
+    collections: usize,
+    active: usize,
+    max_power_ma: NumberStats,
+    /// Average battery rates while the device was plugged in (`true`) and while it was not.
+    rates: BTreeMap<bool, AverageRates>,
+}
+
+fn usb_device_stats(samples: &[Sample], metrics: &[MetricSample]) -> BTreeMap<String, UsbStats> {
+    let mut collections: BTreeMap<OrderedFloat<f64>, BTreeSet<&str>> = BTreeMap::new();
+    let mut devices: BTreeMap<String, UsbStats> = BTreeMap::new();
+    for sample in metrics.iter().filter(|m| m.kind == MetricKind::Usb) {
+        collections
+            .entry(OrderedFloat(sample.ts))
+            .or_default()
+            .insert(&sample.source);
+        let stats = devices.entry(sample.source.clone()).or_default();
+        stats.collections += 1;
+        stats.active += usize::from(usb::is_active(sample));
+        stats.max_power_ma.record_opt(sample.value);
+    }
+    let timeline: Vec<(f64, BTreeSet<&str>)> = collections
+        .into_iter()
+        .map(|(ts, devices)| (ts.0, devices))
+        .collect();
+    let connected_at = |ts: f64| {
+        let idx = timeline.partition_point(|(usb_ts, _)| *usb_ts <= ts);
+        idx.checked_sub(1).map(|i| &timeline[i].1)
+    };
+    for (device, stats) in devices.iter_mut() {
+        stats.rates = average_rates_by(samples, |s| {
+            connected_at(s.ts).map(|connected| connected.contains(device.as_str()))
+        });
+    }
+    devices
+}
+
+fn usb_devices_table(devices: &BTreeMap<String, UsbStats>) -> Table {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Device",
+        "Collections",
+        "Active",
+        "Max power",
+        "Drain plugged in",
+        "Drain without",
+    ]));
+    for (device, stats) in devices {
+        let drain =
+            |connected: bool| format_power(stats.rates.get(&connected).and_then(|r| r.discharge_w));
+        report.add_row(vec![
+            label_cell(device),
+            value_cell(stats.collections),
+            value_cell(format_percent(Some(
+                stats.active as f64 / stats.collections as f64 * 100.0,
+            ))),
+            value_cell(
+                stats
+                    .max_power_ma
+                    .max()
+                    .map(|ma| format!("{ma:.0} mA"))
+                    .unwrap_or_else(|| "--".to_string()),
+            ),
+            value_cell(drain(true)),
+            value_cell(drain(false)),
+        ]);
+    }
+    report
+}
+
+/// Duty cycle (share of collections active) of audio playback, microphone and webcam.
+#[derive(Default)]
+struct MediaDay {
+    playback: NumberStats,
+    capture: NumberStats,
+    webcam: NumberStats,
+}
+
+fn media_by_day(metrics: &[MetricSample]) -> BTreeMap<NaiveDate, MediaDay> {
+    let mut days: BTreeMap<NaiveDate, MediaDay> = BTreeMap::new();
+    for sample in metrics.iter().filter(|m| m.kind == MetricKind::Media) {
+        let Some(day) = locale::current().datetime(sample.ts) else {
+            continue;
+        };
+        let entry = days.entry(day.date_naive()).or_default();
+        let stats = match sample.source.as_str() {
+            AUDIO_PLAYBACK_SOURCE => &mut entry.playback,
+            AUDIO_CAPTURE_SOURCE => &mut entry.capture,
+            WEBCAM_SOURCE => &mut entry.webcam,
+            _ => continue,
+        };
+        stats.record_opt(sample.value.map(|active| active * 100.0));
+    }
+    days
+}
+
+fn media_by_day_table(
+    days: &BTreeMap<NaiveDate, MediaDay>,
+    energy: &BTreeMap<NaiveDate, EnergyTotals>,
+) -> Table {
+    let mut table = themed_table();
+    table.set_header(header_cells(&[
+        "Day",
+        "Audio",
+        "Microphone",
+        "Webcam",
+        "Used",
+    ]));
+    for (day, media) in days {
+        table.add_row(vec![
+            label_cell(&locale::current().day(day)),
+            value_cell(format_percent(media.playback.average())),
+            value_cell(format_percent(media.capture.average())),
+            value_cell(format_percent(media.webcam.average())),
+            value_cell(
+                energy
+                    .get(day)
+                    .map(|totals| format_energy(totals.discharged_wh))
+                    .unwrap_or_else(|| "--".to_string()),
+            ),
+        ]);
+    }
+    table
+}
+
+/// What the machine was doing media-wise after each collection: a call (microphone or
+/// webcam in use), audio playback, or neither.
+fn media_timeline(metrics: &[MetricSample]) -> Vec<(f64, String)> {
+    let mut collections: BTreeMap<OrderedFloat<f64>, (bool, bool)> = BTreeMap::new();
+    for sample in metrics.iter().filter(|m| m.kind == MetricKind::Media) {
+        let active = sample.value.is_some_and(|v| v > 0.0);
+        let (call, playback) = collections.entry(OrderedFloat(sample.ts)).or_default();
+        match sample.source.as_str() {
+            AUDIO_CAPTURE_SOURCE | WEBCAM_SOURCE => *call |= active,
+            AUDIO_PLAYBACK_SOURCE => *playback |= active,
+            _ => {}
+        }
+    }
+    collections
+        .into_iter()
+        .map(|(ts, (call, playback))| {
+            let label = match (call, playback) {
+                (true, _) => "Call",
+                (false, true) => "Audio playback",
+                (false, false) => "Idle",
+            };
+            (ts.0, label.to_string())
+        })
+        .collect()
+}
+
+const NO_WIFI: &str = "No Wi-Fi";
+
+/// The place after each collection with wifi samples: the `[location]` name of the
+/// connected SSID, the SSID itself, or [`NO_WIFI`].
+fn location_timeline(metrics: &[MetricSample], location: &LocationConfig) -> Vec<(f64, String)> {
+    let mut collections: BTreeMap<OrderedFloat<f64>, Option<&str>> = BTreeMap::new();
+    for sample in metrics.iter().filter(|m| m.kind == MetricKind::Wifi) {
+        let ssid = sample.details.get("ssid").and_then(|ssid| ssid.as_str());
+        let entry = collections.entry(OrderedFloat(sample.ts)).or_default();
+        *entry = entry.or(ssid);
+    }
+    collections
+        .into_iter()
+        .map(|(ts, ssid)| {
+            (
+                ts.0,
+                ssid.map_or(NO_WIFI, |ssid| location.place(ssid))
+                    .to_string(),
+            )
+        })
+        .collect()
+}
+
+/// Time at each place (readings at most 15 minutes apart) and the average discharge there.
+fn location_table(places: &[(f64, String)], rates: &BTreeMap<String, AverageRates>) -> Table {
+    const MAX_GAP_SECONDS: f64 = 15.0 * 60.0;
+    let mut hours: BTreeMap<&str, f64> = BTreeMap::new();
+    for pair in places.windows(2) {
+        let gap = pair[1].0 - pair[0].0;
+        let entry = hours.entry(pair[0].1.as_str()).or_default();
+        if gap > 0.0 && gap <= MAX_GAP_SECONDS {
+            *entry += gap / 3600.0;
+        }
+    }
+    let mut table = themed_table();
+    table.set_header(header_cells(&["Location", "Time", "Avg discharge W"]));
+    for (place, hours) in hours {
+        table.add_row(vec![
+            label_cell(place),
+            value_cell(format_runtime(Some(hours))),
+            value_cell(format_power(rates.get(place).and_then(|r| r.discharge_w))),
+        ]);
+    }
+    table
+}
+
+/// Screen-on hours per day, with the average discharge while the screen was on, while it
+/// was off, and overall.
+fn screen_time_table(
+    hours: &BTreeMap<NaiveDate, f64>,
+    by_state: &BTreeMap<(NaiveDate, bool), AverageRates>,
+    overall: &BTreeMap<NaiveDate, AverageRates>,
+) -> Table {
+    let mut table = themed_table();
+    table.set_header(header_cells(&[
+        "Day",
+        "Screen on",
+        "Drain screen on",
+        "Drain screen off",
+        "Drain overall",
+    ]));
+    for (day, hours) in hours {
+        let drain = |on: bool| format_power(by_state.get(&(*day, on)).and_then(|r| r.discharge_w));
+        table.add_row(vec![
+            label_cell(&locale::current().day(day)),
+            value_cell(format_runtime(Some(*hours))),
+            value_cell(drain(true)),
+            value_cell(drain(false)),
+            value_cell(format_power(overall.get(day).and_then(|r| r.discharge_w))),
+        ]);
+    }
+    table
+}
+
+fn media_drain_table(rates: &BTreeMap<String, AverageRates>) -> Table {
+    let mut report = themed_table();
+    report.set_header(header_cells(&["Activity", "Avg discharge W"]));
+    for (activity, rate) in rates {
+        report.add_row(vec![
+            label_cell(activity),
+            value_cell(format_power(rate.discharge_w)),
+        ]);
+    }
+    report
 }
 
 fn battery_stats_table(
     samples: &[Sample],
-    power_draw: &BTreeMap<DateTime<Local>, NumberStats>,
+    power_draw: &BTreeMap<DateTime<Zone>, NumberStats>,
     bucket_seconds: i64,
 ) -> Table {
-    let mut buckets: BTreeMap<DateTime<Local>, Vec<&Sample>> = BTreeMap::new();
+    let mut buckets: BTreeMap<DateTime<Zone>, Vec<&Sample>> = BTreeMap::new();
     for sample in samples {
         let bucket_key = bucket_start(sample.ts, bucket_seconds);
         buckets.entry(bucket_key).or_default().push(sample);
@@ -752,301 +5098,1267 @@ fn battery_stats_table(
     let mut report = themed_table();
     report.set_header(header_cells(&[
         "Window",
-        "Records",
-        "Min %",
-        "Avg %",
-        "Max %",
-        "Avg discharge W",
-        "Avg charge W",
-        "Latest status",
+        "Records",
+        "Min %",
+        "Avg %",
+        "Max %",
+        "Avg discharge W",
+        "Avg charge W",
+        "Drain %/h",
+        "Charge %/h",
+        "Latest status",
+    ]));
+
+    for (bucket_start, bucket_samples) in buckets {
+        let pct_values: Vec<f64> = bucket_samples.iter().filter_map(|s| s.percentage).collect();
+        let (min_pct, avg_pct, max_pct) = pct_stats(&pct_values);
+        let latest_status = bucket_samples
+            .last()
+            .and_then(|s| s.status.as_deref())
+            .unwrap_or("unknown");
+        let rates = average_rates(bucket_samples.iter().copied());
+        let pct_rates = average_pct_rates(bucket_samples.iter().copied(), &[]);
+        let draw = power_draw
+            .get(&bucket_start)
+            .and_then(NumberStats::average)
+            .or(rates.discharge_w);
+        report.add_row(vec![
+            Cell::new(format_bucket(bucket_start, bucket_seconds))
+                .fg(Color::Magenta)
+                .add_attribute(Attribute::Bold),
+            value_cell(bucket_samples.len()),
+            value_cell(min_pct),
+            value_cell(avg_pct),
+            value_cell(max_pct),
+            value_cell(format_power(draw)),
+            value_cell(format_power(rates.charge_w)),
+            value_cell(format_drain_rate(pct_rates.discharge_pct_h)),
+            value_cell(format_drain_rate(pct_rates.charge_pct_h)),
+            status_cell(Some(latest_status)),
+        ]);
+    }
+    report
+}
+
+fn cpu_stats_table(bucket_seconds: i64, usage: &SourceBuckets, freq: &SourceBuckets) -> Table {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Source",
+        "Window",
+        "Samples",
+        "Min usage",
+        "Avg usage",
+        "Peak usage",
+        "Min freq",
+        "Avg freq",
+        "Peak freq",
+    ]));
+
+    let mut sources: Vec<&String> = usage.keys().chain(freq.keys()).collect();
+    sources.sort();
+    sources.dedup();
+
+    for source in sources {
+        let usage_buckets = usage.get(source);
+        let freq_buckets = freq.get(source);
+        let mut keys: Vec<DateTime<Zone>> = usage_buckets
+            .into_iter()
+            .flat_map(|m| m.keys().copied())
+            .chain(freq_buckets.into_iter().flat_map(|m| m.keys().copied()))
+            .collect();
+        keys.sort();
+        keys.dedup();
+
+        for key in keys {
+            let usage_stats = usage_buckets
+                .and_then(|map| map.get(&key).cloned())
+                .unwrap_or_default();
+            let freq_stats = freq_buckets
+                .and_then(|map| map.get(&key).cloned())
+                .unwrap_or_default();
+            let samples = usage_stats.count.max(freq_stats.count);
+            report.add_row(vec![
+                label_cell(source),
+                Cell::new(format_bucket(key, bucket_seconds))
+                    .fg(Color::Magenta)
+                    .add_attribute(Attribute::Bold),
+                value_cell(samples),
+                value_cell(format_percent(usage_stats.min())),
+                value_cell(format_percent(usage_stats.average())),
+                value_cell(format_percent(usage_stats.max())),
+                value_cell(format_freq(freq_stats.min())),
+                value_cell(format_freq(freq_stats.average())),
+                value_cell(format_freq(freq_stats.max())),
+            ]);
+        }
+    }
+    report
+}
+
+fn gpu_stats_table(bucket_seconds: i64, usage: &SourceBuckets, freq: &SourceBuckets) -> Table {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Source",
+        "Window",
+        "Samples",
+        "Min usage",
+        "Avg usage",
+        "Peak usage",
+        "Min freq",
+        "Avg freq",
+        "Peak freq",
+    ]));
+
+    let mut sources: Vec<&String> = usage.keys().chain(freq.keys()).collect();
+    sources.sort();
+    sources.dedup();
+
+    for source in sources {
+        let usage_buckets = usage.get(source);
+        let freq_buckets = freq.get(source);
+        let mut keys: Vec<DateTime<Zone>> = usage_buckets
+            .into_iter()
+            .flat_map(|m| m.keys().copied())
+            .chain(freq_buckets.into_iter().flat_map(|m| m.keys().copied()))
+            .collect();
+        keys.sort();
+        keys.dedup();
+
+        for key in keys {
+            let usage_stats = usage_buckets
+                .and_then(|map| map.get(&key).cloned())
+                .unwrap_or_default();
+            let freq_stats = freq_buckets
+                .and_then(|map| map.get(&key).cloned())
+                .unwrap_or_default();
+            let samples = usage_stats.count.max(freq_stats.count);
+            report.add_row(vec![
+                label_cell(source),
+                Cell::new(format_bucket(key, bucket_seconds))
+                    .fg(Color::Magenta)
+                    .add_attribute(Attribute::Bold),
+                value_cell(samples),
+                value_cell(format_percent(usage_stats.min())),
+                value_cell(format_percent(usage_stats.average())),
+                value_cell(format_percent(usage_stats.max())),
+                value_cell(format_freq(freq_stats.min())),
+                value_cell(format_freq(freq_stats.average())),
+                value_cell(format_freq(freq_stats.max())),
+            ]);
+        }
+    }
+    report
+}
+
+fn memory_stats_table(
+    bucket_seconds: i64,
+    buckets: &BTreeMap<DateTime<Zone>, UsageStats>,
+    details: &BTreeMap<DateTime<Zone>, String>,
+) -> Table {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Window",
+        "Samples",
+        "Min used",
+        "Avg used",
+        "Min used %",
+        "Avg used %",
+        "Peak used %",
+        "Details",
+    ]));
+
+    for (key, stats) in buckets {
+        report.add_row(vec![
+            Cell::new(format_bucket(*key, bucket_seconds))
+                .fg(Color::Magenta)
+                .add_attribute(Attribute::Bold),
+            value_cell(stats.used.count),
+            value_cell(format_opt_bytes(stats.used.min())),
+            value_cell(format_opt_bytes(stats.used.average())),
+            value_cell(format_percent(stats.percent.min())),
+            value_cell(format_percent(stats.percent.average())),
+            value_cell(format_percent(stats.percent.max())),
+            Cell::new(details.get(key).map_or("--", String::as_str)),
+        ]);
+    }
+    report
+}
+
+fn disk_stats_table(
+    bucket_seconds: i64,
+    buckets: &BTreeMap<String, BTreeMap<DateTime<Zone>, UsageStats>>,
+) -> Table {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Mount",
+        "Window",
+        "Samples",
+        "Min used",
+        "Avg used",
+        "Min used %",
+        "Avg used %",
+        "Peak used %",
+    ]));
+
+    for (mount, readings) in buckets {
+        for (key, stats) in readings {
+            report.add_row(vec![
+                label_cell(mount),
+                Cell::new(format_bucket(*key, bucket_seconds))
+                    .fg(Color::Magenta)
+                    .add_attribute(Attribute::Bold),
+                value_cell(stats.used.count),
+                value_cell(format_opt_bytes(stats.used.min())),
+                value_cell(format_opt_bytes(stats.used.average())),
+                value_cell(format_percent(stats.percent.min())),
+                value_cell(format_percent(stats.percent.average())),
+                value_cell(format_percent(stats.percent.max())),
+            ]);
+        }
+    }
+    report
+}
+
+#[derive(Default)]
+struct CgroupStats {
+    cpu: NumberStats,
+    memory: NumberStats,
+    read: NumberStats,
+    write: NumberStats,
+}
+
+fn cgroup_stats_table(bucket_seconds: i64, usage: &BTreeMap<String, Vec<CgroupUsage>>) -> Table {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Cgroup",
+        "Window",
+        "Samples",
+        "Avg CPU",
+        "Peak CPU",
+        "Avg memory",
+        "Read",
+        "Write",
+    ]));
+
+    for (cgroup, readings) in usage {
+        let mut buckets: BTreeMap<DateTime<Zone>, CgroupStats> = BTreeMap::new();
+        for reading in readings {
+            let stats = buckets
+                .entry(bucket_start(reading.ts, bucket_seconds))
+                .or_default();
+            stats.cpu.record_opt(reading.cpu_pct);
+            stats.memory.record_opt(reading.memory_bytes);
+            stats.read.record_opt(reading.read_bytes_per_sec);
+            stats.write.record_opt(reading.write_bytes_per_sec);
+        }
+        for (key, stats) in &buckets {
+            report.add_row(vec![
+                label_cell(cgroup),
+                Cell::new(format_bucket(*key, bucket_seconds))
+                    .fg(Color::Magenta)
+                    .add_attribute(Attribute::Bold),
+                value_cell(stats.cpu.count),
+                value_cell(format_percent(stats.cpu.average())),
+                value_cell(format_percent(stats.cpu.max())),
+                value_cell(format_opt_bytes(stats.memory.average())),
+                value_cell(format_rate(stats.read.average())),
+                value_cell(format_rate(stats.write.average())),
+            ]);
+        }
+    }
+    report
+}
+
+fn load_stats_table(bucket_seconds: i64, buckets: &SourceBuckets) -> Table {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Window",
+        "Samples",
+        "Load 1m",
+        "Load 5m",
+        "Load 15m",
+        "Peak 1m",
+        "Ctx switches/s",
+        "Interrupts/s",
     ]));
 
-    for (bucket_start, bucket_samples) in buckets {
-        let pct_values: Vec<f64> = bucket_samples.iter().filter_map(|s| s.percentage).collect();
-        let (min_pct, avg_pct, max_pct) = pct_stats(&pct_values);
-        let latest_status = bucket_samples
-            .last()
-            .and_then(|s| s.status.as_deref())
-            .unwrap_or("unknown");
-        let rates = average_rates(bucket_samples.iter().copied());
-        let draw = power_draw
-            .get(&bucket_start)
-            .and_then(NumberStats::average)
-            .or(rates.discharge_w);
+    let windows: BTreeSet<DateTime<Zone>> = buckets
+        .values()
+        .flat_map(|readings| readings.keys().copied())
+        .collect();
+    let stats = |source: &str, key: &DateTime<Zone>| {
+        buckets.get(source).and_then(|readings| readings.get(key))
+    };
+    let fixed = |value: Option<f64>, digits: usize| {
+        value
+            .map(|v| format!("{v:.digits$}"))
+            .unwrap_or_else(|| "--".to_string())
+    };
+    let [load1, load5, load15] = LOAD_SOURCES;
+    for key in &windows {
+        let average = |source: &str| stats(source, key).and_then(NumberStats::average);
         report.add_row(vec![
-            Cell::new(format_bucket(bucket_start, bucket_seconds))
+            Cell::new(format_bucket(*key, bucket_seconds))
                 .fg(Color::Magenta)
                 .add_attribute(Attribute::Bold),
-            value_cell(bucket_samples.len()),
-            value_cell(min_pct),
-            value_cell(avg_pct),
-            value_cell(max_pct),
-            value_cell(format_power(draw)),
-            value_cell(format_power(rates.charge_w)),
-            status_cell(Some(latest_status)),
+            value_cell(stats(load1, key).map_or(0, |s| s.count)),
+            value_cell(fixed(average(load1), 2)),
+            value_cell(fixed(average(load5), 2)),
+            value_cell(fixed(average(load15), 2)),
+            value_cell(fixed(stats(load1, key).and_then(NumberStats::max), 2)),
+            value_cell(fixed(average(CONTEXT_SWITCHES_SOURCE), 0)),
+            value_cell(fixed(average(INTERRUPTS_SOURCE), 0)),
         ]);
     }
     report
 }
 
-fn cpu_stats_table(bucket_seconds: i64, usage: &SourceBuckets, freq: &SourceBuckets) -> Table {
+fn temperature_stats_table(bucket_seconds: i64, buckets: &SourceBuckets) -> Table {
     let mut report = themed_table();
     report.set_header(header_cells(&[
         "Source",
         "Window",
         "Samples",
-        "Min usage",
-        "Avg usage",
-        "Peak usage",
-        "Min freq",
-        "Avg freq",
-        "Peak freq",
+        "Min temp",
+        "Avg temp",
+        "Peak temp",
     ]));
 
-    let mut sources: Vec<&String> = usage.keys().chain(freq.keys()).collect();
-    sources.sort();
-    sources.dedup();
-
-    for source in sources {
-        let usage_buckets = usage.get(source);
-        let freq_buckets = freq.get(source);
-        let mut keys: Vec<DateTime<Local>> = usage_buckets
-            .into_iter()
-            .flat_map(|m| m.keys().copied())
-            .chain(freq_buckets.into_iter().flat_map(|m| m.keys().copied()))
-            .collect();
-        keys.sort();
-        keys.dedup();
-
-        for key in keys {
-            let usage_stats = usage_buckets
-                .and_then(|map| map.get(&key).cloned())
-                .unwrap_or_default();
-            let freq_stats = freq_buckets
-                .and_then(|map| map.get(&key).cloned())
-                .unwrap_or_default();
-            let samples = usage_stats.count.max(freq_stats.count);
+    for (source, readings) in buckets {
+        for (key, stats) in readings {
             report.add_row(vec![
                 label_cell(source),
-                Cell::new(format_bucket(key, bucket_seconds))
+                Cell::new(format_bucket(*key, bucket_seconds))
                     .fg(Color::Magenta)
                     .add_attribute(Attribute::Bold),
-                value_cell(samples),
-                value_cell(format_percent(usage_stats.min())),
-                value_cell(format_percent(usage_stats.average())),
-                value_cell(format_percent(usage_stats.max())),
-                value_cell(format_freq(freq_stats.min())),
-                value_cell(format_freq(freq_stats.average())),
-                value_cell(format_freq(freq_stats.max())),
+                value_cell(stats.count),
+                value_cell(
+                    stats
+                        .min()
+                        .map(|v| format!("{v:.1}C"))
+                        .unwrap_or_else(|| "--".to_string()),
+                ),
+                value_cell(
+                    stats
+                        .average()
+                        .map(|v| format!("{v:.1}C"))
+                        .unwrap_or_else(|| "--".to_string()),
+                ),
+                value_cell(
+                    stats
+                        .max()
+                        .map(|v| format!("{v:.1}C"))
+                        .unwrap_or_else(|| "--".to_string()),
+                ),
             ]);
         }
     }
     report
 }
 
-fn gpu_stats_table(bucket_seconds: i64, usage: &SourceBuckets, freq: &SourceBuckets) -> Table {
+fn wifi_stats_table(
+    bucket_seconds: i64,
+    buckets: &BTreeMap<String, BTreeMap<DateTime<Zone>, WifiStats>>,
+) -> Table {
     let mut report = themed_table();
     report.set_header(header_cells(&[
-        "Source",
+        "Interface",
         "Window",
         "Samples",
-        "Min usage",
-        "Avg usage",
-        "Peak usage",
-        "Min freq",
-        "Avg freq",
-        "Peak freq",
+        "Avg signal",
+        "Weakest",
+        "Avg TX rate",
+        "SSID",
     ]));
 
-    let mut sources: Vec<&String> = usage.keys().chain(freq.keys()).collect();
-    sources.sort();
-    sources.dedup();
-
-    for source in sources {
-        let usage_buckets = usage.get(source);
-        let freq_buckets = freq.get(source);
-        let mut keys: Vec<DateTime<Local>> = usage_buckets
-            .into_iter()
-            .flat_map(|m| m.keys().copied())
-            .chain(freq_buckets.into_iter().flat_map(|m| m.keys().copied()))
-            .collect();
-        keys.sort();
-        keys.dedup();
-
-        for key in keys {
-            let usage_stats = usage_buckets
-                .and_then(|map| map.get(&key).cloned())
-                .unwrap_or_default();
-            let freq_stats = freq_buckets
-                .and_then(|map| map.get(&key).cloned())
-                .unwrap_or_default();
-            let samples = usage_stats.count.max(freq_stats.count);
+    let format = |value: Option<f64>, unit: &str| {
+        value
+            .map(|v| format!("{v:.0} {unit}"))
+            .unwrap_or_else(|| "--".to_string())
+    };
+    for (interface, readings) in buckets {
+        for (key, stats) in readings {
+            let ssids = stats.ssids.iter().cloned().collect::<Vec<_>>().join(", ");
             report.add_row(vec![
-                label_cell(source),
-                Cell::new(format_bucket(key, bucket_seconds))
+                label_cell(interface),
+                Cell::new(format_bucket(*key, bucket_seconds))
                     .fg(Color::Magenta)
                     .add_attribute(Attribute::Bold),
-                value_cell(samples),
-                value_cell(format_percent(usage_stats.min())),
-                value_cell(format_percent(usage_stats.average())),
-                value_cell(format_percent(usage_stats.max())),
-                value_cell(format_freq(freq_stats.min())),
-                value_cell(format_freq(freq_stats.average())),
-                value_cell(format_freq(freq_stats.max())),
+                value_cell(stats.signal.count),
+                value_cell(format(stats.signal.average(), "dBm")),
+                value_cell(format(stats.signal.min(), "dBm")),
+                value_cell(format(stats.tx_bitrate.average(), "Mbit/s")),
+                label_cell(if ssids.is_empty() { "--" } else { &ssids }),
             ]);
         }
     }
-    report
+    report
+}
+
+fn network_stats_table(
+    bucket_seconds: i64,
+    buckets: &BTreeMap<DateTime<Zone>, RateStats>,
+) -> Table {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Window",
+        "Samples",
+        "Avg down",
+        "Peak down",
+        "Avg up",
+        "Peak up",
+    ]));
+
+    for (key, stats) in buckets {
+        let samples = stats.rx.count.max(stats.tx.count);
+        report.add_row(vec![
+            Cell::new(format_bucket(*key, bucket_seconds))
+                .fg(Color::Magenta)
+                .add_attribute(Attribute::Bold),
+            value_cell(samples),
+            value_cell(format_rate(stats.rx.average())),
+            value_cell(format_rate(stats.rx.max())),
+            value_cell(format_rate(stats.tx.average())),
+            value_cell(format_rate(stats.tx.max())),
+        ]);
+    }
+    report
+}
+
+/// Above this drain the machine is probably not reaching a deep sleep state
+/// (a healthy s2idle/S3 suspend loses well under 1%/h).
+const HIGH_SLEEP_DRAIN_PCT_PER_HOUR: f64 = 2.0;
+
+fn sleep_drain_rate(interval: &SleepInterval) -> Option<f64> {
+    let hours = interval.slept_seconds / 3600.0;
+    (hours > 0.0).then_some(interval.lost_pct()? / hours)
+}
+
+fn format_drain_rate(value: Option<f64>) -> String {
+    value
+        .map(|v| format!("{v:.2}%/h"))
+        .unwrap_or_else(|| "--".to_string())
+}
+
+/// Totals under the sleep report. Loss and rate only count suspends on battery; those on
+/// the charger are counted apart.
+fn sleep_report_footer(sleeps: &[SleepInterval]) -> String {
+    let slept_hours = sleeps.iter().map(|s| s.slept_seconds).sum::<f64>() / 3600.0;
+    let (lost, battery_hours) = sleeps
+        .iter()
+        .filter_map(|s| Some((s.lost_pct()?, s.slept_seconds / 3600.0)))
+        .fold((None, 0.0), |(lost, hours), (pct, h)| {
+            (Some(lost.unwrap_or(0.0) + pct), hours + h)
+        });
+    let rate = lost
+        .filter(|_| battery_hours > 0.0)
+        .map(|pct| pct / battery_hours);
+    let charging = sleeps.iter().filter(|s| !s.on_battery()).count();
+    let mut footer = format!(
+        "Slept {} across {} suspends, lost {} ({} asleep on battery)",
+        format_runtime(Some(slept_hours)),
+        sleeps.len(),
+        format_percent(lost),
+        format_drain_rate(rate)
+    );
+    if charging > 0 {
+        footer.push_str(&format!("; {charging} on the charger not counted"));
+    }
+    footer
+}
+
+fn sleep_report_table(sleeps: &[SleepInterval]) -> Table {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Suspended",
+        "Resumed",
+        "Asleep",
+        "% at suspend",
+        "% at resume",
+        "Drain",
+    ]));
+    for interval in sleeps {
+        let rate = sleep_drain_rate(interval);
+        let rate_color = match rate {
+            Some(v) if v > HIGH_SLEEP_DRAIN_PCT_PER_HOUR => Color::Red,
+            Some(_) => Color::Green,
+            None => Color::White,
+        };
+        let drain = if interval.on_battery() {
+            format_drain_rate(rate)
+        } else {
+            "charger".to_string()
+        };
+        report.add_row(vec![
+            Cell::new(format_timestamp(interval.start_ts)).fg(Color::Magenta),
+            Cell::new(format_timestamp(interval.end_ts)).fg(Color::Magenta),
+            value_cell(format_runtime(Some(interval.slept_seconds / 3600.0))),
+            value_cell(format_percent(interval.start_pct)),
+            value_cell(format_percent(interval.end_pct)),
+            value_cell(drain)
+                .fg(rate_color)
+                .set_alignment(CellAlignment::Right),
+        ]);
+    }
+    report
+}
+
+fn thermals_table(episodes: &[ThrottleEpisode]) -> Table {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Start",
+        "Duration",
+        "Peak temp",
+        "Freq floor",
+        "Drop",
+    ]));
+    for episode in episodes {
+        report.add_row(vec![
+            Cell::new(format_timestamp(episode.start_ts)).fg(Color::Magenta),
+            value_cell(format_runtime(Some(episode.duration_seconds() / 3600.0))),
+            value_cell(format!("{:.1}°C", episode.peak_temp_c)).fg(Color::Red),
+            value_cell(format!("{:.0} MHz", episode.freq_floor_mhz)),
+            value_cell(format!("{:.0}%", episode.drop_pct())),
+        ]);
+    }
+    report
+}
+
+fn wakeup_sources_table(activity: &[SourceActivity]) -> Table {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Source", "Events", "Avg/s", "Peak/s", "Storms",
+    ]));
+    for source in activity {
+        report.add_row(vec![
+            label_cell(&source.source),
+            value_cell(format!("{:.0}", source.events)),
+            value_cell(format!("{:.1}", source.avg_rate)),
+            value_cell(format!("{:.1}", source.peak_rate)),
+            value_cell(source.storms.to_string()),
+        ]);
+    }
+    report
+}
+
+/// The baseline called `name` (or the latest), failing with a hint when there is none.
+fn saved_baseline(db_path: &Path, name: Option<&str>) -> Result<Baseline> {
+    match (db::fetch_baseline(db_path, name)?, name) {
+        (Some(saved), _) => Ok(saved),
+        (None, Some(name)) => {
+            anyhow::bail!("No baseline named {name}; see `symmetri baseline list`")
+        }
+        (None, None) => Err(Exit::new(
+            ExitStatus::NoData,
+            "No baselines saved; run `symmetri baseline save NAME` after a controlled period.",
+        )
+        .into()),
+    }
+}
+
+/// [`baseline::profile`] of the history between `since_ts` and `until_ts`, cleaned up as
+/// reports clean it so saved and later profiles compare like for like.
+fn baseline_profile(
+    db_path: &Path,
+    since_ts: Option<f64>,
+    until_ts: Option<f64>,
+    config: &Config,
+) -> Result<BTreeMap<String, baseline::Stat>> {
+    let before_end = |ts: f64| until_ts.is_none_or(|until| ts <= until);
+    let mut raw = db::fetch_samples(db_path, since_ts)?;
+    raw.retain(|sample| before_end(sample.ts));
+    let mut samples = aggregate_samples_by_timestamp(&raw);
+    smooth_percentages(&mut samples, &config.smoothing);
+    let mut metrics = db::fetch_metric_samples(db_path, since_ts, Some(&baseline::kinds()))?;
+    metrics.retain(|sample| before_end(sample.ts));
+    sensors::apply_renames(&mut metrics, &config.sensors);
+    reject_power_outliers(&mut samples, &mut metrics, &config.power);
+    let sleeps = db::fetch_sleep_intervals(db_path, since_ts, until_ts)?;
+    Ok(baseline::profile(
+        &discharge_segments(&samples, &sleeps),
+        &metrics,
+    ))
+}
+
+/// A baseline's metrics, with the current window and its z scores when given.
+fn activities_table(usage: &[activity::ActivityUsage]) -> Table {
+    let mut table = themed_table();
+    table.set_header(header_cells(&[
+        "Activity",
+        "Times",
+        "Duration",
+        "On battery",
+        "Energy",
+        "Avg drain",
+    ]));
+    for used in usage {
+        table.add_row(vec![
+            label_cell(&used.name),
+            value_cell(used.count.to_string()),
+            value_cell(format_runtime(Some(used.seconds / 3600.0))),
+            value_cell(format_runtime(Some(used.battery_hours))),
+            value_cell(format_energy(used.wh)),
+            value_cell(format_power(used.watts())),
+        ]);
+    }
+    table
+}
+
+fn workloads_table(summary: &[workload::ClassSummary]) -> Table {
+    let mut table = themed_table();
+    table.set_header(header_cells(&[
+        "Workload",
+        "Time",
+        "Share",
+        "On battery",
+        "Energy",
+        "Avg drain",
+    ]));
+    let total: f64 = summary.iter().map(|class| class.seconds).sum();
+    for class in summary {
+        let rate = class.rate;
+        table.add_row(vec![
+            label_cell(class.workload.as_str()),
+            value_cell(format_runtime(Some(class.seconds / 3600.0))),
+            value_cell(format_percent(
+                (total > 0.0).then(|| class.seconds / total * 100.0),
+            )),
+            value_cell(format_runtime(rate.map(|rate| rate.hours))),
+            value_cell(rate.map_or("--".to_string(), |rate| {
+                format_energy(rate.watts * rate.hours)
+            })),
+            value_cell(format_power(rate.map(|rate| rate.watts))),
+        ]);
+    }
+    table
+}
+
+fn prediction_table(prediction: &predict::Prediction) -> Table {
+    let mut table = themed_table();
+    table.set_header(header_cells(&[
+        "Workload",
+        "Duration",
+        "Drain",
+        "Energy",
+        "Battery after",
+    ]));
+    for step in &prediction.steps {
+        let after = if step.end_pct > 0.0 {
+            Cell::new(format!("{:.0}%", step.end_pct))
+        } else {
+            Cell::new("empty").fg(Color::Red)
+        };
+        table.add_row(vec![
+            label_cell(step.phase.workload.as_str()),
+            value_cell(format_span(step.phase.seconds)),
+            value_cell(format!(
+                "{} ({} learned)",
+                format_power(Some(step.rate.watts)),
+                format_runtime(Some(step.rate.hours))
+            )),
+            value_cell(format_energy(step.used_wh)),
+            after,
+        ]);
+    }
+    table
+}
+
+fn baseline_table(saved: &Baseline, deviations: Option<&[baseline::Deviation]>) -> Table {
+    let value = |value: f64| {
+        if value.abs() >= 100.0 {
+            format!("{value:.0}")
+        } else {
+            format!("{value:.2}")
+        }
+    };
+    let stat = |stat: &baseline::Stat| format!("{} ± {}", value(stat.mean), value(stat.stddev));
+    let mut table = themed_table();
+    match deviations {
+        None => {
+            table.set_header(header_cells(&["Metric", "Mean ± sd", "Readings"]));
+            for (metric, recorded) in &saved.metrics {
+                table.add_row(vec![
+                    label_cell(metric),
+                    value_cell(stat(recorded)),
+                    value_cell(recorded.count.to_string()),
+                ]);
+            }
+        }
+        Some(deviations) => {
+            table.set_header(header_cells(&["Metric", "Baseline", "Now", "z", "Verdict"]));
+            for deviation in deviations {
+                let verdict = if deviation.diverged() {
+                    Cell::new("diverged").fg(Color::Red)
+                } else {
+                    Cell::new("ok")
+                };
+                table.add_row(vec![
+                    label_cell(&deviation.metric),
+                    value_cell(stat(&deviation.baseline)),
+                    value_cell(stat(&deviation.current)),
+                    value_cell(format!("{:+.1}", deviation.z)),
+                    verdict,
+                ]);
+            }
+        }
+    }
+    table
 }
 
-fn memory_stats_table(
-    bucket_seconds: i64,
-    buckets: &BTreeMap<DateTime<Local>, UsageStats>,
-) -> Table {
+fn correlations_table(ranked: &[Correlation]) -> Table {
     let mut report = themed_table();
     report.set_header(header_cells(&[
-        "Window",
-        "Samples",
-        "Min used",
-        "Avg used",
-        "Min used %",
-        "Avg used %",
-        "Peak used %",
+        "Metric", "Source", "Pairs", "r", "Best lag", "r at lag", "Strength",
     ]));
-
-    for (key, stats) in buckets {
+    for found in ranked {
+        let lag = match found.best_lag_seconds {
+            0 => "0".to_string(),
+            lag if lag > 0 => format!("+{}", format_span(lag)),
+            lag => format!("-{}", format_span(-lag)),
+        };
+        let strength = match found.strength() {
+            "strong" => Cell::new("strong").fg(Color::Red),
+            "moderate" => Cell::new("moderate").fg(Color::Yellow),
+            other => Cell::new(other),
+        };
         report.add_row(vec![
-            Cell::new(format_bucket(*key, bucket_seconds))
-                .fg(Color::Magenta)
-                .add_attribute(Attribute::Bold),
-            value_cell(stats.used.count),
-            value_cell(format_opt_bytes(stats.used.min())),
-            value_cell(format_opt_bytes(stats.used.average())),
-            value_cell(format_percent(stats.percent.min())),
-            value_cell(format_percent(stats.percent.average())),
-            value_cell(format_percent(stats.percent.max())),
+            label_cell(&found.metric),
+            label_cell(&found.source),
+            value_cell(found.pairs.to_string()),
+            value_cell(found.r.map_or("--".to_string(), |r| format!("{r:+.2}"))),
+            value_cell(lag),
+            value_cell(format!("{:+.2}", found.best_r)),
+            strength,
         ]);
     }
     report
 }
 
-fn disk_stats_table(bucket_seconds: i64, buckets: &BTreeMap<DateTime<Local>, UsageStats>) -> Table {
+fn regressions_table(compared: &[regression::Comparison], alpha: f64) -> Table {
     let mut report = themed_table();
     report.set_header(header_cells(&[
-        "Window",
-        "Samples",
-        "Min used",
-        "Avg used",
-        "Min used %",
-        "Avg used %",
-        "Peak used %",
+        "Update", "When", "Before", "After", "Change", "p", "Verdict",
     ]));
+    let median = |watts: Option<f64>, count: usize| match watts {
+        Some(watts) => format!("{watts:.2}W (n={count})"),
+        None => "--".to_string(),
+    };
+    for comparison in compared {
+        let verdict = match comparison.verdict(alpha) {
+            "regression" => Cell::new("regression").fg(Color::Red),
+            "improvement" => Cell::new("improvement").fg(Color::Green),
+            other => Cell::new(other),
+        };
+        report.add_row(vec![
+            label_cell(&comparison.change.description),
+            Cell::new(format_timestamp(comparison.change.ts)),
+            value_cell(median(comparison.before_median_w, comparison.before)),
+            value_cell(median(comparison.after_median_w, comparison.after)),
+            value_cell(
+                comparison
+                    .delta_w()
+                    .map_or("--".to_string(), |delta| format!("{delta:+.2}W")),
+            ),
+            value_cell(
+                comparison
+                    .test
+                    .map_or("--".to_string(), |test| regression::format_p(test.p)),
+            ),
+            verdict,
+        ]);
+    }
+    report
+}
 
-    for (key, stats) in buckets {
+fn storms_table(storms: &[Storm]) -> Table {
+    let mut report = themed_table();
+    report.set_header(header_cells(&[
+        "Start", "Duration", "Source", "Peak/s", "Usual/s",
+    ]));
+    for storm in storms {
         report.add_row(vec![
-            Cell::new(format_bucket(*key, bucket_seconds))
-                .fg(Color::Magenta)
-                .add_attribute(Attribute::Bold),
-            value_cell(stats.used.count),
-            value_cell(format_opt_bytes(stats.used.min())),
-            value_cell(format_opt_bytes(stats.used.average())),
-            value_cell(format_percent(stats.percent.min())),
-            value_cell(format_percent(stats.percent.average())),
-            value_cell(format_percent(stats.percent.max())),
+            Cell::new(format_timestamp(storm.start_ts)).fg(Color::Magenta),
+            value_cell(format_runtime(Some(
+                (storm.end_ts - storm.start_ts) / 3600.0,
+            ))),
+            label_cell(&storm.source),
+            value_cell(format!("{:.0}", storm.peak_rate)).fg(Color::Red),
+            value_cell(format!("{:.1}", storm.usual_rate)),
         ]);
     }
     report
 }
 
-fn temperature_stats_table(bucket_seconds: i64, buckets: &SourceBuckets) -> Table {
+fn tunables_table(tunables: &[Tunable]) -> Table {
     let mut report = themed_table();
     report.set_header(header_cells(&[
-        "Source",
-        "Window",
-        "Samples",
-        "Min temp",
-        "Avg temp",
-        "Peak temp",
+        "Tunable",
+        "Current",
+        "Recommended",
+        "State",
     ]));
+    for tunable in tunables {
+        let state = if tunable.is_tuned() {
+            Cell::new("ok").fg(Color::Green)
+        } else {
+            Cell::new("bad").fg(Color::Red)
+        };
+        report.add_row(vec![
+            label_cell(&tunable.name),
+            Cell::new(&tunable.current),
+            Cell::new(tunable.recommended),
+            state,
+        ]);
+    }
+    report
+}
 
-    for (source, readings) in buckets {
-        for (key, stats) in readings {
-            report.add_row(vec![
-                label_cell(source),
-                Cell::new(format_bucket(*key, bucket_seconds))
-                    .fg(Color::Magenta)
-                    .add_attribute(Attribute::Bold),
-                value_cell(stats.count),
-                value_cell(
-                    stats
-                        .min()
-                        .map(|v| format!("{v:.1}C"))
-                        .unwrap_or_else(|| "--".to_string()),
-                ),
+/// Where the live view got its readings from.
+struct LiveReading {
+    preview: CollectionPreview,
+    from_daemon: bool,
+}
+
+/// The running collector's latest samples when one answers on `socket`, else a collection
+/// of our own.
+fn live_reading(socket: &Path, config: &Config) -> LiveReading {
+    match ipc::send_request(socket, Request::Latest).and_then(live::daemon_preview) {
+        Ok(preview) => LiveReading {
+            preview,
+            from_daemon: true,
+        },
+        Err(_) => LiveReading {
+            preview: preview_once(None, config),
+            from_daemon: false,
+        },
+    }
+}
+
+fn live_view(reading: &LiveReading, interval: Duration, paused: bool) -> String {
+    let preview = &reading.preview;
+    let state = if paused { "PAUSED" } else { "live" };
+    let origin = if reading.from_daemon {
+        "from the collector"
+    } else {
+        "collected here"
+    };
+    let mut view = format!(
+        "symmetri {state} — {} — {origin}, every {}s — [space] pause/resume  [h] last hour  [q] quit\n",
+        format_timestamp(preview.ts),
+        interval.as_secs()
+    );
+    if !preview.batteries.is_empty() {
+        let mut batteries = themed_table();
+        batteries.set_header(header_cells(&["Battery", "Charge", "Energy", "Status"]));
+        for battery in &preview.batteries {
+            let name = Path::new(&battery.source_path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| battery.source_path.clone());
+            batteries.add_row(vec![
+                label_cell(&name),
+                value_cell(format_percent(battery.percentage)),
                 value_cell(
-                    stats
-                        .average()
-                        .map(|v| format!("{v:.1}C"))
+                    battery
+                        .energy_now_wh
+                        .map(format_energy)
                         .unwrap_or_else(|| "--".to_string()),
                 ),
+                Cell::new(battery.status.as_deref().unwrap_or("--")),
+            ]);
+        }
+        view.push_str(&format!("{batteries}\n"));
+    }
+    let mut gauges = themed_table();
+    gauges.set_header(header_cells(&["Metric", "Source", "Value"]));
+    for sample in live::live_metrics(preview) {
+        gauges.add_row(vec![
+            Cell::new(sample.kind.as_str()),
+            label_cell(&sample.source),
+            value_cell(gauge_value(
+                sample.value.unwrap_or_default(),
+                sample.unit.as_deref(),
+            )),
+        ]);
+    }
+    view.push_str(&format!("{gauges}\n"));
+    if !preview.failed_collectors.is_empty() {
+        view.push_str(&format!(
+            "Failed collectors: {}\n",
+            preview.failed_collectors.join(", ")
+        ));
+    }
+    view
+}
+
+fn gauge_value(value: f64, unit: Option<&str>) -> String {
+    match unit {
+        Some("bytes") => format_bytes(value),
+        Some(unit) => format!("{value:.1} {unit}"),
+        None => format!("{value:.2}"),
+    }
+}
+
+/// The `h` screen of `symmetri live`: charge change and gauge ranges over the last hour
+/// stored in the database.
+fn history_view(db_path: &Path) -> String {
+    let end = now_ts();
+    let start = end - live::HISTORY_SECONDS;
+    let mut view = "symmetri history — last hour — [h] back to live  [q] quit\n".to_string();
+    let stored = db::fetch_samples_between(db_path, start, end).and_then(|samples| {
+        Ok((
+            samples,
+            db::fetch_metric_samples_between(db_path, start, end, None)?,
+        ))
+    });
+    let (samples, metrics) = match stored {
+        Ok(stored) => stored,
+        Err(err) => {
+            view.push_str(&format!("Cannot read {}: {err:#}\n", db_path.display()));
+            return view;
+        }
+    };
+    if samples.is_empty() && metrics.is_empty() {
+        view.push_str("Nothing was recorded in this window.\n");
+        return view;
+    }
+    let mut by_battery: BTreeMap<&str, Vec<&Sample>> = BTreeMap::new();
+    for sample in &samples {
+        by_battery
+            .entry(sample.source_path.as_str())
+            .or_default()
+            .push(sample);
+    }
+    if !by_battery.is_empty() {
+        let mut batteries = themed_table();
+        batteries.set_header(header_cells(&["Battery", "From", "To", "Change"]));
+        for (source, readings) in &by_battery {
+            let name = Path::new(source)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| source.to_string());
+            let (first, last) = (
+                readings[0].percentage,
+                readings[readings.len() - 1].percentage,
+            );
+            batteries.add_row(vec![
+                label_cell(&name),
+                value_cell(format_percent(first)),
+                value_cell(format_percent(last)),
                 value_cell(
-                    stats
-                        .max()
-                        .map(|v| format!("{v:.1}C"))
+                    first
+                        .zip(last)
+                        .map(|(first, last)| format!("{:+.1}%", last - first))
                         .unwrap_or_else(|| "--".to_string()),
                 ),
             ]);
         }
+        view.push_str(&format!("{batteries}\n"));
     }
-    report
+    let mut gauges = themed_table();
+    gauges.set_header(header_cells(&["Metric", "Source", "Min", "Mean", "Max"]));
+    for gauge in live::gauge_history(&metrics) {
+        gauges.add_row(vec![
+            Cell::new(gauge.kind.as_str()),
+            label_cell(gauge.source),
+            value_cell(gauge_value(gauge.min, gauge.unit)),
+            value_cell(gauge_value(gauge.mean, gauge.unit)),
+            value_cell(gauge_value(gauge.max, gauge.unit)),
+        ]);
+    }
+    view.push_str(&format!("{gauges}\n"));
+    view
 }
 
-fn network_stats_table(
-    bucket_seconds: i64,
-    buckets: &BTreeMap<DateTime<Local>, RateStats>,
-) -> Table {
+fn annotations_table(annotations: &[Annotation]) -> Table {
     let mut report = themed_table();
-    report.set_header(header_cells(&[
-        "Window",
-        "Samples",
-        "Avg down",
-        "Peak down",
-        "Avg up",
-        "Peak up",
-    ]));
-
-    for (key, stats) in buckets {
-        let samples = stats.rx.count.max(stats.tx.count);
+    report.set_header(header_cells(&["Time", "Note"]));
+    for annotation in annotations {
         report.add_row(vec![
-            Cell::new(format_bucket(*key, bucket_seconds))
-                .fg(Color::Magenta)
-                .add_attribute(Attribute::Bold),
-            value_cell(samples),
-            value_cell(format_rate(stats.rx.average())),
-            value_cell(format_rate(stats.rx.max())),
-            value_cell(format_rate(stats.tx.average())),
-            value_cell(format_rate(stats.tx.max())),
+            Cell::new(format_timestamp(annotation.ts)).fg(Color::Magenta),
+            Cell::new(&annotation.text),
         ]);
     }
     report
 }
 
-fn format_bytes(value: f64) -> String {
-    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
-    let mut val = value;
-    let mut unit = "B";
-    for next in &UNITS {
-        unit = next;
-        if val.abs() < 1024.0 || *next == "TiB" {
-            break;
-        }
-        val /= 1024.0;
-    }
-    if unit == "B" {
-        format!("{val:.0}{unit}")
+fn format_timestamp(ts: f64) -> String {
+    let formats = locale::current();
+    formats
+        .datetime(ts)
+        .map(|dt| formats.timestamp(&dt))
+        .unwrap_or_else(|| "--".to_string())
+}
+
+/// `question [Y/n]` on the terminal. `yes` answers yes; without a terminal to ask on,
+/// `default` is taken.
+fn confirm(question: &str, default: bool, yes: bool) -> Result<bool> {
+    use std::io::{BufRead, IsTerminal, Write};
+    if yes {
+        return Ok(true);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Ok(default);
+    }
+    print!("{question} {} ", if default { "[Y/n]" } else { "[y/N]" });
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(match answer.trim().to_lowercase().as_str() {
+        "" => default,
+        answer => answer.starts_with('y'),
+    })
+}
+
+/// `config show`: each setting with the value in effect and what supplied it, then the
+/// order every setting is looked up in.
+fn print_config_show(db_path: Option<&Path>, config_path: Option<&Path>) {
+    let (db, db_origin) = resolve_db_path_with_origin(db_path);
+    let (config, config_origin) = resolve_config_path_with_origin(config_path);
+    let config_value = if config.exists() {
+        config.display().to_string()
+    } else {
+        format!("{} (missing, defaults apply)", config.display())
+    };
+    let (log_value, log_origin) = env::LOG
+        .get()
+        .unwrap_or_else(|| ("info (debug with --verbose)".to_string(), Origin::Default));
+    let socket_origin = env::SOCKET
+        .get()
+        .map_or(Origin::Default, |(_, origin)| origin);
+
+    let mut table = themed_table();
+    table.set_header(header_cells(&["Setting", "Value", "From"]));
+    for (setting, value, origin) in [
+        ("Database", db.display().to_string(), db_origin),
+        ("Config file", config_value, config_origin),
+        ("Log filter", log_value, log_origin),
+        (
+            "Control socket",
+            ipc::socket_path().display().to_string(),
+            socket_origin,
+        ),
+    ] {
+        table.add_row(vec![
+            label_cell(setting),
+            Cell::new(value),
+            Cell::new(origin),
+        ]);
+    }
+    println!("{table}");
+
+    println!("\nResolution order (first match wins)");
+    for (setting, flag, var, default) in [
+        (
+            "Database",
+            Some("--db"),
+            env::DB,
+            "$XDG_DATA_HOME/symmetri/symmetri.sqlite, else /var/lib/symmetri/symmetri.sqlite when only that exists",
+        ),
+        (
+            "Config file",
+            Some("--config"),
+            env::CONFIG,
+            "$XDG_CONFIG_HOME/symmetri/config.toml",
+        ),
+        (
+            "Log filter",
+            None,
+            env::LOG,
+            "info, or debug with --verbose",
+        ),
+        (
+            "Control socket",
+            None,
+            env::SOCKET,
+            "$XDG_RUNTIME_DIR/symmetri.sock",
+        ),
+    ] {
+        let flag = flag
+            .map(|flag| format!("{flag}, then "))
+            .unwrap_or_default();
+        println!("  {setting}: {flag}{}, then {default}", var.order());
+    }
+}
+
+fn print_sources(found: &[DiscoveredSource]) {
+    if found.is_empty() {
+        println!("No sources found");
+        return;
+    }
+    let mut table = themed_table();
+    table.set_header(header_cells(&[
+        "Collector",
+        "Kind",
+        "Source",
+        "Value",
+        "Stored",
+    ]));
+    for source in found {
+        let value = match (source.value, source.unit.as_deref()) {
+            (Some(value), Some(unit)) => format!("{value:.1} {unit}"),
+            (Some(value), None) => format!("{value:.1}"),
+            (None, _) => "--".to_string(),
+        };
+        let stored = match &source.skipped {
+            None => Cell::new("yes"),
+            Some(reason) => Cell::new(format!("no: {reason}")).fg(Color::DarkGrey),
+        };
+        table.add_row(vec![
+            label_cell(&source.collector),
+            Cell::new(&source.kind),
+            Cell::new(&source.source),
+            value_cell(value),
+            stored,
+        ]);
+    }
+    println!("{table}");
+    let skipped = found.iter().filter(|s| s.skipped.is_some()).count();
+    println!(
+        "{} sources, {} stored, {skipped} skipped by the config",
+        found.len(),
+        found.len() - skipped
+    );
+}
+
+fn run_doctor(db_path: Option<&Path>, config_path: Option<&Path>) -> Result<()> {
+    let (db, db_origin) = resolve_db_path_with_origin(db_path);
+    let now = Utc::now().timestamp() as f64;
+    let findings = doctor::run(&db, db_origin, &resolve_config_path(config_path), now);
+    for finding in &findings {
+        println!("{finding}");
+    }
+    let count = |level| findings.iter().filter(|f| f.level == level).count();
+    println!(
+        "\n{} passed, {} warnings, {} failed",
+        count(doctor::Level::Pass),
+        count(doctor::Level::Warn),
+        count(doctor::Level::Fail)
+    );
+    if count(doctor::Level::Fail) > 0 {
+        return Err(Exit::silent(ExitStatus::Error).into());
+    }
+    Ok(())
+}
+
+fn print_nix_module(
+    home_manager: bool,
+    db_path: Option<&Path>,
+    config_path: Option<&Path>,
+    interval_secs: u64,
+) -> Result<()> {
+    // Defaults are left to the module; only a database chosen on purpose is carried over.
+    let (db, db_origin) = resolve_db_path_with_origin(db_path);
+    let db_path = matches!(
+        db_origin,
+        Origin::Flag | Origin::Env(_) | Origin::LegacyEnv(_)
+    )
+    .then_some(db);
+    let config_file = resolve_config_path(config_path);
+    let config = match std::fs::read_to_string(&config_file) {
+        Ok(raw) => {
+            parse_config(&raw)
+                .with_context(|| format!("Invalid config {}", config_file.display()))?;
+            Some(raw)
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("Cannot read config {}", config_file.display()))
+        }
+    };
+    let module = NixModule {
+        home_manager,
+        db_path,
+        config,
+        interval_secs,
+    };
+    print!("{}", module.render());
+    Ok(())
+}
+
+fn run_init(
+    db_path: Option<PathBuf>,
+    config_path: Option<PathBuf>,
+    yes: bool,
+    systemd: bool,
+) -> Result<()> {
+    let config_file = resolve_config_path(config_path.as_deref());
+    if config_file.exists() {
+        println!("Config:   {} (kept)", config_file.display());
+    } else if confirm(
+        &format!("Write a starter config to {}?", config_file.display()),
+        true,
+        yes,
+    )? {
+        setup::write_if_missing(&config_file, setup::STARTER_CONFIG)?;
+        println!("Config:   {} (created)", config_file.display());
+    }
+    let config = load_config(Some(&config_file))?;
+
+    let (resolved, db_origin) = resolve_db_path_with_origin(db_path.as_deref());
+    // The system-wide collector owns its database; this session only reads it.
+    let collector = db_origin != Origin::System;
+    if collector {
+        let existed = resolved.exists();
+        db::init_db(&resolved)?;
+        println!(
+            "Database: {} ({})",
+            resolved.display(),
+            if existed { "kept" } else { "created" }
+        );
+    } else {
+        println!("Database: {} (system-wide collector)", resolved.display());
+    }
+    match config.graph.output_dir() {
+        Some(dir) => {
+            std::fs::create_dir_all(&dir)
+                .with_context(|| format!("Cannot create {}", dir.display()))?;
+            println!("Graphs:   {}", dir.display());
+        }
+        None => println!("Graphs:   the working directory"),
+    }
+
+    let question = if collector {
+        "Install systemd user timers (collect every 5 minutes, evening summary)?"
     } else {
-        format!("{val:.1}{unit}")
+        "Install the systemd user timer for the evening summary?"
+    };
+    if !(systemd || confirm(question, false, yes)?) {
+        return Ok(());
+    }
+    let unit_dir = setup::user_unit_dir()?;
+    let timers = setup::user_timers(collector);
+    for (name, contents) in setup::user_units(&resolved, collector) {
+        let path = unit_dir.join(name);
+        let written = setup::write_if_missing(&path, &contents)?;
+        println!(
+            "Unit:     {} ({})",
+            path.display(),
+            if written { "created" } else { "kept" }
+        );
     }
+    let enabled = std::process::Command::new("systemctl")
+        .args(["--user", "daemon-reload"])
+        .status()
+        .and_then(|_| {
+            std::process::Command::new("systemctl")
+                .args(["--user", "enable", "--now"])
+                .args(timers)
+                .status()
+        });
+    match enabled {
+        Ok(status) if status.success() => println!("Enabled {}", timers.join(" and ")),
+        _ => println!(
+            "Could not enable the timers; run `systemctl --user enable --now {}`",
+            timers.join(" ")
+        ),
+    }
+    Ok(())
+}
+
+fn format_bytes(value: f64) -> String {
+    locale::current().bytes(value)
 }
 
 fn format_opt_bytes(value: Option<f64>) -> String {
@@ -1074,19 +6386,8 @@ fn pct_stats(values: &[f64]) -> (String, String, String) {
     )
 }
 
-fn format_bucket(dt: DateTime<Local>, bucket_seconds: i64) -> String {
-    if bucket_seconds < 3600 {
-        dt.format("%m-%d %H:%M").to_string()
-    } else if bucket_seconds < 24 * 3600 {
-        dt.format("%m-%d %H:00").to_string()
-    } else {
-        let days = bucket_seconds / (24 * 3600);
-        if days <= 1 {
-            dt.format("%Y-%m-%d").to_string()
-        } else {
-            format!("{} (+{days}d)", dt.format("%Y-%m-%d"))
-        }
-    }
+fn format_bucket(dt: DateTime<Zone>, bucket_seconds: i64) -> String {
+    locale::current().bucket(&dt, bucket_seconds)
 }
 
 #[cfg(test)]
@@ -1182,6 +6483,247 @@ mod tests {
         assert_eq!(filtered[0].source, "cpu1");
     }
 
+    #[test]
+    fn comparison_deltas_are_signed() {
+        assert_eq!(format_signed(delta(Some(7.5), Some(6.2)), "W"), "+1.30W");
+        assert_eq!(format_signed(delta(Some(5.0), Some(6.0)), "W"), "-1.00W");
+        assert_eq!(format_signed(delta(Some(5.0), None), "W"), "--");
+        assert_eq!(format_runtime_delta(Some(-1.5)), "-1h30m");
+        assert_eq!(format_runtime_delta(Some(0.25)), "+0h15m");
+    }
+
+    fn battery_sample(ts: f64, energy_now: f64) -> Sample {
+        Sample {
+            ts,
+            percentage: None,
+            capacity_pct: None,
+            health_pct: None,
+            energy_now_wh: Some(energy_now),
+            energy_full_wh: Some(60.0),
+            energy_full_design_wh: Some(70.0),
+            status: Some("Discharging".to_string()),
+            source_path: "BAT0".to_string(),
+        }
+    }
+
+    #[test]
+    fn host_windows_end_at_each_databases_newest_sample() {
+        let tmp = tempfile::tempdir().unwrap();
+        let write = |name: &str, start: f64| {
+            let path = tmp.path().join(name);
+            let mut conn = db::init_db_connection(&path).unwrap();
+            let samples: Vec<Sample> = (0..10)
+                .map(|i| battery_sample(start + f64::from(i) * 600.0, 50.0 - f64::from(i)))
+                .collect();
+            let cpu: Vec<MetricSample> = samples
+                .iter()
+                .map(|s| {
+                    metric_sample_with_source(
+                        MetricKind::CpuUsage,
+                        "cpu",
+                        s.ts,
+                        Some(20.0),
+                        json!(null),
+                    )
+                })
+                .collect();
+            db::insert_all_samples(&mut conn, &samples, &cpu).unwrap();
+            path
+        };
+        // A year apart: a rolling window ending now would find nothing in the old one.
+        let old = write("old.sqlite", 1_700_000_000.0);
+        let new = write("new.sqlite", 1_731_536_000.0);
+        let config = Config::default();
+        let a = host_summary(&old, "old".to_string(), Some(3000.0), &config).unwrap();
+        let b = host_summary(&new, "new".to_string(), None, &config).unwrap();
+        assert_eq!(a.end_ts, 1_700_005_400.0);
+        assert_eq!((a.battery.records, b.battery.records), (6, 10));
+        let table = host_system_table(&a, &b).to_string();
+        assert!(table.contains("Avg CPU usage"), "{table}");
+        assert!(!table.contains("Peak temperature"), "{table}");
+
+        let empty = tmp.path().join("empty.sqlite");
+        db::init_db(&empty).unwrap();
+        let err = host_summary(&empty, "empty".to_string(), None, &config)
+            .err()
+            .unwrap();
+        assert_eq!(exit::status_of(&err), ExitStatus::NoData);
+    }
+
+    #[test]
+    fn profile_breakdown_attributes_drain_to_active_profile() {
+        let metrics = vec![
+            metric_sample_with_source(
+                MetricKind::PowerProfile,
+                CPU_GOVERNOR_SOURCE,
+                0.0,
+                None,
+                json!({"profile": "powersave"}),
+            ),
+            metric_sample_with_source(
+                MetricKind::PowerProfile,
+                PLATFORM_PROFILE_SOURCE,
+                0.0,
+                None,
+                json!({"profile": "low-power"}),
+            ),
+            metric_sample_with_source(
+                MetricKind::PowerProfile,
+                PLATFORM_PROFILE_SOURCE,
+                600.0,
+                None,
+                json!({"profile": "performance"}),
+            ),
+        ];
+        let samples = vec![
+            battery_sample(0.0, 60.0),
+            battery_sample(300.0, 59.5),
+            battery_sample(600.0, 58.5),
+            battery_sample(900.0, 57.5),
+        ];
+
+        let profiles = profile_breakdown(&samples, &metrics);
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles["low-power"].records, 2);
+        assert!((profiles["low-power"].avg_discharge_w().unwrap() - 6.0).abs() < 0.01);
+        assert!((profiles["performance"].avg_discharge_w().unwrap() - 12.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn sleep_drain_rate_is_per_hour_asleep() {
+        let interval = SleepInterval {
+            start_ts: 0.0,
+            end_ts: 8.0 * 3600.0,
+            slept_seconds: 6.0 * 3600.0,
+            start_pct: Some(80.0),
+            end_pct: Some(77.0),
+            start_status: Some("Discharging".to_string()),
+            end_status: Some("Discharging".to_string()),
+        };
+        assert!((sleep_drain_rate(&interval).unwrap() - 0.5).abs() < 1e-9);
+        assert_eq!(format_drain_rate(sleep_drain_rate(&interval)), "0.50%/h");
+
+        let unknown = SleepInterval {
+            end_pct: None,
+            ..interval
+        };
+        assert!(sleep_drain_rate(&unknown).is_none());
+    }
+
+    #[test]
+    fn sleeping_on_the_charger_does_not_reduce_the_sleep_loss() {
+        let on_battery = SleepInterval {
+            start_ts: 0.0,
+            end_ts: 8.0 * 3600.0,
+            slept_seconds: 8.0 * 3600.0,
+            start_pct: Some(80.0),
+            end_pct: Some(77.0),
+            start_status: Some("Discharging".to_string()),
+            end_status: Some("Discharging".to_string()),
+        };
+        let charging = SleepInterval {
+            start_ts: 9.0 * 3600.0,
+            end_ts: 17.0 * 3600.0,
+            start_pct: Some(40.0),
+            end_pct: Some(90.0),
+            start_status: Some("Charging".to_string()),
+            end_status: Some("Full".to_string()),
+            ..on_battery.clone()
+        };
+        let summary = BatterySummary::compute(
+            &[],
+            0,
+            &[],
+            &[on_battery, charging],
+            &RuntimeConfig::default(),
+            None,
+        );
+        assert_eq!(summary.asleep_hours, 16.0);
+        assert_eq!(summary.sleep_loss_pct, Some(3.0));
+    }
+
+    #[test]
+    fn sleep_report_footer_leaves_charging_suspends_out() {
+        let on_battery = SleepInterval {
+            start_ts: 0.0,
+            end_ts: 6.0 * 3600.0,
+            slept_seconds: 6.0 * 3600.0,
+            start_pct: Some(80.0),
+            end_pct: Some(77.0),
+            start_status: Some("Discharging".to_string()),
+            end_status: Some("Discharging".to_string()),
+        };
+        let charging = SleepInterval {
+            start_pct: Some(40.0),
+            end_pct: Some(90.0),
+            start_status: Some("Charging".to_string()),
+            end_status: Some("Charging".to_string()),
+            ..on_battery.clone()
+        };
+        assert_eq!(sleep_drain_rate(&charging), None);
+        assert_eq!(
+            sleep_report_footer(&[on_battery, charging]),
+            "Slept 12h00m across 2 suspends, lost 3.0% (0.50%/h asleep on battery); 1 on the \
+             charger not counted"
+        );
+    }
+
+    #[test]
+    fn runtime_estimate_shows_margin() {
+        let estimate = RuntimeEstimate {
+            hours: 3.0 + 10.0 / 60.0,
+            margin_hours: Some(25.0 / 60.0),
+            remaining_hours: None,
+        };
+        assert_eq!(format_runtime_estimate(Some(&estimate)), "3h10m ± 25m");
+        let wide = RuntimeEstimate {
+            margin_hours: Some(1.5),
+            ..estimate.clone()
+        };
+        assert_eq!(format_runtime_estimate(Some(&wide)), "3h10m ± 1h30m");
+        let plain = RuntimeEstimate {
+            margin_hours: None,
+            ..estimate
+        };
+        assert_eq!(format_runtime_estimate(Some(&plain)), "3h10m");
+    }
+
+    #[test]
+    fn media_timeline_prefers_calls_over_playback() {
+        let media = |source: &str, ts: f64, active: f64| {
+            metric_sample_with_source(MetricKind::Media, source, ts, Some(active), json!({}))
+        };
+        let metrics = vec![
+            media(AUDIO_PLAYBACK_SOURCE, 0.0, 1.0),
+            media(WEBCAM_SOURCE, 0.0, 0.0),
+            media(AUDIO_PLAYBACK_SOURCE, 60.0, 1.0),
+            media(WEBCAM_SOURCE, 60.0, 1.0),
+            media(AUDIO_PLAYBACK_SOURCE, 120.0, 0.0),
+            media(WEBCAM_SOURCE, 120.0, 0.0),
+        ];
+        let timeline = media_timeline(&metrics);
+        let labels: Vec<&str> = timeline.iter().map(|(_, label)| label.as_str()).collect();
+        assert_eq!(labels, vec!["Audio playback", "Call", "Idle"]);
+        assert_eq!(profile_at(&timeline, 90.0), Some("Call"));
+
+        let days = media_by_day(&metrics);
+        let day = days.values().next().unwrap();
+        assert!((day.playback.average().unwrap() - 200.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn mount_filter_only_touches_disk_samples() {
+        let metrics = vec![
+            metric_sample_with_source(MetricKind::DiskUsage, "/", 0.0, Some(1.0), json!({})),
+            metric_sample_with_source(MetricKind::DiskUsage, "/home", 0.0, Some(2.0), json!({})),
+            metric_sample_with_source(MetricKind::CpuUsage, "cpu0", 0.0, Some(3.0), json!({})),
+        ];
+        let filtered = filter_disk_mounts(&metrics, &["/home/".to_string()]);
+        let sources: Vec<&str> = filtered.iter().map(|m| m.source.as_str()).collect();
+        assert_eq!(sources, vec!["/home", "cpu0"]);
+        assert_eq!(filter_disk_mounts(&metrics, &[]).len(), 3);
+    }
+
     #[test]
     fn usage_stats_compute_percentage() {
         let metrics = vec![metric_sample(
@@ -1196,4 +6738,55 @@ mod tests {
         assert!((stats.used.average().unwrap() - 2048.0).abs() < 1e-6);
         assert!((stats.percent.average().unwrap() - 50.0).abs() < 1e-6);
     }
+
+    #[test]
+    fn internal_overhead_orders_core_sources_first() {
+        let metrics = vec![
+            metric_sample_with_source(
+                MetricKind::Internal,
+                "collector:gpu",
+                1.0,
+                Some(4.0),
+                json!(null),
+            ),
+            metric_sample_with_source(
+                MetricKind::Internal,
+                internal::CYCLE_SOURCE,
+                1.0,
+                Some(120.0),
+                json!(null),
+            ),
+            metric_sample_with_source(
+                MetricKind::Internal,
+                internal::CYCLE_SOURCE,
+                2.0,
+                Some(80.0),
+                json!(null),
+            ),
+            metric_sample_with_source(
+                MetricKind::Internal,
+                internal::ERRORS_SOURCE,
+                2.0,
+                Some(1.0),
+                json!({"collectors": ["gpu"]}),
+            ),
+        ];
+
+        let overhead = internal_overhead(&metrics);
+        let sources: Vec<&str> = overhead.iter().map(|(s, _)| s.as_str()).collect();
+        assert_eq!(
+            sources,
+            vec![
+                internal::CYCLE_SOURCE,
+                internal::ERRORS_SOURCE,
+                "collector:gpu"
+            ]
+        );
+        let cycle = &overhead[0].1;
+        assert_eq!(cycle.count, 2);
+        assert_eq!(cycle.average(), 100.0);
+        assert_eq!(cycle.max, 120.0);
+        assert_eq!(cycle.latest, 80.0);
+        assert_eq!(collector_error_counts(&metrics).get("gpu"), Some(&1));
+    }
 }
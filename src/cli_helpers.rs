@@ -1,10 +1,14 @@
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
-use chrono::{DateTime, Local, TimeZone};
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Local, NaiveDate, Offset, TimeZone, Timelike};
 
-use crate::db::Sample;
+use crate::db::{Sample, SleepInterval};
+use crate::locale;
 use crate::timeframe::Timeframe;
+use crate::zone::Zone;
 
 fn sanitize_component(value: &str) -> Cow<'_, str> {
     if value
@@ -61,14 +65,104 @@ pub fn bucket_span_seconds(timeframe: &Timeframe, data_span_seconds: Option<f64>
     }
 }
 
-pub fn bucket_start(ts: f64, bucket_seconds: i64) -> DateTime<Local> {
-    let local_dt = Local.timestamp_opt(ts as i64, 0).unwrap();
-    let offset_seconds = -local_dt.offset().utc_minus_local(); // convert to python-style offset
+/// Most rows a report table may get from `--bucket`.
+pub const MAX_BUCKETS: i64 = 1000;
+
+/// Parses `--bucket` values such as `15m`, `1h`, `1d` or `1w` into seconds.
+pub fn parse_bucket(raw: &str) -> Result<i64, String> {
+    let raw = raw.trim();
+    let split = raw.find(|c: char| !c.is_ascii_digit()).unwrap_or(raw.len());
+    let (count, unit) = raw.split_at(split);
+    let unit_seconds = match unit {
+        "m" => 60,
+        "h" => 3600,
+        "d" => 24 * 3600,
+        "w" => 7 * 24 * 3600,
+        _ => {
+            return Err(format!(
+                "invalid bucket {raw:?}; expected e.g. 15m, 1h or 1d"
+            ))
+        }
+    };
+    match count.parse::<i64>() {
+        Ok(count) if count > 0 => Ok(count * unit_seconds),
+        _ => Err(format!(
+            "invalid bucket {raw:?}; expected e.g. 15m, 1h or 1d"
+        )),
+    }
+}
+
+/// Parses spans such as `snapshot --window 2h`, in the same units as [`parse_bucket`].
+pub fn parse_window(raw: &str) -> Result<i64, String> {
+    parse_bucket(raw).map_err(|_| format!("invalid window {raw:?}; expected e.g. 30m, 2h or 1d"))
+}
+
+/// `seconds` in the largest of w/d/h/m that divides it evenly (`90m`, `2d`).
+pub fn format_span(seconds: i64) -> String {
+    [
+        (7 * 24 * 3600, "w"),
+        (24 * 3600, "d"),
+        (3600, "h"),
+        (60, "m"),
+    ]
+    .into_iter()
+    .find(|(unit, _)| seconds >= *unit && seconds % unit == 0)
+    .map(|(unit, suffix)| format!("{}{suffix}", seconds / unit))
+    .unwrap_or_else(|| format!("{seconds}s"))
+}
+
+/// The `--bucket` size when given, else [`bucket_span_seconds`]. A requested bucket must
+/// fit in the window and split it into at most [`MAX_BUCKETS`] rows.
+pub fn resolve_bucket_seconds(
+    timeframe: &Timeframe,
+    data_span_seconds: Option<f64>,
+    requested: Option<i64>,
+) -> Result<i64> {
+    let Some(bucket) = requested else {
+        return Ok(bucket_span_seconds(timeframe, data_span_seconds));
+    };
+    let Some(window) = timeframe.seconds.or(data_span_seconds) else {
+        return Ok(bucket);
+    };
+    let window = window.ceil() as i64;
+    if bucket > window.max(60) {
+        anyhow::bail!(
+            "--bucket {} is longer than the {} window",
+            format_span(bucket),
+            format_span(window)
+        );
+    }
+    if window / bucket > MAX_BUCKETS {
+        let smallest = [5, 10, 15, 30, 60, 120, 180, 360, 720, 1440, 2880, 4320]
+            .into_iter()
+            .map(|minutes| minutes * 60)
+            .find(|size| window / size <= MAX_BUCKETS)
+            .unwrap_or_else(|| {
+                let per_row = (window + MAX_BUCKETS - 1) / MAX_BUCKETS;
+                (per_row + 604_799) / 604_800 * 604_800
+            });
+        anyhow::bail!(
+            "--bucket {} splits the {} window into {} rows; use at least {}",
+            format_span(bucket),
+            format_span(window),
+            window / bucket,
+            format_span(smallest)
+        );
+    }
+    Ok(bucket)
+}
+
+/// Start of the bucket holding `ts`, aligned to wall-clock time in the display zone
+/// ([`locale::current`]) so hourly and daily buckets start on the hour and at midnight.
+pub fn bucket_start(ts: f64, bucket_seconds: i64) -> DateTime<Zone> {
+    let zone = locale::current().zone;
+    let local_dt = zone.timestamp_opt(ts as i64, 0).unwrap();
+    let offset_seconds = local_dt.offset().fix().local_minus_utc();
     let bucket_epoch = (((ts + offset_seconds as f64) / bucket_seconds as f64).floor()
         * bucket_seconds as f64)
         - offset_seconds as f64;
     let aligned = bucket_epoch.max(0.0) as i64;
-    Local.timestamp_opt(aligned, 0).unwrap()
+    zone.timestamp_opt(aligned, 0).unwrap()
 }
 
 #[derive(Debug, Default, PartialEq)]
@@ -99,14 +193,334 @@ impl RateAccumulator {
 }
 
 pub fn average_rates<'a>(samples: impl IntoIterator<Item = &'a Sample>) -> AverageRates {
-    const MAX_GAP_HOURS: f64 = 5.0 / 60.0;
+    let mut discharge = RateAccumulator::default();
+    let mut charge = RateAccumulator::default();
+    for_each_rate_pair(samples, |_, _, delta, dt_hours| {
+        if delta > 0.0 {
+            charge.record(delta, dt_hours);
+        } else {
+            discharge.record(-delta, dt_hours);
+        }
+    });
+
+    AverageRates {
+        discharge_w: discharge.average(),
+        charge_w: charge.average(),
+    }
+}
 
+/// Same as [`average_rates`] but drops segments that span a recorded suspend, so sleep
+/// drain does not leak into the awake charge/discharge rates.
+pub fn average_rates_awake<'a>(
+    samples: impl IntoIterator<Item = &'a Sample>,
+    sleeps: &[SleepInterval],
+) -> AverageRates {
     let mut discharge = RateAccumulator::default();
     let mut charge = RateAccumulator::default();
-    let mut iter = samples.into_iter().filter(|s| s.energy_now_wh.is_some());
+    for_each_rate_pair(samples, |previous, current, delta, dt_hours| {
+        if sleeps.iter().any(|s| s.overlaps(previous.ts, current.ts)) {
+            return;
+        }
+        if delta > 0.0 {
+            charge.record(delta, dt_hours);
+        } else {
+            discharge.record(-delta, dt_hours);
+        }
+    });
+
+    AverageRates {
+        discharge_w: discharge.average(),
+        charge_w: charge.average(),
+    }
+}
+
+/// Charge and drain speed in percentage points per hour.
+#[derive(Debug, Default, PartialEq)]
+pub struct PercentRates {
+    pub discharge_pct_h: Option<f64>,
+    pub charge_pct_h: Option<f64>,
+}
+
+/// Same as [`average_rates`] but from the percentage, so it works on batteries that do not
+/// report energy or power. Segments spanning a recorded suspend are skipped.
+pub fn average_pct_rates<'a>(
+    samples: impl IntoIterator<Item = &'a Sample>,
+    sleeps: &[SleepInterval],
+) -> PercentRates {
+    let mut discharge = RateAccumulator::default();
+    let mut charge = RateAccumulator::default();
+    for_each_pair_by(
+        samples,
+        |s| s.percentage,
+        MAX_RATE_GAP_HOURS,
+        |previous, current, delta, dt_hours| {
+            if sleeps.iter().any(|s| s.overlaps(previous.ts, current.ts)) {
+                return;
+            }
+            if delta > 0.0 {
+                charge.record(delta, dt_hours);
+            } else {
+                discharge.record(-delta, dt_hours);
+            }
+        },
+    );
+
+    PercentRates {
+        discharge_pct_h: discharge.average(),
+        charge_pct_h: charge.average(),
+    }
+}
+
+/// Battery energy drained and charged, in Wh.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EnergyTotals {
+    pub discharged_wh: f64,
+    pub charged_wh: f64,
+}
+
+impl EnergyTotals {
+    fn record(&mut self, delta_wh: f64) {
+        if delta_wh > 0.0 {
+            self.charged_wh += delta_wh;
+        } else {
+            self.discharged_wh -= delta_wh;
+        }
+    }
+}
+
+/// Sums energy changes between consecutive readings with a consistent charge direction.
+/// Unlike the rates, long gaps still count: the energy left the battery either way.
+pub fn energy_totals<'a>(samples: impl IntoIterator<Item = &'a Sample>) -> EnergyTotals {
+    let mut totals = EnergyTotals::default();
+    for_each_pair_by(
+        samples,
+        |s| s.energy_now_wh,
+        f64::INFINITY,
+        |_, _, delta, _| totals.record(delta),
+    );
+    totals
+}
+
+/// [`energy_totals`] per local calendar day of each segment's end.
+pub fn energy_by_day<'a>(
+    samples: impl IntoIterator<Item = &'a Sample>,
+) -> BTreeMap<NaiveDate, EnergyTotals> {
+    let mut days: BTreeMap<NaiveDate, EnergyTotals> = BTreeMap::new();
+    for_each_pair_by(
+        samples,
+        |s| s.energy_now_wh,
+        f64::INFINITY,
+        |_, current, delta, _| {
+            if let Some(day) = locale::current().datetime(current.ts) {
+                days.entry(day.date_naive()).or_default().record(delta);
+            }
+        },
+    );
+    days
+}
+
+/// Weighted averages by weekday (Monday first) and hour of day in the display zone.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WeekHeatmap {
+    /// (weighted sum, total weight) per weekday and hour.
+    cells: [[(f64, f64); 24]; 7],
+}
+
+impl WeekHeatmap {
+    pub fn record(&mut self, ts: f64, value: f64, weight: f64) {
+        let Some(local) = locale::current().datetime(ts) else {
+            return;
+        };
+        let weekday = local.weekday().num_days_from_monday() as usize;
+        let cell = &mut self.cells[weekday][local.hour() as usize];
+        cell.0 += value * weight;
+        cell.1 += weight;
+    }
+
+    pub fn average(&self, weekday: usize, hour: usize) -> Option<f64> {
+        let (sum, weight) = self.cells[weekday][hour];
+        (weight > 0.0).then(|| sum / weight)
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        (0..7)
+            .flat_map(|weekday| (0..24).filter_map(move |hour| self.average(weekday, hour)))
+            .reduce(f64::max)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.max().is_none()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramBin {
+    pub start: f64,
+    pub end: f64,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub bins: Vec<HistogramBin>,
+    pub total: usize,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+/// Nearest-rank percentile of an ascending slice; `q` is in 0..=1.
+pub fn percentile(sorted: &[f64], q: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (q * sorted.len() as f64).ceil().max(1.0) as usize;
+    sorted.get(rank.min(sorted.len()) - 1).copied()
+}
+
+/// Splits finite values into `bin_count` equal-width bins between their min and max.
+pub fn histogram(values: &[f64], bin_count: usize) -> Option<Histogram> {
+    let mut sorted: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+    if sorted.is_empty() || bin_count == 0 {
+        return None;
+    }
+    sorted.sort_by(f64::total_cmp);
+    let min = sorted[0];
+    let max = sorted[sorted.len() - 1];
+    let width = if max > min {
+        (max - min) / bin_count as f64
+    } else {
+        1.0
+    };
+    let mut bins: Vec<HistogramBin> = (0..bin_count)
+        .map(|index| HistogramBin {
+            start: min + width * index as f64,
+            end: min + width * (index + 1) as f64,
+            count: 0,
+        })
+        .collect();
+    for value in &sorted {
+        let index = (((value - min) / width) as usize).min(bin_count - 1);
+        bins[index].count += 1;
+    }
+    Some(Histogram {
+        bins,
+        total: sorted.len(),
+        p50: percentile(&sorted, 0.5)?,
+        p90: percentile(&sorted, 0.9)?,
+        p99: percentile(&sorted, 0.99)?,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DischargeSegment {
+    /// Timestamp of the segment end.
+    pub ts: f64,
+    pub watts: f64,
+    pub hours: f64,
+}
+
+/// Individual awake discharge segments, oldest first.
+pub fn discharge_segments<'a>(
+    samples: impl IntoIterator<Item = &'a Sample>,
+    sleeps: &[SleepInterval],
+) -> Vec<DischargeSegment> {
+    let mut segments = Vec::new();
+    for_each_rate_pair(samples, |previous, current, delta, dt_hours| {
+        if delta < 0.0 && !sleeps.iter().any(|s| s.overlaps(previous.ts, current.ts)) {
+            segments.push(DischargeSegment {
+                ts: current.ts,
+                watts: -delta / dt_hours,
+                hours: dt_hours,
+            });
+        }
+    });
+    segments
+}
+
+/// The charging counterpart of [`discharge_segments`]; `watts` is the charge power.
+pub fn charge_segments<'a>(
+    samples: impl IntoIterator<Item = &'a Sample>,
+    sleeps: &[SleepInterval],
+) -> Vec<DischargeSegment> {
+    let mut segments = Vec::new();
+    for_each_rate_pair(samples, |previous, current, delta, dt_hours| {
+        if delta > 0.0 && !sleeps.iter().any(|s| s.overlaps(previous.ts, current.ts)) {
+            segments.push(DischargeSegment {
+                ts: current.ts,
+                watts: delta / dt_hours,
+                hours: dt_hours,
+            });
+        }
+    });
+    segments
+}
+
+/// Same as [`average_rates`], split by a per-sample key. A charge/discharge segment only
+/// counts towards a key when both of its endpoints map to that key.
+pub fn average_rates_by<'a, K, F>(
+    samples: impl IntoIterator<Item = &'a Sample>,
+    mut key: F,
+) -> BTreeMap<K, AverageRates>
+where
+    K: Ord,
+    F: FnMut(&Sample) -> Option<K>,
+{
+    let mut accumulators: BTreeMap<K, (RateAccumulator, RateAccumulator)> = BTreeMap::new();
+    for_each_rate_pair(samples, |previous, current, delta, dt_hours| {
+        let (Some(prev_key), Some(next_key)) = (key(previous), key(current)) else {
+            return;
+        };
+        if prev_key != next_key {
+            return;
+        }
+        let (discharge, charge) = accumulators.entry(next_key).or_default();
+        if delta > 0.0 {
+            charge.record(delta, dt_hours);
+        } else {
+            discharge.record(-delta, dt_hours);
+        }
+    });
+    accumulators
+        .into_iter()
+        .map(|(key, (discharge, charge))| {
+            (
+                key,
+                AverageRates {
+                    discharge_w: discharge.average(),
+                    charge_w: charge.average(),
+                },
+            )
+        })
+        .collect()
+}
+
+/// Walks consecutive energy readings and reports every charging or discharging segment
+/// (previous, current, delta Wh, hours) that is short enough to trust.
+fn for_each_rate_pair<'a, F>(samples: impl IntoIterator<Item = &'a Sample>, on_pair: F)
+where
+    F: FnMut(&'a Sample, &'a Sample, f64, f64),
+{
+    for_each_pair_by(samples, |s| s.energy_now_wh, MAX_RATE_GAP_HOURS, on_pair);
+}
+
+/// Longer gaps say little about the instantaneous rate (the machine may have been off).
+const MAX_RATE_GAP_HOURS: f64 = 5.0 / 60.0;
+
+/// [`for_each_rate_pair`] over any per-sample reading, e.g. the percentage.
+fn for_each_pair_by<'a, V, F>(
+    samples: impl IntoIterator<Item = &'a Sample>,
+    value: V,
+    max_gap_hours: f64,
+    mut on_pair: F,
+) where
+    V: Fn(&Sample) -> Option<f64>,
+    F: FnMut(&'a Sample, &'a Sample, f64, f64),
+{
+    let mut iter = samples.into_iter().filter(|s| value(s).is_some());
     let mut previous = match iter.next() {
         Some(sample) => sample,
-        None => return AverageRates::default(),
+        None => return,
     };
 
     for current in iter {
@@ -115,21 +529,16 @@ pub fn average_rates<'a>(samples: impl IntoIterator<Item = &'a Sample>) -> Avera
             continue;
         }
         let dt_hours = (current.ts - previous.ts) / 3600.0;
-        if dt_hours > 0.0 && dt_hours <= MAX_GAP_HOURS {
-            let delta = current.energy_now_wh.unwrap() - previous.energy_now_wh.unwrap();
-            if delta > 0.0 && is_charging(previous) && is_charging(current) {
-                charge.record(delta, dt_hours);
-            } else if delta < 0.0 && is_discharging(previous) && is_discharging(current) {
-                discharge.record(-delta, dt_hours);
+        if dt_hours > 0.0 && dt_hours <= max_gap_hours {
+            let delta = value(current).unwrap() - value(previous).unwrap();
+            if (delta > 0.0 && is_charging(previous) && is_charging(current))
+                || (delta < 0.0 && is_discharging(previous) && is_discharging(current))
+            {
+                on_pair(previous, current, delta, dt_hours);
             }
         }
         previous = current;
     }
-
-    AverageRates {
-        discharge_w: discharge.average(),
-        charge_w: charge.average(),
-    }
 }
 
 fn is_discharging(sample: &Sample) -> bool {
@@ -173,6 +582,40 @@ pub fn estimate_runtime_hours(
     Some(capacity_wh / avg)
 }
 
+#[derive(Debug, Default, PartialEq)]
+pub struct FullChargeTime {
+    pub total_hours: f64,
+    pub longest_hours: f64,
+}
+
+/// Time spent sitting at (or very near) 100%. Gaps longer than an hour are not counted so a
+/// machine that was off does not look like it sat on the charger.
+pub fn full_charge_time(samples: &[Sample]) -> FullChargeTime {
+    const FULL_PCT: f64 = 99.0;
+    const MAX_GAP_HOURS: f64 = 1.0;
+
+    let is_full = |s: &Sample| {
+        s.status
+            .as_deref()
+            .map(|st| st.eq_ignore_ascii_case("full"))
+            .unwrap_or(false)
+            || s.percentage.map(|p| p >= FULL_PCT).unwrap_or(false)
+    };
+    let mut result = FullChargeTime::default();
+    let mut stretch = 0.0;
+    for pair in samples.windows(2) {
+        let dt_hours = (pair[1].ts - pair[0].ts) / 3600.0;
+        if is_full(&pair[0]) && is_full(&pair[1]) && dt_hours > 0.0 && dt_hours <= MAX_GAP_HOURS {
+            stretch += dt_hours;
+            result.total_hours += dt_hours;
+            result.longest_hours = result.longest_hours.max(stretch);
+        } else {
+            stretch = 0.0;
+        }
+    }
+    result
+}
+
 pub fn format_runtime(hours: Option<f64>) -> String {
     match hours {
         None => "--".to_string(),
@@ -189,7 +632,6 @@ pub fn format_runtime(hours: Option<f64>) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Timelike;
 
     fn sample(
         ts: f64,
@@ -298,10 +740,174 @@ mod tests {
         assert!((rates.discharge_w.unwrap() - 6.0).abs() < 0.01);
     }
 
+    #[test]
+    fn full_charge_time_tracks_longest_stretch() {
+        let mut samples: Vec<Sample> = (0..=4)
+            .map(|i| {
+                sample(
+                    i as f64 * 1800.0,
+                    60.0,
+                    Some(60.0),
+                    Some(70.0),
+                    Some("Full"),
+                )
+            })
+            .collect();
+        samples.push(sample(
+            3.0 * 3600.0,
+            55.0,
+            Some(60.0),
+            Some(70.0),
+            Some("Discharging"),
+        ));
+        samples.push(sample(
+            4.0 * 3600.0,
+            60.0,
+            Some(60.0),
+            Some(70.0),
+            Some("Full"),
+        ));
+        samples.push(sample(
+            4.5 * 3600.0,
+            60.0,
+            Some(60.0),
+            Some(70.0),
+            Some("Full"),
+        ));
+
+        let full = full_charge_time(&samples);
+        assert!((full.longest_hours - 2.0).abs() < 1e-9);
+        assert!((full.total_hours - 2.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn average_rates_awake_skips_sleep_segments() {
+        let samples = vec![
+            sample(0.0, 60.0, Some(60.0), Some(70.0), Some("Discharging")),
+            sample(300.0, 59.5, Some(60.0), Some(70.0), Some("Discharging")),
+            sample(600.0, 57.0, Some(60.0), Some(70.0), Some("Discharging")),
+        ];
+        let sleeps = vec![SleepInterval {
+            start_ts: 300.0,
+            end_ts: 600.0,
+            slept_seconds: 240.0,
+            start_pct: None,
+            end_pct: None,
+            start_status: None,
+            end_status: None,
+        }];
+
+        let rates = average_rates_awake(&samples, &sleeps);
+        assert!((rates.discharge_w.unwrap() - 6.0).abs() < 0.01);
+        assert!(average_rates(&samples).discharge_w.unwrap() > 6.0);
+    }
+
+    #[test]
+    fn percent_rates_work_without_energy_readings() {
+        let with_pct = |ts: f64, pct: f64, status: &str| Sample {
+            percentage: Some(pct),
+            energy_now_wh: None,
+            ..sample(ts, 0.0, None, None, Some(status))
+        };
+        let samples = vec![
+            with_pct(0.0, 80.0, "Discharging"),
+            with_pct(300.0, 79.0, "Discharging"),
+            with_pct(600.0, 78.0, "Discharging"),
+            with_pct(900.0, 80.0, "Charging"),
+            with_pct(1200.0, 83.0, "Charging"),
+        ];
+
+        let rates = average_pct_rates(&samples, &[]);
+        assert!((rates.discharge_pct_h.unwrap() - 12.0).abs() < 0.01);
+        assert!((rates.charge_pct_h.unwrap() - 36.0).abs() < 0.01);
+        assert_eq!(average_rates(&samples), AverageRates::default());
+    }
+
+    #[test]
+    fn energy_totals_include_long_gaps() {
+        let samples = vec![
+            sample(0.0, 60.0, Some(60.0), None, Some("Discharging")),
+            sample(300.0, 59.0, Some(60.0), None, Some("Discharging")),
+            sample(7_500.0, 50.0, Some(60.0), None, Some("Discharging")),
+            sample(7_800.0, 50.5, Some(60.0), None, Some("Charging")),
+            sample(8_100.0, 52.5, Some(60.0), None, Some("Charging")),
+        ];
+
+        let totals = energy_totals(&samples);
+        assert!((totals.discharged_wh - 10.0).abs() < 1e-9);
+        assert!((totals.charged_wh - 2.0).abs() < 1e-9);
+
+        let days = energy_by_day(&samples);
+        let summed = days
+            .values()
+            .fold(EnergyTotals::default(), |acc, day| EnergyTotals {
+                discharged_wh: acc.discharged_wh + day.discharged_wh,
+                charged_wh: acc.charged_wh + day.charged_wh,
+            });
+        assert_eq!(summed, totals);
+    }
+
+    #[test]
+    fn heatmap_averages_by_weekday_and_hour() {
+        let tuesday_3pm = Local
+            .with_ymd_and_hms(2025, 11, 25, 15, 10, 0)
+            .unwrap()
+            .timestamp() as f64;
+        let mut heatmap = WeekHeatmap::default();
+        heatmap.record(tuesday_3pm, 10.0, 1.0);
+        heatmap.record(tuesday_3pm + 600.0, 20.0, 3.0);
+        heatmap.record(tuesday_3pm + 3600.0, 5.0, 1.0);
+
+        assert_eq!(heatmap.average(1, 15), Some(17.5));
+        assert_eq!(heatmap.average(1, 16), Some(5.0));
+        assert_eq!(heatmap.average(0, 15), None);
+        assert_eq!(heatmap.max(), Some(17.5));
+        assert!(WeekHeatmap::default().is_empty());
+    }
+
+    #[test]
+    fn histogram_bins_and_percentiles() {
+        let values: Vec<f64> = (1..=100).map(f64::from).collect();
+        let hist = histogram(&values, 4).unwrap();
+        assert_eq!(hist.total, 100);
+        assert_eq!(
+            hist.bins.iter().map(|b| b.count).collect::<Vec<_>>(),
+            vec![25, 25, 25, 25]
+        );
+        assert_eq!(hist.bins[0].start, 1.0);
+        assert_eq!(hist.bins[3].end, 100.0);
+        assert_eq!((hist.p50, hist.p90, hist.p99), (50.0, 90.0, 99.0));
+
+        let flat = histogram(&[3.0, 3.0], 5).unwrap();
+        assert_eq!(flat.bins[0].count, 2);
+        assert!(histogram(&[], 5).is_none());
+    }
+
+    #[test]
+    fn average_rates_by_splits_on_key_changes() {
+        let samples = vec![
+            sample(0.0, 60.0, Some(60.0), Some(70.0), Some("Discharging")),
+            sample(300.0, 59.5, Some(60.0), Some(70.0), Some("Discharging")),
+            sample(600.0, 58.5, Some(60.0), Some(70.0), Some("Discharging")),
+            sample(900.0, 57.5, Some(60.0), Some(70.0), Some("Discharging")),
+        ];
+        let by_profile = average_rates_by(&samples, |s| {
+            Some(if s.ts < 600.0 {
+                "power-saver"
+            } else {
+                "performance"
+            })
+        });
+
+        assert_eq!(by_profile.len(), 2);
+        assert!((by_profile["power-saver"].discharge_w.unwrap() - 6.0).abs() < 0.01);
+        assert!((by_profile["performance"].discharge_w.unwrap() - 12.0).abs() < 0.01);
+    }
+
     #[test]
     fn bucket_alignment_matches_expected_windows() {
         use crate::timeframe::build_timeframe;
-        let timeframe = build_timeframe(6, 0, 0, false).unwrap();
+        let timeframe = build_timeframe(6, 0, 0, false, None).unwrap();
         let span = bucket_span_seconds(&timeframe, None);
         let sample_dt = Local::now()
             .with_minute(37)
@@ -316,7 +922,7 @@ mod tests {
         assert_eq!(bucket.minute() % 10, 0);
         assert_eq!(bucket.second(), 0);
 
-        let one_day = build_timeframe(0, 1, 0, false).unwrap();
+        let one_day = build_timeframe(0, 1, 0, false, None).unwrap();
         let span_day = bucket_span_seconds(&one_day, None);
         let bucket_day = bucket_start(sample_dt.timestamp() as f64, span_day);
         assert_eq!(span_day, 3600);
@@ -328,7 +934,7 @@ mod tests {
     #[test]
     fn short_timeframes_use_five_minute_buckets() {
         use crate::timeframe::build_timeframe;
-        let timeframe = build_timeframe(1, 0, 0, false).unwrap();
+        let timeframe = build_timeframe(1, 0, 0, false, None).unwrap();
         let span = bucket_span_seconds(&timeframe, None);
         let sample_dt = Local::now()
             .with_minute(12)
@@ -347,11 +953,42 @@ mod tests {
     #[test]
     fn all_time_uses_data_span_for_buckets() {
         use crate::timeframe::build_timeframe;
-        let timeframe = build_timeframe(6, 0, 0, true).unwrap();
+        let timeframe = build_timeframe(6, 0, 0, true, None).unwrap();
         let span = bucket_span_seconds(&timeframe, Some(6.0 * 3600.0));
         assert_eq!(span, 10 * 60);
 
         let weekly = bucket_span_seconds(&timeframe, Some(200.0 * 24.0 * 3600.0));
         assert_eq!(weekly, 7 * 24 * 3600);
     }
+
+    #[test]
+    fn requested_buckets_are_validated_against_the_window() {
+        use crate::timeframe::build_timeframe;
+        assert_eq!(parse_bucket("15m"), Ok(15 * 60));
+        assert_eq!(parse_bucket("1d"), Ok(24 * 3600));
+        assert!(parse_bucket("0h").is_err());
+        assert!(parse_bucket("1.5h").is_err());
+        assert!(parse_bucket("h").is_err());
+
+        let six_hours = build_timeframe(6, 0, 0, false, None).unwrap();
+        assert_eq!(resolve_bucket_seconds(&six_hours, None, None).unwrap(), 600);
+        assert_eq!(
+            resolve_bucket_seconds(&six_hours, None, Some(900)).unwrap(),
+            900
+        );
+        let err = resolve_bucket_seconds(&six_hours, None, Some(24 * 3600)).unwrap_err();
+        assert_eq!(err.to_string(), "--bucket 1d is longer than the 6h window");
+
+        let year = build_timeframe(0, 365, 0, false, None).unwrap();
+        let err = resolve_bucket_seconds(&year, None, Some(900)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--bucket 15m splits the 365d window into 35040 rows; use at least 12h"
+        );
+        let all = build_timeframe(0, 0, 0, true, None).unwrap();
+        assert_eq!(
+            resolve_bucket_seconds(&all, Some(3.0 * 3600.0), Some(3600)).unwrap(),
+            3600
+        );
+    }
 }
@@ -0,0 +1,332 @@
+//! Read access to a symmetri database for programs embedding the crate.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use symmetri::client::Db;
+//! use symmetri::metrics::MetricKind;
+//!
+//! let db = Db::open_default();
+//! if let Some(battery) = db.latest_battery()? {
+//!     println!("{:?}% {:?}", battery.percentage, battery.status);
+//! }
+//! let temps = db
+//!     .metrics()
+//!     .last(Duration::from_secs(3600))
+//!     .kind(MetricKind::Temperature)
+//!     .source("cpu")
+//!     .fetch()?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+
+use crate::collector::resolve_db_path;
+use crate::db::{self, Annotation, Sample};
+use crate::metrics::{MetricKind, MetricSample};
+use crate::timeframe::Timeframe;
+
+/// A symmetri database, by path. Every call opens its own connection, so a `Db` is cheap
+/// to keep around next to a running collector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Db {
+    path: PathBuf,
+}
+
+impl Db {
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        Db { path: path.into() }
+    }
+
+    /// The database the CLI would use: `SYMMETRI_DB`, else
+    /// `$XDG_DATA_HOME/symmetri/symmetri.sqlite`.
+    pub fn open_default() -> Self {
+        Db::open(resolve_db_path(None))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The newest battery reading of any battery.
+    pub fn latest_battery(&self) -> Result<Option<Sample>> {
+        db::fetch_latest_sample(&self.path)
+    }
+
+    /// The newest sample of every (kind, source), optionally limited to `kinds`.
+    pub fn latest_metrics(&self, kinds: &[MetricKind]) -> Result<Vec<MetricSample>> {
+        db::fetch_latest_metric_samples(&self.path, (!kinds.is_empty()).then_some(kinds))
+    }
+
+    pub fn batteries(&self) -> BatteryQuery<'_> {
+        BatteryQuery {
+            db: self,
+            window: Window::default(),
+            sources: Vec::new(),
+        }
+    }
+
+    pub fn metrics(&self) -> MetricQuery<'_> {
+        MetricQuery {
+            db: self,
+            window: Window::default(),
+            kinds: Vec::new(),
+            sources: Vec::new(),
+        }
+    }
+
+    /// Annotations with `since <= ts < until`.
+    pub fn annotations(&self, since: Option<f64>, until: Option<f64>) -> Result<Vec<Annotation>> {
+        db::fetch_annotations(&self.path, since, until)
+    }
+}
+
+/// `[since, until)` in Unix seconds; open ends are unbounded.
+#[derive(Debug, Clone, Copy, Default)]
+struct Window {
+    since: Option<f64>,
+    until: Option<f64>,
+}
+
+impl Window {
+    fn last(duration: Duration) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        Window {
+            since: Some(now - duration.as_secs_f64()),
+            until: None,
+        }
+    }
+}
+
+/// Battery readings, oldest first. Built with [`Db::batteries`].
+#[derive(Debug, Clone)]
+pub struct BatteryQuery<'a> {
+    db: &'a Db,
+    window: Window,
+    sources: Vec<String>,
+}
+
+impl BatteryQuery<'_> {
+    /// Readings from the last `duration`.
+    pub fn last(mut self, duration: Duration) -> Self {
+        self.window = Window::last(duration);
+        self
+    }
+
+    /// Readings inside a CLI-style timeframe (`build_timeframe`).
+    pub fn timeframe(mut self, timeframe: &Timeframe) -> Self {
+        self.window.since = timeframe.since_timestamp(None);
+        self.window.until = timeframe.until_timestamp();
+        self
+    }
+
+    pub fn since(mut self, ts: f64) -> Self {
+        self.window.since = Some(ts);
+        self
+    }
+
+    pub fn until(mut self, ts: f64) -> Self {
+        self.window.until = Some(ts);
+        self
+    }
+
+    /// Only this battery, by name (`BAT0`) or sysfs path; repeat to add more.
+    pub fn source(mut self, source: impl Into<String>) -> Self {
+        self.sources.push(source.into());
+        self
+    }
+
+    pub fn fetch(self) -> Result<Vec<Sample>> {
+        self.iter()?.collect()
+    }
+
+    /// The readings one at a time, read from the database a page at a time; for windows
+    /// too long to collect.
+    pub fn iter(self) -> Result<impl Iterator<Item = Result<Sample>>> {
+        let cursor = db::sample_cursor(&self.db.path, self.window.since, self.window.until)?;
+        let sources = self.sources;
+        Ok(cursor.filter(move |row| match row {
+            Ok(sample) => {
+                let name = Path::new(&sample.source_path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string());
+                sources.is_empty()
+                    || sources.iter().any(|source| {
+                        *source == sample.source_path || Some(source) == name.as_ref()
+                    })
+            }
+            Err(_) => true,
+        }))
+    }
+}
+
+/// Metric samples, oldest first. Built with [`Db::metrics`].
+#[derive(Debug, Clone)]
+pub struct MetricQuery<'a> {
+    db: &'a Db,
+    window: Window,
+    kinds: Vec<MetricKind>,
+    sources: Vec<String>,
+}
+
+impl MetricQuery<'_> {
+    /// Samples from the last `duration`.
+    pub fn last(mut self, duration: Duration) -> Self {
+        self.window = Window::last(duration);
+        self
+    }
+
+    /// Samples inside a CLI-style timeframe (`build_timeframe`).
+    pub fn timeframe(mut self, timeframe: &Timeframe) -> Self {
+        self.window.since = timeframe.since_timestamp(None);
+        self.window.until = timeframe.until_timestamp();
+        self
+    }
+
+    pub fn since(mut self, ts: f64) -> Self {
+        self.window.since = Some(ts);
+        self
+    }
+
+    pub fn until(mut self, ts: f64) -> Self {
+        self.window.until = Some(ts);
+        self
+    }
+
+    /// Only this kind; repeat to add more.
+    pub fn kind(mut self, kind: MetricKind) -> Self {
+        self.kinds.push(kind);
+        self
+    }
+
+    /// Only this source (e.g. `cpu`, `wlan0`); repeat to add more.
+    pub fn source(mut self, source: impl Into<String>) -> Self {
+        self.sources.push(source.into());
+        self
+    }
+
+    pub fn fetch(self) -> Result<Vec<MetricSample>> {
+        self.iter()?.collect()
+    }
+
+    /// The samples one at a time, read from the database a page at a time; for windows
+    /// too long to collect.
+    pub fn iter(self) -> Result<impl Iterator<Item = Result<MetricSample>>> {
+        let kinds = (!self.kinds.is_empty()).then_some(self.kinds.as_slice());
+        let cursor = db::metric_cursor(&self.db.path, self.window.since, self.window.until, kinds)?;
+        let sources = self.sources;
+        Ok(cursor.filter(move |row| match row {
+            Ok(sample) => sources.is_empty() || sources.contains(&sample.source),
+            Err(_) => true,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{battery, metric};
+    use serde_json::Value;
+
+    /// Two batteries and a few metrics between ts 10 and 30; the tempdir must outlive the `Db`.
+    fn fixture() -> (tempfile::TempDir, Db) {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = Db::open(tmp.path().join("metrics.db"));
+        let mut conn = db::init_db_connection(db.path()).unwrap();
+        let battery = |ts, name: &str| Sample {
+            source_path: format!("/sys/class/power_supply/{name}"),
+            ..battery(ts, 50.0, "Discharging")
+        };
+        let metric = |ts, kind, source: &str| metric(ts, kind, source, 1.0, Value::Null);
+        db::insert_all_samples(
+            &mut conn,
+            &[
+                battery(10.0, "BAT0"),
+                battery(10.0, "BAT1"),
+                battery(20.0, "BAT0"),
+            ],
+            &[
+                metric(10.0, MetricKind::Temperature, "cpu"),
+                metric(20.0, MetricKind::Temperature, "cpu"),
+                metric(20.0, MetricKind::Temperature, "nvme"),
+                metric(20.0, MetricKind::CpuUsage, "cpu"),
+                metric(30.0, MetricKind::Temperature, "cpu"),
+            ],
+        )
+        .unwrap();
+        (tmp, db)
+    }
+
+    #[test]
+    fn queries_filter_by_window() {
+        let (_tmp, db) = fixture();
+        let streamed = db
+            .batteries()
+            .until(20.0)
+            .iter()
+            .unwrap()
+            .map(|row| row.map(|sample| sample.source_path))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            streamed,
+            ["BAT0", "BAT1"].map(|name| format!("/sys/class/power_supply/{name}"))
+        );
+        let window = db.metrics().since(15.0).until(30.0).fetch().unwrap();
+        assert_eq!(window.len(), 3);
+        assert!(window.iter().all(|sample| sample.ts == 20.0));
+        // Everything is decades old, so a trailing window is empty.
+        assert!(db
+            .metrics()
+            .last(Duration::from_secs(60))
+            .fetch()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn queries_filter_by_kind() {
+        let (_tmp, db) = fixture();
+        let usage = db.metrics().kind(MetricKind::CpuUsage).fetch().unwrap();
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].ts, 20.0);
+        assert_eq!(
+            db.latest_metrics(&[MetricKind::Temperature]).unwrap().len(),
+            2
+        );
+    }
+
+    #[test]
+    fn queries_filter_by_source() {
+        let (_tmp, db) = fixture();
+        let bat0 = db.batteries().source("BAT0").fetch().unwrap();
+        assert_eq!(
+            bat0.iter().map(|s| s.ts).collect::<Vec<_>>(),
+            vec![10.0, 20.0]
+        );
+        let nvme = db.metrics().source("nvme").fetch().unwrap();
+        assert_eq!(nvme.len(), 1);
+        assert_eq!(nvme[0].kind, MetricKind::Temperature);
+    }
+
+    #[test]
+    fn filters_combine() {
+        let (_tmp, db) = fixture();
+        let temps = db
+            .metrics()
+            .since(15.0)
+            .until(30.0)
+            .kind(MetricKind::Temperature)
+            .source("cpu")
+            .fetch()
+            .unwrap();
+        assert_eq!(temps.len(), 1);
+        assert_eq!(temps[0].ts, 20.0);
+        assert_eq!(db.latest_battery().unwrap().unwrap().ts, 20.0);
+    }
+}
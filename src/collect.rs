@@ -0,0 +1,26 @@
+//! One-shot collection for programs embedding the crate.
+//!
+//! ```no_run
+//! let snapshot = symmetri::collect::collect_now()?;
+//! for battery in &snapshot.batteries {
+//!     println!("{}: {:?}%", battery.source_path, battery.percentage);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::Result;
+
+use crate::collector::{preview_once, CollectionPreview};
+use crate::config::{load_config, Config};
+
+/// Runs every collector enabled in the user's config once, without writing to the
+/// database (the same snapshot `symmetri collect --dry-run` prints).
+pub fn collect_now() -> Result<CollectionPreview> {
+    Ok(collect_now_with(&load_config(None)?))
+}
+
+/// Like [`collect_now`] with an explicit config, e.g. `Config::default()` for the
+/// built-in collectors only.
+pub fn collect_now_with(config: &Config) -> CollectionPreview {
+    preview_once(None, config)
+}
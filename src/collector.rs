@@ -1,90 +1,1068 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
 use std::thread;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::Result;
-use log::{info, warn};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tracing::{debug, info, info_span, warn, Span};
 
-use crate::db::{self, Sample};
-use crate::metrics;
-use crate::sysfs::{find_battery_paths, read_battery};
+use crate::aggregate::aggregate_group;
+use crate::alerts::{self, AlertState};
+use crate::config::{load_config, Config};
+use crate::db::{self, Annotation, Sample};
+use crate::derived::DerivedState;
+use crate::env::{self, Origin};
+use crate::exit::ExitStatus;
+use crate::hooks::{self, HookState};
+use crate::internal::{self, CycleStats, INTERNAL_COLLECTOR};
+use crate::ipc::{self, IpcServer, Request};
+use crate::lock::{InstanceLock, REPLACE_TIMEOUT};
+use crate::metrics::{self, MetricKind, MetricSample};
+use crate::privacy;
+use crate::schedule::Schedule;
+use crate::signals;
+use crate::suspend::{self, SuspendProbe};
+use crate::sysfs::BatteryInfo;
+use crate::usb;
+use crate::versions::SystemVersions;
 
+/// A family of metrics gathered on every collection.
+pub trait Collector: Send + Sync {
+    /// Stable name used in logs and in `[collectors.<name>]` config sections.
+    fn name(&self) -> &str;
+    /// Metric kinds this collector produces; empty when it can emit any kind.
+    fn kinds(&self) -> &[MetricKind];
+    fn collect(&self, ts: f64) -> Vec<MetricSample>;
+}
+
+/// Name under which battery readings are scheduled and configured. Batteries are not a
+/// registry collector because they produce `Sample`s, not metric samples.
+pub const BATTERY_COLLECTOR: &str = "battery";
+
+#[derive(Default)]
+pub struct CollectorRegistry {
+    collectors: Vec<Arc<dyn Collector>>,
+}
+
+impl CollectorRegistry {
+    /// Built-in sysfs/procfs collectors, temperatures, then the script collector.
+    #[cfg_attr(
+        not(any(
+            feature = "collector-wifi",
+            feature = "collector-disk",
+            feature = "collector-sensors",
+            feature = "collector-cgroups",
+            feature = "collector-scripts",
+        )),
+        allow(unused_variables)
+    )]
+    pub fn from_config(config: &Config) -> Self {
+        let mut registry = CollectorRegistry::default();
+        for collector in metrics::builtin_collectors() {
+            registry.register(collector);
+        }
+        #[cfg(feature = "collector-wifi")]
+        registry.register(Box::new(crate::wifi::WifiCollector::new(
+            config.location.clone(),
+        )));
+        #[cfg(feature = "collector-disk")]
+        registry.register(Box::new(crate::disk::DiskCollector::new(
+            config.disks.clone(),
+        )));
+        #[cfg(feature = "collector-sensors")]
+        registry.register(Box::new(crate::sensors::TemperatureCollector::new(
+            config.sensors.clone(),
+        )));
+        #[cfg(feature = "collector-cgroups")]
+        registry.register(Box::new(crate::cgroups::CgroupCollector::new(
+            config.cgroups.clone(),
+        )));
+        #[cfg(feature = "collector-scripts")]
+        registry.register(Box::new(crate::scripts::ScriptCollector::new(
+            config.scripts.clone(),
+        )));
+        #[cfg(target_os = "android")]
+        registry.register(Box::new(crate::termux::TermuxCollector));
+        registry
+    }
+
+    pub fn register(&mut self, collector: Box<dyn Collector>) {
+        self.collectors.push(Arc::from(collector));
+    }
+
+    pub fn collectors(&self) -> impl Iterator<Item = &dyn Collector> {
+        self.collectors.iter().map(|collector| collector.as_ref())
+    }
+
+    fn enabled<'a>(&'a self, config: &'a Config) -> impl Iterator<Item = &'a Arc<dyn Collector>> {
+        self.collectors
+            .iter()
+            .filter(|collector| config.collector_enabled(collector.name()))
+    }
+
+    /// Config sections naming a collector that is not registered (likely typos).
+    pub fn unknown_names<'a>(&self, config: &'a Config) -> Vec<&'a str> {
+        config
+            .collectors
+            .keys()
+            .map(String::as_str)
+            .filter(|name| {
+                *name != BATTERY_COLLECTOR
+                    && *name != INTERNAL_COLLECTOR
+                    && !self.collectors().any(|c| c.name() == *name)
+            })
+            .collect()
+    }
+
+    /// Runs every enabled collector on its own thread, each in a `collector` span, and
+    /// waits at most each collector's timeout for its result. Collectors that miss their
+    /// deadline are reported and left behind, so one hung read cannot stall the cycle.
+    pub fn collect(&self, config: &Config, ts: f64) -> Collection {
+        let started = Instant::now();
+        let (sender, receiver) = mpsc::channel();
+        let mut pending: HashMap<String, Instant> = HashMap::new();
+        for collector in self.enabled(config) {
+            let name = collector.name().to_string();
+            pending.insert(name.clone(), started + config.collector_timeout(&name));
+            spawn_collector(collector, ts, sender.clone());
+        }
+        drop(sender);
+
+        let mut collection = Collection::default();
+        while let Some(deadline) = pending.values().min().copied() {
+            match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(result) => {
+                    if pending.remove(&result.name).is_some() {
+                        collection.add(result, config);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    let now = Instant::now();
+                    pending.retain(|name, deadline| {
+                        let alive = *deadline > now;
+                        if !alive {
+                            warn!(
+                                "Collector {name} timed out after {:.1}s; skipping its samples",
+                                (now - started).as_secs_f64()
+                            );
+                            collection.stats.failed.push(name.clone());
+                        }
+                        alive
+                    });
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        collection
+    }
+
+    /// Runs every registered collector, enabled or not, and returns each one's samples in
+    /// registration order. Collectors that panic or miss their timeout come back empty.
+    pub fn collect_all(&self, config: &Config, ts: f64) -> Vec<(String, Vec<MetricSample>)> {
+        let started = Instant::now();
+        let (sender, receiver) = mpsc::channel();
+        let mut deadline = started;
+        for collector in &self.collectors {
+            deadline = deadline.max(started + config.collector_timeout(collector.name()));
+            spawn_collector(collector, ts, sender.clone());
+        }
+        drop(sender);
+        let mut results: HashMap<String, Vec<MetricSample>> = HashMap::new();
+        while let Ok(result) =
+            receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        {
+            results.insert(result.name, result.samples.unwrap_or_default());
+        }
+        self.collectors()
+            .map(|collector| {
+                let name = collector.name().to_string();
+                let samples = results.remove(&name).unwrap_or_default();
+                (name, samples)
+            })
+            .collect()
+    }
+}
+
+/// Samples gathered by the registry plus what gathering them cost.
+#[derive(Debug, Default)]
+pub struct Collection {
+    pub samples: Vec<MetricSample>,
+    pub stats: CycleStats,
+}
+
+impl Collection {
+    /// Adds a finished run, keeping the samples `[collectors.<name>] allow`/`deny` let
+    /// through.
+    fn add(&mut self, result: CollectorResult, config: &Config) {
+        match result.samples {
+            Some(samples) => {
+                self.samples.extend(samples.into_iter().filter(|sample| {
+                    config.source_allowed(&result.name, &sample.source, raw_id(sample))
+                }));
+                self.stats.timings.push((result.name, result.elapsed));
+            }
+            None => self.stats.failed.push(result.name),
+        }
+    }
+}
+
+/// The id a sample's source was derived from, for `allow`/`deny` patterns: the hwmon
+/// `chip/input` of temperatures.
+pub fn raw_id(sample: &MetricSample) -> Option<&str> {
+    sample.details.get("sensor").and_then(Value::as_str)
+}
+
+/// Batteries `[collectors.battery] allow`/`deny` let through, matched on the name (`BAT0`)
+/// or the whole source path.
+fn allowed_batteries(config: &Config, samples: Vec<Sample>) -> Vec<Sample> {
+    samples
+        .into_iter()
+        .filter(|sample| {
+            let path = Path::new(&sample.source_path);
+            let name = path
+                .file_name()
+                .map_or(sample.source_path.as_str(), |name| {
+                    name.to_str().unwrap_or_default()
+                });
+            config.source_allowed(BATTERY_COLLECTOR, name, Some(&sample.source_path))
+        })
+        .collect()
+}
+
+struct CollectorResult {
+    name: String,
+    elapsed: Duration,
+    /// `None` when the collector panicked.
+    samples: Option<Vec<MetricSample>>,
+}
+
+/// Runs a collector on a detached thread inside the caller's span and sends its samples back.
+fn spawn_collector(collector: &Arc<dyn Collector>, ts: f64, sender: Sender<CollectorResult>) {
+    let collector = Arc::clone(collector);
+    let parent = Span::current();
+    thread::spawn(move || {
+        let started = Instant::now();
+        let samples = panic::catch_unwind(AssertUnwindSafe(|| {
+            run_collector(collector.as_ref(), &parent, ts)
+        }));
+        if samples.is_err() {
+            warn!("Collector {} panicked", collector.name());
+        }
+        let _ = sender.send(CollectorResult {
+            name: collector.name().to_string(),
+            elapsed: started.elapsed(),
+            samples: samples.ok(),
+        });
+    });
+}
+
+fn run_collector(collector: &dyn Collector, parent: &Span, ts: f64) -> Vec<MetricSample> {
+    let span = info_span!(parent: parent, "collector", collector = collector.name());
+    let _entered = span.enter();
+    let started = Instant::now();
+    let samples = collector.collect(ts);
+    debug!(
+        samples = samples.len(),
+        elapsed_ms = started.elapsed().as_secs_f64() * 1000.0,
+        "collector finished"
+    );
+    samples
+}
+
+/// Database file name under the data directory.
+const DB_FILE_NAME: &str = "symmetri.sqlite";
+
+/// Data directory of the system-wide collector (`systemd/symmetri.service`).
+const SYSTEM_DATA_DIR: &str = "/var/lib";
+
+/// The user's database, or the system-wide collector's when only that one exists, so one
+/// collector can serve every session on a shared machine.
 pub fn default_db_path() -> PathBuf {
-    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("~"));
-    home.join(".local")
-        .join("share")
-        .join("symmetri")
-        .join("metrics.db")
+    default_db_path_with_origin().0
+}
+
+fn default_db_path_with_origin() -> (PathBuf, Origin) {
+    discover_db(user_db_path(), system_db_path())
+}
+
+fn discover_db(user: PathBuf, system: PathBuf) -> (PathBuf, Origin) {
+    if !user.exists() && system.exists() {
+        (system, Origin::System)
+    } else {
+        (user, Origin::Default)
+    }
+}
+
+/// `$XDG_DATA_HOME/symmetri/symmetri.sqlite`, `~/.local/share/...` when unset. A
+/// `~/.local/share/symmetri/metrics.db` written by older versions is kept in use until
+/// the new file exists, so upgrading never starts an empty history.
+pub fn user_db_path() -> PathBuf {
+    let home = dirs::home_dir();
+    let data_dir = dirs::data_dir()
+        .or_else(|| home.as_ref().map(|home| home.join(".local/share")))
+        .unwrap_or_else(|| PathBuf::from("~/.local/share"));
+    let legacy = home.map(|home| home.join(".local/share/symmetri/metrics.db"));
+    db_path_in(&data_dir, legacy.as_deref())
+}
+
+/// `/var/lib/symmetri/symmetri.sqlite`, or the `metrics.db` older sample units wrote.
+pub fn system_db_path() -> PathBuf {
+    let data_dir = Path::new(SYSTEM_DATA_DIR);
+    db_path_in(data_dir, Some(&data_dir.join("symmetri/metrics.db")))
+}
+
+fn db_path_in(data_dir: &Path, legacy: Option<&Path>) -> PathBuf {
+    let path = data_dir.join("symmetri").join(DB_FILE_NAME);
+    match legacy {
+        Some(legacy) if !path.exists() && legacy.exists() => legacy.to_path_buf(),
+        _ => path,
+    }
 }
 
 pub fn resolve_db_path(db_path: Option<&Path>) -> PathBuf {
+    resolve_db_path_with_origin(db_path).0
+}
+
+/// The database path: `--db`, else `SYMMETRI_DB` (or the deprecated `BATTERY_MONITOR_DB`),
+/// else [`default_db_path`], with where it came from.
+pub fn resolve_db_path_with_origin(db_path: Option<&Path>) -> (PathBuf, Origin) {
     if let Some(path) = db_path {
-        return path.to_path_buf();
+        return (path.to_path_buf(), Origin::Flag);
     }
-    if let Ok(env_path) =
-        std::env::var("SYMMETRI_DB").or_else(|_| std::env::var("BATTERY_MONITOR_DB"))
-    {
+    if let Some((env_path, origin)) = env::DB.get() {
         if let Some(stripped) = env_path.strip_prefix("~/") {
             if let Some(home) = dirs::home_dir() {
-                return home.join(stripped);
+                return (home.join(stripped), origin);
             }
         }
-        return PathBuf::from(env_path);
+        return (PathBuf::from(env_path), origin);
     }
-    default_db_path()
+    default_db_path_with_origin()
 }
 
-pub fn collect_once(db_path: Option<&Path>, sysfs_root: Option<&Path>) -> Result<i32> {
-    let resolved_db = resolve_db_path(db_path);
-    let mut conn = db::init_db_connection(&resolved_db)?;
+/// [`resolve_db_path`] for collecting: a discovered system-wide database this user cannot
+/// write is refused with a hint rather than SQLite's "readonly database".
+fn resolve_writable_db_path(db_path: Option<&Path>) -> Result<PathBuf> {
+    let (path, origin) = resolve_db_path_with_origin(db_path);
+    if origin == Origin::System && !writable(&path) {
+        anyhow::bail!(
+            "{} is written by the system-wide collector and read-only here; pass --db or set SYMMETRI_DB to collect into a database of your own",
+            path.display()
+        );
+    }
+    Ok(path)
+}
 
-    let root = sysfs_root.unwrap_or_else(|| Path::new("/sys/class/power_supply"));
-    let battery_paths = find_battery_paths(root);
-    if battery_paths.is_empty() {
-        warn!("No batteries found in sysfs; collecting other metrics only");
+pub fn writable(path: &Path) -> bool {
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe { libc::access(c_path.as_ptr(), libc::W_OK) == 0 }
+}
+
+/// Stores the kernel, OS and symmetri versions when they differ from the last stored ones,
+/// logging what changed. Oneshot timer runs check on every collection, the daemon at start.
+fn record_versions(conn: &rusqlite::Connection, ts: f64) -> Result<()> {
+    let current = SystemVersions::current();
+    if let Some(previous) = db::record_system_versions(conn, ts, &current)? {
+        for change in current.changes_from(&previous) {
+            info!("{change}");
+        }
     }
+    Ok(())
+}
 
-    let ts = SystemTime::now()
+pub fn now_ts() -> f64 {
+    SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
-        .as_secs_f64();
+        .as_secs_f64()
+}
 
-    let mut samples: Vec<Sample> = Vec::new();
-    for path in battery_paths {
-        let reading = read_battery(&path);
-        samples.push(db::create_sample_from_reading(&reading, Some(ts)));
+#[cfg(not(any(target_os = "freebsd", target_os = "openbsd")))]
+pub fn read_batteries(root: &Path, config: &Config, ts: f64) -> Vec<Sample> {
+    let _battery = info_span!("collector", collector = BATTERY_COLLECTOR).entered();
+    let battery_paths = crate::sysfs::find_battery_paths(root);
+    // Android keeps power_supply from apps on most phones; Termux asks the system instead.
+    #[cfg(target_os = "android")]
+    if battery_paths.is_empty() {
+        if let Some(battery) = crate::termux::read_battery(ts) {
+            return vec![db::create_sample_from_reading(&battery.reading(), Some(ts))];
+        }
     }
+    if battery_paths.is_empty() {
+        warn!("No batteries found in sysfs; collecting other metrics only");
+    }
+    battery_paths
+        .iter()
+        .map(|path| {
+            let reading = crate::sysfs::read_battery(path, &config.quirks_for(path));
+            db::create_sample_from_reading(&reading, Some(ts))
+        })
+        .collect()
+}
 
-    let metric_samples = metrics::collect_metrics(ts);
-    db::insert_all_samples(&mut conn, &samples, &metric_samples)?;
+/// The BSDs have no power_supply class; `acpiconf`, `sysctl hw.sensors` or `apm` stand
+/// in for it and `root` and `[quirks]` do not apply.
+#[cfg(any(target_os = "freebsd", target_os = "openbsd"))]
+pub fn read_batteries(_root: &Path, _config: &Config, ts: f64) -> Vec<Sample> {
+    let _battery = info_span!("collector", collector = BATTERY_COLLECTOR).entered();
+    let batteries = crate::bsd::read_batteries();
+    if batteries.is_empty() {
+        warn!("No batteries found; collecting other metrics only");
+    }
+    batteries
+        .iter()
+        .map(|battery| db::create_sample_from_reading(&battery.reading, Some(ts)))
+        .collect()
+}
 
-    if !samples.is_empty() {
-        for sample in samples {
+#[cfg(not(any(target_os = "freebsd", target_os = "openbsd")))]
+fn battery_info(path: &Path, config: &Config) -> BatteryInfo {
+    crate::sysfs::read_battery_info(path, &config.quirks_for(path))
+}
+
+#[cfg(any(target_os = "freebsd", target_os = "openbsd"))]
+fn battery_info(path: &Path, _config: &Config) -> BatteryInfo {
+    let source_path = path.to_string_lossy();
+    crate::bsd::read_batteries()
+        .into_iter()
+        .find(|battery| battery.info.source_path == source_path)
+        .map(|battery| battery.info)
+        .unwrap_or_default()
+}
+
+/// Suspend bookkeeping, hooks, battery metadata and logging that follow every stored
+/// battery reading. `detach_hooks` leaves hook commands running (see [`hooks::run_hooks`]).
+fn after_battery_insert(
+    conn: &rusqlite::Connection,
+    ts: f64,
+    samples: &[Sample],
+    config: &Config,
+    detach_hooks: bool,
+) -> Result<()> {
+    record_sleep(conn, ts, samples)?;
+    if samples.is_empty() {
+        return Ok(());
+    }
+    fire_hooks(conn, samples, config, detach_hooks)?;
+    for sample in samples {
+        let path = Path::new(&sample.source_path);
+        let mut info = battery_info(path, config);
+        privacy::scrub_battery_info(&config.privacy, &mut info);
+        if db::record_battery_info(conn, ts, &info)? {
+            let field = |value: &Option<String>| value.clone().unwrap_or_else(|| "--".into());
             info!(
-                "Logged record for {}: percent={:.2} health={:.2}",
-                Path::new(&sample.source_path)
-                    .file_name()
-                    .map(|p| p.to_string_lossy())
-                    .unwrap_or_else(|| sample.source_path.clone().into()),
-                sample.percentage.unwrap_or(0.0),
-                sample.health_pct.unwrap_or(0.0)
+                "New battery at {}: manufacturer={} model={} serial={}",
+                sample.source_path,
+                field(&info.manufacturer),
+                field(&info.model_name),
+                field(&info.serial_number)
             );
         }
+        info!(
+            "Logged record for {}: percent={:.2} health={:.2}",
+            Path::new(&sample.source_path)
+                .file_name()
+                .map(|p| p.to_string_lossy())
+                .unwrap_or_else(|| sample.source_path.clone().into()),
+            sample.percentage.unwrap_or(0.0),
+            sample.health_pct.unwrap_or(0.0)
+        );
     }
+    Ok(())
+}
+
+pub fn collect_once(
+    db_path: Option<&Path>,
+    sysfs_root: Option<&Path>,
+    config: &Config,
+) -> Result<ExitStatus> {
+    let ts = now_ts();
+    let cycle = info_span!("cycle", ts);
+    let _entered = cycle.enter();
+
+    let resolved_db = resolve_writable_db_path(db_path)?;
+    let mut conn = db::init_db_connection(&resolved_db)?;
+    record_versions(&conn, ts)?;
+
+    let started = Instant::now();
+    let (
+        samples,
+        Collection {
+            samples: metric_samples,
+            mut stats,
+        },
+    ) = gather(sysfs_root, config, ts);
+    let insert_started = Instant::now();
+    db::insert_all_samples(&mut conn, &samples, &metric_samples)?;
+    stats.db_insert = Some(insert_started.elapsed());
+    push_exporters(config, &samples, &metric_samples);
+    record_usb_changes(&conn, &metric_samples)?;
+    after_battery_insert(&conn, ts, &samples, config, false)?;
+    fire_alerts(&conn, config, &samples, &metric_samples)?;
     if !metric_samples.is_empty() {
         info!("Logged {} system metric records", metric_samples.len());
     }
-    Ok(0)
+    stats.cycles.push((ts, started.elapsed()));
+    store_internal(&mut conn, config, ts, &stats)?;
+    // The samples of the healthy collectors are stored; the failed ones were logged.
+    Ok(if stats.failed.is_empty() {
+        ExitStatus::Success
+    } else {
+        ExitStatus::CollectorFailure
+    })
+}
+
+fn gather(sysfs_root: Option<&Path>, config: &Config, ts: f64) -> (Vec<Sample>, Collection) {
+    let root = sysfs_root.unwrap_or_else(|| Path::new("/sys/class/power_supply"));
+    let samples = if config.collector_enabled(BATTERY_COLLECTOR) {
+        allowed_batteries(config, read_batteries(root, config, ts))
+    } else {
+        Vec::new()
+    };
+    let mut collection = CollectorRegistry::from_config(config).collect(config, ts);
+    let derived = DerivedState::default().derive(&config.derived, &collection.samples);
+    collection.samples.extend(derived);
+    privacy::scrub_metrics(&config.privacy, &mut collection.samples);
+    (samples, collection)
+}
+
+/// What a single collection would store, as printed by `collect --dry-run`.
+#[derive(Debug, Serialize)]
+pub struct CollectionPreview {
+    pub ts: f64,
+    pub batteries: Vec<Sample>,
+    pub metrics: Vec<MetricSample>,
+    /// Collectors that timed out or panicked.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed_collectors: Vec<String>,
+}
+
+/// Runs every enabled collector once without opening the database.
+pub fn preview_once(sysfs_root: Option<&Path>, config: &Config) -> CollectionPreview {
+    let ts = now_ts();
+    let _cycle = info_span!("cycle", ts).entered();
+    let (batteries, collection) = gather(sysfs_root, config, ts);
+    CollectionPreview {
+        ts,
+        batteries,
+        metrics: collection.samples,
+        failed_collectors: collection.stats.failed,
+    }
+}
+
+fn store_internal(
+    conn: &mut rusqlite::Connection,
+    config: &Config,
+    ts: f64,
+    stats: &CycleStats,
+) -> Result<()> {
+    if stats.is_empty() || !config.collector_enabled(INTERNAL_COLLECTOR) {
+        return Ok(());
+    }
+    db::insert_all_samples(conn, &[], &internal::internal_samples(ts, stats))
+}
+
+/// Sends a stored batch to `[influx] url` and `[otlp] endpoint`; an unreachable server
+/// never stops collection.
+#[cfg_attr(
+    not(any(feature = "influx", feature = "otlp")),
+    allow(unused_variables)
+)]
+fn push_exporters(config: &Config, batteries: &[Sample], metrics: &[MetricSample]) {
+    #[cfg(feature = "influx")]
+    if config.influx.url.is_some() {
+        let body = crate::influx::to_line_protocol(batteries, metrics);
+        if let Err(err) = crate::influx::push(&config.influx, &body) {
+            warn!("InfluxDB push failed: {err:#}");
+        }
+    }
+    #[cfg(feature = "otlp")]
+    if config.otlp.endpoint.is_some() {
+        if let Err(err) = crate::otlp::push(&config.otlp, &config.privacy, batteries, metrics) {
+            warn!("OTLP push failed: {err:#}");
+        }
+    }
+}
+
+const SUSPEND_PROBE_KEY: &str = "suspend_probe";
+
+fn record_sleep(conn: &rusqlite::Connection, ts: f64, samples: &[Sample]) -> Result<()> {
+    let battery = aggregate_group(samples).ok();
+    let Some(current) = suspend::probe(
+        ts,
+        battery.as_ref().and_then(|s| s.percentage),
+        battery.and_then(|s| s.status),
+    ) else {
+        return Ok(());
+    };
+    let previous = db::get_state(conn, SUSPEND_PROBE_KEY)?
+        .and_then(|raw| serde_json::from_str::<SuspendProbe>(&raw).ok());
+    if let Some(sleep) = previous.and_then(|prev| suspend::detect_sleep(&prev, &current)) {
+        info!(
+            "Detected suspend of {:.0}s between collections",
+            sleep.slept_seconds
+        );
+        db::insert_sleep_interval(conn, &sleep)?;
+    }
+    db::set_state(conn, SUSPEND_PROBE_KEY, &serde_json::to_string(&current)?)?;
+    Ok(())
+}
+
+const USB_DEVICES_KEY: &str = "usb_devices";
+
+/// Stores an annotation for every USB device that appeared or disappeared since the last
+/// USB collection. Cycles without USB samples (collector disabled or not due) are skipped.
+fn record_usb_changes(conn: &rusqlite::Connection, metric_samples: &[MetricSample]) -> Result<()> {
+    let usb: Vec<&MetricSample> = metric_samples
+        .iter()
+        .filter(|m| m.kind == MetricKind::Usb)
+        .collect();
+    let Some(ts) = usb.first().map(|m| m.ts) else {
+        return Ok(());
+    };
+    let current: BTreeSet<String> = usb.iter().map(|m| m.source.clone()).collect();
+    // The first collection only establishes the baseline.
+    if let Some(previous) = db::get_state(conn, USB_DEVICES_KEY)?
+        .and_then(|raw| serde_json::from_str::<BTreeSet<String>>(&raw).ok())
+    {
+        for text in usb::connection_changes(&previous, &current) {
+            info!("{text}");
+            db::insert_annotation_with_conn(conn, &Annotation { ts, text })?;
+        }
+    }
+    db::set_state(conn, USB_DEVICES_KEY, &serde_json::to_string(&current)?)?;
+    Ok(())
+}
+
+const HOOK_STATE_KEY: &str = "hook_state";
+
+fn fire_hooks(
+    conn: &rusqlite::Connection,
+    samples: &[Sample],
+    config: &Config,
+    detach: bool,
+) -> Result<()> {
+    let combined = aggregate_group(samples)?;
+    let current = HookState {
+        percentage: combined.percentage,
+        status: combined.status,
+    };
+    let previous = db::get_state(conn, HOOK_STATE_KEY)?
+        .and_then(|raw| serde_json::from_str::<HookState>(&raw).ok());
+    if !config.hooks.is_empty() {
+        let events = hooks::detect_events(previous.as_ref(), &current, &config.hooks);
+        hooks::run_hooks(&events, &current, &config.hooks, detach);
+    }
+    db::set_state(conn, HOOK_STATE_KEY, &serde_json::to_string(&current)?)?;
+    Ok(())
+}
+
+const ALERT_STATE_KEY: &str = "alert_state";
+
+/// Evaluates the `[alerts]` rules against stored samples and sends those that fire.
+fn fire_alerts(
+    conn: &rusqlite::Connection,
+    config: &Config,
+    batteries: &[Sample],
+    metrics: &[MetricSample],
+) -> Result<()> {
+    if config.alerts.is_empty() {
+        return Ok(());
+    }
+    let mut state = db::get_state(conn, ALERT_STATE_KEY)?
+        .and_then(|raw| serde_json::from_str::<AlertState>(&raw).ok())
+        .unwrap_or_default();
+    let fired = state.evaluate(&config.alerts, batteries, metrics);
+    alerts::dispatch(fired, config);
+    db::set_state(conn, ALERT_STATE_KEY, &serde_json::to_string(&state)?)?;
+    Ok(())
+}
+
+/// What the running collect loop reports over the control socket.
+#[derive(Default)]
+struct DaemonStatus {
+    started_ts: f64,
+    cycles: u64,
+    battery_samples: u64,
+    metric_samples: u64,
+    batteries: Vec<Sample>,
+    /// Newest sample per (kind, source).
+    metrics: BTreeMap<(&'static str, String), MetricSample>,
+}
+
+impl DaemonStatus {
+    fn record_batteries(&mut self, samples: &[Sample]) {
+        self.battery_samples += samples.len() as u64;
+        if !samples.is_empty() {
+            self.batteries = samples.to_vec();
+        }
+    }
+
+    fn record_metrics(&mut self, samples: &[MetricSample]) {
+        self.metric_samples += samples.len() as u64;
+        for sample in samples {
+            self.metrics.insert(
+                (sample.kind.as_str(), sample.source.clone()),
+                sample.clone(),
+            );
+        }
+    }
+
+    fn latest(&self) -> Value {
+        json!({
+            "batteries": self.batteries,
+            "metrics": self.metrics.values().collect::<Vec<_>>(),
+        })
+    }
+
+    fn stats(&self, db_path: &Path, collectors: Vec<&str>, running: Vec<&String>) -> Value {
+        let now = now_ts();
+        json!({
+            "pid": std::process::id(),
+            "db_path": db_path,
+            "started_ts": self.started_ts,
+            "uptime_seconds": now - self.started_ts,
+            "cycles": self.cycles,
+            "battery_samples": self.battery_samples,
+            "metric_samples": self.metric_samples,
+            "collectors": collectors,
+            "running": running,
+        })
+    }
 }
 
+/// Longest stretch the collect loop waits before checking for signals.
+const SIGNAL_POLL: Duration = Duration::from_millis(250);
+
+fn build_schedule(config: &Config, registry: &CollectorRegistry, default: Duration) -> Schedule {
+    let interval_for = |name: &str| config.collector_interval(name).unwrap_or(default);
+    let mut schedule = Schedule::new(Instant::now());
+    if config.collector_enabled(BATTERY_COLLECTOR) {
+        schedule.add(BATTERY_COLLECTOR, interval_for(BATTERY_COLLECTOR));
+    }
+    for collector in registry.enabled(config) {
+        schedule.add(collector.name(), interval_for(collector.name()));
+    }
+    schedule
+}
+
+/// Collects until SIGINT/SIGTERM, each collector on its own schedule (`[collectors.<name>]
+/// interval_seconds`, defaulting to `interval_seconds`). Metric collectors run on background
+/// threads and their results are stored as they arrive, so a slow collector never holds
+/// back the battery reading; a collector is not restarted while its previous run is pending.
+///
+/// On shutdown, running collectors get until their timeout to finish, their samples are
+/// written and the database is closed. SIGHUP reloads the config file from `config_path`.
+///
+/// Only one loop may write to a database: a second one fails on the [`InstanceLock`]
+/// unless `replace` is set, in which case the running one is stopped first.
+///
+/// The loop also answers [`Request`]s on the control socket ([`ipc::socket_path`]), so
+/// `symmetri ctl` can read live data without going through the database.
 pub fn collect_loop(
     interval_seconds: u64,
     db_path: Option<&Path>,
     sysfs_root: Option<&Path>,
+    config_path: Option<&Path>,
+    config: &Config,
+    replace: bool,
 ) -> Result<()> {
-    loop {
-        let _ = collect_once(db_path, sysfs_root)?;
-        thread::sleep(Duration::from_secs(interval_seconds));
+    let resolved_db = resolve_writable_db_path(db_path)?;
+    let _lock = if replace {
+        InstanceLock::acquire_replacing(&resolved_db, REPLACE_TIMEOUT)?
+    } else {
+        InstanceLock::acquire(&resolved_db)?
+    };
+    signals::install_handlers()?;
+    let mut conn = db::init_db_connection(&resolved_db)?;
+    record_versions(&conn, now_ts())?;
+    let root = sysfs_root.unwrap_or_else(|| Path::new("/sys/class/power_supply"));
+    let default_interval = Duration::from_secs(interval_seconds.max(1));
+    let mut config = config.clone();
+    let mut registry = CollectorRegistry::from_config(&config);
+    let mut schedule = build_schedule(&config, &registry, default_interval);
+
+    let server = match IpcServer::bind(&ipc::socket_path()) {
+        Ok(server) => {
+            info!(
+                "Listening for control requests on {}",
+                server.path().display()
+            );
+            Some(server)
+        }
+        Err(err) => {
+            warn!("Control socket disabled: {err:#}");
+            None
+        }
+    };
+    let mut status = DaemonStatus {
+        started_ts: now_ts(),
+        ..DaemonStatus::default()
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    let mut pipeline = Pipeline::default();
+    while !signals::shutdown_requested() {
+        let mut reload = signals::take_reload_request();
+        for client in server.iter().flat_map(|server| server.accept()) {
+            let response = match client.request {
+                Ok(Request::Latest) => status.latest(),
+                Ok(Request::Collect) => {
+                    schedule.run_all_now(Instant::now());
+                    json!({ "ok": true })
+                }
+                Ok(Request::Reload) => {
+                    reload = true;
+                    json!({ "ok": true })
+                }
+                Ok(Request::Stats) => status.stats(
+                    &resolved_db,
+                    registry.enabled(&config).map(|c| c.name()).collect(),
+                    pipeline.in_flight.keys().collect(),
+                ),
+                Err(ref raw) => ipc::unknown_request(raw),
+            };
+            client.reply(&response);
+        }
+        if reload {
+            match load_config(config_path) {
+                Ok(reloaded) => {
+                    info!("Reloaded configuration");
+                    config = reloaded;
+                    registry = CollectorRegistry::from_config(&config);
+                    schedule = build_schedule(&config, &registry, default_interval);
+                }
+                Err(err) => warn!("Keeping previous configuration: {err:#}"),
+            }
+        }
+
+        let due = schedule.take_due(Instant::now());
+        if !due.is_empty() {
+            let ts = now_ts();
+            let cycle = info_span!("cycle", ts);
+            let _entered = cycle.enter();
+            let tick = pipeline.start_tick(ts);
+            status.cycles += 1;
+            for name in due {
+                if name == BATTERY_COLLECTOR {
+                    let samples = allowed_batteries(&config, read_batteries(root, &config, ts));
+                    match store_batteries(&mut conn, &config, ts, &samples) {
+                        Ok(()) => status.record_batteries(&samples),
+                        Err(err) => warn!("Storing battery samples failed: {err:#}"),
+                    }
+                    continue;
+                }
+                let Some(collector) = registry.enabled(&config).find(|c| c.name() == name) else {
+                    continue;
+                };
+                if pipeline.in_flight.contains_key(&name) {
+                    debug!("Collector {name} is still running; skipping this run");
+                    continue;
+                }
+                let deadline = Instant::now() + config.collector_timeout(&name);
+                pipeline.launch(name, tick, deadline);
+                spawn_collector(collector, ts, sender.clone());
+            }
+        }
+
+        let next_wakeup = schedule
+            .next_due()
+            .into_iter()
+            .chain(pipeline.deadlines())
+            .min();
+        let wait = next_wakeup
+            .map(|next| next.saturating_duration_since(Instant::now()))
+            .unwrap_or(default_interval)
+            .min(SIGNAL_POLL);
+        let first = receiver.recv_timeout(wait).ok();
+        let results = first.into_iter().chain(receiver.try_iter());
+        match store_results(&mut conn, &config, &mut pipeline, results) {
+            Ok(stored) => status.record_metrics(&stored),
+            Err(err) => warn!("Storing collector results failed: {err:#}"),
+        }
+    }
+
+    match pipeline.in_flight.len() {
+        0 => info!("Shutting down"),
+        running => info!("Shutting down; waiting for {running} running collectors"),
+    }
+    while let Some(deadline) = pipeline.deadlines().min() {
+        let first = receiver
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            .ok();
+        let results = first.into_iter().chain(receiver.try_iter());
+        if let Err(err) = store_results(&mut conn, &config, &mut pipeline, results) {
+            warn!("Storing collector results failed: {err:#}");
+        }
+    }
+    conn.close().map_err(|(_, err)| err)?;
+    Ok(())
+}
+
+/// Writes one battery reading from `collect_loop` and runs the hooks and alerts on it.
+/// Errors are the caller's to log: one failed cycle (a locked database, a full disk) must
+/// not stop the daemon.
+fn store_batteries(
+    conn: &mut rusqlite::Connection,
+    config: &Config,
+    ts: f64,
+    samples: &[Sample],
+) -> Result<()> {
+    db::insert_all_samples(conn, samples, &[])?;
+    push_exporters(config, samples, &[]);
+    after_battery_insert(conn, ts, samples, config, true)?;
+    fire_alerts(conn, config, samples, &[])
+}
+
+/// Writes finished results and the overhead metrics of this batch; returns the samples
+/// written.
+fn store_results(
+    conn: &mut rusqlite::Connection,
+    config: &Config,
+    pipeline: &mut Pipeline,
+    results: impl Iterator<Item = CollectorResult>,
+) -> Result<Vec<MetricSample>> {
+    let Collection {
+        samples: mut metric_samples,
+        mut stats,
+    } = pipeline.settle(config, results);
+    let derived = pipeline.derived.derive(&config.derived, &metric_samples);
+    metric_samples.extend(derived);
+    privacy::scrub_metrics(&config.privacy, &mut metric_samples);
+    if !metric_samples.is_empty() {
+        let insert_started = Instant::now();
+        db::insert_all_samples(conn, &[], &metric_samples)?;
+        stats.db_insert = Some(insert_started.elapsed());
+        info!("Logged {} system metric records", metric_samples.len());
+        push_exporters(config, &[], &metric_samples);
+        record_usb_changes(conn, &metric_samples)?;
+        fire_alerts(conn, config, &[], &metric_samples)?;
+    }
+    store_internal(conn, config, now_ts(), &stats)?;
+    Ok(metric_samples)
+}
+
+/// A collector run started by `collect_loop` whose result has not arrived yet. A run that
+/// outlives its deadline stays here until it finishes so the collector is not started twice.
+struct InFlight {
+    tick: u64,
+    deadline: Instant,
+    timed_out: bool,
+}
+
+/// One scheduler wake-up; it completes once every collector it launched has reported.
+struct Tick {
+    ts: f64,
+    started: Instant,
+    pending: usize,
+}
+
+#[derive(Default)]
+struct Pipeline {
+    in_flight: HashMap<String, InFlight>,
+    ticks: HashMap<u64, Tick>,
+    next_tick: u64,
+    derived: DerivedState,
+}
+
+impl Pipeline {
+    fn start_tick(&mut self, ts: f64) -> u64 {
+        let id = self.next_tick;
+        self.next_tick += 1;
+        self.ticks.insert(
+            id,
+            Tick {
+                ts,
+                started: Instant::now(),
+                pending: 0,
+            },
+        );
+        id
+    }
+
+    fn launch(&mut self, name: String, tick: u64, deadline: Instant) {
+        if let Some(tick) = self.ticks.get_mut(&tick) {
+            tick.pending += 1;
+        }
+        self.in_flight.insert(
+            name,
+            InFlight {
+                tick,
+                deadline,
+                timed_out: false,
+            },
+        );
+    }
+
+    fn deadlines(&self) -> impl Iterator<Item = Instant> + '_ {
+        self.in_flight
+            .values()
+            .filter(|run| !run.timed_out)
+            .map(|run| run.deadline)
+    }
+
+    fn finish_run(&mut self, tick: u64) {
+        if let Some(tick) = self.ticks.get_mut(&tick) {
+            tick.pending = tick.pending.saturating_sub(1);
+        }
+    }
+
+    /// Marks overdue runs as timed out, collects runs that finished in time and closes
+    /// ticks with nothing left pending.
+    fn settle(
+        &mut self,
+        config: &Config,
+        results: impl Iterator<Item = CollectorResult>,
+    ) -> Collection {
+        let mut collection = Collection::default();
+        let now = Instant::now();
+        let mut overdue = Vec::new();
+        for (name, run) in self.in_flight.iter_mut() {
+            if !run.timed_out && run.deadline <= now {
+                run.timed_out = true;
+                warn!("Collector {name} timed out; its late samples will be discarded");
+                overdue.push((name.clone(), run.tick));
+            }
+        }
+        for (name, tick) in overdue {
+            collection.stats.failed.push(name);
+            self.finish_run(tick);
+        }
+
+        for result in results {
+            match self.in_flight.remove(&result.name) {
+                Some(run) if !run.timed_out => {
+                    self.finish_run(run.tick);
+                    collection.add(result, config);
+                }
+                _ => debug!("Discarded late samples from {}", result.name),
+            }
+        }
+
+        let finished: Vec<u64> = self
+            .ticks
+            .iter()
+            .filter(|(_, tick)| tick.pending == 0)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in finished {
+            if let Some(tick) = self.ticks.remove(&id) {
+                collection
+                    .stats
+                    .cycles
+                    .push((tick.ts, tick.started.elapsed()));
+            }
+        }
+        collection
     }
 }
 
@@ -116,6 +1094,143 @@ mod tests {
         }
     }
 
+    struct FixedCollector(&'static str);
+
+    impl Collector for FixedCollector {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn kinds(&self) -> &[MetricKind] {
+            &[MetricKind::PowerDraw]
+        }
+
+        fn collect(&self, ts: f64) -> Vec<MetricSample> {
+            vec![MetricSample::new(
+                ts,
+                MetricKind::PowerDraw,
+                self.0,
+                Some(1.0),
+                Some("W"),
+                serde_json::Value::Null,
+            )]
+        }
+    }
+
+    #[test]
+    fn registry_skips_disabled_collectors() {
+        let mut registry = CollectorRegistry::default();
+        registry.register(Box::new(FixedCollector("meter_a")));
+        registry.register(Box::new(FixedCollector("meter_b")));
+        let config = crate::config::parse_config(
+            "[collectors.meter_b]\nenabled = false\n[collectors.typo]\nenabled = false\n",
+        )
+        .unwrap();
+
+        let collection = registry.collect(&config, 5.0);
+        assert_eq!(collection.samples.len(), 1);
+        assert_eq!(collection.samples[0].source, "meter_a");
+        assert_eq!(collection.stats.timings.len(), 1);
+        assert_eq!(registry.unknown_names(&config), vec!["typo"]);
+    }
+
+    struct HungCollector;
+
+    impl Collector for HungCollector {
+        fn name(&self) -> &str {
+            "hung"
+        }
+
+        fn kinds(&self) -> &[MetricKind] {
+            &[]
+        }
+
+        fn collect(&self, _ts: f64) -> Vec<MetricSample> {
+            thread::sleep(Duration::from_secs(30));
+            Vec::new()
+        }
+    }
+
+    #[test]
+    fn hung_collectors_do_not_stall_the_cycle() {
+        let mut registry = CollectorRegistry::default();
+        registry.register(Box::new(HungCollector));
+        registry.register(Box::new(FixedCollector("meter_a")));
+        let config =
+            crate::config::parse_config("[collectors.hung]\ntimeout_seconds = 0.2\n").unwrap();
+
+        let started = Instant::now();
+        let collection = registry.collect(&config, 5.0);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(collection.samples.len(), 1);
+        assert_eq!(collection.samples[0].source, "meter_a");
+        assert_eq!(collection.stats.failed, vec!["hung"]);
+    }
+
+    struct PanickingCollector;
+
+    impl Collector for PanickingCollector {
+        fn name(&self) -> &str {
+            "broken"
+        }
+
+        fn kinds(&self) -> &[MetricKind] {
+            &[]
+        }
+
+        fn collect(&self, _ts: f64) -> Vec<MetricSample> {
+            panic!("sensor vanished")
+        }
+    }
+
+    #[test]
+    fn panicking_collectors_count_as_errors() {
+        let mut registry = CollectorRegistry::default();
+        registry.register(Box::new(PanickingCollector));
+        let collection = registry.collect(&Config::default(), 5.0);
+        assert!(collection.samples.is_empty());
+        assert_eq!(collection.stats.failed, vec!["broken"]);
+    }
+
+    #[test]
+    fn preview_reads_batteries_without_a_database() {
+        let tmp = tempfile::tempdir().unwrap();
+        let bat = tmp.path().join("BAT0");
+        std::fs::create_dir(&bat).unwrap();
+        std::fs::write(bat.join("type"), "Battery\n").unwrap();
+        std::fs::write(bat.join("energy_now"), "20000000\n").unwrap();
+        std::fs::write(bat.join("energy_full"), "40000000\n").unwrap();
+        let disabled: String = CollectorRegistry::from_config(&Config::default())
+            .collectors()
+            .map(|c| format!("[collectors.{}]\nenabled = false\n", c.name()))
+            .collect();
+        let config = crate::config::parse_config(&disabled).unwrap();
+
+        let preview = preview_once(Some(tmp.path()), &config);
+        let json = serde_json::to_value(&preview).unwrap();
+        assert_eq!(json["batteries"][0]["percentage"], 50.0);
+        assert_eq!(json["metrics"], serde_json::json!([]));
+        assert!(json.get("failed_collectors").is_none());
+        assert_eq!(
+            serde_json::to_value(MetricKind::PowerDraw).unwrap(),
+            "power_draw"
+        );
+    }
+
+    #[test]
+    fn builtin_collector_names_are_unique() {
+        let registry = CollectorRegistry::from_config(&Config::default());
+        let mut names: Vec<&str> = registry.collectors().map(|c| c.name()).collect();
+        let total = names.len();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), total);
+        assert_eq!(
+            names.contains(&"scripts"),
+            cfg!(feature = "collector-scripts")
+        );
+    }
+
     #[test]
     fn resolve_db_path_prefers_argument() {
         let _guard = EnvGuard::set("BATTERY_MONITOR_DB", "/tmp/should_not_use.db");
@@ -139,10 +1254,84 @@ mod tests {
         assert_eq!(resolved, PathBuf::from("/tmp/from_env.db"));
     }
 
+    #[test]
+    fn default_db_path_is_under_data_dir_unless_only_legacy_file_exists() {
+        let tmp = tempfile::tempdir().unwrap();
+        let data_dir = tmp.path().join("data");
+        let legacy = tmp.path().join("old/metrics.db");
+        let current = data_dir.join("symmetri/symmetri.sqlite");
+        assert_eq!(db_path_in(&data_dir, Some(&legacy)), current);
+
+        std::fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+        std::fs::write(&legacy, "").unwrap();
+        assert_eq!(db_path_in(&data_dir, Some(&legacy)), legacy);
+
+        std::fs::create_dir_all(current.parent().unwrap()).unwrap();
+        std::fs::write(&current, "").unwrap();
+        assert_eq!(db_path_in(&data_dir, Some(&legacy)), current);
+    }
+
+    #[test]
+    fn system_db_is_used_when_the_user_has_none() {
+        let tmp = tempfile::tempdir().unwrap();
+        let user = tmp.path().join("home/symmetri.sqlite");
+        let system = tmp.path().join("var/symmetri.sqlite");
+        assert_eq!(
+            discover_db(user.clone(), system.clone()),
+            (user.clone(), Origin::Default)
+        );
+
+        std::fs::create_dir_all(system.parent().unwrap()).unwrap();
+        std::fs::write(&system, "").unwrap();
+        assert_eq!(
+            discover_db(user.clone(), system.clone()),
+            (system.clone(), Origin::System)
+        );
+
+        std::fs::create_dir_all(user.parent().unwrap()).unwrap();
+        std::fs::write(&user, "").unwrap();
+        assert_eq!(discover_db(user.clone(), system), (user, Origin::Default));
+    }
+
     #[test]
     fn resolve_db_path_accepts_legacy_env() {
         let _guard = EnvGuard::set("BATTERY_MONITOR_DB", "/tmp/legacy.db");
         let resolved = resolve_db_path(None);
         assert_eq!(resolved, PathBuf::from("/tmp/legacy.db"));
     }
+
+    #[test]
+    fn loop_results_are_scrubbed_before_storage() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("loop.db");
+        let mut conn = db::init_db_connection(&db_path).unwrap();
+        let config = crate::config::parse_config("[privacy]\nssids = false\n").unwrap();
+        let mut pipeline = Pipeline::default();
+        let tick = pipeline.start_tick(1.0);
+        pipeline.launch(
+            "wifi".to_string(),
+            tick,
+            Instant::now() + Duration::from_secs(60),
+        );
+        let result = CollectorResult {
+            name: "wifi".to_string(),
+            elapsed: Duration::ZERO,
+            samples: Some(vec![MetricSample::new(
+                1.0,
+                MetricKind::Wifi,
+                "wlan0",
+                Some(-60.0),
+                Some("dBm"),
+                serde_json::json!({ "ssid": "CorpWiFi", "frequency_mhz": 5180 }),
+            )]),
+        };
+
+        store_results(&mut conn, &config, &mut pipeline, std::iter::once(result)).unwrap();
+        let stored = db::fetch_metric_samples(&db_path, None, Some(&[MetricKind::Wifi])).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(
+            stored[0].details,
+            serde_json::json!({ "frequency_mhz": 5180 })
+        );
+    }
 }
@@ -0,0 +1,119 @@
+#[cfg(feature = "completions")]
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "completions")]
+use anyhow::Context;
+use anyhow::Result;
+use clap::{Command, ValueEnum};
+#[cfg(feature = "completions")]
+use clap_mangen::Man;
+
+#[cfg(not(feature = "completions"))]
+const DISABLED: &str =
+    "symmetri was built without completions; rebuild with `--features completions`";
+
+/// Shells `symmetri completions` can generate a script for.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Nushell,
+}
+
+/// Writes the completion script for `cmd` to `out`.
+#[cfg(feature = "completions")]
+pub fn write_completions(shell: Shell, cmd: &mut Command, out: &mut dyn Write) -> Result<()> {
+    let bin_name = cmd.get_name().to_string();
+    match shell {
+        Shell::Bash => clap_complete::generate(clap_complete::Shell::Bash, cmd, bin_name, out),
+        Shell::Zsh => clap_complete::generate(clap_complete::Shell::Zsh, cmd, bin_name, out),
+        Shell::Fish => clap_complete::generate(clap_complete::Shell::Fish, cmd, bin_name, out),
+        Shell::Nushell => {
+            clap_complete::generate(clap_complete_nushell::Nushell, cmd, bin_name, out)
+        }
+    }
+    Ok(())
+}
+
+/// The top-level man page (`symmetri.1`).
+#[cfg(feature = "completions")]
+pub fn write_man_page(cmd: Command, out: &mut dyn Write) -> Result<()> {
+    Man::new(cmd).render(out)?;
+    Ok(())
+}
+
+/// Writes `symmetri.1` plus one page per subcommand (`symmetri-report.1`,
+/// `symmetri-db-stats.1`, ...) into `dir` and returns the files written.
+#[cfg(feature = "completions")]
+pub fn write_man_pages(cmd: Command, dir: &Path) -> Result<Vec<PathBuf>> {
+    fn generate(cmd: Command, dir: &Path, written: &mut Vec<PathBuf>) -> Result<()> {
+        for sub in cmd.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+            generate(sub.clone(), dir, written)?;
+        }
+        let path = Man::new(cmd)
+            .generate_to(dir)
+            .with_context(|| format!("Failed to write man page into {}", dir.display()))?;
+        written.push(path);
+        Ok(())
+    }
+
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    // Building fills in the `symmetri-<sub>` display names the page names come from.
+    let mut cmd = cmd.disable_help_subcommand(true);
+    cmd.build();
+    let mut written = Vec::new();
+    generate(cmd, dir, &mut written)?;
+    written.sort();
+    Ok(written)
+}
+
+#[cfg(not(feature = "completions"))]
+pub fn write_completions(_shell: Shell, _cmd: &mut Command, _out: &mut dyn Write) -> Result<()> {
+    anyhow::bail!(DISABLED)
+}
+
+#[cfg(not(feature = "completions"))]
+pub fn write_man_page(_cmd: Command, _out: &mut dyn Write) -> Result<()> {
+    anyhow::bail!(DISABLED)
+}
+
+#[cfg(not(feature = "completions"))]
+pub fn write_man_pages(_cmd: Command, _dir: &Path) -> Result<Vec<PathBuf>> {
+    anyhow::bail!(DISABLED)
+}
+
+#[cfg(all(test, feature = "completions"))]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+    use crate::cli::Cli;
+
+    #[test]
+    fn completions_and_man_pages_cover_subcommands() {
+        for shell in Shell::value_variants() {
+            let mut script = Vec::new();
+            write_completions(*shell, &mut Cli::command(), &mut script).unwrap();
+            let script = String::from_utf8(script).unwrap();
+            assert!(script.contains("symmetri"), "{shell:?}");
+            assert!(script.contains("analyze"), "{shell:?}");
+        }
+
+        let tmp = tempfile::tempdir().unwrap();
+        let pages = write_man_pages(Cli::command(), tmp.path()).unwrap();
+        let names: Vec<String> = pages
+            .iter()
+            .map(|page| page.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        for expected in ["symmetri.1", "symmetri-report.1", "symmetri-db-dedupe.1"] {
+            assert!(names.iter().any(|name| name == expected), "{names:?}");
+        }
+        assert!(!names.iter().any(|name| name == "symmetri-help.1"));
+        let report = fs::read_to_string(tmp.path().join("symmetri-report.1")).unwrap();
+        assert!(report.starts_with(".ie \\n(.g .ds Aq"), "{report}");
+        assert!(report.contains("\\-\\-days"));
+    }
+}
@@ -0,0 +1,927 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::alerts::{AlertMetric, Comparator, Severity, SinkKind};
+use crate::derived::Expr;
+use crate::env::{self, Origin};
+use crate::metrics::MetricKind;
+use crate::sensors::sensor_matches;
+use crate::webhook;
+
+/// How long a collector may run before its samples are given up on. Script collectors
+/// also kill each script after `[scripts] timeout_seconds`.
+pub const DEFAULT_COLLECTOR_TIMEOUT_SECONDS: f64 = 15.0;
+
+/// Settings read from `~/.config/symmetri/config.toml`. Every section is optional; a
+/// missing file behaves like an empty one.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub runtime: RuntimeConfig,
+    pub smoothing: SmoothingConfig,
+    pub power: PowerConfig,
+    /// Unit overrides keyed by battery name (`[quirks.BAT0]`) or sysfs path.
+    pub quirks: BTreeMap<String, QuirkOverrides>,
+    pub hooks: HooksConfig,
+    pub scripts: ScriptsConfig,
+    pub energy: EnergyConfig,
+    pub health: HealthConfig,
+    pub graph: GraphConfig,
+    pub sensors: SensorsConfig,
+    pub location: LocationConfig,
+    pub privacy: PrivacyConfig,
+    pub disks: DisksConfig,
+    pub cgroups: CgroupsConfig,
+    pub influx: InfluxConfig,
+    pub otlp: OtlpConfig,
+    /// Per-collector settings keyed by collector name (`[collectors.gpu]`).
+    pub collectors: BTreeMap<String, CollectorSettings>,
+    /// Metrics computed from others at collection time, keyed by the source they are
+    /// stored under (`[derived.total_power]`).
+    pub derived: BTreeMap<String, DerivedConfig>,
+    /// Alert rules keyed by name (`[alerts.cpu_hot]`).
+    pub alerts: BTreeMap<String, AlertConfig>,
+    /// Endpoints alerts and `summary --webhook` post to (`[webhooks.slack]`).
+    pub webhooks: BTreeMap<String, WebhookConfig>,
+    pub ntfy: NtfyConfig,
+    pub gotify: GotifyConfig,
+    pub email: EmailConfig,
+}
+
+impl Config {
+    pub fn collector_enabled(&self, name: &str) -> bool {
+        self.collectors
+            .get(name)
+            .map(|settings| settings.enabled)
+            .unwrap_or(true)
+    }
+
+    /// Whether `[collectors.<name>] allow` and `deny` let a source through. Patterns match
+    /// the source or its raw id (`coretemp/temp1` for hwmon sensors, the sysfs path of a
+    /// battery).
+    pub fn source_allowed(&self, name: &str, source: &str, raw_id: Option<&str>) -> bool {
+        let Some(settings) = self.collectors.get(name) else {
+            return true;
+        };
+        let matches = |pattern: &String| {
+            sensor_matches(pattern, source) || raw_id.is_some_and(|id| sensor_matches(pattern, id))
+        };
+        (settings.allow.is_empty() || settings.allow.iter().any(matches))
+            && !settings.deny.iter().any(matches)
+    }
+
+    pub fn collector_timeout(&self, name: &str) -> Duration {
+        let seconds = self
+            .collectors
+            .get(name)
+            .and_then(|settings| settings.timeout_seconds)
+            .unwrap_or(DEFAULT_COLLECTOR_TIMEOUT_SECONDS);
+        Duration::from_secs_f64(seconds.max(0.0))
+    }
+
+    /// Unit overrides for the battery at `path`; empty when none are configured.
+    pub fn quirks_for(&self, path: &Path) -> QuirkOverrides {
+        let name = path.file_name().and_then(|name| name.to_str());
+        name.and_then(|name| self.quirks.get(name))
+            .or_else(|| path.to_str().and_then(|path| self.quirks.get(path)))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn collector_interval(&self, name: &str) -> Option<Duration> {
+        self.collectors
+            .get(name)
+            .and_then(|settings| settings.interval_seconds)
+            .map(|seconds| Duration::from_secs(seconds.max(1)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CollectorSettings {
+    pub enabled: bool,
+    /// Seconds between runs in `collect --interval`; defaults to the global interval.
+    pub interval_seconds: Option<u64>,
+    /// Seconds to wait for a run before dropping its samples.
+    pub timeout_seconds: Option<f64>,
+    /// Source patterns to store (`*` matches any run, case-insensitive); empty stores all.
+    pub allow: Vec<String>,
+    /// Source patterns never stored, even when `allow` matches.
+    pub deny: Vec<String>,
+}
+
+impl Default for CollectorSettings {
+    fn default() -> Self {
+        CollectorSettings {
+            enabled: true,
+            interval_seconds: None,
+            timeout_seconds: None,
+            allow: Vec::new(),
+            deny: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuntimeModel {
+    /// Full capacity divided by the plain average discharge over the window.
+    Average,
+    /// Exponentially-weighted recent discharge rate.
+    #[default]
+    Ewma,
+    /// EWMA regression of discharge rate against CPU load and screen brightness, evaluated
+    /// at the current readings; CPU load alone without brightness readings.
+    Load,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RuntimeConfig {
+    pub model: RuntimeModel,
+    /// How quickly older discharge segments lose weight in the EWMA models.
+    pub half_life_minutes: f64,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        RuntimeConfig {
+            model: RuntimeModel::default(),
+            half_life_minutes: 30.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmoothingMethod {
+    /// Raw gauge readings.
+    #[default]
+    None,
+    /// Centered running median over `window` readings.
+    Median,
+    /// Kalman filter tracking the level and its drain rate.
+    Kalman,
+}
+
+/// Filtering of the battery percentage before reports compute %/h and draw graphs, for
+/// gauges that jitter by a few percent between readings. The database keeps raw values.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct SmoothingConfig {
+    pub method: SmoothingMethod,
+    /// Readings per median window; even values are rounded up.
+    pub window: usize,
+    /// Kalman: standard deviation of the gauge jitter, in percent.
+    pub measurement_noise_pct: f64,
+    /// Kalman: how far the drain rate may wander in an hour, in %/h.
+    pub rate_drift_pct_h: f64,
+}
+
+impl Default for SmoothingConfig {
+    fn default() -> Self {
+        SmoothingConfig {
+            method: SmoothingMethod::default(),
+            window: 5,
+            measurement_noise_pct: 2.0,
+            rate_drift_pct_h: 5.0,
+        }
+    }
+}
+
+/// Sanity bounds for power readings (power_draw samples and the watts implied by energy
+/// changes). Readings outside them are left out of averages and graphs and counted in
+/// the report; the database keeps them.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PowerConfig {
+    /// Readings above this many watts are rejected.
+    pub max_watts: f64,
+    /// Also reject readings further than this many median absolute deviations from the
+    /// median of their source and direction; 0 turns it off.
+    pub outlier_mads: f64,
+}
+
+impl Default for PowerConfig {
+    fn default() -> Self {
+        PowerConfig {
+            max_watts: 150.0,
+            outlier_mads: 0.0,
+        }
+    }
+}
+
+/// Scale of a sysfs power-supply attribute. The kernel ABI asks for micro-units (µWh,
+/// µAh, µV, µW, µA), but some drivers write milli-units.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnitScale {
+    #[default]
+    Micro,
+    Milli,
+}
+
+impl UnitScale {
+    /// Multiplier from the raw attribute to W, Wh, A, Ah or V.
+    pub fn factor(self) -> f64 {
+        match self {
+            UnitScale::Micro => 1e-6,
+            UnitScale::Milli => 1e-3,
+        }
+    }
+}
+
+/// Forced unit scales for one battery; unset families are detected from the values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct QuirkOverrides {
+    /// `energy_now`, `energy_full`, `energy_full_design`.
+    pub energy: Option<UnitScale>,
+    /// `charge_now`, `charge_full`, `charge_full_design`.
+    pub charge: Option<UnitScale>,
+    pub voltage: Option<UnitScale>,
+    pub power: Option<UnitScale>,
+    pub current: Option<UnitScale>,
+}
+
+/// Electricity price used for the cost lines in `report`; no cost is shown without one.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct EnergyConfig {
+    pub price_per_kwh: Option<f64>,
+    /// Printed after the amount, e.g. "EUR".
+    pub currency: String,
+}
+
+impl EnergyConfig {
+    pub fn cost(&self, wh: f64) -> Option<f64> {
+        self.price_per_kwh.map(|price| wh / 1000.0 * price)
+    }
+}
+
+/// When `symmetri health --notify` warns about wear: once a battery's trend is projected
+/// to reach `warn_below_pct` of its design capacity within `warn_within_days`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    pub warn_below_pct: f64,
+    pub warn_within_days: f64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        HealthConfig {
+            warn_below_pct: 80.0,
+            warn_within_days: 90.0,
+        }
+    }
+}
+
+/// Renames for temperature sources, applied when collecting and when reporting.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct SensorsConfig {
+    /// Keys are a source name ("NVMe Composite", "acpitz") or a raw hwmon id
+    /// ("nvme/temp1").
+    pub names: BTreeMap<String, String>,
+}
+
+impl SensorsConfig {
+    pub fn rename(&self, source: &str, raw_id: Option<&str>) -> Option<&str> {
+        raw_id
+            .and_then(|id| self.names.get(id))
+            .or_else(|| self.names.get(source))
+            .map(String::as_str)
+    }
+}
+
+/// The connected Wi-Fi network, stored with the wifi samples so reports can break drain
+/// down by place.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct LocationConfig {
+    /// `false` keeps SSIDs out of the database entirely.
+    pub enabled: bool,
+    /// Store a hash of the SSID instead of its name. It hides the name from anyone
+    /// reading the database, though a known SSID can still be matched against it.
+    pub hash_ssids: bool,
+    /// Place names keyed by SSID or by its hash (`ssid-…`, see `collect --dry-run`).
+    pub names: BTreeMap<String, String>,
+}
+
+impl Default for LocationConfig {
+    fn default() -> Self {
+        LocationConfig {
+            enabled: true,
+            hash_ssids: false,
+            names: BTreeMap::new(),
+        }
+    }
+}
+
+impl LocationConfig {
+    /// The place name given to `ssid` (as stored), else the SSID itself.
+    pub fn place<'a>(&'a self, ssid: &'a str) -> &'a str {
+        self.names.get(ssid).map(String::as_str).unwrap_or(ssid)
+    }
+}
+
+/// One `[derived.<name>]` metric.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DerivedConfig {
+    /// Kind the result is stored as, which decides where reports show it.
+    pub kind: MetricKind,
+    pub unit: Option<String>,
+    pub expr: Expr,
+}
+
+/// One `[alerts.<name>]` rule.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AlertConfig {
+    pub metric: AlertMetric,
+    pub comparator: Comparator,
+    pub threshold: f64,
+    /// Minutes the condition has to hold before the alert fires.
+    #[serde(default)]
+    pub for_minutes: f64,
+    /// Minutes before an alert that cleared may fire again.
+    #[serde(default = "default_cooldown_minutes")]
+    pub cooldown_minutes: f64,
+    #[serde(default)]
+    pub severity: Severity,
+    /// Only battery readings with this status (`Discharging`, ...) count.
+    pub status: Option<String>,
+    #[serde(default = "default_sinks")]
+    pub sinks: Vec<SinkKind>,
+    /// Run by the `command` sink.
+    pub command: Option<String>,
+    /// A `[webhooks]` entry or URL the `webhook` sink posts to.
+    pub webhook: Option<String>,
+    /// Topic the `ntfy` sink publishes to, instead of `[ntfy] topic`.
+    pub ntfy_topic: Option<String>,
+}
+
+fn default_cooldown_minutes() -> f64 {
+    60.0
+}
+
+fn default_sinks() -> Vec<SinkKind> {
+    vec![SinkKind::Desktop]
+}
+
+impl AlertConfig {
+    /// Fails when a listed sink lacks its setting.
+    fn check(&self, name: &str, config: &Config) -> Result<()> {
+        if self.sinks.contains(&SinkKind::Command) && self.command.is_none() {
+            anyhow::bail!("[alerts.{name}] lists the command sink but sets no command");
+        }
+        if self.sinks.contains(&SinkKind::Webhook) && self.webhook.is_none() {
+            anyhow::bail!("[alerts.{name}] lists the webhook sink but sets no webhook");
+        }
+        if let Some(webhook) = &self.webhook {
+            if webhook::resolve(webhook, &config.webhooks).is_none() {
+                anyhow::bail!(
+                    "[alerts.{name}] webhook {webhook:?} is neither a URL nor a [webhooks] entry"
+                );
+            }
+        }
+        if self.sinks.contains(&SinkKind::Ntfy)
+            && self.ntfy_topic.is_none()
+            && config.ntfy.topic.is_none()
+        {
+            anyhow::bail!(
+                "[alerts.{name}] lists the ntfy sink but neither it nor [ntfy] sets a topic"
+            );
+        }
+        if self.sinks.contains(&SinkKind::Gotify) && config.gotify.server.is_none() {
+            anyhow::bail!("[alerts.{name}] lists the gotify sink but [gotify] sets no server");
+        }
+        Ok(())
+    }
+}
+
+/// One `[webhooks.<name>]` endpoint.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    /// `http://` or `https://` (the latter through curl).
+    pub url: String,
+    /// Body with `{{field}}` placeholders; the event as JSON when unset.
+    pub template: Option<String>,
+    pub content_type: String,
+    /// Extra request headers, e.g. `Authorization`.
+    pub headers: BTreeMap<String, String>,
+    pub timeout_seconds: f64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        WebhookConfig {
+            url: String::new(),
+            template: None,
+            content_type: "application/json".to_string(),
+            headers: BTreeMap::new(),
+            timeout_seconds: 10.0,
+        }
+    }
+}
+
+/// The ntfy server alerts with the `ntfy` sink publish to.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct NtfyConfig {
+    pub server: String,
+    /// Default topic; a rule's `ntfy_topic` wins.
+    pub topic: Option<String>,
+    /// Access token; `SYMMETRI_NTFY_TOKEN` wins.
+    pub token: Option<String>,
+    pub timeout_seconds: f64,
+}
+
+impl Default for NtfyConfig {
+    fn default() -> Self {
+        NtfyConfig {
+            server: "https://ntfy.sh".to_string(),
+            topic: None,
+            token: None,
+            timeout_seconds: 10.0,
+        }
+    }
+}
+
+/// The Gotify server alerts with the `gotify` sink are sent to.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct GotifyConfig {
+    pub server: Option<String>,
+    /// Application token; `SYMMETRI_GOTIFY_TOKEN` wins.
+    pub token: Option<String>,
+    pub timeout_seconds: f64,
+}
+
+impl Default for GotifyConfig {
+    fn default() -> Self {
+        GotifyConfig {
+            server: None,
+            token: None,
+            timeout_seconds: 10.0,
+        }
+    }
+}
+
+/// How `report --email` talks to the SMTP server.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Plain connection upgraded with STARTTLS (port 587).
+    #[default]
+    Starttls,
+    /// TLS from the start (port 465).
+    Tls,
+    /// No encryption, for a relay on the local machine or network (port 25).
+    None,
+}
+
+/// The SMTP server `report --email` sends through.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct EmailConfig {
+    /// `SYMMETRI_SMTP_SERVER` wins.
+    pub server: Option<String>,
+    /// Defaults to the usual port for `security`.
+    pub port: Option<u16>,
+    pub security: SmtpSecurity,
+    /// `SYMMETRI_SMTP_USER` wins.
+    pub username: Option<String>,
+    /// `SYMMETRI_SMTP_PASSWORD` wins.
+    pub password: Option<String>,
+    /// Sender address; defaults to the username.
+    pub from: Option<String>,
+    pub timeout_seconds: f64,
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        EmailConfig {
+            server: None,
+            port: None,
+            security: SmtpSecurity::default(),
+            username: None,
+            password: None,
+            from: None,
+            timeout_seconds: 30.0,
+        }
+    }
+}
+
+/// Identifying fields collectors record. Turning one off drops it from every sample
+/// before it is stored or exported, whatever the collector's own settings say.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct PrivacyConfig {
+    /// Wi-Fi network names (or their `[location]` hashes).
+    pub ssids: bool,
+    /// Names of processes using the webcam, and of the units in cgroup sources (hashed
+    /// when off).
+    pub process_names: bool,
+    /// The `host.name` attribute sent to the OTLP endpoint.
+    pub hostname: bool,
+    /// Battery serial numbers in the battery metadata.
+    pub serial_numbers: bool,
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        PrivacyConfig {
+            ssids: true,
+            process_names: true,
+            hostname: true,
+            serial_numbers: true,
+        }
+    }
+}
+
+/// Filesystems recorded by the disk collector, one source per mountpoint.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct DisksConfig {
+    /// Mountpoints such as "/", "/home" or "/nix"; "*" records every real filesystem.
+    /// Pseudo filesystems (tmpfs, proc, overlay, ...) and unmounted paths are skipped.
+    pub mounts: Vec<String>,
+}
+
+impl Default for DisksConfig {
+    fn default() -> Self {
+        DisksConfig {
+            mounts: vec!["/".to_string()],
+        }
+    }
+}
+
+/// cgroup v2 groups whose CPU, memory and IO are recorded; nothing is collected without
+/// `paths`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CgroupsConfig {
+    /// Paths below the cgroup root; `*` matches within one component, e.g.
+    /// "system.slice/docker-*.scope".
+    pub paths: Vec<String>,
+    /// Defaults to `/sys/fs/cgroup`.
+    pub root: Option<PathBuf>,
+}
+
+/// Where `collect` pushes every batch as InfluxDB line protocol; nothing is sent without
+/// `url`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct InfluxConfig {
+    /// Write endpoint, e.g. "http://localhost:8086/api/v2/write?org=home&bucket=symmetri"
+    /// or VictoriaMetrics' "http://localhost:8428/write".
+    pub url: Option<String>,
+    /// Sent as `Authorization: Token <token>`.
+    pub token: Option<String>,
+    pub timeout_seconds: f64,
+}
+
+impl Default for InfluxConfig {
+    fn default() -> Self {
+        InfluxConfig {
+            url: None,
+            token: None,
+            timeout_seconds: 5.0,
+        }
+    }
+}
+
+/// Where `collect` pushes every batch as OTLP metrics (needs the `otlp` build feature);
+/// nothing is sent without `endpoint`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct OtlpConfig {
+    /// OTLP/HTTP metrics endpoint, e.g. "http://localhost:4318/v1/metrics".
+    pub endpoint: Option<String>,
+    /// Extra request headers, e.g. an API key for a hosted backend.
+    pub headers: BTreeMap<String, String>,
+    pub timeout_seconds: f64,
+}
+
+impl Default for OtlpConfig {
+    fn default() -> Self {
+        OtlpConfig {
+            endpoint: None,
+            headers: BTreeMap::new(),
+            timeout_seconds: 5.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphTheme {
+    #[default]
+    Light,
+    Dark,
+    /// The colors set in `[graph]`, falling back to the light theme for unset ones.
+    Custom,
+}
+
+/// Look of the PNG graphs written by `report --graph`. Colors are `#rrggbb` strings and
+/// only apply with `theme = "custom"`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct GraphConfig {
+    pub theme: GraphTheme,
+    pub width: u32,
+    /// Height of each stacked chart.
+    pub row_height: u32,
+    /// Scales the image size, fonts and lines; 96 draws at the nominal size.
+    pub dpi: u32,
+    pub font: String,
+    /// Longer series are downsampled to this many points per line; 0 plots every sample.
+    pub max_points: usize,
+    pub background: Option<String>,
+    pub foreground: Option<String>,
+    pub grid: Option<String>,
+    /// Line colors, used in order and repeated as needed.
+    pub colors: Vec<String>,
+    /// Where `report -g` saves graphs; defaults to `$XDG_PICTURES_DIR`.
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for GraphConfig {
+    fn default() -> Self {
+        GraphConfig {
+            theme: GraphTheme::default(),
+            width: 1280,
+            row_height: 260,
+            dpi: 96,
+            font: "sans-serif".to_string(),
+            max_points: 2000,
+            background: None,
+            foreground: None,
+            grid: None,
+            colors: Vec::new(),
+            dir: None,
+        }
+    }
+}
+
+impl GraphConfig {
+    /// Directory `report -g` saves into: `dir` (a leading `~/` is the home directory),
+    /// else the XDG pictures directory, else `None` for the working directory.
+    pub fn output_dir(&self) -> Option<PathBuf> {
+        match &self.dir {
+            Some(dir) => match (dir.strip_prefix("~"), dirs::home_dir()) {
+                (Ok(rest), Some(home)) => Some(home.join(rest)),
+                _ => Some(dir.clone()),
+            },
+            None => dirs::picture_dir(),
+        }
+    }
+}
+
+/// Shell commands run by `collect` on battery transitions. Each receives SYMMETRI_EVENT,
+/// SYMMETRI_PERCENT and SYMMETRI_STATUS in its environment.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    pub on_low_battery: Option<String>,
+    pub on_critical: Option<String>,
+    pub on_unplug: Option<String>,
+    pub on_full: Option<String>,
+    pub low_percent: f64,
+    pub critical_percent: f64,
+    /// A single `collect` kills hooks still running after this many seconds;
+    /// `collect --interval` leaves them running.
+    pub timeout_seconds: f64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        HooksConfig {
+            on_low_battery: None,
+            on_critical: None,
+            on_unplug: None,
+            on_full: None,
+            low_percent: 20.0,
+            critical_percent: 5.0,
+            timeout_seconds: 30.0,
+        }
+    }
+}
+
+impl HooksConfig {
+    pub fn is_empty(&self) -> bool {
+        self.on_low_battery.is_none()
+            && self.on_critical.is_none()
+            && self.on_unplug.is_none()
+            && self.on_full.is_none()
+    }
+}
+
+/// Executables whose JSON-lines output is stored as metrics on every collection.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ScriptsConfig {
+    /// Defaults to `~/.config/symmetri/collectors.d`.
+    pub dir: Option<PathBuf>,
+    /// Scripts still running after this many seconds are killed.
+    pub timeout_seconds: f64,
+}
+
+impl Default for ScriptsConfig {
+    fn default() -> Self {
+        ScriptsConfig {
+            dir: None,
+            timeout_seconds: 10.0,
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME`, else `~/.config` in the home directory; `None` when the home
+/// directory is unknown, since a literal `~` is never expanded.
+pub fn user_config_dir() -> Option<PathBuf> {
+    dirs::config_dir().or_else(|| dirs::home_dir().map(|home| home.join(".config")))
+}
+
+pub fn default_config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("~/.config"))
+        .join("symmetri")
+        .join("config.toml")
+}
+
+pub fn resolve_config_path(config_path: Option<&Path>) -> PathBuf {
+    resolve_config_path_with_origin(config_path).0
+}
+
+/// The config path: `--config`, else `SYMMETRI_CONFIG`, else [`default_config_path`],
+/// with where it came from.
+pub fn resolve_config_path_with_origin(config_path: Option<&Path>) -> (PathBuf, Origin) {
+    if let Some(path) = config_path {
+        return (path.to_path_buf(), Origin::Flag);
+    }
+    if let Some((env_path, origin)) = env::CONFIG.get() {
+        return (PathBuf::from(env_path), origin);
+    }
+    (default_config_path(), Origin::Default)
+}
+
+pub fn parse_config(raw: &str) -> Result<Config> {
+    let config: Config = toml::from_str(raw)?;
+    for (name, webhook) in &config.webhooks {
+        if webhook.url.is_empty() {
+            anyhow::bail!("[webhooks.{name}] needs a url");
+        }
+    }
+    for (name, alert) in &config.alerts {
+        alert.check(name, &config)?;
+    }
+    Ok(config)
+}
+
+pub fn load_config(config_path: Option<&Path>) -> Result<Config> {
+    let path = resolve_config_path(config_path);
+    match fs::read_to_string(&path) {
+        Ok(raw) => parse_config(&raw).with_context(|| format!("Invalid config {}", path.display())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(err) => Err(err).with_context(|| format!("Cannot read config {}", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_config_uses_defaults() {
+        let config = parse_config("").unwrap();
+        assert_eq!(config, Config::default());
+        assert_eq!(config.runtime.model, RuntimeModel::Ewma);
+    }
+
+    #[test]
+    fn energy_price_gives_costs() {
+        let config = parse_config("[energy]\nprice_per_kwh = 0.25\ncurrency = \"EUR\"\n").unwrap();
+        assert_eq!(config.energy.cost(2000.0), Some(0.5));
+        assert_eq!(config.energy.currency, "EUR");
+        assert_eq!(Config::default().energy.cost(2000.0), None);
+    }
+
+    #[test]
+    fn graph_section_is_parsed() {
+        let config =
+            parse_config("[graph]\ntheme = \"custom\"\ndpi = 192\ncolors = [\"#ff0000\"]\n")
+                .unwrap();
+        assert_eq!(config.graph.theme, GraphTheme::Custom);
+        assert_eq!(config.graph.dpi, 192);
+        assert_eq!(config.graph.width, 1280);
+        assert_eq!(config.graph.colors, vec!["#ff0000".to_string()]);
+        assert!(parse_config("[graph]\ntheme = \"neon\"\n").is_err());
+    }
+
+    #[test]
+    fn runtime_section_is_parsed() {
+        let config = parse_config("[runtime]\nmodel = \"load\"\nhalf_life_minutes = 10\n").unwrap();
+        assert_eq!(config.runtime.model, RuntimeModel::Load);
+        assert_eq!(config.runtime.half_life_minutes, 10.0);
+        assert!(parse_config("[runtime]\nmodel = \"magic\"\n").is_err());
+    }
+
+    #[test]
+    fn smoothing_section_is_parsed() {
+        let config = parse_config("[smoothing]\nmethod = \"median\"\nwindow = 7\n").unwrap();
+        assert_eq!(config.smoothing.method, SmoothingMethod::Median);
+        assert_eq!(config.smoothing.window, 7);
+        assert_eq!(config.smoothing.measurement_noise_pct, 2.0);
+        assert!(parse_config("[smoothing]\nmethod = \"savgol\"\n").is_err());
+    }
+
+    #[test]
+    fn power_section_is_parsed() {
+        let config = parse_config("[power]\nmax_watts = 90\noutlier_mads = 6\n").unwrap();
+        assert_eq!(config.power.max_watts, 90.0);
+        assert_eq!(config.power.outlier_mads, 6.0);
+        assert_eq!(Config::default().power.max_watts, 150.0);
+    }
+
+    #[test]
+    fn allow_and_deny_filter_sources_per_collector() {
+        let config = parse_config(
+            "[collectors.network]\ndeny = [\"veth*\", \"docker0\"]\n\n\
+             [collectors.temperature]\nallow = [\"coretemp*\"]\ndeny = [\"*core 3\"]\n",
+        )
+        .unwrap();
+        assert!(config.source_allowed("network", "wlan0", None));
+        assert!(!config.source_allowed("network", "vethA1B2", None));
+        assert!(!config.source_allowed("network", "Docker0", None));
+        assert!(config.source_allowed("temperature", "CPU Package", Some("coretemp/temp1")));
+        assert!(!config.source_allowed("temperature", "NVMe Composite", Some("nvme/temp1")));
+        assert!(!config.source_allowed("temperature", "CPU Core 3", Some("coretemp/temp5")));
+        assert!(config.source_allowed("disk", "/", None));
+    }
+
+    #[test]
+    fn quirks_are_looked_up_by_battery_name_or_path() {
+        let config = parse_config(
+            "[quirks.BAT0]\nenergy = \"milli\"\npower = \"milli\"\n\n\
+             [quirks.\"/sys/class/power_supply/CMB1\"]\nvoltage = \"milli\"\n",
+        )
+        .unwrap();
+        let bat0 = config.quirks_for(Path::new("/sys/class/power_supply/BAT0"));
+        assert_eq!(bat0.energy, Some(UnitScale::Milli));
+        assert_eq!(bat0.power, Some(UnitScale::Milli));
+        assert_eq!(bat0.charge, None);
+        let cmb1 = config.quirks_for(Path::new("/sys/class/power_supply/CMB1"));
+        assert_eq!(cmb1.voltage, Some(UnitScale::Milli));
+        assert_eq!(
+            config.quirks_for(Path::new("/sys/class/power_supply/BAT1")),
+            QuirkOverrides::default()
+        );
+    }
+
+    #[test]
+    fn hooks_section_is_parsed() {
+        let config =
+            parse_config("[hooks]\non_critical = \"systemctl hibernate\"\ncritical_percent = 7\n")
+                .unwrap();
+        assert_eq!(
+            config.hooks.on_critical.as_deref(),
+            Some("systemctl hibernate")
+        );
+        assert_eq!(config.hooks.critical_percent, 7.0);
+        assert_eq!(config.hooks.low_percent, 20.0);
+        assert!(!config.hooks.is_empty());
+    }
+
+    #[test]
+    fn collectors_can_be_disabled_by_name() {
+        let config =
+            parse_config("[collectors.gpu]\nenabled = false\n[collectors.disk]\n").unwrap();
+        assert!(!config.collector_enabled("gpu"));
+        assert!(config.collector_enabled("disk"));
+        assert!(config.collector_enabled("memory"));
+    }
+
+    #[test]
+    fn collector_intervals_override_the_global_one() {
+        let config = parse_config("[collectors.disk]\ninterval_seconds = 600\n").unwrap();
+        assert_eq!(
+            config.collector_interval("disk"),
+            Some(Duration::from_secs(600))
+        );
+        assert_eq!(config.collector_interval("battery"), None);
+    }
+
+    #[test]
+    fn missing_config_file_is_not_an_error() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config = load_config(Some(&tmp.path().join("absent.toml"))).unwrap();
+        assert_eq!(config, Config::default());
+    }
+}
@@ -0,0 +1,327 @@
+//! `analyze correlate`: how closely discharge power follows other metrics, directly and
+//! shifted in time, to rank the likely drivers of battery drain.
+
+use std::collections::BTreeMap;
+
+use crate::cli_helpers::DischargeSegment;
+use crate::metrics::{MetricKind, MetricSample};
+
+/// Fewer aligned points than this say nothing worth ranking.
+pub const MIN_PAIRS: usize = 10;
+/// A metric reading older than this no longer describes a discharge segment.
+const STALE_SECONDS: f64 = 5.0 * 60.0;
+
+/// How a `--with` name reads its metric.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Reading {
+    Value,
+    /// A numeric key of the details.
+    Detail(&'static str),
+    /// Per-second growth of the sum of cumulative counters in the details.
+    CounterRate(&'static [&'static str]),
+}
+
+/// The metric a `--with` name stands for: a short alias or any metric kind.
+fn driver(name: &str) -> Option<(MetricKind, Reading)> {
+    let alias = match name {
+        "cpu" => (MetricKind::CpuUsage, Reading::Value),
+        "frequency" => (MetricKind::CpuFrequency, Reading::Value),
+        "gpu" => (MetricKind::GpuUsage, Reading::Value),
+        "memory" => (MetricKind::MemoryUsage, Reading::Value),
+        "load" => (MetricKind::SystemLoad, Reading::Value),
+        "power" => (MetricKind::PowerDraw, Reading::Value),
+        "screen" => (MetricKind::Display, Reading::Value),
+        "brightness" => (MetricKind::Display, Reading::Detail("brightness_pct")),
+        "network" => (
+            MetricKind::NetworkBytes,
+            Reading::CounterRate(&["rx_bytes", "tx_bytes"]),
+        ),
+        "disk" => (
+            MetricKind::Cgroup,
+            Reading::CounterRate(&["io_read_bytes", "io_write_bytes"]),
+        ),
+        other => (MetricKind::from_label(other)?, Reading::Value),
+    };
+    Some(alias)
+}
+
+/// The metric kind a `--with` name reads, so callers can fetch just those samples.
+pub fn metric_kind(name: &str) -> Option<MetricKind> {
+    driver(name).map(|(kind, _)| kind)
+}
+
+/// One source's readings, oldest first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Series {
+    points: Vec<(f64, f64)>,
+}
+
+impl Series {
+    pub fn new(mut points: Vec<(f64, f64)>) -> Self {
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Series { points }
+    }
+
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = f64> + '_ {
+        self.points.iter().map(|(_, value)| *value)
+    }
+
+    /// Mean of the readings in `start..=end`, else the last one before `end` if recent.
+    fn over(&self, start: f64, end: f64) -> Option<f64> {
+        let from = self.points.partition_point(|(ts, _)| *ts < start);
+        let to = self.points.partition_point(|(ts, _)| *ts <= end);
+        if from < to {
+            let inside = &self.points[from..to];
+            return Some(inside.iter().map(|(_, value)| value).sum::<f64>() / inside.len() as f64);
+        }
+        let (ts, value) = *self.points[..to].last()?;
+        (end - ts <= STALE_SECONDS).then_some(value)
+    }
+}
+
+/// The series `name` selects from `metrics`, keyed by source. Overall CPU usage stands
+/// for the per-core sources when it was recorded.
+pub fn series_for(
+    name: &str,
+    metrics: &[MetricSample],
+) -> Result<BTreeMap<String, Series>, String> {
+    let (kind, reading) = driver(name).ok_or_else(|| {
+        format!(
+            "unknown metric {name:?}; use cpu, frequency, gpu, memory, load, power, screen, \
+             brightness, network, disk or a metric kind such as temperature"
+        )
+    })?;
+    let mut by_source: BTreeMap<&str, Vec<&MetricSample>> = BTreeMap::new();
+    for sample in metrics.iter().filter(|sample| sample.kind == kind) {
+        by_source.entry(&sample.source).or_default().push(sample);
+    }
+    if kind == MetricKind::CpuUsage && by_source.contains_key("cpu") {
+        by_source.retain(|source, _| *source == "cpu");
+    }
+    let detail = |sample: &MetricSample, key: &str| sample.details.get(key)?.as_f64();
+    Ok(by_source
+        .into_iter()
+        .map(|(source, mut samples)| {
+            samples.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+            let points = match reading {
+                Reading::Value => samples
+                    .iter()
+                    .filter_map(|sample| Some((sample.ts, sample.value?)))
+                    .collect(),
+                Reading::Detail(key) => samples
+                    .iter()
+                    .filter_map(|sample| Some((sample.ts, detail(sample, key)?)))
+                    .collect(),
+                Reading::CounterRate(keys) => samples
+                    .windows(2)
+                    .filter_map(|pair| {
+                        let total = |sample| -> Option<f64> {
+                            keys.iter().map(|key| detail(sample, key)).sum()
+                        };
+                        let (dt, grown) =
+                            (pair[1].ts - pair[0].ts, total(pair[1])? - total(pair[0])?);
+                        // A counter reset (reboot, interface re-created) is no reading.
+                        (dt > 0.0 && grown >= 0.0).then(|| (pair[1].ts, grown / dt))
+                    })
+                    .collect(),
+            };
+            (source.to_string(), Series::new(points))
+        })
+        .filter(|(_, series)| !series.points.is_empty())
+        .collect())
+}
+
+/// Pearson's r; `None` when either side does not vary.
+pub fn pearson(pairs: &[(f64, f64)]) -> Option<f64> {
+    let n = pairs.len() as f64;
+    if pairs.len() < 2 {
+        return None;
+    }
+    let (sum_x, sum_y) = pairs
+        .iter()
+        .fold((0.0, 0.0), |(x, y), (a, b)| (x + a, y + b));
+    let (mean_x, mean_y) = (sum_x / n, sum_y / n);
+    let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (x, y) in pairs {
+        cov += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x).powi(2);
+        var_y += (y - mean_y).powi(2);
+    }
+    (var_x > 0.0 && var_y > 0.0).then(|| cov / (var_x * var_y).sqrt())
+}
+
+/// How discharge power moves with one metric source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Correlation {
+    pub metric: String,
+    pub source: String,
+    /// Discharge segments with a reading at lag 0.
+    pub pairs: usize,
+    pub r: Option<f64>,
+    /// Shift with the strongest correlation; positive when the metric leads the drain.
+    pub best_lag_seconds: i64,
+    pub best_r: f64,
+}
+
+impl Correlation {
+    /// `strong`, `moderate`, `weak` or `none`, from the strongest |r|.
+    pub fn strength(&self) -> &'static str {
+        match self.best_r.abs() {
+            r if r >= 0.7 => "strong",
+            r if r >= 0.4 => "moderate",
+            r if r >= 0.2 => "weak",
+            _ => "none",
+        }
+    }
+}
+
+/// Readings of `series` paired with each segment's watts, with the metric taken
+/// `lag_seconds` earlier than the segment.
+fn aligned(segments: &[DischargeSegment], series: &Series, lag_seconds: f64) -> Vec<(f64, f64)> {
+    segments
+        .iter()
+        .filter_map(|segment| {
+            let end = segment.ts - lag_seconds;
+            let start = end - segment.hours * 3600.0;
+            Some((series.over(start, end)?, segment.watts))
+        })
+        .collect()
+}
+
+/// Correlations of discharge power with every source of each name in `with`, tried at
+/// each shift of `lag_step` seconds up to `max_lag` either way, strongest first.
+pub fn correlate(
+    segments: &[DischargeSegment],
+    metrics: &[MetricSample],
+    with: &[String],
+    max_lag: i64,
+    lag_step: i64,
+) -> Result<Vec<Correlation>, String> {
+    let steps = if lag_step > 0 { max_lag / lag_step } else { 0 };
+    let mut found = Vec::new();
+    for name in with {
+        for (source, series) in series_for(name, metrics)? {
+            let at = |lag: i64| {
+                let pairs = aligned(segments, &series, lag as f64);
+                (pairs.len() >= MIN_PAIRS)
+                    .then(|| pearson(&pairs))
+                    .flatten()
+                    .map(|r| (r, pairs.len()))
+            };
+            let direct = at(0);
+            let Some((best_lag_seconds, best_r)) = (-steps..=steps)
+                .map(|step| step * lag_step)
+                .filter_map(|lag| Some((lag, at(lag)?.0)))
+                // Ties go to the smaller shift.
+                .min_by(|a, b| {
+                    b.1.abs()
+                        .total_cmp(&a.1.abs())
+                        .then(a.0.abs().cmp(&b.0.abs()))
+                })
+            else {
+                continue;
+            };
+            found.push(Correlation {
+                metric: name.clone(),
+                source,
+                pairs: direct.map_or(0, |(_, pairs)| pairs),
+                r: direct.map(|(r, _)| r),
+                best_lag_seconds,
+                best_r,
+            });
+        }
+    }
+    found.sort_by(|a, b| b.best_r.abs().total_cmp(&a.best_r.abs()));
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_support::{metric, segment};
+
+    fn cpu(minute: i64) -> f64 {
+        ((minute * minute * 37 + minute * 11).rem_euclid(97)) as f64
+    }
+
+    /// One-minute segments whose drain follows CPU load.
+    fn segments() -> Vec<DischargeSegment> {
+        (10..70)
+            .map(|minute| segment(minute as f64 * 60.0, 5.0 + cpu(minute) / 10.0, 1.0 / 60.0))
+            .collect()
+    }
+
+    /// CPU load, a temperature following it five minutes later, and a brightness that
+    /// stays put.
+    fn metrics() -> Vec<MetricSample> {
+        let mut metrics = Vec::new();
+        for minute in 0..70 {
+            let ts = minute as f64 * 60.0 - 30.0;
+            metrics.push(metric(
+                ts,
+                MetricKind::CpuUsage,
+                "cpu",
+                cpu(minute),
+                json!({}),
+            ));
+            metrics.push(metric(ts, MetricKind::CpuUsage, "cpu0", 50.0, json!({})));
+            metrics.push(metric(
+                ts,
+                MetricKind::Temperature,
+                "coretemp",
+                40.0 + cpu(minute - 5),
+                json!({}),
+            ));
+            metrics.push(metric(
+                ts,
+                MetricKind::Display,
+                "screen",
+                1.0,
+                json!({ "brightness_pct": 60.0 }),
+            ));
+        }
+        metrics
+    }
+
+    fn ranked() -> Vec<Correlation> {
+        let with = ["temperature", "cpu", "brightness"].map(String::from);
+        correlate(&segments(), &metrics(), &with, 600, 60).unwrap()
+    }
+
+    #[test]
+    fn pearson_needs_spread_in_both_series() {
+        assert_eq!(pearson(&[(1.0, 2.0), (2.0, 4.0), (3.0, 6.0)]), Some(1.0));
+        assert_eq!(pearson(&[(1.0, 2.0), (1.0, 4.0)]), None);
+    }
+
+    #[test]
+    fn the_metric_driving_drain_ranks_first() {
+        let ranked = ranked();
+        assert_eq!(ranked.len(), 2, "a constant brightness cannot correlate");
+        assert_eq!(
+            (ranked[0].metric.as_str(), ranked[0].source.as_str()),
+            ("cpu", "cpu")
+        );
+        assert!(ranked[0].r.unwrap() > 0.99);
+        assert_eq!(ranked[0].best_lag_seconds, 0);
+    }
+
+    #[test]
+    fn lagging_metrics_are_matched_at_their_best_lag() {
+        let temperature = &ranked()[1];
+        assert!(temperature.r.unwrap().abs() < 0.5);
+        assert_eq!(temperature.best_lag_seconds, -300);
+        assert_eq!(temperature.strength(), "strong");
+    }
+
+    #[test]
+    fn unknown_metrics_are_rejected() {
+        assert!(correlate(&segments(), &metrics(), &["watts".to_string()], 0, 60).is_err());
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::BTreeMap;
+use std::process::Command;
+
+use anyhow::{Context, Result};
+use chrono::{Days, NaiveDate};
+
+use crate::cli_helpers::{average_pct_rates, discharge_segments, energy_totals, format_runtime};
+use crate::db::{Sample, SleepInterval};
+use crate::locale::Formats;
+
+/// Trailing days the evening summary compares against.
+pub const TRAILING_DAYS: u64 = 7;
+
+/// Consecutive readings further apart than this mean the machine was off or asleep
+/// in between, even without a recorded suspend.
+const AWAKE_MAX_GAP_SECONDS: f64 = 15.0 * 60.0;
+
+/// One day of battery use.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DaySummary {
+    /// Hours the machine was awake and collecting; stands in for screen-on time.
+    pub awake_hours: f64,
+    pub used_wh: Option<f64>,
+    pub avg_drain_w: Option<f64>,
+    pub avg_drain_pct_h: Option<f64>,
+    pub min_pct: Option<f64>,
+}
+
+impl DaySummary {
+    /// Summary of `samples` (aggregated, oldest first) that all fall on one day.
+    pub fn compute(samples: &[Sample], sleeps: &[SleepInterval]) -> Self {
+        let awake_seconds: f64 = samples
+            .windows(2)
+            .filter(|pair| {
+                let gap = pair[1].ts - pair[0].ts;
+                gap > 0.0
+                    && gap <= AWAKE_MAX_GAP_SECONDS
+                    && !sleeps.iter().any(|s| s.overlaps(pair[0].ts, pair[1].ts))
+            })
+            .map(|pair| pair[1].ts - pair[0].ts)
+            .sum();
+        let has_energy = samples.iter().any(|s| s.energy_now_wh.is_some());
+        let segments = discharge_segments(samples, sleeps);
+        let drain_hours: f64 = segments.iter().map(|s| s.hours).sum();
+        DaySummary {
+            awake_hours: awake_seconds / 3600.0,
+            used_wh: has_energy.then(|| energy_totals(samples).discharged_wh),
+            avg_drain_w: (drain_hours > 0.0)
+                .then(|| segments.iter().map(|s| s.watts * s.hours).sum::<f64>() / drain_hours),
+            avg_drain_pct_h: average_pct_rates(samples, sleeps).discharge_pct_h,
+            min_pct: samples.iter().filter_map(|s| s.percentage).reduce(f64::min),
+        }
+    }
+
+    /// Per-field mean over `days`, skipping days where a field is missing.
+    fn mean(days: &[DaySummary]) -> Self {
+        fn mean_of(values: impl Iterator<Item = Option<f64>>) -> Option<f64> {
+            let values: Vec<f64> = values.flatten().collect();
+            (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+        }
+        DaySummary {
+            awake_hours: mean_of(days.iter().map(|d| Some(d.awake_hours))).unwrap_or_default(),
+            used_wh: mean_of(days.iter().map(|d| d.used_wh)),
+            avg_drain_w: mean_of(days.iter().map(|d| d.avg_drain_w)),
+            avg_drain_pct_h: mean_of(days.iter().map(|d| d.avg_drain_pct_h)),
+            min_pct: mean_of(days.iter().map(|d| d.min_pct)),
+        }
+    }
+}
+
+/// A day next to the average of the [`TRAILING_DAYS`] before it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyComparison {
+    pub day: NaiveDate,
+    pub summary: DaySummary,
+    /// `None` when none of the trailing days have samples.
+    pub trailing: Option<DaySummary>,
+    /// Trailing days that had samples.
+    pub trailing_days: usize,
+}
+
+impl DailyComparison {
+    /// Splits `samples` by calendar day in `formats.zone` and compares `day` against the
+    /// trailing week.
+    pub fn compute(
+        samples: &[Sample],
+        sleeps: &[SleepInterval],
+        day: NaiveDate,
+        formats: &Formats,
+    ) -> Self {
+        let mut by_day: BTreeMap<NaiveDate, Vec<Sample>> = BTreeMap::new();
+        for sample in samples {
+            if let Some(dt) = formats.datetime(sample.ts) {
+                by_day
+                    .entry(dt.date_naive())
+                    .or_default()
+                    .push(sample.clone());
+            }
+        }
+        let summary = by_day
+            .get(&day)
+            .map(|samples| DaySummary::compute(samples, sleeps))
+            .unwrap_or_default();
+        let trailing: Vec<DaySummary> = (1..=TRAILING_DAYS)
+            .filter_map(|back| day.checked_sub_days(Days::new(back)))
+            .filter_map(|previous| by_day.get(&previous))
+            .filter(|samples| samples.len() > 1)
+            .map(|samples| DaySummary::compute(samples, sleeps))
+            .collect();
+        DailyComparison {
+            day,
+            summary,
+            trailing: (!trailing.is_empty()).then(|| DaySummary::mean(&trailing)),
+            trailing_days: trailing.len(),
+        }
+    }
+
+    /// Notification title and body, one line per figure with the trailing average.
+    pub fn notification(&self, formats: &Formats) -> (String, String) {
+        fn line(label: &str, value: String, average: Option<String>) -> String {
+            match average {
+                Some(average) => format!("{label}: {value} (7d avg {average})"),
+                None => format!("{label}: {value}"),
+            }
+        }
+        fn watts(value: Option<f64>) -> String {
+            value
+                .map(|w| format!("{w:.1}W"))
+                .unwrap_or_else(|| "--".to_string())
+        }
+        let today = &self.summary;
+        let trailing = self.trailing.as_ref();
+        let title = format!("Battery today ({})", formats.day(&self.day));
+        let used = |summary: &DaySummary| summary.used_wh.map(|wh| format!("{wh:.1}Wh"));
+        let drain = |summary: &DaySummary| match (summary.avg_drain_w, summary.avg_drain_pct_h) {
+            (None, None) => "--".to_string(),
+            (w, None) => watts(w),
+            (None, Some(pct)) => format!("{pct:.1}%/h"),
+            (w, Some(pct)) => format!("{} ({pct:.1}%/h)", watts(w)),
+        };
+        let lines = [
+            line(
+                "Screen-on",
+                format!("~{}", format_runtime(Some(today.awake_hours))),
+                trailing.map(|t| format_runtime(Some(t.awake_hours))),
+            ),
+            line(
+                "Used",
+                used(today).unwrap_or_else(|| "--".to_string()),
+                trailing.and_then(used),
+            ),
+            line("Avg drain", drain(today), trailing.map(drain)),
+            line(
+                "Lowest",
+                today
+                    .min_pct
+                    .map(|pct| format!("{pct:.0}%"))
+                    .unwrap_or_else(|| "--".to_string()),
+                trailing.and_then(|t| t.min_pct.map(|pct| format!("{pct:.0}%"))),
+            ),
+        ];
+        (title, lines.join("\n"))
+    }
+}
+
+/// The summary as a webhook event, shaped like an alert's so one template serves both.
+pub fn summary_event(title: &str, body: &str) -> serde_json::Value {
+    serde_json::json!({
+        "event": "summary",
+        "title": title,
+        "message": body,
+        "severity": "info",
+    })
+}
+
+/// Shows a desktop notification through `notify-send` (libnotify).
+pub fn send_notification(title: &str, body: &str) -> Result<()> {
+    let status = Command::new("notify-send")
+        .args(["--app-name=symmetri", "--icon=battery", title, body])
+        .status()
+        .context("Cannot run notify-send; install libnotify to get notifications")?;
+    if !status.success() {
+        anyhow::bail!("notify-send exited with {status}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zone::Zone;
+
+    fn sample(ts: f64, pct: f64, energy: f64) -> Sample {
+        Sample {
+            ts,
+            percentage: Some(pct),
+            capacity_pct: None,
+            health_pct: None,
+            energy_now_wh: Some(energy),
+            energy_full_wh: Some(50.0),
+            energy_full_design_wh: None,
+            status: Some("Discharging".to_string()),
+            source_path: "/sys/class/power_supply/BAT0".to_string(),
+        }
+    }
+
+    /// Two hours of 5-minute readings from `start`, draining `watts`.
+    fn day_of_use(start: f64, watts: f64) -> Vec<Sample> {
+        (0..=24)
+            .map(|step| {
+                let seconds = step as f64 * 300.0;
+                let energy = 50.0 - watts * seconds / 3600.0;
+                sample(start + seconds, energy * 2.0, energy)
+            })
+            .collect()
+    }
+
+    fn utc() -> Formats {
+        Formats {
+            zone: Zone::Utc,
+            ..Formats::default()
+        }
+    }
+
+    fn may_31() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, 31).unwrap()
+    }
+
+    /// 2024-05-31 at 10W after two days at 4W and 6W, each from 08:00 UTC.
+    fn three_days() -> DailyComparison {
+        let day_start = 1_717_142_400.0;
+        let mut samples = day_of_use(day_start - 2.0 * 86_400.0, 4.0);
+        samples.extend(day_of_use(day_start - 86_400.0, 6.0));
+        samples.extend(day_of_use(day_start, 10.0));
+        DailyComparison::compute(&samples, &[], may_31(), &utc())
+    }
+
+    #[test]
+    fn the_day_is_summarised() {
+        let comparison = three_days();
+        let today = &comparison.summary;
+        assert!((today.awake_hours - 2.0).abs() < 1e-9);
+        assert!((today.used_wh.unwrap() - 20.0).abs() < 1e-6);
+        assert!((today.avg_drain_w.unwrap() - 10.0).abs() < 1e-6);
+        assert_eq!(today.min_pct, Some(60.0));
+    }
+
+    #[test]
+    fn trailing_days_are_averaged() {
+        let comparison = three_days();
+        assert_eq!(comparison.trailing_days, 2);
+        let trailing = comparison.trailing.as_ref().unwrap();
+        assert!((trailing.avg_drain_w.unwrap() - 5.0).abs() < 1e-6);
+        assert!((trailing.used_wh.unwrap() - 10.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn the_notification_compares_the_day_with_the_week() {
+        let (title, body) = three_days().notification(&utc());
+        assert_eq!(title, "Battery today (2024-05-31)");
+        assert!(body.contains("Screen-on: ~2h00m (7d avg 2h00m)"), "{body}");
+        assert!(body.contains("Used: 20.0Wh (7d avg 10.0Wh)"), "{body}");
+        assert!(body.contains("Lowest: 60% (7d avg 80%)"), "{body}");
+    }
+
+    #[test]
+    fn a_day_without_samples_has_no_comparison() {
+        let quiet = DailyComparison::compute(&[], &[], may_31(), &utc());
+        assert_eq!(quiet.trailing, None);
+        assert!(quiet.notification(&utc()).1.contains("Used: --"));
+    }
+}
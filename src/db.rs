@@ -1,15 +1,26 @@
 use std::fs;
 use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{collections::HashSet, str::FromStr};
 
 use anyhow::Result;
-use rusqlite::{params, Connection, Row};
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{
+    params, params_from_iter, Batch, Connection, OpenFlags, OptionalExtension, Row, Transaction,
+    TransactionBehavior,
+};
+use serde::{Deserialize, Serialize};
 
+use crate::activity::Activity;
+use crate::baseline::Baseline;
+use crate::calibrate::{Calibration, CalibrationPoint};
+use crate::details;
 use crate::metrics::{MetricKind, MetricSample};
-use crate::sysfs::BatteryReading;
+use crate::sysfs::{BatteryInfo, BatteryReading};
+use crate::versions::SystemVersions;
+use crate::workload::{Labelled, Workload};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Sample {
     pub ts: f64,
     pub percentage: Option<f64>,
@@ -22,6 +33,51 @@ pub struct Sample {
     pub source_path: String,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub ts: f64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SleepInterval {
+    pub start_ts: f64,
+    pub end_ts: f64,
+    pub slept_seconds: f64,
+    pub start_pct: Option<f64>,
+    pub end_pct: Option<f64>,
+    /// Battery status at the collections before and after; `None` in intervals recorded
+    /// by older versions.
+    pub start_status: Option<String>,
+    pub end_status: Option<String>,
+}
+
+impl SleepInterval {
+    /// Whether the battery discharged across the suspend: both collections said so, or,
+    /// without recorded statuses, the charge did not rise.
+    pub fn on_battery(&self) -> bool {
+        match (&self.start_status, &self.end_status) {
+            (Some(start), Some(end)) => {
+                start.eq_ignore_ascii_case("discharging") && end.eq_ignore_ascii_case("discharging")
+            }
+            _ => matches!((self.start_pct, self.end_pct), (Some(start), Some(end)) if end <= start),
+        }
+    }
+
+    /// Charge lost while suspended; `None` when the charger was plugged in on either side,
+    /// where the difference is no loss.
+    pub fn lost_pct(&self) -> Option<f64> {
+        if !self.on_battery() {
+            return None;
+        }
+        Some(self.start_pct? - self.end_pct?)
+    }
+
+    pub fn overlaps(&self, start_ts: f64, end_ts: f64) -> bool {
+        self.start_ts < end_ts && self.end_ts > start_ts
+    }
+}
+
 const SCHEMA: &str = r#"
 CREATE TABLE IF NOT EXISTS samples (
     ts REAL NOT NULL,
@@ -43,16 +99,158 @@ CREATE TABLE IF NOT EXISTS metric_samples (
     unit TEXT,
     details TEXT
 );
-CREATE INDEX IF NOT EXISTS idx_metric_samples_ts ON metric_samples (ts);
+DROP INDEX IF EXISTS idx_metric_samples_ts;
+CREATE INDEX IF NOT EXISTS idx_metric_samples_ts_kind_source ON metric_samples (ts, kind, source);
 CREATE INDEX IF NOT EXISTS idx_metric_samples_kind_ts ON metric_samples (kind, ts);
+CREATE TABLE IF NOT EXISTS annotations (
+    ts REAL NOT NULL,
+    text TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_annotations_ts ON annotations (ts);
+CREATE TABLE IF NOT EXISTS system_versions (
+    ts REAL NOT NULL,
+    kernel TEXT NOT NULL,
+    os_release TEXT,
+    symmetri_version TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_system_versions_ts ON system_versions (ts);
+CREATE TABLE IF NOT EXISTS battery_info (
+    ts REAL NOT NULL,
+    source_path TEXT NOT NULL,
+    manufacturer TEXT,
+    model_name TEXT,
+    serial_number TEXT,
+    technology TEXT,
+    energy_full_design_wh REAL
+);
+CREATE INDEX IF NOT EXISTS idx_battery_info_source_ts ON battery_info (source_path, ts);
+CREATE TABLE IF NOT EXISTS sleep_intervals (
+    start_ts REAL NOT NULL,
+    end_ts REAL NOT NULL,
+    slept_seconds REAL NOT NULL,
+    start_pct REAL,
+    end_pct REAL,
+    start_status TEXT,
+    end_status TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_sleep_intervals_end_ts ON sleep_intervals (end_ts);
+CREATE TABLE IF NOT EXISTS collector_state (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS calibrations (
+    ts REAL NOT NULL,
+    source_path TEXT NOT NULL,
+    full_wh REAL NOT NULL,
+    usable_wh REAL NOT NULL,
+    points TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS baselines (
+    name TEXT PRIMARY KEY,
+    ts REAL NOT NULL,
+    start_ts REAL NOT NULL,
+    end_ts REAL NOT NULL,
+    stats TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS workloads (
+    start_ts REAL PRIMARY KEY,
+    end_ts REAL NOT NULL,
+    workload TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS activities (
+    name TEXT NOT NULL,
+    start_ts REAL NOT NULL,
+    end_ts REAL
+);
+CREATE INDEX IF NOT EXISTS idx_activities_start_ts ON activities (start_ts);
 "#;
 
+/// One battery row per (source, ts); inserts repeating a pair are ignored. Created by a
+/// migration rather than [`SCHEMA`] because databases from before it may hold duplicates.
+const UNIQUE_SAMPLES_INDEX: &str =
+    "CREATE UNIQUE INDEX IF NOT EXISTS idx_samples_source_ts ON samples (source_path, ts)";
+
+/// How long a statement waits for another process's write lock (the collector, a
+/// report, `db prune`) before failing with `database is locked`.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `collector_state` key counting the [`MIGRATIONS`] a database has been through.
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+type Migration = fn(&Connection, &Path) -> Result<()>;
+
+/// Changes to databases written by older versions, in order. Each runs once per database,
+/// new ones included, and is recorded under [`SCHEMA_VERSION_KEY`]; append, never reorder.
+const MIGRATIONS: &[Migration] = &[add_unique_samples_index, add_sleep_statuses];
+
+/// With duplicates the index cannot be built; the database keeps working without it and
+/// `db dedupe` adds it after removing them.
+fn add_unique_samples_index(conn: &Connection, db_path: &Path) -> Result<()> {
+    if let Err(err) = conn.execute_batch(UNIQUE_SAMPLES_INDEX) {
+        tracing::warn!(
+            "{} has duplicate battery samples ({err}); run `symmetri db dedupe`",
+            db_path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Databases created since have the columns from [`SCHEMA`].
+fn add_sleep_statuses(conn: &Connection, _db_path: &Path) -> Result<()> {
+    let columns: Vec<String> = conn
+        .prepare("SELECT name FROM pragma_table_info('sleep_intervals')")?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    for column in ["start_status", "end_status"] {
+        if !columns.iter().any(|name| name == column) {
+            conn.execute_batch(&format!(
+                "ALTER TABLE sleep_intervals ADD COLUMN {column} TEXT"
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+fn schema_version(conn: &Connection) -> Result<usize> {
+    Ok(get_state(conn, SCHEMA_VERSION_KEY)?
+        .and_then(|raw| raw.parse().ok())
+        .unwrap_or(0))
+}
+
+/// Applies the migrations `db_path` has not been through, in one write transaction so
+/// two processes opening it at once do not both run them.
+fn migrate(conn: &Connection, db_path: &Path) -> Result<()> {
+    if schema_version(conn)? >= MIGRATIONS.len() {
+        return Ok(());
+    }
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    for (applied, migration) in MIGRATIONS.iter().enumerate().skip(schema_version(&tx)?) {
+        migration(&tx, db_path)?;
+        set_state(&tx, SCHEMA_VERSION_KEY, &(applied + 1).to_string())?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// A connection that waits up to [`BUSY_TIMEOUT`] for a lock instead of failing at once.
+/// Write transactions start `IMMEDIATE`: a deferred one that read first cannot wait for
+/// the lock when it upgrades, and fails straight away.
+fn connect(db_path: &Path) -> Result<Connection> {
+    let conn = Connection::open(db_path)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+/// Opens `db_path`, creating and migrating it as needed. The database is switched to WAL
+/// so readers do not block the collector's writes, nor the collector them.
 pub fn init_db_connection(db_path: &Path) -> Result<Connection> {
     if let Some(parent) = db_path.parent() {
         fs::create_dir_all(parent)?;
     }
-    let conn = Connection::open(db_path)?;
+    let conn = connect(db_path)?;
+    conn.pragma_update(None, "journal_mode", "WAL")?;
     conn.execute_batch(SCHEMA)?;
+    migrate(&conn, db_path)?;
     Ok(conn)
 }
 
@@ -64,11 +262,11 @@ pub fn insert_samples_with_conn(conn: &mut Connection, samples: &[Sample]) -> Re
     if samples.is_empty() {
         return Ok(());
     }
-    let tx = conn.transaction()?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
     {
         let mut stmt = tx.prepare(
             r#"
-            INSERT INTO samples (
+            INSERT OR IGNORE INTO samples (
                 ts, percentage, capacity_pct, health_pct, energy_now_wh,
                 energy_full_wh, energy_full_design_wh, status, source_path
             ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
@@ -97,16 +295,32 @@ pub fn insert_sample(db_path: &Path, sample: &Sample) -> Result<()> {
 }
 
 pub fn insert_samples(db_path: &Path, samples: &[Sample]) -> Result<()> {
-    let mut conn = Connection::open(db_path)?;
+    let mut conn = connect(db_path)?;
     insert_samples_with_conn(&mut conn, samples)
 }
 
-fn serialize_details(details: &serde_json::Value) -> Option<String> {
+/// `collector_state` key set to `1` while new metric details are stored compressed.
+const COMPRESS_DETAILS_KEY: &str = "compress_details";
+
+/// Builds without zstd keep writing plain JSON even when `db compress` turned it on.
+fn compress_details_enabled(conn: &Connection) -> Result<bool> {
+    Ok(cfg!(feature = "details-compression")
+        && get_state(conn, COMPRESS_DETAILS_KEY)?.as_deref() == Some("1"))
+}
+
+/// `details` as stored: NULL, JSON text, or a [`details::compress`] blob when compression
+/// is on and shrinks it.
+fn serialize_details(details: &serde_json::Value, compress: bool) -> Result<Value> {
     if details.is_null() {
-        None
-    } else {
-        Some(details.to_string())
+        return Ok(Value::Null);
+    }
+    let text = details.to_string();
+    if compress {
+        if let Some(blob) = details::compress(&text)? {
+            return Ok(Value::Blob(blob));
+        }
     }
+    Ok(Value::Text(text))
 }
 
 pub fn insert_metric_samples_with_conn(
@@ -116,8 +330,9 @@ pub fn insert_metric_samples_with_conn(
     if samples.is_empty() {
         return Ok(());
     }
-    let tx = conn.transaction()?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
     {
+        let compress = compress_details_enabled(&tx)?;
         let mut stmt = tx.prepare(
             r#"
             INSERT INTO metric_samples (
@@ -132,7 +347,7 @@ pub fn insert_metric_samples_with_conn(
                 sample.source,
                 sample.value,
                 sample.unit,
-                serialize_details(&sample.details),
+                serialize_details(&sample.details, compress)?,
             ])?;
         }
     }
@@ -141,7 +356,7 @@ pub fn insert_metric_samples_with_conn(
 }
 
 pub fn insert_metric_samples(db_path: &Path, samples: &[MetricSample]) -> Result<()> {
-    let mut conn = Connection::open(db_path)?;
+    let mut conn = connect(db_path)?;
     insert_metric_samples_with_conn(&mut conn, samples)
 }
 
@@ -153,12 +368,23 @@ pub fn insert_all_samples(
     if battery_samples.is_empty() && metric_samples.is_empty() {
         return Ok(());
     }
-    let tx = conn.transaction()?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    insert_all_samples_in(&tx, battery_samples, metric_samples)?;
+    tx.commit()?;
+    Ok(())
+}
 
+/// [`insert_all_samples`] inside a transaction the caller already holds (and may roll
+/// back, as `dev bench` does).
+pub fn insert_all_samples_in(
+    conn: &Connection,
+    battery_samples: &[Sample],
+    metric_samples: &[MetricSample],
+) -> Result<()> {
     if !battery_samples.is_empty() {
-        let mut stmt = tx.prepare(
+        let mut stmt = conn.prepare(
             r#"
-            INSERT INTO samples (
+            INSERT OR IGNORE INTO samples (
                 ts, percentage, capacity_pct, health_pct, energy_now_wh,
                 energy_full_wh, energy_full_design_wh, status, source_path
             ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
@@ -180,7 +406,8 @@ pub fn insert_all_samples(
     }
 
     if !metric_samples.is_empty() {
-        let mut stmt = tx.prepare(
+        let compress = compress_details_enabled(conn)?;
+        let mut stmt = conn.prepare(
             r#"
             INSERT INTO metric_samples (
                 ts, kind, source, value, unit, details
@@ -194,17 +421,516 @@ pub fn insert_all_samples(
                 sample.source,
                 sample.value,
                 sample.unit,
-                serialize_details(&sample.details),
+                serialize_details(&sample.details, compress)?,
             ])?;
         }
     }
+    Ok(())
+}
+
+pub fn insert_annotation(db_path: &Path, annotation: &Annotation) -> Result<()> {
+    let conn = init_db_connection(db_path)?;
+    insert_annotation_with_conn(&conn, annotation)
+}
+
+pub fn insert_annotation_with_conn(conn: &Connection, annotation: &Annotation) -> Result<()> {
+    conn.execute(
+        "INSERT INTO annotations (ts, text) VALUES (?, ?)",
+        params![annotation.ts, annotation.text],
+    )?;
+    Ok(())
+}
+
+/// Stores `versions` at `ts` unless they match the newest stored row. Returns that row
+/// when `versions` replaced it, i.e. after an upgrade.
+pub fn record_system_versions(
+    conn: &Connection,
+    ts: f64,
+    versions: &SystemVersions,
+) -> Result<Option<SystemVersions>> {
+    let previous = conn
+        .query_row(
+            "SELECT kernel, os_release, symmetri_version FROM system_versions \
+             ORDER BY ts DESC LIMIT 1",
+            [],
+            system_versions_from_row,
+        )
+        .optional()?;
+    if previous.as_ref() == Some(versions) {
+        return Ok(None);
+    }
+    conn.execute(
+        "INSERT INTO system_versions (ts, kernel, os_release, symmetri_version) \
+         VALUES (?, ?, ?, ?)",
+        params![ts, versions.kernel, versions.os_release, versions.symmetri],
+    )?;
+    Ok(previous)
+}
+
+fn system_versions_from_row(row: &Row) -> rusqlite::Result<SystemVersions> {
+    Ok(SystemVersions {
+        kernel: row.get(0)?,
+        os_release: row.get(1)?,
+        symmetri: row.get(2)?,
+    })
+}
+
+/// Stores `info` at `ts` unless it matches the newest row for the same battery, so a row
+/// marks the first collection of each battery seen in that slot. Returns whether it did.
+pub fn record_battery_info(conn: &Connection, ts: f64, info: &BatteryInfo) -> Result<bool> {
+    let previous = conn
+        .query_row(
+            "SELECT source_path, manufacturer, model_name, serial_number, technology, \
+             energy_full_design_wh FROM battery_info WHERE source_path = ? \
+             ORDER BY ts DESC LIMIT 1",
+            params![info.source_path],
+            battery_info_from_row,
+        )
+        .optional()?;
+    if previous.as_ref() == Some(info) {
+        return Ok(false);
+    }
+    conn.execute(
+        "INSERT INTO battery_info (ts, source_path, manufacturer, model_name, serial_number, \
+         technology, energy_full_design_wh) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        params![
+            ts,
+            info.source_path,
+            info.manufacturer,
+            info.model_name,
+            info.serial_number,
+            info.technology,
+            info.energy_full_design_wh
+        ],
+    )?;
+    Ok(true)
+}
+
+fn battery_info_from_row(row: &Row) -> rusqlite::Result<BatteryInfo> {
+    Ok(BatteryInfo {
+        source_path: row.get(0)?,
+        manufacturer: row.get(1)?,
+        model_name: row.get(2)?,
+        serial_number: row.get(3)?,
+        technology: row.get(4)?,
+        energy_full_design_wh: row.get(5)?,
+    })
+}
+
+/// Every stored battery, ordered by source and then by when it was first seen.
+pub fn fetch_battery_info(db_path: &Path) -> Result<Vec<(f64, BatteryInfo)>> {
+    let conn = init_db_connection(db_path)?;
+    let mut stmt = conn.prepare(
+        "SELECT source_path, manufacturer, model_name, serial_number, technology, \
+         energy_full_design_wh, ts FROM battery_info ORDER BY source_path, ts",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get(6)?, battery_info_from_row(row)?)))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Average health per UTC day of the battery at `source_path` since `since_ts`, oldest
+/// first, as (mean timestamp, health %) points for [`crate::wear::WearTrend::fit`].
+pub fn fetch_daily_health(
+    db_path: &Path,
+    source_path: &str,
+    since_ts: f64,
+) -> Result<Vec<(f64, f64)>> {
+    let conn = init_db_connection(db_path)?;
+    let mut stmt = conn.prepare(
+        "SELECT AVG(ts), AVG(health_pct) FROM samples \
+         WHERE source_path = ? AND ts >= ? AND health_pct IS NOT NULL \
+         GROUP BY CAST(ts / 86400 AS INTEGER) ORDER BY 1",
+    )?;
+    let rows = stmt.query_map(params![source_path, since_ts], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Every stored versions row before `until_ts`, oldest first. One row per upgrade, so the
+/// whole history stays small.
+pub fn fetch_system_versions(
+    db_path: &Path,
+    until_ts: Option<f64>,
+) -> Result<Vec<(f64, SystemVersions)>> {
+    let conn = init_db_connection(db_path)?;
+    let mut stmt = conn.prepare(
+        "SELECT kernel, os_release, symmetri_version, ts FROM system_versions \
+         WHERE ts < ? ORDER BY ts",
+    )?;
+    let rows = stmt.query_map(params![until_ts.unwrap_or(f64::INFINITY)], |row| {
+        Ok((row.get(3)?, system_versions_from_row(row)?))
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+pub fn fetch_annotations(
+    db_path: &Path,
+    since_ts: Option<f64>,
+    until_ts: Option<f64>,
+) -> Result<Vec<Annotation>> {
+    let conn = init_db_connection(db_path)?;
+    let mut stmt =
+        conn.prepare("SELECT ts, text FROM annotations WHERE ts >= ? AND ts < ? ORDER BY ts")?;
+    let rows = stmt.query_map(
+        params![
+            since_ts.unwrap_or(f64::NEG_INFINITY),
+            until_ts.unwrap_or(f64::INFINITY)
+        ],
+        |row| {
+            Ok(Annotation {
+                ts: row.get(0)?,
+                text: row.get(1)?,
+            })
+        },
+    )?;
+    let mut annotations = Vec::new();
+    for row in rows {
+        annotations.push(row?);
+    }
+    Ok(annotations)
+}
+
+pub fn insert_sleep_interval(conn: &Connection, interval: &SleepInterval) -> Result<()> {
+    conn.execute(
+        r#"
+        INSERT INTO sleep_intervals (
+            start_ts, end_ts, slept_seconds, start_pct, end_pct, start_status, end_status
+        ) VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+        params![
+            interval.start_ts,
+            interval.end_ts,
+            interval.slept_seconds,
+            interval.start_pct,
+            interval.end_pct,
+            interval.start_status,
+            interval.end_status,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Sleep intervals that end inside the window (a suspend is attributed to the window in
+/// which the machine resumed).
+pub fn fetch_sleep_intervals(
+    db_path: &Path,
+    since_ts: Option<f64>,
+    until_ts: Option<f64>,
+) -> Result<Vec<SleepInterval>> {
+    let conn = init_db_connection(db_path)?;
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT start_ts, end_ts, slept_seconds, start_pct, end_pct, start_status, end_status
+        FROM sleep_intervals WHERE end_ts >= ? AND end_ts < ? ORDER BY end_ts
+        "#,
+    )?;
+    let rows = stmt.query_map(
+        params![
+            since_ts.unwrap_or(f64::NEG_INFINITY),
+            until_ts.unwrap_or(f64::INFINITY)
+        ],
+        |row| {
+            Ok(SleepInterval {
+                start_ts: row.get(0)?,
+                end_ts: row.get(1)?,
+                slept_seconds: row.get(2)?,
+                start_pct: row.get(3)?,
+                end_pct: row.get(4)?,
+                start_status: row.get(5)?,
+                end_status: row.get(6)?,
+            })
+        },
+    )?;
+    let mut intervals = Vec::new();
+    for row in rows {
+        intervals.push(row?);
+    }
+    Ok(intervals)
+}
+
+pub fn insert_calibration(db_path: &Path, calibration: &Calibration) -> Result<()> {
+    let conn = init_db_connection(db_path)?;
+    conn.execute(
+        "INSERT INTO calibrations (ts, source_path, full_wh, usable_wh, points) VALUES (?, ?, ?, ?, ?)",
+        params![
+            calibration.ts,
+            calibration.source_path,
+            calibration.full_wh,
+            calibration.usable_wh,
+            serde_json::to_string(&calibration.points)?,
+        ],
+    )?;
+    Ok(())
+}
+
+/// The most recent calibration run for each battery, ordered by source.
+pub fn fetch_calibrations(db_path: &Path) -> Result<Vec<Calibration>> {
+    let conn = init_db_connection(db_path)?;
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT ts, source_path, full_wh, usable_wh, points FROM calibrations AS c
+        WHERE ts = (SELECT MAX(ts) FROM calibrations WHERE source_path = c.source_path)
+        ORDER BY source_path
+        "#,
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, f64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, f64>(2)?,
+            row.get::<_, f64>(3)?,
+            row.get::<_, String>(4)?,
+        ))
+    })?;
+    let mut calibrations = Vec::new();
+    for row in rows {
+        let (ts, source_path, full_wh, usable_wh, points) = row?;
+        let points: Vec<CalibrationPoint> = serde_json::from_str(&points)?;
+        calibrations.push(Calibration {
+            ts,
+            source_path,
+            full_wh,
+            usable_wh,
+            points,
+        });
+    }
+    Ok(calibrations)
+}
+
+/// Latest calibration of the battery at `source_path`.
+pub fn fetch_calibration(db_path: &Path, source_path: &str) -> Result<Option<Calibration>> {
+    Ok(fetch_calibrations(db_path)?
+        .into_iter()
+        .find(|calibration| calibration.source_path == source_path))
+}
+
+/// Stores `baseline`, replacing any saved under the same name.
+pub fn insert_baseline(db_path: &Path, baseline: &Baseline) -> Result<()> {
+    let conn = init_db_connection(db_path)?;
+    conn.execute(
+        "INSERT OR REPLACE INTO baselines (name, ts, start_ts, end_ts, stats) VALUES (?, ?, ?, ?, ?)",
+        params![
+            baseline.name,
+            baseline.ts,
+            baseline.start_ts,
+            baseline.end_ts,
+            serde_json::to_string(&baseline.metrics)?,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Every saved baseline, oldest first.
+pub fn fetch_baselines(db_path: &Path) -> Result<Vec<Baseline>> {
+    let conn = init_db_connection(db_path)?;
+    let mut stmt =
+        conn.prepare("SELECT name, ts, start_ts, end_ts, stats FROM baselines ORDER BY ts, name")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, f64>(1)?,
+            row.get::<_, f64>(2)?,
+            row.get::<_, f64>(3)?,
+            row.get::<_, String>(4)?,
+        ))
+    })?;
+    let mut baselines = Vec::new();
+    for row in rows {
+        let (name, ts, start_ts, end_ts, stats) = row?;
+        baselines.push(Baseline {
+            name,
+            ts,
+            start_ts,
+            end_ts,
+            metrics: serde_json::from_str(&stats)?,
+        });
+    }
+    Ok(baselines)
+}
+
+/// The baseline called `name`, or the most recently saved one without a name.
+pub fn fetch_baseline(db_path: &Path, name: Option<&str>) -> Result<Option<Baseline>> {
+    let baselines = fetch_baselines(db_path)?;
+    Ok(match name {
+        Some(name) => baselines.into_iter().find(|baseline| baseline.name == name),
+        None => baselines.into_iter().last(),
+    })
+}
+
+/// Closes the running activity at `ts`, if any, and returns it.
+fn stop_activity_in(conn: &Connection, ts: f64) -> Result<Option<Activity>> {
+    let running = conn
+        .query_row(
+            "SELECT rowid, name, start_ts FROM activities WHERE end_ts IS NULL \
+             ORDER BY start_ts DESC LIMIT 1",
+            [],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, f64>(2)?,
+                ))
+            },
+        )
+        .optional()?;
+    let Some((rowid, name, start_ts)) = running else {
+        return Ok(None);
+    };
+    conn.execute(
+        "UPDATE activities SET end_ts = ? WHERE rowid = ?",
+        params![ts, rowid],
+    )?;
+    Ok(Some(Activity {
+        name,
+        start_ts,
+        end_ts: Some(ts),
+    }))
+}
 
+/// Starts `name` at `ts`, stopping and returning the activity that was running.
+pub fn start_activity(db_path: &Path, name: &str, ts: f64) -> Result<Option<Activity>> {
+    let mut conn = init_db_connection(db_path)?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let stopped = stop_activity_in(&tx, ts)?;
+    tx.execute(
+        "INSERT INTO activities (name, start_ts) VALUES (?, ?)",
+        params![name, ts],
+    )?;
+    tx.commit()?;
+    Ok(stopped)
+}
+
+/// Stops the running activity at `ts`; `None` when nothing was running.
+pub fn stop_activity(db_path: &Path, ts: f64) -> Result<Option<Activity>> {
+    let conn = init_db_connection(db_path)?;
+    stop_activity_in(&conn, ts)
+}
+
+/// Activities overlapping `since_ts..until_ts`, running ones included, oldest first.
+pub fn fetch_activities(
+    db_path: &Path,
+    since_ts: Option<f64>,
+    until_ts: Option<f64>,
+) -> Result<Vec<Activity>> {
+    let conn = init_db_connection(db_path)?;
+    let mut stmt = conn.prepare(
+        "SELECT name, start_ts, end_ts FROM activities \
+         WHERE start_ts < ? AND (end_ts IS NULL OR end_ts > ?) ORDER BY start_ts",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            until_ts.unwrap_or(f64::INFINITY),
+            since_ts.unwrap_or(f64::NEG_INFINITY)
+        ],
+        |row| {
+            Ok(Activity {
+                name: row.get(0)?,
+                start_ts: row.get(1)?,
+                end_ts: row.get(2)?,
+            })
+        },
+    )?;
+    let mut activities = Vec::new();
+    for row in rows {
+        activities.push(row?);
+    }
+    Ok(activities)
+}
+
+/// Replaces the workload labels starting in `since_ts..until_ts` with `labels`.
+pub fn replace_workloads(
+    db_path: &Path,
+    since_ts: Option<f64>,
+    until_ts: Option<f64>,
+    labels: &[Labelled],
+) -> Result<()> {
+    let mut conn = init_db_connection(db_path)?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    tx.execute(
+        "DELETE FROM workloads WHERE start_ts >= ? AND start_ts < ?",
+        params![
+            since_ts.unwrap_or(f64::NEG_INFINITY),
+            until_ts.unwrap_or(f64::INFINITY)
+        ],
+    )?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR REPLACE INTO workloads (start_ts, end_ts, workload) VALUES (?, ?, ?)",
+        )?;
+        for label in labels {
+            stmt.execute(params![label.start, label.end, label.workload.as_str()])?;
+        }
+    }
     tx.commit()?;
     Ok(())
 }
 
+/// Stored workload labels starting in `since_ts..until_ts`, oldest first; labels this
+/// version does not know are skipped.
+pub fn fetch_workloads(
+    db_path: &Path,
+    since_ts: Option<f64>,
+    until_ts: Option<f64>,
+) -> Result<Vec<Labelled>> {
+    let conn = init_db_connection(db_path)?;
+    let mut stmt = conn.prepare(
+        "SELECT start_ts, end_ts, workload FROM workloads WHERE start_ts >= ? AND start_ts < ? \
+         ORDER BY start_ts",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            since_ts.unwrap_or(f64::NEG_INFINITY),
+            until_ts.unwrap_or(f64::INFINITY)
+        ],
+        |row| {
+            Ok((
+                row.get::<_, f64>(0)?,
+                row.get::<_, f64>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        },
+    )?;
+    let mut labels = Vec::new();
+    for row in rows {
+        let (start, end, workload) = row?;
+        if let Some(workload) = Workload::from_label(&workload) {
+            labels.push(Labelled {
+                start,
+                end,
+                workload,
+            });
+        }
+    }
+    Ok(labels)
+}
+
+/// Whether a baseline called `name` existed.
+pub fn delete_baseline(db_path: &Path, name: &str) -> Result<bool> {
+    let conn = init_db_connection(db_path)?;
+    Ok(conn.execute("DELETE FROM baselines WHERE name = ?", params![name])? > 0)
+}
+
+pub fn get_state(conn: &Connection, key: &str) -> Result<Option<String>> {
+    let mut stmt = conn.prepare("SELECT value FROM collector_state WHERE key = ?")?;
+    let mut rows = stmt.query(params![key])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn set_state(conn: &Connection, key: &str, value: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO collector_state (key, value) VALUES (?, ?) \
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![key, value],
+    )?;
+    Ok(())
+}
+
 pub fn count_samples(db_path: &Path, since_ts: Option<f64>) -> Result<usize> {
-    let conn = Connection::open(db_path)?;
+    let conn = connect(db_path)?;
     let count: i64 = match since_ts {
         Some(ts) => conn.query_row(
             "SELECT COUNT(*) FROM samples WHERE ts >= ?",
@@ -217,7 +943,7 @@ pub fn count_samples(db_path: &Path, since_ts: Option<f64>) -> Result<usize> {
 }
 
 pub fn count_metric_samples(db_path: &Path, since_ts: Option<f64>) -> Result<usize> {
-    let conn = Connection::open(db_path)?;
+    let conn = connect(db_path)?;
     let count: i64 = match since_ts {
         Some(ts) => conn.query_row(
             "SELECT COUNT(*) FROM metric_samples WHERE ts >= ?",
@@ -230,7 +956,7 @@ pub fn count_metric_samples(db_path: &Path, since_ts: Option<f64>) -> Result<usi
 }
 
 pub fn count_events(db_path: &Path, since_ts: Option<f64>) -> Result<usize> {
-    let conn = Connection::open(db_path)?;
+    let conn = connect(db_path)?;
     let count: i64 = match since_ts {
         Some(ts) => conn.query_row(
             "SELECT COUNT(DISTINCT ts) FROM samples WHERE ts >= ?",
@@ -244,6 +970,395 @@ pub fn count_events(db_path: &Path, since_ts: Option<f64>) -> Result<usize> {
     Ok(count as usize)
 }
 
+const TABLES: [&str; 11] = [
+    "samples",
+    "metric_samples",
+    "annotations",
+    "system_versions",
+    "battery_info",
+    "sleep_intervals",
+    "collector_state",
+    "calibrations",
+    "baselines",
+    "workloads",
+    "activities",
+];
+
+/// Size and contents of a database, as shown by `symmetri db stats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbStats {
+    pub file_size_bytes: u64,
+    pub table_rows: Vec<(String, usize)>,
+    /// Metric rows per (kind, source), ordered by kind then source.
+    pub metric_rows: Vec<(String, String, usize)>,
+    pub first_ts: Option<f64>,
+    pub last_ts: Option<f64>,
+}
+
+impl DbStats {
+    fn rows(&self, table: &str) -> usize {
+        self.table_rows
+            .iter()
+            .find(|(name, _)| name == table)
+            .map(|(_, rows)| *rows)
+            .unwrap_or(0)
+    }
+
+    /// Battery and metric rows together.
+    pub fn sample_rows(&self) -> usize {
+        self.rows("samples") + self.rows("metric_samples")
+    }
+
+    /// Spans shorter than a day count as one day rather than extrapolating from minutes.
+    pub fn samples_per_day(&self) -> Option<f64> {
+        let span_days = (self.last_ts? - self.first_ts?) / 86_400.0;
+        Some(self.sample_rows() as f64 / span_days.max(1.0))
+    }
+}
+
+fn open_existing(db_path: &Path) -> Result<Connection> {
+    if !db_path.exists() {
+        anyhow::bail!("No database at {}", db_path.display());
+    }
+    connect(db_path)
+}
+
+fn ts_range(conn: &Connection, table: &str) -> Result<(Option<f64>, Option<f64>)> {
+    Ok(conn.query_row(
+        &format!("SELECT MIN(ts), MAX(ts) FROM {table}"),
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?)
+}
+
+pub fn db_stats(db_path: &Path) -> Result<DbStats> {
+    let conn = open_existing(db_path)?;
+    let table_rows = TABLES
+        .iter()
+        .map(|table| {
+            let count: i64 =
+                conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                    row.get(0)
+                })?;
+            Ok((table.to_string(), count as usize))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT kind, source, COUNT(*) FROM metric_samples GROUP BY kind, source ORDER BY kind, source",
+    )?;
+    let metric_rows = stmt
+        .query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get::<_, i64>(2)? as usize))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let (battery_first, battery_last) = ts_range(&conn, "samples")?;
+    let (metric_first, metric_last) = ts_range(&conn, "metric_samples")?;
+    let pick = |a: Option<f64>, b: Option<f64>, f: fn(f64, f64) -> f64| match (a, b) {
+        (Some(a), Some(b)) => Some(f(a, b)),
+        (a, b) => a.or(b),
+    };
+    Ok(DbStats {
+        file_size_bytes: fs::metadata(db_path)?.len(),
+        table_rows,
+        metric_rows,
+        first_ts: pick(battery_first, metric_first, f64::min),
+        last_ts: pick(battery_last, metric_last, f64::max),
+    })
+}
+
+/// Rebuilds the database file to reclaim free pages; returns the size before and after.
+pub fn vacuum(db_path: &Path) -> Result<(u64, u64)> {
+    let conn = open_existing(db_path)?;
+    let before = fs::metadata(db_path)?.len();
+    conn.execute_batch("VACUUM")?;
+    drop(conn);
+    Ok((before, fs::metadata(db_path)?.len()))
+}
+
+/// Metric rows whose `details` [`set_details_compression`] rewrote, and their size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DetailsRewrite {
+    pub rows: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// Turns compression of metric details on or off: rows inserted afterwards follow the
+/// setting, and stored rows are rewritten to match, a page of rowids at a time in one
+/// transaction. The file only shrinks after a [`vacuum`].
+pub fn set_details_compression(db_path: &Path, compress: bool) -> Result<DetailsRewrite> {
+    set_details_compression_with_progress(db_path, compress, |_, _| {})
+}
+
+/// [`set_details_compression`], calling `progress(done, total)` after each page with the
+/// rowids walked so far out of the highest one, an estimate of the rows left to check.
+pub fn set_details_compression_with_progress(
+    db_path: &Path,
+    compress: bool,
+    mut progress: impl FnMut(u64, u64),
+) -> Result<DetailsRewrite> {
+    #[cfg(not(feature = "details-compression"))]
+    if compress {
+        anyhow::bail!(details::DISABLED);
+    }
+    let mut conn = open_existing(db_path)?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let max_rowid: i64 = tx.query_row(
+        "SELECT IFNULL(MAX(rowid), 0) FROM metric_samples",
+        [],
+        |row| row.get(0),
+    )?;
+    set_state(&tx, COMPRESS_DETAILS_KEY, if compress { "1" } else { "0" })?;
+    let stored_as = if compress { "text" } else { "blob" };
+    let mut rewrite = DetailsRewrite::default();
+    let mut after_rowid = 0_i64;
+    loop {
+        let page: Vec<(i64, Vec<u8>)> = tx
+            .prepare_cached(
+                "SELECT rowid, CAST(details AS BLOB) FROM metric_samples \
+                 WHERE rowid > ? AND typeof(details) = ? ORDER BY rowid LIMIT ?",
+            )?
+            .query_map(params![after_rowid, stored_as, PAGE_ROWS], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        for (rowid, stored) in &page {
+            let rewritten = if compress {
+                details::compress(std::str::from_utf8(stored)?)?.map(Value::Blob)
+            } else {
+                Some(Value::Text(details::decompress(stored)?))
+            };
+            let Some(rewritten) = rewritten else {
+                continue;
+            };
+            rewrite.rows += 1;
+            rewrite.bytes_before += stored.len() as u64;
+            rewrite.bytes_after += match &rewritten {
+                Value::Blob(blob) => blob.len(),
+                Value::Text(text) => text.len(),
+                _ => 0,
+            } as u64;
+            tx.prepare_cached("UPDATE metric_samples SET details = ? WHERE rowid = ?")?
+                .execute(params![rewritten, rowid])?;
+        }
+        if let Some((rowid, _)) = page.last() {
+            progress(*rowid as u64, max_rowid as u64);
+        }
+        match page.last() {
+            Some((rowid, _)) if page.len() == PAGE_ROWS => after_rowid = *rowid,
+            _ => break,
+        }
+    }
+    tx.commit()?;
+    Ok(rewrite)
+}
+
+/// Deletes battery rows repeating an earlier (source, ts) and metric rows repeating an
+/// earlier (kind, source, ts), keeping the first stored, then adds the unique battery index.
+/// Returns the rows removed from `samples` and `metric_samples`.
+pub fn dedupe(db_path: &Path) -> Result<(usize, usize)> {
+    let mut conn = open_existing(db_path)?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let samples = tx.execute(
+        "DELETE FROM samples WHERE rowid NOT IN \
+         (SELECT MIN(rowid) FROM samples GROUP BY source_path, ts)",
+        [],
+    )?;
+    let metrics = tx.execute(
+        "DELETE FROM metric_samples WHERE rowid NOT IN \
+         (SELECT MIN(rowid) FROM metric_samples GROUP BY kind, source, ts)",
+        [],
+    )?;
+    tx.execute_batch(UNIQUE_SAMPLES_INDEX)?;
+    tx.commit()?;
+    Ok((samples, metrics))
+}
+
+/// Runs one statement (several are refused) on a read-only connection and returns its
+/// column names and rows. Integers and reals become JSON numbers, text strings,
+/// compressed details their JSON text and other blobs `<N bytes>`.
+pub fn read_only_query(
+    db_path: &Path,
+    sql: &str,
+) -> Result<(Vec<String>, Vec<Vec<serde_json::Value>>)> {
+    if !db_path.exists() {
+        anyhow::bail!("No database at {}", db_path.display());
+    }
+    let conn = Connection::open_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.pragma_update(None, "query_only", true)?;
+    let mut batch = Batch::new(&conn, sql);
+    let Some(mut stmt) = batch.next()? else {
+        anyhow::bail!("The query is empty");
+    };
+    if batch.next()?.is_some() {
+        anyhow::bail!("Pass one statement at a time");
+    }
+    if !stmt.readonly() {
+        anyhow::bail!("Only read-only statements are allowed (SELECT, WITH, EXPLAIN, ...)");
+    }
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let mut rows = stmt.query([])?;
+    let mut out = Vec::new();
+    while let Some(row) = rows.next()? {
+        let mut cells = Vec::with_capacity(columns.len());
+        for idx in 0..columns.len() {
+            cells.push(match row.get_ref(idx)? {
+                ValueRef::Null => serde_json::Value::Null,
+                ValueRef::Integer(value) => value.into(),
+                ValueRef::Real(value) => value.into(),
+                ValueRef::Text(text) => String::from_utf8_lossy(text).into(),
+                ValueRef::Blob(blob) => match details::decompress(blob) {
+                    Ok(text) => text.into(),
+                    Err(_) => format!("<{} bytes>", blob.len()).into(),
+                },
+            });
+        }
+        out.push(cells);
+    }
+    Ok((columns, out))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub sql_type: String,
+    pub not_null: bool,
+    pub meaning: &'static str,
+    pub unit: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TableSchema {
+    pub name: String,
+    pub sql: String,
+    pub columns: Vec<ColumnSchema>,
+    pub indexes: Vec<String>,
+}
+
+/// Meaning and unit of each column [`SCHEMA`] creates.
+fn column_doc(table: &str, column: &str) -> (&'static str, Option<&'static str>) {
+    match (table, column) {
+        ("samples", "ts") => ("When the battery was read", Some("Unix seconds")),
+        ("samples", "percentage") => ("Charge level", Some("%")),
+        ("samples", "capacity_pct") => ("Capacity level reported by the kernel", Some("%")),
+        ("samples", "health_pct") => ("energy_full as a share of energy_full_design", Some("%")),
+        ("samples", "energy_now_wh") => ("Energy stored now", Some("Wh")),
+        ("samples", "energy_full_wh") => ("Energy stored when full today", Some("Wh")),
+        ("samples", "energy_full_design_wh") => ("Energy stored when full as designed", Some("Wh")),
+        ("samples", "status") => ("Charging, Discharging, Full, Not charging or Unknown", None),
+        ("samples", "source_path") => ("sysfs directory of the battery", None),
+        ("metric_samples", "ts") => ("When the metric was read", Some("Unix seconds")),
+        ("metric_samples", "kind") => ("Metric kind, one of the kinds below", None),
+        ("metric_samples", "source") => {
+            ("What was measured: cpu, cpu0, wlan0, a sensor, ...", None)
+        }
+        ("metric_samples", "value") => ("The reading; NULL for kinds carried in details", None),
+        ("metric_samples", "unit") => ("Unit of value, per kind below", None),
+        ("metric_samples", "details") => (
+            "Extra fields as a JSON object; a zstd blob after `db compress`",
+            None,
+        ),
+        ("annotations", "ts") => ("When the note was added", Some("Unix seconds")),
+        ("annotations", "text") => ("The note", None),
+        ("system_versions", "ts") => (
+            "First collection seen with these versions",
+            Some("Unix seconds"),
+        ),
+        ("system_versions", "kernel") => ("Kernel release (uname -r)", None),
+        ("system_versions", "os_release") => ("PRETTY_NAME from /etc/os-release", None),
+        ("system_versions", "symmetri_version") => ("symmetri version that collected", None),
+        ("battery_info", "ts") => (
+            "First collection seen with this battery",
+            Some("Unix seconds"),
+        ),
+        ("battery_info", "source_path") => ("sysfs directory of the battery", None),
+        ("battery_info", "manufacturer") => ("Vendor reported by the driver", None),
+        ("battery_info", "model_name") => ("Model reported by the driver", None),
+        ("battery_info", "serial_number") => ("Serial number reported by the driver", None),
+        ("battery_info", "technology") => ("Cell chemistry, e.g. Li-ion", None),
+        ("battery_info", "energy_full_design_wh") => ("Capacity when new", Some("Wh")),
+        ("sleep_intervals", "start_ts") => ("Suspend start", Some("Unix seconds")),
+        ("sleep_intervals", "end_ts") => ("Resume", Some("Unix seconds")),
+        ("sleep_intervals", "slept_seconds") => ("Time spent suspended", Some("s")),
+        ("sleep_intervals", "start_pct") => ("Charge level before suspending", Some("%")),
+        ("sleep_intervals", "end_pct") => ("Charge level after resuming", Some("%")),
+        ("sleep_intervals", "start_status") => ("Battery status before suspending", None),
+        ("sleep_intervals", "end_status") => ("Battery status after resuming", None),
+        ("collector_state", "key") => ("Collector state name", None),
+        ("collector_state", "value") => ("Collector state, usually JSON", None),
+        ("calibrations", "ts") => ("When the calibration run finished", Some("Unix seconds")),
+        ("calibrations", "source_path") => ("sysfs directory of the battery", None),
+        ("calibrations", "full_wh") => ("energy_full when the run started", Some("Wh")),
+        ("calibrations", "usable_wh") => ("Energy counted from full to empty", Some("Wh")),
+        ("calibrations", "points") => ("Gauge curve as JSON [{reported_pct, true_pct}, ...]", None),
+        ("baselines", "name") => ("Name given to `baseline save`", None),
+        ("baselines", "ts") => ("When the baseline was saved", Some("Unix seconds")),
+        ("baselines", "start_ts") => ("Start of the recorded period", Some("Unix seconds")),
+        ("baselines", "end_ts") => ("End of the recorded period", Some("Unix seconds")),
+        ("baselines", "stats") => (
+            "Per-metric statistics as JSON {metric: {mean, stddev, count}}",
+            None,
+        ),
+        ("workloads", "start_ts") => ("Start of the classified window", Some("Unix seconds")),
+        ("workloads", "end_ts") => ("End of the classified window", Some("Unix seconds")),
+        ("workloads", "workload") => ("idle, browsing, compiling, video or gaming", None),
+        ("activities", "name") => ("Name given to `mark start`", None),
+        ("activities", "start_ts") => ("When the activity started", Some("Unix seconds")),
+        ("activities", "end_ts") => ("When it stopped; NULL while running", Some("Unix seconds")),
+        _ => ("", None),
+    }
+}
+
+/// The tables symmetri creates, read back from SQLite so the definitions match what
+/// [`init_db_connection`] writes, with the meaning and unit of every column.
+pub fn schema() -> Result<Vec<TableSchema>> {
+    let conn = Connection::open_in_memory()?;
+    conn.execute_batch(SCHEMA)?;
+    migrate(&conn, Path::new(":memory:"))?;
+    let mut stmt =
+        conn.prepare("SELECT name, sql FROM sqlite_master WHERE type = 'table' ORDER BY rowid")?;
+    let tables: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    let mut out = Vec::with_capacity(tables.len());
+    for (name, sql) in tables {
+        let mut columns_stmt =
+            conn.prepare("SELECT name, type, \"notnull\" FROM pragma_table_info(?)")?;
+        let columns = columns_stmt
+            .query_map([&name], |row| {
+                let column: String = row.get(0)?;
+                let (meaning, unit) = column_doc(&name, &column);
+                Ok(ColumnSchema {
+                    name: column,
+                    sql_type: row.get(1)?,
+                    not_null: row.get(2)?,
+                    meaning,
+                    unit,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        let mut index_stmt = conn.prepare(
+            "SELECT sql FROM sqlite_master WHERE type = 'index' AND tbl_name = ? AND sql IS NOT NULL ORDER BY rowid",
+        )?;
+        let indexes = index_stmt
+            .query_map([&name], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        out.push(TableSchema {
+            name,
+            sql,
+            columns,
+            indexes,
+        });
+    }
+    Ok(out)
+}
+
 fn sample_from_row(row: &Row) -> rusqlite::Result<Sample> {
     Ok(Sample {
         ts: row.get("ts")?,
@@ -269,10 +1384,19 @@ fn metric_from_row(row: &Row) -> rusqlite::Result<MetricSample> {
             Box::new(std::fmt::Error),
         )
     })?;
-    let details_raw: Option<String> = row.get("details")?;
-    let details = match details_raw {
-        Some(text) => serde_json::from_str(&text).unwrap_or(serde_json::Value::Null),
-        None => serde_json::Value::Null,
+    let details = match row.get_ref("details")? {
+        ValueRef::Text(text) => serde_json::from_slice(text).unwrap_or(serde_json::Value::Null),
+        ValueRef::Blob(blob) => {
+            let text = details::decompress(blob).map_err(|err| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    0,
+                    rusqlite::types::Type::Blob,
+                    err.into(),
+                )
+            })?;
+            serde_json::from_str(&text).unwrap_or(serde_json::Value::Null)
+        }
+        _ => serde_json::Value::Null,
     };
 
     Ok(MetricSample {
@@ -285,22 +1409,275 @@ fn metric_from_row(row: &Row) -> rusqlite::Result<MetricSample> {
     })
 }
 
-pub fn fetch_samples(db_path: &Path, since_ts: Option<f64>) -> Result<Vec<Sample>> {
-    let conn = Connection::open(db_path)?;
-    let mut stmt = conn.prepare(if since_ts.is_some() {
-        "SELECT * FROM samples WHERE ts >= ? ORDER BY ts"
-    } else {
-        "SELECT * FROM samples ORDER BY ts"
-    })?;
-    let rows = match since_ts {
-        Some(ts) => stmt.query_map(params![ts], sample_from_row)?,
-        None => stmt.query_map([], sample_from_row)?,
-    };
-    let mut samples = Vec::new();
-    for row in rows {
-        samples.push(row?);
+/// Rows per page of a [`Cursor`].
+pub const PAGE_ROWS: usize = 5_000;
+
+/// `SELECT * ... ORDER BY ts` on `samples` or `metric_samples`, built in one place so
+/// `db explain` shows the plans of the statements the reads actually run.
+#[derive(Clone)]
+struct TsQuery {
+    from: &'static str,
+    conditions: Vec<String>,
+    params: Vec<Value>,
+}
+
+impl TsQuery {
+    fn samples() -> Self {
+        TsQuery {
+            from: "samples",
+            conditions: Vec::new(),
+            params: Vec::new(),
+        }
     }
-    Ok(samples)
+
+    /// One kind reads `(kind, ts)`. Several kinds walk `(ts, kind, source)` in time order
+    /// and check the kind in the index, so SQLite never sorts the result (which would
+    /// make every page of a paged read cost as much as the whole window).
+    fn metrics(kinds: Option<&[MetricKind]>) -> Self {
+        let mut query = TsQuery {
+            from: "metric_samples",
+            conditions: Vec::new(),
+            params: Vec::new(),
+        };
+        match kinds {
+            None => {}
+            Some([kind]) => {
+                query.conditions.push("kind = ?".to_string());
+                query.params.push(Value::Text(kind.as_str().to_string()));
+            }
+            Some(kinds) => {
+                // The unary `+` keeps SQLite from using `(kind, ts)` for this term.
+                let placeholders = vec!["?"; kinds.len()].join(", ");
+                query.conditions.push(format!("+kind IN ({placeholders})"));
+                query.params.extend(
+                    kinds
+                        .iter()
+                        .map(|kind| Value::Text(kind.as_str().to_string())),
+                );
+            }
+        }
+        query
+    }
+
+    fn bound(mut self, condition: &str, ts: f64) -> Self {
+        self.conditions.push(condition.to_string());
+        self.params.push(Value::Real(ts));
+        self
+    }
+
+    fn since(self, ts: Option<f64>) -> Self {
+        match ts {
+            Some(ts) => self.bound("ts >= ?", ts),
+            None => self,
+        }
+    }
+
+    fn after(self, ts: f64) -> Self {
+        self.bound("ts > ?", ts)
+    }
+
+    fn until(self, ts: Option<f64>) -> Self {
+        match ts {
+            Some(ts) => self.bound("ts < ?", ts),
+            None => self,
+        }
+    }
+
+    fn at(self, ts: f64) -> Self {
+        self.bound("ts = ?", ts)
+    }
+
+    fn sql(&self, limit: Option<usize>) -> String {
+        let mut sql = format!("SELECT * FROM {}", self.from);
+        if !self.conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&self.conditions.join(" AND "));
+        }
+        sql.push_str(" ORDER BY ts");
+        if let Some(limit) = limit {
+            sql.push_str(&format!(" LIMIT {limit}"));
+        }
+        sql
+    }
+
+    fn count(&self, conn: &Connection) -> Result<usize> {
+        let mut sql = format!("SELECT COUNT(*) FROM {}", self.from);
+        if !self.conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&self.conditions.join(" AND "));
+        }
+        let count: i64 = conn.query_row(&sql, params_from_iter(&self.params), |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    fn fetch<T>(
+        &self,
+        conn: &Connection,
+        limit: Option<usize>,
+        from_row: fn(&Row) -> rusqlite::Result<T>,
+    ) -> Result<Vec<T>> {
+        let mut stmt = conn.prepare(&self.sql(limit))?;
+        let rows = stmt.query_map(params_from_iter(&self.params), from_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+/// Battery or metric samples in a `[since, until)` window, oldest first, read a page of
+/// about [`PAGE_ROWS`] rows at a time so year-long windows never sit in memory whole.
+/// Iterate it for single rows, or call [`Cursor::next_page`] to work a page at a time.
+///
+/// Pages are keyset-paginated: each starts after the last `ts` of the previous one, so it
+/// is an index range scan however deep into the table it is. Rows sharing the last `ts`
+/// of a full page are read in full before moving on, so a collection is never split
+/// across pages.
+pub struct Cursor<T> {
+    conn: Connection,
+    query: TsQuery,
+    since_ts: Option<f64>,
+    until_ts: Option<f64>,
+    after: Option<f64>,
+    done: bool,
+    from_row: fn(&Row) -> rusqlite::Result<T>,
+    ts_of: fn(&T) -> f64,
+    buffered: std::vec::IntoIter<T>,
+}
+
+impl<T> Cursor<T> {
+    fn new(
+        db_path: &Path,
+        query: TsQuery,
+        since_ts: Option<f64>,
+        until_ts: Option<f64>,
+        from_row: fn(&Row) -> rusqlite::Result<T>,
+        ts_of: fn(&T) -> f64,
+    ) -> Result<Self> {
+        Ok(Cursor {
+            conn: connect(db_path)?,
+            query,
+            since_ts,
+            until_ts,
+            after: None,
+            done: false,
+            from_row,
+            ts_of,
+            buffered: Vec::new().into_iter(),
+        })
+    }
+
+    /// Rows in the whole window when called, counted on the same index the pages read.
+    /// An estimate for progress bars: the collector may add rows while they are read.
+    pub fn estimated_rows(&self) -> Result<usize> {
+        self.query
+            .clone()
+            .since(self.since_ts)
+            .until(self.until_ts)
+            .count(&self.conn)
+    }
+
+    /// The rest of the current page, else the next one; `None` once the window is read.
+    pub fn next_page(&mut self) -> Result<Option<Vec<T>>> {
+        let buffered: Vec<T> = self.buffered.by_ref().collect();
+        if !buffered.is_empty() {
+            return Ok(Some(buffered));
+        }
+        if self.done {
+            return Ok(None);
+        }
+        let page_query = match self.after {
+            Some(ts) => self.query.clone().after(ts),
+            None => self.query.clone().since(self.since_ts),
+        };
+        let mut page =
+            page_query
+                .until(self.until_ts)
+                .fetch(&self.conn, Some(PAGE_ROWS), self.from_row)?;
+        let full = page.len() == PAGE_ROWS;
+        match page.last().map(self.ts_of).filter(|_| full) {
+            Some(last) => {
+                let ts_of = self.ts_of;
+                page.retain(|row| ts_of(row) != last);
+                page.extend(
+                    self.query
+                        .clone()
+                        .at(last)
+                        .fetch(&self.conn, None, self.from_row)?,
+                );
+                self.after = Some(last);
+            }
+            None => self.done = true,
+        }
+        Ok((!page.is_empty()).then_some(page))
+    }
+}
+
+impl<T> Iterator for Cursor<T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(row) = self.buffered.next() {
+            return Some(Ok(row));
+        }
+        match self.next_page() {
+            Ok(Some(page)) => {
+                self.buffered = page.into_iter();
+                self.buffered.next().map(Ok)
+            }
+            Ok(None) => None,
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Battery samples in `[since_ts, until_ts)`, streamed; see [`Cursor`].
+pub fn sample_cursor(
+    db_path: &Path,
+    since_ts: Option<f64>,
+    until_ts: Option<f64>,
+) -> Result<Cursor<Sample>> {
+    Cursor::new(
+        db_path,
+        TsQuery::samples(),
+        since_ts,
+        until_ts,
+        sample_from_row,
+        |sample| sample.ts,
+    )
+}
+
+/// Metric samples in `[since_ts, until_ts)`, optionally only `kinds`, streamed; see
+/// [`Cursor`].
+pub fn metric_cursor(
+    db_path: &Path,
+    since_ts: Option<f64>,
+    until_ts: Option<f64>,
+    kinds: Option<&[MetricKind]>,
+) -> Result<Cursor<MetricSample>> {
+    Cursor::new(
+        db_path,
+        TsQuery::metrics(kinds),
+        since_ts,
+        until_ts,
+        metric_from_row,
+        |sample| sample.ts,
+    )
+}
+
+pub fn fetch_samples(db_path: &Path, since_ts: Option<f64>) -> Result<Vec<Sample>> {
+    let conn = connect(db_path)?;
+    TsQuery::samples()
+        .since(since_ts)
+        .fetch(&conn, None, sample_from_row)
+}
+
+pub fn fetch_samples_between(db_path: &Path, start_ts: f64, end_ts: f64) -> Result<Vec<Sample>> {
+    let conn = connect(db_path)?;
+    TsQuery::samples()
+        .since(Some(start_ts))
+        .until(Some(end_ts))
+        .fetch(&conn, None, sample_from_row)
 }
 
 pub fn fetch_metric_samples(
@@ -308,34 +1685,116 @@ pub fn fetch_metric_samples(
     since_ts: Option<f64>,
     kinds: Option<&[MetricKind]>,
 ) -> Result<Vec<MetricSample>> {
-    let conn = Connection::open(db_path)?;
-    let mut stmt = conn.prepare(if since_ts.is_some() {
-        "SELECT * FROM metric_samples WHERE ts >= ? ORDER BY ts"
-    } else {
-        "SELECT * FROM metric_samples ORDER BY ts"
-    })?;
-    let rows = match since_ts {
-        Some(ts) => stmt.query_map(params![ts], metric_from_row)?,
-        None => stmt.query_map([], metric_from_row)?,
-    };
-    let mut samples = Vec::new();
-    for row in rows {
-        let sample = row?;
-        if let Some(filter) = kinds {
-            if !filter.contains(&sample.kind) {
-                continue;
-            }
-        }
-        samples.push(sample);
+    let conn = connect(db_path)?;
+    TsQuery::metrics(kinds)
+        .since(since_ts)
+        .fetch(&conn, None, metric_from_row)
+}
+
+pub fn fetch_metric_samples_between(
+    db_path: &Path,
+    start_ts: f64,
+    end_ts: f64,
+    kinds: Option<&[MetricKind]>,
+) -> Result<Vec<MetricSample>> {
+    let conn = connect(db_path)?;
+    TsQuery::metrics(kinds)
+        .since(Some(start_ts))
+        .until(Some(end_ts))
+        .fetch(&conn, None, metric_from_row)
+}
+
+/// `EXPLAIN QUERY PLAN` of one of the read paths above.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryPlan {
+    pub name: &'static str,
+    pub sql: String,
+    pub steps: Vec<String>,
+}
+
+impl QueryPlan {
+    /// Whether SQLite reads through an index and returns rows in index order, without a
+    /// full table scan or a temporary sort.
+    pub fn is_indexed(&self) -> bool {
+        self.steps.iter().all(|step| {
+            !step.contains("TEMP B-TREE")
+                && (!(step.starts_with("SCAN") || step.starts_with("SEARCH"))
+                    || step.contains("INDEX"))
+        })
     }
-    Ok(samples)
+}
+
+/// Plans SQLite picks for the reads reports, exports and paging run, on the database at
+/// `db_path` (plans depend on its indexes).
+pub fn query_plans(db_path: &Path) -> Result<Vec<QueryPlan>> {
+    let conn = init_db_connection(db_path)?;
+    let several = [MetricKind::PowerDraw, MetricKind::CpuUsage];
+    let paths: Vec<(&'static str, TsQuery, Option<usize>)> = vec![
+        ("samples since", TsQuery::samples().since(Some(0.0)), None),
+        (
+            "samples between",
+            TsQuery::samples().since(Some(0.0)).until(Some(1.0)),
+            None,
+        ),
+        (
+            "samples page",
+            TsQuery::samples().after(0.0).until(Some(1.0)),
+            Some(PAGE_ROWS),
+        ),
+        (
+            "metrics since",
+            TsQuery::metrics(None).since(Some(0.0)),
+            None,
+        ),
+        (
+            "metrics of one kind",
+            TsQuery::metrics(Some(&several[..1]))
+                .since(Some(0.0))
+                .until(Some(1.0)),
+            None,
+        ),
+        (
+            "metrics of several kinds",
+            TsQuery::metrics(Some(&several))
+                .since(Some(0.0))
+                .until(Some(1.0)),
+            None,
+        ),
+        (
+            "metrics page",
+            TsQuery::metrics(None).after(0.0).until(Some(1.0)),
+            Some(PAGE_ROWS),
+        ),
+        (
+            "metrics page, several kinds",
+            TsQuery::metrics(Some(&several)).after(0.0),
+            Some(PAGE_ROWS),
+        ),
+        (
+            "metrics at one timestamp",
+            TsQuery::metrics(Some(&several)).at(0.0),
+            None,
+        ),
+    ];
+    let mut plans = Vec::with_capacity(paths.len());
+    for (name, query, limit) in paths {
+        let sql = query.sql(limit);
+        let mut stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {sql}"))?;
+        let steps = stmt
+            .query_map(params_from_iter(&query.params), |row| {
+                row.get::<_, String>(3)
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        plans.push(QueryPlan { name, sql, steps });
+    }
+    Ok(plans)
 }
 
 pub fn fetch_latest_metric_samples(
     db_path: &Path,
     kinds: Option<&[MetricKind]>,
 ) -> Result<Vec<MetricSample>> {
-    let conn = Connection::open(db_path)?;
+    let conn = connect(db_path)?;
     let mut stmt = conn.prepare("SELECT * FROM metric_samples ORDER BY ts DESC")?;
     let rows = stmt.query_map([], metric_from_row)?;
     let mut seen: HashSet<(MetricKind, String)> = HashSet::new();
@@ -357,7 +1816,7 @@ pub fn fetch_latest_metric_samples(
 }
 
 pub fn fetch_samples_for_timestamp(db_path: &Path, ts: f64) -> Result<Vec<Sample>> {
-    let conn = Connection::open(db_path)?;
+    let conn = connect(db_path)?;
     let mut stmt = conn.prepare("SELECT * FROM samples WHERE ts = ? ORDER BY source_path")?;
     let rows = stmt.query_map(params![ts], sample_from_row)?;
     let mut samples = Vec::new();
@@ -368,7 +1827,7 @@ pub fn fetch_samples_for_timestamp(db_path: &Path, ts: f64) -> Result<Vec<Sample
 }
 
 pub fn fetch_first_sample(db_path: &Path) -> Result<Option<Sample>> {
-    let conn = Connection::open(db_path)?;
+    let conn = connect(db_path)?;
     let mut stmt = conn.prepare("SELECT * FROM samples ORDER BY ts ASC LIMIT 1")?;
     let mut rows = stmt.query([])?;
     if let Some(row) = rows.next()? {
@@ -379,7 +1838,7 @@ pub fn fetch_first_sample(db_path: &Path) -> Result<Option<Sample>> {
 }
 
 pub fn fetch_latest_sample(db_path: &Path) -> Result<Option<Sample>> {
-    let conn = Connection::open(db_path)?;
+    let conn = connect(db_path)?;
     let mut stmt = conn.prepare("SELECT * FROM samples ORDER BY ts DESC LIMIT 1")?;
     let mut rows = stmt.query([])?;
     if let Some(row) = rows.next()? {
@@ -389,8 +1848,19 @@ pub fn fetch_latest_sample(db_path: &Path) -> Result<Option<Sample>> {
     }
 }
 
+/// Timestamp of the newest battery or metric sample, `None` for an empty database.
+pub fn latest_ts(db_path: &Path) -> Result<Option<f64>> {
+    let conn = connect(db_path)?;
+    Ok(conn.query_row(
+        "SELECT MAX(ts) FROM (SELECT MAX(ts) AS ts FROM samples \
+         UNION ALL SELECT MAX(ts) FROM metric_samples)",
+        [],
+        |row| row.get(0),
+    )?)
+}
+
 pub fn fetch_recent_samples(db_path: &Path, limit: usize) -> Result<Vec<Sample>> {
-    let conn = Connection::open(db_path)?;
+    let conn = connect(db_path)?;
     let mut stmt = conn.prepare("SELECT * FROM samples ORDER BY ts DESC LIMIT ?")?;
     let rows = stmt.query_map(params![limit as i64], sample_from_row)?;
     let mut samples = Vec::new();
@@ -401,7 +1871,7 @@ pub fn fetch_recent_samples(db_path: &Path, limit: usize) -> Result<Vec<Sample>>
 }
 
 pub fn fetch_first_event(db_path: &Path) -> Result<Vec<Sample>> {
-    let conn = Connection::open(db_path)?;
+    let conn = connect(db_path)?;
     let mut stmt = conn.prepare("SELECT ts FROM samples ORDER BY ts ASC LIMIT 1")?;
     let mut rows = stmt.query([])?;
     let ts_row = match rows.next()? {
@@ -413,7 +1883,7 @@ pub fn fetch_first_event(db_path: &Path) -> Result<Vec<Sample>> {
 }
 
 pub fn fetch_latest_event(db_path: &Path) -> Result<Vec<Sample>> {
-    let conn = Connection::open(db_path)?;
+    let conn = connect(db_path)?;
     let mut stmt = conn.prepare("SELECT ts FROM samples ORDER BY ts DESC LIMIT 1")?;
     let mut rows = stmt.query([])?;
     let ts_row = match rows.next()? {
@@ -425,7 +1895,7 @@ pub fn fetch_latest_event(db_path: &Path) -> Result<Vec<Sample>> {
 }
 
 pub fn fetch_recent_events(db_path: &Path, limit: usize) -> Result<Vec<Vec<Sample>>> {
-    let conn = Connection::open(db_path)?;
+    let conn = connect(db_path)?;
     let mut stmt = conn.prepare("SELECT ts FROM samples GROUP BY ts ORDER BY ts DESC LIMIT ?")?;
     let mut rows = stmt.query(params![limit as i64])?;
     let mut events = Vec::new();
@@ -459,6 +1929,7 @@ pub fn create_sample_from_reading(reading: &BatteryReading, ts: Option<f64>) ->
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::baseline::Stat;
     use crate::metrics::{MetricKind, MetricSample};
     use serde_json::json;
 
@@ -489,6 +1960,315 @@ mod tests {
         assert_eq!(stored.percentage, Some(75.0));
         assert_eq!(stored.health_pct, Some(87.5));
         assert_eq!(stored.status.as_deref(), Some("Discharging"));
+
+        let mut calibration = Calibration {
+            ts,
+            source_path: "BAT0".to_string(),
+            full_wh: 70.0,
+            usable_wh: 66.0,
+            points: vec![CalibrationPoint {
+                reported_pct: 50.0,
+                true_pct: 42.0,
+            }],
+        };
+        insert_calibration(&db_path, &calibration).unwrap();
+        calibration.ts += 86_400.0;
+        calibration.usable_wh = 65.0;
+        insert_calibration(&db_path, &calibration).unwrap();
+        assert_eq!(fetch_calibrations(&db_path).unwrap(), vec![calibration]);
+        assert_eq!(fetch_calibration(&db_path, "BAT1").unwrap(), None);
+
+        let mut baseline = Baseline {
+            name: "idle".to_string(),
+            ts,
+            start_ts: ts - 3600.0,
+            end_ts: ts,
+            metrics: [(
+                "discharge".to_string(),
+                Stat {
+                    mean: 5.5,
+                    stddev: 0.4,
+                    count: 60,
+                },
+            )]
+            .into(),
+        };
+        insert_baseline(&db_path, &baseline).unwrap();
+        baseline.ts += 60.0;
+        insert_baseline(&db_path, &baseline).unwrap();
+        assert_eq!(fetch_baselines(&db_path).unwrap(), vec![baseline.clone()]);
+        assert_eq!(fetch_baseline(&db_path, None).unwrap(), Some(baseline));
+        assert!(delete_baseline(&db_path, "idle").unwrap());
+        assert_eq!(fetch_baseline(&db_path, Some("idle")).unwrap(), None);
+
+        let label = |start: f64, workload| Labelled {
+            start,
+            end: start + 300.0,
+            workload,
+        };
+        replace_workloads(
+            &db_path,
+            None,
+            None,
+            &[label(0.0, Workload::Idle), label(300.0, Workload::Video)],
+        )
+        .unwrap();
+        replace_workloads(
+            &db_path,
+            Some(300.0),
+            None,
+            &[label(300.0, Workload::Gaming)],
+        )
+        .unwrap();
+        assert_eq!(
+            fetch_workloads(&db_path, None, None).unwrap(),
+            vec![label(0.0, Workload::Idle), label(300.0, Workload::Gaming)]
+        );
+
+        assert_eq!(stop_activity(&db_path, 10.0).unwrap(), None);
+        start_activity(&db_path, "meeting", 100.0).unwrap();
+        let stopped = start_activity(&db_path, "focus", 200.0).unwrap().unwrap();
+        assert_eq!(
+            (stopped.name.as_str(), stopped.end_ts),
+            ("meeting", Some(200.0))
+        );
+        let activities = fetch_activities(&db_path, Some(150.0), None).unwrap();
+        assert_eq!(activities.len(), 2);
+        assert_eq!(activities[1].end_ts, None);
+        assert_eq!(
+            fetch_activities(&db_path, Some(250.0), None).unwrap().len(),
+            1
+        );
+        assert_eq!(
+            stop_activity(&db_path, 300.0).unwrap().map(|a| a.name),
+            Some("focus".to_string())
+        );
+    }
+
+    #[test]
+    fn duplicates_are_collapsed_and_then_ignored() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("dupes.db");
+        // A database from before the unique index, written by two collectors at once.
+        let legacy = Connection::open(&db_path).unwrap();
+        legacy.execute_batch(SCHEMA).unwrap();
+        for percentage in [50.0, 51.0] {
+            legacy
+                .execute(
+                    "INSERT INTO samples (ts, percentage, source_path) VALUES (1.0, ?, 'BAT0')",
+                    params![percentage],
+                )
+                .unwrap();
+            legacy
+                .execute(
+                    "INSERT INTO metric_samples (ts, kind, source, value) VALUES (1.0, 'cpu_usage', 'cpu', ?)",
+                    params![percentage],
+                )
+                .unwrap();
+        }
+        drop(legacy);
+
+        let mut conn = init_db_connection(&db_path).unwrap();
+        assert_eq!(dedupe(&db_path).unwrap(), (1, 1));
+        assert_eq!(dedupe(&db_path).unwrap(), (0, 0));
+        let rows = fetch_samples(&db_path, None).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].percentage, Some(50.0));
+
+        let mut again = rows[0].clone();
+        again.percentage = Some(52.0);
+        insert_all_samples(&mut conn, &[again], &[]).unwrap();
+        assert_eq!(fetch_samples(&db_path, None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn migrations_run_once_per_database() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("migrated.db");
+        let conn = init_db_connection(&db_path).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), MIGRATIONS.len());
+        let indexes = |conn: &Connection| -> usize {
+            conn.query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'idx_samples_source_ts'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(indexes(&conn), 1);
+
+        // Once recorded, opening the database does not create the index again.
+        conn.execute_batch("DROP INDEX idx_samples_source_ts")
+            .unwrap();
+        drop(conn);
+        let conn = init_db_connection(&db_path).unwrap();
+        assert_eq!(indexes(&conn), 0);
+    }
+
+    #[test]
+    fn writers_wait_for_each_other_instead_of_failing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("busy.db");
+        let mut conn = init_db_connection(&db_path).unwrap();
+        let journal: String = conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal, "wal");
+
+        let holder = init_db_connection(&db_path).unwrap();
+        holder.execute_batch("BEGIN IMMEDIATE").unwrap();
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(300));
+            holder.execute_batch("COMMIT").unwrap();
+        });
+        let metric =
+            MetricSample::new(1.0, MetricKind::CpuUsage, "cpu", Some(1.0), None, json!({}));
+        insert_all_samples(&mut conn, &[], &[metric]).unwrap();
+        release.join().unwrap();
+    }
+
+    #[test]
+    fn schema_documents_every_column() {
+        let tables = schema().unwrap();
+        let names: Vec<&str> = tables.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "samples",
+                "metric_samples",
+                "annotations",
+                "system_versions",
+                "battery_info",
+                "sleep_intervals",
+                "collector_state",
+                "calibrations",
+                "baselines",
+                "workloads",
+                "activities"
+            ]
+        );
+        for table in &tables {
+            for column in &table.columns {
+                assert!(!column.meaning.is_empty(), "{}.{}", table.name, column.name);
+            }
+        }
+        let samples = &tables[0];
+        assert!(samples.sql.starts_with("CREATE TABLE samples"));
+        assert_eq!(samples.columns[0].sql_type, "REAL");
+        assert!(samples.columns[0].not_null);
+        assert!(samples
+            .indexes
+            .iter()
+            .any(|index| index.contains("idx_samples_source_ts")));
+    }
+
+    #[test]
+    fn sql_passthrough_is_read_only() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("sql.db");
+        let mut conn = init_db_connection(&db_path).unwrap();
+        let metric = MetricSample::new(
+            1.5,
+            MetricKind::CpuUsage,
+            "cpu",
+            Some(12.5),
+            Some("%"),
+            json!({}),
+        );
+        insert_all_samples(&mut conn, &[], &[metric]).unwrap();
+
+        let (columns, rows) = read_only_query(
+            &db_path,
+            "SELECT kind, value, unit, NULL AS empty, COUNT(*) AS n FROM metric_samples",
+        )
+        .unwrap();
+        assert_eq!(columns, vec!["kind", "value", "unit", "empty", "n"]);
+        assert_eq!(
+            rows,
+            vec![vec![
+                json!("cpu_usage"),
+                json!(12.5),
+                json!("%"),
+                serde_json::Value::Null,
+                json!(1)
+            ]]
+        );
+        let err = read_only_query(&db_path, "DELETE FROM metric_samples").unwrap_err();
+        assert!(err.to_string().contains("read-only"), "{err}");
+        assert!(read_only_query(&db_path, "CREATE TABLE scratch (a)").is_err());
+        assert!(read_only_query(&tmp.path().join("missing.db"), "SELECT 1").is_err());
+    }
+
+    #[test]
+    fn sql_passthrough_refuses_trailing_statements() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("sql.db");
+        let mut conn = init_db_connection(&db_path).unwrap();
+        let metric =
+            MetricSample::new(1.5, MetricKind::CpuUsage, "cpu", Some(1.0), None, json!({}));
+        insert_all_samples(&mut conn, &[], &[metric]).unwrap();
+
+        let err = read_only_query(&db_path, "SELECT 1; DELETE FROM metric_samples").unwrap_err();
+        assert!(err.to_string().contains("one statement at a time"), "{err}");
+        assert!(read_only_query(&db_path, "SELECT 1;\n").is_ok());
+        let rows: i64 = conn
+            .query_row("SELECT COUNT(*) FROM metric_samples", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(rows, 1);
+    }
+
+    #[test]
+    fn stats_count_rows_per_table_and_source() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("stats.db");
+        assert!(db_stats(&db_path).is_err());
+
+        let mut conn = init_db_connection(&db_path).unwrap();
+        let battery = Sample {
+            ts: 86_400.0,
+            percentage: Some(50.0),
+            capacity_pct: None,
+            health_pct: None,
+            energy_now_wh: None,
+            energy_full_wh: None,
+            energy_full_design_wh: None,
+            status: None,
+            source_path: "BAT0".to_string(),
+        };
+        let metric = |ts: f64, source: &str| {
+            MetricSample::new(
+                ts,
+                MetricKind::NetworkBytes,
+                source,
+                Some(1.0),
+                Some("bytes"),
+                serde_json::Value::Null,
+            )
+        };
+        let metrics = vec![
+            metric(0.0, "eth0"),
+            metric(0.0, "lo"),
+            metric(43_200.0, "lo"),
+        ];
+        insert_all_samples(&mut conn, &[battery], &metrics).unwrap();
+        drop(conn);
+
+        let stats = db_stats(&db_path).unwrap();
+        assert_eq!(stats.sample_rows(), 4);
+        assert_eq!(stats.rows("annotations"), 0);
+        assert_eq!(
+            stats.metric_rows,
+            vec![
+                ("network_bytes".to_string(), "eth0".to_string(), 1),
+                ("network_bytes".to_string(), "lo".to_string(), 2),
+            ]
+        );
+        assert_eq!((stats.first_ts, stats.last_ts), (Some(0.0), Some(86_400.0)));
+        assert_eq!(stats.samples_per_day(), Some(4.0));
+        assert!(stats.file_size_bytes > 0);
+
+        let (before, after) = vacuum(&db_path).unwrap();
+        assert!(after <= before);
     }
 
     #[test]
@@ -621,6 +2401,91 @@ mod tests {
         assert_eq!(latest_event.len(), 1);
         assert_eq!(latest_event[0].ts, 5.0);
         assert_eq!(recent_events.len(), 2);
+
+        let between = fetch_samples_between(&db_path, 0.0, 5.0).unwrap();
+        assert_eq!(between.len(), 2);
+        assert!(between.iter().all(|s| s.ts == 1.0));
+    }
+
+    #[test]
+    fn annotations_roundtrip_within_window() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("notes.db");
+        for (ts, text) in [
+            (1.0, "first"),
+            (5.0, "switched to power-saver"),
+            (9.0, "late"),
+        ] {
+            insert_annotation(
+                &db_path,
+                &Annotation {
+                    ts,
+                    text: text.to_string(),
+                },
+            )
+            .unwrap();
+        }
+
+        assert_eq!(fetch_annotations(&db_path, None, None).unwrap().len(), 3);
+        let window = fetch_annotations(&db_path, Some(2.0), Some(9.0)).unwrap();
+        assert_eq!(window.len(), 1);
+        assert_eq!(window[0].text, "switched to power-saver");
+    }
+
+    #[test]
+    fn sleep_intervals_and_state_roundtrip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("sleep.db");
+        let conn = init_db_connection(&db_path).unwrap();
+
+        let interval = SleepInterval {
+            start_ts: 100.0,
+            end_ts: 500.0,
+            slept_seconds: 380.0,
+            start_pct: Some(80.0),
+            end_pct: Some(78.5),
+            start_status: Some("Discharging".to_string()),
+            end_status: Some("Discharging".to_string()),
+        };
+        insert_sleep_interval(&conn, &interval).unwrap();
+        assert_eq!(
+            fetch_sleep_intervals(&db_path, Some(200.0), None).unwrap(),
+            vec![interval]
+        );
+        assert!(fetch_sleep_intervals(&db_path, Some(600.0), None)
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(get_state(&conn, "probe").unwrap(), None);
+        set_state(&conn, "probe", "one").unwrap();
+        set_state(&conn, "probe", "two").unwrap();
+        assert_eq!(get_state(&conn, "probe").unwrap().as_deref(), Some("two"));
+    }
+
+    #[test]
+    fn battery_info_rows_mark_replacements() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("info.db");
+        let conn = init_db_connection(&db_path).unwrap();
+
+        let original = BatteryInfo {
+            source_path: "/sys/class/power_supply/BAT0".to_string(),
+            manufacturer: Some("SMP".to_string()),
+            serial_number: Some("1234".to_string()),
+            energy_full_design_wh: Some(57.0),
+            ..BatteryInfo::default()
+        };
+        let replacement = BatteryInfo {
+            serial_number: Some("9876".to_string()),
+            ..original.clone()
+        };
+        assert!(record_battery_info(&conn, 10.0, &original).unwrap());
+        assert!(!record_battery_info(&conn, 20.0, &original).unwrap());
+        assert!(record_battery_info(&conn, 30.0, &replacement).unwrap());
+        assert_eq!(
+            fetch_battery_info(&db_path).unwrap(),
+            vec![(10.0, original), (30.0, replacement)]
+        );
     }
 
     #[test]
@@ -671,4 +2536,142 @@ mod tests {
         assert_eq!(latest[0].source, "cpu");
         assert_eq!(latest[0].value, Some(50.0));
     }
+
+    #[test]
+    fn cursors_match_full_reads_and_plans_use_indexes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("paged.db");
+        let mut conn = init_db_connection(&db_path).unwrap();
+        // Two batteries and three metric rows per timestamp, so page edges land inside a
+        // timestamp group.
+        let steps = PAGE_ROWS + 7;
+        let battery: Vec<Sample> = (0..steps)
+            .flat_map(|step| {
+                ["BAT0", "BAT1"].map(|source| Sample {
+                    ts: step as f64,
+                    percentage: Some(50.0),
+                    capacity_pct: None,
+                    health_pct: None,
+                    energy_now_wh: None,
+                    energy_full_wh: None,
+                    energy_full_design_wh: None,
+                    status: None,
+                    source_path: source.to_string(),
+                })
+            })
+            .collect();
+        let metrics: Vec<MetricSample> = (0..steps)
+            .flat_map(|step| {
+                [
+                    (MetricKind::CpuUsage, "cpu"),
+                    (MetricKind::CpuUsage, "cpu0"),
+                    (MetricKind::MemoryUsage, "memory"),
+                ]
+                .map(|(kind, source)| MetricSample {
+                    ts: step as f64,
+                    kind,
+                    source: source.to_string(),
+                    value: Some(1.0),
+                    unit: None,
+                    details: serde_json::Value::Null,
+                })
+            })
+            .collect();
+        insert_all_samples(&mut conn, &battery, &metrics).unwrap();
+
+        let mut cursor = sample_cursor(&db_path, Some(3.0), None).unwrap();
+        assert_eq!(cursor.estimated_rows().unwrap(), 2 * (steps - 3));
+        let mut paged: Vec<Sample> = Vec::new();
+        let mut pages = 0;
+        while let Some(page) = cursor.next_page().unwrap() {
+            assert!(paged.last().is_none_or(|last| last.ts < page[0].ts));
+            pages += 1;
+            paged.extend(page);
+        }
+        assert!(pages > 1);
+        assert_eq!(paged, fetch_samples(&db_path, Some(3.0)).unwrap());
+
+        let cpu = [MetricKind::CpuUsage];
+        let cursor = metric_cursor(&db_path, None, Some(10.0), Some(&cpu)).unwrap();
+        assert_eq!(cursor.estimated_rows().unwrap(), 20);
+        let window: Vec<MetricSample> = cursor.collect::<Result<_>>().unwrap();
+        assert_eq!(window.len(), 20);
+        assert!(window.iter().all(|m| m.kind == MetricKind::CpuUsage));
+        let mut cursor = metric_cursor(&db_path, None, None, None).unwrap();
+        // Taking a few rows and then a page hands back the rest of the first page.
+        let first = cursor.by_ref().take(4).count();
+        let rest = cursor.next_page().unwrap().unwrap();
+        assert_eq!(first + rest.len(), PAGE_ROWS + 1);
+        assert_eq!(first + rest.len() + cursor.count(), metrics.len());
+
+        let plans = query_plans(&db_path).unwrap();
+        assert!(!plans.is_empty());
+        for plan in plans {
+            assert!(plan.is_indexed(), "{}: {:?}", plan.name, plan.steps);
+        }
+    }
+
+    #[cfg(feature = "details-compression")]
+    #[test]
+    fn compressed_details_read_back_unchanged() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("compressed.db");
+        let mut conn = init_db_connection(&db_path).unwrap();
+        let memory = |ts| MetricSample {
+            ts,
+            kind: MetricKind::MemoryUsage,
+            source: "memory".to_string(),
+            value: Some(4.0e9),
+            unit: Some("bytes".to_string()),
+            details: json!({"total_bytes": 8.0e9, "available_bytes": 4.0e9, "used_bytes": 4.0e9}),
+        };
+        let stored_as = |conn: &Connection| -> Vec<String> {
+            conn.prepare("SELECT typeof(details) FROM metric_samples ORDER BY ts")
+                .unwrap()
+                .query_map([], |row| row.get(0))
+                .unwrap()
+                .collect::<rusqlite::Result<_>>()
+                .unwrap()
+        };
+        insert_all_samples(&mut conn, &[], &[memory(1.0)]).unwrap();
+
+        let rewrite = set_details_compression(&db_path, true).unwrap();
+        assert_eq!(rewrite.rows, 1);
+        assert!(rewrite.bytes_after < rewrite.bytes_before);
+        // New rows follow the setting; rows without details stay NULL.
+        insert_metric_samples(
+            &db_path,
+            &[
+                memory(2.0),
+                MetricSample {
+                    details: serde_json::Value::Null,
+                    ..memory(3.0)
+                },
+            ],
+        )
+        .unwrap();
+        assert_eq!(stored_as(&conn), ["blob", "blob", "null"]);
+        let details = |since| -> Vec<serde_json::Value> {
+            fetch_metric_samples(&db_path, since, None)
+                .unwrap()
+                .into_iter()
+                .map(|sample| sample.details)
+                .collect()
+        };
+        let expected = memory(1.0).details;
+        assert_eq!(
+            details(None),
+            [expected.clone(), expected.clone(), serde_json::Value::Null]
+        );
+        let (_, rows) = read_only_query(&db_path, "SELECT details FROM metric_samples").unwrap();
+        assert_eq!(rows[0][0], json!(expected.to_string()));
+
+        assert_eq!(set_details_compression(&db_path, false).unwrap().rows, 2);
+        insert_metric_samples(&db_path, &[memory(4.0)]).unwrap();
+        assert_eq!(stored_as(&conn), ["text", "text", "null", "text"]);
+        assert_eq!(
+            details(Some(2.0)),
+            [expected.clone(), serde_json::Value::Null, expected]
+        );
+    }
 }
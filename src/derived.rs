@@ -0,0 +1,421 @@
+//! Metrics computed from other metrics at collection time (`[derived.<name>]`), such as
+//! the sum of two power sources. They are stored as ordinary metric samples under their
+//! own name, so reports, graphs, queries and exporters treat them like collected ones.
+//!
+//! An expression combines numbers and `kind[source]` references with `+ - * /` and
+//! parentheses; `kind[source].field` reads a numeric detail instead of the value, e.g.
+//! `network_bytes[wlan0].rx_bytes`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::config::DerivedConfig;
+use crate::metrics::{MetricKind, MetricSample};
+
+/// Inputs older than this are treated as missing, so a collector that stopped does not
+/// keep feeding a derived metric its last value.
+const MAX_INPUT_AGE_SECONDS: f64 = 15.0 * 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Expr {
+    Number(f64),
+    Metric {
+        kind: MetricKind,
+        source: String,
+        /// A numeric key of the sample's details instead of its value.
+        field: Option<String>,
+    },
+    Neg(Box<Expr>),
+    Binary {
+        op: Op,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+}
+
+impl TryFrom<String> for Expr {
+    type Error = String;
+
+    fn try_from(raw: String) -> Result<Self, Self::Error> {
+        raw.parse()
+    }
+}
+
+impl FromStr for Expr {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { rest: raw };
+        let expr = parser.sum()?;
+        parser.skip_space();
+        match parser.rest.chars().next() {
+            None => Ok(expr),
+            Some(c) => Err(format!("unexpected {c:?} in {raw:?}")),
+        }
+    }
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl Parser<'_> {
+    fn skip_space(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_space();
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut left = self.product()?;
+        loop {
+            let op = if self.eat('+') {
+                Op::Add
+            } else if self.eat('-') {
+                Op::Sub
+            } else {
+                return Ok(left);
+            };
+            let right = self.product()?;
+            left = Expr::Binary {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                Op::Mul
+            } else if self.eat('/') {
+                Op::Div
+            } else {
+                return Ok(left);
+            };
+            let right = self.unary()?;
+            left = Expr::Binary {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat('-') {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        if self.eat('(') {
+            let inner = self.sum()?;
+            if !self.eat(')') {
+                return Err("missing ')'".to_string());
+            }
+            return Ok(inner);
+        }
+        self.skip_space();
+        let word_len = self
+            .rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+            .unwrap_or(self.rest.len());
+        let (word, rest) = self.rest.split_at(word_len);
+        if word.is_empty() {
+            return Err(match self.rest.chars().next() {
+                Some(c) => format!("expected a number or kind[source], found {c:?}"),
+                None => "expected a number or kind[source] at the end".to_string(),
+            });
+        }
+        self.rest = rest;
+        if let Ok(number) = word.parse::<f64>() {
+            return Ok(Expr::Number(number));
+        }
+        let kind = MetricKind::from_str(word)?;
+        let Some(rest) = self.rest.strip_prefix('[') else {
+            return Err(format!("expected [source] after {word}"));
+        };
+        let Some((source, rest)) = rest.split_once(']') else {
+            return Err(format!("missing ']' after {word}["));
+        };
+        self.rest = rest;
+        let field = match self.rest.strip_prefix('.') {
+            Some(rest) => {
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                if len == 0 {
+                    return Err(format!("expected a field name after {word}[{source}]."));
+                }
+                self.rest = &rest[len..];
+                Some(rest[..len].to_string())
+            }
+            None => None,
+        };
+        Ok(Expr::Metric {
+            kind,
+            source: source.to_string(),
+            field,
+        })
+    }
+}
+
+impl Expr {
+    /// Whether `sample` is one of the expression's inputs.
+    fn reads(&self, sample: &MetricSample) -> bool {
+        match self {
+            Expr::Number(_) => false,
+            Expr::Metric { kind, source, .. } => *kind == sample.kind && *source == sample.source,
+            Expr::Neg(inner) => inner.reads(sample),
+            Expr::Binary { left, right, .. } => left.reads(sample) || right.reads(sample),
+        }
+    }
+
+    /// The value with `lookup` resolving references; `None` when an input is missing or
+    /// the result is not a finite number (division by zero).
+    pub fn eval<'a>(
+        &self,
+        lookup: &dyn Fn(&MetricKind, &str) -> Option<&'a MetricSample>,
+    ) -> Option<f64> {
+        let value = match self {
+            Expr::Number(number) => *number,
+            Expr::Metric {
+                kind,
+                source,
+                field,
+            } => {
+                let sample = lookup(kind, source)?;
+                match field {
+                    Some(field) => sample.details.get(field).and_then(Value::as_f64)?,
+                    None => sample.value?,
+                }
+            }
+            Expr::Neg(inner) => -inner.eval(lookup)?,
+            Expr::Binary { op, left, right } => {
+                let (left, right) = (left.eval(lookup)?, right.eval(lookup)?);
+                match op {
+                    Op::Add => left + right,
+                    Op::Sub => left - right,
+                    Op::Mul => left * right,
+                    Op::Div => left / right,
+                }
+            }
+        };
+        value.is_finite().then_some(value)
+    }
+}
+
+/// Latest sample of every input, so a derived metric whose inputs come from collectors on
+/// different intervals is still computed whenever one of them reports.
+#[derive(Debug, Default)]
+pub struct DerivedState {
+    latest: HashMap<(MetricKind, String), MetricSample>,
+}
+
+impl DerivedState {
+    /// Derived samples for a batch of collected `samples`: one per `[derived.<name>]` that
+    /// reads any of them, timestamped with its newest input in the batch.
+    pub fn derive(
+        &mut self,
+        definitions: &BTreeMap<String, DerivedConfig>,
+        samples: &[MetricSample],
+    ) -> Vec<MetricSample> {
+        for sample in samples {
+            self.latest
+                .insert((sample.kind.clone(), sample.source.clone()), sample.clone());
+        }
+        let mut derived = Vec::new();
+        for (name, definition) in definitions {
+            let Some(ts) = samples
+                .iter()
+                .filter(|sample| definition.expr.reads(sample))
+                .map(|sample| sample.ts)
+                .max_by(f64::total_cmp)
+            else {
+                continue;
+            };
+            let lookup = |kind: &MetricKind, source: &str| {
+                self.latest
+                    .get(&(kind.clone(), source.to_string()))
+                    .filter(|sample| ts - sample.ts <= MAX_INPUT_AGE_SECONDS)
+            };
+            if let Some(value) = definition.expr.eval(&lookup) {
+                derived.push(MetricSample::new(
+                    ts,
+                    definition.kind.clone(),
+                    name.clone(),
+                    Some(value),
+                    definition.unit.as_deref(),
+                    Value::Null,
+                ));
+            }
+        }
+        derived
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    fn sample(ts: f64, kind: MetricKind, source: &str, value: f64) -> MetricSample {
+        MetricSample::new(
+            ts,
+            kind,
+            source,
+            Some(value),
+            None,
+            serde_json::json!({ "rx_bytes": value * 2.0 }),
+        )
+    }
+
+    fn eval(expr: &str, inputs: &[MetricSample]) -> Option<f64> {
+        let expr: Expr = expr.parse().unwrap();
+        expr.eval(&|kind: &MetricKind, source: &str| {
+            inputs
+                .iter()
+                .find(|sample| sample.kind == *kind && sample.source == source)
+        })
+    }
+
+    fn total_power() -> BTreeMap<String, DerivedConfig> {
+        parse_config(
+            "[derived.total_power]\nkind = \"power_draw\"\nunit = \"W\"\n\
+             expr = \"power_draw[rapl:power1] + power_draw[amdgpu:power1]\"\n",
+        )
+        .unwrap()
+        .derived
+    }
+
+    #[test]
+    fn expressions_parse_with_precedence_and_fields() {
+        let expr: Expr = "1 + power_draw[rapl:power1] * 2".parse().unwrap();
+        let Expr::Binary { op, right, .. } = expr else {
+            panic!("expected a sum, got {expr:?}");
+        };
+        assert_eq!(op, Op::Add);
+        assert!(matches!(*right, Expr::Binary { op: Op::Mul, .. }));
+        assert_eq!(
+            "network_bytes[wlan0].rx_bytes".parse::<Expr>().unwrap(),
+            Expr::Metric {
+                kind: MetricKind::NetworkBytes,
+                source: "wlan0".to_string(),
+                field: Some("rx_bytes".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn malformed_expressions_are_parse_errors() {
+        assert!(parse_config("[derived.x]\nkind = \"power_draw\"\nexpr = \"cpu[0] +\"\n").is_err());
+        assert!("power_draw[rapl".parse::<Expr>().is_err());
+        assert!("watts[rapl]".parse::<Expr>().is_err());
+        assert!("(1 + 2".parse::<Expr>().is_err());
+        assert!("1 2".parse::<Expr>().is_err());
+        assert!("power_draw[rapl].".parse::<Expr>().is_err());
+    }
+
+    #[test]
+    fn expressions_evaluate_values_and_detail_fields() {
+        let inputs = [sample(10.0, MetricKind::NetworkBytes, "wlan0", 50.0)];
+        assert_eq!(
+            eval(
+                "100 * network_bytes[wlan0].rx_bytes / -(0 - network_bytes[wlan0])",
+                &inputs
+            ),
+            Some(200.0)
+        );
+        assert_eq!(eval("(1 + 2) * 3 - 4 / 2", &[]), Some(7.0));
+    }
+
+    #[test]
+    fn division_by_zero_yields_no_value() {
+        let inputs = [sample(10.0, MetricKind::PowerDraw, "rapl:power1", 0.0)];
+        assert_eq!(eval("10 / power_draw[rapl:power1]", &inputs), None);
+        assert_eq!(eval("0 / 0", &[]), None);
+    }
+
+    #[test]
+    fn a_missing_input_or_field_yields_no_value() {
+        let inputs = [sample(10.0, MetricKind::PowerDraw, "rapl:power1", 6.0)];
+        assert_eq!(
+            eval(
+                "power_draw[rapl:power1] + power_draw[amdgpu:power1]",
+                &inputs
+            ),
+            None
+        );
+        assert_eq!(eval("power_draw[rapl:power1].tx_bytes", &inputs), None);
+    }
+
+    #[test]
+    fn derived_metrics_wait_for_every_input_and_combine_the_latest() {
+        let definitions = total_power();
+        let mut state = DerivedState::default();
+        let first = state.derive(
+            &definitions,
+            &[sample(10.0, MetricKind::PowerDraw, "rapl:power1", 6.0)],
+        );
+        // The GPU has not reported yet, so there is no total.
+        assert!(first.is_empty());
+
+        let second = state.derive(
+            &definitions,
+            &[sample(20.0, MetricKind::PowerDraw, "amdgpu:power1", 9.0)],
+        );
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].source, "total_power");
+        assert_eq!((second[0].ts, second[0].value), (20.0, Some(15.0)));
+        assert_eq!(second[0].unit.as_deref(), Some("W"));
+    }
+
+    #[test]
+    fn batches_without_an_input_derive_nothing() {
+        let mut state = DerivedState::default();
+        let derived = state.derive(
+            &total_power(),
+            &[sample(10.0, MetricKind::NetworkBytes, "wlan0", 50.0)],
+        );
+        assert!(derived.is_empty());
+    }
+
+    #[test]
+    fn stale_inputs_no_longer_count() {
+        let definitions = total_power();
+        let mut state = DerivedState::default();
+        state.derive(
+            &definitions,
+            &[
+                sample(10.0, MetricKind::PowerDraw, "rapl:power1", 6.0),
+                sample(10.0, MetricKind::PowerDraw, "amdgpu:power1", 9.0),
+            ],
+        );
+        let later = state.derive(
+            &definitions,
+            &[sample(2000.0, MetricKind::PowerDraw, "amdgpu:power1", 9.0)],
+        );
+        assert!(later.is_empty());
+    }
+}
@@ -0,0 +1,92 @@
+//! Compressed storage of metric `details`.
+//!
+//! Details are small JSON objects repeating the same keys row after row, too short for
+//! zstd to find the repetition on its own. They are compressed against a dictionary
+//! built into the binary that already holds those keys, so even a 30-byte object shrinks.
+//! A stored blob is one version byte followed by a zstd frame; the version names the
+//! dictionary, so a later dictionary can be added without breaking rows written earlier.
+
+use std::cell::RefCell;
+
+use anyhow::{anyhow, Result};
+use zstd::bulk::{Compressor, Decompressor};
+
+/// Version byte of blobs compressed against [`DICTIONARY_V1`].
+const VERSION_1: u8 = 1;
+
+/// Raw-content dictionary: the shapes of the details the collectors write, so their keys
+/// (and common values) compress to back-references.
+const DICTIONARY_V1: &[u8] = br#"{"sensor":"coretemp/temp1"}{"sensor":"k10temp/temp1"}{"sensor":"nvme/temp1"}{"sensor":"acpitz/temp1"}{"type":"irq"}{"type":"wakeup","wakeup_count":0}{"profile":"balanced"}{"profile":"power-saver"}{"profile":"performance"}{"profile":"powersave"}{"collectors":["gpu"]}{"streams":["card0/pcm0p"]}{"processes":[]}{"port":"1-1","manufacturer":null,"runtime_status":"suspended","speed_mbps":480.0}{"cpu_user_seconds":0.0,"cpu_system_seconds":0.0,"memory_bytes":0.0,"io_read_bytes":0.0,"io_write_bytes":0.0}{"link_quality":70.0,"noise_dbm":-95.0,"ssid":"","rx_bitrate_mbps":0.0,"tx_bitrate_mbps":0.0,"frequency_mhz":5180.0}{"original_bytes":0.0,"compressed_bytes":0.0,"mem_used_bytes":0.0,"ratio":0.0}{"total_bytes":0.0,"available_bytes":0.0,"free_bytes":0.0,"device":"/dev/nvme0n1p2","fstype":"ext4"}{"total_bytes":0.0,"free_bytes":0.0,"used_bytes":0.0}{"total_bytes":0.0,"available_bytes":0.0,"used_bytes":0.0}{"rx_bytes":0.0,"tx_bytes":0.0}"#;
+
+/// zstd level: the rows are tiny, so higher levels buy nothing but time.
+const LEVEL: i32 = 3;
+
+thread_local! {
+    static COMPRESSOR: RefCell<Option<Compressor<'static>>> = const { RefCell::new(None) };
+    static DECOMPRESSOR: RefCell<Option<Decompressor<'static>>> = const { RefCell::new(None) };
+}
+
+/// `json` compressed, or `None` when that would not make it smaller.
+pub fn compress(json: &str) -> Result<Option<Vec<u8>>> {
+    COMPRESSOR.with(|cell| {
+        let mut cell = cell.borrow_mut();
+        let compressor = match cell.as_mut() {
+            Some(compressor) => compressor,
+            None => cell.insert(Compressor::with_dictionary(LEVEL, DICTIONARY_V1)?),
+        };
+        let frame = compressor.compress(json.as_bytes())?;
+        if frame.len() + 1 >= json.len() {
+            return Ok(None);
+        }
+        let mut blob = Vec::with_capacity(frame.len() + 1);
+        blob.push(VERSION_1);
+        blob.extend(frame);
+        Ok(Some(blob))
+    })
+}
+
+/// The JSON text of a blob written by [`compress`].
+pub fn decompress(blob: &[u8]) -> Result<String> {
+    let (&version, frame) = blob
+        .split_first()
+        .ok_or_else(|| anyhow!("Empty details blob"))?;
+    if version != VERSION_1 {
+        anyhow::bail!("Details compressed with unknown dictionary version {version}");
+    }
+    let size = zstd::zstd_safe::get_frame_content_size(frame)
+        .ok()
+        .flatten()
+        .ok_or_else(|| anyhow!("Details blob is not a zstd frame"))?;
+    DECOMPRESSOR.with(|cell| {
+        let mut cell = cell.borrow_mut();
+        let decompressor = match cell.as_mut() {
+            Some(decompressor) => decompressor,
+            None => cell.insert(Decompressor::with_dictionary(DICTIONARY_V1)?),
+        };
+        let text = decompressor.decompress(frame, size as usize)?;
+        Ok(String::from_utf8(text)?)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn details_roundtrip_and_shrink() {
+        let memory = r#"{"total_bytes":17179869184.0,"available_bytes":9999949958.66472,"used_bytes":7179919225.335279}"#;
+        let sensor = r#"{"sensor":"coretemp/temp1"}"#;
+        for json in [memory, sensor, r#"{"rx_bytes":1.0,"tx_bytes":2.0}"#] {
+            let blob = compress(json).unwrap().expect("smaller than the text");
+            assert!(blob.len() < json.len(), "{json}: {} bytes", blob.len());
+            assert_eq!(decompress(&blob).unwrap(), json);
+        }
+        // Nothing to gain on two bytes.
+        assert_eq!(compress("{}").unwrap(), None);
+
+        let mut unknown = compress(memory).unwrap().unwrap();
+        unknown[0] = 9;
+        assert!(decompress(&unknown).is_err());
+        assert!(decompress(&[]).is_err());
+    }
+}
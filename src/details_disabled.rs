@@ -0,0 +1,15 @@
+//! Stand-in for `details` in builds without the `details-compression` feature: new rows
+//! keep plain JSON details, and reading rows a full build compressed fails with a hint.
+
+use anyhow::{bail, Result};
+
+pub const DISABLED: &str = "symmetri was built without details compression; rebuild with \
+                            `--features details-compression`";
+
+pub fn compress(_json: &str) -> Result<Option<Vec<u8>>> {
+    bail!(DISABLED)
+}
+
+pub fn decompress(_blob: &[u8]) -> Result<String> {
+    bail!(DISABLED)
+}
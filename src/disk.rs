@@ -0,0 +1,223 @@
+use std::collections::BTreeSet;
+use std::ffi::CString;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use serde_json::json;
+
+use crate::collector::Collector;
+use crate::config::DisksConfig;
+use crate::metrics::{MetricKind, MetricSample};
+
+/// Config name of the collector recording filesystem usage per mountpoint.
+pub const DISK_COLLECTOR: &str = "disk";
+/// `[disks] mounts` entry standing for every mounted real filesystem.
+pub const ALL_MOUNTS: &str = "*";
+
+/// Kernel and in-memory filesystems whose usage says nothing about the disks.
+const PSEUDO_FILESYSTEMS: &[&str] = &[
+    "autofs",
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "devtmpfs",
+    "efivarfs",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "nsfs",
+    "overlay",
+    "proc",
+    "pstore",
+    "ramfs",
+    "rpc_pipefs",
+    "securityfs",
+    "squashfs",
+    "sysfs",
+    "tmpfs",
+    "tracefs",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    pub device: String,
+    pub mountpoint: String,
+    pub fstype: String,
+}
+
+impl Mount {
+    pub fn is_pseudo(&self) -> bool {
+        PSEUDO_FILESYSTEMS.contains(&self.fstype.as_str()) || self.fstype.starts_with("fuse.")
+    }
+}
+
+/// `/proc/mounts` escapes spaces and tabs in paths as octal (`\040`).
+fn unescape_mount_field(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(index) = rest.find('\\') {
+        out.push_str(&rest[..index]);
+        let code = rest.get(index + 1..index + 4);
+        match code.and_then(|code| u8::from_str_radix(code, 8).ok()) {
+            Some(byte) => {
+                out.push(byte as char);
+                rest = &rest[index + 4..];
+            }
+            None => {
+                out.push('\\');
+                rest = &rest[index + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn parse_mounts(content: &str) -> Vec<Mount> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            Some(Mount {
+                device: unescape_mount_field(fields.next()?),
+                mountpoint: unescape_mount_field(fields.next()?),
+                fstype: fields.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Mounts to record for `[disks] mounts`: listed paths that are mounted on a real
+/// filesystem, in config order, or every real filesystem for `"*"`. A device mounted
+/// twice (bind mounts, btrfs subvolumes) is only recorded at its first mountpoint.
+pub fn select_mounts(mounts: &[Mount], wanted: &[String]) -> Vec<Mount> {
+    // Later entries in the mount table shadow earlier ones on the same path.
+    let mounted = |path: &str| mounts.iter().rev().find(|m| m.mountpoint == path);
+    let candidates: Vec<&Mount> = if wanted.iter().any(|path| path == ALL_MOUNTS) {
+        mounts.iter().filter(|m| !m.is_pseudo()).collect()
+    } else {
+        wanted
+            .iter()
+            .filter_map(|path| mounted(normalize_mountpoint(path)))
+            .filter(|m| !m.is_pseudo())
+            .collect()
+    };
+    let mut devices = BTreeSet::new();
+    let mut paths = BTreeSet::new();
+    candidates
+        .into_iter()
+        .filter(|m| paths.insert(m.mountpoint.clone()) && devices.insert(m.device.clone()))
+        .cloned()
+        .collect()
+}
+
+/// Drops trailing slashes so `/home/` and `/home` name the same source.
+pub fn normalize_mountpoint(path: &str) -> &str {
+    match path.trim_end_matches('/') {
+        "" => "/",
+        trimmed => trimmed,
+    }
+}
+
+fn disk_sample(ts: f64, mount: &Mount) -> Option<MetricSample> {
+    let c_path = CString::new(Path::new(&mount.mountpoint).as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    let rc = unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) };
+    if rc != 0 {
+        return None;
+    }
+    // c_ulong and fsblkcnt_t are 32 bits on some targets (32-bit musl, armv7 routers).
+    #[allow(clippy::unnecessary_cast)]
+    let (block_size, blocks, bfree, bavail) = (
+        stat.f_frsize as u64,
+        stat.f_blocks as u64,
+        stat.f_bfree as u64,
+        stat.f_bavail as u64,
+    );
+    let total = block_size * blocks;
+    if total == 0 {
+        return None;
+    }
+    let free = block_size * bfree;
+    let available = block_size * bavail;
+    let used = total.saturating_sub(free);
+    Some(MetricSample::new(
+        ts,
+        MetricKind::DiskUsage,
+        mount.mountpoint.clone(),
+        Some(used as f64),
+        Some("bytes"),
+        json!({
+            "total_bytes": total as f64,
+            "available_bytes": available as f64,
+            "free_bytes": free as f64,
+            "device": mount.device,
+            "fstype": mount.fstype,
+        }),
+    ))
+}
+
+pub struct DiskCollector {
+    config: DisksConfig,
+}
+
+impl DiskCollector {
+    pub fn new(config: DisksConfig) -> Self {
+        DiskCollector { config }
+    }
+}
+
+impl Collector for DiskCollector {
+    fn name(&self) -> &str {
+        DISK_COLLECTOR
+    }
+
+    fn kinds(&self) -> &[MetricKind] {
+        &[MetricKind::DiskUsage]
+    }
+
+    fn collect(&self, ts: f64) -> Vec<MetricSample> {
+        let Ok(content) = fs::read_to_string("/proc/self/mounts") else {
+            return Vec::new();
+        };
+        select_mounts(&parse_mounts(&content), &self.config.mounts)
+            .iter()
+            .filter_map(|mount| disk_sample(ts, mount))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOUNTS: &str = "\
+proc /proc proc rw,nosuid,nodev,noexec,relatime 0 0
+/dev/nvme0n1p2 / ext4 rw,relatime 0 0
+tmpfs /tmp tmpfs rw,nosuid,nodev 0 0
+/dev/nvme0n1p3 /home btrfs rw,relatime,subvol=/home 0 0
+/dev/nvme0n1p3 /home/user/.snapshots btrfs rw,relatime 0 0
+/dev/sda1 /media/USB\\040Stick vfat rw 0 0
+";
+
+    #[test]
+    fn configured_mounts_skip_pseudo_and_duplicate_filesystems() {
+        let mounts = parse_mounts(MOUNTS);
+        assert_eq!(mounts[5].mountpoint, "/media/USB Stick");
+
+        let wanted = |paths: &[&str]| -> Vec<String> {
+            let wanted: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+            select_mounts(&mounts, &wanted)
+                .into_iter()
+                .map(|m| m.mountpoint)
+                .collect()
+        };
+        assert_eq!(wanted(&["/", "/tmp", "/nix", "/home/"]), vec!["/", "/home"]);
+        assert_eq!(wanted(&["*"]), vec!["/", "/home", "/media/USB Stick"]);
+    }
+}
@@ -0,0 +1,226 @@
+//! Whether the built-in screen is on, from the backlight (`/sys/class/backlight`) and the
+//! DPMS state of the internal panel's DRM connector. Reports turn the samples into
+//! screen-on hours per day and the drain with the screen on and off.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use chrono::NaiveDate;
+use serde_json::{json, Map, Value};
+
+use crate::locale::Formats;
+use crate::metrics::{MetricKind, MetricSample};
+
+pub const SCREEN_SOURCE: &str = "screen";
+
+/// Readings further apart than this mean the machine was off or asleep in between, so
+/// the time is not counted as screen-on.
+const MAX_GAP_SECONDS: f64 = 15.0 * 60.0;
+
+/// Connector name parts of built-in panels; external monitors do not drain the battery.
+const INTERNAL_CONNECTORS: [&str; 3] = ["-eDP-", "-LVDS-", "-DSI-"];
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|raw| raw.trim().to_string())
+        .filter(|raw| !raw.is_empty())
+}
+
+/// The backlight userspace should use, preferring `firmware` over `platform` over `raw`
+/// devices as the kernel documents; ties go to the first name.
+fn preferred_backlight(backlight_root: &Path) -> Option<String> {
+    let entries = fs::read_dir(backlight_root).ok()?;
+    let mut devices: Vec<(usize, String)> = entries
+        .flatten()
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let rank = match read_trimmed(&entry.path().join("type")).as_deref() {
+                Some("firmware") => 0,
+                Some("platform") => 1,
+                _ => 2,
+            };
+            (rank, name)
+        })
+        .collect();
+    devices.sort();
+    devices.into_iter().next().map(|(_, name)| name)
+}
+
+/// DPMS state (`On`, `Off`, ...) of each connected built-in panel.
+fn internal_panel_dpms(drm_root: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(drm_root) else {
+        return Vec::new();
+    };
+    let mut states: Vec<(String, String)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !INTERNAL_CONNECTORS.iter().any(|part| name.contains(part)) {
+                return None;
+            }
+            if read_trimmed(&entry.path().join("status")).as_deref() != Some("connected") {
+                return None;
+            }
+            Some((name, read_trimmed(&entry.path().join("dpms"))?))
+        })
+        .collect();
+    states.sort();
+    states.into_iter().map(|(_, dpms)| dpms).collect()
+}
+
+/// One `screen` sample, 1 while the built-in screen is lit and 0 while it is blanked
+/// (`bl_power`), dimmed to 0 or put to sleep by DPMS. The backlight brightness (% of its
+/// maximum) and the DPMS state go in details. Machines with neither a backlight nor a
+/// built-in panel (desktops, VMs) get no sample.
+pub fn display_samples_from(backlight_root: &Path, drm_root: &Path, ts: f64) -> Vec<MetricSample> {
+    let backlight = preferred_backlight(backlight_root);
+    let dpms = internal_panel_dpms(drm_root);
+    if backlight.is_none() && dpms.is_empty() {
+        return Vec::new();
+    }
+    let mut details = Map::new();
+    let mut lit = true;
+    if let Some(name) = &backlight {
+        let dir = backlight_root.join(name);
+        let read = |file: &str| read_trimmed(&dir.join(file)).and_then(|raw| raw.parse().ok());
+        let brightness: Option<f64> = read("actual_brightness").or_else(|| read("brightness"));
+        let max: Option<f64> = read("max_brightness");
+        // FB_BLANK_UNBLANK is 0; anything else powers the backlight down.
+        let blanked = read("bl_power").is_some_and(|power: f64| power != 0.0);
+        lit = !blanked && brightness.is_none_or(|value| value > 0.0);
+        details.insert("backlight".into(), json!(name));
+        if let (Some(brightness), Some(max)) = (brightness, max.filter(|max| *max > 0.0)) {
+            details.insert("brightness_pct".into(), json!(brightness / max * 100.0));
+        }
+    }
+    if !dpms.is_empty() {
+        lit &= dpms.iter().any(|state| state == "On");
+        details.insert("dpms".into(), json!(dpms.join(",")));
+    }
+    vec![MetricSample::new(
+        ts,
+        MetricKind::Display,
+        SCREEN_SOURCE,
+        Some(if lit { 1.0 } else { 0.0 }),
+        None,
+        Value::Object(details),
+    )]
+}
+
+pub fn display_samples(ts: f64) -> Vec<MetricSample> {
+    display_samples_from(
+        Path::new("/sys/class/backlight"),
+        Path::new("/sys/class/drm"),
+        ts,
+    )
+}
+
+/// Screen state after each `screen` sample, oldest first.
+pub fn screen_timeline(metrics: &[MetricSample]) -> Vec<(f64, bool)> {
+    let mut timeline: Vec<(f64, bool)> = metrics
+        .iter()
+        .filter(|m| m.kind == MetricKind::Display && m.source == SCREEN_SOURCE)
+        .filter_map(|m| m.value.map(|value| (m.ts, value > 0.0)))
+        .collect();
+    timeline.sort_by(|a, b| a.0.total_cmp(&b.0));
+    timeline
+}
+
+/// Whether the screen was on at `ts`, per the latest sample at or before it.
+pub fn screen_on_at(timeline: &[(f64, bool)], ts: f64) -> Option<bool> {
+    let idx = timeline.partition_point(|(sample_ts, _)| *sample_ts <= ts);
+    idx.checked_sub(1).map(|i| timeline[i].1)
+}
+
+/// Screen-on hours per calendar day in `formats.zone`. Each interval between two samples
+/// counts for the day it starts on.
+pub fn screen_on_hours_by_day(
+    timeline: &[(f64, bool)],
+    formats: &Formats,
+) -> BTreeMap<NaiveDate, f64> {
+    let mut days = BTreeMap::new();
+    for pair in timeline.windows(2) {
+        let [(ts, on), (next_ts, _)] = pair else {
+            continue;
+        };
+        let Some(day) = formats.datetime(*ts).map(|dt| dt.date_naive()) else {
+            continue;
+        };
+        let hours = days.entry(day).or_insert(0.0);
+        let gap = next_ts - ts;
+        if *on && gap > 0.0 && gap <= MAX_GAP_SECONDS {
+            *hours += gap / 3600.0;
+        }
+    }
+    days
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zone::Zone;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn backlight_and_dpms_give_screen_state() {
+        let tmp = tempfile::tempdir().unwrap();
+        let backlight = tmp.path().join("backlight");
+        write(&backlight.join("acpi_video0/type"), "firmware\n");
+        write(&backlight.join("acpi_video0/max_brightness"), "100\n");
+        write(&backlight.join("acpi_video0/actual_brightness"), "40\n");
+        write(&backlight.join("acpi_video0/bl_power"), "0\n");
+        write(&backlight.join("intel_backlight/type"), "raw\n");
+        let drm = tmp.path().join("drm");
+        write(&drm.join("card1-eDP-1/status"), "connected\n");
+        write(&drm.join("card1-eDP-1/dpms"), "On\n");
+        write(&drm.join("card1-HDMI-A-1/status"), "connected\n");
+        write(&drm.join("card1-HDMI-A-1/dpms"), "Off\n");
+
+        let on = display_samples_from(&backlight, &drm, 1.0);
+        assert_eq!(on[0].value, Some(1.0));
+        assert_eq!(on[0].details["backlight"], json!("acpi_video0"));
+        assert_eq!(on[0].details["brightness_pct"], json!(40.0));
+        assert_eq!(on[0].details["dpms"], json!("On"));
+
+        write(&drm.join("card1-eDP-1/dpms"), "Off\n");
+        assert_eq!(
+            display_samples_from(&backlight, &drm, 2.0)[0].value,
+            Some(0.0)
+        );
+        write(&drm.join("card1-eDP-1/dpms"), "On\n");
+        write(&backlight.join("acpi_video0/bl_power"), "4\n");
+        assert_eq!(
+            display_samples_from(&backlight, &drm, 3.0)[0].value,
+            Some(0.0)
+        );
+
+        let missing = tmp.path().join("missing");
+        assert!(display_samples_from(&missing, &missing, 4.0).is_empty());
+
+        // On for 20 minutes in 10-minute steps, off for 10, then a night without samples.
+        let formats = Formats {
+            zone: Zone::Utc,
+            ..Formats::default()
+        };
+        let timeline = vec![
+            (0.0, true),
+            (600.0, true),
+            (1200.0, false),
+            (1800.0, true),
+            (86_400.0, true),
+            (87_000.0, false),
+        ];
+        assert_eq!(screen_on_at(&timeline, 1500.0), Some(false));
+        assert_eq!(screen_on_at(&timeline, -1.0), None);
+        let days: Vec<f64> = screen_on_hours_by_day(&timeline, &formats)
+            .into_values()
+            .collect();
+        assert_eq!(days, vec![1200.0 / 3600.0, 600.0 / 3600.0]);
+    }
+}
@@ -0,0 +1,550 @@
+//! `symmetri doctor`: why there is no data. Checks the battery entries and their known
+//! firmware quirks, the temperature sensors, the database and its permissions, the clock
+//! against the newest sample and the systemd units, each as a pass/warn/fail line with a
+//! hint on what to do about it.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::config::{load_config, Config, QuirkOverrides, UnitScale};
+use crate::db;
+use crate::env::Origin;
+use crate::normalize::{normalize, Quirks};
+use crate::sensors::{hwmon_temperature_samples_from, thermal_zone_samples_from};
+use crate::sysfs::{find_battery_paths, read_raw_battery, BatteryReading};
+
+/// Before this (2020-01-01) the clock has not been set.
+const MIN_PLAUSIBLE_TS: f64 = 1_577_836_800.0;
+/// Samples this far ahead of the clock mean it was set back since they were written.
+const FUTURE_TOLERANCE_SECONDS: f64 = 5.0 * 60.0;
+/// The timer collects every 5 minutes; an hour without a sample means it stopped.
+const STALE_AFTER_SECONDS: f64 = 3600.0;
+/// Points the `capacity` file may differ from the energy ratio before it is a quirk.
+const CAPACITY_TOLERANCE_PCT: f64 = 5.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Level::Pass => "pass",
+            Level::Warn => "warn",
+            Level::Fail => "fail",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub level: Level,
+    /// What was checked: `battery BAT0`, `database`, `clock`, ...
+    pub check: String,
+    pub message: String,
+    /// What to do about a warning or failure.
+    pub hint: Option<String>,
+}
+
+impl Finding {
+    fn new(level: Level, check: impl Into<String>, message: impl Into<String>) -> Self {
+        Finding {
+            level,
+            check: check.into(),
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}  {}: {}", self.level, self.check, self.message)?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n      {hint}")?;
+        }
+        Ok(())
+    }
+}
+
+/// `seconds` rounded to minutes, hours or days.
+fn age(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).round() as i64;
+    match minutes {
+        ..120 => format!("{minutes} min"),
+        120..2880 => format!("{} h", minutes / 60),
+        _ => format!("{} days", minutes / 1440),
+    }
+}
+
+/// One finding per `BAT*` entry under `power_supply_root`, plus its firmware quirks:
+/// milli-unit drivers, a full capacity above design, no design capacity, a `capacity`
+/// file that disagrees with the energy ratio and a `power_now` stuck at 0.
+pub fn battery_findings(power_supply_root: &Path, config: &Config) -> Vec<Finding> {
+    let paths = find_battery_paths(power_supply_root);
+    if paths.is_empty() {
+        let (level, message) = if fs::read_dir(power_supply_root).is_ok() {
+            (
+                Level::Warn,
+                format!("no BAT* battery in {}", power_supply_root.display()),
+            )
+        } else {
+            (
+                Level::Fail,
+                format!("cannot read {}", power_supply_root.display()),
+            )
+        };
+        return vec![Finding::new(level, "battery", message).hint(
+            "Desktops and VMs have no battery, so only the other metrics are recorded. On a \
+             laptop, check that the ACPI battery driver loaded (`dmesg | grep -i battery`).",
+        )];
+    }
+    let mut findings = Vec::new();
+    for path in paths {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let check = format!("battery {name}");
+        let raw = read_raw_battery(&path);
+        let overrides = config.quirks_for(&path);
+        let quirks = Quirks::detect(&raw, &overrides);
+        let reading = normalize(&path, &raw, &quirks);
+        if reading.energy_now_wh.is_none() && reading.energy_full_wh.is_none() {
+            let mut finding = Finding::new(
+                Level::Fail,
+                &check,
+                format!(
+                    "{} has no readable energy_* or charge_* attributes",
+                    path.display()
+                ),
+            );
+            finding = if raw.capacity.is_some() {
+                finding.hint(
+                    "Only a percentage is exposed: charge levels are recorded, energy, \
+                     power and health are not.",
+                )
+            } else {
+                finding.hint(
+                    "Check the file permissions, or whether the battery is seated; \
+                     `cat /sys/class/power_supply/BAT*/uevent` shows what the driver reports.",
+                )
+            };
+            findings.push(finding);
+            continue;
+        }
+        findings.push(Finding::new(
+            Level::Pass,
+            &check,
+            format!(
+                "{} ({}, {})",
+                reading
+                    .percentage
+                    .map_or("charge unknown".to_string(), |pct| format!("{pct:.0}%")),
+                reading.status.as_deref().unwrap_or("status unknown"),
+                if raw.energy_full.is_some() {
+                    "energy attributes"
+                } else {
+                    "charge attributes"
+                },
+            ),
+        ));
+        let milli = guessed_milli(&quirks, &overrides);
+        findings.extend(quirk_findings(
+            &name,
+            &check,
+            &milli,
+            &reading,
+            raw.power_now,
+        ));
+    }
+    findings
+}
+
+/// Attribute families guessed (not configured) to be in milli-units.
+fn guessed_milli(quirks: &Quirks, overrides: &QuirkOverrides) -> Vec<&'static str> {
+    [
+        ("energy", quirks.energy, overrides.energy),
+        ("charge", quirks.charge, overrides.charge),
+        ("voltage", quirks.voltage, overrides.voltage),
+        ("power", quirks.power, overrides.power),
+    ]
+    .into_iter()
+    .filter(|(_, scale, configured)| *scale == UnitScale::Milli && configured.is_none())
+    .map(|(family, _, _)| family)
+    .collect()
+}
+
+fn quirk_findings(
+    name: &str,
+    check: &str,
+    milli: &[&str],
+    reading: &BatteryReading,
+    power_now: Option<f64>,
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if !milli.is_empty() {
+        findings.push(
+            Finding::new(
+                Level::Warn,
+                check,
+                format!(
+                    "driver reports {} in milli-units instead of micro-units; converted automatically",
+                    milli.join(", ")
+                ),
+            )
+            .hint(format!(
+                "If readings look 1000× off, pin the scale in the config: [quirks.{name}] {} = \"milli\" (or \"micro\").",
+                milli[0]
+            )),
+        );
+    }
+    match (reading.energy_full_wh, reading.energy_full_design_wh) {
+        (_, None) | (_, Some(0.0)) => findings.push(
+            Finding::new(Level::Warn, check, "no design capacity reported")
+                .hint("Health and wear need it; some firmware only fills it in after a full charge."),
+        ),
+        (Some(full), Some(design)) if full > design * 1.05 => findings.push(
+            Finding::new(
+                Level::Warn,
+                check,
+                format!(
+                    "full capacity {full:.1} Wh is above the design {design:.1} Wh (health {:.0}%)",
+                    full / design * 100.0
+                ),
+            )
+            .hint("Common after a battery swap or firmware update; it settles after a full discharge and charge."),
+        ),
+        _ => {}
+    }
+    if let (Some(file), Some(ratio)) = (reading.capacity_pct, reading.percentage) {
+        if (file - ratio).abs() > CAPACITY_TOLERANCE_PCT {
+            findings.push(
+                Finding::new(
+                    Level::Warn,
+                    check,
+                    format!(
+                        "the capacity file says {file:.0}% but energy_now/energy_full gives {ratio:.0}%"
+                    ),
+                )
+                .hint("The firmware scales its percentage (e.g. to stop at a charge threshold); reports use the energy ratio."),
+            );
+        }
+    }
+    if reading.status.as_deref() == Some("Discharging") && power_now == Some(0.0) {
+        findings.push(
+            Finding::new(Level::Warn, check, "power_now reads 0 while discharging")
+                .hint("Some firmware only updates it every few minutes; if it stays at 0, discharge rates come from the energy readings alone."),
+        );
+    }
+    findings
+}
+
+/// Whether hwmon chips or thermal zones expose temperatures.
+pub fn sensor_findings(hwmon_root: &Path, thermal_root: &Path) -> Finding {
+    let hwmon = hwmon_temperature_samples_from(hwmon_root, 0.0, &BTreeSet::new()).len();
+    let zones = thermal_zone_samples_from(thermal_root, 0.0).len();
+    if hwmon + zones == 0 {
+        return Finding::new(Level::Warn, "sensors", "no hwmon or thermal zone temperatures")
+            .hint("Load the chip's driver (`sensors-detect` from lm-sensors finds it); VMs usually have none.");
+    }
+    Finding::new(
+        Level::Pass,
+        "sensors",
+        format!("{hwmon} hwmon temperature(s), {zones} thermal zone(s)"),
+    )
+}
+
+/// The database: present, readable, and writable unless it is the system-wide
+/// collector's.
+pub fn database_findings(db_path: &Path, origin: Origin) -> Vec<Finding> {
+    let shown = db_path.display();
+    if !db_path.exists() {
+        let parent_writable = db_path
+            .ancestors()
+            .skip(1)
+            .find(|dir| dir.exists())
+            .is_some_and(crate::collector::writable);
+        let finding = Finding::new(Level::Fail, "database", format!("{shown} does not exist"));
+        return vec![if parent_writable {
+            finding.hint("Run `symmetri init`, or `symmetri collect` once, to create it.")
+        } else {
+            finding.hint(format!(
+                "It cannot be created either: the nearest existing directory of {shown} is not writable. Pick another path with --db or SYMMETRI_DB."
+            ))
+        }];
+    }
+    if let Err(err) = fs::File::open(db_path) {
+        let finding = Finding::new(
+            Level::Fail,
+            "database",
+            format!("cannot read {shown}: {err}"),
+        );
+        return vec![if origin == Origin::System {
+            finding.hint(
+                "Join the symmetri group (`sudo usermod -aG symmetri $USER`) and log in again.",
+            )
+        } else {
+            finding.hint(format!("Fix the owner or mode of {shown}."))
+        }];
+    }
+    if origin == Origin::System {
+        return vec![Finding::new(
+            Level::Pass,
+            "database",
+            format!("{shown} (system-wide collector, read-only here)"),
+        )];
+    }
+    if !crate::collector::writable(db_path) {
+        return vec![
+            Finding::new(Level::Fail, "database", format!("{shown} is not writable")).hint(
+                format!("The collector cannot store samples; fix the owner or mode of {shown}."),
+            ),
+        ];
+    }
+    vec![Finding::new(
+        Level::Pass,
+        "database",
+        format!("{shown} ({origin})"),
+    )]
+}
+
+/// The clock against itself and against the newest stored sample.
+pub fn clock_findings(now: f64, latest_ts: Option<f64>) -> Vec<Finding> {
+    if now < MIN_PLAUSIBLE_TS {
+        return vec![Finding::new(Level::Fail, "clock", "the system clock is before 2020")
+            .hint("Enable time sync (`timedatectl set-ntp true`); samples are stored with the wrong dates.")];
+    }
+    let Some(latest) = latest_ts else {
+        return vec![Finding::new(Level::Warn, "clock", "no samples stored yet")
+            .hint("Run `symmetri collect` once, and enable the timer (see the systemd check).")];
+    };
+    if latest > now + FUTURE_TOLERANCE_SECONDS {
+        return vec![Finding::new(
+            Level::Fail,
+            "clock",
+            format!("the newest sample is {} in the future", age(latest - now)),
+        )
+        .hint("The clock was set back since it was written; reports and rates around it are off until time catches up. Keep time sync enabled.")];
+    }
+    if now - latest > STALE_AFTER_SECONDS {
+        return vec![Finding::new(
+            Level::Warn,
+            "clock",
+            format!("the newest sample is {} old", age(now - latest)),
+        )
+        .hint("Nothing is collecting; see the systemd check, or run `symmetri collect --interval 300`.")];
+    }
+    vec![Finding::new(
+        Level::Pass,
+        "clock",
+        format!("the newest sample is {} old", age((now - latest).max(0.0))),
+    )]
+}
+
+/// Whether the config file parses; a missing one is fine.
+pub fn config_finding(config_path: &Path) -> (Finding, Config) {
+    match load_config(Some(config_path)) {
+        Ok(config) if config_path.exists() => (
+            Finding::new(Level::Pass, "config", config_path.display().to_string()),
+            config,
+        ),
+        Ok(config) => (
+            Finding::new(
+                Level::Pass,
+                "config",
+                format!("{} (missing, defaults apply)", config_path.display()),
+            ),
+            config,
+        ),
+        Err(err) => (
+            Finding::new(Level::Fail, "config", format!("{err:#}")).hint(
+                "Every command fails until it is fixed; the README lists the sections and keys.",
+            ),
+            Config::default(),
+        ),
+    }
+}
+
+/// Every check, in the order they are printed.
+pub fn run(db_path: &Path, db_origin: Origin, config_path: &Path, now: f64) -> Vec<Finding> {
+    let (config_finding, config) = config_finding(config_path);
+    let mut findings = vec![config_finding];
+    if cfg!(any(target_os = "linux", target_os = "android")) {
+        findings.extend(battery_findings(
+            Path::new("/sys/class/power_supply"),
+            &config,
+        ));
+        findings.push(sensor_findings(
+            Path::new("/sys/class/hwmon"),
+            Path::new("/sys/class/thermal"),
+        ));
+    }
+    let database = database_findings(db_path, db_origin);
+    let usable = database.iter().all(|finding| finding.level == Level::Pass);
+    findings.extend(database);
+    if usable {
+        match db::latest_ts(db_path) {
+            Ok(latest_ts) => findings.extend(clock_findings(now, latest_ts)),
+            Err(err) => findings.push(
+                Finding::new(Level::Fail, "database", format!("cannot query it: {err:#}"))
+                    .hint("`symmetri db stats` shows whether the file is a symmetri database."),
+            ),
+        }
+    }
+    findings.extend(systemd_findings());
+    findings
+}
+
+/// `systemctl [--user] <verb> <unit>` output, `None` without systemctl.
+fn systemctl(user: bool, verb: &str, unit: &str) -> Option<String> {
+    let mut command = Command::new("systemctl");
+    if user {
+        command.arg("--user");
+    }
+    let output = command
+        .args([verb, unit])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Whether a user or system `symmetri.timer` is active and its service has not failed.
+pub fn systemd_findings() -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut active = false;
+    for (user, scope) in [(false, "system"), (true, "user")] {
+        let Some(state) = systemctl(user, "is-active", "symmetri.timer") else {
+            return vec![Finding::new(Level::Warn, "systemd", "systemctl not found").hint(
+                "Without systemd, run `symmetri collect --interval 300` from your session or a cron job.",
+            )];
+        };
+        if state == "active" {
+            active = true;
+            findings.push(Finding::new(
+                Level::Pass,
+                "systemd",
+                format!("{scope} symmetri.timer is active"),
+            ));
+        }
+        if systemctl(user, "is-failed", "symmetri.service").as_deref() == Some("failed") {
+            let journal = if user {
+                "journalctl --user"
+            } else {
+                "journalctl"
+            };
+            findings.push(
+                Finding::new(
+                    Level::Fail,
+                    "systemd",
+                    format!("{scope} symmetri.service failed on its last run"),
+                )
+                .hint(format!(
+                    "See why with `{journal} -u symmetri.service -n 50`."
+                )),
+            );
+        }
+    }
+    if !active {
+        findings.push(
+            Finding::new(Level::Warn, "systemd", "no symmetri.timer is active").hint(
+                "`symmetri init --systemd` installs and enables the user timers; the README has the system-wide units.",
+            ),
+        );
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    const NOW: f64 = 1_700_000_000.0;
+
+    #[test]
+    fn an_unreadable_power_supply_directory_fails() {
+        let tmp = tempfile::tempdir().unwrap();
+        let missing = battery_findings(&tmp.path().join("power_supply"), &Config::default());
+        assert_eq!(missing[0].level, Level::Fail);
+        assert!(missing[0].message.starts_with("cannot read"));
+    }
+
+    #[test]
+    fn battery_quirks_are_warned_about() {
+        let tmp = tempfile::tempdir().unwrap();
+        let supply = tmp.path().join("power_supply");
+        // A milli-unit driver whose full capacity is above design.
+        write(&supply.join("BAT0/type"), "Battery\n");
+        write(
+            &supply.join("BAT0/uevent"),
+            "POWER_SUPPLY_STATUS=Discharging\nPOWER_SUPPLY_ENERGY_NOW=30000\n\
+             POWER_SUPPLY_ENERGY_FULL=55000\nPOWER_SUPPLY_ENERGY_FULL_DESIGN=50000\n\
+             POWER_SUPPLY_POWER_NOW=0\nPOWER_SUPPLY_CAPACITY=55\n",
+        );
+        let findings = battery_findings(&supply, &Config::default());
+        let levels: Vec<Level> = findings.iter().map(|f| f.level).collect();
+        assert_eq!(levels, [Level::Pass, Level::Warn, Level::Warn, Level::Warn]);
+        assert!(findings[0]
+            .to_string()
+            .starts_with("pass  battery BAT0: 55% (Discharging"));
+        assert!(findings[1].message.contains("energy, power in milli-units"));
+        assert!(findings[2].message.contains("above the design"));
+        assert!(findings[3].message.contains("power_now reads 0"));
+    }
+
+    #[test]
+    fn database_permissions_are_checked() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = tmp.path().join("db.sqlite");
+        assert_eq!(
+            database_findings(&db, Origin::Default)[0].level,
+            Level::Fail
+        );
+        fs::write(&db, "").unwrap();
+        assert_eq!(
+            database_findings(&db, Origin::Default)[0].level,
+            Level::Pass
+        );
+    }
+
+    #[test]
+    fn an_unset_clock_fails() {
+        assert_eq!(clock_findings(1_000.0, None)[0].level, Level::Fail);
+    }
+
+    #[test]
+    fn recent_samples_pass_and_old_ones_warn() {
+        assert_eq!(clock_findings(NOW, Some(NOW - 120.0))[0].level, Level::Pass);
+        let stale = &clock_findings(NOW, Some(NOW - 3.0 * 3600.0))[0];
+        assert_eq!(
+            (stale.level, stale.message.as_str()),
+            (Level::Warn, "the newest sample is 3 h old")
+        );
+    }
+
+    #[test]
+    fn samples_from_the_future_fail() {
+        assert_eq!(
+            clock_findings(NOW, Some(NOW + 7200.0))[0].level,
+            Level::Fail
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! `report --email`: the HTML report sent over SMTP (`[email]`, needs the `email` build
+//! feature), with the graph attached inline so mail clients that block `data:` images
+//! still show it.
+
+#[cfg(not(feature = "email"))]
+use anyhow::bail;
+use anyhow::{Context, Result};
+
+use crate::config::{EmailConfig, SmtpSecurity};
+use crate::env;
+
+/// Content id the HTML refers to the inline graph by (`cid:graph`).
+pub const GRAPH_CID: &str = "graph";
+
+/// `[email]` with `SYMMETRI_SMTP_*` applied, ready to connect.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmtpSettings {
+    pub server: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub credentials: Option<(String, String)>,
+    pub from: String,
+    pub timeout_seconds: f64,
+}
+
+impl SmtpSettings {
+    /// Fails when neither the config nor the environment names a server or sender.
+    pub fn resolve(config: &EmailConfig) -> Result<Self> {
+        let var = |var: env::EnvVar| var.get().map(|(value, _)| value);
+        let server = var(env::SMTP_SERVER)
+            .or_else(|| config.server.clone())
+            .with_context(|| {
+                format!(
+                    "No SMTP server: set [email] server or {}",
+                    env::SMTP_SERVER.name
+                )
+            })?;
+        let username = var(env::SMTP_USER).or_else(|| config.username.clone());
+        let password = var(env::SMTP_PASSWORD).or_else(|| config.password.clone());
+        let credentials = match (username, password) {
+            (Some(username), Some(password)) => Some((username, password)),
+            (Some(username), None) => anyhow::bail!(
+                "SMTP user {username} has no password: set {} or [email] password",
+                env::SMTP_PASSWORD.name
+            ),
+            _ => None,
+        };
+        let from = config
+            .from
+            .clone()
+            .or_else(|| credentials.as_ref().map(|(username, _)| username.clone()))
+            .context("No sender address: set [email] from")?;
+        Ok(SmtpSettings {
+            port: config.port.unwrap_or(match config.security {
+                SmtpSecurity::Starttls => 587,
+                SmtpSecurity::Tls => 465,
+                SmtpSecurity::None => 25,
+            }),
+            server,
+            security: config.security,
+            credentials,
+            from,
+            timeout_seconds: config.timeout_seconds,
+        })
+    }
+}
+
+/// Sends `html` to `recipients`, with `graph_png` as the inline [`GRAPH_CID`] attachment.
+#[cfg(feature = "email")]
+pub fn send_report(
+    settings: &SmtpSettings,
+    recipients: &[String],
+    subject: &str,
+    html: &str,
+    graph_png: Option<&[u8]>,
+) -> Result<()> {
+    use std::time::Duration;
+
+    use lettre::message::header::ContentType;
+    use lettre::message::{Attachment, MultiPart, SinglePart};
+    use lettre::transport::smtp::authentication::Credentials;
+    use lettre::{Message, SmtpTransport, Transport};
+
+    let mut builder = Message::builder()
+        .from(
+            settings
+                .from
+                .parse()
+                .with_context(|| format!("Invalid sender address {}", settings.from))?,
+        )
+        .subject(subject);
+    for recipient in recipients {
+        builder = builder.to(recipient
+            .parse()
+            .with_context(|| format!("Invalid recipient address {recipient}"))?);
+    }
+    let html = SinglePart::html(html.to_string());
+    let message = match graph_png {
+        Some(png) => builder.multipart(
+            MultiPart::related().singlepart(html).singlepart(
+                Attachment::new_inline(GRAPH_CID.to_string())
+                    .body(png.to_vec(), ContentType::parse("image/png")?),
+            ),
+        ),
+        None => builder.singlepart(html),
+    }?;
+
+    let server = settings.server.as_str();
+    let mut transport = match settings.security {
+        SmtpSecurity::Starttls => SmtpTransport::starttls_relay(server)?,
+        SmtpSecurity::Tls => SmtpTransport::relay(server)?,
+        SmtpSecurity::None => SmtpTransport::builder_dangerous(server),
+    }
+    .port(settings.port)
+    .timeout(Some(Duration::from_secs_f64(
+        settings.timeout_seconds.max(1.0),
+    )));
+    if let Some((username, password)) = &settings.credentials {
+        transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+    transport
+        .build()
+        .send(&message)
+        .with_context(|| format!("Cannot send the report through {server}"))?;
+    Ok(())
+}
+
+#[cfg(not(feature = "email"))]
+pub fn send_report(
+    _settings: &SmtpSettings,
+    _recipients: &[String],
+    _subject: &str,
+    _html: &str,
+    _graph_png: Option<&[u8]>,
+) -> Result<()> {
+    bail!("symmetri was built without email; rebuild with `--features email`")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    #[test]
+    fn settings_default_the_port_and_sender() {
+        let config = parse_config(
+            "[email]\nserver = \"smtp.example.org\"\nsecurity = \"tls\"\n\
+             username = \"me@example.org\"\npassword = \"hunter2\"\n",
+        )
+        .unwrap();
+        let settings = SmtpSettings::resolve(&config.email).unwrap();
+        assert_eq!(settings.port, 465);
+        assert_eq!(settings.from, "me@example.org");
+        assert_eq!(
+            settings.credentials,
+            Some(("me@example.org".to_string(), "hunter2".to_string()))
+        );
+
+        let without_password =
+            parse_config("[email]\nserver = \"smtp.lan\"\nusername = \"me\"\n").unwrap();
+        assert!(SmtpSettings::resolve(&without_password.email).is_err());
+        let relay = parse_config(
+            "[email]\nserver = \"smtp.lan\"\nsecurity = \"none\"\nfrom = \"laptop@lan\"\n",
+        )
+        .unwrap();
+        let settings = SmtpSettings::resolve(&relay.email).unwrap();
+        assert_eq!((settings.port, settings.credentials), (25, None));
+    }
+}
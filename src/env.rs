@@ -0,0 +1,227 @@
+//! Environment variables symmetri reads. Every setting has a `SYMMETRI_*` name; names
+//! from before the rename are still honoured, with a deprecation warning, and a few
+//! conventional names shared with other tools are read as a last resort.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::Mutex;
+
+/// One setting that can come from the environment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnvVar {
+    pub name: &'static str,
+    /// Older names, read (with a warning) only when `name` is unset.
+    pub legacy: &'static [&'static str],
+    /// Names other programs read too (`RUST_LOG`), used without a warning when neither
+    /// `name` nor a legacy name is set.
+    pub fallback: &'static [&'static str],
+}
+
+/// Database path, overridden by `--db`.
+pub const DB: EnvVar = EnvVar {
+    name: "SYMMETRI_DB",
+    legacy: &["BATTERY_MONITOR_DB"],
+    fallback: &[],
+};
+
+/// Config file path, overridden by `--config`.
+pub const CONFIG: EnvVar = EnvVar {
+    name: "SYMMETRI_CONFIG",
+    legacy: &[],
+    fallback: &[],
+};
+
+/// Log filter directives (`debug`, `symmetri=trace,info`), on top of `--verbose`.
+pub const LOG: EnvVar = EnvVar {
+    name: "SYMMETRI_LOG",
+    legacy: &[],
+    fallback: &["RUST_LOG"],
+};
+
+/// Control socket path of `collect --interval`.
+pub const SOCKET: EnvVar = EnvVar {
+    name: "SYMMETRI_SOCKET",
+    legacy: &[],
+    fallback: &[],
+};
+
+/// Access token for `[ntfy]`, instead of `token` in the config file.
+pub const NTFY_TOKEN: EnvVar = EnvVar {
+    name: "SYMMETRI_NTFY_TOKEN",
+    legacy: &[],
+    fallback: &[],
+};
+
+/// Application token for `[gotify]`, instead of `token` in the config file.
+pub const GOTIFY_TOKEN: EnvVar = EnvVar {
+    name: "SYMMETRI_GOTIFY_TOKEN",
+    legacy: &[],
+    fallback: &[],
+};
+
+/// SMTP server for `report --email`, instead of `[email] server`.
+pub const SMTP_SERVER: EnvVar = EnvVar {
+    name: "SYMMETRI_SMTP_SERVER",
+    legacy: &[],
+    fallback: &[],
+};
+
+/// SMTP login for `report --email`, instead of `[email] username`.
+pub const SMTP_USER: EnvVar = EnvVar {
+    name: "SYMMETRI_SMTP_USER",
+    legacy: &[],
+    fallback: &[],
+};
+
+/// SMTP password for `report --email`, instead of `[email] password`.
+pub const SMTP_PASSWORD: EnvVar = EnvVar {
+    name: "SYMMETRI_SMTP_PASSWORD",
+    legacy: &[],
+    fallback: &[],
+};
+
+/// Where a resolved setting came from, as listed by `config show`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    Flag,
+    Env(&'static str),
+    LegacyEnv(&'static str),
+    Default,
+    /// The system-wide collector's database, used when the user has none.
+    System,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Flag => write!(f, "command line"),
+            Origin::Env(name) => write!(f, "{name}"),
+            Origin::LegacyEnv(name) => write!(f, "{name} (deprecated)"),
+            Origin::Default => write!(f, "default"),
+            Origin::System => write!(f, "system-wide collector"),
+        }
+    }
+}
+
+static WARNED: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+impl EnvVar {
+    /// The value and where it came from, without warning: `name`, else the first legacy
+    /// name that is set, else the first fallback. Empty values count as unset.
+    pub fn lookup(&self) -> Option<(String, Origin)> {
+        let read = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        if let Some(value) = read(self.name) {
+            return Some((value, Origin::Env(self.name)));
+        }
+        self.legacy
+            .iter()
+            .find_map(|legacy| read(legacy).map(|value| (value, Origin::LegacyEnv(legacy))))
+            .or_else(|| {
+                self.fallback
+                    .iter()
+                    .find_map(|name| read(name).map(|value| (value, Origin::Env(name))))
+            })
+    }
+
+    /// Like [`EnvVar::lookup`], warning once per process when a legacy name was used.
+    pub fn get(&self) -> Option<(String, Origin)> {
+        let found = self.lookup();
+        if let Some((_, Origin::LegacyEnv(legacy))) = &found {
+            self.warn_deprecated(legacy);
+        }
+        found
+    }
+
+    fn warn_deprecated(&self, legacy: &'static str) {
+        let first = WARNED
+            .lock()
+            .map(|mut warned| warned.insert(legacy))
+            .unwrap_or(false);
+        if !first {
+            return;
+        }
+        let message = format!("{legacy} is deprecated; set {} instead", self.name);
+        if tracing::dispatcher::has_been_set() {
+            tracing::warn!("{message}");
+        } else {
+            eprintln!("Warning: {message}");
+        }
+    }
+
+    /// `SYMMETRI_X, then LEGACY_X (deprecated), then OTHER_X` for help texts and
+    /// `config show`.
+    pub fn order(&self) -> String {
+        std::iter::once(self.name.to_string())
+            .chain(
+                self.legacy
+                    .iter()
+                    .map(|legacy| format!("{legacy} (deprecated)")),
+            )
+            .chain(self.fallback.iter().map(|name| name.to_string()))
+            .collect::<Vec<_>>()
+            .join(", then ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_names_win_over_legacy_ones() {
+        // Names no other test touches, so the process environment is ours.
+        const PROBE: EnvVar = EnvVar {
+            name: "SYMMETRI_TEST_PROBE",
+            legacy: &["OLD_TEST_PROBE", "OLDER_TEST_PROBE"],
+            fallback: &[],
+        };
+        assert_eq!(PROBE.lookup(), None);
+        std::env::set_var("OLDER_TEST_PROBE", "older");
+        assert_eq!(
+            PROBE.get(),
+            Some(("older".to_string(), Origin::LegacyEnv("OLDER_TEST_PROBE")))
+        );
+        std::env::set_var("OLD_TEST_PROBE", "old");
+        assert_eq!(PROBE.lookup().unwrap().0, "old");
+        std::env::set_var("SYMMETRI_TEST_PROBE", "");
+        assert_eq!(PROBE.lookup().unwrap().0, "old");
+        std::env::set_var("SYMMETRI_TEST_PROBE", "new");
+        assert_eq!(
+            PROBE.get(),
+            Some(("new".to_string(), Origin::Env("SYMMETRI_TEST_PROBE")))
+        );
+        assert_eq!(
+            PROBE.order(),
+            "SYMMETRI_TEST_PROBE, then OLD_TEST_PROBE (deprecated), then OLDER_TEST_PROBE (deprecated)"
+        );
+        for name in ["SYMMETRI_TEST_PROBE", "OLD_TEST_PROBE", "OLDER_TEST_PROBE"] {
+            std::env::remove_var(name);
+        }
+    }
+
+    #[test]
+    fn fallback_names_are_read_last_and_without_deprecation() {
+        const PROBE: EnvVar = EnvVar {
+            name: "SYMMETRI_FALLBACK_PROBE",
+            legacy: &["OLD_FALLBACK_PROBE"],
+            fallback: &["SHARED_FALLBACK_PROBE"],
+        };
+        std::env::set_var("SHARED_FALLBACK_PROBE", "shared");
+        assert_eq!(
+            PROBE.get(),
+            Some(("shared".to_string(), Origin::Env("SHARED_FALLBACK_PROBE")))
+        );
+        std::env::set_var("OLD_FALLBACK_PROBE", "old");
+        assert_eq!(
+            PROBE.lookup(),
+            Some(("old".to_string(), Origin::LegacyEnv("OLD_FALLBACK_PROBE")))
+        );
+        assert_eq!(
+            PROBE.order(),
+            "SYMMETRI_FALLBACK_PROBE, then OLD_FALLBACK_PROBE (deprecated), then SHARED_FALLBACK_PROBE"
+        );
+        for name in ["OLD_FALLBACK_PROBE", "SHARED_FALLBACK_PROBE"] {
+            std::env::remove_var(name);
+        }
+    }
+}
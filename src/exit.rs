@@ -0,0 +1,139 @@
+//! Process exit codes, so scripts wrapping symmetri can tell failures apart.
+//!
+//! | Code | Meaning |
+//! |------|---------|
+//! | 0 | success |
+//! | 1 | any other error (bad arguments, unreadable files, ...) |
+//! | 2 | no data for the request |
+//! | 3 | database error |
+//! | 4 | a collector failed (timed out or panicked) |
+//! | 5 | a threshold was tripped (`check`, `db explain`) |
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    Success,
+    Error,
+    NoData,
+    Database,
+    CollectorFailure,
+    ThresholdTripped,
+}
+
+impl ExitStatus {
+    pub fn code(self) -> i32 {
+        match self {
+            ExitStatus::Success => 0,
+            ExitStatus::Error => 1,
+            ExitStatus::NoData => 2,
+            ExitStatus::Database => 3,
+            ExitStatus::CollectorFailure => 4,
+            ExitStatus::ThresholdTripped => 5,
+        }
+    }
+}
+
+/// An error ending the process with `status`. Without a message nothing is printed: the
+/// command already said what happened (e.g. the thresholds `check` found crossed).
+#[derive(Debug)]
+pub struct Exit {
+    pub status: ExitStatus,
+    pub message: Option<String>,
+}
+
+impl Exit {
+    pub fn new(status: ExitStatus, message: impl Into<String>) -> Self {
+        Exit {
+            status,
+            message: Some(message.into()),
+        }
+    }
+
+    pub fn silent(status: ExitStatus) -> Self {
+        Exit {
+            status,
+            message: None,
+        }
+    }
+}
+
+impl fmt::Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.message {
+            Some(message) => write!(f, "{message}"),
+            None => write!(f, "exit status {}", self.status.code()),
+        }
+    }
+}
+
+impl std::error::Error for Exit {}
+
+/// `--quiet`: points stdout at `/dev/null` for the rest of the process, so tables,
+/// summaries and progress lines disappear while errors (stderr) and the exit code stay.
+pub fn silence_stdout() -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let null = std::fs::OpenOptions::new().write(true).open("/dev/null")?;
+    if unsafe { libc::dup2(null.as_raw_fd(), libc::STDOUT_FILENO) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// The status an error returned by [`crate::cli::run`] ends the process with: an [`Exit`]
+/// anywhere in its chain decides, then SQLite errors map to [`ExitStatus::Database`].
+pub fn status_of(err: &anyhow::Error) -> ExitStatus {
+    if let Some(exit) = err.chain().find_map(|cause| cause.downcast_ref::<Exit>()) {
+        return exit.status;
+    }
+    if err
+        .chain()
+        .any(|cause| cause.downcast_ref::<rusqlite::Error>().is_some())
+    {
+        return ExitStatus::Database;
+    }
+    ExitStatus::Error
+}
+
+/// Prints `err` to stderr and returns the exit code, for the binaries' `main`. An [`Exit`]
+/// is an outcome rather than a failure: its message is printed as is, or not at all.
+pub fn report(err: &anyhow::Error) -> i32 {
+    match err.downcast_ref::<Exit>() {
+        Some(Exit {
+            message: Some(message),
+            ..
+        }) => eprintln!("{message}"),
+        Some(Exit { message: None, .. }) => {}
+        None => eprintln!("Error: {err:#}"),
+    }
+    status_of(err).code()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn errors_map_to_exit_codes() {
+        let plain = anyhow::anyhow!("bad argument");
+        assert_eq!(status_of(&plain).code(), 1);
+
+        let no_data = anyhow::Error::new(Exit::new(ExitStatus::NoData, "No records"));
+        assert_eq!(status_of(&no_data).code(), 2);
+        assert_eq!(no_data.to_string(), "No records");
+
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        let db = conn
+            .execute("SELECT * FROM missing", [])
+            .context("Cannot read samples")
+            .unwrap_err();
+        assert_eq!(status_of(&db), ExitStatus::Database);
+
+        let tripped = anyhow::Error::new(Exit::silent(ExitStatus::ThresholdTripped))
+            .context("while checking");
+        assert_eq!(status_of(&tripped).code(), 5);
+        assert_eq!(ExitStatus::CollectorFailure.code(), 4);
+    }
+}
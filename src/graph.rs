@@ -1,24 +1,42 @@
 use std::collections::BTreeMap;
 use std::path::Path;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use chrono::{DateTime, TimeZone, Utc};
-use log::{info, warn};
 use ordered_float::OrderedFloat;
 use plotters::coord::Shift;
+use plotters::element::DashedPathElement;
 use plotters::prelude::*;
 use plotters::series::LineSeries;
+use tracing::{info, warn};
 
 use crate::aggregate::aggregate_samples_by_timestamp;
+use crate::cgroups::cgroup_usage;
 use crate::cli::ReportPreset;
-use crate::db::{self, Sample};
-use crate::metrics::{MetricKind, MetricSample};
+use crate::cli_helpers::Histogram;
+use crate::config::{GraphConfig, GraphTheme};
+use crate::db::{self, Annotation, Sample};
+use crate::internal;
+use crate::locale::Formats;
+use crate::metrics::{
+    MetricKind, MetricSample, CONTEXT_SWITCHES_SOURCE, LOAD_SOURCES, MEMORY_SOURCE, SWAP_SOURCE,
+};
+use crate::thermal::{detect_throttling, ThrottleCriteria};
 use crate::timeframe::Timeframe;
 
+/// Aggregated battery series over `timeframe`, built a page at a time so the raw rows of
+/// long windows are never all held at once.
 pub fn load_series(db_path: &Path, timeframe: &Timeframe) -> Result<Vec<Sample>> {
-    let since_ts = timeframe.since_timestamp(None);
-    let raw = db::fetch_samples(db_path, since_ts)?;
-    Ok(aggregate_samples_by_timestamp(&raw))
+    let mut cursor = db::sample_cursor(
+        db_path,
+        timeframe.since_timestamp(None),
+        timeframe.until_timestamp(),
+    )?;
+    let mut series = Vec::new();
+    while let Some(page) = cursor.next_page()? {
+        series.extend(aggregate_samples_by_timestamp(&page));
+    }
+    Ok(series)
 }
 
 struct MetricSeries {
@@ -28,37 +46,313 @@ struct MetricSeries {
 
 type SeriesPoints = Vec<(DateTime<Utc>, f64)>;
 
+/// Only the last hour of a discharge run feeds the trend, so earlier heavy or idle
+/// stretches do not skew the projection.
+const FORECAST_WINDOW_SECONDS: f64 = 3600.0;
+const FORECAST_MIN_SPAN_SECONDS: f64 = 300.0;
+const FORECAST_MAX_HOURS: f64 = 48.0;
+
+/// Linear projection of the charge level from the latest reading down to 0%.
+#[derive(Debug, Clone, PartialEq)]
+struct Forecast {
+    start: (DateTime<Utc>, f64),
+    empty_at: DateTime<Utc>,
+}
+
 struct ChartSpec {
     title: String,
     y_desc: String,
     series: Vec<MetricSeries>,
+    forecast: Option<Forecast>,
+    /// Series drawn against a right-hand y-axis with its own scale.
+    secondary: Option<SecondaryAxis>,
+    /// Time ranges shaded behind the lines, e.g. while charging.
+    shaded: Vec<(DateTime<Utc>, DateTime<Utc>)>,
+    /// Labelled vertical markers drawn below the annotation ones.
+    events: Vec<(DateTime<Utc>, String)>,
+}
+
+struct SecondaryAxis {
+    y_desc: String,
+    series: MetricSeries,
+}
+
+impl ChartSpec {
+    fn new(title: String, y_desc: String, series: Vec<MetricSeries>) -> Self {
+        ChartSpec {
+            title,
+            y_desc,
+            series,
+            forecast: None,
+            secondary: None,
+            shaded: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+}
+
+/// Colors, font, pixel size and point budget used for every PNG graph.
+#[derive(Debug, Clone)]
+pub struct GraphStyle {
+    background: RGBColor,
+    foreground: RGBColor,
+    grid: RGBColor,
+    palette: Vec<RGBColor>,
+    font: String,
+    width: u32,
+    row_height: u32,
+    scale: f64,
+    max_points: usize,
+    formats: Formats,
+}
+
+const DARK_PALETTE: [RGBColor; 8] = [
+    RGBColor(137, 180, 250),
+    RGBColor(243, 139, 168),
+    RGBColor(166, 227, 161),
+    RGBColor(250, 179, 135),
+    RGBColor(203, 166, 247),
+    RGBColor(148, 226, 213),
+    RGBColor(249, 226, 175),
+    RGBColor(235, 160, 172),
+];
+
+impl GraphStyle {
+    /// Resolves `[graph]`, with `theme` (from `--graph-theme`) overriding the configured one.
+    pub fn from_config(config: &GraphConfig, theme: Option<GraphTheme>) -> Result<Self> {
+        let light = GraphStyle {
+            background: WHITE,
+            foreground: BLACK,
+            grid: RGBColor(204, 204, 204),
+            palette: (0..8).map(|idx| rgb(&Palette99::pick(idx))).collect(),
+            font: config.font.clone(),
+            width: config.width.max(1),
+            row_height: config.row_height.max(1),
+            scale: f64::from(config.dpi.max(1)) / 96.0,
+            max_points: config.max_points,
+            formats: Formats::default(),
+        };
+        let style = match theme.unwrap_or(config.theme) {
+            GraphTheme::Light => light,
+            GraphTheme::Dark => GraphStyle {
+                background: RGBColor(30, 30, 46),
+                foreground: RGBColor(205, 214, 244),
+                grid: RGBColor(69, 71, 90),
+                palette: DARK_PALETTE.to_vec(),
+                ..light
+            },
+            GraphTheme::Custom => {
+                let parse = |value: &Option<String>, fallback: RGBColor| {
+                    value.as_deref().map_or(Ok(fallback), parse_color)
+                };
+                let palette = config
+                    .colors
+                    .iter()
+                    .map(|color| parse_color(color))
+                    .collect::<Result<Vec<_>>>()?;
+                GraphStyle {
+                    background: parse(&config.background, light.background)?,
+                    foreground: parse(&config.foreground, light.foreground)?,
+                    grid: parse(&config.grid, light.grid)?,
+                    palette: if palette.is_empty() {
+                        light.palette.clone()
+                    } else {
+                        palette
+                    },
+                    ..light
+                }
+            }
+        };
+        Ok(style)
+    }
+
+    /// Overrides `[graph] max_points` (`report --max-points`).
+    pub fn with_max_points(mut self, max_points: usize) -> Self {
+        self.max_points = max_points;
+        self
+    }
+
+    /// Byte units and time format for axes and labels (`--units`, `--date-order`, `--clock`).
+    pub fn with_formats(mut self, formats: Formats) -> Self {
+        self.formats = formats;
+        self
+    }
+
+    fn color(&self, idx: usize) -> RGBColor {
+        self.palette[idx % self.palette.len()]
+    }
+
+    fn px(&self, value: u32) -> u32 {
+        (f64::from(value) * self.scale).round().max(1.0) as u32
+    }
+
+    fn text<C: Color>(&self, size: u32, color: &C) -> TextStyle<'_> {
+        (self.font.as_str(), self.px(size)).into_font().color(color)
+    }
+
+    fn line<C: Color>(&self, color: &C) -> ShapeStyle {
+        color.stroke_width(self.px(1))
+    }
+}
+
+impl Default for GraphStyle {
+    fn default() -> Self {
+        GraphStyle::from_config(&GraphConfig::default(), None)
+            .expect("the light theme has no colors to parse")
+    }
+}
+
+fn rgb<C: Color>(color: &C) -> RGBColor {
+    let (r, g, b) = color.rgb();
+    RGBColor(r, g, b)
+}
+
+/// Parses `#rrggbb` (the leading `#` is optional).
+fn parse_color(raw: &str) -> Result<RGBColor> {
+    let hex = raw.trim().trim_start_matches('#');
+    let channel = |range: std::ops::Range<usize>| {
+        hex.get(range)
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+    };
+    match (hex.len(), channel(0..2), channel(2..4), channel(4..6)) {
+        (6, Some(r), Some(g), Some(b)) => Ok(RGBColor(r, g, b)),
+        _ => Err(anyhow!(
+            "invalid color {raw:?} in [graph]; expected #rrggbb"
+        )),
+    }
 }
 
 pub fn render_plot(
     battery_samples: &[Sample],
     metrics: &[MetricSample],
+    annotations: &[Annotation],
     presets: &[ReportPreset],
     timeframe: &Timeframe,
     output: &Path,
+    style: &GraphStyle,
 ) -> Result<()> {
-    let charts = build_charts(battery_samples, metrics, presets, timeframe);
+    if draw_plot(
+        battery_samples,
+        metrics,
+        annotations,
+        presets,
+        timeframe,
+        output,
+        style,
+    )? {
+        info!("Saved plot to {}", output.display());
+    }
+    Ok(())
+}
+
+/// Draws the charts to `output` without logging; returns false when nothing was plotted.
+pub fn draw_plot(
+    battery_samples: &[Sample],
+    metrics: &[MetricSample],
+    annotations: &[Annotation],
+    presets: &[ReportPreset],
+    timeframe: &Timeframe,
+    output: &Path,
+    style: &GraphStyle,
+) -> Result<bool> {
+    let charts = build_charts(battery_samples, metrics, presets, timeframe, &style.formats);
     if charts.is_empty() {
         warn!("No values available to plot for selected presets");
-        return Ok(());
+        return Ok(false);
     }
 
     let rows = charts.len().max(1);
-    let height = (rows as u32 * 260).max(260);
-    let root = BitMapBackend::new(output, (1280, height)).into_drawing_area();
-    root.fill(&WHITE)?;
+    let height = style.px(rows as u32 * style.row_height);
+    let root = BitMapBackend::new(output, (style.px(style.width), height)).into_drawing_area();
+    root.fill(&style.background)?;
     let areas = root.split_evenly((rows, 1));
 
     for (area, chart) in areas.into_iter().zip(charts.iter()) {
-        plot_chart(area, chart)?;
+        plot_chart(area, chart, annotations, style)?;
     }
 
     root.present()?;
-    info!("Saved plot to {}", output.display());
+    Ok(true)
+}
+
+/// Draws a histogram of watt readings with dashed p50/p90/p99 markers.
+pub fn render_histogram(
+    histogram: &Histogram,
+    title: &str,
+    x_desc: &str,
+    output: &Path,
+    style: &GraphStyle,
+) -> Result<()> {
+    let (Some(first), Some(last)) = (histogram.bins.first(), histogram.bins.last()) else {
+        return Ok(());
+    };
+    let max_count = histogram
+        .bins
+        .iter()
+        .map(|b| b.count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let size = (style.px(style.width), style.px(style.row_height * 2));
+    let root = BitMapBackend::new(output, size).into_drawing_area();
+    root.fill(&style.background)?;
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, style.text(20, &style.foreground))
+        .margin(style.px(12))
+        .x_label_area_size(style.px(36))
+        .y_label_area_size(style.px(60))
+        .build_cartesian_2d(first.start..last.end, 0.0..max_count as f64 * 1.1)?;
+    chart
+        .configure_mesh()
+        .x_desc(x_desc)
+        .y_desc("Readings")
+        .label_style(style.text(12, &style.foreground))
+        .axis_style(style.line(&style.foreground))
+        .bold_line_style(style.grid)
+        .light_line_style(style.background.mix(0.15))
+        .draw()?;
+
+    let bar_color = style.color(0);
+    chart.draw_series(histogram.bins.iter().map(|bin| {
+        Rectangle::new(
+            [(bin.start, 0.0), (bin.end, bin.count as f64)],
+            bar_color.filled(),
+        )
+    }))?;
+
+    let top = max_count as f64 * 1.05;
+    for (idx, (label, value)) in [
+        ("p50", histogram.p50),
+        ("p90", histogram.p90),
+        ("p99", histogram.p99),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let color = style.color(idx + 1);
+        let width = style.px(2);
+        chart
+            .draw_series(DashedLineSeries::new(
+                vec![(value, 0.0), (value, top)],
+                style.px(8),
+                style.px(4),
+                color.stroke_width(width),
+            ))?
+            .label(format!("{label} {value:.2}W"))
+            .legend(move |(x, y)| {
+                PathElement::new(vec![(x, y), (x + 10, y)], color.stroke_width(width))
+            });
+    }
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperMiddle)
+        .label_font(style.text(12, &style.foreground))
+        .background_style(style.background.mix(0.8))
+        .border_style(style.foreground)
+        .draw()?;
+
+    root.present()?;
+    info!("Saved histogram to {}", output.display());
     Ok(())
 }
 
@@ -67,9 +361,11 @@ fn build_charts(
     metrics: &[MetricSample],
     presets: &[ReportPreset],
     timeframe: &Timeframe,
+    formats: &Formats,
 ) -> Vec<ChartSpec> {
     let mut charts = Vec::new();
     let label = timeframe.label.replace('_', " ");
+    let (giga, giga_unit) = formats.giga();
 
     if presets.contains(&ReportPreset::Battery) {
         let mut series = Vec::new();
@@ -87,98 +383,126 @@ fn build_charts(
                 points: health_points,
             });
         }
-        if !series.is_empty() {
-            charts.push(ChartSpec {
-                title: format!("Battery ({label})"),
-                y_desc: "Percent".to_string(),
-                series,
-            });
-        }
-
         let power_draw = aggregate_metric_series(metrics, MetricKind::PowerDraw, |v, _| v);
-        if !power_draw.is_empty() {
-            charts.push(ChartSpec {
-                title: format!("Power draw ({label})"),
-                y_desc: "Watts".to_string(),
-                series: vec![MetricSeries {
+        if !series.is_empty() {
+            let mut chart =
+                ChartSpec::new(format!("Battery ({label})"), "Percent".to_string(), series);
+            chart.forecast = depletion_forecast(battery_samples);
+            chart.shaded = charging_periods(battery_samples);
+            chart.events = status_changes(battery_samples);
+            if !power_draw.is_empty() {
+                chart.secondary = Some(SecondaryAxis {
+                    y_desc: "Watts".to_string(),
+                    series: MetricSeries {
+                        label: "Power draw (W)".to_string(),
+                        points: power_draw,
+                    },
+                });
+            }
+            charts.push(chart);
+        } else if !power_draw.is_empty() {
+            charts.push(ChartSpec::new(
+                format!("Power draw ({label})"),
+                "Watts".to_string(),
+                vec![MetricSeries {
                     label: "Discharge".to_string(),
                     points: power_draw,
                 }],
-            });
+            ));
         }
     }
 
     if presets.contains(&ReportPreset::Cpu) {
         let usage = aggregate_metric_series_by_source(metrics, MetricKind::CpuUsage, |v, _| v);
         if !usage.is_empty() {
-            charts.push(ChartSpec {
-                title: format!("CPU usage ({label})"),
-                y_desc: "Percent".to_string(),
-                series: usage,
-            });
+            charts.push(ChartSpec::new(
+                format!("CPU usage ({label})"),
+                "Percent".to_string(),
+                usage,
+            ));
         }
         let freq = aggregate_metric_series_by_source(metrics, MetricKind::CpuFrequency, |v, _| v);
         if !freq.is_empty() {
-            charts.push(ChartSpec {
-                title: format!("CPU frequency ({label})"),
-                y_desc: "MHz".to_string(),
-                series: freq,
-            });
+            charts.push(ChartSpec::new(
+                format!("CPU frequency ({label})"),
+                "MHz".to_string(),
+                freq,
+            ));
         }
     }
 
     if presets.contains(&ReportPreset::Gpu) {
         let usage = aggregate_metric_series_by_source(metrics, MetricKind::GpuUsage, |v, _| v);
         if !usage.is_empty() {
-            charts.push(ChartSpec {
-                title: format!("GPU usage ({label})"),
-                y_desc: "Percent".to_string(),
-                series: usage,
-            });
+            charts.push(ChartSpec::new(
+                format!("GPU usage ({label})"),
+                "Percent".to_string(),
+                usage,
+            ));
         }
         let freq = aggregate_metric_series_by_source(metrics, MetricKind::GpuFrequency, |v, _| v);
         if !freq.is_empty() {
-            charts.push(ChartSpec {
-                title: format!("GPU frequency ({label})"),
-                y_desc: "MHz".to_string(),
-                series: freq,
-            });
+            charts.push(ChartSpec::new(
+                format!("GPU frequency ({label})"),
+                "MHz".to_string(),
+                freq,
+            ));
         }
     }
 
     if presets.contains(&ReportPreset::Memory) {
-        let memory = aggregate_metric_series(metrics, MetricKind::MemoryUsage, |used, _| {
-            bytes_to_gib(used)
-        });
+        let by_source = |source: &str| -> Vec<MetricSample> {
+            metrics
+                .iter()
+                .filter(|m| m.kind == MetricKind::MemoryUsage && m.source == source)
+                .cloned()
+                .collect()
+        };
+        let memory = aggregate_metric_series(
+            &by_source(MEMORY_SOURCE),
+            MetricKind::MemoryUsage,
+            |used, _| used / giga,
+        );
         if !memory.is_empty() {
-            charts.push(ChartSpec {
-                title: format!("Memory usage ({label})"),
-                y_desc: "GiB".to_string(),
-                series: vec![MetricSeries {
-                    label: "Used".to_string(),
-                    points: memory,
-                }],
-            });
+            let mut series = vec![MetricSeries {
+                label: "Used".to_string(),
+                points: memory,
+            }];
+            let swap = aggregate_metric_series(
+                &by_source(SWAP_SOURCE),
+                MetricKind::MemoryUsage,
+                |used, _| used / giga,
+            );
+            if !swap.is_empty() {
+                series.push(MetricSeries {
+                    label: "Swap used".to_string(),
+                    points: swap,
+                });
+            }
+            charts.push(ChartSpec::new(
+                format!("Memory usage ({label})"),
+                giga_unit.to_string(),
+                series,
+            ));
         }
     }
 
     if presets.contains(&ReportPreset::Disk) {
-        let disk =
-            aggregate_metric_series(metrics, MetricKind::DiskUsage, |used, _| bytes_to_gib(used));
+        let disk = aggregate_metric_series_by_source(metrics, MetricKind::DiskUsage, |used, _| {
+            used / giga
+        });
         if !disk.is_empty() {
-            charts.push(ChartSpec {
-                title: format!("Disk usage ({label})"),
-                y_desc: "GiB".to_string(),
-                series: vec![MetricSeries {
-                    label: "Used".to_string(),
-                    points: disk,
-                }],
-            });
+            charts.push(ChartSpec::new(
+                format!("Disk usage ({label})"),
+                giga_unit.to_string(),
+                disk,
+            ));
         }
     }
 
     if presets.contains(&ReportPreset::Network) {
-        let (rx, tx) = network_rate_series(metrics);
+        let (mega, mega_unit) = formats.mega();
+        let (rx, tx) = network_rate_series(metrics, mega);
         let mut series = Vec::new();
         if !rx.is_empty() {
             series.push(MetricSeries {
@@ -193,38 +517,152 @@ fn build_charts(
             });
         }
         if !series.is_empty() {
-            charts.push(ChartSpec {
-                title: format!("Network throughput ({label})"),
-                y_desc: "MiB/s".to_string(),
+            charts.push(ChartSpec::new(
+                format!("Network throughput ({label})"),
+                format!("{mega_unit}/s"),
                 series,
-            });
+            ));
+        }
+
+        let signal = aggregate_metric_series_by_source(metrics, MetricKind::Wifi, |v, _| v);
+        if !signal.is_empty() {
+            let mut chart =
+                ChartSpec::new(format!("Wi-Fi signal ({label})"), "dBm".to_string(), signal);
+            let bitrates: Vec<MetricSample> = metrics
+                .iter()
+                .filter(|m| m.kind == MetricKind::Wifi)
+                .filter_map(|m| {
+                    let mut sample = m.clone();
+                    sample.value = m.details.get("tx_bitrate_mbps")?.as_f64();
+                    Some(sample)
+                })
+                .collect();
+            let bitrate = aggregate_metric_series(&bitrates, MetricKind::Wifi, |v, _| v);
+            if !bitrate.is_empty() {
+                chart.secondary = Some(SecondaryAxis {
+                    y_desc: "TX Mbit/s".to_string(),
+                    series: MetricSeries {
+                        label: "TX bitrate".to_string(),
+                        points: bitrate,
+                    },
+                });
+            }
+            charts.push(chart);
         }
     }
 
     if presets.contains(&ReportPreset::Temperature) {
         let temps = aggregate_metric_series_by_source(metrics, MetricKind::Temperature, |v, _| v);
         if !temps.is_empty() {
-            charts.push(ChartSpec {
-                title: format!("Temperature ({label})"),
-                y_desc: "Celsius".to_string(),
-                series: temps,
-            });
+            let mut chart = ChartSpec::new(
+                format!("Temperature ({label})"),
+                "Celsius".to_string(),
+                temps,
+            );
+            chart.events = detect_throttling(metrics, &ThrottleCriteria::default())
+                .iter()
+                .filter_map(|episode| ts_to_datetime(episode.start_ts))
+                .map(|ts| (ts, "Throttled".to_string()))
+                .collect();
+            charts.push(chart);
+        }
+    }
+
+    if presets.contains(&ReportPreset::Load) {
+        let loads: Vec<MetricSample> = metrics
+            .iter()
+            .filter(|m| {
+                m.kind == MetricKind::SystemLoad && LOAD_SOURCES.contains(&m.source.as_str())
+            })
+            .cloned()
+            .collect();
+        let series = aggregate_metric_series_by_source(&loads, MetricKind::SystemLoad, |v, _| v);
+        if !series.is_empty() {
+            let mut chart =
+                ChartSpec::new(format!("System load ({label})"), "Load".to_string(), series);
+            let switches: Vec<MetricSample> = metrics
+                .iter()
+                .filter(|m| m.kind == MetricKind::SystemLoad && m.source == CONTEXT_SWITCHES_SOURCE)
+                .cloned()
+                .collect();
+            let switches = aggregate_metric_series(&switches, MetricKind::SystemLoad, |v, _| v);
+            if !switches.is_empty() {
+                chart.secondary = Some(SecondaryAxis {
+                    y_desc: "Context switches/s".to_string(),
+                    series: MetricSeries {
+                        label: "Context switches/s".to_string(),
+                        points: switches,
+                    },
+                });
+            }
+            charts.push(chart);
+        }
+    }
+
+    if presets.contains(&ReportPreset::Cgroups) {
+        let series: Vec<MetricSeries> = cgroup_usage(metrics)
+            .into_iter()
+            .map(|(cgroup, usage)| MetricSeries {
+                label: cgroup,
+                points: usage
+                    .iter()
+                    .filter_map(|u| Some((ts_to_datetime(u.ts)?, u.cpu_pct?)))
+                    .collect(),
+            })
+            .filter(|series| !series.points.is_empty())
+            .collect();
+        if !series.is_empty() {
+            charts.push(ChartSpec::new(
+                format!("Cgroup CPU ({label})"),
+                "% of one core".to_string(),
+                series,
+            ));
+        }
+    }
+
+    if presets.contains(&ReportPreset::Internal) {
+        let timings: Vec<MetricSample> = metrics
+            .iter()
+            .filter(|m| {
+                m.kind == MetricKind::Internal
+                    && m.unit.as_deref() == Some("ms")
+                    && !internal::is_collector_source(&m.source)
+            })
+            .cloned()
+            .collect();
+        let series = aggregate_metric_series_by_source(&timings, MetricKind::Internal, |v, _| v);
+        if !series.is_empty() {
+            charts.push(ChartSpec::new(
+                format!("Symmetri overhead ({label})"),
+                "ms".to_string(),
+                series,
+            ));
         }
     }
 
     charts
 }
 
-fn plot_chart(area: DrawingArea<BitMapBackend, Shift>, chart: &ChartSpec) -> Result<()> {
+fn plot_chart(
+    area: DrawingArea<BitMapBackend, Shift>,
+    chart: &ChartSpec,
+    annotations: &[Annotation],
+    style: &GraphStyle,
+) -> Result<()> {
     let mut all_points: Vec<(DateTime<Utc>, f64)> = Vec::new();
     for series in &chart.series {
         all_points.extend_from_slice(&series.points);
     }
+    let secondary_points = chart
+        .secondary
+        .as_ref()
+        .map_or(&[][..], |s| &s.series.points);
 
-    let Some(min_ts) = all_points.iter().map(|(ts, _)| *ts).min() else {
+    let timestamps = || all_points.iter().chain(secondary_points).map(|(ts, _)| *ts);
+    let Some(min_ts) = timestamps().min() else {
         return Ok(());
     };
-    let Some(max_ts) = all_points.iter().map(|(ts, _)| *ts).max() else {
+    let Some(mut max_ts) = timestamps().max() else {
         return Ok(());
     };
 
@@ -234,6 +672,10 @@ fn plot_chart(area: DrawingArea<BitMapBackend, Shift>, chart: &ChartSpec) -> Res
         min_y = min_y.min(*value);
         max_y = max_y.max(*value);
     }
+    if let Some(forecast) = &chart.forecast {
+        max_ts = max_ts.max(forecast.empty_at);
+        min_y = min_y.min(0.0);
+    }
     if (max_y - min_y).abs() < 1e-6 {
         min_y -= 1.0;
         max_y += 1.0;
@@ -243,38 +685,299 @@ fn plot_chart(area: DrawingArea<BitMapBackend, Shift>, chart: &ChartSpec) -> Res
     let y_max = max_y + padding;
 
     let mut chart_ctx = ChartBuilder::on(&area)
-        .caption(&chart.title, ("sans-serif", 20).into_font())
-        .margin(12)
-        .x_label_area_size(36)
-        .y_label_area_size(60)
-        .build_cartesian_2d(min_ts..max_ts, y_min..y_max)?;
+        .caption(&chart.title, style.text(20, &style.foreground))
+        .margin(style.px(12))
+        .x_label_area_size(style.px(36))
+        .y_label_area_size(style.px(60))
+        .right_y_label_area_size(if chart.secondary.is_some() {
+            style.px(60)
+        } else {
+            0
+        })
+        .build_cartesian_2d(min_ts..max_ts, y_min..y_max)?
+        .set_secondary_coord(min_ts..max_ts, 0.0..secondary_max(secondary_points));
 
+    let span_seconds = (max_ts - min_ts).num_seconds();
     chart_ctx
         .configure_mesh()
         .x_labels(5)
+        .x_label_formatter(&|ts| {
+            style
+                .formats
+                .axis(&ts.with_timezone(&style.formats.zone), span_seconds)
+        })
         .y_labels(6)
         .x_desc("Time")
         .y_desc(chart.y_desc.as_str())
-        .light_line_style(WHITE.mix(0.15))
+        .label_style(style.text(12, &style.foreground))
+        .axis_style(style.line(&style.foreground))
+        .bold_line_style(style.grid)
+        .light_line_style(style.background.mix(0.15))
         .draw()?;
 
+    if !chart.shaded.is_empty() {
+        let shade = RGBColor(46, 160, 67).mix(0.15);
+        chart_ctx
+            .draw_series(chart.shaded.iter().map(|(start, end)| {
+                Rectangle::new([(*start, y_min), (*end, y_max)], shade.filled())
+            }))?
+            .label("Charging")
+            .legend(move |(x, y)| Rectangle::new([(x, y - 4), (x + 10, y + 4)], shade.filled()));
+    }
+
     for (idx, series) in chart.series.iter().enumerate() {
-        let color = Palette99::pick(idx).to_rgba();
+        let color = style.color(idx);
         chart_ctx
-            .draw_series(LineSeries::new(series.points.clone(), &color))?
+            .draw_series(LineSeries::new(
+                lttb(&series.points, style.max_points),
+                style.line(&color),
+            ))?
             .label(series.label.clone())
             .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], color));
     }
 
+    if let Some(secondary) = &chart.secondary {
+        chart_ctx
+            .configure_secondary_axes()
+            .y_labels(6)
+            .y_desc(secondary.y_desc.as_str())
+            .label_style(style.text(12, &style.foreground))
+            .axis_style(style.line(&style.foreground))
+            .draw()?;
+        let color = style.color(chart.series.len());
+        chart_ctx
+            .draw_secondary_series(LineSeries::new(
+                lttb(&secondary.series.points, style.max_points),
+                style.line(&color),
+            ))?
+            .label(secondary.series.label.clone())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], color));
+    }
+
+    if let Some(forecast) = &chart.forecast {
+        let color = style.color(0);
+        let end = (forecast.empty_at, 0.0);
+        chart_ctx
+            .draw_series(DashedLineSeries::new(
+                vec![forecast.start, end],
+                style.px(6),
+                style.px(4),
+                style.line(&color),
+            ))?
+            .label("Forecast")
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 10, y)], color));
+        let empty_local = forecast.empty_at.with_timezone(&style.formats.zone);
+        let text = format!(
+            "Empty ~{} {}",
+            empty_local.format("%a"),
+            style.formats.time(&empty_local)
+        );
+        chart_ctx.draw_series(std::iter::once(Text::new(
+            text,
+            (forecast.start.0, (y_max - y_min) * 0.08 + y_min),
+            style.text(14, &color),
+        )))?;
+    }
+
+    let markers = annotation_markers(annotations, min_ts, max_ts);
+    let marker_color = RGBColor(120, 120, 120);
+    chart_ctx.draw_series(markers.iter().map(|(ts, _)| {
+        PathElement::new(vec![(*ts, y_min), (*ts, y_max)], style.line(&marker_color))
+    }))?;
+    chart_ctx.draw_series(markers.iter().map(|(ts, text)| {
+        Text::new(
+            text.clone(),
+            (*ts, y_max - (y_max - y_min) * 0.02),
+            style.text(12, &marker_color),
+        )
+    }))?;
+
+    let event_color = style.foreground.mix(0.5);
+    chart_ctx.draw_series(chart.events.iter().map(|(ts, _)| {
+        DashedPathElement::new(
+            vec![(*ts, y_min), (*ts, y_max)],
+            style.px(3),
+            style.px(3),
+            style.line(&event_color),
+        )
+    }))?;
+    chart_ctx.draw_series(chart.events.iter().map(|(ts, text)| {
+        Text::new(
+            text.clone(),
+            (*ts, y_max - (y_max - y_min) * 0.12),
+            style.text(11, &event_color),
+        )
+    }))?;
+
     chart_ctx
         .configure_series_labels()
-        .background_style(WHITE.mix(0.8))
-        .border_style(BLACK)
+        .label_font(style.text(12, &style.foreground))
+        .background_style(style.background.mix(0.8))
+        .border_style(style.foreground)
         .draw()?;
 
     Ok(())
 }
 
+/// Largest-triangle-three-buckets downsampling: keeps the first and last points and, from
+/// each bucket in between, the point spanning the largest triangle with its neighbours, so
+/// peaks survive while flat stretches thin out. `threshold` 0 (or under 3) keeps everything.
+fn lttb(points: &[(DateTime<Utc>, f64)], threshold: usize) -> SeriesPoints {
+    if threshold < 3 || points.len() <= threshold {
+        return points.to_vec();
+    }
+    let x = |idx: usize| points[idx].0.timestamp_millis() as f64;
+    let y = |idx: usize| points[idx].1;
+    let bucket_size = (points.len() - 2) as f64 / (threshold - 2) as f64;
+    let bucket_start = |bucket: usize| (bucket as f64 * bucket_size) as usize + 1;
+
+    let mut sampled = Vec::with_capacity(threshold);
+    sampled.push(points[0]);
+    let mut selected = 0;
+    for bucket in 0..threshold - 2 {
+        let (start, end) = (bucket_start(bucket), bucket_start(bucket + 1));
+        // The next bucket's average stands in for the point chosen there.
+        let next_end = bucket_start(bucket + 2).min(points.len());
+        let next_len = (next_end - end) as f64;
+        let avg_x = (end..next_end).map(x).sum::<f64>() / next_len;
+        let avg_y = (end..next_end).map(y).sum::<f64>() / next_len;
+
+        let (ax, ay) = (x(selected), y(selected));
+        selected = (start..end)
+            .max_by(|&a, &b| {
+                let area = |idx: usize| {
+                    ((ax - avg_x) * (y(idx) - ay) - (ax - x(idx)) * (avg_y - ay)).abs()
+                };
+                area(a).total_cmp(&area(b))
+            })
+            .unwrap_or(start);
+        sampled.push(points[selected]);
+    }
+    sampled.push(points[points.len() - 1]);
+    sampled
+}
+
+/// Fits a least-squares line through the trailing discharge run and projects it to 0%.
+/// Returns `None` unless the latest sample is discharging and the trend is clearly falling.
+fn depletion_forecast(samples: &[Sample]) -> Option<Forecast> {
+    let last = samples.last()?;
+    if !is_discharging(last) {
+        return None;
+    }
+    let run: Vec<(f64, f64)> = samples
+        .iter()
+        .rev()
+        .take_while(|sample| is_discharging(sample))
+        .take_while(|sample| last.ts - sample.ts <= FORECAST_WINDOW_SECONDS)
+        .filter_map(|sample| sample.percentage.map(|pct| (sample.ts, pct)))
+        .collect();
+    let (&(last_ts, last_pct), &(first_ts, _)) = (run.first()?, run.last()?);
+    if run.len() < 2 || last_ts - first_ts < FORECAST_MIN_SPAN_SECONDS {
+        return None;
+    }
+
+    let n = run.len() as f64;
+    let mean_ts = run.iter().map(|(ts, _)| ts).sum::<f64>() / n;
+    let mean_pct = run.iter().map(|(_, pct)| pct).sum::<f64>() / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for (ts, pct) in &run {
+        covariance += (ts - mean_ts) * (pct - mean_pct);
+        variance += (ts - mean_ts).powi(2);
+    }
+    let slope = covariance / variance;
+    if !slope.is_finite() || slope >= 0.0 {
+        return None;
+    }
+    let seconds_left = last_pct / -slope;
+    if seconds_left > FORECAST_MAX_HOURS * 3600.0 {
+        return None;
+    }
+    Some(Forecast {
+        start: (ts_to_datetime(last_ts)?, last_pct),
+        empty_at: ts_to_datetime(last_ts + seconds_left)?,
+    })
+}
+
+/// Upper bound of the right-hand axis, padded like the primary one.
+fn secondary_max(points: &[(DateTime<Utc>, f64)]) -> f64 {
+    let max = points.iter().map(|(_, value)| *value).fold(0.0, f64::max);
+    if max > 0.0 {
+        max * 1.05
+    } else {
+        1.0
+    }
+}
+
+/// Contiguous runs of charging samples, each ending at the first sample that is no longer
+/// charging so the shading covers the whole interval.
+fn charging_periods(samples: &[Sample]) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let mut periods = Vec::new();
+    let mut start: Option<f64> = None;
+    for sample in samples {
+        let charging = sample
+            .status
+            .as_deref()
+            .is_some_and(|status| status.eq_ignore_ascii_case("charging"));
+        match (charging, start) {
+            (true, None) => start = Some(sample.ts),
+            (false, Some(begin)) => {
+                periods.extend(ts_to_datetime(begin).zip(ts_to_datetime(sample.ts)));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let (Some(begin), Some(last)) = (start, samples.last()) {
+        periods.extend(ts_to_datetime(begin).zip(ts_to_datetime(last.ts)));
+    }
+    periods
+}
+
+/// Points where the battery status flips, labelled by what happened. Samples without a
+/// status are skipped so a missing reading does not count as a change.
+fn status_changes(samples: &[Sample]) -> Vec<(DateTime<Utc>, String)> {
+    let mut changes = Vec::new();
+    let mut previous: Option<&str> = None;
+    for sample in samples {
+        let Some(status) = sample.status.as_deref() else {
+            continue;
+        };
+        if let Some(before) = previous.filter(|before| !before.eq_ignore_ascii_case(status)) {
+            let label = match status.to_ascii_lowercase().as_str() {
+                "charging" if before.eq_ignore_ascii_case("discharging") => "Plugged in",
+                "charging" => "Charging",
+                "discharging" => "Unplugged",
+                "full" => "Full",
+                _ => status,
+            };
+            changes.extend(ts_to_datetime(sample.ts).map(|ts| (ts, label.to_string())));
+        }
+        previous = Some(status);
+    }
+    changes
+}
+
+fn is_discharging(sample: &Sample) -> bool {
+    sample
+        .status
+        .as_deref()
+        .is_some_and(|status| status.eq_ignore_ascii_case("discharging"))
+}
+
+fn annotation_markers(
+    annotations: &[Annotation],
+    min_ts: DateTime<Utc>,
+    max_ts: DateTime<Utc>,
+) -> Vec<(DateTime<Utc>, String)> {
+    annotations
+        .iter()
+        .filter_map(|annotation| {
+            let ts = ts_to_datetime(annotation.ts)?;
+            (ts >= min_ts && ts <= max_ts).then(|| (ts, annotation.text.clone()))
+        })
+        .collect()
+}
+
 fn battery_series<F>(samples: &[Sample], mut getter: F) -> Vec<(DateTime<Utc>, f64)>
 where
     F: FnMut(&Sample) -> Option<f64>,
@@ -361,7 +1064,8 @@ where
     series
 }
 
-fn network_rate_series(metrics: &[MetricSample]) -> (SeriesPoints, SeriesPoints) {
+/// Download and upload rates in bytes per second divided by `scale`.
+fn network_rate_series(metrics: &[MetricSample], scale: f64) -> (SeriesPoints, SeriesPoints) {
     let mut by_iface: BTreeMap<&str, Vec<&MetricSample>> = BTreeMap::new();
     for sample in metrics
         .iter()
@@ -396,10 +1100,10 @@ fn network_rate_series(metrics: &[MetricSample]) -> (SeriesPoints, SeriesPoints)
                 None => continue,
             };
             if let Some(rx) = rx_rate {
-                rx_series.push((ts, rx / 1_048_576.0));
+                rx_series.push((ts, rx / scale));
             }
             if let Some(tx) = tx_rate {
-                tx_series.push((ts, tx / 1_048_576.0));
+                tx_series.push((ts, tx / scale));
             }
         }
     }
@@ -422,10 +1126,6 @@ fn detail_number(sample: &MetricSample, key: &str) -> Option<f64> {
         .and_then(|v| v.as_f64().or_else(|| v.as_i64().map(|i| i as f64)))
 }
 
-fn bytes_to_gib(used: f64) -> f64 {
-    used / (1024.0 * 1024.0 * 1024.0)
-}
-
 fn ts_to_datetime(ts: f64) -> Option<DateTime<Utc>> {
     let seconds = ts.trunc() as i64;
     let nanos = ((ts.fract() * 1_000_000_000.0).round() as u32).min(999_999_999);
@@ -447,6 +1147,146 @@ mod tests {
         }
     }
 
+    fn battery_sample(ts: f64, percentage: f64, status: &str) -> Sample {
+        Sample {
+            ts,
+            percentage: Some(percentage),
+            capacity_pct: None,
+            health_pct: None,
+            energy_now_wh: None,
+            energy_full_wh: None,
+            energy_full_design_wh: None,
+            status: Some(status.to_string()),
+            source_path: "BAT0".to_string(),
+        }
+    }
+
+    #[test]
+    fn depletion_forecast_projects_the_discharge_trend() {
+        // Charging, then losing 1% every 60 seconds from 80%.
+        let mut samples = vec![battery_sample(0.0, 70.0, "Charging")];
+        for minute in 1..=10 {
+            let pct = 80.0 - (minute - 1) as f64;
+            samples.push(battery_sample(minute as f64 * 60.0, pct, "Discharging"));
+        }
+        let forecast = depletion_forecast(&samples).unwrap();
+        assert_eq!(forecast.start, (ts_to_datetime(600.0).unwrap(), 71.0));
+        assert_eq!(
+            forecast.empty_at,
+            ts_to_datetime(600.0 + 71.0 * 60.0).unwrap()
+        );
+
+        samples.push(battery_sample(660.0, 70.0, "Charging"));
+        assert_eq!(depletion_forecast(&samples), None);
+
+        let too_short = &samples[1..3];
+        assert_eq!(depletion_forecast(too_short), None);
+    }
+
+    #[test]
+    fn custom_theme_reads_configured_colors() {
+        let config = GraphConfig {
+            theme: GraphTheme::Custom,
+            background: Some("#101820".to_string()),
+            colors: vec!["ff8800".to_string()],
+            ..GraphConfig::default()
+        };
+        let style = GraphStyle::from_config(&config, None).unwrap();
+        assert_eq!(style.background, RGBColor(16, 24, 32));
+        assert_eq!(style.foreground, BLACK);
+        assert_eq!(style.color(3), RGBColor(255, 136, 0));
+
+        let light = GraphStyle::from_config(&config, Some(GraphTheme::Light)).unwrap();
+        assert_eq!(light.background, WHITE);
+
+        let bad = GraphConfig {
+            grid: Some("#12345".to_string()),
+            ..config
+        };
+        assert!(GraphStyle::from_config(&bad, None).is_err());
+    }
+
+    #[test]
+    fn status_changes_are_labelled() {
+        let mut unknown = battery_sample(120.0, 49.0, "Charging");
+        unknown.status = None;
+        let samples = vec![
+            battery_sample(0.0, 50.0, "Discharging"),
+            battery_sample(60.0, 49.0, "Discharging"),
+            unknown,
+            battery_sample(180.0, 52.0, "Charging"),
+            battery_sample(240.0, 100.0, "Full"),
+            battery_sample(300.0, 99.0, "Discharging"),
+        ];
+        let labels: Vec<(f64, String)> = status_changes(&samples)
+            .into_iter()
+            .map(|(ts, label)| (ts.timestamp() as f64, label))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                (180.0, "Plugged in".to_string()),
+                (240.0, "Full".to_string()),
+                (300.0, "Unplugged".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lttb_keeps_endpoints_and_spikes() {
+        let mut points: SeriesPoints = (0..1000)
+            .map(|i| (ts_to_datetime(i as f64 * 60.0).unwrap(), 10.0))
+            .collect();
+        points[517].1 = 90.0;
+        let sampled = lttb(&points, 50);
+        assert_eq!(sampled.len(), 50);
+        assert_eq!(sampled.first(), points.first());
+        assert_eq!(sampled.last(), points.last());
+        assert!(sampled.contains(&points[517]));
+        assert!(sampled.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+        assert_eq!(lttb(&points, 0).len(), 1000);
+        assert_eq!(lttb(&points[..10], 50).len(), 10);
+    }
+
+    #[test]
+    fn charging_periods_cover_each_run() {
+        let samples = vec![
+            battery_sample(0.0, 50.0, "Discharging"),
+            battery_sample(60.0, 49.0, "Charging"),
+            battery_sample(120.0, 55.0, "Charging"),
+            battery_sample(180.0, 60.0, "Full"),
+            battery_sample(240.0, 60.0, "Charging"),
+            battery_sample(300.0, 62.0, "Charging"),
+        ];
+        let at = |ts: f64| ts_to_datetime(ts).unwrap();
+        assert_eq!(
+            charging_periods(&samples),
+            vec![(at(60.0), at(180.0)), (at(240.0), at(300.0))]
+        );
+    }
+
+    #[test]
+    fn annotation_markers_stay_within_chart_range() {
+        let annotations = vec![
+            Annotation {
+                ts: 10.0,
+                text: "before".to_string(),
+            },
+            Annotation {
+                ts: 50.0,
+                text: "inside".to_string(),
+            },
+        ];
+        let markers = annotation_markers(
+            &annotations,
+            ts_to_datetime(20.0).unwrap(),
+            ts_to_datetime(100.0).unwrap(),
+        );
+        assert_eq!(markers.len(), 1);
+        assert_eq!(markers[0].1, "inside");
+    }
+
     #[test]
     fn aggregate_metric_series_is_per_source() {
         let metrics = vec![
@@ -0,0 +1,68 @@
+//! Stand-in for `graph` in builds without the `graphs` feature: the same API, where asking
+//! for a PNG is an error and HTML reports simply go without one.
+
+use std::path::Path;
+
+use anyhow::{bail, Result};
+
+use crate::cli::ReportPreset;
+use crate::cli_helpers::Histogram;
+use crate::config::{GraphConfig, GraphTheme};
+use crate::db::{Annotation, Sample};
+use crate::locale::Formats;
+use crate::metrics::MetricSample;
+use crate::timeframe::Timeframe;
+
+const DISABLED: &str = "symmetri was built without graphs; rebuild with `--features graphs`";
+
+#[derive(Debug, Clone, Default)]
+pub struct GraphStyle;
+
+impl GraphStyle {
+    pub fn from_config(_config: &GraphConfig, _theme: Option<GraphTheme>) -> Result<Self> {
+        Ok(GraphStyle)
+    }
+
+    pub fn with_max_points(self, _max_points: usize) -> Self {
+        self
+    }
+
+    pub fn with_formats(self, _formats: Formats) -> Self {
+        self
+    }
+}
+
+pub fn render_plot(
+    _battery_samples: &[Sample],
+    _metrics: &[MetricSample],
+    _annotations: &[Annotation],
+    _presets: &[ReportPreset],
+    _timeframe: &Timeframe,
+    _output: &Path,
+    _style: &GraphStyle,
+) -> Result<()> {
+    bail!(DISABLED)
+}
+
+/// Never draws, so HTML reports leave the graph out.
+pub fn draw_plot(
+    _battery_samples: &[Sample],
+    _metrics: &[MetricSample],
+    _annotations: &[Annotation],
+    _presets: &[ReportPreset],
+    _timeframe: &Timeframe,
+    _output: &Path,
+    _style: &GraphStyle,
+) -> Result<bool> {
+    Ok(false)
+}
+
+pub fn render_histogram(
+    _histogram: &Histogram,
+    _title: &str,
+    _x_desc: &str,
+    _output: &Path,
+    _style: &GraphStyle,
+) -> Result<()> {
+    bail!(DISABLED)
+}
@@ -0,0 +1,34 @@
+//! Gzip streams for `db backup --gzip`, `db restore` and `snapshot` tarballs. Without the
+//! `gzip` feature both directions fail with a hint.
+
+use std::io::{Read, Write};
+
+use anyhow::Result;
+
+/// Compresses `input` into `output` and returns `output` once everything is written.
+#[cfg(feature = "gzip")]
+pub fn compress<W: Write>(mut input: impl Read, output: W) -> Result<W> {
+    let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+    std::io::copy(&mut input, &mut encoder)?;
+    Ok(encoder.finish()?)
+}
+
+/// Decompresses the gzip stream `input` into `output`.
+#[cfg(feature = "gzip")]
+pub fn decompress(input: impl Read, output: &mut impl Write) -> Result<()> {
+    std::io::copy(&mut flate2::read::GzDecoder::new(input), output)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "gzip"))]
+const DISABLED: &str = "symmetri was built without gzip; rebuild with `--features gzip`";
+
+#[cfg(not(feature = "gzip"))]
+pub fn compress<W: Write>(_input: impl Read, _output: W) -> Result<W> {
+    anyhow::bail!(DISABLED)
+}
+
+#[cfg(not(feature = "gzip"))]
+pub fn decompress(_input: impl Read, _output: &mut impl Write) -> Result<()> {
+    anyhow::bail!(DISABLED)
+}
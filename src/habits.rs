@@ -0,0 +1,195 @@
+//! Charging habits for the report: how deep discharges go, how long the battery sits in
+//! each charge band, and how often it gets short top-ups. Lithium cells last longest
+//! kept between 20% and 80% and cycled shallowly.
+
+use crate::db::Sample;
+
+/// Below and above this, time counts outside the 20–80% band.
+const LOW_PCT: f64 = 20.0;
+const HIGH_PCT: f64 = 80.0;
+/// At or above this (or reported `Full`) the battery counts as full.
+const FULL_PCT: f64 = 99.0;
+/// A charge adding fewer points than this is a micro-charge (a top-up).
+pub const MICRO_CHARGE_PCT: f64 = 10.0;
+/// Longer gaps between readings mean the machine was off or asleep; they are not
+/// counted as time in any band.
+const MAX_GAP_SECONDS: f64 = 3600.0;
+const WEEK_SECONDS: f64 = 7.0 * 86_400.0;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChargingHabits {
+    pub discharges: usize,
+    /// Average and largest drop from the level at unplugging, in points.
+    pub avg_depth_pct: Option<f64>,
+    pub max_depth_pct: Option<f64>,
+    pub charges: usize,
+    pub micro_charges: usize,
+    /// Micro-charges per 7 days of the covered span; `None` under a day of data.
+    pub micro_charges_per_week: Option<f64>,
+    pub hours_below_20: f64,
+    pub hours_20_80: f64,
+    pub hours_above_80: f64,
+    pub hours_full: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Charging,
+    Discharging,
+    Idle,
+}
+
+fn state_of(sample: &Sample) -> State {
+    match sample
+        .status
+        .as_deref()
+        .map(str::to_ascii_lowercase)
+        .as_deref()
+    {
+        Some("charging") => State::Charging,
+        Some("discharging") => State::Discharging,
+        _ => State::Idle,
+    }
+}
+
+fn is_full(sample: &Sample, pct: f64) -> bool {
+    pct >= FULL_PCT
+        || sample
+            .status
+            .as_deref()
+            .is_some_and(|status| status.eq_ignore_ascii_case("full"))
+}
+
+impl ChargingHabits {
+    /// Habits over `samples` (aggregated, oldest first). `None` without two readings
+    /// carrying a percentage.
+    pub fn compute(samples: &[Sample]) -> Option<Self> {
+        let readings: Vec<(&Sample, f64)> = samples
+            .iter()
+            .filter_map(|sample| sample.percentage.map(|pct| (sample, pct)))
+            .collect();
+        if readings.len() < 2 {
+            return None;
+        }
+        let mut habits = ChargingHabits::default();
+
+        for pair in readings.windows(2) {
+            let [(sample, pct), (next, _)] = pair else {
+                continue;
+            };
+            let hours = (next.ts - sample.ts) / 3600.0;
+            if hours <= 0.0 || hours * 3600.0 > MAX_GAP_SECONDS {
+                continue;
+            }
+            let band = if is_full(sample, *pct) {
+                &mut habits.hours_full
+            } else if *pct > HIGH_PCT {
+                &mut habits.hours_above_80
+            } else if *pct >= LOW_PCT {
+                &mut habits.hours_20_80
+            } else {
+                &mut habits.hours_below_20
+            };
+            *band += hours;
+        }
+
+        // Runs of one status; each starts from the level of the reading before it.
+        let mut depths = Vec::new();
+        let mut start = 0;
+        while start < readings.len() {
+            let state = state_of(readings[start].0);
+            let end = readings[start..]
+                .iter()
+                .position(|(sample, _)| state_of(sample) != state)
+                .map_or(readings.len(), |offset| start + offset);
+            let from = readings[start.saturating_sub(1)].1;
+            let run = &readings[start..end];
+            match state {
+                State::Discharging => {
+                    let lowest = run.iter().map(|(_, pct)| *pct).fold(from, f64::min);
+                    depths.push(from - lowest);
+                }
+                State::Charging => {
+                    let highest = run.iter().map(|(_, pct)| *pct).fold(from, f64::max);
+                    habits.charges += 1;
+                    if highest - from < MICRO_CHARGE_PCT {
+                        habits.micro_charges += 1;
+                    }
+                }
+                State::Idle => {}
+            }
+            start = end;
+        }
+        habits.discharges = depths.len();
+        if !depths.is_empty() {
+            habits.avg_depth_pct = Some(depths.iter().sum::<f64>() / depths.len() as f64);
+            habits.max_depth_pct = depths.iter().copied().reduce(f64::max);
+        }
+        let span = readings[readings.len() - 1].0.ts - readings[0].0.ts;
+        if span >= 86_400.0 {
+            habits.micro_charges_per_week = Some(habits.micro_charges as f64 * WEEK_SECONDS / span);
+        }
+        Some(habits)
+    }
+
+    pub fn tracked_hours(&self) -> f64 {
+        self.hours_below_20 + self.hours_20_80 + self.hours_above_80 + self.hours_full
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(ts: f64, pct: f64, status: &str) -> Sample {
+        Sample {
+            ts,
+            percentage: Some(pct),
+            capacity_pct: None,
+            health_pct: None,
+            energy_now_wh: None,
+            energy_full_wh: None,
+            energy_full_design_wh: None,
+            status: Some(status.to_string()),
+            source_path: "BAT0".to_string(),
+        }
+    }
+
+    #[test]
+    fn habits_count_depths_bands_and_top_ups() {
+        let hour = 3600.0;
+        let samples = vec![
+            sample(0.0, 100.0, "Full"),
+            sample(hour, 100.0, "Full"),
+            // Unplugged at 100%, down to 40%.
+            sample(2.0 * hour, 90.0, "Discharging"),
+            sample(3.0 * hour, 60.0, "Discharging"),
+            sample(4.0 * hour, 40.0, "Discharging"),
+            // A full charge back to 75%, then a 5-point top-up after a short drain.
+            sample(5.0 * hour, 60.0, "Charging"),
+            sample(6.0 * hour, 75.0, "Charging"),
+            sample(7.0 * hour, 65.0, "Discharging"),
+            sample(8.0 * hour, 70.0, "Charging"),
+            // The machine was off for a day.
+            sample(32.0 * hour, 15.0, "Discharging"),
+            sample(33.0 * hour, 10.0, "Discharging"),
+        ];
+        let habits = ChargingHabits::compute(&samples).unwrap();
+        assert_eq!(habits.discharges, 3);
+        // 100 → 40, 75 → 65, 70 → 10 (across the gap).
+        assert!((habits.avg_depth_pct.unwrap() - (60.0 + 10.0 + 60.0) / 3.0).abs() < 1e-9);
+        assert_eq!(habits.max_depth_pct, Some(60.0));
+        assert_eq!(habits.charges, 2);
+        assert_eq!(habits.micro_charges, 1);
+        let per_week = habits.micro_charges_per_week.unwrap();
+        assert!((per_week - 7.0 * 24.0 / 33.0).abs() < 1e-9);
+
+        assert_eq!(habits.hours_full, 2.0);
+        assert_eq!(habits.hours_above_80, 1.0);
+        assert_eq!(habits.hours_20_80, 5.0);
+        assert_eq!(habits.hours_below_20, 1.0);
+        assert_eq!(habits.tracked_hours(), 9.0);
+
+        assert!(ChargingHabits::compute(&samples[..1]).is_none());
+    }
+}
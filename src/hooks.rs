@@ -0,0 +1,247 @@
+use std::process::{Child, Command};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::config::HooksConfig;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatteryEvent {
+    LowBattery,
+    Critical,
+    Unplug,
+    Full,
+}
+
+impl BatteryEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BatteryEvent::LowBattery => "low_battery",
+            BatteryEvent::Critical => "critical",
+            BatteryEvent::Unplug => "unplug",
+            BatteryEvent::Full => "full",
+        }
+    }
+
+    fn command<'a>(&self, config: &'a HooksConfig) -> Option<&'a str> {
+        match self {
+            BatteryEvent::LowBattery => config.on_low_battery.as_deref(),
+            BatteryEvent::Critical => config.on_critical.as_deref(),
+            BatteryEvent::Unplug => config.on_unplug.as_deref(),
+            BatteryEvent::Full => config.on_full.as_deref(),
+        }
+    }
+}
+
+/// Battery state remembered between collections so hooks fire on transitions only.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HookState {
+    pub percentage: Option<f64>,
+    pub status: Option<String>,
+}
+
+impl HookState {
+    fn status_is(&self, expected: &str) -> bool {
+        self.status
+            .as_deref()
+            .map(|s| s.eq_ignore_ascii_case(expected))
+            .unwrap_or(false)
+    }
+}
+
+fn crossed_below(previous: Option<&HookState>, current: &HookState, threshold: f64) -> bool {
+    let Some(now) = current.percentage else {
+        return false;
+    };
+    let before = previous.and_then(|p| p.percentage);
+    now < threshold && before.map(|b| b >= threshold).unwrap_or(true)
+}
+
+pub fn detect_events(
+    previous: Option<&HookState>,
+    current: &HookState,
+    config: &HooksConfig,
+) -> Vec<BatteryEvent> {
+    let mut events = Vec::new();
+    let discharging = current.status_is("discharging");
+    if discharging && crossed_below(previous, current, config.low_percent) {
+        events.push(BatteryEvent::LowBattery);
+    }
+    if discharging && crossed_below(previous, current, config.critical_percent) {
+        events.push(BatteryEvent::Critical);
+    }
+    if let Some(previous) = previous {
+        if discharging && !previous.status_is("discharging") && previous.status.is_some() {
+            events.push(BatteryEvent::Unplug);
+        }
+        if current.status_is("full") && !previous.status_is("full") {
+            events.push(BatteryEvent::Full);
+        }
+    }
+    events
+}
+
+/// Starts the configured command for each event. With `detach` (the collection loop) the
+/// hooks are left running, so a slow one does not stall collection; otherwise they get
+/// `timeout_seconds` to finish, since a oneshot service's processes die when it exits.
+pub fn run_hooks(events: &[BatteryEvent], current: &HookState, config: &HooksConfig, detach: bool) {
+    let mut running = Vec::new();
+    for event in events {
+        let Some(command) = event.command(config) else {
+            continue;
+        };
+        info!("Running {} hook: {command}", event.as_str());
+        let spawned = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("SYMMETRI_EVENT", event.as_str())
+            .env(
+                "SYMMETRI_PERCENT",
+                current
+                    .percentage
+                    .map(|p| format!("{p:.1}"))
+                    .unwrap_or_default(),
+            )
+            .env("SYMMETRI_STATUS", current.status.as_deref().unwrap_or(""))
+            .spawn();
+        match spawned {
+            Ok(mut child) if detach => {
+                thread::spawn(move || child.wait());
+            }
+            Ok(child) => running.push((*event, child)),
+            Err(err) => warn!("Failed to run {} hook: {err}", event.as_str()),
+        }
+    }
+    wait_for(
+        running,
+        Duration::from_secs_f64(config.timeout_seconds.max(0.0)),
+    );
+}
+
+/// Waits for the hooks together, killing those still running once `timeout` elapses.
+fn wait_for(mut running: Vec<(BatteryEvent, Child)>, timeout: Duration) {
+    let started = Instant::now();
+    loop {
+        running.retain_mut(|(event, child)| match child.try_wait() {
+            Ok(Some(status)) => {
+                if !status.success() {
+                    warn!("{} hook exited with {status}", event.as_str());
+                }
+                false
+            }
+            Ok(None) => true,
+            Err(err) => {
+                warn!("Failed to wait for {} hook: {err}", event.as_str());
+                false
+            }
+        });
+        if running.is_empty() {
+            return;
+        }
+        if started.elapsed() >= timeout {
+            for (event, mut child) in running {
+                warn!(
+                    "Killed {} hook after {:.0}s",
+                    event.as_str(),
+                    timeout.as_secs_f64()
+                );
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            return;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(percentage: f64, status: &str) -> HookState {
+        HookState {
+            percentage: Some(percentage),
+            status: Some(status.to_string()),
+        }
+    }
+
+    #[test]
+    fn low_and_critical_fire_once_when_crossing() {
+        let config = HooksConfig::default();
+        let above = state(25.0, "Discharging");
+        let low = state(19.0, "Discharging");
+        let lower = state(18.0, "Discharging");
+        let critical = state(4.0, "Discharging");
+
+        assert_eq!(
+            detect_events(Some(&above), &low, &config),
+            vec![BatteryEvent::LowBattery]
+        );
+        assert!(detect_events(Some(&low), &lower, &config).is_empty());
+        assert_eq!(
+            detect_events(Some(&lower), &critical, &config),
+            vec![BatteryEvent::Critical]
+        );
+        assert!(detect_events(Some(&above), &state(10.0, "Charging"), &config).is_empty());
+    }
+
+    #[test]
+    fn unplug_and_full_follow_status_changes() {
+        let config = HooksConfig::default();
+        assert_eq!(
+            detect_events(
+                Some(&state(80.0, "Charging")),
+                &state(80.0, "Discharging"),
+                &config
+            ),
+            vec![BatteryEvent::Unplug]
+        );
+        assert_eq!(
+            detect_events(
+                Some(&state(99.0, "Charging")),
+                &state(100.0, "Full"),
+                &config
+            ),
+            vec![BatteryEvent::Full]
+        );
+        assert!(detect_events(None, &state(100.0, "Full"), &config).is_empty());
+    }
+
+    #[test]
+    fn single_shot_hooks_finish_before_returning() {
+        let tmp = tempfile::tempdir().unwrap();
+        let marker = tmp.path().join("ran");
+        let config = HooksConfig {
+            on_full: Some(format!(
+                "sleep 0.1; echo \"$SYMMETRI_EVENT\" > {}",
+                marker.display()
+            )),
+            ..HooksConfig::default()
+        };
+
+        run_hooks(&[BatteryEvent::Full], &state(100.0, "Full"), &config, false);
+        assert_eq!(std::fs::read_to_string(&marker).unwrap(), "full\n");
+    }
+
+    #[test]
+    fn hung_single_shot_hooks_are_killed() {
+        let config = HooksConfig {
+            on_unplug: Some("sleep 30".to_string()),
+            timeout_seconds: 0.2,
+            ..HooksConfig::default()
+        };
+
+        let started = Instant::now();
+        run_hooks(
+            &[BatteryEvent::Unplug],
+            &state(80.0, "Discharging"),
+            &config,
+            false,
+        );
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
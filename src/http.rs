@@ -0,0 +1,185 @@
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+/// `http://host[:port]/path?query` split into (host:port, path).
+fn split_url(url: &str) -> Result<(String, String)> {
+    let Some(rest) = url.strip_prefix("http://") else {
+        anyhow::bail!("Only http:// URLs are supported (got {url})");
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let authority = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{authority}:80")
+    };
+    Ok((authority, path.to_string()))
+}
+
+/// A bare HTTP/1.1 POST for the push exporters and webhooks, so they need no HTTP client
+/// dependency; `https://` URLs go through `curl` rather than a TLS library. Fails on
+/// anything but a 2xx answer.
+pub fn post(
+    url: &str,
+    content_type: &str,
+    headers: &[(String, String)],
+    body: &str,
+    timeout: Duration,
+) -> Result<()> {
+    if url.starts_with("https://") {
+        return post_with_curl(url, content_type, headers, body, timeout);
+    }
+    let (authority, path) = split_url(url)?;
+    let address = authority
+        .to_socket_addrs()?
+        .next()
+        .with_context(|| format!("Cannot resolve {authority}"))?;
+    let mut stream = TcpStream::connect_timeout(&address, timeout)
+        .with_context(|| format!("Cannot connect to {authority}"))?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    let host = authority.trim_end_matches(":80");
+    let extra: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}\r\n"))
+        .collect();
+    let request = format!(
+        "POST {path} HTTP/1.1\r\nHost: {host}\r\n{extra}Content-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes())?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let status = response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .context("Malformed HTTP answer")?;
+    if !(200..300).contains(&status) {
+        let detail = response.split("\r\n\r\n").nth(1).unwrap_or("").trim();
+        anyhow::bail!("{url} answered {status}: {detail}");
+    }
+    Ok(())
+}
+
+/// curl's command line: fixed flags only. The request itself goes in on stdin as a config
+/// file (see [`curl_config`]), so tokens and webhook URLs never show in the process list.
+fn curl_args(timeout: Duration) -> Vec<String> {
+    [
+        "--silent",
+        "--show-error",
+        "--fail-with-body",
+        "--request",
+        "POST",
+        "--max-time",
+    ]
+    .into_iter()
+    .map(str::to_string)
+    .chain([
+        format!("{:.1}", timeout.as_secs_f64()),
+        "--config".into(),
+        "-".into(),
+    ])
+    .collect()
+}
+
+/// A quoted curl config value; inside quotes curl reads `\\`, `\"`, `\n`, `\r` and `\t`.
+fn config_value(raw: &str) -> String {
+    let mut quoted = String::with_capacity(raw.len() + 2);
+    quoted.push('"');
+    for c in raw.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The request as a curl config file. The body goes through `data-raw`, which unlike
+/// `data-binary` never reads a file for a body starting with `@`.
+fn curl_config(url: &str, content_type: &str, headers: &[(String, String)], body: &str) -> String {
+    let mut config = format!("url = {}\n", config_value(url));
+    let content_type = format!("Content-Type: {content_type}");
+    let extra = headers
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}"));
+    for header in std::iter::once(content_type).chain(extra) {
+        config.push_str(&format!("header = {}\n", config_value(&header)));
+    }
+    config.push_str(&format!("data-raw = {}\n", config_value(body)));
+    config
+}
+
+fn post_with_curl(
+    url: &str,
+    content_type: &str,
+    headers: &[(String, String)],
+    body: &str,
+    timeout: Duration,
+) -> Result<()> {
+    let mut child = Command::new("curl")
+        .args(curl_args(timeout))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Cannot run curl, which posting to https:// URLs needs")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(curl_config(url, content_type, headers, body).as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let detail = [output.stderr, output.stdout]
+            .iter()
+            .map(|text| String::from_utf8_lossy(text).trim().to_string())
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(": ");
+        anyhow::bail!("{url} failed: {detail}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curl_gets_tokens_and_urls_on_stdin() {
+        let url = "https://hooks.example/services/T0/secret-path";
+        let headers = [("Authorization".to_string(), "Bearer s3cret".to_string())];
+
+        let args = curl_args(Duration::from_secs(5));
+        assert!(!args
+            .iter()
+            .any(|arg| arg.contains("s3cret") || arg.contains("secret-path")));
+        assert!(args.ends_with(&["--config".to_string(), "-".to_string()]));
+        assert_eq!(
+            curl_config(url, "application/json", &headers, "{}"),
+            "url = \"https://hooks.example/services/T0/secret-path\"\n\
+             header = \"Content-Type: application/json\"\n\
+             header = \"Authorization: Bearer s3cret\"\n\
+             data-raw = \"{}\"\n"
+        );
+    }
+
+    #[test]
+    fn curl_config_values_are_escaped() {
+        assert_eq!(
+            config_value("say \"hi\"\\\nbye\t"),
+            r#""say \"hi\"\\\nbye\t""#
+        );
+    }
+}
@@ -0,0 +1,317 @@
+#[cfg(feature = "csv")]
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, Context, Result};
+#[cfg(feature = "csv")]
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::db::Sample;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ImportFormat {
+    /// UPower `history-charge-*.dat` files (`timestamp<TAB>percent<TAB>state`)
+    UpowerHistory,
+    /// CSV with a header row; columns are matched to sample fields by name
+    BatstatCsv,
+    /// A JSON array or JSON lines of sample objects
+    Json,
+}
+
+/// Parses exported history into samples stamped with `source` unless a row names its own.
+pub fn parse_import(format: ImportFormat, content: &str, source: &str) -> Result<Vec<Sample>> {
+    match format {
+        ImportFormat::UpowerHistory => parse_upower_history(content, source),
+        ImportFormat::BatstatCsv => parse_csv(content, source),
+        ImportFormat::Json => parse_json(content, source),
+    }
+}
+
+fn empty_sample(ts: f64, source: &str) -> Sample {
+    Sample {
+        ts,
+        percentage: None,
+        capacity_pct: None,
+        health_pct: None,
+        energy_now_wh: None,
+        energy_full_wh: None,
+        energy_full_design_wh: None,
+        status: None,
+        source_path: source.to_string(),
+    }
+}
+
+/// Maps UPower's state names onto the sysfs `status` values symmetri stores.
+fn upower_status(state: &str) -> Option<String> {
+    let status = match state {
+        "charging" => "Charging",
+        "discharging" | "empty" => "Discharging",
+        "fully-charged" => "Full",
+        "pending-charge" | "pending-discharge" => "Not charging",
+        _ => return None,
+    };
+    Some(status.to_string())
+}
+
+fn parse_upower_history(content: &str, source: &str) -> Result<Vec<Sample>> {
+    let mut samples = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() {
+            continue;
+        }
+        let parsed = match fields.as_slice() {
+            [ts, value, rest @ ..] => ts
+                .parse::<f64>()
+                .ok()
+                .zip(value.parse::<f64>().ok())
+                .map(|(ts, value)| (ts, value, rest.first().copied())),
+            _ => None,
+        };
+        let (ts, value, state) = parsed.ok_or_else(|| {
+            anyhow!(
+                "line {}: expected `timestamp value state`, got {line:?}",
+                index + 1
+            )
+        })?;
+        let mut sample = empty_sample(ts, source);
+        sample.percentage = Some(value);
+        sample.status = state.and_then(upower_status);
+        samples.push(sample);
+    }
+    Ok(samples)
+}
+
+/// Accepts Unix seconds, RFC 3339, or `YYYY-MM-DD HH:MM[:SS]` in local time.
+#[cfg(feature = "csv")]
+fn parse_timestamp(raw: &str) -> Option<f64> {
+    let raw = raw.trim();
+    if let Ok(ts) = raw.parse::<f64>() {
+        return Some(ts);
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
+        return Some(dt.timestamp() as f64);
+    }
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(raw, fmt).ok())
+        .and_then(|naive| Local.from_local_datetime(&naive).earliest())
+        .map(|dt| dt.timestamp() as f64)
+}
+
+#[cfg(feature = "csv")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum Field {
+    Ts,
+    Percentage,
+    Capacity,
+    Health,
+    EnergyNow,
+    EnergyFull,
+    EnergyFullDesign,
+    Status,
+    Source,
+}
+
+#[cfg(feature = "csv")]
+fn field_for_column(name: &str) -> Option<Field> {
+    let field = match name.trim().to_ascii_lowercase().as_str() {
+        "ts" | "timestamp" | "time" | "date" => Field::Ts,
+        "percentage" | "percent" | "pct" | "charge" | "level" => Field::Percentage,
+        "capacity" | "capacity_pct" => Field::Capacity,
+        "health" | "health_pct" => Field::Health,
+        "energy_now_wh" | "energy_now" | "energy_wh" => Field::EnergyNow,
+        "energy_full_wh" | "energy_full" => Field::EnergyFull,
+        "energy_full_design_wh" | "energy_full_design" => Field::EnergyFullDesign,
+        "status" | "state" => Field::Status,
+        "source" | "source_path" | "battery" => Field::Source,
+        _ => return None,
+    };
+    Some(field)
+}
+
+/// Fills in the percentage from energy readings when the export only has those.
+fn finish_sample(mut sample: Sample) -> Sample {
+    if sample.percentage.is_none() {
+        if let (Some(now), Some(full)) = (sample.energy_now_wh, sample.energy_full_wh) {
+            if full > 0.0 {
+                sample.percentage = Some(now / full * 100.0);
+            }
+        }
+    }
+    sample
+}
+
+#[cfg(feature = "csv")]
+fn parse_csv(content: &str, source: &str) -> Result<Vec<Sample>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+    let columns: HashMap<Field, usize> = reader
+        .headers()?
+        .iter()
+        .enumerate()
+        .filter_map(|(index, name)| field_for_column(name).map(|field| (field, index)))
+        .collect();
+    let Some(&ts_column) = columns.get(&Field::Ts) else {
+        bail!("CSV header needs a timestamp column (ts, timestamp, time or date)");
+    };
+
+    let mut samples = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        // Header is line 1.
+        let line = index + 2;
+        let record = record.with_context(|| format!("line {line}"))?;
+        let text = |field: Field| {
+            columns
+                .get(&field)
+                .and_then(|&column| record.get(column))
+                .filter(|value| !value.is_empty())
+        };
+        let number = |field: Field| -> Result<Option<f64>> {
+            text(field)
+                .map(|raw| {
+                    raw.trim_end_matches('%')
+                        .parse::<f64>()
+                        .with_context(|| format!("line {line}: invalid number {raw:?}"))
+                })
+                .transpose()
+        };
+        let raw_ts = record.get(ts_column).unwrap_or_default();
+        let ts = parse_timestamp(raw_ts)
+            .ok_or_else(|| anyhow!("line {line}: invalid timestamp {raw_ts:?}"))?;
+        samples.push(finish_sample(Sample {
+            ts,
+            percentage: number(Field::Percentage)?,
+            capacity_pct: number(Field::Capacity)?,
+            health_pct: number(Field::Health)?,
+            energy_now_wh: number(Field::EnergyNow)?,
+            energy_full_wh: number(Field::EnergyFull)?,
+            energy_full_design_wh: number(Field::EnergyFullDesign)?,
+            status: text(Field::Status).map(str::to_string),
+            source_path: text(Field::Source).unwrap_or(source).to_string(),
+        }));
+    }
+    Ok(samples)
+}
+
+#[cfg(not(feature = "csv"))]
+fn parse_csv(_content: &str, _source: &str) -> Result<Vec<Sample>> {
+    bail!("symmetri was built without csv; rebuild with `--features csv`")
+}
+
+/// Field names match `Sample` (and `collect --dry-run` output); only `ts` is required.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonSample {
+    ts: f64,
+    #[serde(default)]
+    percentage: Option<f64>,
+    #[serde(default)]
+    capacity_pct: Option<f64>,
+    #[serde(default)]
+    health_pct: Option<f64>,
+    #[serde(default)]
+    energy_now_wh: Option<f64>,
+    #[serde(default)]
+    energy_full_wh: Option<f64>,
+    #[serde(default)]
+    energy_full_design_wh: Option<f64>,
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    source_path: Option<String>,
+}
+
+impl JsonSample {
+    fn into_sample(self, source: &str) -> Sample {
+        finish_sample(Sample {
+            ts: self.ts,
+            percentage: self.percentage,
+            capacity_pct: self.capacity_pct,
+            health_pct: self.health_pct,
+            energy_now_wh: self.energy_now_wh,
+            energy_full_wh: self.energy_full_wh,
+            energy_full_design_wh: self.energy_full_design_wh,
+            status: self.status,
+            source_path: self
+                .source_path
+                .filter(|path| !path.is_empty())
+                .unwrap_or_else(|| source.to_string()),
+        })
+    }
+}
+
+fn parse_json(content: &str, source: &str) -> Result<Vec<Sample>> {
+    let records: Vec<JsonSample> = if content.trim_start().starts_with('[') {
+        serde_json::from_str(content).context("invalid JSON array")?
+    } else {
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line).with_context(|| format!("line {}", index + 1))
+            })
+            .collect::<Result<_>>()?
+    };
+    Ok(records
+        .into_iter()
+        .map(|record| record.into_sample(source))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upower_history_maps_states() {
+        let content = "1700000000\t85.000\tdischarging\n1700000600\t100.000\tfully-charged\n";
+        let samples = parse_import(ImportFormat::UpowerHistory, content, "upower:BAT0").unwrap();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].ts, 1_700_000_000.0);
+        assert_eq!(samples[0].percentage, Some(85.0));
+        assert_eq!(samples[0].status.as_deref(), Some("Discharging"));
+        assert_eq!(samples[1].status.as_deref(), Some("Full"));
+        assert_eq!(samples[1].source_path, "upower:BAT0");
+
+        assert!(parse_import(ImportFormat::UpowerHistory, "garbage\n", "x").is_err());
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn csv_columns_are_matched_by_name() {
+        let content = "\
+Timestamp,Battery,Energy_Now,Energy_Full,State,notes
+1700000000,BAT1,20.5,41,Discharging,ignored
+2023-11-14T22:23:20Z,,30,40,,
+";
+        let samples = parse_import(ImportFormat::BatstatCsv, content, "legacy.csv").unwrap();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].source_path, "BAT1");
+        assert_eq!(samples[0].percentage, Some(50.0));
+        assert_eq!(samples[0].status.as_deref(), Some("Discharging"));
+        assert_eq!(samples[1].ts, 1_700_000_600.0);
+        assert_eq!(samples[1].source_path, "legacy.csv");
+        assert_eq!(samples[1].status, None);
+
+        let missing_ts = parse_import(ImportFormat::BatstatCsv, "percent\n50\n", "x");
+        assert!(missing_ts.is_err());
+    }
+
+    #[test]
+    fn json_accepts_arrays_and_lines() {
+        let array = r#"[{"ts": 1.0, "percentage": 40.0, "source_path": "BAT0"}]"#;
+        let lines = "{\"ts\": 1.0, \"percentage\": 40.0}\n\n{\"ts\": 2.0}\n";
+        let from_array = parse_import(ImportFormat::Json, array, "import").unwrap();
+        let from_lines = parse_import(ImportFormat::Json, lines, "import").unwrap();
+        assert_eq!(from_array[0].source_path, "BAT0");
+        assert_eq!(from_lines.len(), 2);
+        assert_eq!(from_lines[0].percentage, Some(40.0));
+        assert_eq!(from_lines[1].source_path, "import");
+
+        assert!(parse_import(ImportFormat::Json, r#"{"ts": 1, "typo": 2}"#, "x").is_err());
+    }
+}
@@ -0,0 +1,212 @@
+use std::path::Path;
+#[cfg(feature = "influx")]
+use std::time::Duration;
+
+#[cfg(feature = "influx")]
+use anyhow::Result;
+use serde_json::Value;
+
+#[cfg(feature = "influx")]
+use crate::config::InfluxConfig;
+use crate::db::Sample;
+#[cfg(feature = "influx")]
+use crate::http;
+use crate::metrics::MetricSample;
+
+/// Escapes measurement names (`escape_equals` false) and tag keys/values.
+fn escape(raw: &str, escape_equals: bool) -> String {
+    let mut escaped = String::with_capacity(raw.len());
+    for c in raw.chars() {
+        if c == ',' || c == ' ' || (escape_equals && c == '=') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn string_field(raw: &str) -> String {
+    format!("\"{}\"", raw.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn line(
+    measurement: &str,
+    tags: &[(&str, &str)],
+    fields: &[(String, String)],
+    ts: f64,
+) -> Option<String> {
+    if fields.is_empty() {
+        return None;
+    }
+    let mut line = escape(measurement, false);
+    for (key, value) in tags.iter().filter(|(_, value)| !value.is_empty()) {
+        line.push_str(&format!(",{}={}", escape(key, true), escape(value, true)));
+    }
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("{}={value}", escape(key, true)))
+        .collect();
+    // Stored timestamps carry microseconds at best; more digits would be float noise.
+    let nanos = (ts * 1e6).round() as i64 * 1000;
+    Some(format!("{line} {} {nanos}", fields.join(",")))
+}
+
+/// `battery,source=BAT0 percentage=81.5,...,status="Discharging" <ns>`.
+pub fn battery_line(sample: &Sample) -> Option<String> {
+    let source = Path::new(&sample.source_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| sample.source_path.clone());
+    let mut fields: Vec<(String, String)> = [
+        ("percentage", sample.percentage),
+        ("capacity_pct", sample.capacity_pct),
+        ("health_pct", sample.health_pct),
+        ("energy_now_wh", sample.energy_now_wh),
+        ("energy_full_wh", sample.energy_full_wh),
+        ("energy_full_design_wh", sample.energy_full_design_wh),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| Some((key.to_string(), value?.to_string())))
+    .collect();
+    if let Some(status) = &sample.status {
+        fields.push(("status".to_string(), string_field(status)));
+    }
+    line("battery", &[("source", &source)], &fields, sample.ts)
+}
+
+/// The measurement is the metric kind, `value` its value; numeric and boolean details
+/// become extra fields so e.g. Wi-Fi bitrates stay queryable.
+pub fn metric_line(sample: &MetricSample) -> Option<String> {
+    let mut fields: Vec<(String, String)> = sample
+        .value
+        .filter(|value| value.is_finite())
+        .map(|value| ("value".to_string(), value.to_string()))
+        .into_iter()
+        .collect();
+    if let Value::Object(details) = &sample.details {
+        for (key, value) in details {
+            let field = match value {
+                Value::Number(number) => number.as_f64().map(|n| n.to_string()),
+                Value::Bool(flag) => Some(flag.to_string()),
+                _ => None,
+            };
+            if let Some(field) = field {
+                fields.push((key.clone(), field));
+            }
+        }
+    }
+    line(
+        sample.kind.as_str(),
+        &[
+            ("source", &sample.source),
+            ("unit", sample.unit.as_deref().unwrap_or_default()),
+        ],
+        &fields,
+        sample.ts,
+    )
+}
+
+/// Line protocol for a batch, one point per line with a trailing newline.
+pub fn to_line_protocol(batteries: &[Sample], metrics: &[MetricSample]) -> String {
+    batteries
+        .iter()
+        .filter_map(battery_line)
+        .chain(metrics.iter().filter_map(metric_line))
+        .map(|line| line + "\n")
+        .collect()
+}
+
+/// POSTs `body` to the configured write endpoint (InfluxDB `/api/v2/write`, or
+/// VictoriaMetrics `/write`).
+#[cfg(feature = "influx")]
+pub fn push(config: &InfluxConfig, body: &str) -> Result<()> {
+    let Some(url) = config.url.as_deref() else {
+        return Ok(());
+    };
+    if body.is_empty() {
+        return Ok(());
+    }
+    let headers: Vec<(String, String)> = config
+        .token
+        .iter()
+        .map(|token| ("Authorization".to_string(), format!("Token {token}")))
+        .collect();
+    http::post(
+        url,
+        "text/plain; charset=utf-8",
+        &headers,
+        body,
+        Duration::from_secs_f64(config.timeout_seconds.max(0.1)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::metrics::MetricKind;
+
+    #[test]
+    fn samples_are_encoded() {
+        let battery = Sample {
+            ts: 1.5,
+            percentage: Some(81.5),
+            capacity_pct: None,
+            health_pct: Some(90.0),
+            energy_now_wh: None,
+            energy_full_wh: None,
+            energy_full_design_wh: None,
+            status: Some("Not \"charging\"".to_string()),
+            source_path: "/sys/class/power_supply/BAT0".to_string(),
+        };
+        let wifi = MetricSample::new(
+            2.0,
+            MetricKind::Wifi,
+            "wlan0 home net",
+            Some(-61.0),
+            Some("dBm"),
+            json!({ "ssid": "home", "tx_bitrate_mbps": 866.7 }),
+        );
+        let body = to_line_protocol(&[battery], &[wifi]);
+        assert_eq!(
+            body,
+            "battery,source=BAT0 percentage=81.5,health_pct=90,status=\"Not \\\"charging\\\"\" 1500000000\n\
+             wifi,source=wlan0\\ home\\ net,unit=dBm value=-61,tx_bitrate_mbps=866.7 2000000000\n"
+        );
+    }
+
+    #[cfg(feature = "influx")]
+    #[test]
+    fn batches_are_pushed() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+
+        let body = "wifi,source=wlan0 value=-61 2000000000\n".to_string();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = String::new();
+            let mut chunk = [0u8; 4096];
+            while !request.ends_with("2000000000\n") {
+                let read = stream.read(&mut chunk).unwrap();
+                request.push_str(&String::from_utf8_lossy(&chunk[..read]));
+            }
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            request
+        });
+        let config = InfluxConfig {
+            url: Some(format!("http://127.0.0.1:{port}/api/v2/write?bucket=b")),
+            token: Some("secret".to_string()),
+            ..InfluxConfig::default()
+        };
+        push(&config, &body).unwrap();
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /api/v2/write?bucket=b HTTP/1.1\r\n"));
+        assert!(request.contains("Authorization: Token secret\r\n"));
+        assert!(request.ends_with(&body));
+    }
+}
@@ -0,0 +1,124 @@
+use std::fs;
+use std::time::Duration;
+
+use serde_json::json;
+
+use crate::metrics::{MetricKind, MetricSample};
+
+/// Config name for symmetri's own overhead metrics (`[collectors.internal] enabled = false`).
+pub const INTERNAL_COLLECTOR: &str = "internal";
+
+pub const CYCLE_SOURCE: &str = "cycle_duration";
+pub const DB_INSERT_SOURCE: &str = "db_insert";
+pub const RSS_SOURCE: &str = "rss";
+pub const ERRORS_SOURCE: &str = "collector_errors";
+const COLLECTOR_SOURCE_PREFIX: &str = "collector:";
+
+pub fn collector_source(name: &str) -> String {
+    format!("{COLLECTOR_SOURCE_PREFIX}{name}")
+}
+
+pub fn is_collector_source(source: &str) -> bool {
+    source.starts_with(COLLECTOR_SOURCE_PREFIX)
+}
+
+/// What one collection (or one batch of results in `collect --interval`) cost.
+#[derive(Debug, Clone, Default)]
+pub struct CycleStats {
+    /// Wall time of each finished collector run.
+    pub timings: Vec<(String, Duration)>,
+    /// Collectors that timed out or panicked.
+    pub failed: Vec<String>,
+    /// Start timestamp and duration of each completed cycle.
+    pub cycles: Vec<(f64, Duration)>,
+    pub db_insert: Option<Duration>,
+}
+
+impl CycleStats {
+    pub fn is_empty(&self) -> bool {
+        self.timings.is_empty()
+            && self.failed.is_empty()
+            && self.cycles.is_empty()
+            && self.db_insert.is_none()
+    }
+}
+
+fn millis_sample(ts: f64, source: &str, elapsed: Duration) -> MetricSample {
+    MetricSample::new(
+        ts,
+        MetricKind::Internal,
+        source,
+        Some(elapsed.as_secs_f64() * 1000.0),
+        Some("ms"),
+        serde_json::Value::Null,
+    )
+}
+
+/// Resident set size of this process, from `/proc/self/status`.
+pub fn process_rss_bytes() -> Option<f64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib: f64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024.0)
+}
+
+pub fn internal_samples(ts: f64, stats: &CycleStats) -> Vec<MetricSample> {
+    let mut samples: Vec<MetricSample> = stats
+        .timings
+        .iter()
+        .map(|(name, elapsed)| millis_sample(ts, &collector_source(name), *elapsed))
+        .collect();
+    if let Some(elapsed) = stats.db_insert {
+        samples.push(millis_sample(ts, DB_INSERT_SOURCE, elapsed));
+    }
+    if !stats.failed.is_empty() {
+        samples.push(MetricSample::new(
+            ts,
+            MetricKind::Internal,
+            ERRORS_SOURCE,
+            Some(stats.failed.len() as f64),
+            Some("count"),
+            json!({ "collectors": stats.failed }),
+        ));
+    }
+    for (cycle_ts, elapsed) in &stats.cycles {
+        samples.push(millis_sample(*cycle_ts, CYCLE_SOURCE, *elapsed));
+    }
+    if !stats.cycles.is_empty() {
+        if let Some(rss) = process_rss_bytes() {
+            samples.push(MetricSample::new(
+                ts,
+                MetricKind::Internal,
+                RSS_SOURCE,
+                Some(rss),
+                Some("bytes"),
+                serde_json::Value::Null,
+            ));
+        }
+    }
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_become_internal_samples() {
+        let stats = CycleStats {
+            timings: vec![("memory".to_string(), Duration::from_millis(3))],
+            failed: vec!["gpu".to_string()],
+            cycles: vec![(10.0, Duration::from_millis(120))],
+            db_insert: Some(Duration::from_millis(8)),
+        };
+        let samples = internal_samples(11.0, &stats);
+        assert!(samples.iter().all(|s| s.kind == MetricKind::Internal));
+
+        let find = |source: &str| samples.iter().find(|s| s.source == source).unwrap();
+        assert_eq!(find("collector:memory").value, Some(3.0));
+        assert_eq!(find(DB_INSERT_SOURCE).value, Some(8.0));
+        assert_eq!(find(ERRORS_SOURCE).details["collectors"][0], "gpu");
+        assert_eq!(find(CYCLE_SOURCE).ts, 10.0);
+        assert!(find(RSS_SOURCE).value.unwrap() > 0.0);
+    }
+}
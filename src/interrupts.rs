@@ -0,0 +1,311 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde_json::json;
+
+use crate::cli_helpers::percentile;
+use crate::metrics::{MetricKind, MetricSample};
+
+/// Intervals at least this busy, and [`STORM_FACTOR`] times a source's usual rate, count
+/// as an interrupt storm.
+pub const STORM_MIN_RATE: f64 = 200.0;
+const STORM_FACTOR: f64 = 10.0;
+
+/// Per-IRQ totals summed over all CPUs from `/proc/interrupts`, as (source, total).
+/// Numbered IRQs are named after the driver(s) using them, e.g. `irq 16 xhci_hcd`.
+fn parse_interrupts(content: &str) -> Vec<(String, u64)> {
+    let mut lines = content.lines();
+    let cpus = lines
+        .next()
+        .map(|header| header.split_whitespace().count())
+        .unwrap_or(0);
+    lines
+        .filter_map(|line| {
+            let (irq, rest) = line.split_once(':')?;
+            let irq = irq.trim();
+            let mut fields = rest.split_whitespace().peekable();
+            let mut total = 0u64;
+            for _ in 0..cpus {
+                match fields.peek().and_then(|f| f.parse::<u64>().ok()) {
+                    Some(count) => {
+                        total += count;
+                        fields.next();
+                    }
+                    None => break,
+                }
+            }
+            let description: Vec<&str> = fields.collect();
+            let source = if irq.chars().all(|c| c.is_ascii_digit()) {
+                // `IR-PCI-MSI 327680-edge xhci_hcd`: the drivers follow the trigger type.
+                let trigger = description.iter().position(|f| {
+                    f.ends_with("-edge") || f.ends_with("-level") || f.ends_with("-fasteoi")
+                });
+                let drivers = match trigger {
+                    Some(index) if index + 1 < description.len() => {
+                        description[index + 1..].join(" ")
+                    }
+                    _ => description.last().copied().unwrap_or_default().to_string(),
+                };
+                format!("irq {irq} {drivers}").trim_end().to_string()
+            } else if description.is_empty() {
+                irq.to_string()
+            } else {
+                format!("{irq} {}", description.join(" "))
+            };
+            Some((source, total))
+        })
+        .collect()
+}
+
+/// Kernel wakeup sources from `/sys/class/wakeup` as (name, event count, wakeup count).
+fn wakeup_sources_from(root: &Path) -> Vec<(String, u64, Option<u64>)> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut sources: Vec<_> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let dir = entry.path();
+            let read = |file: &str| fs::read_to_string(dir.join(file)).ok();
+            let name = read("name")?.trim().to_string();
+            let events = read("event_count")?.trim().parse::<u64>().ok()?;
+            let wakeups = read("wakeup_count").and_then(|raw| raw.trim().parse().ok());
+            Some((name, events, wakeups))
+        })
+        .collect();
+    sources.sort();
+    sources
+}
+
+/// Cumulative interrupt and wakeup-source counters; sources that never fired are left out.
+pub fn interrupt_samples_from(interrupts: &str, wakeup_root: &Path, ts: f64) -> Vec<MetricSample> {
+    let irqs = parse_interrupts(interrupts)
+        .into_iter()
+        .filter(|(_, total)| *total > 0)
+        .map(|(source, total)| {
+            MetricSample::new(
+                ts,
+                MetricKind::Interrupts,
+                source,
+                Some(total as f64),
+                None,
+                json!({ "type": "irq" }),
+            )
+        });
+    let wakeups = wakeup_sources_from(wakeup_root)
+        .into_iter()
+        .filter(|(_, events, _)| *events > 0)
+        .map(|(name, events, wakeups)| {
+            MetricSample::new(
+                ts,
+                MetricKind::Interrupts,
+                format!("wakeup {name}"),
+                Some(events as f64),
+                None,
+                json!({ "type": "wakeup", "wakeup_count": wakeups }),
+            )
+        });
+    irqs.chain(wakeups).collect()
+}
+
+pub fn interrupt_samples(ts: f64) -> Vec<MetricSample> {
+    let Ok(interrupts) = fs::read_to_string("/proc/interrupts") else {
+        return Vec::new();
+    };
+    interrupt_samples_from(&interrupts, Path::new("/sys/class/wakeup"), ts)
+}
+
+/// How often one interrupt or wakeup source fired over a window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceActivity {
+    pub source: String,
+    pub events: f64,
+    /// Events per second over the covered intervals.
+    pub avg_rate: f64,
+    pub peak_rate: f64,
+    pub storms: usize,
+}
+
+/// A run of collection intervals where a source fired far above its usual rate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Storm {
+    pub source: String,
+    pub start_ts: f64,
+    pub end_ts: f64,
+    pub peak_rate: f64,
+    /// The source's median rate over the window.
+    pub usual_rate: f64,
+}
+
+/// Ranks sources by events in `metrics` (busiest first) and finds interrupt storms.
+/// Counter resets (reboots) are skipped rather than counted as negative rates.
+pub fn analyze_interrupts(metrics: &[MetricSample]) -> (Vec<SourceActivity>, Vec<Storm>) {
+    let mut by_source: BTreeMap<&str, Vec<(f64, f64)>> = BTreeMap::new();
+    for sample in metrics.iter().filter(|m| m.kind == MetricKind::Interrupts) {
+        if let Some(value) = sample.value {
+            by_source
+                .entry(&sample.source)
+                .or_default()
+                .push((sample.ts, value));
+        }
+    }
+
+    let mut activity = Vec::new();
+    let mut storms = Vec::new();
+    for (source, mut counts) in by_source {
+        counts.sort_by(|a, b| a.0.total_cmp(&b.0));
+        // (end ts, events, seconds) per interval.
+        let intervals: Vec<(f64, f64, f64)> = counts
+            .windows(2)
+            .filter_map(|pair| {
+                let ((start, before), (end, after)) = (pair[0], pair[1]);
+                (end > start && after >= before).then_some((end, after - before, end - start))
+            })
+            .collect();
+        if intervals.is_empty() {
+            continue;
+        }
+        let events: f64 = intervals.iter().map(|(_, events, _)| events).sum();
+        let seconds: f64 = intervals.iter().map(|(_, _, seconds)| seconds).sum();
+        let mut rates: Vec<f64> = intervals.iter().map(|(_, e, s)| e / s).collect();
+        let peak_rate = rates.iter().copied().fold(0.0, f64::max);
+        rates.sort_by(f64::total_cmp);
+        let usual_rate = percentile(&rates, 0.5).unwrap_or(0.0);
+        let threshold = STORM_MIN_RATE.max(usual_rate * STORM_FACTOR);
+
+        let mut current: Option<Storm> = None;
+        let mut source_storms = 0;
+        for (end, interval_events, interval_seconds) in &intervals {
+            let rate = interval_events / interval_seconds;
+            if rate < threshold {
+                if let Some(storm) = current.take() {
+                    storms.push(storm);
+                    source_storms += 1;
+                }
+                continue;
+            }
+            match current.as_mut() {
+                Some(storm) => {
+                    storm.end_ts = *end;
+                    storm.peak_rate = storm.peak_rate.max(rate);
+                }
+                None => {
+                    current = Some(Storm {
+                        source: source.to_string(),
+                        start_ts: end - interval_seconds,
+                        end_ts: *end,
+                        peak_rate: rate,
+                        usual_rate,
+                    })
+                }
+            }
+        }
+        if let Some(storm) = current {
+            storms.push(storm);
+            source_storms += 1;
+        }
+        activity.push(SourceActivity {
+            source: source.to_string(),
+            events,
+            avg_rate: events / seconds,
+            peak_rate,
+            storms: source_storms,
+        });
+    }
+    activity.sort_by(|a, b| b.events.total_cmp(&a.events));
+    storms.sort_by(|a, b| a.start_ts.total_cmp(&b.start_ts));
+    (activity, storms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERRUPTS: &str = "\
+           CPU0       CPU1
+  0:         20          0   IO-APIC   2-edge      timer
+ 16:        100         50   IO-APIC  16-fasteoi   ehci_hcd:usb1, i801_smbus
+129:          0          0  IR-PCI-MSI 327680-edge      xhci_hcd
+NMI:          3          4   Non-maskable interrupts
+ERR:          0
+";
+
+    fn count(ts: f64, value: f64) -> MetricSample {
+        MetricSample::new(
+            ts,
+            MetricKind::Interrupts,
+            "irq 16 i915",
+            Some(value),
+            None,
+            serde_json::Value::Null,
+        )
+    }
+
+    /// A steady 10/s source with one burst of 5000/s between 120s and 180s.
+    fn bursty() -> Vec<MetricSample> {
+        let mut total = 0.0;
+        [10.0, 10.0, 5000.0, 10.0, 10.0]
+            .into_iter()
+            .enumerate()
+            .map(|(i, rate)| {
+                total += rate * 60.0;
+                count((i as f64 + 1.0) * 60.0, total)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn interrupt_lines_are_named_and_summed_across_cpus() {
+        let tmp = tempfile::tempdir().unwrap();
+        let samples = interrupt_samples_from(INTERRUPTS, tmp.path(), 0.0);
+        let sources: Vec<&str> = samples.iter().map(|s| s.source.as_str()).collect();
+        // Lines that never fired and ERR are skipped.
+        assert_eq!(
+            sources,
+            vec![
+                "irq 0 timer",
+                "irq 16 ehci_hcd:usb1, i801_smbus",
+                "NMI Non-maskable interrupts"
+            ]
+        );
+        assert_eq!(samples[1].value, Some(150.0));
+    }
+
+    #[test]
+    fn wakeup_sources_are_read_from_sysfs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let wakeup = tmp.path().join("wakeup0");
+        fs::create_dir_all(&wakeup).unwrap();
+        fs::write(wakeup.join("name"), "PNP0C0D:00\n").unwrap();
+        fs::write(wakeup.join("event_count"), "12\n").unwrap();
+        fs::write(wakeup.join("wakeup_count"), "2\n").unwrap();
+
+        let samples = interrupt_samples_from("", tmp.path(), 0.0);
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].source, "wakeup PNP0C0D:00");
+        assert_eq!(samples[0].details["wakeup_count"], 2);
+    }
+
+    #[test]
+    fn bursts_above_the_usual_rate_are_storms() {
+        let (activity, storms) = analyze_interrupts(&bursty());
+        assert_eq!(activity.len(), 1);
+        assert_eq!(activity[0].peak_rate, 5000.0);
+        assert_eq!(activity[0].storms, 1);
+        assert_eq!(storms.len(), 1);
+        assert_eq!((storms[0].start_ts, storms[0].end_ts), (120.0, 180.0));
+        assert_eq!(storms[0].usual_rate, 10.0);
+    }
+
+    #[test]
+    fn counter_resets_are_not_storms() {
+        let mut metrics = bursty();
+        // A reboot in between resets the counter.
+        metrics.push(count(0.0, 0.0));
+        metrics.push(count(400.0, 5.0));
+        let (activity, storms) = analyze_interrupts(&metrics);
+        assert_eq!(activity[0].peak_rate, 5000.0);
+        assert_eq!(storms.len(), 1);
+    }
+}
@@ -0,0 +1,302 @@
+use std::fs::{self, DirBuilder};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde_json::{json, Value};
+
+use crate::env;
+
+/// Overrides the control socket location (default `$XDG_RUNTIME_DIR/symmetri.sock`).
+pub const SOCKET_ENV: &str = env::SOCKET.name;
+const IO_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Commands understood by the collect daemon, one per line on the control socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Request {
+    /// Latest battery reading and metric samples held by the daemon
+    Latest,
+    /// Run every enabled collector now
+    Collect,
+    /// Reload the config file (same as SIGHUP)
+    Reload,
+    /// Uptime, cycle and sample counts
+    Stats,
+}
+
+impl Request {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Request::Latest => "latest",
+            Request::Collect => "collect",
+            Request::Reload => "reload",
+            Request::Stats => "stats",
+        }
+    }
+
+    fn parse(raw: &str) -> Option<Self> {
+        Request::value_variants()
+            .iter()
+            .copied()
+            .find(|request| request.as_str() == raw.trim())
+    }
+}
+
+fn uid() -> libc::uid_t {
+    // SAFETY: getuid cannot fail.
+    unsafe { libc::getuid() }
+}
+
+/// Where the socket goes without `XDG_RUNTIME_DIR`: a directory of our own under the temp
+/// dir, since a socket name in a shared `/tmp` could be taken by another user first.
+fn fallback_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("symmetri-{}", uid()))
+}
+
+pub fn socket_path() -> PathBuf {
+    if let Some(path) = std::env::var_os(SOCKET_ENV) {
+        return PathBuf::from(path);
+    }
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("symmetri.sock"),
+        None => fallback_dir().join("symmetri.sock"),
+    }
+}
+
+/// Fails unless `dir` belongs to us and nobody else can get into it.
+fn check_private(dir: &Path) -> Result<()> {
+    let meta =
+        fs::symlink_metadata(dir).with_context(|| format!("Cannot inspect {}", dir.display()))?;
+    if !meta.is_dir() || meta.uid() != uid() || meta.mode() & 0o077 != 0 {
+        anyhow::bail!(
+            "{} is not a private directory of this user; remove it or set {SOCKET_ENV}",
+            dir.display()
+        );
+    }
+    Ok(())
+}
+
+/// Refuses a socket in the fallback directory when someone else could have put it there.
+fn check_socket_dir(path: &Path) -> Result<()> {
+    match path.parent() {
+        Some(parent) if parent == fallback_dir() && parent.exists() => check_private(parent),
+        _ => Ok(()),
+    }
+}
+
+/// The daemon side of the control socket. A thread accepts clients and reads their
+/// requests, so a client that connects and stays silent never holds up the collect loop,
+/// which only picks up the clients already read. The socket file is removed on drop.
+pub struct IpcServer {
+    clients: mpsc::Receiver<IpcClient>,
+    stop: Arc<AtomicBool>,
+    path: PathBuf,
+}
+
+impl IpcServer {
+    /// Binds `path`, replacing a stale socket left by a daemon that did not shut down
+    /// cleanly. Fails when another daemon is still answering on it.
+    pub fn bind(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(parent)?;
+        }
+        check_socket_dir(path)?;
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                anyhow::bail!("{} is in use by another collector", path.display());
+            }
+            fs::remove_file(path)
+                .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+        }
+        let listener = UnixListener::bind(path)
+            .with_context(|| format!("Failed to bind control socket {}", path.display()))?;
+        let (sender, clients) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        thread::Builder::new()
+            .name("ipc".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    if stopped.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(err) => {
+                            tracing::warn!("Control socket accept failed: {err}");
+                            continue;
+                        }
+                    };
+                    let sender = sender.clone();
+                    thread::spawn(move || match IpcClient::read(stream) {
+                        Ok(client) => {
+                            let _ = sender.send(client);
+                        }
+                        Err(err) => tracing::debug!("Dropped control client: {err:#}"),
+                    });
+                }
+            })?;
+        Ok(IpcServer {
+            clients,
+            stop,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Clients waiting to be served, with the request each of them sent. Never blocks.
+    pub fn accept(&self) -> Vec<IpcClient> {
+        self.clients.try_iter().collect()
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Wakes the accepting thread so it sees the flag and exits.
+        let _ = UnixStream::connect(&self.path);
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// A connected client; answer it with [`IpcClient::reply`].
+pub struct IpcClient {
+    stream: UnixStream,
+    /// The parsed request, or the unknown command as sent.
+    pub request: std::result::Result<Request, String>,
+}
+
+impl IpcClient {
+    fn read(stream: UnixStream) -> Result<Self> {
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        let mut line = String::new();
+        if BufReader::new(&stream).read_line(&mut line)? == 0 {
+            anyhow::bail!("client hung up without a request");
+        }
+        let request = Request::parse(&line).ok_or_else(|| line.trim().to_string());
+        Ok(IpcClient { stream, request })
+    }
+
+    pub fn reply(mut self, response: &Value) {
+        if let Err(err) = writeln!(self.stream, "{response}") {
+            tracing::debug!("Failed to answer control client: {err}");
+        }
+    }
+}
+
+/// Sends one request to the daemon listening on `path` and returns its JSON answer.
+pub fn send_request(path: &Path, request: Request) -> Result<Value> {
+    check_socket_dir(path)?;
+    let mut stream = UnixStream::connect(path).with_context(|| {
+        format!(
+            "No collector listening on {} (is `symmetri collect --interval` running?)",
+            path.display()
+        )
+    })?;
+    stream.set_read_timeout(Some(IO_TIMEOUT * 5))?;
+    writeln!(stream, "{}", request.as_str())?;
+    let mut raw = String::new();
+    stream.read_to_string(&mut raw)?;
+    let response: Value =
+        serde_json::from_str(raw.trim()).context("Malformed answer from the collector")?;
+    if let Some(error) = response.get("error").and_then(Value::as_str) {
+        anyhow::bail!("{error}");
+    }
+    Ok(response)
+}
+
+pub fn unknown_request(raw: &str) -> Value {
+    json!({ "error": format!("unknown request: {raw}") })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_round_trip_over_the_socket() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("symmetri.sock");
+        // A socket file nobody listens on is replaced.
+        drop(UnixListener::bind(&path).unwrap());
+        let server = IpcServer::bind(&path).unwrap();
+        assert!(IpcServer::bind(&path).is_err());
+
+        let client_path = path.clone();
+        let client = std::thread::spawn(move || send_request(&client_path, Request::Stats));
+        let mut answered = false;
+        for _ in 0..200 {
+            for client in server.accept() {
+                assert_eq!(client.request, Ok(Request::Stats));
+                client.reply(&json!({ "cycles": 3 }));
+                answered = true;
+            }
+            if answered {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(client.join().unwrap().unwrap()["cycles"], 3);
+        assert_eq!(Request::parse("reload\n"), Some(Request::Reload));
+        assert_eq!(Request::parse("nope"), None);
+
+        drop(server);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn a_silent_client_does_not_hold_up_the_loop() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("symmetri.sock");
+        let server = IpcServer::bind(&path).unwrap();
+        let _silent = UnixStream::connect(&path).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        let started = std::time::Instant::now();
+        assert!(server.accept().is_empty());
+        assert!(started.elapsed() < Duration::from_millis(500));
+
+        let client_path = path.clone();
+        let client = std::thread::spawn(move || send_request(&client_path, Request::Latest));
+        let served = (0..200).any(|_| {
+            let clients = server.accept();
+            let found = !clients.is_empty();
+            for client in clients {
+                client.reply(&json!({ "metrics": [] }));
+            }
+            if !found {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            found
+        });
+        assert!(served);
+        assert!(client.join().unwrap().is_ok());
+    }
+
+    #[test]
+    fn socket_directories_must_be_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let created = tmp.path().join("run");
+        drop(IpcServer::bind(&created.join("symmetri.sock")).unwrap());
+        assert!(check_private(&created).is_ok());
+
+        fs::set_permissions(&created, fs::Permissions::from_mode(0o755)).unwrap();
+        let err = check_private(&created).unwrap_err();
+        assert!(err.to_string().contains("not a private directory"), "{err}");
+    }
+}
@@ -1,12 +1,116 @@
+//! Battery and system metrics collection for Linux, stored in SQLite.
+//!
+//! Besides the `symmetri` CLI, the crate can be embedded: [`client::Db`] queries a
+//! database the collector writes to, and [`collect::collect_now`] takes a snapshot
+//! without one.
+//!
+//! ```no_run
+//! use symmetri::client::Db;
+//! use symmetri::metrics::MetricKind;
+//!
+//! let db = Db::open_default();
+//! let last_day = symmetri::build_timeframe(0, 1, 0, false, None)?;
+//! let cpu = db
+//!     .metrics()
+//!     .timeframe(&last_day)
+//!     .kind(MetricKind::CpuUsage)
+//!     .fetch()?;
+//! println!("{} CPU samples today", cpu.len());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod activity;
 pub mod aggregate;
+pub mod alerts;
+pub mod backup;
+pub mod baseline;
+pub mod bench;
+pub mod bsd;
+pub mod calibrate;
+pub mod cgroups;
+pub mod check;
 pub mod cli;
 pub mod cli_helpers;
+pub mod client;
+pub mod collect;
 pub mod collector;
+pub mod completions;
+pub mod config;
+pub mod correlate;
+pub mod daily;
 pub mod db;
+pub mod derived;
+#[cfg(feature = "details-compression")]
+pub mod details;
+#[cfg(not(feature = "details-compression"))]
+#[path = "details_disabled.rs"]
+pub mod details;
+pub mod disk;
+pub mod display;
+pub mod doctor;
+pub mod email;
+pub mod env;
+pub mod exit;
+#[cfg(feature = "graphs")]
+pub mod graph;
+#[cfg(not(feature = "graphs"))]
+#[path = "graph_disabled.rs"]
 pub mod graph;
+pub mod gzip;
+pub mod habits;
+pub mod hooks;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod import;
+pub mod influx;
+pub mod internal;
+pub mod interrupts;
+pub mod ipc;
+pub mod live;
+pub mod locale;
+pub mod lock;
+pub mod logging;
+pub mod media;
 pub mod metrics;
+pub mod nix_module;
+pub mod normalize;
+#[cfg(feature = "otlp")]
+pub mod otlp;
+pub mod predict;
+pub mod privacy;
+pub mod progress;
+pub mod push;
+pub mod query;
+pub mod regression;
+pub mod report;
+pub mod runtime;
+pub mod schedule;
+pub mod scripts;
+pub mod sensors;
+pub mod setup;
+pub mod signals;
+pub mod snapshot;
+pub mod sources;
+pub mod style;
+pub mod suspend;
+pub mod synthetic;
 pub mod sysfs;
+pub mod termux;
+#[cfg(test)]
+mod test_support;
+pub mod thermal;
 pub mod timeframe;
+pub mod tunables;
+pub mod usb;
+pub mod versions;
+pub mod wear;
+pub mod webhook;
+pub mod wifi;
+pub mod workload;
+pub mod zone;
 
-pub use collector::{collect_loop, collect_once, default_db_path, resolve_db_path};
-pub use timeframe::{build_timeframe, since_timestamp, Timeframe, TimeframeError};
+pub use collector::{
+    collect_loop, collect_once, default_db_path, preview_once, resolve_db_path, CollectionPreview,
+    Collector, CollectorRegistry,
+};
+pub use timeframe::{build_timeframe, since_timestamp, CalendarPeriod, Timeframe, TimeframeError};
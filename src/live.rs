@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+use std::io::{self, IsTerminal, Read};
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::collector::{now_ts, CollectionPreview};
+use crate::db::Sample;
+use crate::metrics::{MetricKind, MetricSample};
+
+/// How far back the history view (`h`) of `symmetri live` looks.
+pub const HISTORY_SECONDS: f64 = 3600.0;
+
+/// Kinds shown by `symmetri live`; cumulative counters (network bytes, interrupts, cgroup
+/// CPU time) only make sense as rates and are left to the reports.
+const LIVE_KINDS: [MetricKind; 9] = [
+    MetricKind::PowerDraw,
+    MetricKind::CpuUsage,
+    MetricKind::CpuFrequency,
+    MetricKind::GpuUsage,
+    MetricKind::Temperature,
+    MetricKind::MemoryUsage,
+    MetricKind::SystemLoad,
+    MetricKind::Wifi,
+    MetricKind::PowerProfile,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiveKey {
+    TogglePause,
+    /// Switch between the live gauges and the last [`HISTORY_SECONDS`] from the database.
+    ToggleHistory,
+    Quit,
+}
+
+pub fn key_action(byte: u8) -> Option<LiveKey> {
+    match byte {
+        b' ' | b'p' => Some(LiveKey::TogglePause),
+        b'h' => Some(LiveKey::ToggleHistory),
+        b'q' | 0x1b => Some(LiveKey::Quit),
+        _ => None,
+    }
+}
+
+/// The running collector's answer to `latest` as a collection, so the live view shows
+/// what the daemon holds instead of reading every sensor a second time.
+pub fn daemon_preview(latest: Value) -> Result<CollectionPreview> {
+    #[derive(Deserialize)]
+    struct Latest {
+        batteries: Vec<Sample>,
+        metrics: Vec<MetricSample>,
+    }
+    let Latest { batteries, metrics } = serde_json::from_value(latest)?;
+    let ts = batteries
+        .iter()
+        .map(|sample| sample.ts)
+        .chain(metrics.iter().map(|sample| sample.ts))
+        .max_by(f64::total_cmp)
+        .unwrap_or_else(now_ts);
+    Ok(CollectionPreview {
+        ts,
+        batteries,
+        metrics,
+        failed_collectors: Vec::new(),
+    })
+}
+
+/// The samples of one collection worth watching live, grouped by kind in display order.
+pub fn live_metrics(preview: &CollectionPreview) -> Vec<&MetricSample> {
+    let mut metrics: Vec<&MetricSample> = preview
+        .metrics
+        .iter()
+        .filter(|m| m.value.is_some() && LIVE_KINDS.contains(&m.kind))
+        .collect();
+    metrics.sort_by_key(|m| {
+        (
+            LIVE_KINDS.iter().position(|kind| *kind == m.kind),
+            m.source.clone(),
+        )
+    });
+    metrics
+}
+
+/// Lowest, mean and highest value of one gauge over the history window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GaugeHistory<'a> {
+    pub kind: &'a MetricKind,
+    pub source: &'a str,
+    pub unit: Option<&'a str>,
+    pub min: f64,
+    pub mean: f64,
+    pub max: f64,
+}
+
+/// One row per gauge of the live view found in `metrics`, in the same order.
+pub fn gauge_history(metrics: &[MetricSample]) -> Vec<GaugeHistory<'_>> {
+    let mut gauges: BTreeMap<(usize, &str), Vec<&MetricSample>> = BTreeMap::new();
+    for sample in metrics.iter().filter(|m| m.value.is_some()) {
+        if let Some(position) = LIVE_KINDS.iter().position(|kind| *kind == sample.kind) {
+            gauges
+                .entry((position, sample.source.as_str()))
+                .or_default()
+                .push(sample);
+        }
+    }
+    gauges
+        .into_values()
+        .map(|samples| {
+            let values: Vec<f64> = samples.iter().filter_map(|m| m.value).collect();
+            GaugeHistory {
+                kind: &samples[0].kind,
+                source: &samples[0].source,
+                unit: samples[0].unit.as_deref(),
+                min: values.iter().copied().fold(f64::INFINITY, f64::min),
+                mean: values.iter().sum::<f64>() / values.len() as f64,
+                max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            }
+        })
+        .collect()
+}
+
+/// Puts the terminal into unbuffered, no-echo mode so single key presses can be read, and
+/// restores it on drop. Ctrl-C keeps working.
+pub struct RawTerminal {
+    original: libc::termios,
+}
+
+impl RawTerminal {
+    /// `None` when stdin is not a terminal; the live view then only refreshes.
+    pub fn enable() -> Option<Self> {
+        if !io::stdin().is_terminal() {
+            return None;
+        }
+        // SAFETY: termios is plain data and fd 0 is a terminal.
+        unsafe {
+            let mut original: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                return None;
+            }
+            let mut raw = original;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+            raw.c_cc[libc::VMIN] = 0;
+            raw.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                return None;
+            }
+            Some(RawTerminal { original })
+        }
+    }
+
+    /// Waits up to `timeout` for a key press.
+    pub fn read_key(&self, timeout: Duration) -> Option<u8> {
+        let mut fds = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: one valid pollfd for the duration of the call.
+        let ready = unsafe { libc::poll(&mut fds, 1, timeout.as_millis() as libc::c_int) };
+        if ready <= 0 {
+            return None;
+        }
+        let mut byte = [0u8; 1];
+        match io::stdin().read(&mut byte) {
+            Ok(1) => Some(byte[0]),
+            _ => None,
+        }
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        // SAFETY: restores the settings read in `enable`.
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample(kind: MetricKind, source: &str, value: Option<f64>) -> MetricSample {
+        MetricSample::new(1.0, kind, source, value, None, Value::Null)
+    }
+
+    #[test]
+    fn live_view_keeps_gauges_in_display_order() {
+        let preview = CollectionPreview {
+            ts: 1.0,
+            batteries: Vec::new(),
+            metrics: vec![
+                sample(MetricKind::Temperature, "cpu", Some(60.0)),
+                sample(MetricKind::NetworkBytes, "eth0", Some(1e6)),
+                sample(MetricKind::CpuUsage, "cpu1", Some(5.0)),
+                sample(MetricKind::CpuUsage, "cpu0", Some(10.0)),
+                sample(MetricKind::PowerDraw, "rapl", None),
+            ],
+            failed_collectors: Vec::new(),
+        };
+        let shown: Vec<&str> = live_metrics(&preview)
+            .iter()
+            .map(|m| m.source.as_str())
+            .collect();
+        assert_eq!(shown, vec!["cpu0", "cpu1", "cpu"]);
+    }
+
+    #[test]
+    fn keys_map_to_actions() {
+        assert_eq!(key_action(b' '), Some(LiveKey::TogglePause));
+        assert_eq!(key_action(b'h'), Some(LiveKey::ToggleHistory));
+        assert_eq!(key_action(b'q'), Some(LiveKey::Quit));
+        assert_eq!(key_action(b'x'), None);
+    }
+
+    #[test]
+    fn daemon_answers_become_a_collection() {
+        let battery = crate::test_support::battery(20.0, 55.0, "Discharging");
+        let latest = json!({
+            "batteries": [battery],
+            "metrics": [{ "ts": 30.0, "kind": "cpu_usage", "source": "cpu", "value": 12.0, "unit": "%" }],
+        });
+        let preview = daemon_preview(latest).unwrap();
+        assert_eq!(preview.ts, 30.0);
+        assert_eq!(preview.batteries[0].percentage, Some(55.0));
+        assert_eq!(preview.metrics[0].kind, MetricKind::CpuUsage);
+        assert!(daemon_preview(json!({ "cycles": 3 })).is_err());
+    }
+
+    #[test]
+    fn history_summarises_each_gauge() {
+        let metrics = vec![
+            sample(MetricKind::Temperature, "cpu", Some(50.0)),
+            sample(MetricKind::CpuUsage, "cpu", Some(10.0)),
+            sample(MetricKind::Temperature, "cpu", Some(70.0)),
+            sample(MetricKind::CpuUsage, "cpu", Some(30.0)),
+            sample(MetricKind::NetworkBytes, "eth0", Some(1e6)),
+            sample(MetricKind::CpuUsage, "cpu", None),
+        ];
+        let history = gauge_history(&metrics);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].kind, &MetricKind::CpuUsage);
+        assert_eq!(
+            (history[0].min, history[0].mean, history[0].max),
+            (10.0, 20.0, 30.0)
+        );
+        assert_eq!(history[1].kind, &MetricKind::Temperature);
+        assert_eq!(history[1].mean, 60.0);
+    }
+}
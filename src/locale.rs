@@ -0,0 +1,302 @@
+use std::sync::RwLock;
+
+use chrono::{DateTime, NaiveDate, TimeZone};
+use clap::ValueEnum;
+
+use crate::zone::Zone;
+
+/// Prefixes for byte counts in tables and graph axes.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ByteUnits {
+    /// Powers of 1024: KiB, MiB, GiB
+    #[default]
+    Iec,
+    /// Powers of 1000: kB, MB, GB
+    Si,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum DateOrder {
+    /// 2024-05-31
+    #[default]
+    Ymd,
+    /// 31/05/2024
+    Dmy,
+    /// 05/31/2024
+    Mdy,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Clock {
+    /// 14:30
+    #[default]
+    #[value(name = "24h")]
+    H24,
+    /// 2:30 PM
+    #[value(name = "12h")]
+    H12,
+}
+
+/// How reports print byte counts, dates and times.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Formats {
+    pub units: ByteUnits,
+    pub date_order: DateOrder,
+    pub clock: Clock,
+    /// Zone that dates, buckets and day/hour groupings are shown in.
+    pub zone: Zone,
+}
+
+/// Territories writing month/day/year, and those using a 12-hour clock.
+const MDY_TERRITORIES: [&str; 3] = ["US", "PH", "PR"];
+const H12_TERRITORIES: [&str; 9] = ["US", "PH", "PR", "CA", "AU", "NZ", "IN", "PK", "EG"];
+/// Languages and territories writing year/month/day.
+const YMD_LANGUAGES: [&str; 5] = ["zh", "ja", "ko", "hu", "lt"];
+const YMD_TERRITORIES: [&str; 4] = ["CN", "JP", "KR", "SE"];
+
+impl Formats {
+    const DEFAULT: Formats = Formats {
+        units: ByteUnits::Iec,
+        date_order: DateOrder::Ymd,
+        clock: Clock::H24,
+        zone: Zone::Local,
+    };
+
+    /// Date order and clock for a POSIX locale name such as `de_DE.UTF-8`. `C`, `POSIX`
+    /// and unset locales keep ISO dates and a 24-hour clock.
+    pub fn from_locale(locale: &str) -> Self {
+        let name = locale.split(['.', '@']).next().unwrap_or_default();
+        let (language, territory) = name.split_once('_').unwrap_or((name, ""));
+        if language.is_empty() || language == "C" || language == "POSIX" {
+            return Formats::DEFAULT;
+        }
+        let date_order = if MDY_TERRITORIES.contains(&territory) {
+            DateOrder::Mdy
+        } else if YMD_LANGUAGES.contains(&language) || YMD_TERRITORIES.contains(&territory) {
+            DateOrder::Ymd
+        } else {
+            DateOrder::Dmy
+        };
+        let clock = if H12_TERRITORIES.contains(&territory) {
+            Clock::H12
+        } else {
+            Clock::H24
+        };
+        Formats {
+            date_order,
+            clock,
+            ..Formats::DEFAULT
+        }
+    }
+
+    /// Formats for the time locale of this process (`LC_ALL`, `LC_TIME`, then `LANG`).
+    pub fn from_env() -> Self {
+        let locale = ["LC_ALL", "LC_TIME", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        Formats::from_locale(&locale)
+    }
+
+    /// A stored Unix timestamp in [`Formats::zone`].
+    pub fn datetime(&self, ts: f64) -> Option<DateTime<Zone>> {
+        self.zone.timestamp_opt(ts.floor() as i64, 0).single()
+    }
+
+    /// `1.5GiB` or `1.6GB`.
+    pub fn bytes(&self, value: f64) -> String {
+        let (base, units) = match self.units {
+            ByteUnits::Iec => (1024.0, ["B", "KiB", "MiB", "GiB", "TiB"]),
+            ByteUnits::Si => (1000.0, ["B", "kB", "MB", "GB", "TB"]),
+        };
+        let mut val = value;
+        let mut idx = 0;
+        while val.abs() >= base && idx + 1 < units.len() {
+            val /= base;
+            idx += 1;
+        }
+        if idx == 0 {
+            format!("{val:.0}{}", units[idx])
+        } else {
+            format!("{val:.1}{}", units[idx])
+        }
+    }
+
+    /// Divisor and label for graph axes in giga-/gibibytes.
+    pub fn giga(&self) -> (f64, &'static str) {
+        match self.units {
+            ByteUnits::Iec => (1024.0 * 1024.0 * 1024.0, "GiB"),
+            ByteUnits::Si => (1e9, "GB"),
+        }
+    }
+
+    /// Divisor and label for graph axes in mega-/mebibytes.
+    pub fn mega(&self) -> (f64, &'static str) {
+        match self.units {
+            ByteUnits::Iec => (1024.0 * 1024.0, "MiB"),
+            ByteUnits::Si => (1e6, "MB"),
+        }
+    }
+
+    fn date_pattern(&self) -> &'static str {
+        match self.date_order {
+            DateOrder::Ymd => "%Y-%m-%d",
+            DateOrder::Dmy => "%d/%m/%Y",
+            DateOrder::Mdy => "%m/%d/%Y",
+        }
+    }
+
+    /// Day and month without the year.
+    fn short_date_pattern(&self) -> &'static str {
+        match self.date_order {
+            DateOrder::Ymd => "%m-%d",
+            DateOrder::Dmy => "%d/%m",
+            DateOrder::Mdy => "%m/%d",
+        }
+    }
+
+    fn time_pattern(&self) -> &'static str {
+        match self.clock {
+            Clock::H24 => "%H:%M",
+            Clock::H12 => "%-I:%M %p",
+        }
+    }
+
+    pub fn date<Tz: TimeZone>(&self, dt: &DateTime<Tz>) -> String
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        dt.format(self.date_pattern()).to_string()
+    }
+
+    pub fn day(&self, day: &NaiveDate) -> String {
+        day.format(self.date_pattern()).to_string()
+    }
+
+    pub fn time<Tz: TimeZone>(&self, dt: &DateTime<Tz>) -> String
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        dt.format(self.time_pattern()).to_string()
+    }
+
+    /// Date and time to the minute.
+    pub fn timestamp<Tz: TimeZone>(&self, dt: &DateTime<Tz>) -> String
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        format!("{} {}", self.date(dt), self.time(dt))
+    }
+
+    /// Label for a report bucket starting at `dt`, only as precise as the bucket.
+    pub fn bucket<Tz: TimeZone>(&self, dt: &DateTime<Tz>, bucket_seconds: i64) -> String
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        let short_date = dt.format(self.short_date_pattern());
+        if bucket_seconds < 3600 {
+            format!("{short_date} {}", self.time(dt))
+        } else if bucket_seconds < 24 * 3600 {
+            let hour = match self.clock {
+                Clock::H24 => dt.format("%H:00"),
+                Clock::H12 => dt.format("%-I %p"),
+            };
+            format!("{short_date} {hour}")
+        } else {
+            let days = bucket_seconds / (24 * 3600);
+            if days <= 1 {
+                self.date(dt)
+            } else {
+                format!("{} (+{days}d)", self.date(dt))
+            }
+        }
+    }
+
+    /// Graph time axis label for a chart spanning `span_seconds`.
+    pub fn axis<Tz: TimeZone>(&self, dt: &DateTime<Tz>, span_seconds: i64) -> String
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        if span_seconds <= 24 * 3600 {
+            self.time(dt)
+        } else if span_seconds <= 14 * 24 * 3600 {
+            format!("{} {}", dt.format(self.short_date_pattern()), self.time(dt))
+        } else {
+            self.date(dt)
+        }
+    }
+}
+
+static CURRENT: RwLock<Formats> = RwLock::new(Formats::DEFAULT);
+
+/// Formats used by the CLI tables; set once per run from the locale and flags.
+pub fn current() -> Formats {
+    CURRENT.read().map(|formats| *formats).unwrap_or_default()
+}
+
+pub fn set_current(formats: Formats) {
+    if let Ok(mut current) = CURRENT.write() {
+        *current = formats;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn dt() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 31, 14, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn locales_pick_the_date_order() {
+        assert_eq!(Formats::from_locale(""), Formats::default());
+        assert_eq!(Formats::from_locale("C.UTF-8"), Formats::default());
+        assert_eq!(
+            Formats::from_locale("en_US.UTF-8").date_order,
+            DateOrder::Mdy
+        );
+        assert_eq!(
+            Formats::from_locale("de_DE.UTF-8@euro").date_order,
+            DateOrder::Dmy
+        );
+        assert_eq!(
+            Formats::from_locale("sv_SE.UTF-8").date_order,
+            DateOrder::Ymd
+        );
+
+        let de = Formats::from_locale("de_DE.UTF-8");
+        assert_eq!(Formats::default().timestamp(&dt()), "2024-05-31 14:30");
+        assert_eq!(de.timestamp(&dt()), "31/05/2024 14:30");
+        assert_eq!(Formats::default().bucket(&dt(), 3600), "05-31 14:00");
+        assert_eq!(de.bucket(&dt(), 900), "31/05 14:30");
+        assert_eq!(de.bucket(&dt(), 3 * 86400), "31/05/2024 (+3d)");
+        assert_eq!(de.axis(&dt(), 3 * 86400), "31/05 14:30");
+    }
+
+    #[test]
+    fn locales_pick_a_12_or_24_hour_clock() {
+        let us = Formats::from_locale("en_US.UTF-8");
+        let se = Formats::from_locale("sv_SE.UTF-8");
+        assert_eq!(us.clock, Clock::H12);
+        assert_eq!(se.clock, Clock::H24);
+
+        assert_eq!(us.timestamp(&dt()), "05/31/2024 2:30 PM");
+        assert_eq!(us.bucket(&dt(), 3600), "05/31 2 PM");
+        assert_eq!(us.axis(&dt(), 3600), "2:30 PM");
+    }
+
+    #[test]
+    fn byte_units_are_binary_unless_si_is_asked_for() {
+        let si = Formats {
+            units: ByteUnits::Si,
+            ..Formats::default()
+        };
+        assert_eq!(Formats::default().bytes(1_500_000_000.0), "1.4GiB");
+        assert_eq!(si.bytes(1_500_000_000.0), "1.5GB");
+        assert_eq!(si.bytes(999.0), "999B");
+        assert_eq!(si.giga(), (1e9, "GB"));
+    }
+}
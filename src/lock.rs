@@ -0,0 +1,130 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use tracing::info;
+
+/// How long `--replace` waits for the previous collector to flush and exit.
+pub const REPLACE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// An advisory `flock` on `<db>.lock` held by the one collector writing to that database.
+/// The kernel releases it when the process exits, so a crash never leaves a stale lock.
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+    path: PathBuf,
+}
+
+pub fn lock_path(db_path: &Path) -> PathBuf {
+    let mut path = db_path.as_os_str().to_owned();
+    path.push(".lock");
+    PathBuf::from(path)
+}
+
+fn try_lock(path: &Path) -> Result<Option<File>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| format!("Failed to open lock file {}", path.display()))?;
+    // SAFETY: the descriptor stays open for as long as `file` lives.
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+        let err = std::io::Error::last_os_error();
+        if err.kind() == ErrorKind::WouldBlock {
+            return Ok(None);
+        }
+        return Err(err).with_context(|| format!("Failed to lock {}", path.display()));
+    }
+    file.set_len(0)?;
+    writeln!(file, "{}", std::process::id())?;
+    Ok(Some(file))
+}
+
+/// The pid written by the collector holding the lock.
+fn holder_pid(path: &Path) -> Option<i32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+impl InstanceLock {
+    /// Takes the lock for `db_path`, failing when another collector holds it.
+    pub fn acquire(db_path: &Path) -> Result<Self> {
+        let path = lock_path(db_path);
+        match try_lock(&path)? {
+            Some(file) => Ok(InstanceLock { _file: file, path }),
+            None => {
+                let holder = holder_pid(&path)
+                    .map(|pid| format!(" (pid {pid})"))
+                    .unwrap_or_default();
+                anyhow::bail!(
+                    "Another collector{holder} is already writing to {}; stop it or pass --replace",
+                    db_path.display()
+                )
+            }
+        }
+    }
+
+    /// Takes the lock for `db_path`, asking a running collector to shut down (SIGTERM)
+    /// and waiting up to `timeout` for it to release the lock.
+    pub fn acquire_replacing(db_path: &Path, timeout: Duration) -> Result<Self> {
+        let path = lock_path(db_path);
+        if let Some(file) = try_lock(&path)? {
+            return Ok(InstanceLock { _file: file, path });
+        }
+        let pid = holder_pid(&path)
+            .with_context(|| format!("{} is locked but names no process", path.display()))?;
+        info!("Asking the running collector (pid {pid}) to shut down");
+        // SAFETY: plain syscall; a stale pid just makes it fail.
+        if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+            let err = std::io::Error::last_os_error();
+            return Err(err).with_context(|| format!("Failed to stop collector pid {pid}"));
+        }
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if let Some(file) = try_lock(&path)? {
+                return Ok(InstanceLock { _file: file, path });
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        anyhow::bail!(
+            "Collector pid {pid} did not exit within {}s",
+            timeout.as_secs()
+        )
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_instance_is_refused_until_the_first_exits() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db = tmp.path().join("metrics.db");
+        let first = InstanceLock::acquire(&db).unwrap();
+        assert_eq!(first.path(), tmp.path().join("metrics.db.lock"));
+        assert_eq!(holder_pid(first.path()), Some(std::process::id() as i32));
+
+        let err = InstanceLock::acquire(&db).unwrap_err().to_string();
+        assert!(
+            err.contains(&format!("pid {}", std::process::id())),
+            "{err}"
+        );
+        assert!(err.contains("--replace"), "{err}");
+
+        drop(first);
+        assert!(InstanceLock::acquire(&db).is_ok());
+    }
+}
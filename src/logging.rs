@@ -0,0 +1,108 @@
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use tracing::level_filters::LevelFilter;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry};
+
+use crate::env;
+
+/// Rotated log files kept next to `--log-file` (one per day).
+const MAX_LOG_FILES: usize = 7;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LogOptions {
+    pub verbose: bool,
+    pub format: LogFormat,
+    pub file: Option<PathBuf>,
+}
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+fn rolling_appender(path: &Path) -> Result<RollingFileAppender> {
+    let prefix = path
+        .file_name()
+        .with_context(|| format!("Log file {} has no file name", path.display()))?;
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(directory)
+        .with_context(|| format!("Cannot create log directory {}", directory.display()))?;
+    RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(prefix.to_string_lossy())
+        .max_log_files(MAX_LOG_FILES)
+        .build(directory)
+        .with_context(|| format!("Cannot open log file in {}", directory.display()))
+}
+
+fn output_layer(options: &LogOptions) -> Result<BoxedLayer> {
+    let layer = tracing_subscriber::fmt::layer();
+    let layer: BoxedLayer = match (&options.file, options.format) {
+        (None, LogFormat::Text) => layer
+            .without_time()
+            .with_target(false)
+            .with_level(false)
+            .with_ansi(std::io::stderr().is_terminal())
+            .with_writer(std::io::stderr)
+            .boxed(),
+        (None, LogFormat::Json) => layer.json().with_writer(std::io::stderr).boxed(),
+        (Some(path), LogFormat::Text) => layer
+            .with_ansi(false)
+            .with_writer(rolling_appender(path)?)
+            .boxed(),
+        (Some(path), LogFormat::Json) => layer.json().with_writer(rolling_appender(path)?).boxed(),
+    };
+    Ok(layer)
+}
+
+/// Installs the global subscriber. `SYMMETRI_LOG` directives (else `RUST_LOG`) are
+/// honoured on top of the info/debug default picked by `--verbose`.
+/// Calling it twice keeps the first subscriber.
+pub fn init_logging(options: &LogOptions) -> Result<()> {
+    let default_level = if options.verbose {
+        LevelFilter::DEBUG
+    } else {
+        LevelFilter::INFO
+    };
+    let builder = EnvFilter::builder().with_default_directive(default_level.into());
+    // Looked up before the subscriber exists but warned about after, so the deprecation
+    // warning goes through the configured output.
+    let directives = env::LOG.lookup();
+    let filter = match &directives {
+        Some((directives, _)) => builder.parse_lossy(directives),
+        None => builder.parse_lossy(""),
+    };
+    let _ = tracing_subscriber::registry()
+        .with(output_layer(options)?.with_filter(filter))
+        .try_init();
+    env::LOG.get();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_file_directory_is_created() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("logs").join("symmetri.log");
+        rolling_appender(&path).unwrap();
+        assert!(tmp.path().join("logs").is_dir());
+        assert!(rolling_appender(Path::new("/")).is_err());
+    }
+}
@@ -2,7 +2,6 @@ use std::env;
 
 fn main() {
     if let Err(err) = symmetri::cli::run(env::args_os()) {
-        eprintln!("Error: {err}");
-        std::process::exit(1);
+        std::process::exit(symmetri::exit::report(&err));
     }
 }
@@ -0,0 +1,185 @@
+use std::fs;
+use std::path::Path;
+
+use serde_json::json;
+
+use crate::metrics::{MetricKind, MetricSample};
+
+pub const AUDIO_PLAYBACK_SOURCE: &str = "audio_playback";
+pub const AUDIO_CAPTURE_SOURCE: &str = "audio_capture";
+pub const WEBCAM_SOURCE: &str = "webcam";
+
+fn sorted_entries(dir: &Path, prefix: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with(prefix))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Running ALSA substreams as `card0/pcm3p`, split into playback and capture. PulseAudio
+/// and PipeWire keep their devices open while idle, but the substream only runs while
+/// something is actually playing or recording.
+fn running_pcm_streams(asound: &Path) -> (Vec<String>, Vec<String>) {
+    let mut playback = Vec::new();
+    let mut capture = Vec::new();
+    for card in sorted_entries(asound, "card") {
+        for pcm in sorted_entries(&asound.join(&card), "pcm") {
+            let pcm_dir = asound.join(&card).join(&pcm);
+            let running = sorted_entries(&pcm_dir, "sub").iter().any(|sub| {
+                fs::read_to_string(pcm_dir.join(sub).join("status"))
+                    .is_ok_and(|status| status.contains("state: RUNNING"))
+            });
+            if !running {
+                continue;
+            }
+            let stream = format!("{card}/{pcm}");
+            if pcm.ends_with('p') {
+                playback.push(stream);
+            } else if pcm.ends_with('c') {
+                capture.push(stream);
+            }
+        }
+    }
+    (playback, capture)
+}
+
+/// Names of processes holding a `/dev/video*` device open. Only processes we may inspect
+/// are seen, which covers the user's own video calls.
+fn webcam_users(proc_root: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(proc_root) else {
+        return Vec::new();
+    };
+    let mut users: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().parse::<u32>().is_ok())
+        .filter(|entry| {
+            let Ok(fds) = fs::read_dir(entry.path().join("fd")) else {
+                return false;
+            };
+            fds.flatten().any(|fd| {
+                fs::read_link(fd.path())
+                    .is_ok_and(|target| target.to_string_lossy().starts_with("/dev/video"))
+            })
+        })
+        .filter_map(|entry| {
+            fs::read_to_string(entry.path().join("comm"))
+                .ok()
+                .map(|comm| comm.trim().to_string())
+        })
+        .collect();
+    users.sort();
+    users.dedup();
+    users
+}
+
+fn activity_sample(ts: f64, source: &str, active: &[String], key: &str) -> MetricSample {
+    MetricSample::new(
+        ts,
+        MetricKind::Media,
+        source,
+        Some(if active.is_empty() { 0.0 } else { 1.0 }),
+        None,
+        json!({ key: active }),
+    )
+}
+
+/// Whether audio is playing, the microphone is recording and the webcam is in use, as 1/0
+/// samples; averaging them gives the duty cycle. Machines without sound cards or video
+/// devices get no samples for them.
+pub fn media_samples_from(
+    asound: &Path,
+    video4linux: &Path,
+    proc_root: &Path,
+    ts: f64,
+) -> Vec<MetricSample> {
+    let mut samples = Vec::new();
+    if asound.is_dir() {
+        let (playback, capture) = running_pcm_streams(asound);
+        samples.push(activity_sample(
+            ts,
+            AUDIO_PLAYBACK_SOURCE,
+            &playback,
+            "streams",
+        ));
+        samples.push(activity_sample(
+            ts,
+            AUDIO_CAPTURE_SOURCE,
+            &capture,
+            "streams",
+        ));
+    }
+    if !sorted_entries(video4linux, "video").is_empty() {
+        samples.push(activity_sample(
+            ts,
+            WEBCAM_SOURCE,
+            &webcam_users(proc_root),
+            "processes",
+        ));
+    }
+    samples
+}
+
+pub fn media_samples(ts: f64) -> Vec<MetricSample> {
+    media_samples_from(
+        Path::new("/proc/asound"),
+        Path::new("/sys/class/video4linux"),
+        Path::new("/proc"),
+        ts,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use super::*;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn running_streams_and_open_cameras_are_active() {
+        let tmp = tempfile::tempdir().unwrap();
+        let asound = tmp.path().join("asound");
+        write(
+            &asound.join("card0/pcm0p/sub0/status"),
+            "state: RUNNING\nowner_pid   : 1234\n",
+        );
+        write(&asound.join("card0/pcm0c/sub0/status"), "closed\n");
+        write(&asound.join("card1/pcm3p/sub0/status"), "state: PREPARED\n");
+
+        let video4linux = tmp.path().join("video4linux");
+        fs::create_dir_all(video4linux.join("video0")).unwrap();
+        let proc_root = tmp.path().join("proc");
+        write(&proc_root.join("42/comm"), "zoom\n");
+        fs::create_dir_all(proc_root.join("42/fd")).unwrap();
+        symlink("/dev/video0", proc_root.join("42/fd/7")).unwrap();
+        write(&proc_root.join("43/comm"), "bash\n");
+        fs::create_dir_all(proc_root.join("43/fd")).unwrap();
+        symlink("/dev/null", proc_root.join("43/fd/0")).unwrap();
+
+        let samples = media_samples_from(&asound, &video4linux, &proc_root, 1.0);
+        let values: Vec<(&str, Option<f64>)> = samples
+            .iter()
+            .map(|s| (s.source.as_str(), s.value))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (AUDIO_PLAYBACK_SOURCE, Some(1.0)),
+                (AUDIO_CAPTURE_SOURCE, Some(0.0)),
+                (WEBCAM_SOURCE, Some(1.0)),
+            ]
+        );
+        assert_eq!(samples[0].details["streams"], json!(["card0/pcm0p"]));
+        assert_eq!(samples[2].details["processes"], json!(["zoom"]));
+    }
+}
@@ -1,7 +1,9 @@
+// The sysfs/procfs readers below are only reachable through `builtin_collectors`, which
+// leaves out those whose `collector-*` feature is off.
+#![cfg_attr(not(feature = "collectors"), allow(dead_code))]
+
 use std::collections::BTreeMap;
-use std::ffi::CString;
 use std::fs;
-use std::os::unix::ffi::OsStrExt;
 use std::path::Path;
 use std::str::FromStr;
 use std::thread;
@@ -10,7 +12,10 @@ use std::time::Duration;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
+use crate::collector::Collector;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
 pub enum MetricKind {
     CpuUsage,
     CpuFrequency,
@@ -21,9 +26,47 @@ pub enum MetricKind {
     DiskUsage,
     Temperature,
     PowerDraw,
+    PowerProfile,
+    /// Load averages and kernel activity rates from `/proc`.
+    SystemLoad,
+    /// Wireless signal strength (dBm) per interface; SSID and bitrates in details.
+    Wifi,
+    /// Cumulative CPU seconds per cgroup; memory and IO counters in details.
+    Cgroup,
+    /// Audio playback, microphone and webcam activity as 1/0 samples.
+    Media,
+    /// Whether the built-in screen is lit, as 1/0; backlight brightness and DPMS in details.
+    Display,
+    /// Connected USB devices and the power budget (mA) they requested.
+    Usb,
+    /// Cumulative interrupt counts per IRQ and events per kernel wakeup source.
+    Interrupts,
+    /// symmetri's own overhead: timings, errors and memory use.
+    Internal,
 }
 
 impl MetricKind {
+    pub const ALL: [MetricKind; 18] = [
+        MetricKind::CpuUsage,
+        MetricKind::CpuFrequency,
+        MetricKind::GpuUsage,
+        MetricKind::GpuFrequency,
+        MetricKind::NetworkBytes,
+        MetricKind::MemoryUsage,
+        MetricKind::DiskUsage,
+        MetricKind::Temperature,
+        MetricKind::PowerDraw,
+        MetricKind::PowerProfile,
+        MetricKind::SystemLoad,
+        MetricKind::Wifi,
+        MetricKind::Cgroup,
+        MetricKind::Media,
+        MetricKind::Display,
+        MetricKind::Usb,
+        MetricKind::Interrupts,
+        MetricKind::Internal,
+    ];
+
     pub fn as_str(&self) -> &'static str {
         match self {
             MetricKind::CpuUsage => "cpu_usage",
@@ -35,6 +78,15 @@ impl MetricKind {
             MetricKind::DiskUsage => "disk_usage",
             MetricKind::Temperature => "temperature",
             MetricKind::PowerDraw => "power_draw",
+            MetricKind::PowerProfile => "power_profile",
+            MetricKind::SystemLoad => "system_load",
+            MetricKind::Wifi => "wifi",
+            MetricKind::Cgroup => "cgroup",
+            MetricKind::Media => "media",
+            MetricKind::Display => "display",
+            MetricKind::Usb => "usb",
+            MetricKind::Interrupts => "interrupts",
+            MetricKind::Internal => "internal",
         }
     }
 
@@ -49,9 +101,66 @@ impl MetricKind {
             "disk_usage" => Some(MetricKind::DiskUsage),
             "temperature" => Some(MetricKind::Temperature),
             "power_draw" => Some(MetricKind::PowerDraw),
+            "power_profile" => Some(MetricKind::PowerProfile),
+            "system_load" => Some(MetricKind::SystemLoad),
+            "wifi" => Some(MetricKind::Wifi),
+            "cgroup" => Some(MetricKind::Cgroup),
+            "media" => Some(MetricKind::Media),
+            "display" => Some(MetricKind::Display),
+            "usb" => Some(MetricKind::Usb),
+            "interrupts" => Some(MetricKind::Interrupts),
+            "internal" => Some(MetricKind::Internal),
             _ => None,
         }
     }
+
+    /// What a sample of this kind measures, for `symmetri db schema`.
+    pub fn description(&self) -> &'static str {
+        match self {
+            MetricKind::CpuUsage => "Busy share of CPU time, overall and per core",
+            MetricKind::CpuFrequency => "Current clock per CPU core",
+            MetricKind::GpuUsage => "GPU busy share",
+            MetricKind::GpuFrequency => "Current GPU clock",
+            MetricKind::NetworkBytes => "Cumulative bytes received and sent per interface",
+            MetricKind::MemoryUsage => "Used memory and swap",
+            MetricKind::DiskUsage => "Used space per mounted filesystem",
+            MetricKind::Temperature => "Sensor temperature",
+            MetricKind::PowerDraw => "Power drawn from the battery or adapter",
+            MetricKind::PowerProfile => "Active power profile, as details.profile (no value)",
+            MetricKind::SystemLoad => "Load averages and kernel activity rates from /proc",
+            MetricKind::Wifi => "Signal strength per interface; SSID and bitrates in details",
+            MetricKind::Cgroup => "Cumulative CPU seconds per cgroup; memory and IO in details",
+            MetricKind::Media => "Audio playback, microphone and webcam activity as 1/0",
+            MetricKind::Display => "Built-in screen lit as 1/0; brightness and DPMS in details",
+            MetricKind::Usb => "Power budget requested by each connected USB device",
+            MetricKind::Interrupts => {
+                "Cumulative interrupt counts per IRQ and wakeup source events"
+            }
+            MetricKind::Internal => "symmetri's own timings, errors and memory use",
+        }
+    }
+
+    /// The `unit` collectors store with this kind; `None` when it varies or is unitless.
+    pub fn unit(&self) -> Option<&'static str> {
+        match self {
+            MetricKind::CpuUsage | MetricKind::GpuUsage => Some("%"),
+            MetricKind::CpuFrequency | MetricKind::GpuFrequency => Some("MHz"),
+            MetricKind::NetworkBytes | MetricKind::MemoryUsage | MetricKind::DiskUsage => {
+                Some("bytes")
+            }
+            MetricKind::Temperature => Some("C"),
+            MetricKind::PowerDraw => Some("W"),
+            MetricKind::Wifi => Some("dBm"),
+            MetricKind::Cgroup => Some("s"),
+            MetricKind::Usb => Some("mA"),
+            MetricKind::PowerProfile
+            | MetricKind::SystemLoad
+            | MetricKind::Media
+            | MetricKind::Display
+            | MetricKind::Interrupts
+            | MetricKind::Internal => None,
+        }
+    }
 }
 
 impl FromStr for MetricKind {
@@ -233,43 +342,210 @@ fn cpu_frequency_samples(ts: f64) -> Vec<MetricSample> {
     samples
 }
 
-fn parse_meminfo() -> Option<(f64, f64)> {
-    let content = fs::read_to_string("/proc/meminfo").ok()?;
+/// Source of the RAM sample; swap and zram devices are recorded next to it.
+pub const MEMORY_SOURCE: &str = "memory";
+pub const SWAP_SOURCE: &str = "swap";
+
+/// Byte values from `/proc/meminfo`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Meminfo {
+    total: f64,
+    available: f64,
+    swap_total: Option<f64>,
+    swap_free: Option<f64>,
+}
+
+fn parse_meminfo(content: &str) -> Option<Meminfo> {
     let mut total_kb = None;
     let mut available_kb = None;
+    let mut swap_total_kb = None;
+    let mut swap_free_kb = None;
     for line in content.lines() {
         let mut parts = line.split_whitespace();
-        match parts.next()? {
-            "MemTotal:" => total_kb = parts.next()?.parse::<f64>().ok(),
-            "MemAvailable:" => available_kb = parts.next()?.parse::<f64>().ok(),
+        let (Some(key), Some(value)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        let slot = match key {
+            "MemTotal:" => &mut total_kb,
+            "MemAvailable:" => &mut available_kb,
+            "SwapTotal:" => &mut swap_total_kb,
+            "SwapFree:" => &mut swap_free_kb,
             _ => continue,
-        }
-    }
-    match (total_kb, available_kb) {
-        (Some(total), Some(avail)) => Some((total * 1024.0, avail * 1024.0)),
-        _ => None,
+        };
+        *slot = value.parse::<f64>().ok();
     }
+    let bytes = |kb: Option<f64>| kb.map(|kb| kb * 1024.0);
+    Some(Meminfo {
+        total: bytes(total_kb)?,
+        available: bytes(available_kb)?,
+        swap_total: bytes(swap_total_kb),
+        swap_free: bytes(swap_free_kb),
+    })
 }
 
-fn memory_samples(ts: f64) -> Vec<MetricSample> {
-    let (total, available) = match parse_meminfo() {
-        Some(v) => v,
-        None => return Vec::new(),
-    };
-    let used = (total - available).max(0.0);
-    let details = json!({
-        "total_bytes": total,
-        "available_bytes": available,
-        "used_bytes": used
-    });
-    vec![MetricSample::new(
+fn meminfo_samples(ts: f64, meminfo: &Meminfo) -> Vec<MetricSample> {
+    let used = (meminfo.total - meminfo.available).max(0.0);
+    let mut samples = vec![MetricSample::new(
         ts,
         MetricKind::MemoryUsage,
-        "memory",
+        MEMORY_SOURCE,
         Some(used),
         Some("bytes"),
-        details,
-    )]
+        json!({
+            "total_bytes": meminfo.total,
+            "available_bytes": meminfo.available,
+            "used_bytes": used
+        }),
+    )];
+    // Machines without swap report SwapTotal: 0; nothing worth recording.
+    if let (Some(total), Some(free)) = (meminfo.swap_total, meminfo.swap_free) {
+        if total > 0.0 {
+            let used = (total - free).max(0.0);
+            samples.push(MetricSample::new(
+                ts,
+                MetricKind::MemoryUsage,
+                SWAP_SOURCE,
+                Some(used),
+                Some("bytes"),
+                json!({
+                    "total_bytes": total,
+                    "free_bytes": free,
+                    "used_bytes": used
+                }),
+            ));
+        }
+    }
+    samples
+}
+
+/// One sample per active zram device from `<block>/zram*/mm_stat`: the value is the
+/// compressed size, `details.original_bytes` what it holds uncompressed.
+fn zram_samples_from(block: &Path, ts: f64) -> Vec<MetricSample> {
+    let Ok(entries) = fs::read_dir(block) else {
+        return Vec::new();
+    };
+    let mut devices: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with("zram"))
+        .collect();
+    devices.sort();
+    devices
+        .into_iter()
+        .filter_map(|device| {
+            let mm_stat = fs::read_to_string(block.join(&device).join("mm_stat")).ok()?;
+            let mut fields = mm_stat.split_whitespace().map(|f| f.parse::<f64>().ok());
+            let original = fields.next()??;
+            let compressed = fields.next()??;
+            let mem_used = fields.next().flatten();
+            if original <= 0.0 {
+                return None;
+            }
+            let mut details = json!({
+                "original_bytes": original,
+                "compressed_bytes": compressed,
+            });
+            if let Some(mem_used) = mem_used {
+                details["mem_used_bytes"] = json!(mem_used);
+            }
+            if compressed > 0.0 {
+                details["ratio"] = json!(original / compressed);
+            }
+            Some(MetricSample::new(
+                ts,
+                MetricKind::MemoryUsage,
+                device,
+                Some(compressed),
+                Some("bytes"),
+                details,
+            ))
+        })
+        .collect()
+}
+
+fn memory_samples(ts: f64) -> Vec<MetricSample> {
+    let Some(meminfo) = fs::read_to_string("/proc/meminfo")
+        .ok()
+        .and_then(|content| parse_meminfo(&content))
+    else {
+        return Vec::new();
+    };
+    let mut samples = meminfo_samples(ts, &meminfo);
+    samples.extend(zram_samples_from(Path::new("/sys/block"), ts));
+    samples
+}
+
+pub const LOAD_SOURCES: [&str; 3] = ["load1", "load5", "load15"];
+pub const CONTEXT_SWITCHES_SOURCE: &str = "context_switches";
+pub const INTERRUPTS_SOURCE: &str = "interrupts";
+const ACTIVITY_WINDOW: Duration = Duration::from_millis(250);
+
+/// The 1, 5 and 15 minute load averages from `/proc/loadavg`.
+fn parse_loadavg(content: &str) -> Option<[f64; 3]> {
+    let mut fields = content.split_whitespace().map(|f| f.parse::<f64>().ok());
+    Some([fields.next()??, fields.next()??, fields.next()??])
+}
+
+/// Context switch and interrupt totals since boot (`ctxt` and `intr` in `/proc/stat`).
+fn parse_activity_counters(content: &str) -> Option<(u64, u64)> {
+    let mut ctxt = None;
+    let mut intr = None;
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            Some("ctxt") => ctxt = parts.next().and_then(|v| v.parse().ok()),
+            Some("intr") => intr = parts.next().and_then(|v| v.parse().ok()),
+            _ => {}
+        }
+    }
+    Some((ctxt?, intr?))
+}
+
+fn load_samples(ts: f64) -> Vec<MetricSample> {
+    let mut samples = Vec::new();
+    if let Some(loads) = fs::read_to_string("/proc/loadavg")
+        .ok()
+        .and_then(|content| parse_loadavg(&content))
+    {
+        for (source, load) in LOAD_SOURCES.into_iter().zip(loads) {
+            samples.push(MetricSample::new(
+                ts,
+                MetricKind::SystemLoad,
+                source,
+                Some(load),
+                None,
+                Value::Null,
+            ));
+        }
+    }
+
+    let read_counters = || {
+        fs::read_to_string("/proc/stat")
+            .ok()
+            .and_then(|content| parse_activity_counters(&content))
+    };
+    let Some((ctxt_before, intr_before)) = read_counters() else {
+        return samples;
+    };
+    thread::sleep(ACTIVITY_WINDOW);
+    let Some((ctxt_after, intr_after)) = read_counters() else {
+        return samples;
+    };
+    let seconds = ACTIVITY_WINDOW.as_secs_f64();
+    for (source, before, after) in [
+        (CONTEXT_SWITCHES_SOURCE, ctxt_before, ctxt_after),
+        (INTERRUPTS_SOURCE, intr_before, intr_after),
+    ] {
+        samples.push(MetricSample::new(
+            ts,
+            MetricKind::SystemLoad,
+            source,
+            Some(after.saturating_sub(before) as f64 / seconds),
+            Some("/s"),
+            Value::Null,
+        ));
+    }
+    samples
 }
 
 fn network_samples(ts: f64) -> Vec<MetricSample> {
@@ -317,78 +593,6 @@ fn network_samples(ts: f64) -> Vec<MetricSample> {
     samples
 }
 
-fn disk_samples(ts: f64) -> Vec<MetricSample> {
-    let path = Path::new("/");
-    let c_path = match CString::new(path.as_os_str().as_bytes()) {
-        Ok(c) => c,
-        Err(_) => return Vec::new(),
-    };
-    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
-    let rc = unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) };
-    if rc != 0 {
-        return Vec::new();
-    }
-    let block_size = stat.f_frsize;
-    let total = block_size * stat.f_blocks;
-    let free = block_size * stat.f_bfree;
-    let available = block_size * stat.f_bavail;
-    let used = total.saturating_sub(free);
-    let details = json!({
-        "total_bytes": total as f64,
-        "available_bytes": available as f64,
-        "free_bytes": free as f64
-    });
-    vec![MetricSample::new(
-        ts,
-        MetricKind::DiskUsage,
-        path.display().to_string(),
-        Some(used as f64),
-        Some("bytes"),
-        details,
-    )]
-}
-
-fn temperature_samples(ts: f64) -> Vec<MetricSample> {
-    let root = Path::new("/sys/class/thermal");
-    let entries = match fs::read_dir(root) {
-        Ok(entries) => entries,
-        Err(_) => return Vec::new(),
-    };
-    let mut samples = Vec::new();
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if !entry
-            .file_name()
-            .to_string_lossy()
-            .starts_with("thermal_zone")
-        {
-            continue;
-        }
-        let label = fs::read_to_string(path.join("type"))
-            .ok()
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .unwrap_or_else(|| entry.file_name().to_string_lossy().to_string());
-        let temp_mc = match fs::read_to_string(path.join("temp"))
-            .ok()
-            .and_then(|s| s.trim().parse::<f64>().ok())
-        {
-            Some(v) => v,
-            None => continue,
-        };
-        let temp_c = temp_mc / 1000.0;
-        samples.push(MetricSample::new(
-            ts,
-            MetricKind::Temperature,
-            label,
-            Some(temp_c),
-            Some("C"),
-            Value::Null,
-        ));
-    }
-    samples
-}
-
 fn parse_pp_dpm_sclk(path: &Path) -> Option<f64> {
     let content = fs::read_to_string(path).ok()?;
     for line in content.lines() {
@@ -496,19 +700,227 @@ fn power_samples(ts: f64) -> Vec<MetricSample> {
     samples
 }
 
-pub fn collect_metrics(ts: f64) -> Vec<MetricSample> {
-    let cpu_usage_handle = thread::spawn(move || cpu_usage_samples(ts));
-
-    let mut metrics = Vec::new();
-    metrics.extend(cpu_frequency_samples(ts));
-    metrics.extend(memory_samples(ts));
-    metrics.extend(network_samples(ts));
-    metrics.extend(disk_samples(ts));
-    metrics.extend(temperature_samples(ts));
-    metrics.extend(gpu_samples(ts));
-    metrics.extend(power_samples(ts));
-    if let Ok(cpu_samples) = cpu_usage_handle.join() {
-        metrics.extend(cpu_samples);
+pub const PLATFORM_PROFILE_SOURCE: &str = "platform_profile";
+pub const CPU_GOVERNOR_SOURCE: &str = "cpu_governor";
+
+/// Active power profile (ACPI platform_profile, as set by power-profiles-daemon) and
+/// the cpufreq governor. Stored as categorical samples: no value, name in `details.profile`.
+fn power_profile_samples_from(sys_root: &Path, ts: f64) -> Vec<MetricSample> {
+    let mut samples = Vec::new();
+    let sources = [
+        (
+            PLATFORM_PROFILE_SOURCE,
+            sys_root.join("firmware/acpi/platform_profile"),
+        ),
+        (
+            CPU_GOVERNOR_SOURCE,
+            sys_root.join("devices/system/cpu/cpu0/cpufreq/scaling_governor"),
+        ),
+    ];
+    for (source, path) in sources {
+        let profile = match fs::read_to_string(&path) {
+            Ok(raw) if !raw.trim().is_empty() => raw.trim().to_string(),
+            _ => continue,
+        };
+        samples.push(MetricSample::new(
+            ts,
+            MetricKind::PowerProfile,
+            source,
+            None,
+            None,
+            json!({ "profile": profile }),
+        ));
+    }
+    samples
+}
+
+fn power_profile_samples(ts: f64) -> Vec<MetricSample> {
+    power_profile_samples_from(Path::new("/sys"), ts)
+}
+
+/// A metric family read straight from sysfs/procfs.
+struct BuiltinCollector {
+    name: &'static str,
+    kinds: &'static [MetricKind],
+    collect: fn(f64) -> Vec<MetricSample>,
+}
+
+impl Collector for BuiltinCollector {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn kinds(&self) -> &[MetricKind] {
+        self.kinds
+    }
+
+    fn collect(&self, ts: f64) -> Vec<MetricSample> {
+        (self.collect)(ts)
+    }
+}
+
+pub fn builtin_collectors() -> Vec<Box<dyn Collector>> {
+    let builtins: Vec<BuiltinCollector> = vec![
+        #[cfg(feature = "collector-cpu")]
+        BuiltinCollector {
+            name: "cpu_usage",
+            kinds: &[MetricKind::CpuUsage],
+            collect: cpu_usage_samples,
+        },
+        #[cfg(feature = "collector-cpu")]
+        BuiltinCollector {
+            name: "cpu_frequency",
+            kinds: &[MetricKind::CpuFrequency],
+            collect: cpu_frequency_samples,
+        },
+        #[cfg(feature = "collector-memory")]
+        BuiltinCollector {
+            name: "memory",
+            kinds: &[MetricKind::MemoryUsage],
+            collect: memory_samples,
+        },
+        #[cfg(feature = "collector-network")]
+        BuiltinCollector {
+            name: "network",
+            kinds: &[MetricKind::NetworkBytes],
+            collect: network_samples,
+        },
+        #[cfg(feature = "collector-gpu")]
+        BuiltinCollector {
+            name: "gpu",
+            kinds: &[MetricKind::GpuUsage, MetricKind::GpuFrequency],
+            collect: gpu_samples,
+        },
+        #[cfg(feature = "collector-power")]
+        BuiltinCollector {
+            name: "power",
+            kinds: &[MetricKind::PowerDraw],
+            collect: power_samples,
+        },
+        #[cfg(feature = "collector-load")]
+        BuiltinCollector {
+            name: "load",
+            kinds: &[MetricKind::SystemLoad],
+            collect: load_samples,
+        },
+        #[cfg(feature = "collector-media")]
+        BuiltinCollector {
+            name: "media",
+            kinds: &[MetricKind::Media],
+            collect: crate::media::media_samples,
+        },
+        #[cfg(feature = "collector-display")]
+        BuiltinCollector {
+            name: "display",
+            kinds: &[MetricKind::Display],
+            collect: crate::display::display_samples,
+        },
+        #[cfg(feature = "collector-usb")]
+        BuiltinCollector {
+            name: "usb",
+            kinds: &[MetricKind::Usb],
+            collect: crate::usb::usb_samples,
+        },
+        #[cfg(feature = "collector-interrupts")]
+        BuiltinCollector {
+            name: "interrupts",
+            kinds: &[MetricKind::Interrupts],
+            collect: crate::interrupts::interrupt_samples,
+        },
+        #[cfg(feature = "collector-power-profile")]
+        BuiltinCollector {
+            name: "power_profile",
+            kinds: &[MetricKind::PowerProfile],
+            collect: power_profile_samples,
+        },
+    ];
+    builtins
+        .into_iter()
+        .map(|collector| Box::new(collector) as Box<dyn Collector>)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_kinds_round_trip_their_labels() {
+        for kind in MetricKind::ALL {
+            assert_eq!(MetricKind::from_label(kind.as_str()), Some(kind.clone()));
+            assert!(!kind.description().is_empty());
+        }
+    }
+
+    #[test]
+    fn swap_and_zram_are_separate_memory_sources() {
+        let meminfo = parse_meminfo(
+            "MemTotal:       16000000 kB\nMemAvailable:    8000000 kB\nSwapTotal:       4000000 kB\nSwapFree:        3000000 kB\n",
+        )
+        .unwrap();
+        let samples = meminfo_samples(1.0, &meminfo);
+        let sources: Vec<&str> = samples.iter().map(|s| s.source.as_str()).collect();
+        assert_eq!(sources, vec![MEMORY_SOURCE, SWAP_SOURCE]);
+        assert_eq!(samples[1].value, Some(1_000_000.0 * 1024.0));
+        assert_eq!(samples[1].details["total_bytes"], 4_000_000.0 * 1024.0);
+
+        let no_swap = parse_meminfo(
+            "MemTotal: 1000 kB\nMemAvailable: 500 kB\nSwapTotal: 0 kB\nSwapFree: 0 kB\n",
+        )
+        .unwrap();
+        assert_eq!(meminfo_samples(1.0, &no_swap).len(), 1);
+
+        let tmp = tempfile::tempdir().unwrap();
+        for (device, mm_stat) in [
+            ("zram0", "4194304 1048576 1261568 0 1261568 12 0 0 0\n"),
+            ("zram1", "0 0 0 0 0 0 0 0 0\n"),
+        ] {
+            fs::create_dir_all(tmp.path().join(device)).unwrap();
+            fs::write(tmp.path().join(device).join("mm_stat"), mm_stat).unwrap();
+        }
+        fs::create_dir_all(tmp.path().join("nvme0n1")).unwrap();
+        let zram = zram_samples_from(tmp.path(), 1.0);
+        assert_eq!(zram.len(), 1);
+        assert_eq!(zram[0].source, "zram0");
+        assert_eq!(zram[0].value, Some(1_048_576.0));
+        assert_eq!(zram[0].details["original_bytes"], 4_194_304.0);
+        assert_eq!(zram[0].details["ratio"], 4.0);
+    }
+
+    #[test]
+    fn load_and_activity_counters_are_parsed() {
+        assert_eq!(
+            parse_loadavg("0.52 0.58 0.59 2/1123 45678\n"),
+            Some([0.52, 0.58, 0.59])
+        );
+        assert_eq!(parse_loadavg("garbage"), None);
+
+        let stat = "cpu  1 2 3 4 5 6 7 8\nintr 987654 12 0 3\nctxt 123456\nbtime 1700000000\n";
+        assert_eq!(parse_activity_counters(stat), Some((123_456, 987_654)));
+        assert_eq!(parse_activity_counters("cpu  1 2 3\n"), None);
+    }
+
+    #[test]
+    fn power_profile_reads_platform_profile_and_governor() {
+        let tmp = tempfile::tempdir().unwrap();
+        let acpi = tmp.path().join("firmware/acpi");
+        let cpufreq = tmp.path().join("devices/system/cpu/cpu0/cpufreq");
+        fs::create_dir_all(&acpi).unwrap();
+        fs::create_dir_all(&cpufreq).unwrap();
+        fs::write(acpi.join("platform_profile"), "low-power\n").unwrap();
+        fs::write(cpufreq.join("scaling_governor"), "powersave\n").unwrap();
+
+        let samples = power_profile_samples_from(tmp.path(), 1.0);
+        assert_eq!(samples.len(), 2);
+        assert!(samples.iter().all(|s| s.kind == MetricKind::PowerProfile));
+        assert_eq!(samples[0].source, PLATFORM_PROFILE_SOURCE);
+        assert_eq!(samples[0].details["profile"], "low-power");
+        assert_eq!(samples[1].details["profile"], "powersave");
+    }
+
+    #[test]
+    fn power_profile_skips_missing_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(power_profile_samples_from(tmp.path(), 1.0).is_empty());
     }
-    metrics
 }
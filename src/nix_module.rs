@@ -0,0 +1,297 @@
+//! `symmetri nix-module`: the systemd units of `systemd/` (collector, timer and evening
+//! summary) and the current config file as a NixOS or home-manager module, so a NixOS
+//! deployment is a copy-paste instead of hand-written services.
+
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// The system-wide collector's database, as in `systemd/symmetri.service`.
+pub const SYSTEM_DB: &str = "/var/lib/symmetri/symmetri.sqlite";
+const SYSTEM_CONFIG: &str = "/etc/symmetri/config.toml";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NixModule {
+    /// A home-manager module with user units instead of a NixOS one with a system service.
+    pub home_manager: bool,
+    /// Where the collector writes. `None` keeps the default: [`SYSTEM_DB`] for NixOS, the
+    /// user's database for home-manager.
+    pub db_path: Option<PathBuf>,
+    /// Contents of the config file, installed to `/etc/symmetri` or `~/.config/symmetri`.
+    pub config: Option<String>,
+    /// Seconds between collections.
+    pub interval_secs: u64,
+}
+
+/// `value` as a double-quoted Nix string.
+fn nix_string(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("${", "\\${");
+    format!("\"{escaped}\"")
+}
+
+/// `text` as an indented Nix string whose lines start at `indent`.
+fn nix_indented(text: &str, indent: &str) -> String {
+    let mut out = String::from("''\n");
+    for line in text.lines() {
+        let line = line.replace("''", "'''").replace("${", "''${");
+        if line.is_empty() {
+            out.push('\n');
+        } else {
+            let _ = writeln!(out, "{indent}  {line}");
+        }
+    }
+    out.push_str(indent);
+    out.push_str("''");
+    out
+}
+
+/// A systemd time span: `5min` rather than `300s` when it divides evenly.
+fn time_span(secs: u64) -> String {
+    if secs >= 60 && secs.is_multiple_of(60) {
+        format!("{}min", secs / 60)
+    } else {
+        format!("{secs}s")
+    }
+}
+
+impl NixModule {
+    pub fn render(&self) -> String {
+        if self.home_manager {
+            self.home_manager_module()
+        } else {
+            self.nixos_module()
+        }
+    }
+
+    fn db(&self) -> Option<String> {
+        self.db_path.as_ref().map(|path| path.display().to_string())
+    }
+
+    fn header(out: &mut String, kind: &str) {
+        let _ = writeln!(out, "# {kind} module generated by `symmetri nix-module`.");
+        let _ = writeln!(
+            out,
+            "# Import it with the package of the symmetri flake input, e.g.\n\
+             #   imports = [ (import ./symmetri.nix {{ symmetri = symmetri.packages.${{system}}.default; }}) ];"
+        );
+        out.push_str("{ symmetri }:\n{ ... }:\n{\n");
+    }
+
+    fn nixos_module(&self) -> String {
+        let db = self.db().unwrap_or_else(|| SYSTEM_DB.to_string());
+        let mut out = String::new();
+        Self::header(&mut out, "NixOS");
+        out.push_str(
+            "  environment.systemPackages = [ symmetri ];\n\
+             \n\
+             \x20 # Members of this group can read the database, e.g.\n\
+             \x20 # users.users.alice.extraGroups = [ \"symmetri\" ];\n\
+             \x20 users.users.symmetri = {\n\
+             \x20   isSystemUser = true;\n\
+             \x20   group = \"symmetri\";\n\
+             \x20 };\n\
+             \x20 users.groups.symmetri = { };\n\
+             \n",
+        );
+        if let Some(config) = &self.config {
+            let _ = writeln!(
+                out,
+                "  environment.etc.\"symmetri/config.toml\".text = {};\n",
+                nix_indented(config, "  ")
+            );
+        }
+        let _ = writeln!(
+            out,
+            "  systemd.services.symmetri = {{\n\
+             \x20   description = \"Symmetri metrics collector\";\n\
+             \x20   after = [ \"network.target\" ];\n\
+             \x20   unitConfig.ConditionPathExists = \"/sys\";\n\
+             \x20   environment = {{\n\
+             \x20     SYMMETRI_DB = {db};\n\
+             \x20     SYMMETRI_CONFIG = {config};\n\
+             \x20   }};\n\
+             \x20   serviceConfig = {{\n\
+             \x20     Type = \"oneshot\";\n\
+             \x20     User = \"symmetri\";\n\
+             \x20     Group = \"symmetri\";\n\
+             \x20     ExecStart = \"${{symmetri}}/bin/symmetri-collect\";\n\
+             \x20     StateDirectory = \"symmetri\";\n\
+             \x20     StateDirectoryMode = \"0750\";\n\
+             \x20     UMask = \"0027\";\n\
+             \x20   }};\n\
+             \x20 }};\n\
+             \n\
+             \x20 systemd.timers.symmetri = {{\n\
+             \x20   description = \"Collect system metrics periodically\";\n\
+             \x20   wantedBy = [ \"timers.target\" ];\n\
+             \x20   timerConfig = {{\n\
+             \x20     OnBootSec = \"1min\";\n\
+             \x20     OnUnitActiveSec = \"{interval}\";\n\
+             \x20     AccuracySec = \"1s\";\n\
+             \x20   }};\n\
+             \x20 }};\n\
+             \n\
+             \x20 # The summary notifies on the desktop, so it runs in each user's session.\n\
+             \x20 systemd.user.services.symmetri-summary = {{\n\
+             \x20   description = \"Symmetri daily battery summary notification\";\n\
+             \x20   environment.SYMMETRI_DB = {db};\n\
+             \x20   serviceConfig = {{\n\
+             \x20     Type = \"oneshot\";\n\
+             \x20     ExecStart = [\n\
+             \x20       \"${{symmetri}}/bin/symmetri summary --notify\"\n\
+             \x20       \"-${{symmetri}}/bin/symmetri health --notify\"\n\
+             \x20     ];\n\
+             \x20   }};\n\
+             \x20 }};\n\
+             \n\
+             \x20 systemd.user.timers.symmetri-summary = {{\n\
+             \x20   description = \"Send the daily battery summary each evening\";\n\
+             \x20   wantedBy = [ \"timers.target\" ];\n\
+             \x20   timerConfig = {{\n\
+             \x20     OnCalendar = \"*-*-* 21:00\";\n\
+             \x20     Persistent = false;\n\
+             \x20   }};\n\
+             \x20 }};\n\
+             }}",
+            db = nix_string(&db),
+            config = nix_string(SYSTEM_CONFIG),
+            interval = time_span(self.interval_secs),
+        );
+        out
+    }
+
+    fn home_manager_module(&self) -> String {
+        let environment = self
+            .db()
+            .map(|db| {
+                format!(
+                    "      Environment = [ {} ];\n",
+                    nix_string(&format!("SYMMETRI_DB={db}"))
+                )
+            })
+            .unwrap_or_default();
+        let mut out = String::new();
+        Self::header(&mut out, "home-manager");
+        out.push_str("  home.packages = [ symmetri ];\n\n");
+        if let Some(config) = &self.config {
+            let _ = writeln!(
+                out,
+                "  xdg.configFile.\"symmetri/config.toml\".text = {};\n",
+                nix_indented(config, "  ")
+            );
+        }
+        let _ = writeln!(
+            out,
+            "  systemd.user.services.symmetri = {{\n\
+             \x20   Unit = {{\n\
+             \x20     Description = \"Symmetri metrics collector\";\n\
+             \x20     ConditionPathExists = \"/sys\";\n\
+             \x20   }};\n\
+             \x20   Service = {{\n\
+             \x20     Type = \"oneshot\";\n\
+             {environment}\
+             \x20     ExecStart = \"${{symmetri}}/bin/symmetri-collect\";\n\
+             \x20   }};\n\
+             \x20 }};\n\
+             \n\
+             \x20 systemd.user.timers.symmetri = {{\n\
+             \x20   Unit.Description = \"Collect system metrics periodically\";\n\
+             \x20   Timer = {{\n\
+             \x20     OnBootSec = \"1min\";\n\
+             \x20     OnUnitActiveSec = \"{interval}\";\n\
+             \x20     AccuracySec = \"1s\";\n\
+             \x20   }};\n\
+             \x20   Install.WantedBy = [ \"timers.target\" ];\n\
+             \x20 }};\n\
+             \n\
+             \x20 systemd.user.services.symmetri-summary = {{\n\
+             \x20   Unit.Description = \"Symmetri daily battery summary notification\";\n\
+             \x20   Service = {{\n\
+             \x20     Type = \"oneshot\";\n\
+             {environment}\
+             \x20     ExecStart = [\n\
+             \x20       \"${{symmetri}}/bin/symmetri summary --notify\"\n\
+             \x20       \"-${{symmetri}}/bin/symmetri health --notify\"\n\
+             \x20     ];\n\
+             \x20   }};\n\
+             \x20 }};\n\
+             \n\
+             \x20 systemd.user.timers.symmetri-summary = {{\n\
+             \x20   Unit.Description = \"Send the daily battery summary each evening\";\n\
+             \x20   Timer = {{\n\
+             \x20     OnCalendar = \"*-*-* 21:00\";\n\
+             \x20     Persistent = false;\n\
+             \x20   }};\n\
+             \x20   Install.WantedBy = [ \"timers.target\" ];\n\
+             \x20 }};\n\
+             }}",
+            interval = time_span(self.interval_secs),
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nixos(config: Option<&str>) -> String {
+        NixModule {
+            home_manager: false,
+            db_path: None,
+            config: config.map(str::to_string),
+            interval_secs: 300,
+        }
+        .render()
+    }
+
+    fn home_manager(db_path: Option<&str>) -> String {
+        NixModule {
+            home_manager: true,
+            db_path: db_path.map(PathBuf::from),
+            config: None,
+            interval_secs: 90,
+        }
+        .render()
+    }
+
+    #[test]
+    fn nixos_modules_run_a_system_service() {
+        let module = nixos(None);
+        assert!(module.contains("systemd.services.symmetri = {"));
+        assert!(module.contains("SYMMETRI_DB = \"/var/lib/symmetri/symmetri.sqlite\";"));
+        assert!(module.contains("OnUnitActiveSec = \"5min\";"));
+        assert!(module.contains("ExecStart = \"${symmetri}/bin/symmetri-collect\";"));
+        assert_eq!(module.matches('{').count(), module.matches('}').count());
+    }
+
+    #[test]
+    fn home_manager_modules_run_a_user_timer() {
+        let module = home_manager(None);
+        assert!(module.contains("systemd.user.timers.symmetri = {"));
+        assert!(module.contains("OnUnitActiveSec = \"90s\";"));
+        assert!(!module.contains("xdg.configFile"));
+        assert!(!module.contains("systemd.services"));
+        assert_eq!(module.matches('{').count(), module.matches('}').count());
+    }
+
+    #[test]
+    fn config_text_is_escaped_in_an_indented_string() {
+        let module = nixos(Some("[graph]\ndir = \"${HOME}/graphs\"\n\n# it''s here\n"));
+        assert!(module.contains("environment.etc.\"symmetri/config.toml\".text = ''\n"));
+        assert!(module.contains("    dir = \"''${HOME}/graphs\"\n\n    # it'''s here\n  '';"));
+    }
+
+    #[test]
+    fn database_paths_are_escaped_in_strings() {
+        let module = home_manager(Some("/home/me/${odd}/symmetri.sqlite"));
+        assert_eq!(
+            module
+                .matches("Environment = [ \"SYMMETRI_DB=/home/me/\\${odd}/symmetri.sqlite\" ];")
+                .count(),
+            2
+        );
+    }
+}
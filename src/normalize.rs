@@ -0,0 +1,291 @@
+use std::path::Path;
+
+use crate::config::{QuirkOverrides, UnitScale};
+use crate::sysfs::BatteryReading;
+
+/// A micro-unit capacity or voltage below this would mean under 1 Wh, 1 Ah or 1 V, which
+/// no laptop battery has; the driver is writing milli-units.
+const MICRO_FLOOR: f64 = 1_000_000.0;
+
+/// A discharging laptop draws well over this; a smaller `power_now` read as microwatts
+/// is really milliwatts.
+const MIN_DISCHARGE_W: f64 = 0.1;
+
+/// Power-supply attributes as the driver wrote them, before any unit conversion.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RawBattery {
+    pub energy_now: Option<f64>,
+    pub energy_full: Option<f64>,
+    pub energy_full_design: Option<f64>,
+    pub charge_now: Option<f64>,
+    pub charge_full: Option<f64>,
+    pub charge_full_design: Option<f64>,
+    pub voltage_now: Option<f64>,
+    /// `voltage_min_design`, else `voltage_max_design`.
+    pub voltage_design: Option<f64>,
+    pub power_now: Option<f64>,
+    pub current_now: Option<f64>,
+    pub capacity: Option<f64>,
+    pub status: Option<String>,
+}
+
+/// Unit scale of each attribute family on one battery.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quirks {
+    pub energy: UnitScale,
+    pub charge: UnitScale,
+    pub voltage: UnitScale,
+    pub power: UnitScale,
+    pub current: UnitScale,
+}
+
+impl Quirks {
+    /// Scales guessed from the magnitudes in `raw`; `overrides` win over the guesses.
+    pub fn detect(raw: &RawBattery, overrides: &QuirkOverrides) -> Self {
+        let energy = overrides
+            .energy
+            .unwrap_or_else(|| scale_of([raw.energy_full_design, raw.energy_full, raw.energy_now]));
+        let charge = overrides
+            .charge
+            .unwrap_or_else(|| scale_of([raw.charge_full_design, raw.charge_full, raw.charge_now]));
+        let voltage = overrides
+            .voltage
+            .unwrap_or_else(|| scale_of([raw.voltage_now, raw.voltage_design]));
+        // Current has no magnitude of its own to go by; drivers use the charge scale.
+        let current = overrides.current.unwrap_or(charge);
+        let power = overrides
+            .power
+            .unwrap_or_else(|| power_scale(raw, energy, current, voltage));
+        Quirks {
+            energy,
+            charge,
+            voltage,
+            power,
+            current,
+        }
+    }
+}
+
+fn scale_of<const N: usize>(values: [Option<f64>; N]) -> UnitScale {
+    match values.into_iter().flatten().find(|value| *value > 0.0) {
+        Some(value) if value < MICRO_FLOOR => UnitScale::Milli,
+        _ => UnitScale::Micro,
+    }
+}
+
+/// `power_now` follows the scale that agrees with `current_now × voltage_now` when both
+/// are exposed, else the energy scale. A discharge draw too small to be real under the
+/// energy scale means milliwatts.
+fn power_scale(
+    raw: &RawBattery,
+    energy: UnitScale,
+    current: UnitScale,
+    voltage: UnitScale,
+) -> UnitScale {
+    let Some(power) = raw.power_now.map(f64::abs).filter(|power| *power > 0.0) else {
+        return energy;
+    };
+    if let (Some(current_now), Some(voltage_now)) = (raw.current_now, raw.voltage_now) {
+        let expected_w = (current_now * current.factor() * voltage_now * voltage.factor()).abs();
+        if expected_w > 0.0 {
+            let error = |scale: UnitScale| (power * scale.factor() / expected_w).ln().abs();
+            return if error(UnitScale::Milli) < error(UnitScale::Micro) {
+                UnitScale::Milli
+            } else {
+                UnitScale::Micro
+            };
+        }
+    }
+    let discharging = raw.status.as_deref() == Some("Discharging");
+    if discharging && energy == UnitScale::Micro && power * energy.factor() < MIN_DISCHARGE_W {
+        UnitScale::Milli
+    } else {
+        energy
+    }
+}
+
+fn energy_wh(
+    energy: Option<f64>,
+    charge: Option<f64>,
+    voltage_v: Option<f64>,
+    quirks: &Quirks,
+) -> Option<f64> {
+    energy
+        .map(|value| value * quirks.energy.factor())
+        .or_else(|| {
+            let charge_ah = charge? * quirks.charge.factor();
+            Some(charge_ah * voltage_v?)
+        })
+}
+
+/// Reading of `raw` in W, Wh and percent. Charge-only batteries are converted with the
+/// present voltage (else the design voltage); percentages are clamped to 0–100.
+pub fn normalize(path: &Path, raw: &RawBattery, quirks: &Quirks) -> BatteryReading {
+    let voltage_v = raw
+        .voltage_now
+        .or(raw.voltage_design)
+        .map(|value| value * quirks.voltage.factor());
+    let energy_now_wh = energy_wh(raw.energy_now, raw.charge_now, voltage_v, quirks);
+    let energy_full_wh = energy_wh(raw.energy_full, raw.charge_full, voltage_v, quirks);
+    let energy_full_design_wh = energy_wh(
+        raw.energy_full_design,
+        raw.charge_full_design,
+        voltage_v,
+        quirks,
+    );
+    let ratio_pct = |part: Option<f64>, whole: Option<f64>| match (part, whole) {
+        (Some(part), Some(whole)) if whole != 0.0 => Some(part / whole * 100.0),
+        _ => None,
+    };
+    BatteryReading {
+        path: path.to_path_buf(),
+        capacity_pct: raw.capacity.map(|pct| pct.clamp(0.0, 100.0)),
+        percentage: ratio_pct(energy_now_wh, energy_full_wh).map(|pct| pct.clamp(0.0, 100.0)),
+        energy_now_wh,
+        energy_full_wh,
+        energy_full_design_wh,
+        health_pct: ratio_pct(energy_full_wh, energy_full_design_wh),
+        status: raw.status.clone(),
+    }
+}
+
+/// Power flowing out of (or into) the battery in watts: `power_now`, else `current_now`
+/// times `voltage_now`. Drivers disagree on the sign, so the magnitude is returned.
+pub fn power_w(raw: &RawBattery, quirks: &Quirks) -> Option<f64> {
+    if let Some(power) = raw.power_now {
+        return Some(power.abs() * quirks.power.factor());
+    }
+    let current_a = raw.current_now? * quirks.current.factor();
+    let voltage_v = raw.voltage_now? * quirks.voltage.factor();
+    Some((current_a * voltage_v).abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(value: Option<f64>, expected: f64) -> bool {
+        value.is_some_and(|value| (value - expected).abs() < 1e-6)
+    }
+
+    fn reading(raw: &RawBattery) -> (Quirks, BatteryReading) {
+        let quirks = Quirks::detect(raw, &QuirkOverrides::default());
+        (quirks, normalize(Path::new("/sys/BAT0"), raw, &quirks))
+    }
+
+    #[test]
+    fn known_problem_devices_normalize_to_watts_and_watt_hours() {
+        // Reference driver: µWh and µW, as the ABI says.
+        let reference = RawBattery {
+            energy_now: Some(40_000_000.0),
+            energy_full: Some(80_000_000.0),
+            energy_full_design: Some(90_000_000.0),
+            power_now: Some(8_500_000.0),
+            status: Some("Discharging".to_string()),
+            ..RawBattery::default()
+        };
+        let (quirks, battery) = reading(&reference);
+        assert_eq!(quirks, Quirks::default());
+        assert!(close(battery.energy_now_wh, 40.0));
+        assert!(close(battery.percentage, 50.0));
+        assert!(close(power_w(&reference, &quirks), 8.5));
+
+        // Charge-only gauge (µAh and µV), converted with the present voltage.
+        let charge_only = RawBattery {
+            charge_now: Some(2_000_000.0),
+            charge_full: Some(4_000_000.0),
+            charge_full_design: Some(4_500_000.0),
+            voltage_now: Some(11_000_000.0),
+            current_now: Some(-1_000_000.0),
+            capacity: Some(50.0),
+            ..RawBattery::default()
+        };
+        let (quirks, battery) = reading(&charge_only);
+        assert_eq!(quirks, Quirks::default());
+        assert!(close(battery.energy_full_design_wh, 49.5));
+        assert!(close(power_w(&charge_only, &quirks), 11.0));
+
+        // Firmware writing mWh and mW, and overshooting full while charging.
+        let milli_energy = RawBattery {
+            energy_now: Some(46_000.0),
+            energy_full: Some(45_000.0),
+            energy_full_design: Some(50_000.0),
+            power_now: Some(12_000.0),
+            capacity: Some(101.0),
+            status: Some("Charging".to_string()),
+            ..RawBattery::default()
+        };
+        let (quirks, battery) = reading(&milli_energy);
+        assert_eq!(
+            (quirks.energy, quirks.power),
+            (UnitScale::Milli, UnitScale::Milli)
+        );
+        assert!(close(battery.energy_full_wh, 45.0));
+        assert!(close(battery.health_pct, 90.0));
+        assert_eq!(battery.percentage, Some(100.0));
+        assert_eq!(battery.capacity_pct, Some(100.0));
+        assert!(close(power_w(&milli_energy, &quirks), 12.0));
+
+        // µWh energy but power_now in mW, caught against current × voltage...
+        let milli_power = RawBattery {
+            power_now: Some(9_000.0),
+            current_now: Some(750_000.0),
+            voltage_now: Some(12_000_000.0),
+            ..reference.clone()
+        };
+        let (quirks, _) = reading(&milli_power);
+        assert_eq!(
+            (quirks.energy, quirks.power),
+            (UnitScale::Micro, UnitScale::Milli)
+        );
+        assert!(close(power_w(&milli_power, &quirks), 9.0));
+        // ...or by a discharge draw too small to be real.
+        let (quirks, _) = reading(&RawBattery {
+            current_now: None,
+            ..milli_power
+        });
+        assert_eq!(quirks.power, UnitScale::Milli);
+
+        // mAh and mV gauge.
+        let milli_charge = RawBattery {
+            charge_now: Some(3_000.0),
+            charge_full: Some(6_000.0),
+            voltage_design: Some(7_600.0),
+            ..RawBattery::default()
+        };
+        let (quirks, battery) = reading(&milli_charge);
+        assert_eq!(
+            (quirks.charge, quirks.voltage, quirks.current),
+            (UnitScale::Milli, UnitScale::Milli, UnitScale::Milli)
+        );
+        assert!(close(battery.energy_full_wh, 45.6));
+        assert!(close(battery.percentage, 50.0));
+    }
+
+    #[test]
+    fn config_overrides_win_over_detection() {
+        // A small cell whose µWh values look like milli-units to the detector.
+        let raw = RawBattery {
+            energy_now: Some(400_000.0),
+            energy_full: Some(800_000.0),
+            power_now: Some(500_000.0),
+            ..RawBattery::default()
+        };
+        assert_eq!(
+            Quirks::detect(&raw, &QuirkOverrides::default()).energy,
+            UnitScale::Milli
+        );
+        let overrides = QuirkOverrides {
+            energy: Some(UnitScale::Micro),
+            ..QuirkOverrides::default()
+        };
+        let quirks = Quirks::detect(&raw, &overrides);
+        assert_eq!(
+            (quirks.energy, quirks.power),
+            (UnitScale::Micro, UnitScale::Micro)
+        );
+        let battery = normalize(Path::new("/sys/BAT0"), &raw, &quirks);
+        assert!(close(battery.energy_full_wh, 0.8));
+        assert!(close(power_w(&raw, &quirks), 0.5));
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::config::{OtlpConfig, PrivacyConfig};
+use crate::db::Sample;
+use crate::http;
+use crate::metrics::{MetricKind, MetricSample};
+
+/// Kinds whose value is a running counter rather than a reading; they become monotonic
+/// cumulative sums so backends can compute rates.
+fn is_cumulative(kind: &MetricKind) -> bool {
+    matches!(
+        kind,
+        MetricKind::NetworkBytes | MetricKind::Interrupts | MetricKind::Cgroup
+    )
+}
+
+/// symmetri units as UCUM codes, which OTLP expects.
+fn ucum_unit(unit: Option<&str>) -> &str {
+    match unit {
+        None => "1",
+        Some("bytes") => "By",
+        Some("°C") => "Cel",
+        Some("/s") => "1/s",
+        Some(unit) => unit,
+    }
+}
+
+fn attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+fn hostname() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|name| name.trim().to_string())
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+fn data_point(ts: f64, value: f64, attributes: Vec<Value>) -> Value {
+    json!({
+        "attributes": attributes,
+        "timeUnixNano": ((ts * 1e6).round() as u64 * 1000).to_string(),
+        "asDouble": value,
+    })
+}
+
+/// One OTLP metric per battery field and per (kind, unit), data points keyed by `source`.
+fn otlp_metrics(batteries: &[Sample], metrics: &[MetricSample]) -> Vec<Value> {
+    // (name, unit, cumulative) -> data points
+    let mut grouped: BTreeMap<(String, String, bool), Vec<Value>> = BTreeMap::new();
+    for sample in batteries {
+        let source = Path::new(&sample.source_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| sample.source_path.clone());
+        let mut attributes = vec![attribute("source", &source)];
+        if let Some(status) = &sample.status {
+            attributes.push(attribute("status", status));
+        }
+        for (field, unit, value) in [
+            ("percentage", "%", sample.percentage),
+            ("health", "%", sample.health_pct),
+            ("energy_now", "Wh", sample.energy_now_wh),
+            ("energy_full", "Wh", sample.energy_full_wh),
+        ] {
+            if let Some(value) = value {
+                grouped
+                    .entry((format!("symmetri.battery.{field}"), unit.to_string(), false))
+                    .or_default()
+                    .push(data_point(sample.ts, value, attributes.clone()));
+            }
+        }
+    }
+    for sample in metrics {
+        let Some(value) = sample.value.filter(|v| v.is_finite()) else {
+            continue;
+        };
+        let key = (
+            format!("symmetri.{}", sample.kind.as_str()),
+            ucum_unit(sample.unit.as_deref()).to_string(),
+            is_cumulative(&sample.kind),
+        );
+        grouped.entry(key).or_default().push(data_point(
+            sample.ts,
+            value,
+            vec![attribute("source", &sample.source)],
+        ));
+    }
+    grouped
+        .into_iter()
+        .map(|((name, unit, cumulative), points)| {
+            let data = if cumulative {
+                json!({ "sum": {
+                    "dataPoints": points,
+                    // AGGREGATION_TEMPORALITY_CUMULATIVE
+                    "aggregationTemporality": 2,
+                    "isMonotonic": true,
+                }})
+            } else {
+                json!({ "gauge": { "dataPoints": points } })
+            };
+            let mut metric = json!({ "name": name, "unit": unit });
+            if let (Value::Object(metric), Value::Object(data)) = (&mut metric, data) {
+                metric.extend(data);
+            }
+            metric
+        })
+        .collect()
+}
+
+/// An OTLP/HTTP `ExportMetricsServiceRequest` in its JSON encoding, with host resource
+/// attributes; `host.name` is left out without `host`.
+pub fn export_request(host: Option<&str>, batteries: &[Sample], metrics: &[MetricSample]) -> Value {
+    let mut attributes = vec![
+        attribute("service.name", "symmetri"),
+        attribute("service.version", env!("CARGO_PKG_VERSION")),
+    ];
+    if let Some(host) = host {
+        attributes.push(attribute("host.name", host));
+    }
+    attributes.push(attribute("host.arch", std::env::consts::ARCH));
+    attributes.push(attribute("os.type", std::env::consts::OS));
+    json!({
+        "resourceMetrics": [{
+            "resource": { "attributes": attributes },
+            "scopeMetrics": [{
+                "scope": { "name": "symmetri", "version": env!("CARGO_PKG_VERSION") },
+                "metrics": otlp_metrics(batteries, metrics),
+            }],
+        }]
+    })
+}
+
+/// Sends a batch to `[otlp] endpoint` (an OTLP/HTTP receiver, usually `:4318/v1/metrics`).
+/// The hostname is only sent when `[privacy] hostname` allows it.
+pub fn push(
+    config: &OtlpConfig,
+    privacy: &PrivacyConfig,
+    batteries: &[Sample],
+    metrics: &[MetricSample],
+) -> Result<()> {
+    let Some(endpoint) = config.endpoint.as_deref() else {
+        return Ok(());
+    };
+    if batteries.is_empty() && metrics.is_empty() {
+        return Ok(());
+    }
+    let host = privacy.hostname.then(hostname);
+    let body = export_request(host.as_deref(), batteries, metrics).to_string();
+    let headers: Vec<(String, String)> = config
+        .headers
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    http::post(
+        endpoint,
+        "application/json",
+        &headers,
+        &body,
+        Duration::from_secs_f64(config.timeout_seconds.max(0.1)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds_map_onto_gauges_and_sums() {
+        let battery = Sample {
+            ts: 1.0,
+            percentage: Some(80.0),
+            capacity_pct: None,
+            health_pct: None,
+            energy_now_wh: None,
+            energy_full_wh: None,
+            energy_full_design_wh: None,
+            status: Some("Discharging".to_string()),
+            source_path: "/sys/class/power_supply/BAT0".to_string(),
+        };
+        let metric = |kind, source: &str, value, unit| {
+            MetricSample::new(2.0, kind, source, Some(value), unit, Value::Null)
+        };
+        let request = export_request(
+            Some("laptop"),
+            &[battery],
+            &[
+                metric(MetricKind::Temperature, "cpu", 55.0, Some("°C")),
+                metric(MetricKind::Temperature, "nvme", 40.0, Some("°C")),
+                metric(MetricKind::NetworkBytes, "wlan0", 1e6, Some("bytes")),
+            ],
+        );
+        let resource = &request["resourceMetrics"][0];
+        assert!(resource["resource"]["attributes"]
+            .as_array()
+            .unwrap()
+            .contains(&attribute("host.name", "laptop")));
+        let anonymous = export_request(None, &[], &[]);
+        let attributes = anonymous["resourceMetrics"][0]["resource"]["attributes"]
+            .as_array()
+            .unwrap();
+        assert!(!attributes.iter().any(|a| a["key"] == "host.name"));
+        let metrics = resource["scopeMetrics"][0]["metrics"].as_array().unwrap();
+        let names: Vec<&str> = metrics
+            .iter()
+            .map(|m| m["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "symmetri.battery.percentage",
+                "symmetri.network_bytes",
+                "symmetri.temperature"
+            ]
+        );
+        let battery_point = &metrics[0]["gauge"]["dataPoints"][0];
+        assert_eq!(battery_point["timeUnixNano"], "1000000000");
+        assert_eq!(
+            battery_point["attributes"][1],
+            attribute("status", "Discharging")
+        );
+        assert_eq!(metrics[1]["unit"], "By");
+        assert_eq!(metrics[1]["sum"]["isMonotonic"], true);
+        assert_eq!(metrics[2]["unit"], "Cel");
+        assert_eq!(
+            metrics[2]["gauge"]["dataPoints"].as_array().unwrap().len(),
+            2
+        );
+    }
+}
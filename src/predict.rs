@@ -0,0 +1,205 @@
+//! `symmetri predict`: whether the current charge lasts through a planned mix of
+//! workloads, priced with the drain each class has cost in the recorded history.
+
+use std::collections::BTreeMap;
+
+use crate::cli_helpers::{format_runtime, parse_window};
+use crate::workload::{Rate, Workload};
+
+/// One stretch of a plan.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Phase {
+    pub workload: Workload,
+    pub seconds: i64,
+}
+
+/// Parses `video:2h,browsing:3h`.
+pub fn parse_plan(raw: &str) -> Result<Vec<Phase>, String> {
+    let plan: Vec<Phase> = raw
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let (name, span) = part
+                .split_once(':')
+                .ok_or_else(|| format!("invalid plan entry {part:?}; expected e.g. video:2h"))?;
+            let workload = Workload::from_label(name.trim()).ok_or_else(|| {
+                format!(
+                    "unknown workload {name:?}; use {}",
+                    Workload::ALL.map(|workload| workload.as_str()).join(", ")
+                )
+            })?;
+            Ok(Phase {
+                workload,
+                seconds: parse_window(span.trim())?,
+            })
+        })
+        .collect::<Result<_, String>>()?;
+    if plan.is_empty() {
+        return Err("empty plan; expected e.g. \"video:2h,browsing:3h\"".to_string());
+    }
+    Ok(plan)
+}
+
+/// One phase played out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Step {
+    pub phase: Phase,
+    pub rate: Rate,
+    pub used_wh: f64,
+    /// Charge left after the phase; below zero once the battery would have run out.
+    pub end_pct: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prediction {
+    pub start_pct: f64,
+    pub steps: Vec<Step>,
+    /// Seconds into the plan when the battery empties, if it does.
+    pub empty_after: Option<f64>,
+    pub plan_seconds: f64,
+}
+
+impl Prediction {
+    /// Charge left at the end of the plan, floored at 0%.
+    pub fn end_pct(&self) -> f64 {
+        self.steps
+            .last()
+            .map_or(self.start_pct, |step| step.end_pct)
+            .max(0.0)
+    }
+
+    /// How much of the plan the charge falls short of.
+    pub fn shortfall_seconds(&self) -> Option<f64> {
+        self.empty_after.map(|after| self.plan_seconds - after)
+    }
+
+    /// `Survives with ~23% left.` or `Runs out 4h10m in, during browsing: 0h50m short.`
+    pub fn verdict(&self) -> String {
+        let Some(after) = self.empty_after else {
+            return format!("Survives with ~{:.0}% left.", self.end_pct());
+        };
+        let mut elapsed = 0.0;
+        let during = self
+            .steps
+            .iter()
+            .find(|step| {
+                elapsed += step.phase.seconds as f64;
+                after <= elapsed
+            })
+            .map_or("the plan", |step| step.phase.workload.as_str());
+        format!(
+            "Runs out {} in, during {during}: {} short.",
+            format_runtime(Some(after / 3600.0)),
+            format_runtime(self.shortfall_seconds().map(|seconds| seconds / 3600.0))
+        )
+    }
+}
+
+/// Plays `plan` out from `start_pct` of a `capacity_wh` battery; fails with the first
+/// workload the history has no drain rate for.
+pub fn predict(
+    start_pct: f64,
+    capacity_wh: f64,
+    plan: &[Phase],
+    rates: &BTreeMap<Workload, Rate>,
+) -> Result<Prediction, Workload> {
+    let mut left_wh = start_pct / 100.0 * capacity_wh;
+    let mut elapsed = 0.0;
+    let mut empty_after = None;
+    let mut steps = Vec::new();
+    for phase in plan {
+        let rate = *rates.get(&phase.workload).ok_or(phase.workload)?;
+        let used_wh = rate.watts * phase.seconds as f64 / 3600.0;
+        if empty_after.is_none() && used_wh > left_wh && rate.watts > 0.0 {
+            empty_after = Some(elapsed + left_wh.max(0.0) / rate.watts * 3600.0);
+        }
+        left_wh -= used_wh;
+        elapsed += phase.seconds as f64;
+        steps.push(Step {
+            phase: *phase,
+            rate,
+            used_wh,
+            end_pct: left_wh / capacity_wh * 100.0,
+        });
+    }
+    Ok(Prediction {
+        start_pct,
+        steps,
+        empty_after,
+        plan_seconds: elapsed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan() -> Vec<Phase> {
+        parse_plan("video:2h, browsing:3h").unwrap()
+    }
+
+    /// 6W for video and 8W for browsing, each learned from ten hours.
+    fn rates() -> BTreeMap<Workload, Rate> {
+        let rate = |watts| Rate { watts, hours: 10.0 };
+        BTreeMap::from([
+            (Workload::Video, rate(6.0)),
+            (Workload::Browsing, rate(8.0)),
+        ])
+    }
+
+    #[test]
+    fn plans_parse_into_timed_phases() {
+        assert_eq!(
+            plan(),
+            [
+                Phase {
+                    workload: Workload::Video,
+                    seconds: 7200
+                },
+                Phase {
+                    workload: Workload::Browsing,
+                    seconds: 10800
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_plans_are_rejected() {
+        assert!(parse_plan("video").is_err());
+        assert!(parse_plan("netflix:2h").is_err());
+        assert!(parse_plan("").is_err());
+    }
+
+    #[test]
+    fn plans_that_fit_report_the_charge_left() {
+        // 80% of 60Wh: 12Wh of video, then 24Wh of browsing leaves 12Wh.
+        let fits = predict(80.0, 60.0, &plan(), &rates()).unwrap();
+        assert_eq!(fits.empty_after, None);
+        assert!((fits.end_pct() - 20.0).abs() < 1e-9);
+        assert_eq!(fits.verdict(), "Survives with ~20% left.");
+    }
+
+    #[test]
+    fn plans_that_run_out_report_when_and_how_short() {
+        // 50% is 30Wh: 18Wh left after the video lasts 2h15m of browsing.
+        let short = predict(50.0, 60.0, &plan(), &rates()).unwrap();
+        assert_eq!(short.empty_after, Some(15300.0));
+        assert_eq!(short.end_pct(), 0.0);
+        assert_eq!(short.shortfall_seconds(), Some(2700.0));
+        assert_eq!(
+            short.verdict(),
+            "Runs out 4h15m in, during browsing: 0h45m short."
+        );
+    }
+
+    #[test]
+    fn workloads_without_a_learned_rate_are_named() {
+        let unknown = parse_plan("gaming:1h").unwrap();
+        assert_eq!(
+            predict(80.0, 60.0, &unknown, &rates()),
+            Err(Workload::Gaming)
+        );
+    }
+}
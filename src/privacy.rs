@@ -0,0 +1,146 @@
+//! `[privacy]` scoping: identifying fields are removed from what the collectors return,
+//! before anything is stored, exported or printed by `collect --dry-run`.
+
+use serde_json::Value;
+
+use crate::config::PrivacyConfig;
+use crate::metrics::{MetricKind, MetricSample};
+use crate::sysfs::BatteryInfo;
+
+/// Detail keys each setting controls, per metric kind.
+fn withheld_keys(config: &PrivacyConfig, kind: &MetricKind) -> &'static [&'static str] {
+    match kind {
+        MetricKind::Wifi if !config.ssids => &["ssid"],
+        MetricKind::Media if !config.process_names => &["processes"],
+        _ => &[],
+    }
+}
+
+/// A stable stand-in for an identifying string (64-bit FNV-1a), so values can still be
+/// told apart without the database naming them.
+pub fn stable_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// A cgroup path with every unit below the slices hashed, so
+/// `app.slice/app-firefox-1234.scope` becomes `app.slice/scope-<hash>`: the hierarchy and
+/// per-cgroup rates survive, the application names do not.
+fn hash_cgroup_units(source: &str) -> String {
+    source
+        .split('/')
+        .map(|part| match part.rsplit_once('.') {
+            Some((_, "slice")) => part.to_string(),
+            Some((_, unit)) => format!("{unit}-{:016x}", stable_hash(part)),
+            None => format!("cgroup-{:016x}", stable_hash(part)),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Drops the details `config` turns off from `samples`, and hashes the unit names of
+/// cgroup sources when process names are off. Values are kept: a webcam in use still
+/// counts as in use, only the process names go.
+pub fn scrub_metrics(config: &PrivacyConfig, samples: &mut [MetricSample]) {
+    for sample in samples {
+        if sample.kind == MetricKind::Cgroup && !config.process_names {
+            sample.source = hash_cgroup_units(&sample.source);
+        }
+        let keys = withheld_keys(config, &sample.kind);
+        if let Value::Object(details) = &mut sample.details {
+            for key in keys {
+                details.remove(*key);
+            }
+        }
+    }
+}
+
+pub fn scrub_battery_info(config: &PrivacyConfig, info: &mut BatteryInfo) {
+    if !config.serial_numbers {
+        info.serial_number = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn withheld_fields_are_removed() {
+        let wifi = MetricSample::new(
+            1.0,
+            MetricKind::Wifi,
+            "wlan0",
+            Some(-60.0),
+            Some("dBm"),
+            json!({ "ssid": "CorpWiFi", "frequency_mhz": 5180 }),
+        );
+        let webcam = MetricSample::new(
+            1.0,
+            MetricKind::Media,
+            "webcam",
+            Some(1.0),
+            None,
+            json!({ "processes": ["zoom"] }),
+        );
+        let info = BatteryInfo {
+            serial_number: Some("1234".into()),
+            model_name: Some("5B10W13930".into()),
+            ..BatteryInfo::default()
+        };
+
+        let mut kept = vec![wifi.clone(), webcam.clone()];
+        scrub_metrics(&PrivacyConfig::default(), &mut kept);
+        assert_eq!(kept[0].details, wifi.details);
+        assert_eq!(kept[1].details, webcam.details);
+
+        let strict = PrivacyConfig {
+            ssids: false,
+            process_names: false,
+            hostname: false,
+            serial_numbers: false,
+        };
+        let mut scrubbed = vec![wifi, webcam];
+        scrub_metrics(&strict, &mut scrubbed);
+        assert_eq!(scrubbed[0].details, json!({ "frequency_mhz": 5180 }));
+        assert_eq!(scrubbed[0].value, Some(-60.0));
+        assert_eq!(scrubbed[1].details, json!({}));
+        assert_eq!(scrubbed[1].value, Some(1.0));
+
+        let mut scrubbed_info = info.clone();
+        scrub_battery_info(&strict, &mut scrubbed_info);
+        assert_eq!(scrubbed_info.serial_number, None);
+        assert_eq!(scrubbed_info.model_name, info.model_name);
+    }
+
+    #[test]
+    fn cgroup_unit_names_are_hashed_without_process_names() {
+        let source =
+            "user.slice/user-1000.slice/user@1000.service/app.slice/app-firefox-1234.scope";
+        let cgroup = MetricSample::new(1.0, MetricKind::Cgroup, source, Some(2.0), None, json!({}));
+
+        let mut kept = vec![cgroup.clone()];
+        scrub_metrics(&PrivacyConfig::default(), &mut kept);
+        assert_eq!(kept[0].source, source);
+
+        let strict = PrivacyConfig {
+            process_names: false,
+            ..PrivacyConfig::default()
+        };
+        let mut scrubbed = vec![cgroup.clone(), cgroup];
+        scrub_metrics(&strict, &mut scrubbed);
+        let hashed = &scrubbed[0].source;
+        assert!(!hashed.contains("firefox"), "{hashed}");
+        assert!(!hashed.contains("user@1000"), "{hashed}");
+        assert!(
+            hashed.starts_with("user.slice/user-1000.slice/service-"),
+            "{hashed}"
+        );
+        assert!(hashed.contains("/app.slice/scope-"), "{hashed}");
+        // The same cgroup keeps the same name, so its rates can still be computed.
+        assert_eq!(scrubbed[1].source, *hashed);
+        assert_eq!(scrubbed[0].value, Some(2.0));
+    }
+}
@@ -0,0 +1,78 @@
+//! Progress bars on stderr for exports, rewrites and reports reading many rows. They only
+//! show on a terminal, so pipes, logs and systemd journals never see them. Builds without
+//! the `progress` feature hand out bars that draw nothing.
+
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "progress")]
+pub use indicatif::ProgressBar;
+#[cfg(feature = "progress")]
+use indicatif::ProgressStyle;
+
+/// Below this many rows the work is over before a bar would be worth drawing.
+const MIN_ROWS: u64 = 20_000;
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turns every later bar off (`--quiet`).
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// A bar counting up to an estimated `total` rows, labelled `message`. Hidden (every call
+/// on it a no-op) when bars are off, stderr is not a terminal or `total` is small.
+pub fn rows(message: &'static str, total: u64) -> ProgressBar {
+    if !ENABLED.load(Ordering::Relaxed) || total < MIN_ROWS || !std::io::stderr().is_terminal() {
+        return ProgressBar::hidden();
+    }
+    bar(message, total)
+}
+
+#[cfg(feature = "progress")]
+fn bar(message: &'static str, total: u64) -> ProgressBar {
+    let bar = ProgressBar::new(total).with_message(message);
+    bar.set_style(
+        ProgressStyle::with_template("{msg:>10} [{bar:30}] {human_pos}/{human_len} rows, {eta}")
+            .expect("valid progress template")
+            .progress_chars("=> "),
+    );
+    bar
+}
+
+#[cfg(not(feature = "progress"))]
+fn bar(_message: &'static str, _total: u64) -> ProgressBar {
+    ProgressBar
+}
+
+/// What `rows` returns without the `progress` feature: every call is a no-op.
+#[cfg(not(feature = "progress"))]
+#[derive(Debug)]
+pub struct ProgressBar;
+
+#[cfg(not(feature = "progress"))]
+impl ProgressBar {
+    fn hidden() -> Self {
+        ProgressBar
+    }
+
+    pub fn inc(&self, _delta: u64) {}
+
+    pub fn set_position(&self, _pos: u64) {}
+
+    pub fn finish_and_clear(&self) {}
+
+    pub fn is_hidden(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_totals_get_no_bar() {
+        assert!(rows("Exporting", MIN_ROWS - 1).is_hidden());
+    }
+}
@@ -0,0 +1,188 @@
+//! Clients for the push notification servers alerts can go to besides generic webhooks:
+//! ntfy (`[ntfy]`) and Gotify (`[gotify]`), each with its priority scale mapped from the
+//! alert severity and the token read from the environment.
+
+#[cfg(feature = "push")]
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::alerts::Severity;
+use crate::config::{GotifyConfig, NtfyConfig};
+use crate::env::{self, EnvVar};
+#[cfg(feature = "push")]
+use crate::http;
+
+/// A notification for either server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub title: String,
+    pub body: String,
+    pub severity: Severity,
+}
+
+/// ntfy priorities run from 1 (min) to 5 (urgent), 3 being the default.
+pub fn ntfy_priority(severity: Severity) -> u8 {
+    match severity {
+        Severity::Info => 3,
+        Severity::Warning => 4,
+        Severity::Critical => 5,
+    }
+}
+
+/// Gotify priorities run from 0 to 10; clients show 4-7 as a notification and 8 and up
+/// as one that interrupts.
+pub fn gotify_priority(severity: Severity) -> u8 {
+    match severity {
+        Severity::Info => 2,
+        Severity::Warning => 5,
+        Severity::Critical => 8,
+    }
+}
+
+fn token(var: EnvVar, configured: &Option<String>) -> Option<String> {
+    var.get()
+        .map(|(token, _)| token)
+        .or_else(|| configured.clone())
+}
+
+/// URL, JSON body and headers publishing `message` to `topic`.
+pub fn ntfy_request(
+    config: &NtfyConfig,
+    topic: &str,
+    message: &Message,
+    token: Option<&str>,
+) -> (String, Value, Vec<(String, String)>) {
+    let tag = match message.severity {
+        Severity::Info => "information_source",
+        Severity::Warning => "warning",
+        Severity::Critical => "rotating_light",
+    };
+    let body = json!({
+        "topic": topic,
+        "title": message.title,
+        "message": message.body,
+        "priority": ntfy_priority(message.severity),
+        "tags": [tag],
+    });
+    let headers = token
+        .map(|token| ("Authorization".to_string(), format!("Bearer {token}")))
+        .into_iter()
+        .collect();
+    // JSON messages go to the server root, with the topic in the body.
+    (
+        format!("{}/", config.server.trim_end_matches('/')),
+        body,
+        headers,
+    )
+}
+
+#[cfg(feature = "push")]
+fn post(url: &str, body: &Value, headers: &[(String, String)], timeout_seconds: f64) -> Result<()> {
+    http::post(
+        url,
+        "application/json",
+        headers,
+        &body.to_string(),
+        Duration::from_secs_f64(timeout_seconds.max(0.1)),
+    )
+}
+
+/// Without the `push` feature there is no HTTP client to post with.
+#[cfg(not(feature = "push"))]
+fn post(url: &str, _body: &Value, _headers: &[(String, String)], _timeout: f64) -> Result<()> {
+    anyhow::bail!(
+        "Cannot post to {url}: symmetri was built without ntfy and Gotify support; rebuild \
+         with `--features push`"
+    )
+}
+
+pub fn send_ntfy(config: &NtfyConfig, topic: &str, message: &Message) -> Result<()> {
+    let token = token(env::NTFY_TOKEN, &config.token);
+    let (url, body, headers) = ntfy_request(config, topic, message, token.as_deref());
+    post(&url, &body, &headers, config.timeout_seconds)
+}
+
+/// URL, JSON body and headers sending `message` with the application `token`.
+pub fn gotify_request(
+    server: &str,
+    message: &Message,
+    token: &str,
+) -> (String, Value, Vec<(String, String)>) {
+    let body = json!({
+        "title": message.title,
+        "message": message.body,
+        "priority": gotify_priority(message.severity),
+    });
+    (
+        format!("{}/message", server.trim_end_matches('/')),
+        body,
+        vec![("X-Gotify-Key".to_string(), token.to_string())],
+    )
+}
+
+pub fn send_gotify(config: &GotifyConfig, message: &Message) -> Result<()> {
+    let server = config
+        .server
+        .as_deref()
+        .context("[gotify] sets no server")?;
+    let token = token(env::GOTIFY_TOKEN, &config.token).with_context(|| {
+        format!(
+            "Gotify needs an application token: set {} or [gotify] token",
+            env::GOTIFY_TOKEN.name
+        )
+    })?;
+    let (url, body, headers) = gotify_request(server, message, &token);
+    post(&url, &body, &headers, config.timeout_seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts;
+    use crate::config::parse_config;
+
+    #[test]
+    fn requests_map_severity_to_each_priority_scale() {
+        let message = Message {
+            title: "symmetri: cpu_hot".to_string(),
+            body: "coretemp is 96.0 °C (> 90)".to_string(),
+            severity: Severity::Critical,
+        };
+        let (url, body, headers) =
+            ntfy_request(&NtfyConfig::default(), "laptop", &message, Some("tk_abc"));
+        assert_eq!(url, "https://ntfy.sh/");
+        assert_eq!(body["topic"], "laptop");
+        assert_eq!(body["priority"], 5);
+        assert_eq!(body["tags"][0], "rotating_light");
+        assert_eq!(
+            headers,
+            [("Authorization".to_string(), "Bearer tk_abc".to_string())]
+        );
+
+        let config = parse_config(
+            "[ntfy]\nserver = \"https://ntfy.lan\"\n\n\
+             [alerts.hot]\nmetric = \"temperature[*]\"\ncomparator = \">\"\nthreshold = 90\n\
+             sinks = [\"ntfy\"]\nntfy_topic = \"laptop\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            alerts::sinks(&config.alerts["hot"], &config)[0].name(),
+            "ntfy"
+        );
+        let without_topic = "[alerts.hot]\nmetric = \"temperature[*]\"\ncomparator = \">\"\n\
+                             threshold = 90\nsinks = [\"ntfy\"]\n";
+        assert!(parse_config(without_topic).is_err());
+        assert!(parse_config(&without_topic.replace("ntfy", "gotify")).is_err());
+
+        let (url, body, headers) = gotify_request("https://gotify.lan/", &message, "A1b2");
+        assert_eq!(url, "https://gotify.lan/message");
+        assert_eq!(body["priority"], 8);
+        assert_eq!(headers[0].1, "A1b2");
+        assert!([Severity::Info, Severity::Warning, Severity::Critical]
+            .windows(2)
+            .all(|pair| ntfy_priority(pair[0]) < ntfy_priority(pair[1])
+                && gotify_priority(pair[0]) < gotify_priority(pair[1])));
+    }
+}
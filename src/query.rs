@@ -0,0 +1,798 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike};
+use serde_json::Value;
+
+use crate::aggregate::aggregate_samples_by_timestamp;
+use crate::cli_helpers::{charge_segments, discharge_segments, parse_bucket};
+use crate::db::{self, Sample, SleepInterval};
+use crate::locale::Formats;
+use crate::metrics::{MetricKind, MetricSample};
+use crate::sensors::sensor_matches;
+use crate::zone::Zone;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum QueryError {
+    #[error("expected {expected}, found {found}")]
+    Expected {
+        expected: &'static str,
+        found: String,
+    },
+    #[error("unknown aggregate {0:?}; use avg, min, max, sum or count")]
+    UnknownAggregate(String),
+    #[error("unknown field {0:?}; use a metric kind or one of {BATTERY_FIELDS}")]
+    UnknownField(String),
+    #[error("unknown grouping {0:?}; use hour, day, week, month or source")]
+    UnknownGroup(String),
+    #[error("invalid duration {0:?}; expected e.g. 6h, 30d or 2w")]
+    InvalidDuration(String),
+    #[error("status filters only apply to battery fields")]
+    StatusOnMetric,
+    #[error("unterminated quote")]
+    UnterminatedQuote,
+}
+
+const BATTERY_FIELDS: &str = "percentage, health, capacity, energy_wh, discharge_w, charge_w";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Avg,
+    Min,
+    Max,
+    Sum,
+    Count,
+}
+
+impl Aggregate {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Aggregate::Avg => "avg",
+            Aggregate::Min => "min",
+            Aggregate::Max => "max",
+            Aggregate::Sum => "sum",
+            Aggregate::Count => "count",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatteryField {
+    Percentage,
+    Health,
+    Capacity,
+    EnergyWh,
+    /// Discharge power from consecutive energy readings, awake time only.
+    DischargeW,
+    ChargeW,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field {
+    Battery(BatteryField),
+    /// The value of every sample of this kind.
+    Metric(MetricKind),
+}
+
+impl BatteryField {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "percentage" => BatteryField::Percentage,
+            "health" => BatteryField::Health,
+            "capacity" => BatteryField::Capacity,
+            "energy_wh" => BatteryField::EnergyWh,
+            "discharge_w" => BatteryField::DischargeW,
+            "charge_w" => BatteryField::ChargeW,
+            _ => return None,
+        })
+    }
+
+    /// The field as read from a single sample; `None` for the rates, which need
+    /// consecutive readings.
+    pub fn reading(self, sample: &Sample) -> Option<f64> {
+        match self {
+            BatteryField::Percentage => sample.percentage,
+            BatteryField::Health => sample.health_pct,
+            BatteryField::Capacity => sample.capacity_pct,
+            BatteryField::EnergyWh => sample.energy_now_wh,
+            BatteryField::DischargeW | BatteryField::ChargeW => None,
+        }
+    }
+}
+
+impl Field {
+    fn parse(raw: &str) -> Result<Self, QueryError> {
+        match BatteryField::from_name(raw) {
+            Some(battery) => Ok(Field::Battery(battery)),
+            None => MetricKind::from_str(raw)
+                .map(Field::Metric)
+                .map_err(|_| QueryError::UnknownField(raw.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Hour,
+    Day,
+    /// Weeks starting on Monday.
+    Week,
+    Month,
+    Source,
+}
+
+impl GroupBy {
+    fn as_str(&self) -> &'static str {
+        match self {
+            GroupBy::Hour => "hour",
+            GroupBy::Day => "day",
+            GroupBy::Week => "week",
+            GroupBy::Month => "month",
+            GroupBy::Source => "source",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// `source=NAME` / `source!=NAME`; `*` wildcards, case-insensitive.
+    Source { pattern: String, negate: bool },
+    /// `status=NAME` on battery fields.
+    Status { status: String, negate: bool },
+}
+
+impl Filter {
+    fn keeps(&self, source: &str, status: Option<&str>) -> bool {
+        match self {
+            Filter::Source { pattern, negate } => sensor_matches(pattern, source) != *negate,
+            Filter::Status {
+                status: wanted,
+                negate,
+            } => status.is_some_and(|status| status.eq_ignore_ascii_case(wanted)) != *negate,
+        }
+    }
+}
+
+/// `AGG(FIELD) [where COND [and COND]...] [by GROUP[, GROUP]...] [last DURATION]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub aggregate: Aggregate,
+    pub field: Field,
+    pub filters: Vec<Filter>,
+    pub group_by: Vec<GroupBy>,
+    /// Only look this many seconds back; all history when unset.
+    pub last_seconds: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Open,
+    Close,
+    Comma,
+    Eq,
+    NotEq,
+}
+
+impl Token {
+    fn describe(token: Option<&Token>) -> String {
+        match token {
+            None => "end of query".to_string(),
+            Some(Token::Word(word)) => format!("{word:?}"),
+            Some(Token::Open) => "\"(\"".to_string(),
+            Some(Token::Close) => "\")\"".to_string(),
+            Some(Token::Comma) => "\",\"".to_string(),
+            Some(Token::Eq) => "\"=\"".to_string(),
+            Some(Token::NotEq) => "\"!=\"".to_string(),
+        }
+    }
+}
+
+fn tokenize(raw: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            ',' => tokens.push(Token::Comma),
+            '=' => tokens.push(Token::Eq),
+            '!' if chars.peek() == Some(&'=') => {
+                chars.next();
+                tokens.push(Token::NotEq);
+            }
+            '"' | '\'' => {
+                let mut quoted = String::new();
+                loop {
+                    match chars.next() {
+                        Some(next) if next == c => break,
+                        Some(next) => quoted.push(next),
+                        None => return Err(QueryError::UnterminatedQuote),
+                    }
+                }
+                tokens.push(Token::Word(quoted));
+            }
+            _ => {
+                let mut word = c.to_string();
+                while let Some(next) = chars.peek() {
+                    if next.is_whitespace() || "(),=!".contains(*next) {
+                        break;
+                    }
+                    word.push(*next);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: std::vec::IntoIter<Token>,
+    peeked: Option<Token>,
+}
+
+impl Parser {
+    fn peek(&mut self) -> Option<&Token> {
+        if self.peeked.is_none() {
+            self.peeked = self.tokens.next();
+        }
+        self.peeked.as_ref()
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        self.peek();
+        self.peeked.take()
+    }
+
+    fn expect(&mut self, wanted: Token, expected: &'static str) -> Result<(), QueryError> {
+        match self.next() {
+            Some(token) if token == wanted => Ok(()),
+            other => Err(QueryError::Expected {
+                expected,
+                found: Token::describe(other.as_ref()),
+            }),
+        }
+    }
+
+    fn word(&mut self, expected: &'static str) -> Result<String, QueryError> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(word),
+            other => Err(QueryError::Expected {
+                expected,
+                found: Token::describe(other.as_ref()),
+            }),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found =
+            matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword));
+        if found {
+            self.next();
+        }
+        found
+    }
+
+    fn filter(&mut self) -> Result<Filter, QueryError> {
+        let key = self.word("source or status")?.to_lowercase();
+        let negate = match self.next() {
+            Some(Token::Eq) => false,
+            Some(Token::NotEq) => true,
+            other => {
+                return Err(QueryError::Expected {
+                    expected: "\"=\" or \"!=\"",
+                    found: Token::describe(other.as_ref()),
+                })
+            }
+        };
+        let value = self.word("a value")?;
+        match key.as_str() {
+            "source" => Ok(Filter::Source {
+                pattern: value,
+                negate,
+            }),
+            "status" => Ok(Filter::Status {
+                status: value,
+                negate,
+            }),
+            _ => Err(QueryError::Expected {
+                expected: "source or status",
+                found: format!("{key:?}"),
+            }),
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(raw)?.into_iter(),
+            peeked: None,
+        };
+        let aggregate = match parser
+            .word("an aggregate such as avg(...)")?
+            .to_lowercase()
+            .as_str()
+        {
+            "avg" | "mean" => Aggregate::Avg,
+            "min" => Aggregate::Min,
+            "max" => Aggregate::Max,
+            "sum" => Aggregate::Sum,
+            "count" => Aggregate::Count,
+            other => return Err(QueryError::UnknownAggregate(other.to_string())),
+        };
+        parser.expect(Token::Open, "\"(\"")?;
+        let field = Field::parse(&parser.word("a field")?.to_lowercase())?;
+        parser.expect(Token::Close, "\")\"")?;
+
+        let mut query = Query {
+            aggregate,
+            field,
+            filters: Vec::new(),
+            group_by: Vec::new(),
+            last_seconds: None,
+        };
+        while parser.peek().is_some() {
+            if parser.keyword("where") {
+                query.filters.push(parser.filter()?);
+                while parser.keyword("and") {
+                    query.filters.push(parser.filter()?);
+                }
+            } else if parser.keyword("by") {
+                loop {
+                    let group = parser.word("hour, day, week, month or source")?;
+                    query.group_by.push(match group.to_lowercase().as_str() {
+                        "hour" => GroupBy::Hour,
+                        "day" => GroupBy::Day,
+                        "week" => GroupBy::Week,
+                        "month" => GroupBy::Month,
+                        "source" => GroupBy::Source,
+                        _ => return Err(QueryError::UnknownGroup(group)),
+                    });
+                    if parser.peek() != Some(&Token::Comma) {
+                        break;
+                    }
+                    parser.next();
+                }
+            } else if parser.keyword("last") {
+                let raw = parser.word("a duration")?;
+                let seconds = parse_bucket(&raw).map_err(|_| QueryError::InvalidDuration(raw))?;
+                query.last_seconds = Some(seconds);
+            } else {
+                return Err(QueryError::Expected {
+                    expected: "where, by or last",
+                    found: Token::describe(parser.peek()),
+                });
+            }
+        }
+        if matches!(query.field, Field::Metric(_))
+            && query
+                .filters
+                .iter()
+                .any(|filter| matches!(filter, Filter::Status { .. }))
+        {
+            return Err(QueryError::StatusOnMetric);
+        }
+        Ok(query)
+    }
+}
+
+/// One value going into an aggregate; rates carry their duration as weight.
+#[derive(Debug, Clone, PartialEq)]
+struct Point {
+    ts: f64,
+    source: String,
+    value: f64,
+    weight: f64,
+}
+
+fn battery_name(sample: &Sample) -> String {
+    Path::new(&sample.source_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| sample.source_path.clone())
+}
+
+fn battery_points(
+    field: BatteryField,
+    samples: &[Sample],
+    sleeps: &[SleepInterval],
+    filters: &[Filter],
+) -> Vec<Point> {
+    let kept: Vec<Sample> = samples
+        .iter()
+        .filter(|sample| {
+            let name = battery_name(sample);
+            filters
+                .iter()
+                .all(|filter| filter.keeps(&name, sample.status.as_deref()))
+        })
+        .cloned()
+        .collect();
+    let segments = match field {
+        BatteryField::DischargeW => {
+            discharge_segments(&aggregate_samples_by_timestamp(&kept), sleeps)
+        }
+        BatteryField::ChargeW => charge_segments(&aggregate_samples_by_timestamp(&kept), sleeps),
+        _ => {
+            return kept
+                .iter()
+                .filter_map(|sample| {
+                    Some(Point {
+                        ts: sample.ts,
+                        source: battery_name(sample),
+                        value: field.reading(sample)?,
+                        weight: 1.0,
+                    })
+                })
+                .collect()
+        }
+    };
+    // Rates come from the summed batteries, like the report's.
+    segments
+        .into_iter()
+        .map(|segment| Point {
+            ts: segment.ts,
+            source: "total".to_string(),
+            value: segment.watts,
+            weight: segment.hours,
+        })
+        .collect()
+}
+
+fn metric_points(metrics: &[MetricSample], filters: &[Filter]) -> Vec<Point> {
+    metrics
+        .iter()
+        .filter(|sample| {
+            filters
+                .iter()
+                .all(|filter| filter.keeps(&sample.source, None))
+        })
+        .filter_map(|sample| {
+            Some(Point {
+                ts: sample.ts,
+                source: sample.source.clone(),
+                value: sample.value.filter(|value| value.is_finite())?,
+                weight: 1.0,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum GroupKey {
+    /// Start of the hour/day/week/month in the display zone, as a Unix timestamp.
+    Time(i64),
+    Source(String),
+}
+
+fn local_start(zone: &Zone, date: NaiveDate, hour: u32) -> Option<i64> {
+    let naive = date.and_hms_opt(hour, 0, 0)?;
+    Some(zone.from_local_datetime(&naive).earliest()?.timestamp())
+}
+
+fn group_key(group: GroupBy, point: &Point, formats: &Formats) -> Option<GroupKey> {
+    let zone = &formats.zone;
+    let dt = formats.datetime(point.ts)?;
+    let date = dt.date_naive();
+    let start = match group {
+        GroupBy::Source => return Some(GroupKey::Source(point.source.clone())),
+        GroupBy::Hour => local_start(zone, date, dt.hour()),
+        GroupBy::Day => local_start(zone, date, 0),
+        GroupBy::Week => local_start(
+            zone,
+            date - Duration::days(i64::from(date.weekday().num_days_from_monday())),
+            0,
+        ),
+        GroupBy::Month => local_start(zone, date.with_day(1)?, 0),
+    };
+    start.map(GroupKey::Time)
+}
+
+fn group_label(group: GroupBy, key: &GroupKey, formats: &Formats) -> String {
+    match key {
+        GroupKey::Source(source) => source.clone(),
+        GroupKey::Time(ts) => {
+            let Some(dt) =
+                DateTime::from_timestamp(*ts, 0).map(|dt| dt.with_timezone(&formats.zone))
+            else {
+                return ts.to_string();
+            };
+            match group {
+                GroupBy::Hour => formats.timestamp(&dt),
+                GroupBy::Month => dt.format("%Y-%m").to_string(),
+                _ => formats.date(&dt),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Accumulator {
+    weighted_sum: f64,
+    weight: f64,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+    count: usize,
+}
+
+impl Accumulator {
+    fn add(&mut self, point: &Point) {
+        self.weighted_sum += point.value * point.weight;
+        self.weight += point.weight;
+        self.sum += point.value;
+        self.min = Some(self.min.map_or(point.value, |min| min.min(point.value)));
+        self.max = Some(self.max.map_or(point.value, |max| max.max(point.value)));
+        self.count += 1;
+    }
+
+    fn result(&self, aggregate: Aggregate) -> Value {
+        let value = match aggregate {
+            Aggregate::Avg => (self.weight > 0.0).then(|| self.weighted_sum / self.weight),
+            Aggregate::Min => self.min,
+            Aggregate::Max => self.max,
+            Aggregate::Sum => Some(self.sum),
+            Aggregate::Count => return Value::from(self.count),
+        };
+        value.map_or(Value::Null, Value::from)
+    }
+}
+
+/// Column names and rows of a query result; cells are numbers, strings or null.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Query {
+    fn column_name(&self) -> String {
+        let field = match &self.field {
+            Field::Battery(BatteryField::Percentage) => "percentage",
+            Field::Battery(BatteryField::Health) => "health",
+            Field::Battery(BatteryField::Capacity) => "capacity",
+            Field::Battery(BatteryField::EnergyWh) => "energy_wh",
+            Field::Battery(BatteryField::DischargeW) => "discharge_w",
+            Field::Battery(BatteryField::ChargeW) => "charge_w",
+            Field::Metric(kind) => kind.as_str(),
+        };
+        format!("{}({field})", self.aggregate.as_str())
+    }
+
+    /// Aggregates already fetched rows; `samples`/`metrics` hold the window's data.
+    pub fn evaluate(
+        &self,
+        samples: &[Sample],
+        metrics: &[MetricSample],
+        sleeps: &[SleepInterval],
+        formats: &Formats,
+    ) -> QueryResult {
+        let points = match &self.field {
+            Field::Battery(field) => battery_points(*field, samples, sleeps, &self.filters),
+            Field::Metric(kind) => {
+                let of_kind: Vec<MetricSample> = metrics
+                    .iter()
+                    .filter(|sample| sample.kind == *kind)
+                    .cloned()
+                    .collect();
+                metric_points(&of_kind, &self.filters)
+            }
+        };
+        let mut groups: BTreeMap<Vec<GroupKey>, Accumulator> = BTreeMap::new();
+        for point in &points {
+            let key: Option<Vec<GroupKey>> = self
+                .group_by
+                .iter()
+                .map(|group| group_key(*group, point, formats))
+                .collect();
+            if let Some(key) = key {
+                groups.entry(key).or_default().add(point);
+            }
+        }
+
+        let mut columns: Vec<String> = self
+            .group_by
+            .iter()
+            .map(|group| group.as_str().to_string())
+            .collect();
+        columns.push(self.column_name());
+        if self.aggregate != Aggregate::Count {
+            columns.push("samples".to_string());
+        }
+        let rows = groups
+            .iter()
+            .map(|(key, acc)| {
+                let mut row: Vec<Value> = self
+                    .group_by
+                    .iter()
+                    .zip(key)
+                    .map(|(group, key)| Value::from(group_label(*group, key, formats)))
+                    .collect();
+                row.push(acc.result(self.aggregate));
+                if self.aggregate != Aggregate::Count {
+                    row.push(Value::from(acc.count));
+                }
+                row
+            })
+            .collect();
+        QueryResult { columns, rows }
+    }
+
+    /// Fetches the rows the query needs from `db_path` and aggregates them.
+    pub fn run(&self, db_path: &Path, now: f64, formats: &Formats) -> Result<QueryResult> {
+        let since = self.last_seconds.map(|seconds| now - seconds as f64);
+        let (samples, metrics, sleeps) = match &self.field {
+            Field::Battery(_) => (
+                db::fetch_samples(db_path, since)?,
+                Vec::new(),
+                db::fetch_sleep_intervals(db_path, since, None)?,
+            ),
+            Field::Metric(kind) => (
+                Vec::new(),
+                db::fetch_metric_samples(db_path, since, Some(std::slice::from_ref(kind)))?,
+                Vec::new(),
+            ),
+        };
+        Ok(self.evaluate(&samples, &metrics, &sleeps, formats))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{battery, metric};
+    use serde_json::json;
+
+    fn temperatures() -> [MetricSample; 3] {
+        let temperature =
+            |ts, source, value| metric(ts, MetricKind::Temperature, source, value, json!({}));
+        [
+            temperature(0.0, "cpu", 40.0),
+            temperature(60.0, "cpu", 60.0),
+            temperature(60.0, "nvme0", 35.0),
+        ]
+    }
+
+    fn energy(ts: f64, wh: f64) -> Sample {
+        Sample {
+            percentage: None,
+            energy_now_wh: Some(wh),
+            ..battery(ts, 0.0, "Discharging")
+        }
+    }
+
+    #[test]
+    fn filters_groupings_and_windows_are_parsed() {
+        let query: Query = "AVG(temperature) where source != 'nvme*' by source last 2h"
+            .parse()
+            .unwrap();
+        assert_eq!(query.aggregate, Aggregate::Avg);
+        assert_eq!(query.field, Field::Metric(MetricKind::Temperature));
+        assert_eq!(query.group_by, vec![GroupBy::Source]);
+        assert_eq!(query.last_seconds, Some(7200));
+        assert_eq!(
+            query.filters,
+            vec![Filter::Source {
+                pattern: "nvme*".to_string(),
+                negate: true
+            }]
+        );
+    }
+
+    #[test]
+    fn unknown_fields_and_kinds_are_rejected() {
+        assert_eq!(
+            "avg(watts)".parse::<Query>(),
+            Err(QueryError::UnknownField("watts".to_string()))
+        );
+        assert_eq!(
+            "median(percentage)".parse::<Query>(),
+            Err(QueryError::UnknownAggregate("median".to_string()))
+        );
+        assert_eq!(
+            "avg(percentage) by year".parse::<Query>(),
+            Err(QueryError::UnknownGroup("year".to_string()))
+        );
+    }
+
+    #[test]
+    fn status_filters_only_apply_to_battery_fields() {
+        assert_eq!(
+            "max(cpu_usage) where status=Charging".parse::<Query>(),
+            Err(QueryError::StatusOnMetric)
+        );
+        assert!("max(percentage) where status=Charging"
+            .parse::<Query>()
+            .is_ok());
+    }
+
+    #[test]
+    fn malformed_queries_are_rejected() {
+        for malformed in [
+            "",
+            "avg percentage",
+            "avg(percentage",
+            "avg(percentage) by day last",
+            "avg(percentage) where source",
+            "avg(percentage) sorted",
+        ] {
+            assert!(
+                matches!(malformed.parse::<Query>(), Err(QueryError::Expected { .. })),
+                "{malformed:?}"
+            );
+        }
+        assert_eq!(
+            "avg(percentage) where source='BAT0".parse::<Query>(),
+            Err(QueryError::UnterminatedQuote)
+        );
+        assert_eq!(
+            "avg(percentage) last soon".parse::<Query>(),
+            Err(QueryError::InvalidDuration("soon".to_string()))
+        );
+    }
+
+    #[test]
+    fn metrics_are_aggregated_per_source_after_filtering() {
+        let query: Query = "avg(temperature) where source != 'nvme*' by source"
+            .parse()
+            .unwrap();
+        let result = query.evaluate(&[], &temperatures(), &[], &Formats::default());
+        assert_eq!(
+            result.columns,
+            vec!["source", "avg(temperature)", "samples"]
+        );
+        assert_eq!(result.rows, vec![vec![json!("cpu"), json!(50.0), json!(2)]]);
+    }
+
+    #[test]
+    fn counts_have_no_separate_samples_column() {
+        let query: Query = "count(temperature)".parse().unwrap();
+        let result = query.evaluate(&[], &temperatures(), &[], &Formats::default());
+        assert_eq!(result.columns, vec!["count(temperature)"]);
+        assert_eq!(result.rows, vec![vec![json!(3)]]);
+    }
+
+    #[test]
+    fn discharge_is_averaged_per_day_weighted_by_duration() {
+        let day = chrono::Local
+            .with_ymd_and_hms(2024, 5, 31, 10, 0, 0)
+            .unwrap()
+            .timestamp() as f64;
+        // 1 Wh over 1 minute, then 0.5 Wh over 1 minute: 45 W on average.
+        let samples = [
+            energy(day, 50.0),
+            energy(day + 60.0, 49.0),
+            energy(day + 120.0, 48.5),
+        ];
+        let by_day: Query = "avg(discharge_w) by day".parse().unwrap();
+        let result = by_day.evaluate(&samples, &[], &[], &Formats::default());
+        assert_eq!(
+            result.rows,
+            vec![vec![json!("2024-05-31"), json!(45.0), json!(2)]]
+        );
+    }
+
+    #[test]
+    fn empty_windows_yield_no_rows() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_path = tmp.path().join("query.db");
+        let mut conn = db::init_db_connection(&db_path).unwrap();
+        db::insert_all_samples(&mut conn, &[], &temperatures()).unwrap();
+
+        let query: Query = "max(temperature) by source last 1h".parse().unwrap();
+        let result = query.run(&db_path, 86_400.0, &Formats::default()).unwrap();
+        assert_eq!(
+            result.columns,
+            vec!["source", "max(temperature)", "samples"]
+        );
+        assert!(result.rows.is_empty());
+    }
+}
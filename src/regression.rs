@@ -0,0 +1,289 @@
+//! `analyze regressions`: discharge power before and after each recorded kernel, OS or
+//! symmetri update, compared with a Mann–Whitney U test so a shift in drain stands out
+//! from day-to-day noise.
+
+use crate::cli_helpers::{percentile, DischargeSegment};
+use crate::versions::{self, SystemVersions};
+
+/// Fewer discharge readings than this on either side make no comparison.
+pub const MIN_READINGS: usize = 20;
+
+/// A recorded update: every version that changed at one collector start.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub ts: f64,
+    /// `kernel 6.6.1 → 6.9.2`, several joined with `, `.
+    pub description: String,
+}
+
+/// The updates in `history` (oldest first, as stored).
+pub fn changes(history: &[(f64, SystemVersions)]) -> Vec<Change> {
+    let mut changes: Vec<Change> = Vec::new();
+    for note in versions::change_annotations(history, None) {
+        let text = note.text.replacen(" changed", "", 1);
+        match changes.last_mut() {
+            Some(last) if last.ts == note.ts => {
+                last.description.push_str(", ");
+                last.description.push_str(&text);
+            }
+            _ => changes.push(Change {
+                ts: note.ts,
+                description: text,
+            }),
+        }
+    }
+    changes
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MannWhitney {
+    /// U of the first sample.
+    pub u: f64,
+    pub z: f64,
+    /// Two-sided, from the normal approximation with tie correction.
+    pub p: f64,
+}
+
+/// Complementary error function (Numerical Recipes' Chebyshev fit, relative error below
+/// 1.2e-7), for normal tail probabilities.
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = -z * z - 1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98
+                                + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    let r = t * poly.exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
+    }
+}
+
+/// Whether `a` and `b` come from the same distribution; `None` when either is empty or
+/// every value is the same.
+pub fn mann_whitney(a: &[f64], b: &[f64]) -> Option<MannWhitney> {
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let mut all: Vec<(f64, bool)> = a
+        .iter()
+        .map(|&v| (v, true))
+        .chain(b.iter().map(|&v| (v, false)))
+        .collect();
+    all.sort_by(|x, y| x.0.total_cmp(&y.0));
+    let n = all.len() as f64;
+    // Tied values share the mean of their ranks.
+    let (mut rank_sum, mut ties, mut i) = (0.0, 0.0, 0);
+    while i < all.len() {
+        let j = i + all[i..].iter().take_while(|(v, _)| *v == all[i].0).count();
+        let rank = (i + 1 + j) as f64 / 2.0;
+        rank_sum += rank * all[i..j].iter().filter(|(_, first)| *first).count() as f64;
+        let t = (j - i) as f64;
+        ties += t * t * t - t;
+        i = j;
+    }
+    let u = rank_sum - n1 * (n1 + 1.0) / 2.0;
+    let mean = n1 * n2 / 2.0;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - ties / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        return None;
+    }
+    // Continuity correction towards the mean.
+    let z = (u - mean - 0.5 * (u - mean).signum()) / variance.sqrt();
+    Some(MannWhitney {
+        u,
+        z,
+        p: erfc(z.abs() / std::f64::consts::SQRT_2).min(1.0),
+    })
+}
+
+/// `p<0.001`, `p<0.01` or `p=0.04`.
+pub fn format_p(p: f64) -> String {
+    if p < 0.001 {
+        "p<0.001".to_string()
+    } else if p < 0.01 {
+        "p<0.01".to_string()
+    } else {
+        format!("p={p:.2}")
+    }
+}
+
+/// Drain on either side of one update.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub change: Change,
+    pub before: usize,
+    pub after: usize,
+    pub before_median_w: Option<f64>,
+    pub after_median_w: Option<f64>,
+    /// `None` with too few readings on a side.
+    pub test: Option<MannWhitney>,
+}
+
+impl Comparison {
+    pub fn delta_w(&self) -> Option<f64> {
+        Some(self.after_median_w? - self.before_median_w?)
+    }
+
+    /// Drain went up and the test says it is no accident.
+    pub fn is_regression(&self, alpha: f64) -> bool {
+        self.test.is_some_and(|test| test.p < alpha) && self.delta_w().is_some_and(|d| d > 0.0)
+    }
+
+    pub fn verdict(&self, alpha: f64) -> &'static str {
+        match (self.test, self.delta_w()) {
+            (None, _) | (_, None) => "too little data",
+            (Some(test), _) if test.p >= alpha => "no change",
+            (_, Some(delta)) if delta > 0.0 => "regression",
+            _ => "improvement",
+        }
+    }
+
+    /// `update kernel 6.6.1 → 6.9.2: +1.8W median drain (p<0.01)`.
+    pub fn summary(&self) -> String {
+        format!(
+            "update {}: {:+.1}W median drain ({})",
+            self.change.description,
+            self.delta_w().unwrap_or(0.0),
+            self.test
+                .map_or("no test".to_string(), |test| format_p(test.p))
+        )
+    }
+}
+
+/// Discharge readings up to `window` seconds either side of each change, stopping at the
+/// neighbouring changes so each side runs a single set of versions. Segments spanning the
+/// change itself count for neither side.
+pub fn compare(changes: &[Change], segments: &[DischargeSegment], window: f64) -> Vec<Comparison> {
+    changes
+        .iter()
+        .enumerate()
+        .map(|(index, change)| {
+            let from = index
+                .checked_sub(1)
+                .map_or(f64::NEG_INFINITY, |previous| changes[previous].ts)
+                .max(change.ts - window);
+            let to = changes
+                .get(index + 1)
+                .map_or(f64::INFINITY, |next| next.ts)
+                .min(change.ts + window);
+            let start = |segment: &DischargeSegment| segment.ts - segment.hours * 3600.0;
+            let side = |keep: &dyn Fn(&DischargeSegment) -> bool| {
+                let mut watts: Vec<f64> = segments
+                    .iter()
+                    .filter(|segment| keep(segment))
+                    .map(|segment| segment.watts)
+                    .collect();
+                watts.sort_by(f64::total_cmp);
+                watts
+            };
+            let before = side(&|segment| start(segment) >= from && segment.ts <= change.ts);
+            let after = side(&|segment| start(segment) >= change.ts && segment.ts <= to);
+            let test = (before.len() >= MIN_READINGS && after.len() >= MIN_READINGS)
+                .then(|| mann_whitney(&after, &before))
+                .flatten();
+            Comparison {
+                change: change.clone(),
+                before: before.len(),
+                after: after.len(),
+                before_median_w: percentile(&before, 0.5),
+                after_median_w: percentile(&after, 0.5),
+                test,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::segment;
+
+    fn at(kernel: &str) -> SystemVersions {
+        SystemVersions {
+            kernel: kernel.to_string(),
+            os_release: Some("Arch Linux".to_string()),
+            symmetri: "0.3.0".to_string(),
+        }
+    }
+
+    /// A kernel and symmetri update at 100000s, a kernel point release at 200000s.
+    fn history() -> Vec<(f64, SystemVersions)> {
+        let mut newer = at("6.9.2");
+        newer.symmetri = "0.4.0".to_string();
+        vec![
+            (0.0, at("6.6.1")),
+            (100_000.0, newer),
+            (200_000.0, at("6.9.3")),
+        ]
+    }
+
+    /// Hourly readings: ~6W before the first update, ~7.8W after it, unchanged after the
+    /// second one.
+    fn segments() -> Vec<DischargeSegment> {
+        (1..=55)
+            .map(|hour| {
+                let ts = hour as f64 * 3600.0;
+                let noise = ((hour * 7) % 5) as f64 * 0.1;
+                let watts = if ts > 100_000.0 { 7.8 } else { 6.0 };
+                segment(ts, watts + noise, 1.0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn mann_whitney_needs_more_than_ties() {
+        let test = mann_whitney(&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]).unwrap();
+        assert_eq!(test.u, 0.0);
+        assert!(mann_whitney(&[2.0, 2.0], &[2.0]).is_none());
+    }
+
+    #[test]
+    fn erfc_matches_reference_values() {
+        assert!((erfc(0.0) - 1.0).abs() < 1e-7);
+        assert!((erfc(1.959_964 / std::f64::consts::SQRT_2) - 0.05).abs() < 1e-6);
+    }
+
+    #[test]
+    fn small_p_values_are_bounded() {
+        assert_eq!(format_p(0.004), "p<0.01");
+        assert_eq!(format_p(0.2), "p=0.20");
+    }
+
+    #[test]
+    fn version_changes_are_described_together() {
+        let changes = changes(&history());
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            changes[0].description,
+            "kernel 6.6.1 → 6.9.2, symmetri 0.3.0 → 0.4.0"
+        );
+    }
+
+    #[test]
+    fn updates_that_raise_drain_are_flagged() {
+        let compared = compare(&changes(&history()), &segments(), 7.0 * 86400.0);
+        let kernel = &compared[0];
+        assert_eq!((kernel.before, kernel.after), (27, 27));
+        assert!(kernel.is_regression(0.01));
+        assert_eq!(kernel.verdict(0.01), "regression");
+        assert_eq!(
+            kernel.summary(),
+            "update kernel 6.6.1 → 6.9.2, symmetri 0.3.0 → 0.4.0: +1.8W median drain (p<0.001)"
+        );
+    }
+
+    #[test]
+    fn updates_with_little_data_after_them_get_no_verdict() {
+        // Only one day of readings after the second update.
+        let compared = compare(&changes(&history()), &segments(), 7.0 * 86400.0);
+        assert_eq!(compared[1].verdict(0.01), "too little data");
+    }
+}
@@ -0,0 +1,246 @@
+use std::fmt;
+
+use comfy_table::{Row, Table};
+use serde_json::{json, Map, Value};
+
+/// One block of `report` output. The same sections feed the terminal and HTML renderers so
+/// both always show identical numbers.
+pub enum ReportSection {
+    Table { title: String, table: Table },
+    Note(String),
+}
+
+impl ReportSection {
+    pub fn table(title: impl Into<String>, table: Table) -> Self {
+        ReportSection::Table {
+            title: title.into(),
+            table,
+        }
+    }
+
+    pub fn note(text: impl Into<String>) -> Self {
+        ReportSection::Note(text.into())
+    }
+}
+
+impl fmt::Display for ReportSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReportSection::Table { title, table } => write!(f, "\n{title}\n{table}"),
+            ReportSection::Note(text) => write!(f, "\n{text}"),
+        }
+    }
+}
+
+const STYLE: &str = "body{font-family:sans-serif;max-width:1320px;margin:2em auto;color:#222}\
+h1{font-size:1.5em}h2{font-size:1.15em;margin-top:1.6em}\
+table{border-collapse:collapse;font-size:0.9em}\
+th,td{border:1px solid #ccc;padding:4px 10px;text-align:right}\
+th{background:#eef4f8}td:first-child,th:first-child{text-align:left}\
+p.note{color:#a15c00}img{max-width:100%}";
+
+/// Renders a standalone HTML page; the graph, if any, is inlined as a base64 PNG so the file
+/// can be mailed or archived on its own.
+pub fn render_html(
+    title: &str,
+    generated_at: &str,
+    sections: &[ReportSection],
+    graph_png: Option<&[u8]>,
+) -> String {
+    let graph_src = graph_png.map(|png| format!("data:image/png;base64,{}", base64_encode(png)));
+    render_html_with_graph(title, generated_at, sections, graph_src.as_deref())
+}
+
+/// [`render_html`] with the graph image at `graph_src`, e.g. `cid:graph` for an email
+/// carrying it as an inline attachment.
+pub fn render_html_with_graph(
+    title: &str,
+    generated_at: &str,
+    sections: &[ReportSection],
+    graph_src: Option<&str>,
+) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape_html(title)));
+    html.push_str(&format!("<style>{STYLE}</style>\n</head>\n<body>\n"));
+    html.push_str(&format!("<h1>{}</h1>\n", escape_html(title)));
+    html.push_str(&format!("<p>Generated {}</p>\n", escape_html(generated_at)));
+
+    for section in sections {
+        match section {
+            ReportSection::Table { title, table } => {
+                html.push_str(&format!("<h2>{}</h2>\n", escape_html(title)));
+                html.push_str(&table_html(table));
+            }
+            ReportSection::Note(text) => {
+                html.push_str(&format!("<p class=\"note\">{}</p>\n", escape_html(text)));
+            }
+        }
+    }
+
+    if let Some(src) = graph_src {
+        html.push_str("<h2>Graphs</h2>\n");
+        html.push_str(&format!(
+            "<img alt=\"symmetri graphs\" src=\"{}\">\n",
+            escape_html(src)
+        ));
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Renders the sections as JSON for scripts: each table row becomes an object keyed by the
+/// column headers, with cells kept as the formatted strings shown in the terminal.
+pub fn render_json(title: &str, generated_at: &str, sections: &[ReportSection]) -> Value {
+    let sections: Vec<Value> = sections
+        .iter()
+        .map(|section| match section {
+            ReportSection::Table { title, table } => json!({
+                "title": title,
+                "rows": table_json(table),
+            }),
+            ReportSection::Note(text) => json!({ "note": text }),
+        })
+        .collect();
+    json!({
+        "title": title,
+        "generated_at": generated_at,
+        "sections": sections,
+    })
+}
+
+fn row_cells(row: &Row) -> Vec<String> {
+    row.cell_iter().map(|cell| cell.content()).collect()
+}
+
+fn table_json(table: &Table) -> Vec<Value> {
+    let columns = table.header().map(row_cells).unwrap_or_default();
+    table
+        .row_iter()
+        .map(|row| {
+            let cells = row_cells(row);
+            if columns.is_empty() {
+                return json!(cells);
+            }
+            let object: Map<String, Value> = columns
+                .iter()
+                .cloned()
+                .zip(cells.into_iter().map(Value::String))
+                .collect();
+            Value::Object(object)
+        })
+        .collect()
+}
+
+fn row_html(row: &Row, tag: &str) -> String {
+    let cells: String = row
+        .cell_iter()
+        .map(|cell| format!("<{tag}>{}</{tag}>", escape_html(&cell.content())))
+        .collect();
+    format!("<tr>{cells}</tr>\n")
+}
+
+fn table_html(table: &Table) -> String {
+    let mut html = String::from("<table>\n");
+    if let Some(header) = table.header() {
+        html.push_str(&format!("<thead>{}</thead>\n", row_html(header, "th")));
+    }
+    html.push_str("<tbody>\n");
+    for row in table.row_iter() {
+        html.push_str(&row_html(row, "td"));
+    }
+    html.push_str("</tbody>\n</table>\n");
+    html
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        out.push(ALPHABET[(n >> 18) as usize & 63] as char);
+        out.push(ALPHABET[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 {
+            ALPHABET[(n >> 6) as usize & 63] as char
+        } else {
+            '='
+        });
+        out.push(if chunk.len() > 2 {
+            ALPHABET[n as usize & 63] as char
+        } else {
+            '='
+        });
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_matches_reference_vectors() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn html_contains_escaped_tables_notes_and_graph() {
+        let mut table = Table::new();
+        table.set_header(vec!["Metric", "Value"]);
+        table.add_row(vec!["Runtime <est>", "3h10m"]);
+        let sections = vec![
+            ReportSection::table("Battery summary (last 7 days)", table),
+            ReportSection::note("Warning: battery sat at 100% & more"),
+        ];
+
+        let html = render_html("Weekly report", "2025-11-28 10:00", &sections, Some(b"png"));
+        assert!(html.contains("<h2>Battery summary (last 7 days)</h2>"));
+        assert!(html.contains("<th>Metric</th><th>Value</th>"));
+        assert!(html.contains("<td>Runtime &lt;est&gt;</td><td>3h10m</td>"));
+        assert!(html.contains("100% &amp; more"));
+        assert!(html.contains("data:image/png;base64,cG5n"));
+        let mailed =
+            render_html_with_graph("Weekly report", "2025-11-28", &sections, Some("cid:graph"));
+        assert!(mailed.contains("src=\"cid:graph\""));
+    }
+
+    #[test]
+    fn json_rows_are_keyed_by_header() {
+        let mut table = Table::new();
+        table.set_header(vec!["Day", "Used"]);
+        table.add_row(vec!["2025-11-28", "42.0Wh"]);
+        let sections = vec![
+            ReportSection::table("Energy per day (last 7 days)", table),
+            ReportSection::note("No CPU samples"),
+        ];
+
+        let json = render_json("Weekly report", "2025-11-28 10:00", &sections);
+        assert_eq!(json["sections"][0]["rows"][0]["Used"], "42.0Wh");
+        assert_eq!(json["sections"][1]["note"], "No CPU samples");
+    }
+}
@@ -0,0 +1,466 @@
+use crate::calibrate::Calibration;
+use crate::cli_helpers::{discharge_segments, DischargeSegment};
+use crate::config::{RuntimeConfig, RuntimeModel};
+use crate::db::{Sample, SleepInterval};
+use crate::metrics::{MetricKind, MetricSample};
+
+/// CPU usage and brightness are matched to a discharge segment when sampled this close to
+/// its end.
+const LOAD_MATCH_SECONDS: f64 = 600.0;
+const MIN_REGRESSION_POINTS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeEstimate {
+    pub hours: f64,
+    /// Half-width of the ±1σ band around `hours`, when the model can tell.
+    pub margin_hours: Option<f64>,
+    /// Hours left from the latest reading, while it is discharging.
+    pub remaining_hours: Option<f64>,
+}
+
+pub fn estimate_runtime(
+    config: &RuntimeConfig,
+    samples: &[Sample],
+    metrics: &[MetricSample],
+    sleeps: &[SleepInterval],
+    avg_discharge_w: Option<f64>,
+    calibration: Option<&Calibration>,
+) -> Option<RuntimeEstimate> {
+    let latest = samples.last()?;
+    let calibration = calibration.filter(|c| c.source_path == latest.source_path);
+    let estimate = |rate: f64, sd: f64| estimate_with_band(latest, calibration, rate, sd);
+    let average = || estimate(avg_discharge_w?, 0.0);
+    let segments = discharge_segments(samples, sleeps);
+    let ewma = || {
+        let (rate, sd) = ewma_rate(&segments, config.half_life_minutes)?;
+        estimate(rate, sd)
+    };
+    match config.model {
+        RuntimeModel::Average => average(),
+        RuntimeModel::Ewma => ewma().or_else(average),
+        RuntimeModel::Load => load_conditioned(&segments, metrics, config.half_life_minutes)
+            .and_then(|(rate, sd)| estimate(rate, sd))
+            .or_else(ewma)
+            .or_else(average),
+    }
+}
+
+/// Energy a full charge delivers: what a calibration run counted when there is one (the
+/// gauge's own energy_full can be optimistic), else energy_full or the design capacity.
+fn capacity_wh(latest: &Sample, calibration: Option<&Calibration>) -> Option<f64> {
+    let reported = latest.energy_full_wh.or(latest.energy_full_design_wh);
+    calibration
+        .map(|c| c.usable_wh_now(reported))
+        .or(reported)
+        .filter(|wh| *wh > 0.0)
+}
+
+/// Charge level of a reading in percent. A calibration maps the reported level through
+/// the measured curve, so nonlinear gauges do not skew it.
+fn true_pct(latest: &Sample, calibration: Option<&Calibration>) -> Option<f64> {
+    let reported = latest.capacity_pct.or(latest.percentage)?;
+    let pct = calibration.map_or(reported, |c| c.true_pct(reported));
+    Some(pct.clamp(0.0, 100.0))
+}
+
+/// Share of a full charge left at the latest reading, while it is discharging.
+fn remaining_fraction(latest: &Sample, calibration: Option<&Calibration>) -> Option<f64> {
+    if latest.status.as_deref() != Some("Discharging") {
+        return None;
+    }
+    Some(true_pct(latest, calibration)? / 100.0)
+}
+
+/// Charge level (%) and the energy of a full charge (Wh) at `latest`, whatever its status,
+/// for planning ahead from the current charge.
+pub fn charge_now(latest: &Sample, calibration: Option<&Calibration>) -> Option<(f64, f64)> {
+    let calibration = calibration.filter(|c| c.source_path == latest.source_path);
+    Some((
+        true_pct(latest, calibration)?,
+        capacity_wh(latest, calibration)?,
+    ))
+}
+
+fn estimate_with_band(
+    latest: &Sample,
+    calibration: Option<&Calibration>,
+    rate: f64,
+    sd: f64,
+) -> Option<RuntimeEstimate> {
+    if rate <= 0.0 {
+        return None;
+    }
+    let capacity_wh = capacity_wh(latest, calibration)?;
+    let hours = capacity_wh / rate;
+    let margin_hours = (rate - sd > 0.0 && sd > 0.0)
+        .then(|| (capacity_wh / (rate - sd) - capacity_wh / (rate + sd)) / 2.0);
+    Some(RuntimeEstimate {
+        hours,
+        margin_hours,
+        remaining_hours: remaining_fraction(latest, calibration).map(|share| share * hours),
+    })
+}
+
+/// Segment weight: recency decay times segment length, so long quiet stretches count more
+/// than a single noisy 5-minute reading.
+fn segment_weights(segments: &[DischargeSegment], half_life_minutes: f64) -> Vec<f64> {
+    let Some(last_ts) = segments.last().map(|s| s.ts) else {
+        return Vec::new();
+    };
+    let half_life_seconds = (half_life_minutes * 60.0).max(1.0);
+    segments
+        .iter()
+        .map(|s| 0.5f64.powf((last_ts - s.ts) / half_life_seconds) * s.hours)
+        .collect()
+}
+
+fn ewma_rate(segments: &[DischargeSegment], half_life_minutes: f64) -> Option<(f64, f64)> {
+    let weights = segment_weights(segments, half_life_minutes);
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mean = segments
+        .iter()
+        .zip(&weights)
+        .map(|(s, w)| s.watts * w)
+        .sum::<f64>()
+        / total;
+    let variance = segments
+        .iter()
+        .zip(&weights)
+        .map(|(s, w)| w * (s.watts - mean).powi(2))
+        .sum::<f64>()
+        / total;
+    Some((mean, variance.sqrt()))
+}
+
+/// `(ts, value)` of the matching readings, oldest first.
+fn series(
+    metrics: &[MetricSample],
+    reading: impl Fn(&MetricSample) -> Option<f64>,
+) -> Vec<(f64, f64)> {
+    let mut series: Vec<(f64, f64)> = metrics
+        .iter()
+        .filter_map(|m| Some((m.ts, reading(m)?)))
+        .collect();
+    series.sort_by(|a, b| a.0.total_cmp(&b.0));
+    series
+}
+
+fn reading_at(series: &[(f64, f64)], ts: f64) -> Option<f64> {
+    let idx = series.partition_point(|(sample_ts, _)| *sample_ts <= ts);
+    let (sample_ts, value) = series.get(idx.checked_sub(1)?)?;
+    (ts - sample_ts <= LOAD_MATCH_SECONDS).then_some(*value)
+}
+
+/// Solves `a · x = b` by Gaussian elimination; `None` when a regressor is (nearly) constant
+/// or a combination of the others.
+fn solve<const N: usize>(mut a: [[f64; N]; N], mut b: [f64; N]) -> Option<[f64; N]> {
+    let scale: [f64; N] = std::array::from_fn(|i| a[i][i].abs());
+    for col in 0..N {
+        let pivot = (col..N).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() <= 1e-9 * scale[col].max(f64::EPSILON) {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        for row in col + 1..N {
+            let factor = a[row][col] / a[col][col];
+            let (upper, lower) = a.split_at_mut(row);
+            for (target, pivot_value) in lower[0][col..].iter_mut().zip(&upper[col][col..]) {
+                *target -= factor * pivot_value;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    let mut x = [0.0; N];
+    for row in (0..N).rev() {
+        let rest: f64 = (row + 1..N).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - rest) / a[row][row];
+    }
+    Some(x)
+}
+
+/// Weighted least squares of discharge watts against the regressors of each
+/// `(regressors, watts, weight)` point, evaluated at `at`. Returns the predicted rate and
+/// the residual standard deviation.
+fn weighted_fit<const N: usize>(
+    points: &[([f64; N], f64, f64)],
+    at: [f64; N],
+) -> Option<(f64, f64)> {
+    if points.len() < MIN_REGRESSION_POINTS {
+        return None;
+    }
+    let total: f64 = points.iter().map(|(_, _, w)| w).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mean_x: [f64; N] =
+        std::array::from_fn(|i| points.iter().map(|(x, _, w)| x[i] * w).sum::<f64>() / total);
+    let mean_y = points.iter().map(|(_, y, w)| y * w).sum::<f64>() / total;
+    // Normal equations on centered values, so the intercept drops out.
+    let mut cov_xx = [[0.0; N]; N];
+    let mut cov_xy = [0.0; N];
+    for (x, y, w) in points {
+        for i in 0..N {
+            cov_xy[i] += w * (x[i] - mean_x[i]) * (y - mean_y);
+            for j in 0..N {
+                cov_xx[i][j] += w * (x[i] - mean_x[i]) * (x[j] - mean_x[j]);
+            }
+        }
+    }
+    let slopes = solve(cov_xx, cov_xy)?;
+    let predict =
+        |x: &[f64; N]| mean_y + (0..N).map(|i| slopes[i] * (x[i] - mean_x[i])).sum::<f64>();
+    let residual = points
+        .iter()
+        .map(|(x, y, w)| w * (y - predict(x)).powi(2))
+        .sum::<f64>()
+        / total;
+
+    let predicted = predict(&at);
+    (predicted > 0.0).then_some((predicted, residual.sqrt()))
+}
+
+/// Discharge watts regressed on overall CPU usage and screen brightness, evaluated at the
+/// latest readings of both. Without brightness readings from the display collector, or
+/// when brightness never changed, CPU usage alone.
+fn load_conditioned(
+    segments: &[DischargeSegment],
+    metrics: &[MetricSample],
+    half_life_minutes: f64,
+) -> Option<(f64, f64)> {
+    let cpu = series(metrics, |m| {
+        (m.kind == MetricKind::CpuUsage && m.source == "cpu")
+            .then_some(m.value)
+            .flatten()
+    });
+    let brightness = series(metrics, |m| {
+        (m.kind == MetricKind::Display)
+            .then(|| m.details.get("brightness_pct")?.as_f64())
+            .flatten()
+    });
+    let current_load = cpu.last()?.1;
+    let weights = segment_weights(segments, half_life_minutes);
+
+    if let Some(&(_, current_brightness)) = brightness.last() {
+        let points: Vec<([f64; 2], f64, f64)> = segments
+            .iter()
+            .zip(&weights)
+            .filter_map(|(s, w)| {
+                let x = [reading_at(&cpu, s.ts)?, reading_at(&brightness, s.ts)?];
+                Some((x, s.watts, *w))
+            })
+            .collect();
+        if let Some(fit) = weighted_fit(&points, [current_load, current_brightness]) {
+            return Some(fit);
+        }
+    }
+    let points: Vec<([f64; 1], f64, f64)> = segments
+        .iter()
+        .zip(&weights)
+        .filter_map(|(s, w)| Some(([reading_at(&cpu, s.ts)?], s.watts, *w)))
+        .collect();
+    weighted_fit(&points, [current_load])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calibrate::CalibrationPoint;
+    use serde_json::Value;
+
+    fn sample(ts: f64, energy_now: f64) -> Sample {
+        Sample {
+            ts,
+            percentage: None,
+            capacity_pct: None,
+            health_pct: None,
+            energy_now_wh: Some(energy_now),
+            energy_full_wh: Some(60.0),
+            energy_full_design_wh: Some(70.0),
+            status: Some("Discharging".to_string()),
+            source_path: "BAT0".to_string(),
+        }
+    }
+
+    fn cpu(ts: f64, load: f64) -> MetricSample {
+        MetricSample::new(
+            ts,
+            MetricKind::CpuUsage,
+            "cpu",
+            Some(load),
+            Some("%"),
+            Value::Null,
+        )
+    }
+
+    fn config(model: RuntimeModel) -> RuntimeConfig {
+        RuntimeConfig {
+            model,
+            half_life_minutes: 30.0,
+        }
+    }
+
+    #[test]
+    fn ewma_favors_recent_discharge() {
+        // 6W for an hour, then 12W for the last 15 minutes.
+        let mut samples = Vec::new();
+        let mut energy = 60.0;
+        for i in 0..=15 {
+            let watts = if i <= 12 { 6.0 } else { 12.0 };
+            if i > 0 {
+                energy -= watts * 300.0 / 3600.0;
+            }
+            samples.push(sample(i as f64 * 300.0, energy));
+        }
+        let average = estimate_runtime(
+            &config(RuntimeModel::Average),
+            &samples,
+            &[],
+            &[],
+            Some(6.0),
+            None,
+        )
+        .unwrap();
+        let ewma = estimate_runtime(
+            &config(RuntimeModel::Ewma),
+            &samples,
+            &[],
+            &[],
+            Some(6.0),
+            None,
+        )
+        .unwrap();
+        assert!((average.hours - 10.0).abs() < 0.01);
+        assert!(average.margin_hours.is_none());
+        assert!(ewma.hours < average.hours);
+        assert!(ewma.margin_hours.unwrap() > 0.0);
+    }
+
+    #[test]
+    fn load_model_predicts_at_current_load() {
+        // Drain follows 4W + 0.1W per CPU %, current load is 80%.
+        let loads = [10.0, 50.0, 20.0, 70.0, 30.0, 80.0];
+        let mut samples = vec![sample(0.0, 60.0)];
+        let mut metrics = Vec::new();
+        let mut energy = 60.0;
+        for (i, load) in loads.iter().enumerate() {
+            let ts = (i + 1) as f64 * 300.0;
+            energy -= (4.0 + 0.1 * load) * 300.0 / 3600.0;
+            samples.push(sample(ts, energy));
+            metrics.push(cpu(ts, *load));
+        }
+        let estimate = estimate_runtime(
+            &config(RuntimeModel::Load),
+            &samples,
+            &metrics,
+            &[],
+            None,
+            None,
+        )
+        .unwrap();
+        assert!((estimate.hours - 60.0 / 12.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn load_model_accounts_for_brightness() {
+        // Drain follows 3W + 0.05W per CPU % + 0.04W per brightness %; the screen is at
+        // full brightness now.
+        let readings = [
+            (10.0, 20.0),
+            (60.0, 30.0),
+            (20.0, 80.0),
+            (70.0, 50.0),
+            (40.0, 10.0),
+            (50.0, 100.0),
+        ];
+        let mut samples = vec![sample(0.0, 60.0)];
+        let mut metrics = Vec::new();
+        let mut energy = 60.0;
+        for (i, (load, brightness)) in readings.iter().enumerate() {
+            let ts = (i + 1) as f64 * 300.0;
+            energy -= (3.0 + 0.05 * load + 0.04 * brightness) * 300.0 / 3600.0;
+            samples.push(sample(ts, energy));
+            metrics.push(cpu(ts, *load));
+            metrics.push(MetricSample::new(
+                ts,
+                MetricKind::Display,
+                "intel_backlight",
+                Some(1.0),
+                None,
+                serde_json::json!({ "brightness_pct": brightness }),
+            ));
+        }
+        let estimate = estimate_runtime(
+            &config(RuntimeModel::Load),
+            &samples,
+            &metrics,
+            &[],
+            None,
+            None,
+        )
+        .unwrap();
+        assert!((estimate.hours - 60.0 / 9.5).abs() < 0.01);
+
+        // A screen kept at one brightness leaves CPU usage to explain the drain.
+        for metric in metrics.iter_mut().filter(|m| m.kind == MetricKind::Display) {
+            metric.details = serde_json::json!({ "brightness_pct": 60.0 });
+        }
+        let segments = discharge_segments(&samples, &[]);
+        let cpu_only: Vec<MetricSample> = metrics
+            .iter()
+            .filter(|m| m.kind == MetricKind::CpuUsage)
+            .cloned()
+            .collect();
+        let fallback = load_conditioned(&segments, &cpu_only, 30.0);
+        assert!(fallback.is_some());
+        assert_eq!(load_conditioned(&segments, &metrics, 30.0), fallback);
+    }
+
+    #[test]
+    fn load_model_falls_back_without_cpu_data() {
+        let samples = vec![sample(0.0, 60.0), sample(300.0, 59.5), sample(600.0, 59.0)];
+        let load = estimate_runtime(&config(RuntimeModel::Load), &samples, &[], &[], None, None);
+        let ewma = estimate_runtime(&config(RuntimeModel::Ewma), &samples, &[], &[], None, None);
+        assert_eq!(load, ewma);
+        assert!((load.unwrap().hours - 10.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn calibration_corrects_capacity_and_time_left() {
+        // 6W from 60Wh; the gauge reads 50% but only 40% of the usable energy is left.
+        let mut samples: Vec<Sample> = (0..=12)
+            .map(|i| sample(i as f64 * 300.0, 60.0 - i as f64 * 0.5))
+            .collect();
+        samples.last_mut().unwrap().capacity_pct = Some(50.0);
+        let average = config(RuntimeModel::Average);
+        let plain = estimate_runtime(&average, &samples, &[], &[], Some(6.0), None).unwrap();
+        assert!((plain.hours - 10.0).abs() < 1e-9);
+        assert!((plain.remaining_hours.unwrap() - 5.0).abs() < 1e-9);
+
+        let calibration = Calibration {
+            ts: 0.0,
+            source_path: "BAT0".to_string(),
+            full_wh: 60.0,
+            usable_wh: 54.0,
+            points: vec![CalibrationPoint {
+                reported_pct: 50.0,
+                true_pct: 40.0,
+            }],
+        };
+        let corrected =
+            estimate_runtime(&average, &samples, &[], &[], Some(6.0), Some(&calibration)).unwrap();
+        assert!((corrected.hours - 9.0).abs() < 1e-9);
+        assert!((corrected.remaining_hours.unwrap() - 3.6).abs() < 1e-9);
+
+        let other = Calibration {
+            source_path: "BAT1".to_string(),
+            ..calibration
+        };
+        let ignored =
+            estimate_runtime(&average, &samples, &[], &[], Some(6.0), Some(&other)).unwrap();
+        assert_eq!(ignored, plain);
+    }
+}
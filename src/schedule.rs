@@ -0,0 +1,101 @@
+use std::time::{Duration, Instant};
+
+struct Entry {
+    name: String,
+    interval: Duration,
+    next_due: Instant,
+}
+
+/// Tracks when each collector should run next. Every entry is due immediately when added.
+pub struct Schedule {
+    start: Instant,
+    entries: Vec<Entry>,
+}
+
+impl Schedule {
+    pub fn new(start: Instant) -> Self {
+        Schedule {
+            start,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, name: &str, interval: Duration) {
+        self.entries.push(Entry {
+            name: name.to_string(),
+            interval,
+            next_due: self.start,
+        });
+    }
+
+    /// Names due at `now`, in insertion order. Each returned entry is pushed back by its
+    /// interval; entries that fell more than one interval behind (e.g. after a suspend)
+    /// restart from `now` instead of firing repeatedly to catch up.
+    pub fn take_due(&mut self, now: Instant) -> Vec<String> {
+        let mut due = Vec::new();
+        for entry in self.entries.iter_mut().filter(|e| e.next_due <= now) {
+            due.push(entry.name.clone());
+            entry.next_due += entry.interval;
+            if entry.next_due <= now {
+                entry.next_due = now + entry.interval;
+            }
+        }
+        due
+    }
+
+    /// Makes every entry due at `now`, e.g. for a collection forced over the control socket.
+    pub fn run_all_now(&mut self, now: Instant) {
+        for entry in &mut self.entries {
+            entry.next_due = now;
+        }
+    }
+
+    pub fn next_due(&self) -> Option<Instant> {
+        self.entries.iter().map(|entry| entry.next_due).min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collectors_fire_on_their_own_intervals() {
+        let start = Instant::now();
+        let mut schedule = Schedule::new(start);
+        schedule.add("battery", Duration::from_secs(30));
+        schedule.add("disk", Duration::from_secs(600));
+
+        assert_eq!(schedule.take_due(start), vec!["battery", "disk"]);
+        assert_eq!(schedule.next_due(), Some(start + Duration::from_secs(30)));
+        assert!(schedule
+            .take_due(start + Duration::from_secs(10))
+            .is_empty());
+
+        let mut battery_runs = 0;
+        let mut disk_runs = 0;
+        for second in (30..=600).step_by(30) {
+            for name in schedule.take_due(start + Duration::from_secs(second)) {
+                match name.as_str() {
+                    "battery" => battery_runs += 1,
+                    _ => disk_runs += 1,
+                }
+            }
+        }
+        assert_eq!(battery_runs, 20);
+        assert_eq!(disk_runs, 1);
+    }
+
+    #[test]
+    fn late_entries_do_not_catch_up() {
+        let start = Instant::now();
+        let mut schedule = Schedule::new(start);
+        schedule.add("battery", Duration::from_secs(30));
+        schedule.take_due(start);
+
+        let resumed = start + Duration::from_secs(3600);
+        assert_eq!(schedule.take_due(resumed), vec!["battery"]);
+        assert!(schedule.take_due(resumed).is_empty());
+        assert_eq!(schedule.next_due(), Some(resumed + Duration::from_secs(30)));
+    }
+}
@@ -0,0 +1,304 @@
+use std::fs;
+use std::io::Read;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{debug, info_span, warn, Span};
+
+use crate::collector::Collector;
+use crate::config::{user_config_dir, ScriptsConfig};
+use crate::metrics::{MetricKind, MetricSample};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// `$XDG_CONFIG_HOME/symmetri/collectors.d`, `None` when the home directory is unknown.
+pub fn default_collectors_dir() -> Option<PathBuf> {
+    user_config_dir().map(|dir| dir.join("symmetri").join("collectors.d"))
+}
+
+/// One line of script output. Every field except `kind` and `source` is optional.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScriptRecord {
+    kind: String,
+    source: String,
+    #[serde(default)]
+    value: Option<f64>,
+    #[serde(default)]
+    unit: Option<String>,
+    #[serde(default)]
+    details: Value,
+}
+
+fn validate(record: ScriptRecord, ts: f64) -> Result<MetricSample, String> {
+    let kind = MetricKind::from_label(&record.kind)
+        .ok_or_else(|| format!("unknown kind {:?}", record.kind))?;
+    if kind == MetricKind::Internal {
+        return Err("the internal kind is reserved for symmetri itself".to_string());
+    }
+    if record.source.trim().is_empty() {
+        return Err("empty source".to_string());
+    }
+    if record.value.is_some_and(|v| !v.is_finite()) {
+        return Err("value is not finite".to_string());
+    }
+    if !(record.details.is_null() || record.details.is_object()) {
+        return Err("details must be an object".to_string());
+    }
+    Ok(MetricSample::new(
+        ts,
+        kind,
+        record.source,
+        record.value,
+        record.unit.as_deref(),
+        record.details,
+    ))
+}
+
+/// Parses the JSON lines printed by a script; invalid lines are logged and skipped so one
+/// bad record does not drop the rest.
+pub fn parse_script_output(script: &str, output: &str, ts: f64) -> Vec<MetricSample> {
+    let mut samples = Vec::new();
+    for (index, line) in output.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let parsed = serde_json::from_str::<ScriptRecord>(line)
+            .map_err(|err| err.to_string())
+            .and_then(|record| validate(record, ts));
+        match parsed {
+            Ok(sample) => samples.push(sample),
+            Err(err) => warn!("Ignoring line {} from {script}: {err}", index + 1),
+        }
+    }
+    samples
+}
+
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path)
+        .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+pub fn find_scripts(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut scripts: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| is_executable(path))
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+/// Kills the script and everything it started: it leads its own process group.
+fn kill_group(child: &mut Child) {
+    // SAFETY: plain syscall on a group we created; a group that is gone makes it fail.
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.wait();
+}
+
+/// Runs a script and returns its stdout, killing it once `timeout` elapses. The script
+/// runs in a process group of its own, so commands it left running in the background
+/// are killed with it, and cannot hold stdout open past the timeout.
+fn run_script(path: &Path, timeout: Duration) -> Result<String, String> {
+    let mut child = Command::new(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .map_err(|err| err.to_string())?;
+    let mut stdout = child.stdout.take().ok_or("stdout unavailable")?;
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = stdout.read_to_string(&mut buf);
+        let _ = sender.send(buf);
+    });
+
+    let deadline = Instant::now() + timeout;
+    let timed_out = || format!("timed out after {:.0}s", timeout.as_secs_f64());
+    let status = loop {
+        match child.try_wait().map_err(|err| err.to_string())? {
+            Some(status) => break status,
+            None if Instant::now() >= deadline => {
+                kill_group(&mut child);
+                return Err(timed_out());
+            }
+            None => thread::sleep(POLL_INTERVAL),
+        }
+    };
+    let output = match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        Ok(output) => output,
+        Err(_) => {
+            kill_group(&mut child);
+            return Err(timed_out());
+        }
+    };
+    if !status.success() {
+        return Err(format!("exited with {status}"));
+    }
+    Ok(output)
+}
+
+fn run_and_parse(path: &Path, timeout: Duration, ts: f64) -> Vec<MetricSample> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let span = info_span!("script", script = name.as_str());
+    let _entered = span.enter();
+    match run_script(path, timeout) {
+        Ok(output) => {
+            let parsed = parse_script_output(&name, &output, ts);
+            debug!(samples = parsed.len(), "script finished");
+            parsed
+        }
+        Err(err) => {
+            warn!("Script collector {name} failed: {err}");
+            Vec::new()
+        }
+    }
+}
+
+/// Runs every script concurrently, so the whole batch finishes within one script timeout.
+pub fn collect_script_samples(config: &ScriptsConfig, ts: f64) -> Vec<MetricSample> {
+    let Some(dir) = config.dir.clone().or_else(default_collectors_dir) else {
+        warn!("Cannot find the home directory for collectors.d; set [scripts] dir");
+        return Vec::new();
+    };
+    let timeout = Duration::from_secs_f64(config.timeout_seconds.max(0.0));
+    let scripts = find_scripts(&dir);
+    let parent = Span::current();
+    thread::scope(|scope| {
+        let handles: Vec<_> = scripts
+            .iter()
+            .map(|path| {
+                let parent = &parent;
+                scope.spawn(move || parent.in_scope(|| run_and_parse(path, timeout, ts)))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .filter_map(|handle| handle.join().ok())
+            .flatten()
+            .collect()
+    })
+}
+
+/// Registry entry for everything in the collectors directory. Scripts may emit any kind.
+pub struct ScriptCollector {
+    config: ScriptsConfig,
+}
+
+impl ScriptCollector {
+    pub fn new(config: ScriptsConfig) -> Self {
+        ScriptCollector { config }
+    }
+}
+
+impl Collector for ScriptCollector {
+    fn name(&self) -> &str {
+        "scripts"
+    }
+
+    fn kinds(&self) -> &[MetricKind] {
+        &[]
+    }
+
+    fn collect(&self, ts: f64) -> Vec<MetricSample> {
+        collect_script_samples(&self.config, ts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_script(dir: &Path, name: &str, body: &str, mode: u32) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, body).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        path
+    }
+
+    #[test]
+    fn parses_valid_lines_and_skips_invalid_ones() {
+        let output = r#"
+{"kind":"power_draw","source":"usb-meter","value":4.2,"unit":"W","details":{"port":1}}
+{"kind":"mystery","source":"x","value":1}
+{"kind":"temperature","source":""}
+not json
+{"kind":"temperature","source":"probe","value":41.5}
+"#;
+        let samples = parse_script_output("meter", output, 7.0);
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].kind, MetricKind::PowerDraw);
+        assert_eq!(samples[0].source, "usb-meter");
+        assert_eq!(samples[0].unit.as_deref(), Some("W"));
+        assert_eq!(samples[0].details["port"], 1);
+        assert_eq!(samples[1].ts, 7.0);
+        assert!(samples[1].details.is_null());
+    }
+
+    #[test]
+    fn runs_only_executable_scripts() {
+        let tmp = tempfile::tempdir().unwrap();
+        write_script(
+            tmp.path(),
+            "meter.sh",
+            "#!/bin/sh\necho '{\"kind\":\"power_draw\",\"source\":\"usb\",\"value\":3}'\n",
+            0o755,
+        );
+        write_script(tmp.path(), "notes.txt", "not a script", 0o644);
+        write_script(tmp.path(), "broken.sh", "#!/bin/sh\nexit 3\n", 0o755);
+
+        let config = ScriptsConfig {
+            dir: Some(tmp.path().to_path_buf()),
+            ..ScriptsConfig::default()
+        };
+        let samples = collect_script_samples(&config, 1.0);
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].value, Some(3.0));
+    }
+
+    #[test]
+    fn hung_scripts_are_killed() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = write_script(tmp.path(), "slow.sh", "#!/bin/sh\nsleep 5\n", 0o755);
+        let started = Instant::now();
+        assert!(run_script(&path, Duration::from_millis(100)).is_err());
+        assert!(started.elapsed() < Duration::from_secs(3));
+    }
+
+    #[test]
+    fn background_children_are_killed_with_the_script() {
+        let tmp = tempfile::tempdir().unwrap();
+        let marker = tmp.path().join("survived");
+        let body = format!(
+            "#!/bin/sh\n(sleep 1; touch {}) &\necho '{{}}'\n",
+            marker.display()
+        );
+        let path = write_script(tmp.path(), "forks.sh", &body, 0o755);
+        let started = Instant::now();
+        // The script exits at once, but the background job keeps its stdout open.
+        assert!(run_script(&path, Duration::from_millis(300)).is_err());
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        thread::sleep(Duration::from_millis(1500));
+        assert!(!marker.exists(), "the background job outlived the script");
+    }
+}
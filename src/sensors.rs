@@ -0,0 +1,302 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::collector::Collector;
+use crate::config::SensorsConfig;
+use crate::metrics::{MetricKind, MetricSample};
+
+/// Config name of the collector reading thermal zones and hwmon temperature inputs.
+pub const TEMPERATURE_COLLECTOR: &str = "temperature";
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|raw| raw.trim().to_string())
+        .filter(|raw| !raw.is_empty())
+}
+
+fn read_millidegrees(path: &Path) -> Option<f64> {
+    read_trimmed(path)?
+        .parse::<f64>()
+        .ok()
+        .map(|milli| milli / 1000.0)
+}
+
+/// Readable name for an hwmon temperature input from its chip name and `temp*_label`,
+/// e.g. coretemp "Package id 0" becomes "CPU Package" and nvme "Composite" "NVMe Composite".
+pub fn hwmon_sensor_name(chip: &str, label: Option<&str>, input: &str) -> String {
+    let device = match chip {
+        "coretemp" | "k10temp" | "zenpower" | "cpu_thermal" => "CPU",
+        "nvme" => "NVMe",
+        "amdgpu" | "nouveau" | "radeon" | "i915" | "xe" => "GPU",
+        "acpitz" => "ACPI",
+        "pch_cannonlake" | "pch_cometlake" | "pch_skylake" => "PCH",
+        chip if chip.starts_with("iwlwifi") => "Wi-Fi",
+        chip if chip.starts_with("ath") || chip.starts_with("mt7") => "Wi-Fi",
+        chip if chip.starts_with("nvidia") => "GPU",
+        chip if chip.starts_with("bat") || chip.starts_with("BAT") => "Battery",
+        chip => chip,
+    };
+    let label = label.map(|label| {
+        if label.starts_with("Package id") {
+            "Package"
+        } else {
+            label
+        }
+    });
+    match label {
+        Some(label) if label.eq_ignore_ascii_case(device) => device.to_string(),
+        Some(label) => format!("{device} {label}"),
+        None if device == chip => format!("{chip} {input}"),
+        None => device.to_string(),
+    }
+}
+
+/// Thermal zones keep their `type` as the source so existing history stays comparable.
+pub fn thermal_zone_samples_from(root: &Path, ts: f64) -> Vec<MetricSample> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut zones: Vec<_> = entries
+        .flatten()
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with("thermal_zone")
+        })
+        .collect();
+    zones.sort_by_key(|entry| entry.file_name());
+    zones
+        .into_iter()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let label = read_trimmed(&path.join("type"))
+                .unwrap_or_else(|| entry.file_name().to_string_lossy().to_string());
+            let temp_c = read_millidegrees(&path.join("temp"))?;
+            Some(MetricSample::new(
+                ts,
+                MetricKind::Temperature,
+                label,
+                Some(temp_c),
+                Some("C"),
+                Value::Null,
+            ))
+        })
+        .collect()
+}
+
+/// hwmon `temp*_input` files, named with [`hwmon_sensor_name`]. Chips in `skip_chips` (the
+/// thermal zones already read) are left out; `details.sensor` keeps the raw `chip/tempN` id.
+pub fn hwmon_temperature_samples_from(
+    root: &Path,
+    ts: f64,
+    skip_chips: &BTreeSet<String>,
+) -> Vec<MetricSample> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut chips: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+    chips.sort();
+
+    let mut samples = Vec::new();
+    let mut used: BTreeSet<String> = BTreeSet::new();
+    for chip_path in chips {
+        let Some(chip) = read_trimmed(&chip_path.join("name")) else {
+            continue;
+        };
+        if skip_chips.contains(&chip) {
+            continue;
+        }
+        let Ok(files) = fs::read_dir(&chip_path) else {
+            continue;
+        };
+        let mut inputs: Vec<String> = files
+            .flatten()
+            .filter_map(|file| {
+                let name = file.file_name().to_string_lossy().to_string();
+                let input = name.strip_suffix("_input")?;
+                input.starts_with("temp").then(|| input.to_string())
+            })
+            .collect();
+        inputs.sort_by_key(|input| input[4..].parse::<u32>().unwrap_or(u32::MAX));
+        for input in inputs {
+            let Some(temp_c) = read_millidegrees(&chip_path.join(format!("{input}_input"))) else {
+                continue;
+            };
+            let label = read_trimmed(&chip_path.join(format!("{input}_label")));
+            let mut name = hwmon_sensor_name(&chip, label.as_deref(), &input);
+            // Two NVMe drives both report "Composite"; number the later ones.
+            let base = name.clone();
+            let mut index = 2;
+            while used.contains(&name) {
+                name = format!("{base} #{index}");
+                index += 1;
+            }
+            used.insert(name.clone());
+            samples.push(MetricSample::new(
+                ts,
+                MetricKind::Temperature,
+                name,
+                Some(temp_c),
+                Some("C"),
+                json!({ "sensor": format!("{chip}/{input}") }),
+            ));
+        }
+    }
+    samples
+}
+
+/// Thermal zones plus hwmon temperature inputs, with `[sensors] names` renames applied.
+pub fn temperature_samples_from(
+    sys_class: &Path,
+    ts: f64,
+    config: &SensorsConfig,
+) -> Vec<MetricSample> {
+    let mut samples = thermal_zone_samples_from(&sys_class.join("thermal"), ts);
+    let zone_types: BTreeSet<String> = samples.iter().map(|s| s.source.clone()).collect();
+    samples.extend(hwmon_temperature_samples_from(
+        &sys_class.join("hwmon"),
+        ts,
+        &zone_types,
+    ));
+    apply_renames(&mut samples, config);
+    samples
+}
+
+/// Applies `[sensors] names` to temperature samples, so renames also cover history
+/// collected before they were configured.
+pub fn apply_renames(samples: &mut [MetricSample], config: &SensorsConfig) {
+    if config.names.is_empty() {
+        return;
+    }
+    for sample in samples
+        .iter_mut()
+        .filter(|s| s.kind == MetricKind::Temperature)
+    {
+        let raw_id = sample.details.get("sensor").and_then(Value::as_str);
+        if let Some(name) = config.rename(&sample.source, raw_id) {
+            sample.source = name.to_string();
+        }
+    }
+}
+
+/// True when `source` matches a `--sensor` pattern: case-insensitive, `*` matches any run
+/// of characters.
+pub fn sensor_matches(pattern: &str, source: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let source = source.to_lowercase();
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = source.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+pub struct TemperatureCollector {
+    config: SensorsConfig,
+}
+
+impl TemperatureCollector {
+    pub fn new(config: SensorsConfig) -> Self {
+        TemperatureCollector { config }
+    }
+}
+
+impl Collector for TemperatureCollector {
+    fn name(&self) -> &str {
+        TEMPERATURE_COLLECTOR
+    }
+
+    fn kinds(&self) -> &[MetricKind] {
+        &[MetricKind::Temperature]
+    }
+
+    fn collect(&self, ts: f64) -> Vec<MetricSample> {
+        temperature_samples_from(Path::new("/sys/class"), ts, &self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn hwmon_inputs_get_readable_names() {
+        let tmp = tempfile::tempdir().unwrap();
+        let class = tmp.path();
+        write(&class.join("thermal/thermal_zone0/type"), "acpitz\n");
+        write(&class.join("thermal/thermal_zone0/temp"), "45000\n");
+        write(&class.join("hwmon/hwmon0/name"), "acpitz\n");
+        write(&class.join("hwmon/hwmon0/temp1_input"), "45000\n");
+        write(&class.join("hwmon/hwmon1/name"), "coretemp\n");
+        write(&class.join("hwmon/hwmon1/temp1_input"), "61000\n");
+        write(&class.join("hwmon/hwmon1/temp1_label"), "Package id 0\n");
+        write(&class.join("hwmon/hwmon1/temp2_input"), "58000\n");
+        write(&class.join("hwmon/hwmon1/temp2_label"), "Core 0\n");
+        for hwmon in ["hwmon2", "hwmon3"] {
+            write(&class.join(format!("hwmon/{hwmon}/name")), "nvme\n");
+            write(&class.join(format!("hwmon/{hwmon}/temp1_input")), "39850\n");
+            write(
+                &class.join(format!("hwmon/{hwmon}/temp1_label")),
+                "Composite\n",
+            );
+        }
+        write(&class.join("hwmon/hwmon4/name"), "mystery\n");
+        write(&class.join("hwmon/hwmon4/temp3_input"), "30000\n");
+
+        let config = SensorsConfig {
+            names: BTreeMap::from([
+                ("acpitz".to_string(), "Motherboard".to_string()),
+                ("NVMe Composite".to_string(), "SSD".to_string()),
+                ("mystery/temp3".to_string(), "Chassis".to_string()),
+            ]),
+        };
+        let samples = temperature_samples_from(class, 1.0, &config);
+        let sources: Vec<&str> = samples.iter().map(|s| s.source.as_str()).collect();
+        assert_eq!(
+            sources,
+            vec![
+                "Motherboard",
+                "CPU Package",
+                "CPU Core 0",
+                "SSD",
+                "NVMe Composite #2",
+                "Chassis"
+            ]
+        );
+        assert_eq!(samples[1].value, Some(61.0));
+        assert_eq!(samples[1].details["sensor"], "coretemp/temp1");
+    }
+
+    #[test]
+    fn sensor_patterns_are_case_insensitive_globs() {
+        assert!(sensor_matches("cpu package", "CPU Package"));
+        assert!(sensor_matches("CPU*", "CPU Core 3"));
+        assert!(sensor_matches("*composite*", "NVMe Composite #2"));
+        assert!(sensor_matches("c*e*0", "CPU Core 0"));
+        assert!(!sensor_matches("cpu1", "cpu10"));
+        assert!(!sensor_matches("*gpu", "GPU edge"));
+    }
+}
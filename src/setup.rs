@@ -0,0 +1,156 @@
+//! First-run setup for `symmetri init`: a starter config, the database, and systemd user
+//! units running the collector and the evening summary.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::config::user_config_dir;
+
+/// Written by `init` when there is no config yet. Every line is commented out, so it
+/// behaves exactly like a missing file until edited.
+pub const STARTER_CONFIG: &str = r#"# symmetri configuration; every section is optional. See the README for all of them.
+
+# [runtime]
+# model = "ewma"            # average, ewma or load
+# half_life_minutes = 30
+
+# [hooks]
+# on_low_battery = "notify-send 'Battery low'"
+# low_percent = 20
+
+# [health]
+# warn_below_pct = 80       # `health --notify` warns when wear will reach this...
+# warn_within_days = 90     # ...within this many days
+
+# [smoothing]
+# method = "kalman"         # none, median or kalman
+
+# [graph]
+# theme = "dark"
+# dir = "~/Pictures/symmetri"   # where `report -g` saves; default $XDG_PICTURES_DIR
+
+# [collectors.gpu]
+# enabled = false
+"#;
+
+const COLLECT_TIMER: &str = include_str!("../systemd/symmetri.timer");
+const SUMMARY_SERVICE: &str = include_str!("../systemd/symmetri-summary.service");
+const SUMMARY_TIMER: &str = include_str!("../systemd/symmetri-summary.timer");
+
+/// Timers `init` enables.
+pub const USER_TIMERS: [&str; 2] = ["symmetri.timer", "symmetri-summary.timer"];
+
+/// [`USER_TIMERS`], or only the summary timer when a system-wide collector already runs.
+pub fn user_timers(collector: bool) -> &'static [&'static str] {
+    if collector {
+        &USER_TIMERS
+    } else {
+        &USER_TIMERS[1..]
+    }
+}
+
+/// `$XDG_CONFIG_HOME/systemd/user`.
+pub fn user_unit_dir() -> Result<PathBuf> {
+    let config_dir = user_config_dir()
+        .context("cannot find the home directory for the systemd user units; set $HOME")?;
+    Ok(config_dir.join("systemd").join("user"))
+}
+
+/// File name and contents of the user units: the sample units from `systemd/`, with the
+/// collector writing to `db_path` instead of the system-wide `/var/lib` database. Without
+/// `collector`, only the summary units reading `db_path`.
+pub fn user_units(db_path: &Path, collector: bool) -> Vec<(&'static str, String)> {
+    let collect_service = format!(
+        "[Unit]\n\
+         Description=Symmetri metrics collector\n\
+         ConditionPathExists=/sys\n\
+         \n\
+         [Service]\n\
+         Type=oneshot\n\
+         Environment=\"SYMMETRI_DB={}\"\n\
+         ExecStart=/usr/bin/env symmetri-collect\n",
+        db_path.display()
+    );
+    let summary_service = SUMMARY_SERVICE.replace(
+        "[Service]\n",
+        &format!(
+            "[Service]\nEnvironment=\"SYMMETRI_DB={}\"\n",
+            db_path.display()
+        ),
+    );
+    vec![
+        ("symmetri.service", collect_service),
+        ("symmetri.timer", COLLECT_TIMER.to_string()),
+        ("symmetri-summary.service", summary_service),
+        ("symmetri-summary.timer", SUMMARY_TIMER.to_string()),
+    ]
+    .into_iter()
+    .filter(|(name, _)| collector || name.starts_with("symmetri-summary."))
+    .collect()
+}
+
+/// Writes `contents` to `path`, creating its directory, unless the file exists. Returns
+/// whether it was written; existing files are never touched.
+pub fn write_if_missing(path: &Path, contents: &str) -> Result<bool> {
+    if path.exists() {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Cannot create {}", parent.display()))?;
+    }
+    fs::write(path, contents).with_context(|| format!("Cannot write {}", path.display()))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{parse_config, Config};
+
+    #[test]
+    fn starter_files_are_valid_and_never_overwrite() {
+        assert_eq!(parse_config(STARTER_CONFIG).unwrap(), Config::default());
+        let uncommented: String = STARTER_CONFIG
+            .lines()
+            .filter_map(|line| line.strip_prefix("# "))
+            .filter(|line| line.starts_with('[') || line.contains(" = "))
+            .map(|line| format!("{line}\n"))
+            .collect();
+        parse_config(&uncommented).expect("commented examples parse once enabled");
+
+        let units = user_units(
+            Path::new("/home/me/.local/share/symmetri/symmetri.sqlite"),
+            true,
+        );
+        assert_eq!(units.len(), 4);
+        for (name, contents) in &units {
+            assert!(contents.contains("[Unit]"), "{name}");
+            if name.ends_with(".service") {
+                assert!(
+                    contents.contains(
+                        "Environment=\"SYMMETRI_DB=/home/me/.local/share/symmetri/symmetri.sqlite\""
+                    ),
+                    "{name}"
+                );
+                assert!(!contents.contains("/var/lib"), "{name}");
+            }
+        }
+
+        let summary_only = user_units(Path::new("/var/lib/symmetri/symmetri.sqlite"), false);
+        let names: Vec<&str> = summary_only.iter().map(|(name, _)| *name).collect();
+        assert_eq!(
+            names,
+            ["symmetri-summary.service", "symmetri-summary.timer"]
+        );
+        assert_eq!(user_timers(false), ["symmetri-summary.timer"]);
+
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("nested/config.toml");
+        assert!(write_if_missing(&path, "first").unwrap());
+        assert!(!write_if_missing(&path, "second").unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+    }
+}
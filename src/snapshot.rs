@@ -0,0 +1,341 @@
+//! `symmetri snapshot`: everything recorded around one incident (raw samples, annotations,
+//! suspends, versions and a graph) packed into a `.tar.gz` to attach to a kernel or driver
+//! bug report.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, TimeZone};
+use serde_json::{json, Value};
+
+use crate::cli_helpers::format_span;
+use crate::db::{self, Annotation, Sample, SleepInterval};
+use crate::gzip;
+use crate::locale::Formats;
+use crate::metrics::MetricSample;
+use crate::sysfs::BatteryInfo;
+use crate::timeframe::Timeframe;
+use crate::versions::{self, SystemVersions};
+
+const BLOCK: usize = 512;
+
+/// Accepts Unix seconds, RFC 3339, or `YYYY-MM-DD HH:MM[:SS]` in `zone`.
+pub fn parse_instant<Tz: TimeZone>(raw: &str, zone: &Tz) -> Result<f64, String> {
+    let raw = raw.trim();
+    if let Ok(ts) = raw.parse::<f64>() {
+        return Ok(ts);
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(dt.timestamp() as f64);
+    }
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(raw, fmt).ok())
+        .and_then(|naive| zone.from_local_datetime(&naive).earliest())
+        .map(|dt| dt.timestamp() as f64)
+        .ok_or_else(|| format!("invalid time {raw:?}; expected e.g. \"2024-05-02 14:30\""))
+}
+
+/// The stretch of history a snapshot covers, centered on the incident.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Window {
+    pub around: f64,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Window {
+    pub fn centered(around: f64, seconds: i64) -> Self {
+        let half = seconds as f64 / 2.0;
+        Window {
+            around,
+            start: around - half,
+            end: around + half,
+        }
+    }
+
+    /// A timeframe for the graph, labelled with the window length.
+    pub fn timeframe(&self) -> Timeframe {
+        let seconds = self.end - self.start;
+        Timeframe {
+            label: format!("{} around the incident", format_span(seconds as i64)),
+            seconds: Some(seconds),
+            hours: (seconds / 3600.0).ceil() as u64,
+            days: 0,
+            months: 0,
+            calendar: None,
+        }
+    }
+}
+
+/// What the database holds for a [`Window`].
+#[derive(Debug, Clone)]
+pub struct Incident {
+    pub window: Window,
+    pub samples: Vec<Sample>,
+    pub metrics: Vec<MetricSample>,
+    /// Notes plus the kernel/OS/symmetri upgrades made during the window.
+    pub annotations: Vec<Annotation>,
+    pub sleeps: Vec<SleepInterval>,
+    /// Versions running at the end of the window, when the collector recorded them.
+    pub versions: Option<SystemVersions>,
+    pub batteries: Vec<(f64, BatteryInfo)>,
+}
+
+impl Incident {
+    pub fn load(db_path: &Path, window: Window) -> Result<Self> {
+        let (start, end) = (Some(window.start), Some(window.end));
+        let history = db::fetch_system_versions(db_path, end)?;
+        let mut annotations = db::fetch_annotations(db_path, start, end)?;
+        annotations.extend(versions::change_annotations(&history, start));
+        annotations.sort_by(|a, b| a.ts.total_cmp(&b.ts));
+        Ok(Incident {
+            window,
+            samples: db::fetch_samples_between(db_path, window.start, window.end)?,
+            metrics: db::fetch_metric_samples_between(db_path, window.start, window.end, None)?,
+            annotations,
+            sleeps: db::fetch_sleep_intervals(db_path, start, end)?,
+            versions: history.last().map(|(_, versions)| versions.clone()),
+            batteries: db::fetch_battery_info(db_path)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty() && self.metrics.is_empty()
+    }
+
+    /// The archive's files: `samples.json`, `annotations.json`, `system.json` and, when
+    /// one was drawn, `graph.png`. Battery serial numbers are left out, since the archive
+    /// is meant to be posted publicly.
+    pub fn files(
+        &self,
+        formats: &Formats,
+        graph_png: Option<Vec<u8>>,
+    ) -> Result<Vec<(String, Vec<u8>)>> {
+        let time = |ts: f64| {
+            formats
+                .datetime(ts)
+                .map(|dt| dt.to_rfc3339())
+                .map_or(Value::Null, Value::String)
+        };
+        let versions = |versions: &SystemVersions| {
+            json!({
+                "kernel": versions.kernel,
+                "os": versions.os_release,
+                "symmetri": versions.symmetri,
+            })
+        };
+        let mut kinds: Vec<String> = self
+            .metrics
+            .iter()
+            .map(|sample| format!("{}[{}]", sample.kind.as_str(), sample.source))
+            .collect();
+        kinds.sort();
+        kinds.dedup();
+
+        let samples = json!({
+            "battery": self.samples,
+            "metrics": self.metrics,
+            "sleeps": self.sleeps.iter().map(|sleep| json!({
+                "start_ts": sleep.start_ts,
+                "end_ts": sleep.end_ts,
+                "slept_seconds": sleep.slept_seconds,
+                "start_pct": sleep.start_pct,
+                "end_pct": sleep.end_pct,
+                "start_status": sleep.start_status,
+                "end_status": sleep.end_status,
+            })).collect::<Vec<_>>(),
+        });
+        let annotations: Vec<Value> = self
+            .annotations
+            .iter()
+            .map(|note| json!({ "ts": note.ts, "time": time(note.ts), "text": note.text }))
+            .collect();
+        let system = json!({
+            "window": {
+                "around": time(self.window.around),
+                "start": time(self.window.start),
+                "end": time(self.window.end),
+                "start_ts": self.window.start,
+                "end_ts": self.window.end,
+            },
+            "versions": self.versions.as_ref().map(versions),
+            "snapshot_taken_with": versions(&SystemVersions::current()),
+            "batteries": self.batteries.iter().map(|(first_seen, info)| json!({
+                "source": info.source_path,
+                "manufacturer": info.manufacturer,
+                "model": info.model_name,
+                "technology": info.technology,
+                "energy_full_design_wh": info.energy_full_design_wh,
+                "first_seen": time(*first_seen),
+            })).collect::<Vec<_>>(),
+            "counts": {
+                "battery_samples": self.samples.len(),
+                "metric_samples": self.metrics.len(),
+                "annotations": self.annotations.len(),
+                "sleeps": self.sleeps.len(),
+            },
+            "metrics": kinds,
+        });
+
+        let mut files = vec![
+            (
+                "samples.json".to_string(),
+                serde_json::to_vec_pretty(&samples)?,
+            ),
+            (
+                "annotations.json".to_string(),
+                serde_json::to_vec_pretty(&annotations)?,
+            ),
+            (
+                "system.json".to_string(),
+                serde_json::to_vec_pretty(&system)?,
+            ),
+        ];
+        if let Some(png) = graph_png {
+            files.push(("graph.png".to_string(), png));
+        }
+        Ok(files)
+    }
+}
+
+/// A ustar header for a regular file; names have to fit the 100-byte field.
+fn tar_header(name: &str, size: usize, mtime: f64) -> Result<[u8; BLOCK]> {
+    if name.len() >= 100 {
+        anyhow::bail!("Archive member name too long: {name}");
+    }
+    let mut header = [0u8; BLOCK];
+    let mut put = |offset: usize, value: &[u8]| {
+        header[offset..offset + value.len()].copy_from_slice(value);
+    };
+    put(0, name.as_bytes());
+    put(100, b"0000644\0");
+    put(108, b"0000000\0");
+    put(116, b"0000000\0");
+    put(124, format!("{size:011o}\0").as_bytes());
+    put(136, format!("{:011o}\0", mtime.max(0.0) as u64).as_bytes());
+    put(148, b"        ");
+    put(156, b"0");
+    put(257, b"ustar\0");
+    put(263, b"00");
+    let checksum: u32 = header.iter().map(|&byte| u32::from(byte)).sum();
+    header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+    Ok(header)
+}
+
+/// Writes `files` as a gzipped tarball, each under `dir/`.
+pub fn write_tar_gz(path: &Path, dir: &str, files: &[(String, Vec<u8>)], mtime: f64) -> Result<()> {
+    let write = || -> Result<()> {
+        let mut tar = Vec::new();
+        for (name, body) in files {
+            tar.extend(tar_header(&format!("{dir}/{name}"), body.len(), mtime)?);
+            tar.extend(body);
+            let padding = (BLOCK - body.len() % BLOCK) % BLOCK;
+            tar.extend(vec![0; padding]);
+        }
+        // Two empty blocks end the archive.
+        tar.extend([0; 2 * BLOCK]);
+        gzip::compress(tar.as_slice(), BufWriter::new(File::create(path)?))?.flush()?;
+        Ok(())
+    };
+    write().with_context(|| format!("Cannot write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::metrics::MetricKind;
+    use crate::test_support::metric;
+
+    const AROUND: f64 = 1714660200.0;
+
+    fn incident() -> Incident {
+        Incident {
+            window: Window::centered(AROUND, 2 * 3600),
+            samples: Vec::new(),
+            metrics: vec![MetricSample {
+                unit: Some("W".to_string()),
+                ..metric(
+                    1714660000.0,
+                    MetricKind::PowerDraw,
+                    "rapl:power1",
+                    14.5,
+                    Value::Null,
+                )
+            }],
+            annotations: vec![Annotation {
+                ts: 1714659000.0,
+                text: "kernel changed 6.8.7 → 6.8.9".to_string(),
+            }],
+            sleeps: Vec::new(),
+            versions: None,
+            batteries: vec![(
+                1700000000.0,
+                BatteryInfo {
+                    source_path: "/sys/class/power_supply/BAT0".to_string(),
+                    serial_number: Some("1234".to_string()),
+                    ..BatteryInfo::default()
+                },
+            )],
+        }
+    }
+
+    #[test]
+    fn instants_parse_as_local_times_or_with_an_offset() {
+        assert_eq!(parse_instant("2024-05-02 14:30", &Utc), Ok(AROUND));
+        assert_eq!(parse_instant("2024-05-02T16:30:00+02:00", &Utc), Ok(AROUND));
+        assert!(parse_instant("May 2nd", &Utc).is_err());
+    }
+
+    #[test]
+    fn windows_are_centered_on_the_incident() {
+        let window = Window::centered(AROUND, 2 * 3600);
+        assert_eq!((window.start, window.end), (1714656600.0, 1714663800.0));
+        assert_eq!(window.timeframe().label, "2h around the incident");
+    }
+
+    #[test]
+    fn system_summary_counts_rows_and_leaves_out_serials() {
+        let files = incident().files(&Formats::default(), None).unwrap();
+        let system = String::from_utf8(files[2].1.clone()).unwrap();
+        assert!(system.contains("\"metric_samples\": 1"));
+        assert!(!system.contains("1234"));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn tarballs_hold_the_files_under_checksummed_headers() {
+        let files = incident().files(&Formats::default(), None).unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("incident.tar.gz");
+        write_tar_gz(&path, "incident", &files, AROUND).unwrap();
+        let mut tar = Vec::new();
+        gzip::decompress(File::open(&path).unwrap(), &mut tar).unwrap();
+        assert_eq!(tar.len() % BLOCK, 0);
+
+        let header = &tar[..BLOCK];
+        assert!(header.starts_with(b"incident/samples.json\0"));
+        let stored: u32 =
+            u32::from_str_radix(std::str::from_utf8(&header[148..154]).unwrap(), 8).unwrap();
+        let summed: u32 = header
+            .iter()
+            .enumerate()
+            .map(|(i, &byte)| {
+                if (148..156).contains(&i) {
+                    32
+                } else {
+                    u32::from(byte)
+                }
+            })
+            .sum();
+        assert_eq!(stored, summed);
+        let size =
+            usize::from_str_radix(std::str::from_utf8(&header[124..135]).unwrap(), 8).unwrap();
+        let samples: Value = serde_json::from_slice(&tar[BLOCK..BLOCK + size]).unwrap();
+        assert_eq!(samples["metrics"][0]["value"], 14.5);
+    }
+}
@@ -0,0 +1,138 @@
+//! `symmetri sources`: every battery, sensor, interface and disk the collectors can see on
+//! this machine with what they would report right now, including those the config leaves
+//! out, and why each one is left out.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::collector::{now_ts, raw_id, read_batteries, CollectorRegistry, BATTERY_COLLECTOR};
+use crate::config::Config;
+use crate::disk::{parse_mounts, select_mounts, ALL_MOUNTS, DISK_COLLECTOR};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiscoveredSource {
+    pub collector: String,
+    pub kind: String,
+    pub source: String,
+    pub value: Option<f64>,
+    pub unit: Option<String>,
+    /// Why it would not be stored with the current config; `None` when it would.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<String>,
+}
+
+/// What decides whether a source found by discovery is stored.
+pub struct Selection<'a> {
+    pub config: &'a Config,
+    /// Mountpoints `[disks] mounts` selects on this machine.
+    pub mountpoints: BTreeSet<String>,
+}
+
+impl<'a> Selection<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        let mounts = fs::read_to_string("/proc/self/mounts")
+            .map(|content| parse_mounts(&content))
+            .unwrap_or_default();
+        Selection {
+            config,
+            mountpoints: select_mounts(&mounts, &config.disks.mounts)
+                .into_iter()
+                .map(|mount| mount.mountpoint)
+                .collect(),
+        }
+    }
+
+    /// Why `source` of `collector` would not be stored, or `None`.
+    pub fn skipped(&self, collector: &str, source: &str, raw_id: Option<&str>) -> Option<String> {
+        if !self.config.collector_enabled(collector) {
+            return Some(format!("[collectors.{collector}] enabled = false"));
+        }
+        if !self.config.source_allowed(collector, source, raw_id) {
+            return Some(format!("[collectors.{collector}] allow/deny"));
+        }
+        if collector == DISK_COLLECTOR && !self.mountpoints.contains(source) {
+            return Some("not in [disks] mounts".to_string());
+        }
+        None
+    }
+}
+
+/// Reads every battery and runs every collector once, with `[disks] mounts = ["*"]` so all
+/// real filesystems show up, and marks what the current config would skip. cgroups and
+/// scripts only report what their sections configure.
+pub fn discover(sysfs_root: Option<&Path>, config: &Config) -> Vec<DiscoveredSource> {
+    let ts = now_ts();
+    let selection = Selection::new(config);
+    let mut everything = config.clone();
+    everything.disks.mounts = vec![ALL_MOUNTS.to_string()];
+
+    let root = sysfs_root.unwrap_or_else(|| Path::new("/sys/class/power_supply"));
+    let mut sources: Vec<DiscoveredSource> = read_batteries(root, config, ts)
+        .into_iter()
+        .map(|sample| {
+            let name = Path::new(&sample.source_path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            DiscoveredSource {
+                collector: BATTERY_COLLECTOR.to_string(),
+                kind: BATTERY_COLLECTOR.to_string(),
+                skipped: selection.skipped(BATTERY_COLLECTOR, &name, Some(&sample.source_path)),
+                source: sample.source_path,
+                value: sample.percentage,
+                unit: Some("%".to_string()),
+            }
+        })
+        .collect();
+    for (collector, samples) in
+        CollectorRegistry::from_config(&everything).collect_all(&everything, ts)
+    {
+        for sample in samples {
+            sources.push(DiscoveredSource {
+                skipped: selection.skipped(&collector, &sample.source, raw_id(&sample)),
+                collector: collector.clone(),
+                kind: sample.kind.as_str().to_string(),
+                source: sample.source,
+                value: sample.value,
+                unit: sample.unit,
+            });
+        }
+    }
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    #[test]
+    fn disabled_collectors_and_unlisted_mounts_are_skipped() {
+        let config = parse_config(
+            "[collectors.gpu]\nenabled = false\n\n[collectors.network]\ndeny = [\"veth*\"]\n\n\
+             [disks]\nmounts = [\"/\", \"/home\"]\n",
+        )
+        .unwrap();
+        let selection = Selection {
+            config: &config,
+            mountpoints: ["/".to_string()].into(),
+        };
+        assert_eq!(selection.skipped("network", "wlan0", None), None);
+        assert_eq!(
+            selection.skipped("network", "veth1a2b", None).as_deref(),
+            Some("[collectors.network] allow/deny")
+        );
+        assert_eq!(selection.skipped(DISK_COLLECTOR, "/", None), None);
+        assert_eq!(
+            selection.skipped(DISK_COLLECTOR, "/boot", None).as_deref(),
+            Some("not in [disks] mounts")
+        );
+        assert_eq!(
+            selection.skipped("gpu", "card0", None).as_deref(),
+            Some("[collectors.gpu] enabled = false")
+        );
+    }
+}
@@ -0,0 +1,42 @@
+//! Table colors. Builds without the `color` feature (comfy-table's `tty`) get stand-ins
+//! that leave cells unstyled, so report code reads the same either way.
+
+#[cfg(feature = "color")]
+pub use comfy_table::{Attribute, Color};
+
+#[cfg(not(feature = "color"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Blue,
+    Cyan,
+    DarkGrey,
+    Green,
+    Magenta,
+    Red,
+    White,
+    Yellow,
+}
+
+#[cfg(not(feature = "color"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attribute {
+    Bold,
+}
+
+/// `Cell::fg` and `Cell::add_attribute` as no-ops.
+#[cfg(not(feature = "color"))]
+pub trait Unstyled {
+    fn fg(self, color: Color) -> Self;
+    fn add_attribute(self, attribute: Attribute) -> Self;
+}
+
+#[cfg(not(feature = "color"))]
+impl Unstyled for comfy_table::Cell {
+    fn fg(self, _color: Color) -> Self {
+        self
+    }
+
+    fn add_attribute(self, _attribute: Attribute) -> Self {
+        self
+    }
+}
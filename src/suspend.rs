@@ -0,0 +1,146 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::SleepInterval;
+
+/// Shorter suspends are ignored (lid bounces, failed suspend attempts).
+pub const MIN_SLEEP_SECONDS: f64 = 60.0;
+
+/// Snapshot taken at every collection; comparing two consecutive probes tells how long the
+/// machine was suspended in between. `suspended_seconds` is CLOCK_BOOTTIME minus
+/// CLOCK_MONOTONIC, i.e. the total time spent suspended since boot.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SuspendProbe {
+    pub ts: f64,
+    pub boot_id: String,
+    pub suspended_seconds: f64,
+    pub percentage: Option<f64>,
+    /// Battery status; absent in probes stored by older versions.
+    #[serde(default)]
+    pub status: Option<String>,
+}
+
+fn clock_seconds(clock: libc::clockid_t) -> Option<f64> {
+    let mut spec: libc::timespec = unsafe { std::mem::zeroed() };
+    let rc = unsafe { libc::clock_gettime(clock, &mut spec) };
+    if rc != 0 {
+        return None;
+    }
+    Some(spec.tv_sec as f64 + spec.tv_nsec as f64 / 1_000_000_000.0)
+}
+
+pub fn suspended_seconds_since_boot() -> Option<f64> {
+    let boottime = clock_seconds(libc::CLOCK_BOOTTIME)?;
+    let monotonic = clock_seconds(libc::CLOCK_MONOTONIC)?;
+    Some((boottime - monotonic).max(0.0))
+}
+
+pub fn read_boot_id() -> Option<String> {
+    fs::read_to_string("/proc/sys/kernel/random/boot_id")
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+pub fn probe(ts: f64, percentage: Option<f64>, status: Option<String>) -> Option<SuspendProbe> {
+    Some(SuspendProbe {
+        ts,
+        boot_id: read_boot_id()?,
+        suspended_seconds: suspended_seconds_since_boot()?,
+        percentage,
+        status,
+    })
+}
+
+/// Sleep between two probes of the same boot. The exact suspend/resume instants are not
+/// known, so the interval is bounded by the two collections around it.
+pub fn detect_sleep(previous: &SuspendProbe, current: &SuspendProbe) -> Option<SleepInterval> {
+    if previous.boot_id != current.boot_id || current.ts <= previous.ts {
+        return None;
+    }
+    let slept = current.suspended_seconds - previous.suspended_seconds;
+    if slept < MIN_SLEEP_SECONDS {
+        return None;
+    }
+    Some(SleepInterval {
+        start_ts: previous.ts,
+        end_ts: current.ts,
+        slept_seconds: slept.min(current.ts - previous.ts),
+        start_pct: previous.percentage,
+        end_pct: current.percentage,
+        start_status: previous.status.clone(),
+        end_status: current.status.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe_at(ts: f64, boot_id: &str, suspended: f64, pct: f64) -> SuspendProbe {
+        SuspendProbe {
+            ts,
+            boot_id: boot_id.to_string(),
+            suspended_seconds: suspended,
+            percentage: Some(pct),
+            status: Some("Discharging".to_string()),
+        }
+    }
+
+    #[test]
+    fn detects_sleep_from_suspended_clock_delta() {
+        let before = probe_at(1_000.0, "boot-a", 10.0, 80.0);
+        let after = probe_at(30_000.0, "boot-a", 28_010.0, 77.0);
+
+        let sleep = detect_sleep(&before, &after).unwrap();
+        assert_eq!(sleep.start_ts, 1_000.0);
+        assert_eq!(sleep.end_ts, 30_000.0);
+        assert_eq!(sleep.slept_seconds, 28_000.0);
+        assert_eq!(sleep.lost_pct(), Some(3.0));
+    }
+
+    #[test]
+    fn charging_while_asleep_is_not_a_loss() {
+        let plugged = |probe: SuspendProbe, status: &str| SuspendProbe {
+            status: Some(status.to_string()),
+            ..probe
+        };
+        let before = plugged(probe_at(1_000.0, "boot-a", 10.0, 40.0), "Charging");
+        let after = plugged(probe_at(30_000.0, "boot-a", 28_010.0, 90.0), "Charging");
+        let sleep = detect_sleep(&before, &after).unwrap();
+        assert_eq!(sleep.start_status.as_deref(), Some("Charging"));
+        assert!(!sleep.on_battery());
+        assert_eq!(sleep.lost_pct(), None);
+
+        // Unplugged before suspending but plugged in by resume.
+        let unplugged = probe_at(1_000.0, "boot-a", 10.0, 80.0);
+        assert_eq!(detect_sleep(&unplugged, &after).unwrap().lost_pct(), None);
+
+        // Intervals stored before statuses were fall back to whether the charge rose.
+        let legacy = |start_pct, end_pct| SleepInterval {
+            start_status: None,
+            end_status: None,
+            start_pct: Some(start_pct),
+            end_pct: Some(end_pct),
+            ..sleep.clone()
+        };
+        assert_eq!(legacy(80.0, 77.0).lost_pct(), Some(3.0));
+        assert_eq!(legacy(40.0, 90.0).lost_pct(), None);
+    }
+
+    #[test]
+    fn ignores_short_sleeps_and_reboots() {
+        let before = probe_at(1_000.0, "boot-a", 10.0, 80.0);
+        let blip = probe_at(1_300.0, "boot-a", 40.0, 80.0);
+        let rebooted = probe_at(30_000.0, "boot-b", 0.0, 60.0);
+
+        assert!(detect_sleep(&before, &blip).is_none());
+        assert!(detect_sleep(&before, &rebooted).is_none());
+    }
+
+    #[test]
+    fn suspended_clock_is_available() {
+        assert!(suspended_seconds_since_boot().unwrap() >= 0.0);
+    }
+}
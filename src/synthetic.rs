@@ -0,0 +1,482 @@
+use std::f64::consts::TAU;
+
+use chrono::{Datelike, NaiveDate, Timelike, Weekday};
+use clap::ValueEnum;
+use serde_json::json;
+
+use crate::db::{Sample, SleepInterval};
+use crate::locale::Formats;
+use crate::metrics::{MetricKind, MetricSample, LOAD_SOURCES, MEMORY_SOURCE};
+
+const BATTERY_PATH: &str = "/sys/class/power_supply/BAT0";
+const CORES: usize = 4;
+const MEMORY_TOTAL_BYTES: f64 = 16.0 * 1024.0 * 1024.0 * 1024.0;
+/// Drain while suspended (s2idle on a modern laptop).
+const SUSPENDED_W: f64 = 0.4;
+/// Capacity lost per day of use, as a fraction of the design capacity.
+const WEAR_PER_DAY: f64 = 0.0002;
+
+/// Usage pattern `dev generate` simulates.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Profile {
+    /// Weekdays at a desk with the charger, unplugged meetings and evenings, charged overnight
+    #[default]
+    OfficeLaptop,
+    /// Long unplugged days with short top-ups, not charged every night
+    Traveler,
+    /// On the charger all day behind an 80% charge limit
+    Docked,
+}
+
+/// Machine the profile runs on.
+struct Hardware {
+    design_wh: f64,
+    idle_w: f64,
+    busy_w: f64,
+    charge_w: f64,
+    /// Charging stops here ("Not charging"), as with a vendor charge threshold.
+    charge_limit_pct: f64,
+}
+
+impl Profile {
+    fn hardware(self) -> Hardware {
+        match self {
+            Profile::OfficeLaptop => Hardware {
+                design_wh: 57.0,
+                idle_w: 4.5,
+                busy_w: 22.0,
+                charge_w: 45.0,
+                charge_limit_pct: 100.0,
+            },
+            Profile::Traveler => Hardware {
+                design_wh: 72.0,
+                idle_w: 3.5,
+                busy_w: 18.0,
+                charge_w: 60.0,
+                charge_limit_pct: 100.0,
+            },
+            Profile::Docked => Hardware {
+                design_wh: 50.0,
+                idle_w: 6.0,
+                busy_w: 35.0,
+                charge_w: 65.0,
+                charge_limit_pct: 80.0,
+            },
+        }
+    }
+
+    /// Awake stretches of one day, jittered from the profile's template. The machine is
+    /// suspended the rest of the time.
+    fn day_plan(self, weekday: Weekday, rng: &mut Rng) -> DayPlan {
+        let weekend = matches!(weekday, Weekday::Sat | Weekday::Sun);
+        let blocks: &[(f64, f64, bool, f64)] = match (self, weekend) {
+            (Profile::OfficeLaptop, false) => &[
+                (7.5, 8.3, false, 0.15),
+                (9.0, 12.0, true, 0.35),
+                (13.2, 14.0, true, 0.3),
+                (14.0, 15.5, false, 0.25),
+                (15.5, 17.6, true, 0.4),
+                (19.5, 22.5, false, 0.3),
+            ],
+            (Profile::OfficeLaptop, true) => &[
+                (10.0, 12.0, false, 0.2),
+                (15.0, 18.0, true, 0.45),
+                (21.0, 23.0, true, 0.6),
+            ],
+            (Profile::Traveler, _) => &[
+                (7.0, 9.5, false, 0.2),
+                (10.0, 13.0, false, 0.3),
+                (13.0, 14.0, true, 0.2),
+                (15.0, 19.0, false, 0.35),
+                (21.0, 23.5, false, 0.25),
+            ],
+            (Profile::Docked, false) => &[(8.0, 12.5, true, 0.4), (13.0, 19.0, true, 0.45)],
+            (Profile::Docked, true) => &[(11.0, 13.0, false, 0.3), (20.0, 23.0, true, 0.7)],
+        };
+        let jitter = |rng: &mut Rng, hour: f64| (hour + rng.normal() * 0.2).clamp(0.0, 24.0);
+        let mut awake = Vec::new();
+        let mut previous_end = 0.0_f64;
+        for &(start, end, plugged, load) in blocks {
+            let start = jitter(rng, start).max(previous_end);
+            let end = jitter(rng, end).max(start);
+            previous_end = end;
+            let load = (load * (1.0 + rng.normal() * 0.25)).clamp(0.02, 0.95);
+            awake.push(AwakeBlock {
+                start,
+                end,
+                plugged,
+                load,
+            });
+        }
+        // Travellers find a socket for the night about two times out of three.
+        let overnight_plugged = match self {
+            Profile::Traveler => rng.uniform() < 0.65,
+            _ => true,
+        };
+        DayPlan {
+            awake,
+            overnight_plugged,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct AwakeBlock {
+    start: f64,
+    end: f64,
+    plugged: bool,
+    load: f64,
+}
+
+#[derive(Debug, Clone)]
+struct DayPlan {
+    awake: Vec<AwakeBlock>,
+    overnight_plugged: bool,
+}
+
+impl DayPlan {
+    fn block_at(&self, hour: f64) -> Option<&AwakeBlock> {
+        self.awake
+            .iter()
+            .find(|block| block.start <= hour && hour < block.end)
+    }
+
+    /// Suspended machines charge before the first and after the last awake stretch.
+    fn suspended_plugged(&self, hour: f64) -> bool {
+        let first = self.awake.first().map_or(24.0, |block| block.start);
+        let last = self.awake.last().map_or(0.0, |block| block.end);
+        self.overnight_plugged && (hour < first || hour >= last)
+    }
+}
+
+/// SplitMix64, so a seed always gives the same database.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1).
+    fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Standard normal (Box–Muller).
+    fn normal(&mut self) -> f64 {
+        let u1 = self.uniform().max(f64::MIN_POSITIVE);
+        let u2 = self.uniform();
+        (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos()
+    }
+}
+
+/// What `dev generate` writes: battery readings and metrics for every awake reading, and
+/// the suspends between them.
+#[derive(Debug, Clone, Default)]
+pub struct Synthetic {
+    pub samples: Vec<Sample>,
+    pub metrics: Vec<MetricSample>,
+    pub sleeps: Vec<SleepInterval>,
+}
+
+/// Simulates `days` of `profile` ending at `end_ts`, one reading every `interval_seconds`
+/// while awake. Days follow the wall clock of `formats.zone`.
+pub fn generate(
+    profile: Profile,
+    days: u32,
+    end_ts: f64,
+    interval_seconds: f64,
+    seed: u64,
+    formats: &Formats,
+) -> Synthetic {
+    let hardware = profile.hardware();
+    let mut rng = Rng(seed);
+    let mut out = Synthetic::default();
+    let start_ts = end_ts - f64::from(days) * 86_400.0;
+    let mut energy_wh = hardware.design_wh * 0.95 * 0.8;
+    let mut plans: Option<(NaiveDate, DayPlan)> = None;
+    let mut sleep_start: Option<(f64, Option<f64>, Option<String>)> = None;
+    let mut load_avg = [0.0_f64; 3];
+    let mut network_bytes = 0.0;
+    let mut ts = start_ts;
+    while ts <= end_ts {
+        let Some(local) = formats.datetime(ts) else {
+            ts += interval_seconds;
+            continue;
+        };
+        let date = local.date_naive();
+        if plans.as_ref().is_none_or(|(day, _)| *day != date) {
+            plans = Some((date, profile.day_plan(local.weekday(), &mut rng)));
+        }
+        let plan = &plans.as_ref().expect("plan for today").1;
+        let hour = f64::from(local.hour()) + f64::from(local.minute()) / 60.0;
+        let days_in = (ts - start_ts) / 86_400.0;
+        let full_wh = hardware.design_wh * (0.95 - WEAR_PER_DAY * days_in);
+        let limit_wh = full_wh * hardware.charge_limit_pct / 100.0;
+        let hours = interval_seconds / 3600.0;
+
+        let Some(block) = plan.block_at(hour).copied() else {
+            // Suspended: nothing is collected, the battery keeps changing.
+            if sleep_start.is_none() {
+                let last = out.samples.last();
+                sleep_start = Some((
+                    last.map_or(ts, |s| s.ts),
+                    last.and_then(|s| s.percentage),
+                    last.and_then(|s| s.status.clone()),
+                ));
+            }
+            energy_wh = if plan.suspended_plugged(hour) {
+                (energy_wh + charge_w(&hardware, energy_wh, limit_wh) * hours).min(limit_wh)
+            } else {
+                (energy_wh - SUSPENDED_W * hours).max(0.0)
+            };
+            ts += interval_seconds;
+            continue;
+        };
+
+        let load = (block.load * (1.0 + 0.35 * rng.normal())).clamp(0.01, 1.0);
+        let draw_w = hardware.idle_w + (hardware.busy_w - hardware.idle_w) * load;
+        let status = if block.plugged {
+            if energy_wh >= limit_wh - 0.05 {
+                energy_wh = limit_wh;
+                if hardware.charge_limit_pct < 100.0 {
+                    "Not charging"
+                } else {
+                    "Full"
+                }
+            } else {
+                energy_wh =
+                    (energy_wh + charge_w(&hardware, energy_wh, limit_wh) * hours).min(limit_wh);
+                "Charging"
+            }
+        } else {
+            energy_wh = (energy_wh - draw_w * hours).max(0.0);
+            // Out of battery: the machine shuts down until someone plugs it in.
+            if energy_wh <= 0.0 {
+                ts += interval_seconds;
+                continue;
+            }
+            "Discharging"
+        };
+        if let Some((start, start_pct, start_status)) = sleep_start.take() {
+            if !out.samples.is_empty() {
+                out.sleeps.push(SleepInterval {
+                    start_ts: start,
+                    end_ts: ts,
+                    slept_seconds: ts - start,
+                    start_pct,
+                    end_pct: Some(energy_wh / full_wh * 100.0),
+                    start_status,
+                    end_status: Some(status.to_string()),
+                });
+            }
+        }
+
+        // The gauge reads a little off from the true charge.
+        let reported_wh = (energy_wh + rng.normal() * 0.03).clamp(0.0, full_wh);
+        let percentage = reported_wh / full_wh * 100.0;
+        out.samples.push(Sample {
+            ts,
+            percentage: Some(percentage),
+            capacity_pct: Some(percentage.round()),
+            health_pct: Some(full_wh / hardware.design_wh * 100.0),
+            energy_now_wh: Some(reported_wh),
+            energy_full_wh: Some(full_wh),
+            energy_full_design_wh: Some(hardware.design_wh),
+            status: Some(status.to_string()),
+            source_path: BATTERY_PATH.to_string(),
+        });
+
+        for (average, minutes) in load_avg.iter_mut().zip([1.0, 5.0, 15.0]) {
+            let weight = (interval_seconds / (minutes * 60.0)).min(1.0);
+            *average += weight * (load * CORES as f64 - *average);
+        }
+        network_bytes += (load * 400_000.0 + rng.uniform() * 50_000.0) * interval_seconds;
+        out.metrics.extend(metrics_at(
+            ts,
+            load,
+            draw_w,
+            &load_avg,
+            network_bytes,
+            &mut rng,
+        ));
+        ts += interval_seconds;
+    }
+    out
+}
+
+/// Charging power, tapering off over the last 20% below the limit.
+fn charge_w(hardware: &Hardware, energy_wh: f64, limit_wh: f64) -> f64 {
+    let remaining = ((limit_wh - energy_wh) / limit_wh).clamp(0.0, 1.0);
+    hardware.charge_w * (remaining / 0.2).clamp(0.08, 1.0)
+}
+
+fn metrics_at(
+    ts: f64,
+    load: f64,
+    draw_w: f64,
+    load_avg: &[f64; 3],
+    network_bytes: f64,
+    rng: &mut Rng,
+) -> Vec<MetricSample> {
+    let mut metrics = Vec::new();
+    let cpu = |source: String, value: f64| {
+        MetricSample::new(
+            ts,
+            MetricKind::CpuUsage,
+            source,
+            Some(value.clamp(0.0, 100.0)),
+            Some("%"),
+            serde_json::Value::Null,
+        )
+    };
+    metrics.push(cpu("cpu".to_string(), load * 100.0));
+    for core in 0..CORES {
+        metrics.push(cpu(
+            format!("cpu{core}"),
+            load * 100.0 * (1.0 + rng.normal() * 0.3),
+        ));
+        metrics.push(MetricSample::new(
+            ts,
+            MetricKind::CpuFrequency,
+            format!("cpu{core}"),
+            Some((800.0 + 3_400.0 * load + rng.normal() * 150.0).clamp(400.0, 4_800.0)),
+            Some("MHz"),
+            serde_json::Value::Null,
+        ));
+    }
+    let used = MEMORY_TOTAL_BYTES * (0.35 + 0.3 * load + rng.uniform() * 0.05);
+    metrics.push(MetricSample::new(
+        ts,
+        MetricKind::MemoryUsage,
+        MEMORY_SOURCE,
+        Some(used),
+        Some("bytes"),
+        json!({
+            "total_bytes": MEMORY_TOTAL_BYTES,
+            "available_bytes": MEMORY_TOTAL_BYTES - used,
+            "used_bytes": used
+        }),
+    ));
+    metrics.push(MetricSample::new(
+        ts,
+        MetricKind::NetworkBytes,
+        "wlan0",
+        Some(network_bytes),
+        Some("bytes"),
+        json!({ "rx_bytes": network_bytes * 0.85, "tx_bytes": network_bytes * 0.15 }),
+    ));
+    metrics.push(MetricSample::new(
+        ts,
+        MetricKind::Temperature,
+        "CPU Package",
+        Some(38.0 + 2.2 * draw_w + rng.normal()),
+        Some("C"),
+        json!({ "sensor": "coretemp/temp1" }),
+    ));
+    for (source, value) in LOAD_SOURCES.into_iter().zip(load_avg) {
+        metrics.push(MetricSample::new(
+            ts,
+            MetricKind::SystemLoad,
+            source,
+            Some(*value),
+            None,
+            serde_json::Value::Null,
+        ));
+    }
+    metrics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zone::Zone;
+
+    // 2024-06-01 00:00 UTC.
+    const END_TS: f64 = 1_717_200_000.0;
+
+    fn utc() -> Formats {
+        Formats {
+            zone: Zone::Utc,
+            ..Formats::default()
+        }
+    }
+
+    fn office_fortnight() -> Synthetic {
+        generate(Profile::OfficeLaptop, 14, END_TS, 120.0, 7, &utc())
+    }
+
+    #[test]
+    fn the_same_seed_generates_the_same_data() {
+        assert_eq!(office_fortnight().samples, office_fortnight().samples);
+    }
+
+    #[test]
+    fn office_days_discharge_and_charge() {
+        let data = office_fortnight();
+        let statuses = |status: &str| {
+            data.samples
+                .iter()
+                .filter(|s| s.status.as_deref() == Some(status))
+                .count()
+        };
+        assert!(statuses("Discharging") > 500);
+        assert!(statuses("Charging") > 50);
+        assert!(statuses("Full") > 50);
+    }
+
+    #[test]
+    fn office_nights_are_suspended() {
+        let data = office_fortnight();
+        // Roughly one overnight suspend and a few short ones per day.
+        assert!(
+            (14..=14 * 5).contains(&data.sleeps.len()),
+            "{}",
+            data.sleeps.len()
+        );
+        // Every reading is awake, and none fall in the small hours.
+        let formats = utc();
+        let night = data
+            .samples
+            .iter()
+            .filter(|s| formats.datetime(s.ts).unwrap().hour() < 6)
+            .count();
+        assert_eq!(night, 0);
+    }
+
+    #[test]
+    fn samples_are_ordered_and_in_range() {
+        let data = office_fortnight();
+        assert!(data
+            .samples
+            .iter()
+            .all(|s| (0.0..=100.0).contains(&s.percentage.unwrap())));
+        assert!(data.samples.windows(2).all(|pair| pair[0].ts < pair[1].ts));
+    }
+
+    #[test]
+    fn every_battery_reading_has_a_cpu_reading() {
+        let data = office_fortnight();
+        let cpu_rows = data
+            .metrics
+            .iter()
+            .filter(|m| m.kind == MetricKind::CpuUsage && m.source == "cpu")
+            .count();
+        assert_eq!(cpu_rows, data.samples.len());
+    }
+
+    #[test]
+    fn docked_laptops_stay_under_their_charge_limit() {
+        let docked = generate(Profile::Docked, 3, END_TS, 300.0, 1, &utc());
+        let max_pct = docked
+            .samples
+            .iter()
+            .filter_map(|s| s.percentage)
+            .fold(0.0, f64::max);
+        assert!(max_pct < 81.0, "{max_pct}");
+    }
+}
@@ -2,6 +2,9 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::config::QuirkOverrides;
+use crate::normalize::{normalize, power_w, Quirks, RawBattery};
+
 #[derive(Debug, Clone)]
 pub struct BatteryReading {
     pub path: PathBuf,
@@ -14,6 +17,18 @@ pub struct BatteryReading {
     pub status: Option<String>,
 }
 
+/// What a battery reports about itself, kept so replaced batteries and multi-battery
+/// histories can be told apart.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatteryInfo {
+    pub source_path: String,
+    pub manufacturer: Option<String>,
+    pub model_name: Option<String>,
+    pub serial_number: Option<String>,
+    pub technology: Option<String>,
+    pub energy_full_design_wh: Option<f64>,
+}
+
 fn parse_uevent(path: &Path) -> HashMap<String, String> {
     let mut data = HashMap::new();
     let content = fs::read_to_string(path.join("uevent")).unwrap_or_default();
@@ -55,36 +70,6 @@ fn read_str(path: &Path) -> Option<String> {
     }
 }
 
-fn wh_from_energy(raw_value: Option<f64>) -> Option<f64> {
-    raw_value.map(|v| v / 1_000_000.0)
-}
-
-fn energy_wh_from_charge(charge_uah: Option<f64>, voltage_uv: Option<f64>) -> Option<f64> {
-    match (charge_uah, voltage_uv) {
-        (Some(charge), Some(voltage)) => Some((charge * voltage) / 1_000_000_000_000.0),
-        _ => None,
-    }
-}
-
-fn read_voltage(path: &Path, uevent: &HashMap<String, String>) -> Option<f64> {
-    float_from_uevent(
-        uevent,
-        &[
-            "POWER_SUPPLY_VOLTAGE_NOW",
-            "POWER_SUPPLY_VOLTAGE_MIN_DESIGN",
-            "POWER_SUPPLY_VOLTAGE_MAX_DESIGN",
-        ],
-    )
-    .or_else(|| {
-        for name in ["voltage_now", "voltage_min_design", "voltage_max_design"] {
-            if let Some(value) = read_float(&path.join(name)) {
-                return Some(value);
-            }
-        }
-        None
-    })
-}
-
 pub fn find_battery_paths(sysfs_root: &Path) -> Vec<PathBuf> {
     let mut batteries = Vec::new();
     let entries = match fs::read_dir(sysfs_root) {
@@ -111,89 +96,139 @@ pub fn find_battery_paths(sysfs_root: &Path) -> Vec<PathBuf> {
     batteries
 }
 
-pub fn read_battery(path: &Path) -> BatteryReading {
+/// Raw attribute values, preferring `uevent` over the individual files.
+pub fn read_raw_battery(path: &Path) -> RawBattery {
     let uevent = parse_uevent(path);
-
-    let mut energy_now_raw = float_from_uevent(&uevent, &["POWER_SUPPLY_ENERGY_NOW"]);
-    if energy_now_raw.is_none() {
-        energy_now_raw = read_float(&path.join("energy_now"));
-    }
-
-    let mut energy_full_raw = float_from_uevent(&uevent, &["POWER_SUPPLY_ENERGY_FULL"]);
-    if energy_full_raw.is_none() {
-        energy_full_raw = read_float(&path.join("energy_full"));
-    }
-
-    let mut energy_full_design_raw =
-        float_from_uevent(&uevent, &["POWER_SUPPLY_ENERGY_FULL_DESIGN"]);
-    if energy_full_design_raw.is_none() {
-        energy_full_design_raw = read_float(&path.join("energy_full_design"));
-    }
-
-    let mut charge_now = float_from_uevent(&uevent, &["POWER_SUPPLY_CHARGE_NOW"]);
-    if charge_now.is_none() {
-        charge_now = read_float(&path.join("charge_now"));
+    let value = |key: &str, file: &str| {
+        float_from_uevent(&uevent, &[key]).or_else(|| read_float(&path.join(file)))
+    };
+    RawBattery {
+        energy_now: value("POWER_SUPPLY_ENERGY_NOW", "energy_now"),
+        energy_full: value("POWER_SUPPLY_ENERGY_FULL", "energy_full"),
+        energy_full_design: value("POWER_SUPPLY_ENERGY_FULL_DESIGN", "energy_full_design"),
+        charge_now: value("POWER_SUPPLY_CHARGE_NOW", "charge_now"),
+        charge_full: value("POWER_SUPPLY_CHARGE_FULL", "charge_full"),
+        charge_full_design: value("POWER_SUPPLY_CHARGE_FULL_DESIGN", "charge_full_design"),
+        voltage_now: value("POWER_SUPPLY_VOLTAGE_NOW", "voltage_now"),
+        voltage_design: value("POWER_SUPPLY_VOLTAGE_MIN_DESIGN", "voltage_min_design")
+            .or_else(|| value("POWER_SUPPLY_VOLTAGE_MAX_DESIGN", "voltage_max_design")),
+        power_now: value("POWER_SUPPLY_POWER_NOW", "power_now"),
+        current_now: value("POWER_SUPPLY_CURRENT_NOW", "current_now"),
+        capacity: value("POWER_SUPPLY_CAPACITY", "capacity"),
+        status: uevent
+            .get("POWER_SUPPLY_STATUS")
+            .cloned()
+            .or_else(|| read_str(&path.join("status"))),
     }
+}
 
-    let mut charge_full = float_from_uevent(&uevent, &["POWER_SUPPLY_CHARGE_FULL"]);
-    if charge_full.is_none() {
-        charge_full = read_float(&path.join("charge_full"));
+/// Vendor, model, serial and design capacity of the battery at `path`. Charge-only
+/// batteries are converted with the design voltage, so the value stays put between reads.
+pub fn read_battery_info(path: &Path, overrides: &QuirkOverrides) -> BatteryInfo {
+    let uevent = parse_uevent(path);
+    let text = |key: &str, file: &str| {
+        uevent
+            .get(key)
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .or_else(|| read_str(&path.join(file)))
+    };
+    let raw = read_raw_battery(path);
+    let quirks = Quirks::detect(&raw, overrides);
+    let energy_full_design_wh = raw
+        .energy_full_design
+        .map(|value| value * quirks.energy.factor())
+        .or_else(|| {
+            let charge_ah = raw.charge_full_design? * quirks.charge.factor();
+            Some(charge_ah * raw.voltage_design? * quirks.voltage.factor())
+        });
+    BatteryInfo {
+        source_path: path.display().to_string(),
+        manufacturer: text("POWER_SUPPLY_MANUFACTURER", "manufacturer"),
+        model_name: text("POWER_SUPPLY_MODEL_NAME", "model_name"),
+        serial_number: text("POWER_SUPPLY_SERIAL_NUMBER", "serial_number"),
+        technology: text("POWER_SUPPLY_TECHNOLOGY", "technology"),
+        energy_full_design_wh,
     }
+}
 
-    let mut charge_full_design = float_from_uevent(&uevent, &["POWER_SUPPLY_CHARGE_FULL_DESIGN"]);
-    if charge_full_design.is_none() {
-        charge_full_design = read_float(&path.join("charge_full_design"));
-    }
+/// Reading in W, Wh and percent, with unit quirks detected per device and `overrides`
+/// (from `[quirks]`) taking precedence.
+pub fn read_battery(path: &Path, overrides: &QuirkOverrides) -> BatteryReading {
+    let raw = read_raw_battery(path);
+    normalize(path, &raw, &Quirks::detect(&raw, overrides))
+}
 
-    let mut capacity_pct = float_from_uevent(&uevent, &["POWER_SUPPLY_CAPACITY"]);
-    if capacity_pct.is_none() {
-        capacity_pct = read_float(&path.join("capacity"));
-    }
+/// Power flowing out of (or into) the battery in watts; see [`power_w`].
+pub fn read_power_w(path: &Path, overrides: &QuirkOverrides) -> Option<f64> {
+    let raw = read_raw_battery(path);
+    power_w(&raw, &Quirks::detect(&raw, overrides))
+}
 
-    let status = uevent
-        .get("POWER_SUPPLY_STATUS")
-        .cloned()
-        .or_else(|| read_str(&path.join("status")));
-    let voltage = read_voltage(path, &uevent);
+const END_THRESHOLD_FILES: [&str; 2] = ["charge_control_end_threshold", "charge_stop_threshold"];
+const START_THRESHOLD_FILES: [&str; 2] =
+    ["charge_control_start_threshold", "charge_start_threshold"];
 
-    let mut energy_now_wh = wh_from_energy(energy_now_raw);
-    let mut energy_full_wh = wh_from_energy(energy_full_raw);
-    let mut energy_full_design_wh = wh_from_energy(energy_full_design_raw);
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChargeThresholds {
+    pub start_pct: Option<u8>,
+    pub end_pct: Option<u8>,
+}
 
-    if energy_now_wh.is_none() {
-        energy_now_wh = energy_wh_from_charge(charge_now, voltage);
-    }
-    if energy_full_wh.is_none() {
-        energy_full_wh = energy_wh_from_charge(charge_full, voltage);
-    }
-    if energy_full_design_wh.is_none() {
-        energy_full_design_wh = energy_wh_from_charge(charge_full_design, voltage);
-    }
+fn existing_file(path: &Path, names: &[&str]) -> Option<PathBuf> {
+    names.iter().map(|n| path.join(n)).find(|p| p.exists())
+}
 
-    let mut percentage = None;
-    if let (Some(now), Some(full)) = (energy_now_wh, energy_full_wh) {
-        if full != 0.0 {
-            percentage = Some((now / full) * 100.0);
-        }
-    }
+/// Vendor charge thresholds (ThinkPad, ASUS, ...). `None` when the battery exposes none.
+pub fn read_charge_thresholds(path: &Path) -> Option<ChargeThresholds> {
+    let end_file = existing_file(path, &END_THRESHOLD_FILES)?;
+    let read_pct = |file: &Path| read_float(file).map(|v| v.round().clamp(0.0, 100.0) as u8);
+    Some(ChargeThresholds {
+        start_pct: existing_file(path, &START_THRESHOLD_FILES).and_then(|f| read_pct(&f)),
+        end_pct: read_pct(&end_file),
+    })
+}
 
-    let mut health_pct = None;
-    if let (Some(full), Some(design)) = (energy_full_wh, energy_full_design_wh) {
-        if design != 0.0 {
-            health_pct = Some((full / design) * 100.0);
-        }
+/// Writes the stop threshold (and optionally the start threshold). Needs root or a udev
+/// rule granting write access to the sysfs files.
+pub fn write_charge_thresholds(
+    path: &Path,
+    end_pct: u8,
+    start_pct: Option<u8>,
+) -> anyhow::Result<()> {
+    use anyhow::Context;
+
+    let end_file = existing_file(path, &END_THRESHOLD_FILES).with_context(|| {
+        format!(
+            "{} does not expose a charge threshold interface",
+            path.display()
+        )
+    })?;
+    let start_file = match start_pct {
+        Some(_) => Some(
+            existing_file(path, &START_THRESHOLD_FILES).with_context(|| {
+                format!("{} does not support a start threshold", path.display())
+            })?,
+        ),
+        None => None,
+    };
+    // Lowering the end below the current start is rejected by some drivers, so write the
+    // start first when it moves down and last when it moves up.
+    let current_start = start_file.as_deref().and_then(read_float);
+    let start_first =
+        matches!((start_pct, current_start), (Some(new), Some(cur)) if (new as f64) < cur);
+    let write = |file: &Path, value: u8| {
+        fs::write(file, format!("{value}\n"))
+            .with_context(|| format!("Failed to write {} (are you root?)", file.display()))
+    };
+    if let (true, Some(file), Some(value)) = (start_first, start_file.as_deref(), start_pct) {
+        write(file, value)?;
     }
-
-    BatteryReading {
-        path: path.to_path_buf(),
-        capacity_pct,
-        percentage,
-        energy_now_wh,
-        energy_full_wh,
-        energy_full_design_wh,
-        health_pct,
-        status,
+    write(&end_file, end_pct)?;
+    if let (false, Some(file), Some(value)) = (start_first, start_file.as_deref(), start_pct) {
+        write(file, value)?;
     }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -232,7 +267,7 @@ mod tests {
         write(&bat.join("capacity"), "95\n");
         write(&bat.join("status"), "Discharging\n");
 
-        let reading = read_battery(&bat);
+        let reading = read_battery(&bat, &QuirkOverrides::default());
         assert_eq!(reading.energy_now_wh, Some(40.0));
         assert_eq!(reading.energy_full_wh, Some(80.0));
         assert_eq!(reading.energy_full_design_wh, Some(90.0));
@@ -255,7 +290,7 @@ mod tests {
         write(&bat.join("capacity"), "90\n");
         write(&bat.join("status"), "Charging\n");
 
-        let reading = read_battery(&bat);
+        let reading = read_battery(&bat, &QuirkOverrides::default());
         assert_eq!(reading.energy_now_wh, Some(22.0));
         assert_eq!(reading.energy_full_wh, Some(44.0));
         assert_eq!(reading.energy_full_design_wh, Some(49.5));
@@ -263,6 +298,43 @@ mod tests {
         assert!((reading.health_pct.unwrap() - (44.0 / 49.5 * 100.0)).abs() < 1e-6);
         assert_eq!(reading.capacity_pct, Some(90.0));
         assert_eq!(reading.status.as_deref(), Some("Charging"));
+
+        assert_eq!(read_power_w(&bat, &QuirkOverrides::default()), None);
+        write(&bat.join("current_now"), "-1000000\n");
+        write(&bat.join("voltage_now"), "12000000\n");
+        assert!((read_power_w(&bat, &QuirkOverrides::default()).unwrap() - 12.0).abs() < 1e-9);
+        write(&bat.join("power_now"), "7500000\n");
+        assert_eq!(read_power_w(&bat, &QuirkOverrides::default()), Some(7.5));
+    }
+
+    #[test]
+    fn charge_thresholds_roundtrip() {
+        let tmp = tempfile::tempdir().unwrap();
+        let bat = tmp.path().join("BAT0");
+        fs::create_dir(&bat).unwrap();
+        assert!(read_charge_thresholds(&bat).is_none());
+        assert!(write_charge_thresholds(&bat, 80, None).is_err());
+
+        write(&bat.join("charge_control_end_threshold"), "100\n");
+        write(&bat.join("charge_control_start_threshold"), "95\n");
+        write_charge_thresholds(&bat, 80, Some(75)).unwrap();
+
+        let thresholds = read_charge_thresholds(&bat).unwrap();
+        assert_eq!(thresholds.end_pct, Some(80));
+        assert_eq!(thresholds.start_pct, Some(75));
+    }
+
+    #[test]
+    fn charge_thresholds_accept_legacy_names() {
+        let tmp = tempfile::tempdir().unwrap();
+        let bat = tmp.path().join("BAT1");
+        fs::create_dir(&bat).unwrap();
+        write(&bat.join("charge_stop_threshold"), "90\n");
+
+        let thresholds = read_charge_thresholds(&bat).unwrap();
+        assert_eq!(thresholds.end_pct, Some(90));
+        assert_eq!(thresholds.start_pct, None);
+        assert!(write_charge_thresholds(&bat, 80, Some(70)).is_err());
     }
 
     #[test]
@@ -280,7 +352,7 @@ mod tests {
         .join("\n");
         write(&bat.join("uevent"), &content);
 
-        let reading = read_battery(&bat);
+        let reading = read_battery(&bat, &QuirkOverrides::default());
         assert_eq!(reading.energy_now_wh, Some(30.0));
         assert_eq!(reading.energy_full_wh, Some(60.0));
         assert_eq!(reading.energy_full_design_wh, Some(80.0));
@@ -289,4 +361,29 @@ mod tests {
         assert_eq!(reading.capacity_pct, Some(85.0));
         assert_eq!(reading.status.as_deref(), Some("Discharging"));
     }
+
+    #[test]
+    fn read_battery_info_reads_identity_and_design_voltage() {
+        let tmp = tempfile::tempdir().unwrap();
+        let bat = tmp.path().join("BAT0");
+        fs::create_dir(&bat).unwrap();
+        let content = [
+            "POWER_SUPPLY_MANUFACTURER=SMP",
+            "POWER_SUPPLY_MODEL_NAME=5B10W13975",
+            "POWER_SUPPLY_TECHNOLOGY=Li-poly",
+            "POWER_SUPPLY_CHARGE_FULL_DESIGN=4000000",
+            "POWER_SUPPLY_VOLTAGE_MIN_DESIGN=15000000",
+            "POWER_SUPPLY_VOLTAGE_NOW=16800000",
+        ]
+        .join("\n");
+        write(&bat.join("uevent"), &content);
+        write(&bat.join("serial_number"), " 1234\n");
+
+        let info = read_battery_info(&bat, &QuirkOverrides::default());
+        assert_eq!(info.manufacturer.as_deref(), Some("SMP"));
+        assert_eq!(info.model_name.as_deref(), Some("5B10W13975"));
+        assert_eq!(info.serial_number.as_deref(), Some("1234"));
+        assert_eq!(info.technology.as_deref(), Some("Li-poly"));
+        assert_eq!(info.energy_full_design_wh, Some(60.0));
+    }
 }
@@ -0,0 +1,284 @@
+//! Phone batteries under Termux on Android, where `/sys/class/power_supply` is usually
+//! not readable by apps: `termux-battery-status` (from the Termux:API add-on), else
+//! `dumpsys battery` where the shell is allowed to run it. The charge becomes a battery
+//! sample; temperature and the current drawn become metric samples of the `termux`
+//! collector.
+
+use serde_json::{json, Value};
+
+use crate::metrics::{MetricKind, MetricSample};
+use crate::sysfs::BatteryReading;
+
+pub const TERMUX_COLLECTOR: &str = "termux";
+/// `source_path` of the phone battery.
+pub const TERMUX_BATTERY: &str = "termux/battery";
+/// Temperature and power source name.
+pub const BATTERY_SOURCE: &str = "Battery";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AndroidBattery {
+    pub percentage: Option<f64>,
+    /// In the sysfs vocabulary (`Charging`, `Discharging`, `Full`, `Not charging`).
+    pub status: Option<String>,
+    pub temperature_c: Option<f64>,
+    /// Positive while charging, negative while discharging.
+    pub current_a: Option<f64>,
+    pub voltage_v: Option<f64>,
+    /// Charge left, in Ah.
+    pub charge_counter_ah: Option<f64>,
+    /// `good`, `overheat`, `cold`, ...
+    pub health: Option<String>,
+    /// `ac`, `usb`, `wireless` or `unplugged`.
+    pub plugged: Option<String>,
+}
+
+fn sysfs_status(raw: &str) -> Option<String> {
+    let status = match raw.to_ascii_lowercase().as_str() {
+        "charging" | "2" => "Charging",
+        "discharging" | "3" => "Discharging",
+        "not_charging" | "4" => "Not charging",
+        "full" | "5" => "Full",
+        _ => return None,
+    };
+    Some(status.to_string())
+}
+
+/// `termux-battery-status` JSON. `current` is in µA; `voltage` (mV) and `charge_counter`
+/// (µAh) only come from newer Termux:API releases.
+pub fn parse_termux_status(output: &str) -> Option<AndroidBattery> {
+    let value: Value = serde_json::from_str(output).ok()?;
+    let number = |key: &str| value.get(key).and_then(Value::as_f64);
+    let text = |key: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::to_ascii_lowercase)
+    };
+    Some(AndroidBattery {
+        percentage: Some(number("percentage")?),
+        status: value.get("status")?.as_str().and_then(sysfs_status),
+        temperature_c: number("temperature"),
+        current_a: number("current").map(|ua| ua / 1e6),
+        voltage_v: number("voltage").map(|mv| mv / 1000.0),
+        charge_counter_ah: number("charge_counter").map(|uah| uah / 1e6),
+        health: text("health"),
+        plugged: text("plugged").map(|plugged| plugged.trim_start_matches("plugged_").to_string()),
+    })
+}
+
+/// `dumpsys battery` output: `level`/`scale`, numeric `status` and `health`, temperature
+/// in tenths of °C, voltage in mV and `Charge counter` in µAh. It has no current.
+pub fn parse_dumpsys_battery(output: &str) -> Option<AndroidBattery> {
+    let field = |key: &str| {
+        output.lines().find_map(|line| {
+            let (name, value) = line.trim().split_once(':')?;
+            (name.trim() == key).then(|| value.trim())
+        })
+    };
+    let number = |key: &str| field(key).and_then(|value| value.parse::<f64>().ok());
+    let level = number("level")?;
+    let scale = number("scale")
+        .filter(|scale| *scale > 0.0)
+        .unwrap_or(100.0);
+    let plugged = ["AC", "USB", "Wireless"]
+        .into_iter()
+        .find(|source| field(&format!("{source} powered")) == Some("true"))
+        .map_or("unplugged".to_string(), str::to_ascii_lowercase);
+    let health = field("health").and_then(|code| {
+        let name = match code {
+            "2" => "good",
+            "3" => "overheat",
+            "4" => "dead",
+            "5" => "over_voltage",
+            "6" => "unspecified_failure",
+            "7" => "cold",
+            _ => return None,
+        };
+        Some(name.to_string())
+    });
+    Some(AndroidBattery {
+        percentage: Some(level / scale * 100.0),
+        status: field("status").and_then(sysfs_status),
+        temperature_c: number("temperature").map(|tenths| tenths / 10.0),
+        current_a: None,
+        voltage_v: number("voltage").map(|mv| mv / 1000.0),
+        charge_counter_ah: number("Charge counter").map(|uah| uah / 1e6),
+        health,
+        plugged: Some(plugged),
+    })
+}
+
+impl AndroidBattery {
+    /// The battery sample. Energy left is the charge counter at the present voltage; the
+    /// full and design capacities are not reported.
+    pub fn reading(&self) -> BatteryReading {
+        BatteryReading {
+            path: TERMUX_BATTERY.into(),
+            capacity_pct: self.percentage,
+            percentage: self.percentage,
+            energy_now_wh: self
+                .charge_counter_ah
+                .zip(self.voltage_v)
+                .map(|(ah, v)| ah * v),
+            energy_full_wh: None,
+            energy_full_design_wh: None,
+            health_pct: None,
+            status: self.status.clone(),
+        }
+    }
+
+    /// Battery temperature, and the power drawn from it (current × voltage, negative while
+    /// charging) with the raw current, voltage and charge counter.
+    pub fn metric_samples(&self, ts: f64) -> Vec<MetricSample> {
+        let mut samples = Vec::new();
+        if let Some(celsius) = self.temperature_c {
+            samples.push(MetricSample::new(
+                ts,
+                MetricKind::Temperature,
+                BATTERY_SOURCE,
+                Some(celsius),
+                Some("°C"),
+                json!({ "health": self.health }),
+            ));
+        }
+        if let (Some(current), Some(voltage)) = (self.current_a, self.voltage_v) {
+            samples.push(MetricSample::new(
+                ts,
+                MetricKind::PowerDraw,
+                BATTERY_SOURCE,
+                Some(-current * voltage),
+                Some("W"),
+                json!({
+                    "current_a": current,
+                    "voltage_v": voltage,
+                    "charge_counter_ah": self.charge_counter_ah,
+                    "plugged": self.plugged,
+                }),
+            ));
+        }
+        samples
+    }
+}
+
+#[cfg(target_os = "android")]
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    use std::process::{Command, Stdio};
+
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// The phone battery, read once per collection timestamp: the battery and `termux`
+/// collectors share it, and `termux-battery-status` takes a while to answer.
+#[cfg(target_os = "android")]
+pub fn read_battery(ts: f64) -> Option<AndroidBattery> {
+    use std::sync::Mutex;
+
+    static LAST: Mutex<Option<(f64, Option<AndroidBattery>)>> = Mutex::new(None);
+    let mut last = LAST.lock().ok()?;
+    if let Some((read_ts, battery)) = last.as_ref() {
+        if *read_ts == ts {
+            return battery.clone();
+        }
+    }
+    let battery = run("termux-battery-status", &[])
+        .and_then(|output| parse_termux_status(&output))
+        .or_else(|| parse_dumpsys_battery(&run("dumpsys", &["battery"])?));
+    *last = Some((ts, battery.clone()));
+    battery
+}
+
+/// Temperature and power of the phone battery.
+#[cfg(target_os = "android")]
+pub struct TermuxCollector;
+
+#[cfg(target_os = "android")]
+impl crate::collector::Collector for TermuxCollector {
+    fn name(&self) -> &str {
+        TERMUX_COLLECTOR
+    }
+
+    fn kinds(&self) -> &[MetricKind] {
+        &[MetricKind::Temperature, MetricKind::PowerDraw]
+    }
+
+    fn collect(&self, ts: f64) -> Vec<MetricSample> {
+        read_battery(ts)
+            .map(|battery| battery.metric_samples(ts))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TERMUX: &str = r#"{
+        "health": "GOOD",
+        "percentage": 85,
+        "plugged": "UNPLUGGED",
+        "status": "DISCHARGING",
+        "temperature": 29.5,
+        "current": -500000,
+        "voltage": 4000,
+        "charge_counter": 3000000
+    }"#;
+
+    const DUMPSYS: &str =
+        "Current Battery Service state:\n  AC powered: false\n  USB powered: true\n  \
+         Wireless powered: false\n  Charge counter: 2500000\n  status: 2\n  \
+         health: 2\n  present: true\n  level: 170\n  scale: 200\n  \
+         voltage: 4200\n  temperature: 312\n  technology: Li-ion\n";
+
+    #[test]
+    fn termux_battery_status_becomes_a_reading_and_metrics() {
+        let battery = parse_termux_status(TERMUX).unwrap();
+        assert_eq!(battery.status.as_deref(), Some("Discharging"));
+        assert_eq!(battery.plugged.as_deref(), Some("unplugged"));
+        let reading = battery.reading();
+        assert_eq!(reading.path, std::path::PathBuf::from(TERMUX_BATTERY));
+        assert_eq!(reading.percentage, Some(85.0));
+        assert_eq!(reading.energy_now_wh, Some(12.0));
+        let samples = battery.metric_samples(1.0);
+        assert_eq!(samples[0].kind, MetricKind::Temperature);
+        assert_eq!(samples[0].value, Some(29.5));
+        assert_eq!(samples[1].kind, MetricKind::PowerDraw);
+        assert_eq!(samples[1].value, Some(2.0));
+    }
+
+    #[test]
+    fn dumpsys_battery_is_scaled_and_decoded() {
+        let battery = parse_dumpsys_battery(DUMPSYS).unwrap();
+        assert_eq!(battery.percentage, Some(85.0));
+        assert_eq!(battery.status.as_deref(), Some("Charging"));
+        assert_eq!(battery.health.as_deref(), Some("good"));
+        assert_eq!(battery.plugged.as_deref(), Some("usb"));
+        assert_eq!(battery.temperature_c, Some(31.2));
+        assert!((battery.reading().energy_now_wh.unwrap() - 10.5).abs() < 1e-9);
+        // Without a current there is no power sample.
+        assert_eq!(battery.metric_samples(1.0).len(), 1);
+    }
+
+    #[test]
+    fn truncated_or_invalid_termux_json_is_rejected() {
+        assert!(parse_termux_status("").is_none());
+        assert!(parse_termux_status("{}").is_none());
+        assert!(parse_termux_status(&TERMUX[..TERMUX.len() / 2]).is_none());
+        assert!(parse_termux_status("termux-battery-status: command not found").is_none());
+        assert!(parse_termux_status(r#"{"percentage": "85", "status": "FULL"}"#).is_none());
+    }
+
+    #[test]
+    fn dumpsys_without_a_level_is_rejected() {
+        assert!(parse_dumpsys_battery("").is_none());
+        assert!(parse_dumpsys_battery("Can't find service: battery\n").is_none());
+    }
+}
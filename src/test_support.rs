@@ -0,0 +1,32 @@
+//! Builders shared by the unit tests.
+
+use serde_json::Value;
+
+use crate::cli_helpers::DischargeSegment;
+use crate::db::Sample;
+use crate::metrics::{MetricKind, MetricSample};
+
+/// A reading without a unit; `details` is `Value::Null` when the test needs none.
+pub fn metric(ts: f64, kind: MetricKind, source: &str, value: f64, details: Value) -> MetricSample {
+    MetricSample::new(ts, kind, source, Some(value), None, details)
+}
+
+/// Discharge at `watts` for `hours`, ending at `ts`.
+pub fn segment(ts: f64, watts: f64, hours: f64) -> DischargeSegment {
+    DischargeSegment { ts, watts, hours }
+}
+
+/// A `BAT0` sample with only the percentage and status set.
+pub fn battery(ts: f64, percentage: f64, status: &str) -> Sample {
+    Sample {
+        ts,
+        percentage: Some(percentage),
+        capacity_pct: None,
+        health_pct: None,
+        energy_now_wh: None,
+        energy_full_wh: None,
+        energy_full_design_wh: None,
+        status: Some(status.to_string()),
+        source_path: "/sys/class/power_supply/BAT0".to_string(),
+    }
+}
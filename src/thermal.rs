@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+
+use ordered_float::OrderedFloat;
+
+use crate::cli_helpers::percentile;
+use crate::metrics::{MetricKind, MetricSample};
+
+pub const DEFAULT_MIN_TEMP_C: f64 = 85.0;
+pub const DEFAULT_FREQ_DROP_PCT: f64 = 20.0;
+/// Collections further apart than this end an episode (suspend, collector stopped).
+const MAX_GAP_SECONDS: f64 = 600.0;
+/// The frequency the CPU reaches when nothing holds it back, taken from the window itself.
+const REFERENCE_PERCENTILE: f64 = 0.95;
+
+/// When a collection counts as throttled: hot, and clocked well below the usual speed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThrottleCriteria {
+    pub min_temp_c: f64,
+    /// How far (in percent) the average core frequency must sit below the reference.
+    pub freq_drop_pct: f64,
+}
+
+impl Default for ThrottleCriteria {
+    fn default() -> Self {
+        ThrottleCriteria {
+            min_temp_c: DEFAULT_MIN_TEMP_C,
+            freq_drop_pct: DEFAULT_FREQ_DROP_PCT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThrottleEpisode {
+    pub start_ts: f64,
+    /// First collection after the episode, or the last throttled one when none followed.
+    pub end_ts: f64,
+    pub peak_temp_c: f64,
+    pub freq_floor_mhz: f64,
+    pub reference_mhz: f64,
+}
+
+impl ThrottleEpisode {
+    pub fn duration_seconds(&self) -> f64 {
+        self.end_ts - self.start_ts
+    }
+
+    pub fn drop_pct(&self) -> f64 {
+        (1.0 - self.freq_floor_mhz / self.reference_mhz) * 100.0
+    }
+}
+
+/// Hottest sensor and average core frequency of one collection.
+#[derive(Debug, Clone, Copy)]
+struct Reading {
+    ts: f64,
+    temp_c: f64,
+    freq_mhz: f64,
+}
+
+fn readings(metrics: &[MetricSample]) -> Vec<Reading> {
+    let mut temps: BTreeMap<OrderedFloat<f64>, f64> = BTreeMap::new();
+    let mut freqs: BTreeMap<OrderedFloat<f64>, (f64, usize)> = BTreeMap::new();
+    for sample in metrics {
+        let Some(value) = sample.value else {
+            continue;
+        };
+        match sample.kind {
+            MetricKind::Temperature => {
+                let hottest = temps.entry(OrderedFloat(sample.ts)).or_insert(value);
+                *hottest = hottest.max(value);
+            }
+            MetricKind::CpuFrequency => {
+                let (sum, count) = freqs.entry(OrderedFloat(sample.ts)).or_default();
+                *sum += value;
+                *count += 1;
+            }
+            _ => {}
+        }
+    }
+    temps
+        .into_iter()
+        .filter_map(|(ts, temp_c)| {
+            let (sum, count) = freqs.get(&ts)?;
+            Some(Reading {
+                ts: ts.0,
+                temp_c,
+                freq_mhz: sum / *count as f64,
+            })
+        })
+        .collect()
+}
+
+/// Episodes where the CPU ran hot while its average frequency sat `freq_drop_pct` below the
+/// 95th percentile seen in `metrics`. Needs both temperature and CPU frequency samples.
+pub fn detect_throttling(
+    metrics: &[MetricSample],
+    criteria: &ThrottleCriteria,
+) -> Vec<ThrottleEpisode> {
+    let readings = readings(metrics);
+    let mut sorted: Vec<f64> = readings.iter().map(|r| r.freq_mhz).collect();
+    sorted.sort_by(f64::total_cmp);
+    let Some(reference_mhz) = percentile(&sorted, REFERENCE_PERCENTILE).filter(|f| *f > 0.0) else {
+        return Vec::new();
+    };
+    let ceiling = reference_mhz * (1.0 - criteria.freq_drop_pct / 100.0);
+
+    let mut episodes: Vec<ThrottleEpisode> = Vec::new();
+    let mut current: Option<ThrottleEpisode> = None;
+    let mut previous_ts: Option<f64> = None;
+    for reading in &readings {
+        let contiguous = previous_ts.is_some_and(|ts| reading.ts - ts <= MAX_GAP_SECONDS);
+        previous_ts = Some(reading.ts);
+        let throttled = reading.temp_c >= criteria.min_temp_c && reading.freq_mhz <= ceiling;
+        match (current.as_mut(), throttled && contiguous) {
+            (Some(episode), true) => {
+                episode.end_ts = reading.ts;
+                episode.peak_temp_c = episode.peak_temp_c.max(reading.temp_c);
+                episode.freq_floor_mhz = episode.freq_floor_mhz.min(reading.freq_mhz);
+                continue;
+            }
+            (Some(episode), false) if contiguous => episode.end_ts = reading.ts,
+            _ => {}
+        }
+        episodes.extend(current.take());
+        if throttled {
+            current = Some(ThrottleEpisode {
+                start_ts: reading.ts,
+                end_ts: reading.ts,
+                peak_temp_c: reading.temp_c,
+                freq_floor_mhz: reading.freq_mhz,
+                reference_mhz,
+            });
+        }
+    }
+    episodes.extend(current);
+    episodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(kind: MetricKind, source: &str, ts: f64, value: f64) -> MetricSample {
+        MetricSample::new(ts, kind, source, Some(value), None, serde_json::Value::Null)
+    }
+
+    fn cycle(ts: f64, temp_c: f64, freq_mhz: f64) -> Vec<MetricSample> {
+        vec![
+            sample(MetricKind::Temperature, "x86_pkg_temp", ts, temp_c),
+            sample(MetricKind::Temperature, "acpitz", ts, 40.0),
+            sample(MetricKind::CpuFrequency, "cpu0", ts, freq_mhz + 100.0),
+            sample(MetricKind::CpuFrequency, "cpu1", ts, freq_mhz - 100.0),
+        ]
+    }
+
+    #[test]
+    fn hot_and_slow_cycles_form_episodes() {
+        let mut metrics = Vec::new();
+        for (i, (temp, freq)) in [
+            (60.0, 3000.0),
+            (70.0, 3000.0),
+            (92.0, 1800.0),
+            (95.0, 1500.0),
+            (80.0, 2900.0),
+            (90.0, 3000.0),
+            (88.0, 2000.0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            metrics.extend(cycle(i as f64 * 60.0, temp, freq));
+        }
+        // Hot but slow after a long gap starts a fresh episode.
+        metrics.extend(cycle(3600.0, 91.0, 1900.0));
+
+        let episodes = detect_throttling(&metrics, &ThrottleCriteria::default());
+        assert_eq!(episodes.len(), 3);
+        assert_eq!(episodes[0].start_ts, 120.0);
+        assert_eq!(episodes[0].end_ts, 240.0);
+        assert_eq!(episodes[0].peak_temp_c, 95.0);
+        assert_eq!(episodes[0].freq_floor_mhz, 1500.0);
+        assert_eq!(episodes[0].reference_mhz, 3000.0);
+        assert_eq!(episodes[0].drop_pct(), 50.0);
+        assert_eq!(episodes[1].duration_seconds(), 0.0);
+        assert_eq!(episodes[2].start_ts, 3600.0);
+
+        let temps_only: Vec<MetricSample> = metrics
+            .into_iter()
+            .filter(|m| m.kind == MetricKind::Temperature)
+            .collect();
+        assert!(detect_throttling(&temps_only, &ThrottleCriteria::default()).is_empty());
+    }
+}
@@ -1,9 +1,100 @@
 use std::time::SystemTime;
 
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, TimeZone, Utc};
+
 const SECONDS_PER_HOUR: u64 = 3600;
 const SECONDS_PER_DAY: u64 = 24 * SECONDS_PER_HOUR;
 const APPROX_DAYS_PER_MONTH: u64 = 30;
 
+/// A window snapped to local-calendar boundaries instead of ending now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalendarPeriod {
+    /// Since local midnight.
+    Today,
+    /// The previous local day, midnight to midnight.
+    Yesterday,
+    /// Since Monday 00:00.
+    ThisWeek,
+    /// Since the 1st of the month, 00:00.
+    ThisMonth,
+}
+
+impl CalendarPeriod {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CalendarPeriod::Today => "today",
+            CalendarPeriod::Yesterday => "yesterday",
+            CalendarPeriod::ThisWeek => "this_week",
+            CalendarPeriod::ThisMonth => "this_month",
+        }
+    }
+
+    /// First day of the period containing `today`, of the one before it, and of the one
+    /// after it.
+    fn days(&self, today: NaiveDate) -> (NaiveDate, NaiveDate, NaiveDate) {
+        let day = Days::new(1);
+        match self {
+            CalendarPeriod::Today => (today - day, today, today + day),
+            CalendarPeriod::Yesterday => (today - Days::new(2), today - day, today),
+            CalendarPeriod::ThisWeek => {
+                let monday = today - Days::new(today.weekday().num_days_from_monday().into());
+                (monday - Days::new(7), monday, monday + Days::new(7))
+            }
+            CalendarPeriod::ThisMonth => {
+                let first = today.with_day(1).unwrap_or(today);
+                (first - Months::new(1), first, first + Months::new(1))
+            }
+        }
+    }
+}
+
+/// Unix bounds of a [`CalendarPeriod`], fixed when the timeframe is built.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CalendarWindow {
+    pub period: CalendarPeriod,
+    pub start: f64,
+    /// End of a period that is already over (`yesterday`); `None` runs up to now.
+    pub end: Option<f64>,
+    /// Start of the period before, which ends at `start`.
+    pub previous_start: f64,
+}
+
+/// Unix time of 00:00 local time on `day`; on days where DST skips midnight, the first
+/// instant of the day that exists.
+fn local_midnight<Tz: TimeZone>(tz: &Tz, day: NaiveDate) -> f64 {
+    let midnight = day.and_hms_opt(0, 0, 0).unwrap_or_default();
+    let resolved = tz.from_local_datetime(&midnight).earliest().or_else(|| {
+        (1..=3)
+            .filter_map(|hour| day.and_hms_opt(hour, 0, 0))
+            .find_map(|later| tz.from_local_datetime(&later).earliest())
+    });
+    resolved
+        .map(|dt| dt.timestamp() as f64)
+        .unwrap_or_else(|| midnight.and_utc().timestamp() as f64)
+}
+
+fn calendar_timeframe<Tz: TimeZone>(period: CalendarPeriod, now: &DateTime<Tz>) -> Timeframe {
+    let tz = now.timezone();
+    let (previous, start, next) = period.days(now.date_naive());
+    let start_ts = local_midnight(&tz, start);
+    let next_ts = local_midnight(&tz, next);
+    let end = (period == CalendarPeriod::Yesterday).then_some(next_ts);
+    Timeframe {
+        label: period.label().to_string(),
+        // The whole period, so bucket sizes match a finished one.
+        seconds: Some(next_ts - start_ts),
+        hours: 0,
+        days: 0,
+        months: 0,
+        calendar: Some(CalendarWindow {
+            period,
+            start: start_ts,
+            end,
+            previous_start: local_midnight(&tz, previous),
+        }),
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Timeframe {
     pub label: String,
@@ -11,10 +102,14 @@ pub struct Timeframe {
     pub hours: u64,
     pub days: u64,
     pub months: u64,
+    pub calendar: Option<CalendarWindow>,
 }
 
 impl Timeframe {
     pub fn since_timestamp(&self, now: Option<SystemTime>) -> Option<f64> {
+        if let Some(calendar) = &self.calendar {
+            return Some(calendar.start);
+        }
         let seconds = self.seconds?;
         let reference = now.unwrap_or_else(SystemTime::now);
         let reference_secs = reference
@@ -23,6 +118,28 @@ impl Timeframe {
             .as_secs_f64();
         Some(reference_secs - seconds)
     }
+
+    /// End of the window for periods that are already over; `None` means up to now.
+    pub fn until_timestamp(&self) -> Option<f64> {
+        self.calendar.and_then(|calendar| calendar.end)
+    }
+
+    /// Whether `ts` falls before [`Timeframe::until_timestamp`]; filters rows fetched with
+    /// only a lower bound.
+    pub fn is_before_end(&self, ts: f64) -> bool {
+        self.until_timestamp().is_none_or(|end| ts < end)
+    }
+
+    /// Start/end of the window of equal length immediately preceding this one. For
+    /// calendar periods, the whole previous day, week or month.
+    pub fn previous_window(&self, now: Option<SystemTime>) -> Option<(f64, f64)> {
+        if let Some(calendar) = &self.calendar {
+            return Some((calendar.previous_start, calendar.start));
+        }
+        let seconds = self.seconds?;
+        let end = self.since_timestamp(now)?;
+        Some((end - seconds, end))
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -48,11 +165,14 @@ fn plural_suffix(value: u64, singular: &'static str, plural: &'static str) -> &'
     }
 }
 
+/// `calendar` takes precedence over `hours`/`days`/`months` and snaps the window to the
+/// calendar of the display zone ([`crate::locale::current`]); `all_time` over everything.
 pub fn build_timeframe(
     hours: i64,
     days: i64,
     months: i64,
     all_time: bool,
+    calendar: Option<CalendarPeriod>,
 ) -> Result<Timeframe, TimeframeError> {
     validate_non_negative(hours, "hours")?;
     validate_non_negative(days, "days")?;
@@ -65,9 +185,15 @@ pub fn build_timeframe(
             hours: 0,
             days: 0,
             months: 0,
+            calendar: None,
         });
     }
 
+    if let Some(period) = calendar {
+        let now = Utc::now().with_timezone(&crate::locale::current().zone);
+        return Ok(calendar_timeframe(period, &now));
+    }
+
     if months > 0 {
         let months_u = months as u64;
         let seconds = months_u * APPROX_DAYS_PER_MONTH * SECONDS_PER_DAY;
@@ -82,6 +208,7 @@ pub fn build_timeframe(
             hours: 0,
             days: 0,
             months: months_u,
+            calendar: None,
         });
     }
 
@@ -95,6 +222,7 @@ pub fn build_timeframe(
             hours: 0,
             days: days_u,
             months: 0,
+            calendar: None,
         });
     }
 
@@ -115,6 +243,7 @@ pub fn build_timeframe(
         hours: hours_u,
         days: 0,
         months: 0,
+        calendar: None,
     })
 }
 
@@ -133,7 +262,7 @@ mod tests {
 
     #[test]
     fn default_timeframe_is_last_six_hours() {
-        let timeframe = build_timeframe(6, 0, 0, false).unwrap();
+        let timeframe = build_timeframe(6, 0, 0, false, None).unwrap();
         assert_eq!(timeframe.hours, 6);
         assert_eq!(timeframe.seconds, Some(6.0 * 3600.0));
         assert_eq!(timeframe.label, "last_6_hours");
@@ -141,8 +270,8 @@ mod tests {
 
     #[test]
     fn days_and_months_take_precedence_over_hours() {
-        let timeframe_days = build_timeframe(2, 1, 0, false).unwrap();
-        let timeframe_months = build_timeframe(2, 2, 1, false).unwrap();
+        let timeframe_days = build_timeframe(2, 1, 0, false, None).unwrap();
+        let timeframe_months = build_timeframe(2, 2, 1, false, None).unwrap();
 
         assert_eq!(timeframe_days.days, 1);
         assert_eq!(timeframe_days.seconds, Some(24.0 * 3600.0));
@@ -156,26 +285,78 @@ mod tests {
     #[test]
     fn since_timestamp_uses_reference() {
         let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
-        let timeframe = build_timeframe(1, 0, 0, false).unwrap();
+        let timeframe = build_timeframe(1, 0, 0, false, None).unwrap();
         let since = since_timestamp(&timeframe, Some(now)).unwrap();
         assert_eq!(since, 1_700_000_000.0 - 3600.0);
     }
 
     #[test]
     fn since_timestamp_allows_unbounded() {
-        let timeframe = build_timeframe(6, 0, 0, true).unwrap();
+        let timeframe = build_timeframe(6, 0, 0, true, None).unwrap();
         assert!(since_timestamp(&timeframe, None).is_none());
         assert_eq!(timeframe.label, "all");
     }
 
+    #[test]
+    fn previous_window_precedes_current() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let timeframe = build_timeframe(0, 1, 0, false, None).unwrap();
+        let (start, end) = timeframe.previous_window(Some(now)).unwrap();
+        assert_eq!(end, 1_700_000_000.0 - 86_400.0);
+        assert_eq!(start, 1_700_000_000.0 - 2.0 * 86_400.0);
+
+        let all = build_timeframe(6, 0, 0, true, None).unwrap();
+        assert!(all.previous_window(Some(now)).is_none());
+    }
+
+    #[test]
+    fn calendar_periods_snap_to_local_midnight() {
+        use chrono::FixedOffset;
+
+        // Thursday 2024-02-15 10:30 at UTC+2; local midnight is 22:00 UTC the day before.
+        let tz = FixedOffset::east_opt(2 * 3600).unwrap();
+        let now = tz.with_ymd_and_hms(2024, 2, 15, 10, 30, 0).unwrap();
+        let midnight = |y, m, d| tz.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap().timestamp() as f64;
+
+        let today = calendar_timeframe(CalendarPeriod::Today, &now);
+        assert_eq!(today.label, "today");
+        assert_eq!(today.since_timestamp(None), Some(midnight(2024, 2, 15)));
+        assert_eq!(today.until_timestamp(), None);
+        assert_eq!(today.seconds, Some(86_400.0));
+        assert_eq!(
+            today.previous_window(None),
+            Some((midnight(2024, 2, 14), midnight(2024, 2, 15)))
+        );
+
+        let yesterday = calendar_timeframe(CalendarPeriod::Yesterday, &now);
+        assert_eq!(yesterday.since_timestamp(None), Some(midnight(2024, 2, 14)));
+        assert_eq!(yesterday.until_timestamp(), Some(midnight(2024, 2, 15)));
+        assert!(yesterday.is_before_end(midnight(2024, 2, 15) - 1.0));
+        assert!(!yesterday.is_before_end(midnight(2024, 2, 15)));
+
+        let week = calendar_timeframe(CalendarPeriod::ThisWeek, &now);
+        assert_eq!(week.since_timestamp(None), Some(midnight(2024, 2, 12)));
+        assert_eq!(week.previous_window(None).unwrap().0, midnight(2024, 2, 5));
+
+        let month = calendar_timeframe(CalendarPeriod::ThisMonth, &now);
+        assert_eq!(month.since_timestamp(None), Some(midnight(2024, 2, 1)));
+        assert_eq!(month.seconds, Some(29.0 * 86_400.0));
+        assert_eq!(month.previous_window(None).unwrap().0, midnight(2024, 1, 1));
+
+        let built = build_timeframe(6, 0, 0, false, Some(CalendarPeriod::ThisWeek)).unwrap();
+        assert_eq!(built.label, "this_week");
+        let all = build_timeframe(6, 0, 0, true, Some(CalendarPeriod::Today)).unwrap();
+        assert!(all.calendar.is_none());
+    }
+
     #[test]
     fn invalid_inputs_raise() {
         assert!(matches!(
-            build_timeframe(0, 0, 0, false),
+            build_timeframe(0, 0, 0, false, None),
             Err(TimeframeError::InvalidHours)
         ));
         assert!(matches!(
-            build_timeframe(1, -1, 0, false),
+            build_timeframe(1, -1, 0, false, None),
             Err(TimeframeError::NegativeValue(_))
         ));
     }
@@ -0,0 +1,156 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+/// A kernel power setting with its current value and the one powertop would pick.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tunable {
+    pub name: String,
+    pub path: PathBuf,
+    pub current: String,
+    pub recommended: &'static str,
+}
+
+impl Tunable {
+    pub fn is_tuned(&self) -> bool {
+        self.current == self.recommended
+    }
+}
+
+/// The active entry of a `default performance [powersave]` choice list, or the whole
+/// trimmed value for plain files.
+fn read_setting(path: &Path) -> Option<String> {
+    let raw = fs::read_to_string(path).ok()?;
+    let raw = raw.trim();
+    let active = raw
+        .split_whitespace()
+        .find(|choice| choice.starts_with('[') && choice.ends_with(']'))
+        .map(|choice| choice.trim_matches(|c| c == '[' || c == ']'))
+        .unwrap_or(raw);
+    Some(active.to_string())
+}
+
+fn sorted_dirs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    dirs.sort();
+    dirs
+}
+
+/// Every known tunable present under `sys_root` (usually `/sys`) and `proc_root`
+/// (usually `/proc`): USB autosuspend per device, SATA link power per host, the PCIe ASPM
+/// policy and the NMI watchdog.
+pub fn audit_tunables_from(sys_root: &Path, proc_root: &Path) -> Vec<Tunable> {
+    let mut tunables = Vec::new();
+    let mut push = |name: String, path: PathBuf, recommended: &'static str| {
+        if let Some(current) = read_setting(&path) {
+            tunables.push(Tunable {
+                name,
+                path,
+                current,
+                recommended,
+            });
+        }
+    };
+
+    for device in sorted_dirs(&sys_root.join("bus/usb/devices")) {
+        let port = device.file_name().unwrap_or_default().to_string_lossy();
+        // Interfaces (`1-2:1.0`) have no power/control of their own.
+        if port.contains(':') {
+            continue;
+        }
+        let product = fs::read_to_string(device.join("product"))
+            .map(|product| format!(" ({})", product.trim()))
+            .unwrap_or_default();
+        let name = format!("USB autosuspend {port}{product}");
+        push(name, device.join("power/control"), "auto");
+    }
+    for host in sorted_dirs(&sys_root.join("class/scsi_host")) {
+        let name = format!(
+            "SATA link power {}",
+            host.file_name().unwrap_or_default().to_string_lossy()
+        );
+        push(
+            name,
+            host.join("link_power_management_policy"),
+            "med_power_with_dipm",
+        );
+    }
+    push(
+        "PCIe ASPM policy".to_string(),
+        sys_root.join("module/pcie_aspm/parameters/policy"),
+        "powersave",
+    );
+    push(
+        "NMI watchdog".to_string(),
+        proc_root.join("sys/kernel/nmi_watchdog"),
+        "0",
+    );
+    tunables
+}
+
+pub fn audit_tunables() -> Vec<Tunable> {
+    audit_tunables_from(Path::new("/sys"), Path::new("/proc"))
+}
+
+/// Writes the recommended value; needs root for the real files.
+pub fn apply_tunable(tunable: &Tunable) -> anyhow::Result<()> {
+    fs::write(&tunable.path, tunable.recommended)
+        .with_context(|| format!("Failed to write {} (are you root?)", tunable.path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn tunables_are_audited_and_applied() {
+        let tmp = tempfile::tempdir().unwrap();
+        let sys = tmp.path().join("sys");
+        let proc_root = tmp.path().join("proc");
+        write(&sys.join("bus/usb/devices/1-2/power/control"), "on\n");
+        write(&sys.join("bus/usb/devices/1-2/product"), "USB Receiver\n");
+        write(&sys.join("bus/usb/devices/1-2:1.0/power/control"), "on\n");
+        write(&sys.join("bus/usb/devices/usb1/power/control"), "auto\n");
+        write(
+            &sys.join("class/scsi_host/host0/link_power_management_policy"),
+            "max_performance\n",
+        );
+        write(
+            &sys.join("module/pcie_aspm/parameters/policy"),
+            "default performance [powersave] powersupersave\n",
+        );
+        write(&proc_root.join("sys/kernel/nmi_watchdog"), "1\n");
+
+        let tunables = audit_tunables_from(&sys, &proc_root);
+        let states: Vec<(&str, &str, bool)> = tunables
+            .iter()
+            .map(|t| (t.name.as_str(), t.current.as_str(), t.is_tuned()))
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                ("USB autosuspend 1-2 (USB Receiver)", "on", false),
+                ("USB autosuspend usb1", "auto", true),
+                ("SATA link power host0", "max_performance", false),
+                ("PCIe ASPM policy", "powersave", true),
+                ("NMI watchdog", "1", false),
+            ]
+        );
+
+        for tunable in tunables.iter().filter(|t| !t.is_tuned()) {
+            apply_tunable(tunable).unwrap();
+        }
+        assert!(audit_tunables_from(&sys, &proc_root)
+            .iter()
+            .all(Tunable::is_tuned));
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use serde_json::json;
+
+use crate::metrics::{MetricKind, MetricSample};
+
+/// A device under `/sys/bus/usb/devices`, without its interfaces and root hubs.
+#[derive(Debug, Clone, PartialEq)]
+struct UsbDevice {
+    port: String,
+    vendor_id: String,
+    product_id: String,
+    manufacturer: Option<String>,
+    product: Option<String>,
+    max_power_ma: Option<f64>,
+    runtime_status: Option<String>,
+    speed_mbps: Option<f64>,
+}
+
+impl UsbDevice {
+    /// Stable across ports and reboots, so a dock is one source wherever it is plugged in.
+    fn source(&self) -> String {
+        let ids = format!("{}:{}", self.vendor_id, self.product_id);
+        match &self.product {
+            Some(product) => format!("{product} ({ids})"),
+            None => ids,
+        }
+    }
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|raw| raw.trim().to_string())
+        .filter(|raw| !raw.is_empty())
+}
+
+fn usb_devices_from(root: &Path) -> Vec<UsbDevice> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut ports: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        // `1-2:1.0` are interfaces and `usb1` root hubs.
+        .filter(|name| !name.contains(':') && !name.starts_with("usb"))
+        .collect();
+    ports.sort();
+    ports
+        .into_iter()
+        .filter_map(|port| {
+            let dir = root.join(&port);
+            let number = |file: &str| {
+                read_trimmed(&dir.join(file))?
+                    .trim_end_matches("mA")
+                    .parse::<f64>()
+                    .ok()
+            };
+            Some(UsbDevice {
+                vendor_id: read_trimmed(&dir.join("idVendor"))?,
+                product_id: read_trimmed(&dir.join("idProduct"))?,
+                manufacturer: read_trimmed(&dir.join("manufacturer")),
+                product: read_trimmed(&dir.join("product")),
+                max_power_ma: number("bMaxPower"),
+                runtime_status: read_trimmed(&dir.join("power/runtime_status")),
+                speed_mbps: number("speed"),
+                port,
+            })
+        })
+        .collect()
+}
+
+/// One sample per connected USB device: the value is the power budget it asked for
+/// (`bMaxPower`, mA) and `details.runtime_status` whether it is active or suspended.
+pub fn usb_samples_from(root: &Path, ts: f64) -> Vec<MetricSample> {
+    usb_devices_from(root)
+        .into_iter()
+        .map(|device| {
+            MetricSample::new(
+                ts,
+                MetricKind::Usb,
+                device.source(),
+                device.max_power_ma,
+                Some("mA"),
+                json!({
+                    "port": device.port,
+                    "manufacturer": device.manufacturer,
+                    "runtime_status": device.runtime_status,
+                    "speed_mbps": device.speed_mbps,
+                }),
+            )
+        })
+        .collect()
+}
+
+pub fn usb_samples(ts: f64) -> Vec<MetricSample> {
+    usb_samples_from(Path::new("/sys/bus/usb/devices"), ts)
+}
+
+/// True when the device was active (not runtime-suspended) at this collection.
+pub fn is_active(sample: &MetricSample) -> bool {
+    sample
+        .details
+        .get("runtime_status")
+        .and_then(|s| s.as_str())
+        != Some("suspended")
+}
+
+/// Annotation texts for devices that appeared or went away between two collections.
+pub fn connection_changes(previous: &BTreeSet<String>, current: &BTreeSet<String>) -> Vec<String> {
+    current
+        .difference(previous)
+        .map(|device| format!("USB connected: {device}"))
+        .chain(
+            previous
+                .difference(current)
+                .map(|device| format!("USB disconnected: {device}")),
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn devices_are_read_and_changes_named() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        write(&root.join("usb1/idVendor"), "1d6b\n");
+        write(&root.join("usb1/idProduct"), "0002\n");
+        write(&root.join("1-2/idVendor"), "04e8\n");
+        write(&root.join("1-2/idProduct"), "4001\n");
+        write(&root.join("1-2/product"), "Portable SSD T7\n");
+        write(&root.join("1-2/bMaxPower"), "896mA\n");
+        write(&root.join("1-2/speed"), "5000\n");
+        write(&root.join("1-2/power/runtime_status"), "suspended\n");
+        write(&root.join("1-2:1.0/bInterfaceClass"), "08\n");
+        write(&root.join("1-3/idVendor"), "046d\n");
+        write(&root.join("1-3/idProduct"), "c52b\n");
+        write(&root.join("1-3/power/runtime_status"), "active\n");
+
+        let samples = usb_samples_from(root, 1.0);
+        let sources: Vec<&str> = samples.iter().map(|s| s.source.as_str()).collect();
+        assert_eq!(sources, vec!["Portable SSD T7 (04e8:4001)", "046d:c52b"]);
+        assert_eq!(samples[0].value, Some(896.0));
+        assert_eq!(samples[0].details["speed_mbps"], 5000.0);
+        assert!(!is_active(&samples[0]));
+        assert!(is_active(&samples[1]));
+
+        let before = BTreeSet::from(["046d:c52b".to_string(), "Dock (17ef:a396)".to_string()]);
+        let after: BTreeSet<String> = sources.iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            connection_changes(&before, &after),
+            vec![
+                "USB connected: Portable SSD T7 (04e8:4001)",
+                "USB disconnected: Dock (17ef:a396)"
+            ]
+        );
+    }
+}
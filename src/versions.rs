@@ -0,0 +1,130 @@
+//! Kernel, OS and symmetri versions. The collector stores them whenever one changes, and
+//! reports mark the changes, since upgrades are the usual suspects behind a drain regression.
+
+use std::fs;
+
+use crate::db::Annotation;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemVersions {
+    /// `uname -r`
+    pub kernel: String,
+    /// `PRETTY_NAME` from os-release, e.g. `Fedora Linux 40 (Workstation Edition)`
+    pub os_release: Option<String>,
+    pub symmetri: String,
+}
+
+impl SystemVersions {
+    pub fn current() -> Self {
+        SystemVersions {
+            kernel: fs::read_to_string("/proc/sys/kernel/osrelease")
+                .map(|raw| raw.trim().to_string())
+                .unwrap_or_else(|_| "unknown".to_string()),
+            os_release: fs::read_to_string("/etc/os-release")
+                .or_else(|_| fs::read_to_string("/usr/lib/os-release"))
+                .ok()
+                .and_then(|raw| parse_os_release(&raw)),
+            symmetri: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// One `kernel changed 6.6.1 → 6.9.2` line per version that differs from `previous`.
+    pub fn changes_from(&self, previous: &SystemVersions) -> Vec<String> {
+        let unknown = |value: &Option<String>| value.as_deref().unwrap_or("unknown").to_string();
+        [
+            ("kernel", previous.kernel.clone(), self.kernel.clone()),
+            (
+                "OS",
+                unknown(&previous.os_release),
+                unknown(&self.os_release),
+            ),
+            ("symmetri", previous.symmetri.clone(), self.symmetri.clone()),
+        ]
+        .into_iter()
+        .filter(|(_, before, after)| before != after)
+        .map(|(what, before, after)| format!("{what} changed {before} → {after}"))
+        .collect()
+    }
+}
+
+/// The distribution name from an os-release file: `PRETTY_NAME`, else `NAME VERSION_ID`.
+pub fn parse_os_release(raw: &str) -> Option<String> {
+    let field = |key: &str| {
+        raw.lines().find_map(|line| {
+            let value = line.strip_prefix(key)?.strip_prefix('=')?;
+            let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+            (!value.is_empty()).then(|| value.to_string())
+        })
+    };
+    field("PRETTY_NAME").or_else(|| {
+        let name = field("NAME")?;
+        Some(match field("VERSION_ID") {
+            Some(version) => format!("{name} {version}"),
+            None => name,
+        })
+    })
+}
+
+/// Notes for the changes in `history` (oldest first, as stored) made at or after
+/// `since_ts`, for the report's annotations and graph markers.
+pub fn change_annotations(
+    history: &[(f64, SystemVersions)],
+    since_ts: Option<f64>,
+) -> Vec<Annotation> {
+    history
+        .windows(2)
+        .filter(|pair| since_ts.is_none_or(|since| pair[1].0 >= since))
+        .flat_map(|pair| {
+            let ts = pair[1].0;
+            pair[1]
+                .1
+                .changes_from(&pair[0].1)
+                .into_iter()
+                .map(move |text| Annotation { ts, text })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrades_become_annotations() {
+        let fedora = "NAME=\"Fedora Linux\"\nVERSION_ID=40\nPRETTY_NAME=\"Fedora Linux 40 (Workstation Edition)\"\n";
+        assert_eq!(
+            parse_os_release(fedora).as_deref(),
+            Some("Fedora Linux 40 (Workstation Edition)")
+        );
+        assert_eq!(
+            parse_os_release("NAME=NixOS\nVERSION_ID='24.05'\n").as_deref(),
+            Some("NixOS 24.05")
+        );
+        assert_eq!(parse_os_release("ID=linux\n"), None);
+
+        let at = |kernel: &str, symmetri: &str| SystemVersions {
+            kernel: kernel.to_string(),
+            os_release: Some("NixOS 24.05".to_string()),
+            symmetri: symmetri.to_string(),
+        };
+        let history = [
+            (10.0, at("6.6.1", "0.3.0")),
+            (20.0, at("6.9.2", "0.3.0")),
+            (30.0, at("6.9.3", "0.4.0")),
+        ];
+        let notes = change_annotations(&history, None);
+        let texts: Vec<&str> = notes.iter().map(|n| n.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            [
+                "kernel changed 6.6.1 → 6.9.2",
+                "kernel changed 6.9.2 → 6.9.3",
+                "symmetri changed 0.3.0 → 0.4.0"
+            ]
+        );
+        assert_eq!(notes[0].ts, 20.0);
+        // The window starts after the first upgrade, but still sees the row before it.
+        assert_eq!(change_annotations(&history, Some(25.0)).len(), 2);
+        assert!(change_annotations(&history[..1], None).is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+//! Battery wear trend for `symmetri health`: a least-squares line through the daily
+//! health (full capacity against design) of one battery, projected forward to the levels
+//! at which batteries are usually replaced.
+
+/// Health levels the projection is reported at, in percent of design capacity.
+pub const PROJECTION_TARGETS: [f64; 2] = [80.0, 60.0];
+
+/// Fewer days, or a shorter span, and calibration drift outweighs the actual wear.
+const MIN_DAYS: usize = 7;
+const MIN_SPAN_DAYS: f64 = 14.0;
+
+const DAY_SECONDS: f64 = 86_400.0;
+const YEAR_SECONDS: f64 = 365.25 * DAY_SECONDS;
+
+/// Readings outside this are gauges misreporting, not health.
+const HEALTH_RANGE: std::ops::RangeInclusive<f64> = 1.0..=150.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WearTrend {
+    /// First and last day the line was fitted over.
+    pub first_ts: f64,
+    pub last_ts: f64,
+    /// Fitted health on the last day, in percent.
+    pub current_pct: f64,
+    /// Health lost per year, in percentage points; negative when it rose.
+    pub loss_pct_per_year: f64,
+}
+
+impl WearTrend {
+    /// Fits `daily` (timestamp, health %) points, oldest first. `None` when there are too
+    /// few days or they span too little time to tell wear from noise.
+    pub fn fit(daily: &[(f64, f64)]) -> Option<Self> {
+        let points: Vec<(f64, f64)> = daily
+            .iter()
+            .copied()
+            .filter(|(_, pct)| HEALTH_RANGE.contains(pct))
+            .collect();
+        let (&(first_ts, _), &(last_ts, _)) = (points.first()?, points.last()?);
+        if points.len() < MIN_DAYS || last_ts - first_ts < MIN_SPAN_DAYS * DAY_SECONDS {
+            return None;
+        }
+        let n = points.len() as f64;
+        let mean_ts = points.iter().map(|(ts, _)| ts).sum::<f64>() / n;
+        let mean_pct = points.iter().map(|(_, pct)| pct).sum::<f64>() / n;
+        let (mut covariance, mut variance) = (0.0, 0.0);
+        for (ts, pct) in &points {
+            covariance += (ts - mean_ts) * (pct - mean_pct);
+            variance += (ts - mean_ts).powi(2);
+        }
+        let slope = covariance / variance;
+        if !slope.is_finite() {
+            return None;
+        }
+        Some(WearTrend {
+            first_ts,
+            last_ts,
+            current_pct: mean_pct + slope * (last_ts - mean_ts),
+            loss_pct_per_year: -slope * YEAR_SECONDS,
+        })
+    }
+
+    pub fn fitted_days(&self) -> f64 {
+        (self.last_ts - self.first_ts) / DAY_SECONDS
+    }
+
+    /// When the line reaches `target_pct`: the last fitted day if it already has, `None`
+    /// if health is not falling.
+    pub fn reaches(&self, target_pct: f64) -> Option<f64> {
+        if self.current_pct <= target_pct {
+            return Some(self.last_ts);
+        }
+        if self.loss_pct_per_year <= 0.0 {
+            return None;
+        }
+        let years = (self.current_pct - target_pct) / self.loss_pct_per_year;
+        Some(self.last_ts + years * YEAR_SECONDS)
+    }
+
+    /// Whether the line reaches `target_pct` within `days` of `now_ts`, the condition the
+    /// `[health]` warning fires on.
+    pub fn warns(&self, target_pct: f64, days: f64, now_ts: f64) -> bool {
+        self.reaches(target_pct)
+            .is_some_and(|ts| ts <= now_ts + days * DAY_SECONDS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fits_daily_health_and_projects_targets() {
+        // 95% falling 0.02 points a day (7.3 points a year), with a little jitter.
+        let daily: Vec<(f64, f64)> = (0..60)
+            .map(|day| {
+                let jitter = if day % 2 == 0 { 0.1 } else { -0.1 };
+                (day as f64 * DAY_SECONDS, 95.0 - 0.02 * day as f64 + jitter)
+            })
+            .collect();
+        let trend = WearTrend::fit(&daily).unwrap();
+        assert!((trend.loss_pct_per_year - 7.305).abs() < 0.1);
+        assert!((trend.current_pct - 93.82).abs() < 0.1);
+        assert!((trend.fitted_days() - 59.0).abs() < 1e-9);
+
+        let at_80 = trend.reaches(80.0).unwrap();
+        let years = (at_80 - trend.last_ts) / YEAR_SECONDS;
+        assert!((years - 13.82 / 7.305).abs() < 0.05);
+        assert!(trend.reaches(60.0).unwrap() > at_80);
+        assert_eq!(trend.reaches(95.0), Some(trend.last_ts));
+        assert!(trend.warns(80.0, 700.0, trend.last_ts));
+        assert!(!trend.warns(80.0, 600.0, trend.last_ts));
+
+        let flat: Vec<(f64, f64)> = daily.iter().map(|(ts, _)| (*ts, 90.0)).collect();
+        assert_eq!(WearTrend::fit(&flat).unwrap().reaches(80.0), None);
+        assert!(WearTrend::fit(&daily[..10]).is_none());
+    }
+}
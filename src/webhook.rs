@@ -0,0 +1,150 @@
+//! `[webhooks.<name>]` endpoints that alerts and `summary --webhook` post to: the event as
+//! JSON, or a body rendered from a template with `{{field}}` placeholders so Slack, Discord
+//! or ntfy get the shape they expect without a glue script.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+#[cfg(feature = "webhooks")]
+use std::time::Duration;
+
+#[cfg(feature = "webhooks")]
+use anyhow::Context;
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::config::WebhookConfig;
+#[cfg(feature = "webhooks")]
+use crate::http;
+
+/// `target` as a `[webhooks]` entry, or as a URL with the default settings.
+pub fn resolve<'a>(
+    target: &str,
+    webhooks: &'a BTreeMap<String, WebhookConfig>,
+) -> Option<Cow<'a, WebhookConfig>> {
+    match webhooks.get(target) {
+        Some(webhook) => Some(Cow::Borrowed(webhook)),
+        None if target.contains("://") => Some(Cow::Owned(WebhookConfig {
+            url: target.to_string(),
+            ..WebhookConfig::default()
+        })),
+        None => None,
+    }
+}
+
+/// `template` with each `{{field}}` replaced by that field of `event`. With `json`, strings
+/// are escaped to sit inside a JSON string literal; missing fields become empty.
+pub fn render(template: &str, event: &Value, json: bool) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let name = rest[start + 2..start + 2 + len].trim();
+        match event.get(name) {
+            Some(Value::String(text)) if json => {
+                let quoted = Value::String(text.clone()).to_string();
+                out.push_str(&quoted[1..quoted.len() - 1]);
+            }
+            Some(Value::String(text)) => out.push_str(text),
+            Some(Value::Null) | None => {}
+            Some(other) => out.push_str(&other.to_string()),
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Posts `event` (a JSON object with `event`, `title` and `message` at least).
+#[cfg(feature = "webhooks")]
+pub fn send(webhook: &WebhookConfig, event: &Value) -> Result<()> {
+    let json = webhook.content_type.contains("json");
+    let body = match &webhook.template {
+        Some(template) => {
+            let body = render(template, event, json);
+            if json {
+                serde_json::from_str::<Value>(&body).with_context(|| {
+                    format!(
+                        "The template for {} does not render valid JSON",
+                        webhook.url
+                    )
+                })?;
+            }
+            body
+        }
+        None => event.to_string(),
+    };
+    let headers: Vec<(String, String)> = webhook
+        .headers
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    http::post(
+        &webhook.url,
+        &webhook.content_type,
+        &headers,
+        &body,
+        Duration::from_secs_f64(webhook.timeout_seconds.max(0.1)),
+    )
+}
+
+/// Without the `webhooks` feature there is no HTTP client to post with.
+#[cfg(not(feature = "webhooks"))]
+pub fn send(webhook: &WebhookConfig, _event: &Value) -> Result<()> {
+    anyhow::bail!(
+        "Cannot post to {}: symmetri was built without webhooks; rebuild with `--features webhooks`",
+        webhook.url
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::config::parse_config;
+
+    #[test]
+    fn templates_fill_in_and_escape_fields() {
+        let event = json!({
+            "event": "alert",
+            "title": "symmetri: cpu_hot",
+            "message": "coretemp is \"hot\"\nreally",
+            "value": 96.5,
+            "unit": null,
+        });
+        assert_eq!(
+            render(
+                r#"{"text": "{{title}}: {{ message }} {{value}}{{unit}}"}"#,
+                &event,
+                true
+            ),
+            r#"{"text": "symmetri: cpu_hot: coretemp is \"hot\"\nreally 96.5"}"#
+        );
+        assert_eq!(
+            render("{{message}} {{missing}}{{", &event, false),
+            "coretemp is \"hot\"\nreally {{"
+        );
+
+        let config = parse_config(
+            "[webhooks.slack]\nurl = \"https://hooks.slack.com/services/T/B/X\"\n\
+             template = '{\"text\": \"{{title}}\"}'\n\n\
+             [alerts.hot]\nmetric = \"temperature[*]\"\ncomparator = \">\"\nthreshold = 90\n\
+             sinks = [\"webhook\"]\nwebhook = \"slack\"\n",
+        )
+        .unwrap();
+        let slack = resolve("slack", &config.webhooks).unwrap();
+        assert_eq!(slack.content_type, "application/json");
+        assert!(slack.template.is_some());
+        let bare = resolve("http://alerts.lan/hook", &config.webhooks).unwrap();
+        assert_eq!(bare.url, "http://alerts.lan/hook");
+        assert!(resolve("matrix", &config.webhooks).is_none());
+        assert!(parse_config(
+            "[alerts.hot]\nmetric = \"temperature[*]\"\ncomparator = \">\"\nthreshold = 90\n\
+             sinks = [\"webhook\"]\nwebhook = \"matrix\"\n"
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use std::fs;
+use std::process::{Command, Stdio};
+
+use serde_json::{Map, Value};
+
+use crate::collector::Collector;
+use crate::config::LocationConfig;
+use crate::metrics::{MetricKind, MetricSample};
+use crate::privacy;
+
+pub const WIFI_COLLECTOR: &str = "wifi";
+
+/// One row of `/proc/net/wireless`.
+#[derive(Debug, Clone, PartialEq)]
+struct WirelessStatus {
+    interface: String,
+    link_quality: f64,
+    signal_dbm: f64,
+    noise_dbm: Option<f64>,
+}
+
+/// What `iw dev <iface> link` adds on top of `/proc/net/wireless`.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct LinkInfo {
+    ssid: Option<String>,
+    signal_dbm: Option<f64>,
+    rx_bitrate_mbps: Option<f64>,
+    tx_bitrate_mbps: Option<f64>,
+    frequency_mhz: Option<f64>,
+}
+
+fn parse_level(raw: &str) -> Option<f64> {
+    raw.trim_end_matches('.').parse::<f64>().ok()
+}
+
+fn parse_proc_wireless(content: &str) -> Vec<WirelessStatus> {
+    content
+        .lines()
+        .skip(2)
+        .filter_map(|line| {
+            let (interface, rest) = line.split_once(':')?;
+            let fields: Vec<&str> = rest.split_whitespace().collect();
+            let signal_dbm = parse_level(fields.get(2)?)?;
+            // Drivers without a noise reading report -256.
+            let noise_dbm = fields
+                .get(3)
+                .and_then(|raw| parse_level(raw))
+                .filter(|noise| *noise > -256.0);
+            Some(WirelessStatus {
+                interface: interface.trim().to_string(),
+                link_quality: parse_level(fields.get(1)?)?,
+                signal_dbm,
+                noise_dbm,
+            })
+        })
+        .collect()
+}
+
+fn parse_iw_link(output: &str) -> Option<LinkInfo> {
+    if !output.trim_start().starts_with("Connected to") {
+        return None;
+    }
+    let mut info = LinkInfo::default();
+    let first_number = |raw: &str| {
+        raw.split_whitespace()
+            .next()
+            .and_then(|value| value.parse::<f64>().ok())
+    };
+    for line in output.lines().map(str::trim) {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "SSID" => info.ssid = Some(value.to_string()).filter(|ssid| !ssid.is_empty()),
+            "signal" => info.signal_dbm = first_number(value),
+            "rx bitrate" => info.rx_bitrate_mbps = first_number(value),
+            "tx bitrate" => info.tx_bitrate_mbps = first_number(value),
+            "freq" => info.frequency_mhz = first_number(value),
+            _ => {}
+        }
+    }
+    Some(info)
+}
+
+/// SSID and bitrates need nl80211; `iw` is the least intrusive way to reach it. Missing
+/// `iw` just leaves those details out.
+fn iw_link(interface: &str) -> Option<LinkInfo> {
+    let output = Command::new("iw")
+        .args(["dev", interface, "link"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_iw_link(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn wifi_sample(ts: f64, status: &WirelessStatus, link: Option<&LinkInfo>) -> MetricSample {
+    let mut details = Map::new();
+    let mut put = |key: &str, value: Option<Value>| {
+        if let Some(value) = value {
+            details.insert(key.to_string(), value);
+        }
+    };
+    put("link_quality", Some(status.link_quality.into()));
+    put("noise_dbm", status.noise_dbm.map(Value::from));
+    if let Some(link) = link {
+        put("ssid", link.ssid.clone().map(Value::from));
+        put("rx_bitrate_mbps", link.rx_bitrate_mbps.map(Value::from));
+        put("tx_bitrate_mbps", link.tx_bitrate_mbps.map(Value::from));
+        put("frequency_mhz", link.frequency_mhz.map(Value::from));
+    }
+    let signal = link
+        .and_then(|link| link.signal_dbm)
+        .unwrap_or(status.signal_dbm);
+    MetricSample::new(
+        ts,
+        MetricKind::Wifi,
+        status.interface.clone(),
+        Some(signal),
+        Some("dBm"),
+        Value::Object(details),
+    )
+}
+
+/// A stable stand-in for `ssid`, so places can be told apart without the database naming
+/// them.
+pub fn hash_ssid(ssid: &str) -> String {
+    format!("ssid-{:016x}", privacy::stable_hash(ssid))
+}
+
+/// Signal strength per associated wireless interface, with link rates and, unless
+/// `[location]` turns it off, the SSID (or its hash) in details.
+pub struct WifiCollector {
+    config: LocationConfig,
+}
+
+impl WifiCollector {
+    pub fn new(config: LocationConfig) -> Self {
+        WifiCollector { config }
+    }
+
+    fn scope(&self, mut link: LinkInfo) -> LinkInfo {
+        link.ssid = match link.ssid {
+            _ if !self.config.enabled => None,
+            Some(ssid) if self.config.hash_ssids => Some(hash_ssid(&ssid)),
+            ssid => ssid,
+        };
+        link
+    }
+}
+
+impl Collector for WifiCollector {
+    fn name(&self) -> &str {
+        WIFI_COLLECTOR
+    }
+
+    fn kinds(&self) -> &[MetricKind] {
+        &[MetricKind::Wifi]
+    }
+
+    fn collect(&self, ts: f64) -> Vec<MetricSample> {
+        let Ok(content) = fs::read_to_string("/proc/net/wireless") else {
+            return Vec::new();
+        };
+        parse_proc_wireless(&content)
+            .iter()
+            .map(|status| {
+                let link = iw_link(&status.interface).map(|link| self.scope(link));
+                wifi_sample(ts, status, link.as_ref())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROC_WIRELESS: &str = "\
+Inter-| sta-|   Quality        |   Discarded packets               | Missed | WE
+ face | tus | link level noise |  nwid  crypt   frag  retry   misc | beacon | 22
+wlp2s0: 0000   54.  -56.  -256        0      0      0      0      0        0
+";
+
+    const IW_LINK: &str = "\
+Connected to aa:bb:cc:dd:ee:ff (on wlp2s0)
+\tSSID: Home Network
+\tfreq: 5180
+\tRX: 123456 bytes (789 packets)
+\tsignal: -57 dBm
+\trx bitrate: 433.3 MBit/s VHT-MCS 9 80MHz short GI VHT-NSS 1
+\ttx bitrate: 390.0 MBit/s VHT-MCS 8 80MHz short GI VHT-NSS 1
+";
+
+    #[test]
+    fn proc_wireless_and_iw_output_become_a_sample() {
+        let statuses = parse_proc_wireless(PROC_WIRELESS);
+        assert_eq!(
+            statuses,
+            vec![WirelessStatus {
+                interface: "wlp2s0".to_string(),
+                link_quality: 54.0,
+                signal_dbm: -56.0,
+                noise_dbm: None,
+            }]
+        );
+
+        let link = parse_iw_link(IW_LINK).unwrap();
+        assert_eq!(link.ssid.as_deref(), Some("Home Network"));
+        assert_eq!(link.tx_bitrate_mbps, Some(390.0));
+        assert_eq!(parse_iw_link("Not connected.\n"), None);
+
+        let sample = wifi_sample(1.0, &statuses[0], Some(&link));
+        assert_eq!(sample.value, Some(-57.0));
+        assert_eq!(sample.details["rx_bitrate_mbps"], 433.3);
+        assert_eq!(sample.details["frequency_mhz"], 5180.0);
+        assert!(sample.details.get("noise_dbm").is_none());
+
+        let without_iw = wifi_sample(1.0, &statuses[0], None);
+        assert_eq!(without_iw.value, Some(-56.0));
+        assert!(without_iw.details.get("ssid").is_none());
+
+        let scoped = |enabled: bool, hash_ssids: bool| {
+            let config = LocationConfig {
+                enabled,
+                hash_ssids,
+                ..LocationConfig::default()
+            };
+            WifiCollector::new(config).scope(link.clone()).ssid
+        };
+        assert_eq!(scoped(true, false).as_deref(), Some("Home Network"));
+        assert_eq!(scoped(true, true), Some(hash_ssid("Home Network")));
+        assert_eq!(hash_ssid("Home Network"), hash_ssid("Home Network"));
+        assert_ne!(hash_ssid("Home Network"), hash_ssid("Office"));
+        assert_eq!(scoped(false, true), None);
+    }
+}
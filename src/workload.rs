@@ -0,0 +1,436 @@
+//! Workload classes (idle, browsing, compiling, video, gaming) read off CPU, GPU, disk,
+//! network and backlight patterns in fixed windows of history, and the drain each class
+//! costs. `symmetri classify` stores the labels for reports to group drain by.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::cli_helpers::DischargeSegment;
+use crate::correlate;
+use crate::metrics::{MetricKind, MetricSample};
+
+/// Length of a classified window.
+pub const WINDOW_SECONDS: i64 = 5 * 60;
+
+/// Below both, nothing much runs.
+const IDLE_CPU_PCT: f64 = 8.0;
+const IDLE_GPU_PCT: f64 = 5.0;
+/// A backlight this dim counts as a screen nobody is looking at.
+const DIM_BRIGHTNESS_PCT: f64 = 5.0;
+const GAMING_GPU_PCT: f64 = 40.0;
+const GAMING_CPU_PCT: f64 = 20.0;
+const COMPILING_CPU_PCT: f64 = 60.0;
+/// Builds that wait on the disk keep fewer cores busy but read and write a lot.
+const BUSY_CPU_PCT: f64 = 35.0;
+const BUSY_DISK_BPS: f64 = 1_000_000.0;
+/// Enough traffic to stream video (2 Mbit/s).
+const STREAMING_NET_BPS: f64 = 250_000.0;
+/// Hardware decoding keeps the GPU busy and the CPU light; without a GPU reading, video
+/// shows as light CPU use that barely moves, unlike the bursts of browsing.
+const VIDEO_GPU_PCT: f64 = 10.0;
+const VIDEO_CPU_PCT: f64 = 35.0;
+const STEADY_CPU_SPREAD: f64 = 5.0;
+const MIN_STEADY_READINGS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Workload {
+    Idle,
+    Browsing,
+    Compiling,
+    Video,
+    Gaming,
+}
+
+impl Workload {
+    pub const ALL: [Workload; 5] = [
+        Workload::Idle,
+        Workload::Browsing,
+        Workload::Compiling,
+        Workload::Video,
+        Workload::Gaming,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Workload::Idle => "idle",
+            Workload::Browsing => "browsing",
+            Workload::Compiling => "compiling",
+            Workload::Video => "video",
+            Workload::Gaming => "gaming",
+        }
+    }
+
+    pub fn from_label(raw: &str) -> Option<Self> {
+        Workload::ALL
+            .into_iter()
+            .find(|workload| workload.as_str() == raw)
+    }
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What one window looked like; `None` where the metric was not recorded.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Features {
+    pub cpu_pct: Option<f64>,
+    /// Standard deviation of the CPU readings.
+    pub cpu_spread: f64,
+    pub cpu_readings: usize,
+    pub gpu_pct: Option<f64>,
+    pub brightness_pct: Option<f64>,
+    /// Share of readings with the screen lit.
+    pub screen_lit: Option<f64>,
+    /// Read and write rate of the busiest recorded cgroup.
+    pub disk_bytes_per_s: Option<f64>,
+    /// Received and sent, over every interface.
+    pub network_bytes_per_s: Option<f64>,
+}
+
+/// The class of a window; `None` without a CPU reading to go on.
+pub fn classify(features: &Features) -> Option<Workload> {
+    let cpu = features.cpu_pct?;
+    let gpu = features.gpu_pct.unwrap_or(0.0);
+    let disk = features.disk_bytes_per_s.unwrap_or(0.0);
+    let streaming = features
+        .network_bytes_per_s
+        .is_some_and(|rate| rate >= STREAMING_NET_BPS);
+    let screen_off = features.screen_lit.is_some_and(|lit| lit < 0.5)
+        || features
+            .brightness_pct
+            .is_some_and(|pct| pct <= DIM_BRIGHTNESS_PCT);
+    let steady =
+        features.cpu_readings >= MIN_STEADY_READINGS && features.cpu_spread < STEADY_CPU_SPREAD;
+    Some(if cpu < IDLE_CPU_PCT && gpu < IDLE_GPU_PCT && !streaming {
+        Workload::Idle
+    } else if gpu >= GAMING_GPU_PCT && cpu >= GAMING_CPU_PCT {
+        Workload::Gaming
+    } else if cpu >= COMPILING_CPU_PCT || (cpu >= BUSY_CPU_PCT && disk >= BUSY_DISK_BPS) {
+        Workload::Compiling
+    } else if screen_off {
+        Workload::Idle
+    } else if cpu < VIDEO_CPU_PCT && (gpu >= VIDEO_GPU_PCT || steady || streaming) {
+        Workload::Video
+    } else {
+        Workload::Browsing
+    })
+}
+
+/// A classified window of history.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Labelled {
+    pub start: f64,
+    pub end: f64,
+    pub workload: Workload,
+}
+
+/// Readings of every source of `name`, pooled per window index.
+fn pooled(name: &str, metrics: &[MetricSample], window: f64) -> BTreeMap<i64, Vec<f64>> {
+    let mut pooled: BTreeMap<i64, Vec<f64>> = BTreeMap::new();
+    for series in correlate::series_for(name, metrics)
+        .unwrap_or_default()
+        .values()
+    {
+        for &(ts, value) in series.points() {
+            pooled
+                .entry((ts / window).floor() as i64)
+                .or_default()
+                .push(value);
+        }
+    }
+    pooled
+}
+
+/// Each source's mean per window index.
+fn source_means(name: &str, metrics: &[MetricSample], window: f64) -> BTreeMap<i64, Vec<f64>> {
+    let mut means: BTreeMap<i64, Vec<f64>> = BTreeMap::new();
+    for series in correlate::series_for(name, metrics)
+        .unwrap_or_default()
+        .values()
+    {
+        let mut by_window: BTreeMap<i64, Vec<f64>> = BTreeMap::new();
+        for &(ts, value) in series.points() {
+            by_window
+                .entry((ts / window).floor() as i64)
+                .or_default()
+                .push(value);
+        }
+        for (index, values) in by_window {
+            means.entry(index).or_default().push(mean(&values));
+        }
+    }
+    means
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Features of each window of `window_seconds` (aligned to the epoch, so reruns agree)
+/// that holds any reading.
+pub fn features(metrics: &[MetricSample], window_seconds: i64) -> BTreeMap<i64, Features> {
+    let window = window_seconds as f64;
+    let gpu = pooled("gpu", metrics, window);
+    let brightness = pooled("brightness", metrics, window);
+    let screen = pooled("screen", metrics, window);
+    let mut features: BTreeMap<i64, Features> = BTreeMap::new();
+    for (index, cpu) in pooled("cpu", metrics, window) {
+        let cpu_pct = mean(&cpu);
+        let spread =
+            (cpu.iter().map(|v| (v - cpu_pct).powi(2)).sum::<f64>() / cpu.len() as f64).sqrt();
+        features.insert(
+            index,
+            Features {
+                cpu_pct: Some(cpu_pct),
+                cpu_spread: spread,
+                cpu_readings: cpu.len(),
+                ..Features::default()
+            },
+        );
+    }
+    for (index, values) in gpu {
+        features.entry(index).or_default().gpu_pct = Some(mean(&values));
+    }
+    for (index, values) in brightness {
+        features.entry(index).or_default().brightness_pct = Some(mean(&values));
+    }
+    for (index, values) in screen {
+        features.entry(index).or_default().screen_lit =
+            Some(values.iter().filter(|lit| **lit >= 0.5).count() as f64 / values.len() as f64);
+    }
+    // Parent cgroups include their children, so summing them would count I/O twice.
+    for (index, rates) in source_means("disk", metrics, window) {
+        features.entry(index).or_default().disk_bytes_per_s = rates.into_iter().reduce(f64::max);
+    }
+    for (index, rates) in source_means("network", metrics, window) {
+        features.entry(index).or_default().network_bytes_per_s = Some(rates.iter().sum());
+    }
+    features
+}
+
+/// The metric kinds [`features`] reads, for fetching only those.
+pub fn kinds() -> Vec<MetricKind> {
+    vec![
+        MetricKind::CpuUsage,
+        MetricKind::GpuUsage,
+        MetricKind::Display,
+        MetricKind::Cgroup,
+        MetricKind::NetworkBytes,
+    ]
+}
+
+/// Every window of `metrics` that [`classify`] can place, oldest first.
+pub fn label_windows(metrics: &[MetricSample], window_seconds: i64) -> Vec<Labelled> {
+    let window = window_seconds as f64;
+    features(metrics, window_seconds)
+        .into_iter()
+        .filter_map(|(index, features)| {
+            Some(Labelled {
+                start: index as f64 * window,
+                end: (index + 1) as f64 * window,
+                workload: classify(&features)?,
+            })
+        })
+        .collect()
+}
+
+/// Discharge power of one class over awake, on-battery time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rate {
+    /// Energy drawn over hours on battery, so long segments count for their length.
+    pub watts: f64,
+    pub hours: f64,
+}
+
+/// Drain per class, each segment counted in the window its end falls in. `labels` must be
+/// sorted.
+pub fn drain_rates(labels: &[Labelled], segments: &[DischargeSegment]) -> BTreeMap<Workload, Rate> {
+    let mut totals: BTreeMap<Workload, (f64, f64)> = BTreeMap::new();
+    for segment in segments {
+        let after = labels.partition_point(|label| label.start <= segment.ts);
+        let Some(label) = after
+            .checked_sub(1)
+            .map(|index| &labels[index])
+            .filter(|label| segment.ts < label.end)
+        else {
+            continue;
+        };
+        let (wh, hours) = totals.entry(label.workload).or_default();
+        *wh += segment.watts * segment.hours;
+        *hours += segment.hours;
+    }
+    totals
+        .into_iter()
+        .filter(|(_, (_, hours))| *hours > 0.0)
+        .map(|(workload, (wh, hours))| {
+            (
+                workload,
+                Rate {
+                    watts: wh / hours,
+                    hours,
+                },
+            )
+        })
+        .collect()
+}
+
+/// Time and drain of one class over a stretch of labelled history.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClassSummary {
+    pub workload: Workload,
+    pub seconds: f64,
+    /// `None` when the class never ran on battery.
+    pub rate: Option<Rate>,
+}
+
+/// Every class in `labels`, in [`Workload::ALL`] order.
+pub fn summarize(labels: &[Labelled], segments: &[DischargeSegment]) -> Vec<ClassSummary> {
+    let rates = drain_rates(labels, segments);
+    Workload::ALL
+        .into_iter()
+        .filter_map(|workload| {
+            let seconds: f64 = labels
+                .iter()
+                .filter(|label| label.workload == workload)
+                .map(|label| label.end - label.start)
+                .sum();
+            (seconds > 0.0).then(|| ClassSummary {
+                workload,
+                seconds,
+                rate: rates.get(&workload).copied(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_support::{metric, segment};
+
+    fn at(cpu: f64, gpu: Option<f64>) -> Features {
+        Features {
+            cpu_pct: Some(cpu),
+            cpu_spread: 12.0,
+            cpu_readings: 5,
+            gpu_pct: gpu,
+            ..Features::default()
+        }
+    }
+
+    /// Ten minutes of compiling, then ten of video with the GPU decoding.
+    fn labels() -> Vec<Labelled> {
+        let mut metrics = Vec::new();
+        for minute in 0..20 {
+            let ts = minute as f64 * 60.0;
+            let compiling = minute < 10;
+            let cpu = if compiling {
+                90.0
+            } else {
+                20.0 + (minute % 4) as f64 * 5.0
+            };
+            let gpu = if compiling { 0.0 } else { 30.0 };
+            metrics.push(metric(ts, MetricKind::CpuUsage, "cpu", cpu, json!({})));
+            metrics.push(metric(ts, MetricKind::GpuUsage, "card0", gpu, json!({})));
+        }
+        label_windows(&metrics, WINDOW_SECONDS)
+    }
+
+    #[test]
+    fn windows_without_cpu_readings_are_not_classified() {
+        assert_eq!(classify(&Features::default()), None);
+    }
+
+    #[test]
+    fn cpu_and_gpu_load_pick_the_class() {
+        assert_eq!(classify(&at(3.0, None)), Some(Workload::Idle));
+        assert_eq!(classify(&at(45.0, Some(70.0))), Some(Workload::Gaming));
+        assert_eq!(classify(&at(85.0, Some(2.0))), Some(Workload::Compiling));
+        assert_eq!(classify(&at(15.0, Some(25.0))), Some(Workload::Video));
+        assert_eq!(classify(&at(25.0, None)), Some(Workload::Browsing));
+    }
+
+    #[test]
+    fn steady_moderate_cpu_load_is_video() {
+        let steady = Features {
+            cpu_spread: 1.5,
+            ..at(12.0, None)
+        };
+        assert_eq!(classify(&steady), Some(Workload::Video));
+    }
+
+    #[test]
+    fn a_dark_screen_is_idle() {
+        let dark = Features {
+            screen_lit: Some(0.0),
+            ..at(25.0, None)
+        };
+        assert_eq!(classify(&dark), Some(Workload::Idle));
+    }
+
+    #[test]
+    fn disk_and_network_traffic_refine_the_class() {
+        let linking = Features {
+            disk_bytes_per_s: Some(40e6),
+            ..at(45.0, None)
+        };
+        assert_eq!(classify(&linking), Some(Workload::Compiling));
+        let streaming = Features {
+            network_bytes_per_s: Some(600e3),
+            ..at(5.0, None)
+        };
+        assert_eq!(classify(&streaming), Some(Workload::Video));
+    }
+
+    #[test]
+    fn stored_labels_parse_back() {
+        assert_eq!(Workload::from_label("video"), Some(Workload::Video));
+    }
+
+    #[test]
+    fn windows_are_labelled_from_metrics() {
+        let classes: Vec<Workload> = labels().iter().map(|label| label.workload).collect();
+        assert_eq!(
+            classes,
+            [
+                Workload::Compiling,
+                Workload::Compiling,
+                Workload::Video,
+                Workload::Video
+            ]
+        );
+    }
+
+    #[test]
+    fn drain_rates_are_weighted_per_class() {
+        let rates = drain_rates(
+            &labels(),
+            &[
+                segment(120.0, 24.0, 0.05),
+                segment(480.0, 20.0, 0.15),
+                segment(900.0, 8.0, 0.1),
+                // Past the last window.
+                segment(5000.0, 99.0, 0.1),
+            ],
+        );
+        assert_eq!(rates.len(), 2);
+        let compiling = rates[&Workload::Compiling];
+        assert!((compiling.watts - 21.0).abs() < 1e-9);
+        assert!((compiling.hours - 0.2).abs() < 1e-9);
+        assert_eq!(rates[&Workload::Video].watts, 8.0);
+    }
+
+    #[test]
+    fn summaries_cover_classes_without_battery_time() {
+        let summary = summarize(&labels(), &[segment(900.0, 8.0, 0.1)]);
+        assert_eq!(summary.len(), 2);
+        assert_eq!(summary[0].workload, Workload::Compiling);
+        assert_eq!((summary[0].seconds, summary[0].rate), (600.0, None));
+        assert_eq!(summary[1].rate.map(|rate| rate.hours), Some(0.1));
+    }
+}
@@ -0,0 +1,149 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{
+    FixedOffset, Local, MappedLocalTime, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc,
+};
+use chrono_tz::{Tz, TzOffset};
+
+/// The timezone reports, buckets and graph axes are shown in. Samples are always stored
+/// as UTC Unix seconds; this only changes how they are presented.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Zone {
+    /// The system timezone (`TZ`, else `/etc/localtime`).
+    #[default]
+    Local,
+    Utc,
+    /// An IANA zone such as `Europe/Paris`.
+    Named(Tz),
+}
+
+impl FromStr for Zone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "local" => Ok(Zone::Local),
+            "UTC" | "utc" | "Z" => Ok(Zone::Utc),
+            name => Tz::from_str(name).map(Zone::Named).map_err(|_| {
+                format!(
+                    "Unknown timezone `{name}`; use an IANA name like Europe/Paris, UTC or local"
+                )
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Zone::Local => f.write_str("local"),
+            Zone::Utc => f.write_str("UTC"),
+            Zone::Named(tz) => f.write_str(tz.name()),
+        }
+    }
+}
+
+/// Offset of a [`Zone`] at some instant; prints like the zone it came from (`+02:00` for
+/// local time, `UTC`, `CEST`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ZoneOffset {
+    Local(FixedOffset),
+    Utc,
+    Named(TzOffset),
+}
+
+impl Offset for ZoneOffset {
+    fn fix(&self) -> FixedOffset {
+        match self {
+            ZoneOffset::Local(offset) => *offset,
+            ZoneOffset::Utc => Utc.fix(),
+            ZoneOffset::Named(offset) => offset.fix(),
+        }
+    }
+}
+
+impl fmt::Display for ZoneOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ZoneOffset::Local(offset) => offset.fmt(f),
+            ZoneOffset::Utc => f.write_str("UTC"),
+            ZoneOffset::Named(offset) => offset.fmt(f),
+        }
+    }
+}
+
+impl TimeZone for Zone {
+    type Offset = ZoneOffset;
+
+    fn from_offset(offset: &ZoneOffset) -> Self {
+        match offset {
+            ZoneOffset::Local(_) => Zone::Local,
+            ZoneOffset::Utc => Zone::Utc,
+            ZoneOffset::Named(offset) => Zone::Named(Tz::from_offset(offset)),
+        }
+    }
+
+    fn offset_from_local_date(&self, local: &NaiveDate) -> MappedLocalTime<ZoneOffset> {
+        match self {
+            Zone::Local => Local.offset_from_local_date(local).map(ZoneOffset::Local),
+            Zone::Utc => MappedLocalTime::Single(ZoneOffset::Utc),
+            Zone::Named(tz) => tz.offset_from_local_date(local).map(ZoneOffset::Named),
+        }
+    }
+
+    fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> MappedLocalTime<ZoneOffset> {
+        match self {
+            Zone::Local => Local
+                .offset_from_local_datetime(local)
+                .map(ZoneOffset::Local),
+            Zone::Utc => MappedLocalTime::Single(ZoneOffset::Utc),
+            Zone::Named(tz) => tz.offset_from_local_datetime(local).map(ZoneOffset::Named),
+        }
+    }
+
+    fn offset_from_utc_date(&self, utc: &NaiveDate) -> ZoneOffset {
+        match self {
+            Zone::Local => ZoneOffset::Local(Local.offset_from_utc_date(utc)),
+            Zone::Utc => ZoneOffset::Utc,
+            Zone::Named(tz) => ZoneOffset::Named(tz.offset_from_utc_date(utc)),
+        }
+    }
+
+    fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> ZoneOffset {
+        match self {
+            Zone::Local => ZoneOffset::Local(Local.offset_from_utc_datetime(utc)),
+            Zone::Utc => ZoneOffset::Utc,
+            Zone::Named(tz) => ZoneOffset::Named(tz.offset_from_utc_datetime(utc)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zones_parse_and_shift_wall_clock() {
+        assert_eq!("utc".parse::<Zone>(), Ok(Zone::Utc));
+        assert_eq!("local".parse::<Zone>(), Ok(Zone::Local));
+        assert!("Mars/Olympus".parse::<Zone>().is_err());
+
+        let paris: Zone = "Europe/Paris".parse().unwrap();
+        assert_eq!(paris.to_string(), "Europe/Paris");
+        // 2024-07-01 10:00 UTC is 12:00 CEST, and 2024-01-01 10:00 UTC is 11:00 CET.
+        let summer = paris.timestamp_opt(1_719_828_000, 0).unwrap();
+        assert_eq!(summer.format("%H:%M %Z").to_string(), "12:00 CEST");
+        let winter = paris.timestamp_opt(1_704_103_200, 0).unwrap();
+        assert_eq!(winter.format("%H:%M").to_string(), "11:00");
+        let utc = Zone::Utc.timestamp_opt(1_719_828_000, 0).unwrap();
+        assert_eq!(utc.format("%H:%M %Z").to_string(), "10:00 UTC");
+
+        let midnight = NaiveDate::from_ymd_opt(2024, 7, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let start = paris.from_local_datetime(&midnight).unwrap();
+        assert_eq!(start.timestamp(), 1_719_784_800);
+    }
+}